@@ -5,11 +5,14 @@ import { SettingsPage } from './pages/SettingsPage';
 import { TasksPage } from './pages/TasksPage';
 import { CronPage } from './pages/CronPage';
 import { GuardrailsPage } from './pages/GuardrailsPage';
+import { SavedPromptsPage } from './pages/SavedPromptsPage';
 import { ApprovalsPage } from './pages/ApprovalsPage';
 import { MemoryPage } from './pages/MemoryPage';
 import { ContextPage } from './pages/ContextPage';
 import { AuthPage } from './pages/AuthPage';
 import { DiagnosticsPage } from './pages/DiagnosticsPage';
+import { TestPromptPage } from './pages/TestPromptPage';
+import { ConfigBundlePage } from './pages/ConfigBundlePage';
 
 export default function App() {
   return (
@@ -18,15 +21,18 @@ export default function App() {
         <Route index element={<Navigate to="tasks" replace />} />
         <Route path="status" element={<StatusPage />} />
         <Route path="settings" element={<SettingsPage />} />
+        <Route path="config-bundle" element={<ConfigBundlePage />} />
         <Route path="tasks" element={<TasksPage />} />
         <Route path="tasks/:id" element={<TasksPage />} />
         <Route path="cron" element={<CronPage />} />
         <Route path="guardrails" element={<GuardrailsPage />} />
+        <Route path="saved-prompts" element={<SavedPromptsPage />} />
         <Route path="approvals" element={<ApprovalsPage />} />
         <Route path="memory" element={<MemoryPage />} />
         <Route path="context/*" element={<ContextPage />} />
         <Route path="auth" element={<AuthPage />} />
         <Route path="diagnostics" element={<DiagnosticsPage />} />
+        <Route path="test-prompt" element={<TestPromptPage />} />
         <Route path="*" element={<NotFoundPage />} />
       </Route>
     </Routes>
@@ -4,12 +4,15 @@ type GlyphName =
   | 'queue'
   | 'status'
   | 'diagnostics'
+  | 'test'
   | 'cron'
+  | 'prompts'
   | 'guardrails'
   | 'approvals'
   | 'context'
   | 'memory'
   | 'settings'
+  | 'bundle'
   | 'auth';
 
 interface NavItem {
@@ -25,12 +28,14 @@ const NAV_SECTIONS: Array<{ title: string; items: NavItem[] }> = [
       { to: '/tasks', label: 'Queue', glyph: 'queue' },
       { to: '/status', label: 'Status', glyph: 'status' },
       { to: '/diagnostics', label: 'Diagnostics', glyph: 'diagnostics' },
+      { to: '/test-prompt', label: 'Test Prompt', glyph: 'test' },
     ],
   },
   {
     title: 'Automation',
     items: [
       { to: '/cron', label: 'Cron Jobs', glyph: 'cron' },
+      { to: '/saved-prompts', label: 'Saved Prompts', glyph: 'prompts' },
       { to: '/guardrails', label: 'Guardrails', glyph: 'guardrails' },
       { to: '/approvals', label: 'Approvals', glyph: 'approvals' },
     ],
@@ -46,6 +51,7 @@ const NAV_SECTIONS: Array<{ title: string; items: NavItem[] }> = [
     title: 'System',
     items: [
       { to: '/settings', label: 'Settings', glyph: 'settings' },
+      { to: '/config-bundle', label: 'Config Bundle', glyph: 'bundle' },
       { to: '/auth', label: 'Auth', glyph: 'auth' },
     ],
   },
@@ -83,6 +89,12 @@ function NavGlyph({ name }: { name: GlyphName }) {
           <circle cx="8" cy="8" r="2.3" />
         </svg>
       );
+    case 'test':
+      return (
+        <svg viewBox="0 0 16 16" aria-hidden="true">
+          <path d="M6 2.5h4M6.75 2.5v4L3 12.5a1 1 0 0 0 .85 1.5h8.3a1 1 0 0 0 .85-1.5L9.25 6.5v-4" />
+        </svg>
+      );
     case 'cron':
       return (
         <svg viewBox="0 0 16 16" aria-hidden="true">
@@ -90,6 +102,13 @@ function NavGlyph({ name }: { name: GlyphName }) {
           <path d="M8 4.5V8l2.5 1.5" />
         </svg>
       );
+    case 'prompts':
+      return (
+        <svg viewBox="0 0 16 16" aria-hidden="true">
+          <path d="M4 2.5h6l2.5 2.5v8.5H4z" />
+          <path d="M6 7.5l1.5 1.5L6 10.5M8.5 10.5h2" />
+        </svg>
+      );
     case 'guardrails':
       return (
         <svg viewBox="0 0 16 16" aria-hidden="true">
@@ -122,6 +141,13 @@ function NavGlyph({ name }: { name: GlyphName }) {
           <circle cx="8" cy="8" r="1.9" />
         </svg>
       );
+    case 'bundle':
+      return (
+        <svg viewBox="0 0 16 16" aria-hidden="true">
+          <path d="M8 2.5l5.5 2.75L8 8 2.5 5.25z" />
+          <path d="M2.5 8L8 10.75 13.5 8M2.5 10.75L8 13.5l5.5-2.75" />
+        </svg>
+      );
     case 'auth':
       return (
         <svg viewBox="0 0 16 16" aria-hidden="true">
@@ -10,11 +10,39 @@ async function request<T>(path: string, opts?: RequestInit): Promise<T> {
   });
   if (!res.ok) {
     const text = await res.text();
-    throw new Error(`${res.status}: ${text}`);
+    let message = text;
+    try {
+      const body = JSON.parse(text) as { error?: string; reference?: string };
+      if (body.error) message = body.reference ? `${body.error} (ref ${body.reference})` : body.error;
+    } catch { /* not JSON */ }
+    throw new Error(`${res.status}: ${message}`);
   }
   return res.json();
 }
 
+/** Download URL for the usage analytics export (served as an attachment). */
+export function analyticsExportUrl(opts: { days?: number; format?: 'csv' | 'json'; anonymize?: boolean }): string {
+  const params = new URLSearchParams();
+  if (opts.days) params.set('days', String(opts.days));
+  if (opts.format) params.set('format', opts.format);
+  if (opts.anonymize) params.set('anonymize', 'true');
+  return `${BASE}/analytics/export?${params.toString()}`;
+}
+
+/** Download URL for a stored task artifact (e.g. approved command output). */
+export function taskArtifactUrl(taskId: number, artifactId: string): string {
+  return `${BASE}/tasks/${taskId}/artifacts/${encodeURIComponent(artifactId)}`;
+}
+
+/** Download URL for the YAML configuration bundle (settings, rules, cron jobs, prompts). */
+export const configExportUrl = `${BASE}/config/export`;
+
+const yamlBody = (yaml: string): RequestInit => ({
+  method: 'POST',
+  body: yaml,
+  headers: { 'Content-Type': 'application/yaml' },
+});
+
 export const api = {
   // Status
   getStatus: () => request<StatusData>('/status'),
@@ -32,7 +60,19 @@ export const api = {
 
   // Tasks
   getTasks: () => request<{ tasks: TaskListItemData[] }>('/tasks'),
-  getTask: (id: number) => request<{ task: TaskData; traces: TaskTraceData[] }>(`/tasks/${id}`),
+  getTask: (id: number) =>
+    request<{ task: TaskData; traces: TaskTraceData[]; artifacts: TaskArtifactData[] }>(`/tasks/${id}`),
+  rerunTasks: (input: TaskRerunInput) =>
+    request<{ ok: boolean; tasks: { from: number; id: number }[]; skipped: number[] }>('/tasks/rerun', {
+      method: 'POST',
+      body: JSON.stringify(input),
+    }),
+  resolveTaskLink: (link: string) =>
+    request<{ id: number; status: string; links: TaskLinksData }>(`/tasks/resolve?link=${encodeURIComponent(link)}`),
+  addTask: (task: TaskAddInput) =>
+    request<{ ok: boolean; task_id: number }>('/tasks/add', { method: 'POST', body: JSON.stringify(task) }),
+  testPrompt: (input: TestPromptInput) =>
+    request<{ ok: boolean; task_id: number }>('/tasks/test', { method: 'POST', body: JSON.stringify(input) }),
   cancelTask: (id: number) => request<{ ok: boolean }>(`/tasks/${id}/cancel`, { method: 'POST' }),
   retryTask: (id: number) => request<{ ok: boolean }>(`/tasks/${id}/retry`, { method: 'POST' }),
 
@@ -63,11 +103,32 @@ export const api = {
   deleteGuardrail: (id: string) => request<{ ok: boolean }>(`/guardrails/${id}/delete`, { method: 'POST' }),
   enableGuardrail: (id: string) => request<{ ok: boolean }>(`/guardrails/${id}/enable`, { method: 'POST' }),
   disableGuardrail: (id: string) => request<{ ok: boolean }>(`/guardrails/${id}/disable`, { method: 'POST' }),
+  getGuardrailCanary: () => request<GuardrailCanaryState>('/guardrails/canary'),
+  startGuardrailCanary: (data: { channels: string; hours: number; rules: GuardrailRuleSpec[] }) =>
+    request<{ ok: boolean; changes: ConfigChange[] }>('/guardrails/canary/start', { method: 'POST', body: JSON.stringify(data) }),
+  promoteGuardrailCanary: () =>
+    request<{ ok: boolean; changes: ConfigChange[] }>('/guardrails/canary/promote', { method: 'POST' }),
+  discardGuardrailCanary: () => request<{ ok: boolean }>('/guardrails/canary/discard', { method: 'POST' }),
+  getElevations: () => request<{ sessions: ElevatedSessionData[]; max_hours: number }>('/elevations'),
+  addElevation: (data: { provider: string; workspace_id?: string; channel_id: string; minutes: number; reason: string }) =>
+    request<{ ok: boolean; id: string }>('/elevations/add', { method: 'POST', body: JSON.stringify(data) }),
+  revokeElevation: (id: string) => request<{ ok: boolean }>(`/elevations/${encodeURIComponent(id)}/revoke`, { method: 'POST' }),
+
+  // Saved prompts
+  getSavedPrompts: () => request<{ prompts: SavedPromptData[] }>('/saved-prompts'),
+  addSavedPrompt: (prompt: SavedPromptInput) =>
+    request<{ ok: boolean }>('/saved-prompts/add', { method: 'POST', body: JSON.stringify(prompt) }),
+  deleteSavedPrompt: (id: string) => request<{ ok: boolean }>(`/saved-prompts/${id}/delete`, { method: 'POST' }),
+  enableSavedPrompt: (id: string) => request<{ ok: boolean }>(`/saved-prompts/${id}/enable`, { method: 'POST' }),
+  disableSavedPrompt: (id: string) => request<{ ok: boolean }>(`/saved-prompts/${id}/disable`, { method: 'POST' }),
 
   // Approvals
   getApprovals: () => request<{ approvals: ApprovalData[] }>('/approvals'),
+  getApprovalInsights: () => request<ApprovalInsightsData>('/approvals/insights'),
   approveApproval: (id: string) => request<{ ok: boolean }>(`/approvals/${id}/approve`, { method: 'POST' }),
   alwaysApproval: (id: string) => request<{ ok: boolean }>(`/approvals/${id}/always`, { method: 'POST' }),
+  alwaysApprovalScope: (id: string, scope: string) =>
+    request<{ ok: boolean }>(`/approvals/${id}/always/${scope}`, { method: 'POST' }),
   denyApproval: (id: string) => request<{ ok: boolean }>(`/approvals/${id}/deny`, { method: 'POST' }),
 
   // Auth
@@ -88,6 +149,11 @@ export const api = {
   // Diagnostics
   getDiagnostics: () => request<DiagnosticsData>('/diagnostics'),
   runCodexTest: () => request<DiagnosticsData>('/diagnostics/codex', { method: 'POST' }),
+
+  // Configuration bundle
+  previewConfigImport: (yaml: string) => request<ConfigImportPlan>('/config/import/preview', yamlBody(yaml)),
+  importConfig: (yaml: string) =>
+    request<{ ok: boolean; changes: ConfigChange[]; ignored: string[] }>('/config/import', yamlBody(yaml)),
 };
 
 // ── Types ──
@@ -105,10 +171,20 @@ export interface StatusData {
   slack_actions_url: string;
   telegram_webhook_url: string;
   worker_lock_owner: string;
+  workers: WorkerData[];
+  web_search_quota: WebSearchQuotaData | null;
   active_task_id: string;
   active_task_started_at: string;
   pending_approvals: number;
   guardrails_enabled: number;
+  slack_signature_checks: {
+    verified: number;
+    missing_header: number;
+    invalid_header: number;
+    timestamp_skew: number;
+    signature_mismatch: number;
+    replayed: number;
+  };
   browser_enabled: boolean;
   browser_novnc_enabled: boolean;
   browser_novnc_url: string;
@@ -118,6 +194,27 @@ export interface StatusData {
   browser_cdp_port: string;
 }
 
+export interface WorkerData {
+  worker_id: string;
+  hostname: string;
+  pid: number;
+  is_leader: boolean;
+  active_tasks: number;
+  started_at: string;
+  last_seen_at: string;
+  seconds_since_heartbeat: number;
+}
+
+export interface WebSearchQuotaData {
+  updated_at: number;
+  windows: { limit: number; remaining: number; reset_seconds: number; resets_at: number }[];
+  local_requests: number;
+  local_resets_at: number;
+  blocked_until: number | null;
+  last_error: string | null;
+  fallback_searches: number;
+}
+
 export interface SettingsData {
   context_last_n: number;
   model: string;
@@ -150,9 +247,34 @@ export interface SettingsData {
   web_allow_domains: string;
   web_deny_domains: string;
   github_client_id: string;
+  context_sources: string;
+  llm_backends: string;
+  response_cache_mode: string;
+  response_cache_ttl_seconds: number;
+  command_env: string;
+  slack_long_reply_mode: string;
+  slack_long_reply_chars: number;
+  reply_language: string;
+  task_bump_allow_from: string;
+  public_status_page: boolean;
+  slack_no_retry_after_enqueue: boolean;
+  context_token_budget: string;
+  content_policy_moderation: boolean;
+  content_policy_message: string;
+  approval_output_chars: number;
+  scale_queue_depth_threshold: number;
+  scale_wait_seconds_threshold: number;
+  scale_webhook_url: string;
+  reply_task_links: boolean;
+  usage_report_minutes_per_task: number;
+  speech_to_text: string;
+  slack_admin_channel: string;
+  slack_elevate_allow_from: string;
+  telegram_elevate_allow_from: string;
   // Secret status flags
   master_key_set: boolean;
   openai_api_key_set: boolean;
+  anthropic_api_key_set: boolean;
   slack_signing_secret_set: boolean;
   slack_bot_token_set: boolean;
   telegram_bot_token_set: boolean;
@@ -173,6 +295,10 @@ export interface TaskListItemData {
   created_at: string;
   started_at: string;
   finished_at: string;
+  depends_on_task_id: number | null;
+  is_synthetic: boolean;
+  /** Set on copies made by a bulk re-run. */
+  rerun_of_task_id: number | null;
 }
 
 export interface TaskData extends TaskListItemData {
@@ -181,6 +307,48 @@ export interface TaskData extends TaskListItemData {
   event_ts: string;
   requested_by_user_id: string;
   files_json: string;
+  on_dependency_failure: string;
+  /** JSON Schema the answer must conform to (API tasks only). */
+  output_schema: unknown | null;
+  /** The validated answer, parsed, when the task has an output schema. */
+  output: unknown | null;
+  links: TaskLinksData;
+  /** Ids of re-runs of this task. */
+  reruns: number[];
+}
+
+export interface TaskRerunInput {
+  task_ids: number[];
+  model?: string;
+  read_only?: boolean;
+  timeout_seconds?: number;
+}
+
+export interface TaskLinksData {
+  /** `grail://task/<id>` */
+  app: string;
+  /** Dashboard URL, absolute when BASE_URL is set. */
+  web: string;
+  /** Permalink of the originating Slack message. */
+  slack: string | null;
+}
+
+export interface TestPromptInput {
+  provider: string;
+  channel_id: string;
+  user_id: string;
+  thread_ts?: string;
+  prompt_text: string;
+  output_schema?: unknown;
+}
+
+export interface TaskAddInput {
+  channel_id: string;
+  thread_ts?: string;
+  prompt_text: string;
+  depends_on_task_id?: number;
+  on_dependency_failure?: 'fail' | 'cancel' | 'run';
+  output_schema?: unknown;
 }
 
 export interface TaskTraceData {
@@ -192,6 +360,15 @@ export interface TaskTraceData {
   created_at: string;
 }
 
+export interface TaskArtifactData {
+  id: string;
+  kind: string;
+  name: string;
+  approval_id: string | null;
+  chars: number;
+  created_at: string;
+}
+
 export interface SessionData {
   conversation_key: string;
   codex_thread_id: string;
@@ -257,6 +434,74 @@ export interface GuardrailInput {
   pattern: string;
 }
 
+/** A guardrail rule as stored in config bundles and canaries. */
+export interface GuardrailRuleSpec {
+  id: string;
+  name: string;
+  kind: string;
+  pattern_kind: string;
+  pattern: string;
+  action: string;
+  priority: number;
+  enabled: boolean;
+}
+
+export interface GuardrailCanaryDecision {
+  id: number;
+  task_id: number;
+  channel_id: string;
+  kind: string;
+  subject: string;
+  applied: 'active' | 'canary';
+  active_decision: string;
+  active_rule_id: string | null;
+  canary_decision: string;
+  canary_rule_id: string | null;
+  created_at: string;
+}
+
+export interface GuardrailCanaryState {
+  canary: {
+    channels: string[];
+    rules: GuardrailRuleSpec[];
+    started_at: string;
+    ends_at: string;
+    live: boolean;
+    changes: ConfigChange[];
+    errors: string[];
+  } | null;
+  decisions: GuardrailCanaryDecision[];
+}
+
+export interface ElevatedSessionData {
+  id: string;
+  provider: string;
+  workspace_id: string;
+  channel_id: string;
+  granted_by: string;
+  reason: string;
+  started_at: string;
+  ends_at: string;
+  revoked_at: string | null;
+  revoked_by: string | null;
+  active: boolean;
+}
+
+export interface SavedPromptData {
+  id: string;
+  name: string;
+  description: string;
+  template: string;
+  enabled: boolean;
+  updated_at: string;
+}
+
+export interface SavedPromptInput {
+  name: string;
+  description: string;
+  template: string;
+}
+
 export interface ApprovalData {
   id: string;
   status: string;
@@ -266,6 +511,47 @@ export interface ApprovalData {
   details: string;
 }
 
+export interface RuleSuggestionData {
+  action: string;
+  pattern_kind: string;
+  pattern: string;
+  name: string;
+  reason: string;
+  approved: number;
+  denied: number;
+}
+
+export interface StaleRuleData {
+  id: string;
+  name: string;
+  action: string;
+  pattern: string;
+  match_count: number;
+  last_matched_at: number | null;
+  reason: string;
+}
+
+export interface ApprovalInsightsData {
+  window_days: number;
+  summary: {
+    total: number;
+    approved: number;
+    denied: number;
+    expired: number;
+    pending: number;
+    median_wait_seconds: number | null;
+  };
+  suggestions: RuleSuggestionData[];
+  stale_rules: StaleRuleData[];
+}
+
+export interface AlwaysScopeData {
+  scope: string;
+  label: string;
+  pattern_kind: string;
+  pattern: string;
+}
+
 export interface AuthData {
   openai_api_key_set: boolean;
   codex_auth_file_set: boolean;
@@ -294,3 +580,17 @@ export interface DiagnosticsData {
   codex_result?: string;
   codex_error?: string;
 }
+
+export interface ConfigChange {
+  section: string;
+  key: string;
+  action: 'add' | 'change' | 'remove';
+  from?: unknown;
+  to?: unknown;
+}
+
+export interface ConfigImportPlan {
+  changes: ConfigChange[];
+  ignored: string[];
+  errors: string[];
+}
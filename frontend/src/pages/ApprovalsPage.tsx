@@ -1,13 +1,33 @@
 import { useEffect, useState } from 'react';
-import { api, type ApprovalData } from '../lib/api';
+import { api, type AlwaysScopeData, type ApprovalData, type ApprovalInsightsData, type RuleSuggestionData } from '../lib/api';
+
+function alwaysScopes(details: string): AlwaysScopeData[] {
+  try {
+    const parsed = JSON.parse(details);
+    return Array.isArray(parsed?.always_scopes) ? parsed.always_scopes : [];
+  } catch {
+    return [];
+  }
+}
 
 export function ApprovalsPage() {
   const [approvals, setApprovals] = useState<ApprovalData[]>([]);
+  const [insights, setInsights] = useState<ApprovalInsightsData | null>(null);
   const [error, setError] = useState('');
 
-  const load = () => api.getApprovals().then((d) => setApprovals(d.approvals)).catch((e) => setError(e.message));
+  const load = () => {
+    api.getApprovals().then((d) => setApprovals(d.approvals)).catch((e) => setError(e.message));
+    api.getApprovalInsights().then(setInsights).catch((e) => setError(e.message));
+  };
   useEffect(() => { load(); }, []);
 
+  const addRule = (s: RuleSuggestionData) =>
+    api
+      .addGuardrail({ kind: 'command', action: s.action, priority: 1, name: s.name, pattern_kind: s.pattern_kind, pattern: s.pattern })
+      .then(load)
+      .catch((e) => setError(e.message));
+  const disableRule = (id: string) => api.disableGuardrail(id).then(load).catch((e) => setError(e.message));
+
   return (
     <>
       <h2>Approvals</h2>
@@ -15,6 +35,34 @@ export function ApprovalsPage() {
 
       {error && <div className="card" style={{ color: 'var(--red)' }}>Error: {error}</div>}
 
+      {insights && (
+        <div className="card">
+          <div className="card-title">Insights (last {insights.window_days} days)</div>
+          <p className="section-desc" style={{ marginTop: 0 }}>
+            {insights.summary.total} command approvals: {insights.summary.approved} approved, {insights.summary.denied} denied,
+            {' '}{insights.summary.expired} expired
+            {insights.summary.median_wait_seconds !== null && <>; median wait {Math.round(insights.summary.median_wait_seconds / 60)} min</>}.
+          </p>
+          {insights.suggestions.map((s) => (
+            <div key={`${s.action}:${s.pattern}`} className="form-checkbox-row" style={{ justifyContent: 'space-between' }}>
+              <span>{s.reason} — suggest a{s.action === 'allow' ? 'n' : ''} <strong>{s.action}</strong> rule</span>
+              <button className={`btn btn-sm ${s.action === 'deny' ? 'btn-danger' : ''}`} title={s.pattern} onClick={() => addRule(s)}>
+                Add {s.action} rule
+              </button>
+            </div>
+          ))}
+          {insights.stale_rules.map((r) => (
+            <div key={r.id} className="form-checkbox-row" style={{ justifyContent: 'space-between' }}>
+              <span>Rule <strong>{r.name}</strong>: {r.reason}</span>
+              <button className="btn btn-sm" title={r.pattern} onClick={() => disableRule(r.id)}>Disable</button>
+            </div>
+          ))}
+          {insights.suggestions.length === 0 && insights.stale_rules.length === 0 && (
+            <p className="section-desc">No suggestions yet.</p>
+          )}
+        </div>
+      )}
+
       <table>
         <thead>
           <tr><th>ID</th><th>Kind</th><th>Status</th><th>Details</th><th>Created</th><th>Actions</th></tr>
@@ -36,6 +84,11 @@ export function ApprovalsPage() {
                   <div style={{ display: 'flex', gap: 4 }}>
                     <button className="btn btn-sm" style={{ color: 'var(--green)' }} onClick={() => { api.approveApproval(a.id).then(load); }}>Approve</button>
                     <button className="btn btn-sm" onClick={() => { api.alwaysApproval(a.id).then(load); }}>Always</button>
+                    {alwaysScopes(a.details).map((s) => (
+                      <button key={s.scope} className="btn btn-sm" title={s.pattern} onClick={() => { api.alwaysApprovalScope(a.id, s.scope).then(load); }}>
+                        {s.label.replace(/`/g, '')}
+                      </button>
+                    ))}
                     <button className="btn btn-sm btn-danger" onClick={() => { api.denyApproval(a.id).then(load); }}>Deny</button>
                   </div>
                 )}
@@ -0,0 +1,122 @@
+import { useState } from 'react';
+import { api, configExportUrl, type ConfigImportPlan } from '../lib/api';
+
+const brief = (v: unknown) => (v === undefined ? '' : typeof v === 'string' ? v : JSON.stringify(v));
+
+export function ConfigBundlePage() {
+  const [yaml, setYaml] = useState('');
+  const [plan, setPlan] = useState<ConfigImportPlan | null>(null);
+  const [error, setError] = useState('');
+  const [busy, setBusy] = useState(false);
+  const [imported, setImported] = useState(false);
+
+  const edit = (value: string) => {
+    setYaml(value);
+    setPlan(null);
+    setImported(false);
+  };
+
+  const loadFile = async (file: File | undefined) => {
+    if (file) edit(await file.text());
+  };
+
+  const preview = async () => {
+    setBusy(true);
+    try {
+      setPlan(await api.previewConfigImport(yaml));
+      setError('');
+    } catch (e) {
+      setPlan(null);
+      setError(e instanceof Error ? e.message : 'Preview failed');
+    }
+    setBusy(false);
+  };
+
+  const runImport = async () => {
+    setBusy(true);
+    try {
+      await api.importConfig(yaml);
+      setError('');
+      setPlan(null);
+      setImported(true);
+    } catch (e) {
+      setError(e instanceof Error ? e.message : 'Import failed');
+    }
+    setBusy(false);
+  };
+
+  const canImport = plan !== null && plan.errors.length === 0 && plan.changes.length > 0;
+
+  return (
+    <>
+      <h2>Config Bundle</h2>
+      <p className="section-desc">
+        Export settings, guardrail rules, cron jobs and saved prompts as one YAML file and import it into another instance.
+        Secrets are never included.
+      </p>
+
+      {error && <div className="card" style={{ color: 'var(--red)' }}>Error: {error}</div>}
+
+      <div className="card">
+        <div className="card-title">Export</div>
+        <a className="btn btn-sm" href={configExportUrl}>Download YAML</a>
+      </div>
+
+      <div className="card">
+        <div className="card-title">Import</div>
+        <p className="section-desc" style={{ marginTop: 0 }}>
+          Each section in the bundle replaces the current one: items are matched by id (saved prompts by name) and items
+          missing from the bundle are removed. Sections left out of the bundle are not touched.
+        </p>
+        <div className="form-group">
+          <input type="file" accept=".yaml,.yml" onChange={(e) => loadFile(e.target.files?.[0])} />
+        </div>
+        <div className="form-group">
+          <textarea className="form-textarea" rows={12} value={yaml} onChange={(e) => edit(e.target.value)} placeholder="version: 1…" style={{ fontFamily: 'var(--mono)', fontSize: 12 }} />
+        </div>
+        <div style={{ display: 'flex', gap: 8, alignItems: 'center' }}>
+          <button className="btn" onClick={preview} disabled={busy || !yaml.trim()}>Preview</button>
+          <button className="btn btn-primary" onClick={runImport} disabled={busy || !canImport}>Import</button>
+          {imported && <span style={{ color: 'var(--green)', fontSize: 13 }}>✓ Imported</span>}
+        </div>
+      </div>
+
+      {plan && (
+        <div className="card">
+          <div className="card-title">Preview</div>
+          {plan.errors.map((e) => (
+            <div key={e} style={{ color: 'var(--red)', fontSize: 13 }}>{e}</div>
+          ))}
+          {plan.ignored.length > 0 && (
+            <p className="section-desc">Unknown settings, skipped: {plan.ignored.join(', ')}</p>
+          )}
+          {plan.changes.length === 0 && plan.errors.length === 0 && (
+            <p className="section-desc">No changes: this instance already matches the bundle.</p>
+          )}
+          {plan.changes.length > 0 && (
+            <table>
+              <thead>
+                <tr><th>Section</th><th>Key</th><th>Action</th><th>Current</th><th>Incoming</th></tr>
+              </thead>
+              <tbody>
+                {plan.changes.map((c) => (
+                  <tr key={`${c.section}/${c.key}`}>
+                    <td>{c.section}</td>
+                    <td>{c.key}</td>
+                    <td>
+                      <span className={`pill ${c.action === 'remove' ? 'pill-bad' : 'pill-ok'}`}>
+                        <span className="pill-dot" />{c.action}
+                      </span>
+                    </td>
+                    <td style={{ fontFamily: 'var(--mono)', fontSize: 12, wordBreak: 'break-all' }}>{brief(c.from)}</td>
+                    <td style={{ fontFamily: 'var(--mono)', fontSize: 12, wordBreak: 'break-all' }}>{brief(c.to)}</td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          )}
+        </div>
+      )}
+    </>
+  );
+}
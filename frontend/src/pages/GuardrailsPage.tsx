@@ -1,5 +1,5 @@
 import { useEffect, useState } from 'react';
-import { api, type GuardrailData } from '../lib/api';
+import { api, type ElevatedSessionData, type GuardrailCanaryState, type GuardrailData } from '../lib/api';
 
 export function GuardrailsPage() {
   const [rules, setRules] = useState<GuardrailData[]>([]);
@@ -40,6 +40,8 @@ export function GuardrailsPage() {
             <label className="form-label">Kind</label>
             <select className="form-select" value={kind} onChange={(e) => setKind(e.target.value)}>
               <option value="command">command</option>
+              <option value="env">env (variable names)</option>
+              <option value="topic">topic (prompts and replies)</option>
             </select>
           </div>
           <div className="form-group">
@@ -104,6 +106,236 @@ export function GuardrailsPage() {
           )}
         </tbody>
       </table>
+
+      <CanaryCard rules={rules} onPromoted={load} />
+      <ElevationCard />
     </>
   );
 }
+
+const when = (ts: string) => new Date(Number(ts) * 1000).toLocaleString();
+
+/** Stage a candidate rule set in test channels, compare its decisions, then promote or discard it. */
+function CanaryCard({ rules, onPromoted }: { rules: GuardrailData[]; onPromoted: () => void }) {
+  const [state, setState] = useState<GuardrailCanaryState | null>(null);
+  const [error, setError] = useState('');
+  const [channels, setChannels] = useState('');
+  const [hours, setHours] = useState('72');
+  const [draft, setDraft] = useState('');
+
+  const load = () => api.getGuardrailCanary().then(setState).catch((e) => setError(e.message));
+  useEffect(() => { load(); }, []);
+
+  const copyActive = () => {
+    const specs = rules.map((r) => ({
+      id: r.id, name: r.name, kind: r.kind, pattern_kind: r.pattern_kind, pattern: r.pattern,
+      action: r.action, priority: Number(r.priority), enabled: r.enabled,
+    }));
+    setDraft(JSON.stringify(specs, null, 2));
+  };
+
+  const run = async (action: () => Promise<unknown>, after?: () => void) => {
+    try {
+      await action();
+      setError('');
+      after?.();
+      load();
+    } catch (e) { setError(e instanceof Error ? e.message : 'Failed'); }
+  };
+
+  const start = () => {
+    let parsed;
+    try { parsed = JSON.parse(draft); } catch { setError('Candidate rules must be a JSON array'); return; }
+    run(() => api.startGuardrailCanary({ channels, hours: parseInt(hours) || 72, rules: parsed }), () => setDraft(''));
+  };
+
+  const canary = state?.canary;
+  return (
+    <div className="card" style={{ marginTop: 16 }}>
+      <div className="card-title">Canary</div>
+      <p className="section-desc" style={{ marginTop: 0 }}>
+        Try a complete candidate rule set in test channels first. While the canary runs, those channels use the candidate
+        rules and every decision is logged next to what the active rules would have decided; elsewhere, only decisions
+        the candidate would change are logged.
+      </p>
+      {error && <div style={{ color: 'var(--red)', marginBottom: 8 }}>Error: {error}</div>}
+
+      {canary ? (
+        <>
+          <div className="kv-grid">
+            <div className="kv-item">
+              <div className="kv-label">Status</div>
+              <div className="kv-value">
+                <span className={`pill ${canary.live ? 'pill-ok' : 'pill-bad'}`}>
+                  <span className="pill-dot" />{canary.live ? 'Live' : 'Ended, awaiting promotion'}
+                </span>
+              </div>
+            </div>
+            <div className="kv-item">
+              <div className="kv-label">Channels</div>
+              <div className="kv-value">{canary.channels.join(', ')}</div>
+            </div>
+            <div className="kv-item">
+              <div className="kv-label">Window</div>
+              <div className="kv-value">{when(canary.started_at)} – {when(canary.ends_at)}</div>
+            </div>
+            <div className="kv-item">
+              <div className="kv-label">Promotion changes</div>
+              <div className="kv-value">
+                {canary.changes.length === 0 ? 'none' : canary.changes.map((c) => `${c.action} ${c.key}`).join(', ')}
+              </div>
+            </div>
+          </div>
+          {canary.errors.map((e) => <div key={e} style={{ color: 'var(--red)', fontSize: 13 }}>{e}</div>)}
+          <div style={{ display: 'flex', gap: 8, margin: '12px 0' }}>
+            <button className="btn btn-primary" onClick={() => run(api.promoteGuardrailCanary, onPromoted)} disabled={canary.errors.length > 0}>
+              Promote to all channels
+            </button>
+            <button className="btn btn-danger" onClick={() => run(api.discardGuardrailCanary)}>Discard</button>
+          </div>
+        </>
+      ) : (
+        <>
+          <div style={{ display: 'grid', gridTemplateColumns: '2fr 1fr', gap: 16 }}>
+            <div className="form-group">
+              <label className="form-label">Test Channels (comma-separated IDs)</label>
+              <input className="form-input" value={channels} onChange={(e) => setChannels(e.target.value)} />
+            </div>
+            <div className="form-group">
+              <label className="form-label">Duration (hours)</label>
+              <input className="form-input" type="number" value={hours} onChange={(e) => setHours(e.target.value)} />
+            </div>
+          </div>
+          <div className="form-group">
+            <label className="form-label">Candidate Rules (JSON)</label>
+            <textarea className="form-textarea" rows={10} value={draft} onChange={(e) => setDraft(e.target.value)} style={{ fontFamily: 'var(--mono)', fontSize: 12 }} />
+          </div>
+          <div style={{ display: 'flex', gap: 8 }}>
+            <button className="btn" onClick={copyActive}>Start from active rules</button>
+            <button className="btn btn-primary" onClick={start} disabled={!channels.trim() || !draft.trim()}>Start Canary</button>
+          </div>
+        </>
+      )}
+
+      {state && state.decisions.length > 0 && (
+        <table style={{ marginTop: 16 }}>
+          <thead>
+            <tr><th>Time</th><th>Task</th><th>Channel</th><th>Kind</th><th>Subject</th><th>Active</th><th>Canary</th><th>Applied</th></tr>
+          </thead>
+          <tbody>
+            {state.decisions.map((d) => (
+              <tr key={d.id}>
+                <td>{when(d.created_at)}</td>
+                <td>#{d.task_id}</td>
+                <td>{d.channel_id}</td>
+                <td>{d.kind}</td>
+                <td style={{ fontFamily: 'var(--mono)', fontSize: 12 }}>{d.subject}</td>
+                <td>{d.active_decision}{d.active_rule_id ? ` (${d.active_rule_id})` : ''}</td>
+                <td style={{ color: d.canary_decision !== d.active_decision ? 'var(--yellow)' : undefined }}>
+                  {d.canary_decision}{d.canary_rule_id ? ` (${d.canary_rule_id})` : ''}
+                </td>
+                <td>{d.applied}</td>
+              </tr>
+            ))}
+          </tbody>
+        </table>
+      )}
+    </div>
+  );
+}
+
+/** Time-boxed full permissions for one channel; every session stays listed as the audit trail. */
+function ElevationCard() {
+  const [sessions, setSessions] = useState<ElevatedSessionData[]>([]);
+  const [maxHours, setMaxHours] = useState(24);
+  const [error, setError] = useState('');
+  const [provider, setProvider] = useState('slack');
+  const [channel, setChannel] = useState('');
+  const [minutes, setMinutes] = useState('120');
+  const [reason, setReason] = useState('');
+
+  const load = () =>
+    api.getElevations()
+      .then((r) => { setSessions(r.sessions); setMaxHours(r.max_hours); })
+      .catch((e) => setError(e.message));
+  useEffect(() => { load(); }, []);
+
+  const run = async (action: () => Promise<unknown>, after?: () => void) => {
+    try {
+      await action();
+      setError('');
+      after?.();
+      load();
+    } catch (e) { setError(e instanceof Error ? e.message : 'Failed'); }
+  };
+
+  const grant = () =>
+    run(
+      () => api.addElevation({ provider, channel_id: channel.trim(), minutes: parseInt(minutes) || 60, reason }),
+      () => { setChannel(''); setReason(''); },
+    );
+
+  return (
+    <div className="card" style={{ marginTop: 16 }}>
+      <div className="card-title">Elevated Sessions</div>
+      <p className="section-desc" style={{ marginTop: 0 }}>
+        Give one channel <code>full</code> permissions for a bounded window (at most {maxHours}h), e.g. during an incident,
+        without changing the global permissions mode. It reverts on its own. Users listed under Slack or Telegram Elevate
+        Allowed Users in settings can also send <code>elevate 2h &lt;reason&gt;</code> and <code>end elevation</code> in the channel.
+      </p>
+      {error && <div style={{ color: 'var(--red)', marginBottom: 8 }}>Error: {error}</div>}
+      <div style={{ display: 'grid', gridTemplateColumns: '1fr 2fr 1fr 3fr', gap: 16 }}>
+        <div className="form-group">
+          <label className="form-label">Provider</label>
+          <select className="form-select" value={provider} onChange={(e) => setProvider(e.target.value)}>
+            <option value="slack">slack</option>
+            <option value="telegram">telegram</option>
+          </select>
+        </div>
+        <div className="form-group">
+          <label className="form-label">Channel / Chat ID</label>
+          <input className="form-input" value={channel} onChange={(e) => setChannel(e.target.value)} />
+        </div>
+        <div className="form-group">
+          <label className="form-label">Minutes</label>
+          <input className="form-input" type="number" value={minutes} onChange={(e) => setMinutes(e.target.value)} />
+        </div>
+        <div className="form-group">
+          <label className="form-label">Reason</label>
+          <input className="form-input" value={reason} onChange={(e) => setReason(e.target.value)} />
+        </div>
+      </div>
+      <button className="btn btn-primary" onClick={grant} disabled={!channel.trim()}>Elevate</button>
+
+      {sessions.length > 0 && (
+        <table style={{ marginTop: 16 }}>
+          <thead>
+            <tr><th>Channel</th><th>Granted By</th><th>Reason</th><th>Window</th><th>Status</th><th /></tr>
+          </thead>
+          <tbody>
+            {sessions.map((s) => (
+              <tr key={s.id}>
+                <td>{s.provider} {s.channel_id}</td>
+                <td>{s.granted_by}</td>
+                <td>{s.reason}</td>
+                <td>{when(s.started_at)} – {when(s.ends_at)}</td>
+                <td>
+                  {s.active ? (
+                    <span className="pill pill-bad"><span className="pill-dot" />Elevated</span>
+                  ) : s.revoked_at ? (
+                    `Ended early by ${s.revoked_by ?? 'unknown'} at ${when(s.revoked_at)}`
+                  ) : (
+                    'Expired'
+                  )}
+                </td>
+                <td>
+                  {s.active && <button className="btn btn-danger" onClick={() => run(() => api.revokeElevation(s.id))}>End now</button>}
+                </td>
+              </tr>
+            ))}
+          </tbody>
+        </table>
+      )}
+    </div>
+  );
+}
@@ -0,0 +1,113 @@
+import { useEffect, useState } from 'react';
+import { api, type SavedPromptData } from '../lib/api';
+
+export function SavedPromptsPage() {
+  const [prompts, setPrompts] = useState<SavedPromptData[] | null>(null);
+  const [error, setError] = useState('');
+  const [name, setName] = useState('');
+  const [description, setDescription] = useState('');
+  const [template, setTemplate] = useState('');
+
+  const load = () =>
+    api
+      .getSavedPrompts()
+      .then((d) => {
+        setPrompts(d.prompts);
+        setError('');
+      })
+      .catch((e) => setError(e.message));
+  useEffect(() => { load(); }, []);
+
+  const save = async () => {
+    try {
+      await api.addSavedPrompt({ name, description, template });
+      setName(''); setDescription(''); setTemplate('');
+      load();
+    } catch (e) { setError(e instanceof Error ? e.message : 'Failed'); }
+  };
+
+  const edit = (p: SavedPromptData) => {
+    setName(p.name); setDescription(p.description); setTemplate(p.template);
+  };
+
+  if (!prompts) {
+    if (error) return <div className="card" style={{ color: 'var(--red)' }}>Error: {error}</div>;
+    return <div className="loading">Loading…</div>;
+  }
+
+  return (
+    <>
+      <h2>Saved Prompts</h2>
+      <p className="section-desc">
+        Curated templates for recurring asks. Run one from chat with <code>@bot run &lt;name&gt; [input]</code>;
+        <code> list prompts</code> shows what is available.
+      </p>
+
+      {error && <div className="card" style={{ color: 'var(--red)' }}>Error: {error}</div>}
+
+      <div className="card">
+        <div className="card-title">Add or Update Prompt</div>
+        <div style={{ display: 'grid', gridTemplateColumns: '1fr 2fr', gap: 16 }}>
+          <div className="form-group">
+            <label className="form-label">Name</label>
+            <input className="form-input" value={name} onChange={(e) => setName(e.target.value)} placeholder="incident-summary" />
+          </div>
+          <div className="form-group">
+            <label className="form-label">Description</label>
+            <input className="form-input" value={description} onChange={(e) => setDescription(e.target.value)} placeholder="Draft a postmortem from this thread" />
+          </div>
+        </div>
+        <div className="form-group">
+          <label className="form-label">Template</label>
+          <textarea
+            className="form-textarea"
+            rows={6}
+            value={template}
+            onChange={(e) => setTemplate(e.target.value)}
+            placeholder={'Write a postmortem draft for {{args}} (reported by <@{{user}}> on {{date}}).\n\nThread so far:\n{{thread}}'}
+          />
+          <p className="section-desc" style={{ margin: '6px 0 0' }}>
+            Slots: <code>{'{{args}}'}</code> (text after the name), <code>{'{{thread}}'}</code> (Slack thread so far),
+            <code> {'{{user}}'}</code>, <code>{'{{channel}}'}</code>, <code>{'{{date}}'}</code>. Saving an existing name replaces it.
+          </p>
+        </div>
+        <button className="btn btn-primary" onClick={save}>Save Prompt</button>
+      </div>
+
+      <table>
+        <thead>
+          <tr>
+            <th>Name</th><th>Description</th><th>Status</th><th>Actions</th>
+          </tr>
+        </thead>
+        <tbody>
+          {prompts.map((p) => (
+            <tr key={p.id}>
+              <td style={{ fontFamily: 'var(--mono)', fontSize: 12 }}>{p.name}</td>
+              <td style={{ fontSize: 12, color: 'var(--text-secondary)' }}>{p.description || '—'}</td>
+              <td>
+                <span className={`pill ${p.enabled ? 'pill-ok' : 'pill-bad'}`}>
+                  <span className="pill-dot" />{p.enabled ? 'Enabled' : 'Disabled'}
+                </span>
+              </td>
+              <td>
+                <div style={{ display: 'flex', gap: 4 }}>
+                  <button className="btn btn-sm" onClick={() => edit(p)}>Edit</button>
+                  {p.enabled ? (
+                    <button className="btn btn-sm" onClick={() => { api.disableSavedPrompt(p.id).then(load); }}>Disable</button>
+                  ) : (
+                    <button className="btn btn-sm" onClick={() => { api.enableSavedPrompt(p.id).then(load); }}>Enable</button>
+                  )}
+                  <button className="btn btn-sm btn-danger" onClick={() => { api.deleteSavedPrompt(p.id).then(load); }}>Delete</button>
+                </div>
+              </td>
+            </tr>
+          ))}
+          {prompts.length === 0 && (
+            <tr><td colSpan={4} style={{ textAlign: 'center', color: 'var(--text-tertiary)', padding: 32 }}>No saved prompts</td></tr>
+          )}
+        </tbody>
+      </table>
+    </>
+  );
+}
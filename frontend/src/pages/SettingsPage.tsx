@@ -133,6 +133,24 @@ export function SettingsPage() {
           <label className="form-label">Role Description</label>
           <textarea className="form-textarea" rows={3} value={data.role_description} onChange={(e) => update('role_description', e.target.value)} />
         </div>
+        <div className="form-group">
+          <label className="form-label">Reply Language</label>
+          <textarea className="form-textarea" rows={2} value={data.reply_language} onChange={(e) => update('reply_language', e.target.value)} placeholder={'{"default": "auto", "channels": {"C0123456789": "German"}}'} />
+          <p className="section-desc">
+            <code>auto</code> (default) replies in the language of the request, <code>off</code> adds no instruction, and any other value pins that language. Channel entries override the default.
+          </p>
+        </div>
+        <div className="form-checkbox-row">
+          <input type="checkbox" checked={data.reply_task_links} onChange={(e) => update('reply_task_links', e.target.checked)} />
+          <label className="form-label" style={{ margin: 0 }}>End replies with a link to the task (<code>grail://task/&lt;id&gt;</code> and the dashboard URL)</label>
+        </div>
+        <div className="form-group">
+          <label className="form-label">Usage Report: Minutes Saved per Task</label>
+          <input className="form-input" type="number" min={0} max={480} value={data.usage_report_minutes_per_task} onChange={(e) => update('usage_report_minutes_per_task', parseInt(e.target.value) || 0)} style={{ width: 200 }} />
+          <p className="section-desc">
+            Channels opt in to a monthly usage report with <code>usage report on</code>. Its time-saved estimate counts this many minutes per completed task (0 = leave it out).
+          </p>
+        </div>
       </div>
 
       <div className="card">
@@ -169,6 +187,81 @@ export function SettingsPage() {
           <label className="form-label">Context Last N Messages</label>
           <input className="form-input" type="number" value={data.context_last_n} onChange={(e) => update('context_last_n', parseInt(e.target.value) || 0)} style={{ width: 120 }} />
         </div>
+        <div className="form-group">
+          <label className="form-label">Context Sources</label>
+          <textarea className="form-textarea" rows={5} value={data.context_sources} onChange={(e) => update('context_sources', e.target.value)} placeholder={'{"budget_chars": 24000, "sources": {"pinned_messages": {"enabled": true, "weight": 1}}, "channels": {"C123": {"sources": {"channel_history": {"enabled": false}}}}}'} />
+        </div>
+        <div className="form-group">
+          <label className="form-label">Context Token Budget</label>
+          <textarea className="form-textarea" rows={2} value={data.context_token_budget} onChange={(e) => update('context_token_budget', e.target.value)} placeholder={'{"default": 8000, "models": {"gpt-5.2": 32000, "llama3.1": 4000}}'} />
+          <p className="section-desc">
+            Optional. When set, up to 200 recent messages are fetched and as many whole messages, files and memories as fit the model's budget are kept; this replaces Last N Messages and <code>budget_chars</code>. Model keys match exactly or by prefix.
+          </p>
+        </div>
+      </div>
+
+      <div className="card">
+        <div className="card-title">Response Cache</div>
+        <p className="section-desc" style={{ marginTop: 0 }}>
+          Repeated top-level Slack prompts in the same channel are answered from a recent reply, with a
+          Regenerate button to run the agent again. Semantic matching uses OpenAI embeddings.
+        </p>
+        <div style={{ display: 'grid', gridTemplateColumns: '1fr 1fr', gap: 16 }}>
+          <div className="form-group">
+            <label className="form-label">Mode</label>
+            <select className="form-select" value={data.response_cache_mode} onChange={(e) => update('response_cache_mode', e.target.value)}>
+              <option value="off">Off</option>
+              <option value="exact">Exact match</option>
+              <option value="semantic">Near-duplicate (embeddings)</option>
+            </select>
+          </div>
+          <div className="form-group">
+            <label className="form-label">Reuse Answers For (seconds)</label>
+            <input className="form-input" type="number" value={data.response_cache_ttl_seconds} onChange={(e) => update('response_cache_ttl_seconds', parseInt(e.target.value) || 0)} style={{ width: 160 }} />
+          </div>
+        </div>
+      </div>
+
+      <div className="card">
+        <div className="card-title">Long Slack Replies</div>
+        <p className="section-desc" style={{ marginTop: 0 }}>
+          Replies longer than the threshold are published as a canvas or an uploaded Markdown file, and
+          the thread gets a short summary that links to it. Canvases need the canvases:write scope; if
+          creating one fails, the report is uploaded as a file instead.
+        </p>
+        <div style={{ display: 'grid', gridTemplateColumns: '1fr 1fr', gap: 16 }}>
+          <div className="form-group">
+            <label className="form-label">Publish As</label>
+            <select className="form-select" value={data.slack_long_reply_mode} onChange={(e) => update('slack_long_reply_mode', e.target.value)}>
+              <option value="off">Off (post in thread)</option>
+              <option value="file">Markdown file</option>
+              <option value="canvas">Canvas</option>
+            </select>
+          </div>
+          <div className="form-group">
+            <label className="form-label">Threshold (characters)</label>
+            <input className="form-input" type="number" value={data.slack_long_reply_chars} onChange={(e) => update('slack_long_reply_chars', parseInt(e.target.value) || 0)} style={{ width: 160 }} />
+          </div>
+        </div>
+      </div>
+
+      <div className="card">
+        <div className="card-title">Agent Backend</div>
+        <p className="section-desc" style={{ marginTop: 0 }}>
+          Codex (OpenAI) is the default and the only backend with tools. Anthropic and OpenAI-compatible
+          endpoints (vLLM, Ollama) answer from context only. Workspace entries replace the default.
+        </p>
+        <div className="form-group">
+          <label className="form-label">LLM Backends</label>
+          <textarea className="form-textarea" rows={5} value={data.llm_backends} onChange={(e) => update('llm_backends', e.target.value)} placeholder={'{"default": {"provider": "codex", "allowed_models": ["gpt-5.2"]}, "workspaces": {"T123": {"provider": "openai_compatible", "base_url": "http://ollama:11434/v1", "model": "llama3.1"}}}'} />
+        </div>
+        <div className="form-group">
+          <label className="form-label">Speech-to-Text</label>
+          <textarea className="form-textarea" rows={2} value={data.speech_to_text} onChange={(e) => update('speech_to_text', e.target.value)} placeholder={'{"provider": "openai", "model": "whisper-1"} or {"provider": "whisper_cpp", "base_url": "http://127.0.0.1:8080"}'} />
+          <p className="section-desc">
+            Transcribes Telegram voice notes and audio files attached in Slack so they can be used as prompts. Leave empty to turn it off.
+          </p>
+        </div>
       </div>
 
       <div className="card">
@@ -188,6 +281,13 @@ export function SettingsPage() {
             <option value="auto">Auto-approve (not recommended)</option>
           </select>
         </div>
+        <div className="form-group">
+          <label className="form-label">Approved Command Output (characters)</label>
+          <input className="form-input" type="number" min={0} max={20000} value={data.approval_output_chars} onChange={(e) => update('approval_output_chars', parseInt(e.target.value) || 0)} style={{ width: 200 }} />
+          <p className="section-desc">
+            After an approved command runs, its redacted output is posted to the thread, cut to this length (0 = don't post). The full output is kept with the task.
+          </p>
+        </div>
         <div className="form-checkbox-row">
           <input type="checkbox" checked={data.shell_network_access} onChange={(e) => update('shell_network_access', e.target.checked)} />
           <label className="form-label" style={{ margin: 0 }}>Shell Network Access</label>
@@ -200,6 +300,48 @@ export function SettingsPage() {
           <input type="checkbox" checked={data.auto_apply_guardrail_tighten} onChange={(e) => update('auto_apply_guardrail_tighten', e.target.checked)} />
           <label className="form-label" style={{ margin: 0 }}>Auto-apply Guardrail Tighten</label>
         </div>
+        <div className="form-group">
+          <label className="form-label">Command Environment</label>
+          <textarea className="form-textarea" rows={4} value={data.command_env} onChange={(e) => update('command_env', e.target.value)} placeholder={'{"inherit": "core", "vars": {"TZ": "UTC"}, "secrets": ["NPM_TOKEN"], "repos": {"acme/api": {"vars": {"STAGE": "dev"}}}}'} />
+          <p className="section-desc">
+            Environment for sandboxed commands. Secrets are names of stored secrets (<code>POST /api/secrets/custom.NAME</code>), exported under that name. Guardrail rules of kind <code>env</code> decide which names commands may read.
+          </p>
+        </div>
+        <div className="form-group">
+          <label className="form-label">Task Bump Allowed Users (comma-separated user IDs)</label>
+          <input className="form-input" value={data.task_bump_allow_from} onChange={(e) => update('task_bump_allow_from', e.target.value)} />
+          <p className="section-desc">
+            These users can send <code>bump #&lt;id&gt;</code> to move a queued task to the front of the queue.
+          </p>
+        </div>
+        <div className="form-group">
+          <label className="form-label">Slack Elevate Allowed Users (comma-separated Slack user IDs)</label>
+          <input className="form-input" value={data.slack_elevate_allow_from} onChange={(e) => update('slack_elevate_allow_from', e.target.value)} />
+        </div>
+        <div className="form-group">
+          <label className="form-label">Telegram Elevate Allowed Users (comma-separated Telegram user IDs)</label>
+          <input className="form-input" value={data.telegram_elevate_allow_from} onChange={(e) => update('telegram_elevate_allow_from', e.target.value)} />
+          <p className="section-desc">
+            Users in either list can send <code>elevate 2h &lt;reason&gt;</code> to give their channel full permissions for a
+            while (see Guardrails → Elevated Sessions). Each list only applies to its own provider.
+          </p>
+        </div>
+      </div>
+
+      <div className="card">
+        <div className="card-title">Content Policy</div>
+        <p className="section-desc" style={{ marginTop: 0 }}>
+          Guardrail rules of kind <code>topic</code> are checked against incoming prompts and outgoing replies;
+          a <code>deny</code> match declines the message and records it in the task trace, an <code>allow</code> match exempts it.
+        </p>
+        <div className="form-checkbox-row">
+          <input type="checkbox" checked={data.content_policy_moderation} onChange={(e) => update('content_policy_moderation', e.target.checked)} />
+          <label className="form-label" style={{ margin: 0 }}>Also check with the OpenAI moderation API</label>
+        </div>
+        <div className="form-group">
+          <label className="form-label">Decline Message</label>
+          <textarea className="form-textarea" rows={2} value={data.content_policy_message} onChange={(e) => update('content_policy_message', e.target.value)} placeholder="Sorry, I can't help with that topic here. Please reach out to the appropriate team directly." />
+        </div>
       </div>
 
       <div className="card">
@@ -209,7 +351,7 @@ export function SettingsPage() {
           <input className="form-input" value={data.slack_allow_from} onChange={(e) => update('slack_allow_from', e.target.value)} />
         </div>
         <div className="form-group">
-          <label className="form-label">Allow Channels (comma-separated)</label>
+          <label className="form-label">Allow Channels (comma-separated; Grid: T…:C… or T…:*)</label>
           <input className="form-input" value={data.slack_allow_channels} onChange={(e) => update('slack_allow_channels', e.target.value)} />
         </div>
         <div className="form-checkbox-row">
@@ -233,6 +375,17 @@ export function SettingsPage() {
           <input type="checkbox" checked={data.allow_slack_mcp} onChange={(e) => update('allow_slack_mcp', e.target.checked)} />
           <label className="form-label" style={{ margin: 0 }}>Enable Slack MCP Tools</label>
         </div>
+        <div className="form-checkbox-row">
+          <input type="checkbox" checked={data.slack_no_retry_after_enqueue} onChange={(e) => update('slack_no_retry_after_enqueue', e.target.checked)} />
+          <label className="form-label" style={{ margin: 0 }}>Tell Slack not to retry accepted events (X-Slack-No-Retry)</label>
+        </div>
+        <div className="form-group">
+          <label className="form-label">Admin Channel</label>
+          <input className="form-input" value={data.slack_admin_channel} onChange={(e) => update('slack_admin_channel', e.target.value)} placeholder="C0123456789" style={{ width: 200 }} />
+          <p className="section-desc">
+            Notified when a channel is archived or deleted, or the bot is removed from one; that channel's queued tasks are cancelled and its cron jobs disabled. Requires Slack app events: <span className="pill">channel_archive</span> <span className="pill">group_archive</span> <span className="pill">channel_deleted</span> <span className="pill">group_deleted</span> <span className="pill">member_left_channel</span>.
+          </p>
+        </div>
       </div>
 
       <div className="card">
@@ -311,6 +464,38 @@ export function SettingsPage() {
         </div>
       </div>
 
+      <div className="card">
+        <div className="card-title">Public Status Page</div>
+        <div className="form-checkbox-row">
+          <input type="checkbox" checked={data.public_status_page} onChange={(e) => update('public_status_page', e.target.checked)} />
+          <label className="form-label" style={{ margin: 0 }}>Serve /status without login</label>
+        </div>
+        <p className="section-desc">
+          Shows only aggregate health (overall verdict, queue depth bucket, uptime, chat provider activity) at <code>/status</code> and <code>/status.json</code>. No task content or IDs.
+        </p>
+      </div>
+
+      <div className="card">
+        <div className="card-title">Autoscaling</div>
+        <p className="section-desc">
+          Orchestrators can poll <code>/api/v1/scale-hint</code> (bearer <code>GRAIL_SCALE_HINT_TOKEN</code>) for queue depth, predicted wait and a suggested worker count. Crossing a threshold below raises a <code>scale.pressure</code> event, and dropping back under raises <code>scale.recovered</code>.
+        </p>
+        <div className="form-group">
+          <label className="form-label">Queue Depth Threshold</label>
+          <input className="form-input" type="number" min={0} value={data.scale_queue_depth_threshold} onChange={(e) => update('scale_queue_depth_threshold', parseInt(e.target.value) || 0)} style={{ width: 200 }} />
+        </div>
+        <div className="form-group">
+          <label className="form-label">Predicted Wait Threshold (seconds)</label>
+          <input className="form-input" type="number" min={0} value={data.scale_wait_seconds_threshold} onChange={(e) => update('scale_wait_seconds_threshold', parseInt(e.target.value) || 0)} style={{ width: 200 }} />
+          <p className="section-desc">0 turns a threshold off.</p>
+        </div>
+        <div className="form-group">
+          <label className="form-label">Event Webhook URL</label>
+          <input className="form-input" value={data.scale_webhook_url} onChange={(e) => update('scale_webhook_url', e.target.value)} placeholder="https://keda-hooks.internal/fastclaw" />
+          <p className="section-desc">Events are always logged; when set, they are also POSTed here as JSON.</p>
+        </div>
+      </div>
+
       <div className="card">
         <div className="card-title">Extra MCP Config</div>
         <div className="form-group">
@@ -323,6 +508,7 @@ export function SettingsPage() {
         <div className="kv-grid">
           {secretRow('Master Key', 'master_key', data.master_key_set)}
           {secretRow('OpenAI API Key', 'openai', data.openai_api_key_set)}
+          {secretRow('Anthropic API Key', 'anthropic', data.anthropic_api_key_set)}
           {secretRow('Slack Signing Secret', 'slack_signing', data.slack_signing_secret_set)}
           {secretRow('Slack Bot Token', 'slack_bot', data.slack_bot_token_set)}
           {secretRow('Telegram Bot Token', 'telegram_bot', data.telegram_bot_token_set)}
@@ -339,6 +525,7 @@ export function SettingsPage() {
               Environment variables take precedence over stored secrets.
             </p>
             {secretManagerRow('OpenAI API Key', 'openai', 'sk-…')}
+            {secretManagerRow('Anthropic API Key', 'anthropic', 'sk-ant-…')}
             {secretManagerRow('Slack Signing Secret', 'slack_signing')}
             {secretManagerRow('Slack Bot Token', 'slack_bot', 'xoxb-…')}
             {secretManagerRow('Telegram Bot Token', 'telegram_bot', '123456:ABC…')}
@@ -87,9 +87,84 @@ export function StatusPage() {
             <div className="kv-label">Guardrails Enabled</div>
             <div className="kv-value">{data.guardrails_enabled}</div>
           </div>
+          <div className="kv-item">
+            <div className="kv-label">Slack Requests Rejected</div>
+            <div className="kv-value" title={`missing header ${data.slack_signature_checks.missing_header}, invalid header ${data.slack_signature_checks.invalid_header}`}>
+              {data.slack_signature_checks.signature_mismatch} bad signature · {data.slack_signature_checks.timestamp_skew} stale · {data.slack_signature_checks.replayed} replayed
+            </div>
+          </div>
         </div>
       </div>
 
+      <div className="card">
+        <div className="card-title">Worker Instances</div>
+        <table>
+          <thead>
+            <tr><th>Worker</th><th>Host</th><th>Role</th><th>Active Tasks</th><th>Last Heartbeat</th></tr>
+          </thead>
+          <tbody>
+            {data.workers.map((w) => (
+              <tr key={w.worker_id}>
+                <td style={{ fontFamily: 'var(--mono)', fontSize: 12 }}>{w.worker_id.slice(0, 15)}</td>
+                <td>{w.hostname} (pid {w.pid})</td>
+                <td>{w.is_leader ? 'Leader' : 'Follower'}</td>
+                <td>{w.active_tasks}</td>
+                <td>
+                  <span className={`pill ${w.seconds_since_heartbeat <= 45 ? 'pill-ok' : 'pill-bad'}`}>
+                    <span className="pill-dot" />{w.seconds_since_heartbeat}s ago
+                  </span>
+                </td>
+              </tr>
+            ))}
+            {data.workers.length === 0 && (
+              <tr><td colSpan={5} style={{ textAlign: 'center', color: 'var(--text-tertiary)', padding: 16 }}>No workers</td></tr>
+            )}
+          </tbody>
+        </table>
+      </div>
+
+      <div className="card">
+        <div className="card-title">Web Search Quota (Brave)</div>
+        {data.web_search_quota ? (() => {
+          const q = data.web_search_quota;
+          const now = Date.now() / 1000;
+          const blocked = q.blocked_until !== null && q.blocked_until > now;
+          const fmt = (ts: number) => new Date(ts * 1000).toLocaleString();
+          return (
+            <div className="kv-grid">
+              <div className="kv-item">
+                <div className="kv-label">Availability</div>
+                <div className="kv-value">
+                  {statusPill(!blocked, 'Available', `Unavailable until ${q.blocked_until ? fmt(q.blocked_until) : ''}`)}
+                </div>
+              </div>
+              {q.windows.map((w, i) => (
+                <div className="kv-item" key={i}>
+                  <div className="kv-label">Quota window {i + 1}</div>
+                  <div className="kv-value">{w.remaining} / {w.limit} left (resets {fmt(w.resets_at)})</div>
+                </div>
+              ))}
+              <div className="kv-item">
+                <div className="kv-label">Requests This Period</div>
+                <div className="kv-value">{q.local_requests}</div>
+              </div>
+              <div className="kv-item">
+                <div className="kv-label">Fallback Searches</div>
+                <div className="kv-value">{q.fallback_searches}</div>
+              </div>
+              {q.last_error && (
+                <div className="kv-item">
+                  <div className="kv-label">Last Error</div>
+                  <div className="kv-value">{q.last_error}</div>
+                </div>
+              )}
+            </div>
+          );
+        })() : (
+          <div style={{ color: 'var(--text-tertiary)' }}>No web searches recorded yet.</div>
+        )}
+      </div>
+
       <div className="card">
         <div className="card-title">Browser Automation</div>
         <div className="kv-grid">
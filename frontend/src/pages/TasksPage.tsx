@@ -1,6 +1,14 @@
 import { useEffect, useMemo, useState } from 'react';
 import { Link, useNavigate, useParams } from 'react-router-dom';
-import { api, type TaskData, type TaskListItemData, type TaskTraceData } from '../lib/api';
+import {
+  analyticsExportUrl,
+  api,
+  taskArtifactUrl,
+  type TaskArtifactData,
+  type TaskData,
+  type TaskListItemData,
+  type TaskTraceData,
+} from '../lib/api';
 
 type TranscriptRole = 'user' | 'assistant' | 'tool' | 'system';
 
@@ -145,9 +153,17 @@ export function TasksPage() {
   const [tasks, setTasks] = useState<TaskListItemData[]>([]);
   const [detailTask, setDetailTask] = useState<TaskData | null>(null);
   const [traces, setTraces] = useState<TaskTraceData[]>([]);
+  const [artifacts, setArtifacts] = useState<TaskArtifactData[]>([]);
   const [statusFilter, setStatusFilter] = useState('all');
   const [listError, setListError] = useState('');
   const [detailError, setDetailError] = useState('');
+  const [openLink, setOpenLink] = useState('');
+  const [openLinkError, setOpenLinkError] = useState('');
+  const [rerunIds, setRerunIds] = useState<number[]>([]);
+  const [rerunModel, setRerunModel] = useState('');
+  const [rerunTimeoutMinutes, setRerunTimeoutMinutes] = useState('');
+  const [rerunReadOnly, setRerunReadOnly] = useState(false);
+  const [rerunMessage, setRerunMessage] = useState('');
 
   const statusColor = (s: string) => {
     if (s === 'succeeded' || s === 'completed' || s === 'done') return 'var(--green)';
@@ -171,6 +187,7 @@ export function TasksPage() {
         if (!exists) {
           setDetailTask(null);
           setTraces([]);
+          setArtifacts([]);
           navigate('/tasks', { replace: true });
         }
       }
@@ -179,11 +196,24 @@ export function TasksPage() {
     }
   };
 
+  const openTaskLink = async () => {
+    if (!openLink.trim()) return;
+    try {
+      const response = await api.resolveTaskLink(openLink.trim());
+      setOpenLink('');
+      setOpenLinkError('');
+      navigate(`/tasks/${response.id}`);
+    } catch (err) {
+      setOpenLinkError(err instanceof Error ? err.message : 'Failed to open task link');
+    }
+  };
+
   const loadDetail = async (taskId: number) => {
     try {
       const response = await api.getTask(taskId);
       setDetailTask(response.task);
       setTraces(response.traces);
+      setArtifacts(response.artifacts);
       setDetailError('');
     } catch (err) {
       setDetailError(err instanceof Error ? err.message : 'Failed to load task details');
@@ -202,6 +232,7 @@ export function TasksPage() {
     if (!selectedTaskId) {
       setDetailTask(null);
       setTraces([]);
+      setArtifacts([]);
       setDetailError('');
       return;
     }
@@ -260,6 +291,29 @@ export function TasksPage() {
       .catch((err) => setDetailError(err instanceof Error ? err.message : 'Failed to retry task'));
   };
 
+  const toggleRerun = (taskId: number) => {
+    setRerunIds((ids) => (ids.includes(taskId) ? ids.filter((i) => i !== taskId) : [...ids, taskId]));
+  };
+
+  const rerunSelected = async () => {
+    const minutes = parseInt(rerunTimeoutMinutes, 10);
+    try {
+      const response = await api.rerunTasks({
+        task_ids: rerunIds,
+        model: rerunModel.trim() || undefined,
+        read_only: rerunReadOnly,
+        timeout_seconds: minutes > 0 ? minutes * 60 : undefined,
+      });
+      const queued = response.tasks.map((t) => `#${t.from} → #${t.id}`).join(', ');
+      const skipped = response.skipped.map((i) => `#${i}`).join(', ');
+      setRerunMessage(`Re-queued ${queued || 'nothing'}${skipped ? `; skipped ${skipped} (not failed)` : ''}.`);
+      setRerunIds([]);
+      void loadList();
+    } catch (err) {
+      setRerunMessage(err instanceof Error ? `Error: ${err.message}` : 'Failed to re-run tasks');
+    }
+  };
+
   const transcript = useMemo<TranscriptMessage[]>(() => {
     if (!detailTask) return [];
 
@@ -323,32 +377,106 @@ export function TasksPage() {
             </button>
           ))}
         </div>
+        <form
+          style={{ display: 'flex', gap: 4, marginLeft: 'auto' }}
+          onSubmit={(e) => {
+            e.preventDefault();
+            void openTaskLink();
+          }}
+        >
+          <input
+            className="form-input"
+            value={openLink}
+            onChange={(e) => setOpenLink(e.target.value)}
+            placeholder="grail://task/42 or #42"
+            style={{ width: 200 }}
+          />
+          <button className="btn btn-sm" type="submit">Open</button>
+        </form>
+        <div style={{ display: 'flex', gap: 4 }}>
+          <a className="btn btn-sm" href={analyticsExportUrl({ days: 30 })} title="Last 30 days, including prompt and result text">
+            Export usage
+          </a>
+          <a
+            className="btn btn-sm"
+            href={analyticsExportUrl({ days: 30, anonymize: true })}
+            title="No prompt/result text; user, channel and workspace IDs hashed; times rounded to the hour"
+          >
+            Export anonymized
+          </a>
+        </div>
       </div>
 
+      {rerunIds.length > 0 && (
+        <div className="card" style={{ display: 'flex', gap: 8, alignItems: 'center', flexWrap: 'wrap', marginBottom: 12 }}>
+          <span>Re-run {rerunIds.length} selected as new tasks with:</span>
+          <input
+            className="form-input"
+            value={rerunModel}
+            onChange={(e) => setRerunModel(e.target.value)}
+            placeholder="model (unchanged)"
+            style={{ width: 180 }}
+          />
+          <input
+            className="form-input"
+            type="number"
+            min={1}
+            value={rerunTimeoutMinutes}
+            onChange={(e) => setRerunTimeoutMinutes(e.target.value)}
+            placeholder="timeout (min)"
+            style={{ width: 130 }}
+          />
+          <label className="form-checkbox-row" style={{ margin: 0 }}>
+            <input type="checkbox" checked={rerunReadOnly} onChange={(e) => setRerunReadOnly(e.target.checked)} />
+            Read-only
+          </label>
+          <button className="btn btn-sm btn-primary" onClick={() => void rerunSelected()}>Re-run</button>
+          <button className="btn btn-sm" onClick={() => setRerunIds([])}>Clear</button>
+        </div>
+      )}
+      {rerunMessage && (
+        <div className="card" style={{ marginBottom: 12, color: rerunMessage.startsWith('Error') ? 'var(--red)' : undefined }}>
+          {rerunMessage}
+        </div>
+      )}
+
       <div className="tasks-layout">
         <section className="tasks-sidebar card">
           <div className="card-title">Tasks</div>
           <div className="tasks-sidebar-inner">
             {visibleTasks.map((task) => (
-              <Link
-                key={task.id}
-                className={`task-item ${task.id === selectedTaskId ? 'active' : ''}`}
-                to={`/tasks/${task.id}`}
-              >
-                <div className="task-item-head">
-                  <span className="task-item-id">#{task.id}</span>
-                  <span className="pill" style={{ color: statusColor(task.status) }}>
-                    <span className="pill-dot" />
-                    {formatStatusLabel(task.status)}
-                  </span>
-                </div>
-                <div className="task-item-title">{task.prompt_text?.trim() || 'No prompt text.'}</div>
-                <div className="task-item-meta">
-                  <span>{task.provider}</span>
-                  {task.is_proactive && <span className="pill mini-pill">proactive</span>}
-                  <span>{task.created_at}</span>
-                </div>
-              </Link>
+              <div key={task.id} style={{ display: 'flex', gap: 6, alignItems: 'flex-start' }}>
+                {['failed', 'cancelled'].includes(task.status) && (
+                  <input
+                    type="checkbox"
+                    title="Select for re-run"
+                    checked={rerunIds.includes(task.id)}
+                    onChange={() => toggleRerun(task.id)}
+                    style={{ marginTop: 14 }}
+                  />
+                )}
+                <Link
+                  className={`task-item ${task.id === selectedTaskId ? 'active' : ''}`}
+                  to={`/tasks/${task.id}`}
+                  style={{ flex: 1 }}
+                >
+                  <div className="task-item-head">
+                    <span className="task-item-id">#{task.id}</span>
+                    <span className="pill" style={{ color: statusColor(task.status) }}>
+                      <span className="pill-dot" />
+                      {formatStatusLabel(task.status)}
+                    </span>
+                  </div>
+                  <div className="task-item-title">{task.prompt_text?.trim() || 'No prompt text.'}</div>
+                  <div className="task-item-meta">
+                    <span>{task.provider}</span>
+                    {task.is_proactive && <span className="pill mini-pill">proactive</span>}
+                    {task.is_synthetic && <span className="pill mini-pill">synthetic</span>}
+                    {task.rerun_of_task_id != null && <span className="pill mini-pill">re-run of #{task.rerun_of_task_id}</span>}
+                    <span>{task.created_at}</span>
+                  </div>
+                </Link>
+              </div>
             ))}
             {visibleTasks.length === 0 && (
               <div className="tasks-sidebar-empty">
@@ -416,6 +544,53 @@ export function TasksPage() {
                   <div className="kv-label">Finished</div>
                   <div className="kv-value">{detailTask.finished_at || '—'}</div>
                 </div>
+                <div className="kv-item">
+                  <div className="kv-label">Link</div>
+                  <div className="kv-value">
+                    <code>{detailTask.links.app}</code>
+                    {' · '}
+                    <a href={detailTask.links.web}>dashboard</a>
+                    {detailTask.links.slack && (
+                      <>
+                        {' · '}
+                        <a href={detailTask.links.slack} target="_blank" rel="noreferrer">Slack message</a>
+                      </>
+                    )}
+                  </div>
+                </div>
+                {(detailTask.rerun_of_task_id != null || detailTask.reruns.length > 0) && (
+                  <div className="kv-item">
+                    <div className="kv-label">Lineage</div>
+                    <div className="kv-value">
+                      {detailTask.rerun_of_task_id != null && (
+                        <>
+                          re-run of <Link to={`/tasks/${detailTask.rerun_of_task_id}`}>#{detailTask.rerun_of_task_id}</Link>
+                        </>
+                      )}
+                      {detailTask.rerun_of_task_id != null && detailTask.reruns.length > 0 && ' · '}
+                      {detailTask.reruns.length > 0 && (
+                        <>
+                          re-run as{' '}
+                          {detailTask.reruns.map((r, i) => (
+                            <span key={r}>
+                              {i > 0 && ', '}
+                              <Link to={`/tasks/${r}`}>#{r}</Link>
+                            </span>
+                          ))}
+                        </>
+                      )}
+                    </div>
+                  </div>
+                )}
+                {detailTask.depends_on_task_id != null && (
+                  <div className="kv-item">
+                    <div className="kv-label">After</div>
+                    <div className="kv-value">
+                      #{detailTask.depends_on_task_id}
+                      {detailTask.on_dependency_failure === 'run' ? ' (regardless)' : ` (on failure: ${detailTask.on_dependency_failure})`}
+                    </div>
+                  </div>
+                )}
               </div>
 
               <div className="kv-grid task-summary-grid">
@@ -431,6 +606,19 @@ export function TasksPage() {
                 </div>
               </div>
 
+              {artifacts.length > 0 && (
+                <div className="trace-panel">
+                  <div className="card-title" style={{ marginBottom: 8 }}>Artifacts</div>
+                  <p className="trace-subtitle">Full output of commands run after approval (secrets redacted).</p>
+                  {artifacts.map((a) => (
+                    <div key={a.id} style={{ display: 'flex', gap: 8, alignItems: 'center', marginBottom: 4 }}>
+                      <a className="btn btn-sm" href={taskArtifactUrl(detailTask.id, a.id)}>{a.name}</a>
+                      <span className="chat-time">{a.chars} chars · {a.created_at}</span>
+                    </div>
+                  ))}
+                </div>
+              )}
+
               <div className="trace-panel">
                 <div className="card-title" style={{ marginBottom: 8 }}>Chat Transcript</div>
                 <p className="trace-subtitle">Read-only AI SDK-style timeline of user, tool, system, and assistant messages.</p>
@@ -466,6 +654,7 @@ export function TasksPage() {
         </section>
       </div>
 
+      {openLinkError && <div className="card" style={{ color: 'var(--red)', marginTop: 12 }}>Error: {openLinkError}</div>}
       {listError && <div className="card" style={{ color: 'var(--red)', marginTop: 12 }}>Error: {listError}</div>}
     </>
   );
@@ -0,0 +1,127 @@
+import { useEffect, useState } from 'react';
+import { Link } from 'react-router-dom';
+import { api, type TaskData } from '../lib/api';
+
+const FINISHED = ['succeeded', 'failed', 'cancelled', 'ignored'];
+
+export function TestPromptPage() {
+  const [provider, setProvider] = useState('slack');
+  const [channelId, setChannelId] = useState('');
+  const [userId, setUserId] = useState('');
+  const [threadTs, setThreadTs] = useState('');
+  const [prompt, setPrompt] = useState('');
+  const [schema, setSchema] = useState('');
+  const [taskId, setTaskId] = useState<number | null>(null);
+  const [task, setTask] = useState<TaskData | null>(null);
+  const [error, setError] = useState('');
+
+  useEffect(() => {
+    if (taskId === null) return;
+    let stopped = false;
+    const poll = () => {
+      api
+        .getTask(taskId)
+        .then((d) => {
+          if (stopped) return;
+          setTask(d.task);
+          if (!FINISHED.includes(d.task.status)) setTimeout(poll, 2000);
+        })
+        .catch((e) => setError(e.message));
+    };
+    poll();
+    return () => { stopped = true; };
+  }, [taskId]);
+
+  const run = async () => {
+    try {
+      setError('');
+      setTask(null);
+      let outputSchema: unknown;
+      if (schema.trim()) {
+        try { outputSchema = JSON.parse(schema); } catch { setError('Output schema is not valid JSON'); return; }
+      }
+      const res = await api.testPrompt({
+        provider, channel_id: channelId, user_id: userId, thread_ts: threadTs, prompt_text: prompt,
+        output_schema: outputSchema,
+      });
+      setTaskId(res.task_id);
+    } catch (e) { setError(e instanceof Error ? e.message : 'Failed'); }
+  };
+
+  return (
+    <>
+      <h2>Test Prompt</h2>
+      <p className="section-desc">
+        Run a prompt through the full pipeline as if it came from a channel and user. The run is marked
+        synthetic: nothing is posted to the channel, side effects (context writes, uploads, cron jobs,
+        guardrail rules, memory updates) are skipped, and approvals appear only on the Approvals page.
+      </p>
+
+      {error && <div className="card" style={{ color: 'var(--red)' }}>Error: {error}</div>}
+
+      <div className="card">
+        <div className="card-title">Synthetic Request</div>
+        <div style={{ display: 'grid', gridTemplateColumns: '1fr 1fr', gap: 16 }}>
+          <div className="form-group">
+            <label className="form-label">Provider</label>
+            <select className="form-select" value={provider} onChange={(e) => setProvider(e.target.value)}>
+              <option value="slack">slack</option>
+              <option value="telegram">telegram</option>
+              <option value="whatsapp">whatsapp</option>
+              <option value="discord">discord</option>
+              <option value="msteams">msteams</option>
+            </select>
+          </div>
+          <div className="form-group">
+            <label className="form-label">Channel ID</label>
+            <input className="form-input" value={channelId} onChange={(e) => setChannelId(e.target.value)} />
+          </div>
+          <div className="form-group">
+            <label className="form-label">User ID</label>
+            <input className="form-input" value={userId} onChange={(e) => setUserId(e.target.value)} />
+          </div>
+          <div className="form-group">
+            <label className="form-label">Thread TS (optional)</label>
+            <input className="form-input" value={threadTs} onChange={(e) => setThreadTs(e.target.value)} />
+          </div>
+        </div>
+        <div className="form-group">
+          <label className="form-label">Prompt</label>
+          <textarea className="form-textarea" rows={4} value={prompt} onChange={(e) => setPrompt(e.target.value)} />
+        </div>
+        <div className="form-group">
+          <label className="form-label">Output Schema (optional)</label>
+          <textarea className="form-textarea" rows={4} value={schema} onChange={(e) => setSchema(e.target.value)} placeholder={'{"type": "object", "required": ["summary"], "properties": {"summary": {"type": "string"}}}'} />
+          <p className="section-desc">A JSON Schema the answer must conform to. The answer is validated, repaired if needed, and returned parsed as <code>output</code>.</p>
+        </div>
+        <button className="btn btn-primary" onClick={run}>Run Test</button>
+      </div>
+
+      {taskId !== null && (
+        <div className="card">
+          <div className="card-title">
+            Result <span className="pill mini-pill">synthetic</span>
+          </div>
+          <div className="kv-grid">
+            <div className="kv-item">
+              <div className="kv-label">Task</div>
+              <div className="kv-value"><Link to={`/tasks/${taskId}`}>#{taskId}</Link></div>
+            </div>
+            <div className="kv-item">
+              <div className="kv-label">Status</div>
+              <div className="kv-value">{task?.status ?? 'queued'}</div>
+            </div>
+          </div>
+          {task?.output != null ? (
+            <pre style={{ whiteSpace: 'pre-wrap', marginTop: 12 }}>{JSON.stringify(task.output, null, 2)}</pre>
+          ) : task?.result_text && (
+            <pre style={{ whiteSpace: 'pre-wrap', marginTop: 12 }}>{task.result_text}</pre>
+          )}
+          {task?.error_text && (
+            <pre style={{ whiteSpace: 'pre-wrap', marginTop: 12, color: 'var(--red)' }}>{task.error_text}</pre>
+          )}
+        </div>
+      )}
+    </>
+  );
+}
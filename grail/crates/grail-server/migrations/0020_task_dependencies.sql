@@ -0,0 +1,11 @@
+-- Task dependency chains: a task may wait for a predecessor ("after #N").
+--
+-- on_dependency_failure controls what happens when the predecessor does not succeed:
+--   fail   - mark this task failed (propagates further down the chain)
+--   cancel - mark this task cancelled
+--   run    - run anyway once the predecessor has finished
+ALTER TABLE tasks ADD COLUMN depends_on_task_id INTEGER;
+ALTER TABLE tasks ADD COLUMN on_dependency_failure TEXT NOT NULL DEFAULT 'fail';
+
+CREATE INDEX IF NOT EXISTS tasks_depends_on_task_id_idx
+  ON tasks(depends_on_task_id);
@@ -0,0 +1,18 @@
+-- Multiple grail-server replicas can share one database: every instance processes
+-- tasks, while the worker_lock holder acts as leader (cron, cleanup, orphan recovery).
+
+CREATE TABLE IF NOT EXISTS worker_heartbeats (
+  worker_id TEXT PRIMARY KEY,
+  hostname TEXT NOT NULL DEFAULT '',
+  pid INTEGER NOT NULL DEFAULT 0,
+  is_leader INTEGER NOT NULL DEFAULT 0,
+  active_tasks INTEGER NOT NULL DEFAULT 0,
+  started_at INTEGER NOT NULL,
+  last_seen_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS worker_heartbeats_last_seen_at_idx
+  ON worker_heartbeats(last_seen_at);
+
+-- Which worker instance claimed a running task (so only orphaned tasks are re-queued).
+ALTER TABLE tasks ADD COLUMN worker_id TEXT;
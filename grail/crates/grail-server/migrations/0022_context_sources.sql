@@ -0,0 +1,2 @@
+-- Per-task context source selection (JSON; empty = defaults).
+ALTER TABLE settings ADD COLUMN context_sources TEXT NOT NULL DEFAULT '';
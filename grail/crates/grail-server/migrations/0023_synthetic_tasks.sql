@@ -0,0 +1,2 @@
+-- Admin test prompts: run the full pipeline but never post to the originating channel.
+ALTER TABLE tasks ADD COLUMN is_synthetic INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Agent backend selection per workspace (JSON; empty = Codex for every workspace).
+ALTER TABLE settings ADD COLUMN llm_backends TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,22 @@
+-- Reuse recent answers for repeated prompts in the same channel.
+-- response_cache_mode: off | exact | semantic (embeddings; falls back to exact).
+ALTER TABLE settings ADD COLUMN response_cache_mode TEXT NOT NULL DEFAULT 'off';
+ALTER TABLE settings ADD COLUMN response_cache_ttl_seconds INTEGER NOT NULL DEFAULT 86400;
+
+CREATE TABLE IF NOT EXISTS response_cache (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  workspace_id TEXT NOT NULL,
+  channel_id TEXT NOT NULL,
+  prompt_hash TEXT NOT NULL,
+  prompt_text TEXT NOT NULL,
+  embedding_json TEXT,
+  task_id INTEGER NOT NULL,
+  response_text TEXT NOT NULL,
+  created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS response_cache_channel_created_at_idx
+  ON response_cache(workspace_id, channel_id, created_at);
+
+-- "Regenerate" re-runs bypass the cache.
+ALTER TABLE tasks ADD COLUMN skip_response_cache INTEGER NOT NULL DEFAULT 0;
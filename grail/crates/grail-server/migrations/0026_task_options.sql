@@ -0,0 +1,3 @@
+-- Per-task options picked in the Slack task modal (model, permissions, context channels,
+-- deadline), stored as JSON. Empty for tasks from mentions and DMs.
+ALTER TABLE tasks ADD COLUMN options_json TEXT NOT NULL DEFAULT '';
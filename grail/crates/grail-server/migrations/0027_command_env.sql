@@ -0,0 +1,2 @@
+-- Environment for sandboxed commands (JSON; empty = Codex "core" inheritance, nothing injected).
+ALTER TABLE settings ADD COLUMN command_env TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,3 @@
+-- How often each guardrail rule decided a command, for approval insights (stale rules).
+ALTER TABLE guardrail_rules ADD COLUMN match_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE guardrail_rules ADD COLUMN last_matched_at INTEGER;
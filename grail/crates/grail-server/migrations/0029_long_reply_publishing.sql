@@ -0,0 +1,3 @@
+-- Long Slack replies: 'off' (post in the thread), 'file' (Markdown upload) or 'canvas'.
+ALTER TABLE settings ADD COLUMN slack_long_reply_mode TEXT NOT NULL DEFAULT 'off';
+ALTER TABLE settings ADD COLUMN slack_long_reply_chars INTEGER NOT NULL DEFAULT 4000;
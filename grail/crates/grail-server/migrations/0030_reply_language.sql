@@ -0,0 +1,2 @@
+-- Reply language policy (JSON; empty = detect the request's language everywhere).
+ALTER TABLE settings ADD COLUMN reply_language TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,6 @@
+-- Queue priority (higher runs first) and who may raise it with "bump #<id>".
+ALTER TABLE tasks ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE settings ADD COLUMN task_bump_allow_from TEXT NOT NULL DEFAULT '';
+
+CREATE INDEX IF NOT EXISTS tasks_status_priority_created_at_idx
+  ON tasks(status, priority DESC, created_at);
@@ -0,0 +1,10 @@
+-- Admin-curated prompt templates, run from chat with "run <name>".
+CREATE TABLE IF NOT EXISTS saved_prompts (
+  id TEXT PRIMARY KEY,
+  name TEXT NOT NULL UNIQUE,   -- lowercase slug, e.g. incident-summary
+  description TEXT NOT NULL DEFAULT '',
+  template TEXT NOT NULL,      -- {{args}} {{thread}} {{user}} {{channel}} {{date}}
+  enabled INTEGER NOT NULL DEFAULT 1,
+  created_at INTEGER NOT NULL,
+  updated_at INTEGER NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Opt-in unauthenticated /status page with aggregate health only.
+ALTER TABLE settings ADD COLUMN public_status_page INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Answer accepted Slack events with X-Slack-No-Retry: 1 so Slack doesn't redeliver them.
+ALTER TABLE settings ADD COLUMN slack_no_retry_after_enqueue INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Token budget for conversation context, per model. '' keeps the character budget
+-- (context_sources.budget_chars) and the context_last_n message count.
+ALTER TABLE settings ADD COLUMN context_token_budget TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,3 @@
+-- Banned-topics content policy: optional OpenAI moderation check and the decline message.
+ALTER TABLE settings ADD COLUMN content_policy_moderation INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE settings ADD COLUMN content_policy_message TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,3 @@
+-- Claiming skips tasks whose reply thread already has a running task.
+CREATE INDEX IF NOT EXISTS tasks_status_channel_thread_idx
+  ON tasks(status, channel_id, thread_ts);
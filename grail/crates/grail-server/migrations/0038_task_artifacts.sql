@@ -0,0 +1,17 @@
+-- Files kept with a task, e.g. the full output of a command run after approval.
+CREATE TABLE IF NOT EXISTS task_artifacts (
+  id TEXT PRIMARY KEY,
+  task_id INTEGER NOT NULL,
+  kind TEXT NOT NULL,          -- command_output
+  name TEXT NOT NULL,
+  content TEXT NOT NULL,
+  approval_id TEXT,
+  created_at INTEGER NOT NULL,
+  FOREIGN KEY (task_id) REFERENCES tasks(id)
+);
+
+CREATE INDEX IF NOT EXISTS task_artifacts_task_id_idx
+  ON task_artifacts(task_id);
+
+-- Approved commands post their output to the thread, cut to this many characters (0 = off).
+ALTER TABLE settings ADD COLUMN approval_output_chars INTEGER NOT NULL DEFAULT 1500;
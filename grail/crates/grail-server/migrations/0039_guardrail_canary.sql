@@ -0,0 +1,28 @@
+-- Canary guardrail policy: a full candidate rule set (JSON, config bundle rule format) that
+-- decides only in the listed channels until ends_at, then waits to be promoted or discarded.
+CREATE TABLE IF NOT EXISTS guardrail_canary (
+  id INTEGER PRIMARY KEY CHECK (id = 1),
+  channels TEXT NOT NULL,      -- comma-separated channel ids
+  rules_json TEXT NOT NULL,
+  started_at INTEGER NOT NULL,
+  ends_at INTEGER NOT NULL
+);
+
+-- Side-by-side decisions while a canary is live: every decision in a canary channel, plus
+-- decisions elsewhere where the canary would have decided differently.
+CREATE TABLE IF NOT EXISTS guardrail_canary_decisions (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  task_id INTEGER NOT NULL,
+  channel_id TEXT NOT NULL,
+  kind TEXT NOT NULL,          -- command | topic
+  subject TEXT NOT NULL,       -- redacted, truncated command or message
+  applied TEXT NOT NULL,       -- active | canary
+  active_decision TEXT NOT NULL,
+  active_rule_id TEXT,
+  canary_decision TEXT NOT NULL,
+  canary_rule_id TEXT,
+  created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS guardrail_canary_decisions_created_at_idx
+  ON guardrail_canary_decisions(created_at);
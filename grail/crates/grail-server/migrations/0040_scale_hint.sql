@@ -0,0 +1,4 @@
+-- Autoscaling hints: thresholds that raise scale events (0 = off) and where to send them.
+ALTER TABLE settings ADD COLUMN scale_queue_depth_threshold INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE settings ADD COLUMN scale_wait_seconds_threshold INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE settings ADD COLUMN scale_webhook_url TEXT;
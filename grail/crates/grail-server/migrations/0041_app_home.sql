@@ -0,0 +1,8 @@
+-- Who asked for a cron job (Slack user id), so the App Home can list a user's reminders.
+ALTER TABLE cron_jobs ADD COLUMN created_by_user_id TEXT;
+
+CREATE INDEX IF NOT EXISTS cron_jobs_created_by_user_id_idx
+  ON cron_jobs(created_by_user_id);
+
+CREATE INDEX IF NOT EXISTS approvals_requested_by_status_idx
+  ON approvals(requested_by_user_id, status);
@@ -0,0 +1,2 @@
+-- Append a link back to the task (grail://task/<id> and the dashboard URL) to replies.
+ALTER TABLE settings ADD COLUMN reply_task_links INTEGER NOT NULL DEFAULT 0;
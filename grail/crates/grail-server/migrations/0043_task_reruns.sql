@@ -0,0 +1,5 @@
+-- Bulk re-runs from the admin UI: each copy points at the task it re-runs.
+ALTER TABLE tasks ADD COLUMN rerun_of_task_id INTEGER;
+
+CREATE INDEX IF NOT EXISTS tasks_rerun_of_task_id_idx
+  ON tasks(rerun_of_task_id);
@@ -0,0 +1,13 @@
+-- Channels that opted in to the monthly usage report ("usage report on").
+CREATE TABLE IF NOT EXISTS usage_reports (
+  provider TEXT NOT NULL,                  -- slack | telegram
+  workspace_id TEXT NOT NULL,
+  channel_id TEXT NOT NULL,
+  enabled_by_user_id TEXT NOT NULL DEFAULT '',
+  last_period TEXT NOT NULL DEFAULT '',    -- YYYY-MM of the last report posted
+  created_at INTEGER NOT NULL,
+  PRIMARY KEY (provider, workspace_id, channel_id)
+);
+
+-- Minutes of work a completed task is assumed to save, for the report's estimate.
+ALTER TABLE settings ADD COLUMN usage_report_minutes_per_task INTEGER NOT NULL DEFAULT 10;
@@ -0,0 +1,2 @@
+-- Speech-to-text for voice messages (JSON; empty = voice messages are not transcribed).
+ALTER TABLE settings ADD COLUMN speech_to_text TEXT NOT NULL DEFAULT '';
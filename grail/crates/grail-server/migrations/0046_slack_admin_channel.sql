@@ -0,0 +1,3 @@
+-- Slack channel for admin notices, e.g. when a channel the bot served was archived or
+-- the bot was removed from it. Empty = log only.
+ALTER TABLE settings ADD COLUMN slack_admin_channel TEXT NOT NULL DEFAULT '';
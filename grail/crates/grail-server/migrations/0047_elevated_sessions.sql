@@ -0,0 +1,21 @@
+-- Time-boxed "full" permissions for one channel ("elevate 2h incident"). Rows are never
+-- deleted: together they are the audit trail of who elevated what, when and why.
+CREATE TABLE IF NOT EXISTS elevated_sessions (
+  id TEXT PRIMARY KEY,
+  provider TEXT NOT NULL,                  -- slack | telegram
+  workspace_id TEXT NOT NULL,
+  channel_id TEXT NOT NULL,
+  granted_by TEXT NOT NULL,                -- chat user id, or "admin" from the dashboard
+  reason TEXT NOT NULL DEFAULT '',
+  started_at INTEGER NOT NULL,
+  ends_at INTEGER NOT NULL,
+  revoked_at INTEGER,
+  revoked_by TEXT,
+  end_notified INTEGER NOT NULL DEFAULT 0  -- the channel was told permissions reverted
+);
+
+CREATE INDEX IF NOT EXISTS idx_elevated_sessions_channel
+  ON elevated_sessions(provider, workspace_id, channel_id, ends_at);
+
+-- Users allowed to elevate a channel from chat (comma/space separated ids). Empty = nobody.
+ALTER TABLE settings ADD COLUMN elevate_allow_from TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,3 @@
+-- Bumped on every claim. A worker that stalls past its heartbeat and has its task
+-- re-queued sees the generation move on and drops its result instead of applying it twice.
+ALTER TABLE tasks ADD COLUMN claim_generation INTEGER NOT NULL DEFAULT 0;
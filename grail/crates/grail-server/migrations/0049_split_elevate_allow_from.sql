@@ -0,0 +1,4 @@
+-- Slack and Telegram user ids are separate namespaces, so each provider gets its own list of
+-- users allowed to elevate. Existing entries stay with Slack.
+ALTER TABLE settings RENAME COLUMN elevate_allow_from TO slack_elevate_allow_from;
+ALTER TABLE settings ADD COLUMN telegram_elevate_allow_from TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,4 @@
+-- Failed usage report posts back off instead of retrying on every check; the count resets
+-- when a report goes out or the report for that month is given up.
+ALTER TABLE usage_reports ADD COLUMN failures INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE usage_reports ADD COLUMN retry_at INTEGER;
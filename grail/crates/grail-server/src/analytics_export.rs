@@ -0,0 +1,229 @@
+//! Usage analytics export: one row per task, as CSV or JSON.
+//!
+//! With `anonymize`, the export is meant to be shared for capacity planning: prompt,
+//! result and error text are dropped, task ids are omitted, workspace/channel/user ids
+//! are replaced with salted hashes and timestamps are rounded down to the hour. The salt
+//! is random per export, so pseudonyms are consistent within one file but can't be
+//! joined across exports or reversed by hashing known Slack ids.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::models::Task;
+
+pub const DEFAULT_DAYS: i64 = 30;
+pub const MAX_DAYS: i64 = 365;
+pub const MAX_ROWS: i64 = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExportRow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<i64>,
+    pub provider: String,
+    pub workspace: String,
+    pub channel: String,
+    pub user: String,
+    pub status: String,
+    pub is_proactive: bool,
+    pub created_at: i64,
+    /// Seconds from enqueue to start.
+    pub queue_seconds: Option<i64>,
+    /// Seconds from start to finish.
+    pub run_seconds: Option<i64>,
+    pub prompt_chars: usize,
+    pub result_chars: usize,
+    pub file_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_text: Option<String>,
+}
+
+/// Stable-within-one-export pseudonym such as `u_3f9a0c1b2d4e`. Empty ids stay empty.
+pub fn pseudonym(salt: &str, prefix: &str, id: &str) -> String {
+    if id.trim().is_empty() {
+        return String::new();
+    }
+    let digest = Sha256::digest(format!("{salt}\0{prefix}\0{id}").as_bytes());
+    format!("{prefix}_{}", &hex::encode(digest)[..12])
+}
+
+fn file_count(files_json: &str) -> usize {
+    serde_json::from_str::<Vec<serde_json::Value>>(files_json)
+        .map(|v| v.len())
+        .unwrap_or(0)
+}
+
+pub fn build_rows(tasks: &[Task], anonymize: bool, salt: &str) -> Vec<ExportRow> {
+    tasks
+        .iter()
+        .map(|t| {
+            let result = t.result_text.clone().unwrap_or_default();
+            let id = |prefix: &str, raw: &str| {
+                if anonymize {
+                    pseudonym(salt, prefix, raw)
+                } else {
+                    raw.to_string()
+                }
+            };
+            ExportRow {
+                task_id: (!anonymize).then_some(t.id),
+                provider: t.provider.clone(),
+                workspace: id("w", &t.workspace_id),
+                channel: id("c", &t.channel_id),
+                user: id("u", &t.requested_by_user_id),
+                status: t.status.clone(),
+                is_proactive: t.is_proactive,
+                created_at: if anonymize {
+                    t.created_at - t.created_at.rem_euclid(3_600)
+                } else {
+                    t.created_at
+                },
+                queue_seconds: t.started_at.map(|s| (s - t.created_at).max(0)),
+                run_seconds: t.started_at.zip(t.finished_at).map(|(s, f)| (f - s).max(0)),
+                prompt_chars: t.prompt_text.chars().count(),
+                result_chars: result.chars().count(),
+                file_count: file_count(&t.files_json),
+                prompt_text: (!anonymize).then(|| t.prompt_text.clone()),
+                result_text: (!anonymize).then_some(result),
+                error_text: if anonymize {
+                    None
+                } else {
+                    t.error_text.clone()
+                },
+            }
+        })
+        .collect()
+}
+
+/// Quote a text cell. Text a spreadsheet would evaluate as a formula (starting with `=`,
+/// `+`, `-`, `@`, a tab or a carriage return) gets a leading `'` so it opens as plain text.
+fn csv_field(s: &str) -> String {
+    let s = if s.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{s}")
+    } else {
+        s.to_string()
+    };
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s
+    }
+}
+
+/// CSV with a header row. Text columns are only present when `anonymize` is off.
+pub fn to_csv(rows: &[ExportRow], anonymize: bool) -> String {
+    let mut header = vec![
+        "provider",
+        "workspace",
+        "channel",
+        "user",
+        "status",
+        "is_proactive",
+        "created_at",
+        "queue_seconds",
+        "run_seconds",
+        "prompt_chars",
+        "result_chars",
+        "file_count",
+    ];
+    if !anonymize {
+        header.insert(0, "task_id");
+        header.extend(["prompt_text", "result_text", "error_text"]);
+    }
+    let opt = |v: Option<i64>| v.map(|n| n.to_string()).unwrap_or_default();
+    let mut out = header.join(",");
+    out.push('\n');
+    for r in rows {
+        let mut fields = vec![
+            csv_field(&r.provider),
+            csv_field(&r.workspace),
+            csv_field(&r.channel),
+            csv_field(&r.user),
+            csv_field(&r.status),
+            r.is_proactive.to_string(),
+            r.created_at.to_string(),
+            opt(r.queue_seconds),
+            opt(r.run_seconds),
+            r.prompt_chars.to_string(),
+            r.result_chars.to_string(),
+            r.file_count.to_string(),
+        ];
+        if !anonymize {
+            fields.insert(0, opt(r.task_id));
+            for text in [&r.prompt_text, &r.result_text, &r.error_text] {
+                fields.push(csv_field(text.as_deref().unwrap_or("")));
+            }
+        }
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anonymized_analytics_export_drops_text_and_hashes_ids() {
+        let task = crate::models::Task {
+            id: 7,
+            status: "succeeded".to_string(),
+            provider: "slack".to_string(),
+            is_proactive: false,
+            workspace_id: "T1".to_string(),
+            channel_id: "C1".to_string(),
+            thread_ts: "1.0".to_string(),
+            conversation_key: String::new(),
+            event_ts: "1.0".to_string(),
+            requested_by_user_id: "U1".to_string(),
+            prompt_text: "why is checkout down, \"again\"?".to_string(),
+            files_json: r#"[{"name":"a.log"}]"#.to_string(),
+            result_text: Some("db failover".to_string()),
+            error_text: None,
+            created_at: 1_700_003_725,
+            started_at: Some(1_700_003_730),
+            finished_at: Some(1_700_003_790),
+            depends_on_task_id: None,
+            on_dependency_failure: "fail".to_string(),
+            is_synthetic: false,
+            skip_response_cache: false,
+            options_json: String::new(),
+            rerun_of_task_id: None,
+        };
+        let tasks = vec![task.clone(), task];
+
+        let raw = build_rows(&tasks, false, "s");
+        assert_eq!(raw[0].user, "U1");
+        assert_eq!(raw[0].queue_seconds, Some(5));
+        assert_eq!(raw[0].run_seconds, Some(60));
+        assert_eq!(raw[0].file_count, 1);
+        assert!(to_csv(&raw, false).contains(r#""why is checkout down, ""again""?""#));
+        // Cells a spreadsheet would run as formulas are opened as text instead.
+        let mut formula = raw[0].clone();
+        formula.prompt_text = Some(r#"=HYPERLINK("http://x")"#.to_string());
+        formula.user = "@here".to_string();
+        formula.error_text = Some("-1+1".to_string());
+        let csv = to_csv(&[formula], false);
+        assert!(csv.contains(r#""'=HYPERLINK(""http://x"")""#), "{csv}");
+        assert!(
+            csv.contains(",'@here,") && csv.contains(",'-1+1\n"),
+            "{csv}"
+        );
+
+        let anon = build_rows(&tasks, true, "s");
+        assert_eq!(anon[0].task_id, None);
+        assert_eq!(anon[0].prompt_text, None);
+        assert_eq!(anon[0].result_chars, 11);
+        assert_eq!(anon[0].created_at, 1_700_002_800);
+        assert!(anon[0].user.starts_with("u_") && anon[0].user != "U1");
+        assert_eq!(anon[0].user, anon[1].user);
+        assert_ne!(anon[0].user, build_rows(&tasks, true, "other")[0].user);
+        let csv = to_csv(&anon, true);
+        assert!(!csv.contains("checkout") && !csv.contains("U1") && !csv.contains("C1"));
+        assert!(csv.starts_with("provider,workspace,channel,user,"));
+    }
+}
@@ -49,6 +49,23 @@ pub async fn api_status(State(state): State<AppState>) -> ApiResult<Value> {
     let worker_lock_owner = db::get_worker_lock_owner(&state.pool)
         .await?
         .unwrap_or_default();
+    let now = chrono::Utc::now().timestamp();
+    let workers: Vec<Value> = db::list_worker_heartbeats(&state.pool)
+        .await?
+        .into_iter()
+        .map(|w| {
+            json!({
+                "worker_id": w.worker_id,
+                "hostname": w.hostname,
+                "pid": w.pid,
+                "is_leader": w.is_leader,
+                "active_tasks": w.active_tasks,
+                "started_at": format!("{}", w.started_at),
+                "last_seen_at": format!("{}", w.last_seen_at),
+                "seconds_since_heartbeat": now - w.last_seen_at,
+            })
+        })
+        .collect();
     let active_task = db::list_active_tasks(&state.pool, 1)
         .await?
         .into_iter()
@@ -71,6 +88,13 @@ pub async fn api_status(State(state): State<AppState>) -> ApiResult<Value> {
             .map(|b| format!("{}/{}", b.trim_end_matches('/'), suffix))
             .unwrap_or_else(|| format!("/{suffix}"))
     };
+    let web_search_quota: Value = tokio::fs::read(crate::codex::brave_quota_path(
+        &state.config.effective_codex_home(),
+    ))
+    .await
+    .ok()
+    .and_then(|raw| serde_json::from_slice(&raw).ok())
+    .unwrap_or(Value::Null);
     let browser = BrowserEnvConfig::from_env();
     let browser_novnc_url = if browser.enabled && browser.novnc_enabled {
         browser.novnc_url.clone()
@@ -96,10 +120,13 @@ pub async fn api_status(State(state): State<AppState>) -> ApiResult<Value> {
         "slack_actions_url": mk("slack/actions"),
         "telegram_webhook_url": mk("telegram/webhook"),
         "worker_lock_owner": worker_lock_owner,
+        "workers": workers,
+        "web_search_quota": web_search_quota,
         "active_task_id": active_task.as_ref().map(|(id, _)| format!("{id}")).unwrap_or_default(),
         "active_task_started_at": active_task.as_ref().map(|(_, ts)| format!("{ts}")).unwrap_or_default(),
         "pending_approvals": pending_approvals,
         "guardrails_enabled": guardrails_enabled,
+        "slack_signature_checks": state.slack_guard.metrics(),
         "browser_enabled": browser.enabled,
         "browser_novnc_enabled": browser.novnc_enabled,
         "browser_novnc_url": browser_novnc_url,
@@ -112,13 +139,14 @@ pub async fn api_status(State(state): State<AppState>) -> ApiResult<Value> {
 
 // ─── Settings ──────────────────────────────────────────────────────────────
 
-pub async fn api_settings_get(State(state): State<AppState>) -> ApiResult<Value> {
-    let s = db::get_settings(&state.pool).await?;
-    Ok(Json(json!({
+/// Every admin-editable setting, keyed like [`ApiSettingsPost`]. Used by the settings page
+/// and configuration bundles, so it never includes secrets.
+pub fn settings_json(s: &crate::models::Settings) -> Value {
+    json!({
         "context_last_n": s.context_last_n,
-        "model": s.model.unwrap_or_default(),
-        "reasoning_effort": s.reasoning_effort.unwrap_or_default(),
-        "reasoning_summary": s.reasoning_summary.unwrap_or_default(),
+        "model": s.model.as_deref().unwrap_or_default(),
+        "reasoning_effort": s.reasoning_effort.as_deref().unwrap_or_default(),
+        "reasoning_summary": s.reasoning_summary.as_deref().unwrap_or_default(),
         "permissions_mode": s.permissions_mode.as_db_str(),
         "slack_allow_from": s.slack_allow_from,
         "slack_allow_channels": s.slack_allow_channels,
@@ -146,14 +174,50 @@ pub async fn api_settings_get(State(state): State<AppState>) -> ApiResult<Value>
         "web_allow_domains": s.web_allow_domains,
         "web_deny_domains": s.web_deny_domains,
         "github_client_id": s.github_client_id,
+        "context_sources": s.context_sources,
+        "llm_backends": s.llm_backends,
+        "response_cache_mode": s.response_cache_mode,
+        "response_cache_ttl_seconds": s.response_cache_ttl_seconds,
+        "command_env": s.command_env,
+        "slack_long_reply_mode": s.slack_long_reply_mode,
+        "slack_long_reply_chars": s.slack_long_reply_chars,
+        "reply_language": s.reply_language,
+        "task_bump_allow_from": s.task_bump_allow_from,
+        "public_status_page": s.public_status_page,
+        "slack_no_retry_after_enqueue": s.slack_no_retry_after_enqueue,
+        "context_token_budget": s.context_token_budget,
+        "content_policy_moderation": s.content_policy_moderation,
+        "content_policy_message": s.content_policy_message,
+        "approval_output_chars": s.approval_output_chars,
+        "scale_queue_depth_threshold": s.scale_queue_depth_threshold,
+        "scale_wait_seconds_threshold": s.scale_wait_seconds_threshold,
+        "scale_webhook_url": s.scale_webhook_url,
+        "reply_task_links": s.reply_task_links,
+        "usage_report_minutes_per_task": s.usage_report_minutes_per_task,
+        "speech_to_text": s.speech_to_text,
+        "slack_admin_channel": s.slack_admin_channel,
+        "slack_elevate_allow_from": s.slack_elevate_allow_from,
+        "telegram_elevate_allow_from": s.telegram_elevate_allow_from,
+    })
+}
+
+pub async fn api_settings_get(State(state): State<AppState>) -> ApiResult<Value> {
+    let s = db::get_settings(&state.pool).await?;
+    let mut out = settings_json(&s);
+    let flags = json!({
         "master_key_set": state.crypto.is_some(),
         "openai_api_key_set": crate::secrets::openai_api_key_configured(&state).await.unwrap_or(false),
+        "anthropic_api_key_set": crate::secrets::anthropic_api_key_configured(&state).await.unwrap_or(false),
         "slack_signing_secret_set": crate::secrets::slack_signing_secret_configured(&state).await.unwrap_or(false),
         "slack_bot_token_set": crate::secrets::slack_bot_token_configured(&state).await.unwrap_or(false),
         "telegram_bot_token_set": crate::secrets::telegram_bot_token_configured(&state).await.unwrap_or(false),
         "telegram_webhook_secret_set": crate::secrets::telegram_webhook_secret_configured(&state).await.unwrap_or(false),
         "brave_search_api_key_set": crate::secrets::brave_search_api_key_configured(&state).await.unwrap_or(false),
-    })))
+    });
+    if let (Some(out), Value::Object(flags)) = (out.as_object_mut(), flags) {
+        out.extend(flags);
+    }
+    Ok(Json(out))
 }
 
 #[derive(Debug, Deserialize)]
@@ -189,6 +253,30 @@ pub struct ApiSettingsPost {
     pub web_allow_domains: Option<String>,
     pub web_deny_domains: Option<String>,
     pub github_client_id: Option<String>,
+    pub context_sources: Option<String>,
+    pub llm_backends: Option<String>,
+    pub response_cache_mode: Option<String>,
+    pub response_cache_ttl_seconds: Option<i64>,
+    pub command_env: Option<String>,
+    pub slack_long_reply_mode: Option<String>,
+    pub slack_long_reply_chars: Option<i64>,
+    pub reply_language: Option<String>,
+    pub task_bump_allow_from: Option<String>,
+    pub public_status_page: Option<bool>,
+    pub slack_no_retry_after_enqueue: Option<bool>,
+    pub context_token_budget: Option<String>,
+    pub content_policy_moderation: Option<bool>,
+    pub content_policy_message: Option<String>,
+    pub approval_output_chars: Option<i64>,
+    pub scale_queue_depth_threshold: Option<i64>,
+    pub scale_wait_seconds_threshold: Option<i64>,
+    pub scale_webhook_url: Option<String>,
+    pub reply_task_links: Option<bool>,
+    pub usage_report_minutes_per_task: Option<i64>,
+    pub speech_to_text: Option<String>,
+    pub slack_admin_channel: Option<String>,
+    pub slack_elevate_allow_from: Option<String>,
+    pub telegram_elevate_allow_from: Option<String>,
 }
 
 pub async fn api_settings_post(
@@ -196,6 +284,16 @@ pub async fn api_settings_post(
     Json(form): Json<ApiSettingsPost>,
 ) -> ApiResult<Value> {
     let mut s = db::get_settings(&state.pool).await?;
+    apply_settings_form(&mut s, form)?;
+    db::update_settings(&state.pool, &s).await?;
+    Ok(Json(json!({"ok": true})))
+}
+
+/// Validate `form` and apply the fields it sets to `s`.
+pub fn apply_settings_form(
+    s: &mut crate::models::Settings,
+    form: ApiSettingsPost,
+) -> anyhow::Result<()> {
     if let Some(v) = form.context_last_n {
         s.context_last_n = v.clamp(1, 200);
     }
@@ -299,8 +397,113 @@ pub async fn api_settings_post(
     if let Some(v) = form.github_client_id {
         s.github_client_id = v.trim().chars().take(200).collect();
     }
-    db::update_settings(&state.pool, &s).await?;
-    Ok(Json(json!({"ok": true})))
+    if let Some(v) = form.context_sources {
+        let v = v.trim().to_string();
+        crate::context_sources::parse_context_sources(&v)
+            .map_err(|e| crate::errors::bad_request(format!("{e:#}")))?;
+        s.context_sources = v;
+    }
+    if let Some(v) = form.llm_backends {
+        let v = v.trim().to_string();
+        crate::llm::parse_llm_backends(&v)
+            .map_err(|e| crate::errors::bad_request(format!("{e:#}")))?;
+        s.llm_backends = v;
+    }
+    if let Some(v) = form.response_cache_mode {
+        let v = v.trim().to_string();
+        if !matches!(v.as_str(), "off" | "exact" | "semantic") {
+            return Err(crate::errors::bad_request(format!(
+                "unknown response_cache_mode: {v}"
+            )));
+        }
+        s.response_cache_mode = v;
+    }
+    if let Some(v) = form.response_cache_ttl_seconds {
+        s.response_cache_ttl_seconds = v.clamp(60, 30 * 24 * 3600);
+    }
+    if let Some(v) = form.command_env {
+        let v = v.trim().to_string();
+        crate::command_env::parse_command_env(&v)
+            .map_err(|e| crate::errors::bad_request(format!("{e:#}")))?;
+        s.command_env = v;
+    }
+    if let Some(v) = form.slack_long_reply_mode {
+        let v = v.trim().to_string();
+        if !matches!(v.as_str(), "off" | "file" | "canvas") {
+            return Err(crate::errors::bad_request(format!(
+                "unknown slack_long_reply_mode: {v}"
+            )));
+        }
+        s.slack_long_reply_mode = v;
+    }
+    if let Some(v) = form.slack_long_reply_chars {
+        s.slack_long_reply_chars = v.clamp(500, 40_000);
+    }
+    if let Some(v) = form.reply_language {
+        let v = v.trim().to_string();
+        crate::language::parse_reply_language(&v)
+            .map_err(|e| crate::errors::bad_request(format!("{e:#}")))?;
+        s.reply_language = v;
+    }
+    if let Some(v) = form.task_bump_allow_from {
+        s.task_bump_allow_from = v;
+    }
+    if let Some(v) = form.public_status_page {
+        s.public_status_page = v;
+    }
+    if let Some(v) = form.slack_no_retry_after_enqueue {
+        s.slack_no_retry_after_enqueue = v;
+    }
+    if let Some(v) = form.context_token_budget {
+        let v = v.trim().to_string();
+        crate::token_budget::parse_token_budget(&v)
+            .map_err(|e| crate::errors::bad_request(format!("{e:#}")))?;
+        s.context_token_budget = v;
+    }
+    if let Some(v) = form.content_policy_moderation {
+        s.content_policy_moderation = v;
+    }
+    if let Some(v) = form.content_policy_message {
+        s.content_policy_message = v;
+    }
+    if let Some(v) = form.approval_output_chars {
+        s.approval_output_chars = v.clamp(0, 20_000);
+    }
+    if let Some(v) = form.scale_queue_depth_threshold {
+        s.scale_queue_depth_threshold = v.clamp(0, 100_000);
+    }
+    if let Some(v) = form.scale_wait_seconds_threshold {
+        s.scale_wait_seconds_threshold = v.clamp(0, 7 * 24 * 60 * 60);
+    }
+    if let Some(v) = form.scale_webhook_url {
+        let v = v.trim().to_string();
+        if !v.is_empty() {
+            crate::scale_hint::parse_webhook_url(&v)
+                .map_err(|e| crate::errors::bad_request(format!("{e:#}")))?;
+        }
+        s.scale_webhook_url = v;
+    }
+    if let Some(v) = form.reply_task_links {
+        s.reply_task_links = v;
+    }
+    if let Some(v) = form.usage_report_minutes_per_task {
+        s.usage_report_minutes_per_task = v.clamp(0, 8 * 60);
+    }
+    if let Some(v) = form.speech_to_text {
+        let v = v.trim().to_string();
+        crate::stt::parse_config(&v).map_err(|e| crate::errors::bad_request(format!("{e:#}")))?;
+        s.speech_to_text = v;
+    }
+    if let Some(v) = form.slack_admin_channel {
+        s.slack_admin_channel = v.trim().to_string();
+    }
+    if let Some(v) = form.slack_elevate_allow_from {
+        s.slack_elevate_allow_from = v;
+    }
+    if let Some(v) = form.telegram_elevate_allow_from {
+        s.telegram_elevate_allow_from = v;
+    }
+    Ok(())
 }
 
 // ─── Secrets ───────────────────────────────────────────────────────────────
@@ -310,29 +513,46 @@ pub struct SecretValue {
     pub value: String,
 }
 
+/// The `secrets` row for an API key: a built-in secret, or `custom.NAME` for a named
+/// secret that settings such as `command_env` refer to.
+fn secret_db_key(key: &str) -> anyhow::Result<String> {
+    let db_key = match key {
+        "openai" => "openai_api_key",
+        "anthropic" => "anthropic_api_key",
+        "brave" => "brave_search_api_key",
+        "slack_signing" => "slack_signing_secret",
+        "slack_bot" => "slack_bot_token",
+        "telegram_bot" => "telegram_bot_token",
+        "telegram_webhook" => "telegram_webhook_secret",
+        _ => match key.strip_prefix("custom.") {
+            Some(name) if crate::secrets::valid_custom_secret_name(name) => {
+                return Ok(crate::secrets::custom_secret_key(name));
+            }
+            _ => {
+                return Err(crate::errors::bad_request(format!(
+                    "unknown secret key: {key}"
+                )))
+            }
+        },
+    };
+    Ok(db_key.to_string())
+}
+
 pub async fn api_set_secret(
     State(state): State<AppState>,
     Path(key): Path<String>,
     Json(body): Json<SecretValue>,
 ) -> ApiResult<Value> {
     let Some(crypto) = state.crypto.as_deref() else {
-        return Err(anyhow::anyhow!("GRAIL_MASTER_KEY is required to store secrets").into());
+        return Err(crate::errors::config("GRAIL_MASTER_KEY is required to store secrets").into());
     };
     let v = body.value.trim().to_string();
     if v.is_empty() {
-        return Err(anyhow::anyhow!("value is empty").into());
+        return Err(crate::errors::bad_request("value is empty").into());
     }
-    let db_key = match key.as_str() {
-        "openai" => "openai_api_key",
-        "brave" => "brave_search_api_key",
-        "slack_signing" => "slack_signing_secret",
-        "slack_bot" => "slack_bot_token",
-        "telegram_bot" => "telegram_bot_token",
-        "telegram_webhook" => "telegram_webhook_secret",
-        _ => return Err(anyhow::anyhow!("unknown secret key: {key}").into()),
-    };
+    let db_key = secret_db_key(&key)?;
     let (nonce, ciphertext) = crypto.encrypt(db_key.as_bytes(), v.as_bytes())?;
-    db::upsert_secret(&state.pool, db_key, &nonce, &ciphertext).await?;
+    db::upsert_secret(&state.pool, &db_key, &nonce, &ciphertext).await?;
     Ok(Json(json!({"ok": true})))
 }
 
@@ -340,16 +560,8 @@ pub async fn api_delete_secret(
     State(state): State<AppState>,
     Path(key): Path<String>,
 ) -> ApiResult<Value> {
-    let db_key = match key.as_str() {
-        "openai" => "openai_api_key",
-        "brave" => "brave_search_api_key",
-        "slack_signing" => "slack_signing_secret",
-        "slack_bot" => "slack_bot_token",
-        "telegram_bot" => "telegram_bot_token",
-        "telegram_webhook" => "telegram_webhook_secret",
-        _ => return Err(anyhow::anyhow!("unknown secret key: {key}").into()),
-    };
-    db::delete_secret(&state.pool, db_key).await?;
+    let db_key = secret_db_key(&key)?;
+    db::delete_secret(&state.pool, &db_key).await?;
     Ok(Json(json!({"ok": true})))
 }
 
@@ -373,6 +585,9 @@ pub async fn api_tasks(State(state): State<AppState>) -> ApiResult<Value> {
                 "created_at": format!("{}", t.created_at),
                 "started_at": t.started_at.map_or_else(|| String::new(), |ts| format!("{ts}")),
                 "finished_at": t.finished_at.map_or_else(|| String::new(), |ts| format!("{ts}")),
+                "depends_on_task_id": t.depends_on_task_id,
+                "is_synthetic": t.is_synthetic,
+                "rerun_of_task_id": t.rerun_of_task_id,
             })
         })
         .collect();
@@ -385,7 +600,7 @@ pub async fn api_task_details(
 ) -> ApiResult<Value> {
     let task = db::get_task(&state.pool, id)
         .await?
-        .ok_or_else(|| anyhow::anyhow!("task not found"))?;
+        .ok_or_else(|| crate::errors::not_found("task not found"))?;
     let traces = db::list_task_traces(&state.pool, id, 1000).await?;
     let trace_rows: Vec<Value> = traces
         .into_iter()
@@ -401,6 +616,10 @@ pub async fn api_task_details(
         })
         .collect();
 
+    let options = crate::models::TaskOptions::parse(&task.options_json);
+    let output = crate::output_contract::parsed_output(&options, task.result_text.as_deref());
+    let links = crate::task_links::links_json(&state, &task).await;
+    let reruns = db::list_task_reruns(&state.pool, id).await?;
     let task_value = json!({
         "id": task.id,
         "status": task.status,
@@ -419,13 +638,219 @@ pub async fn api_task_details(
         "created_at": format!("{}", task.created_at),
         "started_at": task.started_at.map_or_else(|| String::new(), |ts| format!("{ts}")),
         "finished_at": task.finished_at.map_or_else(|| String::new(), |ts| format!("{ts}")),
+        "depends_on_task_id": task.depends_on_task_id,
+        "on_dependency_failure": task.on_dependency_failure,
+        "is_synthetic": task.is_synthetic,
+        "output_schema": options.output_schema,
+        "output": output,
+        "links": links,
+        "rerun_of_task_id": task.rerun_of_task_id,
+        "reruns": reruns,
     });
+    let artifacts: Vec<Value> = db::list_task_artifacts(&state.pool, id)
+        .await?
+        .into_iter()
+        .map(|a| {
+            json!({
+                "id": a.id,
+                "kind": a.kind,
+                "name": a.name,
+                "approval_id": a.approval_id,
+                "chars": a.content.chars().count(),
+                "created_at": format!("{}", a.created_at),
+            })
+        })
+        .collect();
     Ok(Json(json!({
         "task": task_value,
         "traces": trace_rows,
+        "artifacts": artifacts,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaskResolveQuery {
+    pub link: String,
+}
+
+/// Resolve a `grail://task/<id>` link, dashboard URL or `#<id>` to a task.
+pub async fn api_task_resolve(
+    State(state): State<AppState>,
+    axum::extract::Query(q): axum::extract::Query<TaskResolveQuery>,
+) -> ApiResult<Value> {
+    let id = crate::task_links::parse(&q.link)
+        .ok_or_else(|| crate::errors::bad_request("not a task link"))?;
+    let task = db::get_task(&state.pool, id)
+        .await?
+        .ok_or_else(|| crate::errors::not_found("task not found"))?;
+    Ok(Json(json!({
+        "id": task.id,
+        "status": task.status,
+        "links": crate::task_links::links_json(&state, &task).await,
     })))
 }
 
+pub async fn api_task_artifact(
+    State(state): State<AppState>,
+    axum::extract::Path((id, artifact_id)): axum::extract::Path<(i64, String)>,
+) -> Result<axum::response::Response, crate::AppError> {
+    use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+    use axum::response::IntoResponse;
+
+    let artifact = db::get_task_artifact(&state.pool, id, &artifact_id)
+        .await?
+        .ok_or_else(|| crate::errors::not_found("artifact not found"))?;
+    let filename = artifact.name.replace(['"', '/', '\\'], "_");
+    Ok((
+        [
+            (CONTENT_TYPE, "text/plain; charset=utf-8".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        artifact.content,
+    )
+        .into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TaskAddBody {
+    pub channel_id: String,
+    pub thread_ts: Option<String>,
+    pub prompt_text: String,
+    pub depends_on_task_id: Option<i64>,
+    pub on_dependency_failure: Option<String>,
+    /// JSON Schema the answer must conform to; see `output_contract`.
+    pub output_schema: Option<Value>,
+}
+
+/// `options_json` for an API task with an optional output contract.
+fn api_task_options(output_schema: Option<Value>) -> anyhow::Result<String> {
+    let Some(schema) = output_schema.filter(|s| !s.is_null()) else {
+        return Ok(String::new());
+    };
+    crate::output_contract::validate_schema(&schema)
+        .map_err(|e| crate::errors::bad_request(format!("{e:#}")))?;
+    let options = crate::models::TaskOptions {
+        output_schema: Some(schema),
+        ..Default::default()
+    };
+    serde_json::to_string(&options).context("serialize task options")
+}
+
+pub async fn api_task_add(
+    State(state): State<AppState>,
+    Json(form): Json<TaskAddBody>,
+) -> ApiResult<Value> {
+    let settings = db::get_settings(&state.pool).await?;
+    let workspace_id = settings
+        .workspace_id
+        .as_deref()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| crate::errors::config("workspace_id not set"))?;
+    let prompt_text = form.prompt_text.trim();
+    if prompt_text.is_empty() {
+        return Err(crate::errors::bad_request("prompt_text is empty").into());
+    }
+    let options_json = api_task_options(form.output_schema)?;
+    if let Some(dep) = form.depends_on_task_id {
+        db::get_task_status(&state.pool, dep)
+            .await?
+            .ok_or_else(|| crate::errors::not_found(format!("dependency task #{dep} not found")))?;
+    }
+    let now = chrono::Utc::now();
+    let event_ts = format!("{}.{:06}", now.timestamp(), now.timestamp_subsec_micros());
+    let task_id = db::enqueue_task(
+        &state.pool,
+        &db::NewTask {
+            provider: "slack",
+            workspace_id: &workspace_id,
+            channel_id: form.channel_id.trim(),
+            thread_ts: form.thread_ts.as_deref().unwrap_or("").trim(),
+            event_ts: &event_ts,
+            requested_by_user_id: "admin",
+            prompt_text,
+            depends_on_task_id: form.depends_on_task_id,
+            on_dependency_failure: form
+                .on_dependency_failure
+                .as_deref()
+                .unwrap_or("fail")
+                .trim(),
+            options_json: &options_json,
+            ..Default::default()
+        },
+    )
+    .await?;
+    state.task_notify.notify_waiters();
+    Ok(Json(json!({"ok": true, "task_id": task_id})))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TestPromptBody {
+    pub provider: Option<String>,
+    pub channel_id: String,
+    pub user_id: String,
+    pub thread_ts: Option<String>,
+    pub prompt_text: String,
+    pub output_schema: Option<Value>,
+}
+
+/// Run a prompt through the full pipeline as if it came from the given channel/user.
+/// The task is marked synthetic: results stay in the admin UI and nothing is posted.
+pub async fn api_task_test(
+    State(state): State<AppState>,
+    Json(form): Json<TestPromptBody>,
+) -> ApiResult<Value> {
+    let provider = form
+        .provider
+        .as_deref()
+        .unwrap_or("slack")
+        .trim()
+        .to_ascii_lowercase();
+    if !matches!(
+        provider.as_str(),
+        "slack" | "telegram" | "whatsapp" | "discord" | "msteams"
+    ) {
+        return Err(crate::errors::bad_request(format!("unknown provider: {provider}")).into());
+    }
+    let channel_id = form.channel_id.trim();
+    let user_id = form.user_id.trim();
+    let prompt_text = form.prompt_text.trim();
+    if channel_id.is_empty() || user_id.is_empty() || prompt_text.is_empty() {
+        return Err(
+            crate::errors::bad_request("channel_id, user_id and prompt_text are required").into(),
+        );
+    }
+    let options_json = api_task_options(form.output_schema)?;
+    let workspace_id = if provider == "slack" {
+        db::get_settings(&state.pool)
+            .await?
+            .workspace_id
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    } else {
+        provider.clone()
+    };
+    let task_id = db::enqueue_synthetic_task(
+        &state.pool,
+        &db::NewTask {
+            provider: &provider,
+            workspace_id: &workspace_id,
+            channel_id,
+            thread_ts: form.thread_ts.as_deref().unwrap_or("").trim(),
+            requested_by_user_id: user_id,
+            prompt_text,
+            options_json: &options_json,
+            ..Default::default()
+        },
+    )
+    .await?;
+    state.task_notify.notify_waiters();
+    Ok(Json(json!({"ok": true, "task_id": task_id})))
+}
+
 pub async fn api_task_cancel(
     State(state): State<AppState>,
     Path(id): Path<i64>,
@@ -442,6 +867,103 @@ pub async fn api_task_retry(
     Ok(Json(json!({"ok": true})))
 }
 
+/// Most tasks one bulk re-run may copy.
+const MAX_RERUN_TASKS: usize = 100;
+/// Longest timeout a re-run may be given.
+const MAX_RERUN_TIMEOUT_SECONDS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Deserialize)]
+pub struct TaskRerunBody {
+    pub task_ids: Vec<i64>,
+    /// Replaces the model for the copies; still checked against `allowed_models`.
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub read_only: bool,
+    #[serde(default)]
+    pub timeout_seconds: Option<i64>,
+}
+
+/// Re-run failed or cancelled tasks as new tasks with altered options. Each copy records the
+/// task it re-runs; tasks that are missing or didn't fail are reported as skipped.
+pub async fn api_tasks_rerun(
+    State(state): State<AppState>,
+    Json(body): Json<TaskRerunBody>,
+) -> ApiResult<Value> {
+    let mut ids = body.task_ids;
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.is_empty() {
+        return Err(crate::errors::bad_request("task_ids is empty").into());
+    }
+    if ids.len() > MAX_RERUN_TASKS {
+        return Err(crate::errors::bad_request(format!(
+            "at most {MAX_RERUN_TASKS} tasks can be re-run at once"
+        ))
+        .into());
+    }
+    if let Some(t) = body.timeout_seconds {
+        if !(60..=MAX_RERUN_TIMEOUT_SECONDS).contains(&t) {
+            return Err(crate::errors::bad_request(format!(
+                "timeout_seconds must be between 60 and {MAX_RERUN_TIMEOUT_SECONDS}"
+            ))
+            .into());
+        }
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let mut rerun = Vec::new();
+    let mut skipped = Vec::new();
+    for id in ids {
+        let Some(task) = db::get_task(&state.pool, id).await? else {
+            skipped.push(id);
+            continue;
+        };
+        let options = crate::models::TaskOptions::parse(&task.options_json).for_rerun(
+            body.model.as_deref(),
+            body.read_only,
+            body.timeout_seconds,
+            now,
+        );
+        let options_json = serde_json::to_string(&options).context("serialize task options")?;
+        let Some(new_id) = db::enqueue_rerun_task(&state.pool, id, &options_json).await? else {
+            skipped.push(id);
+            continue;
+        };
+        let details = json!({
+            "rerun_of_task_id": id,
+            "rerun_task_id": new_id,
+            "options": options,
+        })
+        .to_string();
+        let _ = db::create_task_trace(
+            &state.pool,
+            id,
+            "task.rerun",
+            "info",
+            &format!("Re-run as #{new_id}"),
+            &details,
+        )
+        .await;
+        let _ = db::create_task_trace(
+            &state.pool,
+            new_id,
+            "task.rerun",
+            "info",
+            &format!("Re-run of #{id}"),
+            &details,
+        )
+        .await;
+        rerun.push(json!({ "from": id, "id": new_id }));
+    }
+    if !rerun.is_empty() {
+        state.task_notify.notify_waiters();
+    }
+    Ok(Json(
+        json!({ "ok": true, "tasks": rerun, "skipped": skipped }),
+    ))
+}
+
 // ─── Memory ────────────────────────────────────────────────────────────────
 
 pub async fn api_memory(State(state): State<AppState>) -> ApiResult<Value> {
@@ -537,7 +1059,7 @@ pub async fn api_context_file_post(
 ) -> ApiResult<Value> {
     let path = body.path.trim().to_string();
     if path.is_empty() {
-        return Err(anyhow::anyhow!("path is empty").into());
+        return Err(crate::errors::bad_request("path is empty").into());
     }
     let context_dir = state.config.data_dir.join("context");
     let context_dir = tokio::fs::canonicalize(&context_dir)
@@ -614,7 +1136,7 @@ pub async fn api_cron_add(
         .as_deref()
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty())
-        .ok_or_else(|| anyhow::anyhow!("workspace_id not set"))?;
+        .ok_or_else(|| crate::errors::config("workspace_id not set"))?;
     let now = chrono::Utc::now().timestamp();
     let mut job = crate::models::CronJob {
         id: crate::random_id("cron"),
@@ -632,6 +1154,7 @@ pub async fn api_cron_add(
         channel_id: form.channel_id.trim().to_string(),
         thread_ts: form.thread_ts.unwrap_or_default().trim().to_string(),
         prompt_text: form.prompt_text.trim().to_string(),
+        created_by_user_id: None,
         next_run_at: None,
         last_run_at: None,
         last_status: None,
@@ -657,7 +1180,11 @@ pub async fn api_cron_add(
                     .timestamp(),
             )
         }
-        other => return Err(anyhow::anyhow!("unknown schedule_kind: {other}").into()),
+        other => {
+            return Err(
+                crate::errors::bad_request(format!("unknown schedule_kind: {other}")).into(),
+            )
+        }
     };
     db::insert_cron_job(&state.pool, &job).await?;
     Ok(Json(json!({"ok": true})))
@@ -732,7 +1259,8 @@ pub async fn api_guardrails_add(
         created_at: now,
         updated_at: now,
     };
-    crate::guardrails::validate_rule(&rule)?;
+    crate::guardrails::validate_rule(&rule)
+        .map_err(|e| crate::errors::bad_request(format!("{e:#}")))?;
     db::insert_guardrail_rule(&state.pool, &rule).await?;
     Ok(Json(json!({"ok": true})))
 }
@@ -761,6 +1289,396 @@ pub async fn api_guardrails_disable(
     Ok(Json(json!({"ok": true})))
 }
 
+// ─── Guardrail canary ──────────────────────────────────────────────────────
+
+pub async fn api_guardrail_canary_get(State(state): State<AppState>) -> ApiResult<Value> {
+    use crate::guardrail_canary::{as_bundle, load};
+
+    let decisions: Vec<Value> = db::list_guardrail_canary_decisions(&state.pool, 200)
+        .await?
+        .into_iter()
+        .map(|d| {
+            json!({
+                "id": d.id,
+                "task_id": d.task_id,
+                "channel_id": d.channel_id,
+                "kind": d.kind,
+                "subject": d.subject,
+                "applied": d.applied,
+                "active_decision": d.active_decision,
+                "active_rule_id": d.active_rule_id,
+                "canary_decision": d.canary_decision,
+                "canary_rule_id": d.canary_rule_id,
+                "created_at": format!("{}", d.created_at),
+            })
+        })
+        .collect();
+    let Some(canary) = load(&state).await? else {
+        return Ok(Json(json!({"canary": null, "decisions": decisions})));
+    };
+    let plan = crate::config_bundle::plan(&state, &as_bundle(&canary.specs)).await?;
+    Ok(Json(json!({
+        "canary": {
+            "channels": canary.channels,
+            "rules": canary.specs,
+            "started_at": format!("{}", canary.started_at),
+            "ends_at": format!("{}", canary.ends_at),
+            "live": canary.is_live(chrono::Utc::now().timestamp()),
+            // What promotion would change in the active rules.
+            "changes": plan.changes,
+            "errors": plan.errors,
+        },
+        "decisions": decisions,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GuardrailCanaryBody {
+    /// Comma-separated channel ids.
+    pub channels: String,
+    pub hours: Option<i64>,
+    pub rules: Option<Vec<crate::config_bundle::GuardrailRuleSpec>>,
+    /// A YAML config bundle; its `guardrail_rules` are used when `rules` is absent.
+    pub bundle: Option<String>,
+}
+
+pub async fn api_guardrail_canary_start(
+    State(state): State<AppState>,
+    Json(body): Json<GuardrailCanaryBody>,
+) -> ApiResult<Value> {
+    use crate::guardrail_canary::{as_bundle, parse_channels, MAX_HOURS};
+
+    let channels = parse_channels(&body.channels);
+    if channels.is_empty() {
+        return Err(crate::errors::bad_request("at least one canary channel is required").into());
+    }
+    let specs = match (body.rules, body.bundle) {
+        (Some(rules), _) => rules,
+        (None, Some(yaml)) => parse_config_bundle(&yaml)?
+            .guardrail_rules
+            .ok_or_else(|| crate::errors::bad_request("bundle has no guardrail_rules section"))?,
+        (None, None) => {
+            return Err(crate::errors::bad_request("rules or bundle is required").into());
+        }
+    };
+    let plan = crate::config_bundle::plan(&state, &as_bundle(&specs)).await?;
+    if !plan.errors.is_empty() {
+        return Err(crate::errors::bad_request(format!(
+            "canary rules are invalid: {}",
+            plan.errors.join("; ")
+        ))
+        .into());
+    }
+    let now = chrono::Utc::now().timestamp();
+    let hours = body.hours.unwrap_or(72).clamp(1, MAX_HOURS);
+    db::set_guardrail_canary(
+        &state.pool,
+        &crate::models::GuardrailCanary {
+            channels: channels.join(","),
+            rules_json: serde_json::to_string(&specs).context("serialize canary rules")?,
+            started_at: now,
+            ends_at: now + hours * 3600,
+        },
+    )
+    .await?;
+    Ok(Json(json!({"ok": true, "changes": plan.changes})))
+}
+
+/// Replace the active guardrail rules with the canary's and end the canary.
+pub async fn api_guardrail_canary_promote(State(state): State<AppState>) -> ApiResult<Value> {
+    let canary = crate::guardrail_canary::load(&state)
+        .await?
+        .ok_or_else(|| crate::errors::not_found("no guardrail canary"))?;
+    let plan =
+        crate::config_bundle::import(&state, &crate::guardrail_canary::as_bundle(&canary.specs))
+            .await?;
+    db::delete_guardrail_canary(&state.pool).await?;
+    Ok(Json(json!({"ok": true, "changes": plan.changes})))
+}
+
+pub async fn api_guardrail_canary_discard(State(state): State<AppState>) -> ApiResult<Value> {
+    let deleted = db::delete_guardrail_canary(&state.pool).await?;
+    Ok(Json(json!({"ok": deleted})))
+}
+
+// ─── Elevated sessions ─────────────────────────────────────────────────────
+
+pub async fn api_elevations_list(State(state): State<AppState>) -> ApiResult<Value> {
+    let now = chrono::Utc::now().timestamp();
+    let sessions: Vec<Value> = db::list_elevated_sessions(&state.pool, 100)
+        .await?
+        .into_iter()
+        .map(|s| {
+            json!({
+                "id": s.id,
+                "provider": s.provider,
+                "workspace_id": s.workspace_id,
+                "channel_id": s.channel_id,
+                "granted_by": s.granted_by,
+                "reason": s.reason,
+                "started_at": format!("{}", s.started_at),
+                "ends_at": format!("{}", s.ends_at),
+                "revoked_at": s.revoked_at.map(|t| format!("{t}")),
+                "revoked_by": s.revoked_by,
+                "active": s.is_active(now),
+            })
+        })
+        .collect();
+    Ok(Json(json!({
+        "sessions": sessions,
+        "max_hours": crate::elevation::MAX_HOURS,
+    })))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ElevationBody {
+    /// slack | telegram
+    pub provider: String,
+    /// Defaults to the pinned Slack workspace, or `telegram` for Telegram chats.
+    pub workspace_id: Option<String>,
+    pub channel_id: String,
+    pub minutes: Option<i64>,
+    #[serde(default)]
+    pub reason: String,
+}
+
+pub async fn api_elevations_add(
+    State(state): State<AppState>,
+    Json(body): Json<ElevationBody>,
+) -> ApiResult<Value> {
+    let provider = body.provider.trim().to_ascii_lowercase();
+    let channel_id = body.channel_id.trim();
+    if channel_id.is_empty() {
+        return Err(crate::errors::bad_request("channel_id is required").into());
+    }
+    let workspace_id = match provider.as_str() {
+        "telegram" => "telegram".to_string(),
+        "slack" => match body
+            .workspace_id
+            .as_deref()
+            .map(str::trim)
+            .filter(|w| !w.is_empty())
+        {
+            Some(w) => w.to_string(),
+            None => db::get_settings(&state.pool)
+                .await?
+                .workspace_id
+                .filter(|w| !w.trim().is_empty())
+                .ok_or_else(|| {
+                    crate::errors::bad_request(
+                        "workspace_id is required when no Slack workspace is pinned",
+                    )
+                })?,
+        },
+        _ => return Err(crate::errors::bad_request("provider must be slack or telegram").into()),
+    };
+    let session = crate::elevation::grant(
+        &state,
+        &provider,
+        &workspace_id,
+        channel_id,
+        "admin",
+        body.minutes.unwrap_or(crate::elevation::DEFAULT_MINUTES),
+        &grail_text::truncate_line(&body.reason, 200),
+    )
+    .await?;
+    Ok(Json(json!({"ok": true, "id": session.id})))
+}
+
+pub async fn api_elevations_revoke(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Value> {
+    let revoked = crate::elevation::revoke(&state, &id, "admin").await?;
+    Ok(Json(json!({"ok": revoked.is_some()})))
+}
+
+// ─── Configuration bundles ─────────────────────────────────────────────────
+
+pub async fn api_config_export(
+    State(state): State<AppState>,
+) -> Result<axum::response::Response, crate::AppError> {
+    use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+    use axum::response::IntoResponse;
+
+    let bundle = crate::config_bundle::export(&state).await?;
+    let filename = format!(
+        "fastclaw-config-{}.yaml",
+        chrono::Utc::now().format("%Y%m%d")
+    );
+    Ok((
+        [
+            (CONTENT_TYPE, "application/yaml; charset=utf-8".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        crate::config_bundle::to_yaml(&bundle)?,
+    )
+        .into_response())
+}
+
+fn parse_config_bundle(body: &str) -> anyhow::Result<crate::config_bundle::ConfigBundle> {
+    crate::config_bundle::parse(body).map_err(|e| crate::errors::bad_request(format!("{e:#}")))
+}
+
+/// Body: the YAML bundle. Returns what importing it would change, without changing it.
+pub async fn api_config_import_preview(
+    State(state): State<AppState>,
+    body: String,
+) -> ApiResult<Value> {
+    let bundle = parse_config_bundle(&body)?;
+    let plan = crate::config_bundle::plan(&state, &bundle).await?;
+    Ok(Json(json!(plan)))
+}
+
+pub async fn api_config_import(State(state): State<AppState>, body: String) -> ApiResult<Value> {
+    let bundle = parse_config_bundle(&body)?;
+    let plan = crate::config_bundle::import(&state, &bundle).await?;
+    Ok(Json(
+        json!({"ok": true, "changes": plan.changes, "ignored": plan.ignored}),
+    ))
+}
+
+// ─── Analytics export ──────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsExportQuery {
+    pub days: Option<i64>,
+    /// `csv` (default) or `json`.
+    pub format: Option<String>,
+    /// Strip prompt/result text and hash ids for sharing.
+    pub anonymize: Option<bool>,
+}
+
+pub async fn api_analytics_export(
+    State(state): State<AppState>,
+    Query(q): Query<AnalyticsExportQuery>,
+) -> Result<axum::response::Response, crate::AppError> {
+    use crate::analytics_export::{build_rows, to_csv, DEFAULT_DAYS, MAX_DAYS, MAX_ROWS};
+    use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+    use axum::response::IntoResponse;
+
+    let days = q.days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+    let anonymize = q.anonymize.unwrap_or(false);
+    let format = q
+        .format
+        .as_deref()
+        .unwrap_or("csv")
+        .trim()
+        .to_ascii_lowercase();
+    if format != "csv" && format != "json" {
+        return Err(crate::errors::bad_request("format must be csv or json").into());
+    }
+    let now = chrono::Utc::now();
+    let tasks =
+        db::list_tasks_created_since(&state.pool, now.timestamp() - days * 86_400, MAX_ROWS)
+            .await?;
+    let rows = build_rows(&tasks, anonymize, &crate::random_id("salt"));
+    let filename = format!(
+        "fastclaw-usage-{}{}.{format}",
+        now.format("%Y%m%d"),
+        if anonymize { "-anonymized" } else { "" }
+    );
+    let (content_type, body) = if format == "csv" {
+        ("text/csv; charset=utf-8", to_csv(&rows, anonymize))
+    } else {
+        let doc = json!({
+            "generated_at": now.timestamp(),
+            "days": days,
+            "anonymized": anonymize,
+            "rows": rows,
+        });
+        ("application/json", doc.to_string())
+    };
+    Ok((
+        [
+            (CONTENT_TYPE, content_type.to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+// ─── Saved prompts ─────────────────────────────────────────────────────────
+
+pub async fn api_saved_prompts_list(State(state): State<AppState>) -> ApiResult<Value> {
+    let prompts = db::list_saved_prompts(&state.pool).await?;
+    let rows: Vec<Value> = prompts
+        .into_iter()
+        .map(|p| {
+            json!({
+                "id": p.id, "name": p.name, "description": p.description,
+                "template": p.template, "enabled": p.enabled,
+                "updated_at": format!("{}", p.updated_at),
+            })
+        })
+        .collect();
+    Ok(Json(json!({"prompts": rows})))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SavedPromptAddBody {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub template: String,
+}
+
+/// Adds a prompt, or replaces the one with the same name.
+pub async fn api_saved_prompts_add(
+    State(state): State<AppState>,
+    Json(form): Json<SavedPromptAddBody>,
+) -> ApiResult<Value> {
+    let name = crate::saved_prompts::validate_name(&form.name)
+        .map_err(|e| crate::errors::bad_request(format!("{e:#}")))?;
+    let template = form.template.trim();
+    if template.is_empty() {
+        return Err(crate::errors::bad_request("template is required").into());
+    }
+    let now = chrono::Utc::now().timestamp();
+    let prompt = crate::models::SavedPrompt {
+        id: crate::random_id("sp"),
+        name,
+        description: form.description.trim().to_string(),
+        template: template.to_string(),
+        enabled: true,
+        created_at: now,
+        updated_at: now,
+    };
+    db::upsert_saved_prompt(&state.pool, &prompt).await?;
+    Ok(Json(json!({"ok": true})))
+}
+
+pub async fn api_saved_prompts_delete(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Value> {
+    db::delete_saved_prompt(&state.pool, &id).await?;
+    Ok(Json(json!({"ok": true})))
+}
+
+pub async fn api_saved_prompts_enable(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Value> {
+    db::set_saved_prompt_enabled(&state.pool, &id, true).await?;
+    Ok(Json(json!({"ok": true})))
+}
+
+pub async fn api_saved_prompts_disable(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Value> {
+    db::set_saved_prompt_enabled(&state.pool, &id, false).await?;
+    Ok(Json(json!({"ok": true})))
+}
+
 // ─── Approvals ─────────────────────────────────────────────────────────────
 
 pub async fn api_approvals_list(State(state): State<AppState>) -> ApiResult<Value> {
@@ -779,6 +1697,22 @@ pub async fn api_approvals_list(State(state): State<AppState>) -> ApiResult<Valu
     Ok(Json(json!({"approvals": rows})))
 }
 
+pub async fn api_approvals_insights(State(state): State<AppState>) -> ApiResult<Value> {
+    let now = chrono::Utc::now().timestamp();
+    let since = now - crate::approval_insights::WINDOW_DAYS * 24 * 60 * 60;
+    let approvals = db::list_command_approvals_since(&state.pool, since, 5_000).await?;
+    let rules = db::list_guardrail_rules(&state.pool, Some("command"), 500).await?;
+    let matches = db::list_guardrail_rule_matches(&state.pool)
+        .await?
+        .into_iter()
+        .map(|(id, count, last)| (id, (count, last)))
+        .collect();
+    let tracking_since = db::guardrail_match_tracking_since(&state.pool).await?;
+    let insights =
+        crate::approval_insights::analyze(&approvals, &rules, &matches, tracking_since, now);
+    Ok(Json(json!(insights)))
+}
+
 pub async fn api_approval_approve(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -795,6 +1729,21 @@ pub async fn api_approval_always(
     Ok(Json(json!({"ok": true})))
 }
 
+pub async fn api_approval_always_scope(
+    State(state): State<AppState>,
+    Path((id, scope)): Path<(String, String)>,
+) -> ApiResult<Value> {
+    let action = match scope.as_str() {
+        "program" => "always_program",
+        "directory" => "always_directory",
+        _ => {
+            return Err(crate::errors::bad_request(format!("unknown always scope: {scope}")).into())
+        }
+    };
+    crate::approvals::handle_approval_command(&state, action, &id).await?;
+    Ok(Json(json!({"ok": true})))
+}
+
 pub async fn api_approval_deny(
     State(state): State<AppState>,
     Path(id): Path<String>,
@@ -0,0 +1,352 @@
+//! Approval analytics: turn the command approval log into guardrail suggestions.
+//!
+//! Commands that admins keep approving (and never deny) become allow-rule suggestions,
+//! commands they keep denying become deny-rule suggestions, and command rules that have
+//! not decided anything within the window are flagged as stale.
+//!
+//! Related commands are grouped by their "always" program scope, which is pinned to the
+//! subcommand (`cargo test`, never all of `cargo`) and not offered for interpreters or
+//! destructive programs. Rule matches are only counted since match tracking was added, so
+//! no rule is called stale until tracking has covered the whole window.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::guardrails::{decision_from_action, rule_matches, suggest_always_scopes, Decision};
+use crate::models::{Approval, GuardrailRule};
+
+pub const WINDOW_DAYS: i64 = 90;
+/// Approvals (with no denials) before an allow rule is suggested.
+const MIN_APPROVALS: usize = 5;
+/// Denials (with no approvals) before a deny rule is suggested.
+const MIN_DENIALS: usize = 3;
+const MAX_SUGGESTIONS: usize = 20;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    pub total: usize,
+    pub approved: usize,
+    pub denied: usize,
+    pub expired: usize,
+    pub pending: usize,
+    /// Median seconds from request to an admin decision.
+    pub median_wait_seconds: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleSuggestion {
+    /// `allow` or `deny`.
+    pub action: String,
+    pub pattern_kind: String,
+    pub pattern: String,
+    pub name: String,
+    pub reason: String,
+    pub approved: usize,
+    pub denied: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StaleRule {
+    pub id: String,
+    pub name: String,
+    pub action: String,
+    pub pattern: String,
+    pub match_count: i64,
+    pub last_matched_at: Option<i64>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Insights {
+    pub window_days: i64,
+    pub summary: Summary,
+    pub suggestions: Vec<RuleSuggestion>,
+    pub stale_rules: Vec<StaleRule>,
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    label: String,
+    approved: usize,
+    denied: usize,
+    commands: BTreeSet<String>,
+}
+
+fn is_approved(a: &Approval) -> bool {
+    a.status == "approved"
+}
+
+fn is_denied(a: &Approval) -> bool {
+    a.status == "denied"
+}
+
+/// True when the first enabled command rule matching `command` already allows it.
+fn already_allowed(rules: &[GuardrailRule], command: &str) -> bool {
+    rules
+        .iter()
+        .filter(|r| r.enabled && r.kind == "command")
+        .find(|r| rule_matches(r, command).unwrap_or(false))
+        .is_some_and(|r| decision_from_action(&r.action) == Decision::Allow)
+}
+
+fn short(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        let mut out: String = s.chars().take(max.saturating_sub(1)).collect();
+        out.push('…');
+        out
+    }
+}
+
+/// `approvals` are command approvals from the window; `matches` maps rule id to
+/// `(match_count, last_matched_at)`, counted since `tracking_since`.
+pub fn analyze(
+    approvals: &[Approval],
+    rules: &[GuardrailRule],
+    matches: &HashMap<String, (i64, Option<i64>)>,
+    tracking_since: Option<i64>,
+    now: i64,
+) -> Insights {
+    let since = now - WINDOW_DAYS * 24 * 60 * 60;
+
+    let mut summary = Summary {
+        total: approvals.len(),
+        ..Default::default()
+    };
+    let mut waits = Vec::new();
+    // Keyed by pattern so output order is stable.
+    let mut exact: BTreeMap<String, Group> = BTreeMap::new();
+    let mut programs: BTreeMap<String, Group> = BTreeMap::new();
+    for a in approvals {
+        match a.status.as_str() {
+            "approved" => summary.approved += 1,
+            "denied" => summary.denied += 1,
+            "expired" => summary.expired += 1,
+            _ => summary.pending += 1,
+        }
+        if is_approved(a) || is_denied(a) {
+            if let Some(resolved) = a.resolved_at {
+                waits.push((resolved - a.created_at).max(0));
+            }
+        }
+        let details: serde_json::Value = serde_json::from_str(&a.details_json).unwrap_or_default();
+        let command = details
+            .get("command")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .trim()
+            .to_string();
+        if command.is_empty() || !(is_approved(a) || is_denied(a)) {
+            continue;
+        }
+        let mut groups = vec![exact.entry(command.clone()).or_insert_with(|| Group {
+            label: command.clone(),
+            ..Default::default()
+        })];
+        if let Some(scope) = suggest_always_scopes(&command)
+            .into_iter()
+            .find(|s| s.scope == "program")
+        {
+            groups.push(programs.entry(scope.pattern).or_insert_with(|| Group {
+                label: scope.label.replace('`', ""),
+                ..Default::default()
+            }));
+        }
+        for g in groups {
+            g.commands.insert(command.clone());
+            if is_approved(a) {
+                g.approved += 1;
+            } else {
+                g.denied += 1;
+            }
+        }
+    }
+    waits.sort_unstable();
+    summary.median_wait_seconds = waits.get(waits.len() / 2).copied();
+
+    let mut suggestions = Vec::new();
+    let mut covered: BTreeSet<String> = BTreeSet::new();
+    // Broader program-level suggestions first; they cover their exact commands.
+    for (pattern, g) in &programs {
+        if g.approved < MIN_APPROVALS || g.denied > 0 || g.commands.len() < 2 {
+            continue;
+        }
+        if g.commands.iter().all(|c| already_allowed(rules, c)) {
+            continue;
+        }
+        covered.extend(g.commands.iter().cloned());
+        suggestions.push(RuleSuggestion {
+            action: "allow".to_string(),
+            pattern_kind: "regex".to_string(),
+            pattern: pattern.clone(),
+            name: format!("suggested: {}", short(&g.label, 48)),
+            reason: format!(
+                "{} approved {} times across {} commands, never denied",
+                g.label,
+                g.approved,
+                g.commands.len()
+            ),
+            approved: g.approved,
+            denied: 0,
+        });
+    }
+    for (command, g) in &exact {
+        if covered.contains(command) {
+            continue;
+        }
+        if g.approved >= MIN_APPROVALS && g.denied == 0 && !already_allowed(rules, command) {
+            suggestions.push(RuleSuggestion {
+                action: "allow".to_string(),
+                pattern_kind: "exact".to_string(),
+                pattern: command.clone(),
+                name: format!("suggested: {}", short(command, 48)),
+                reason: format!(
+                    "`{}` approved {} times, never denied",
+                    short(command, 80),
+                    g.approved
+                ),
+                approved: g.approved,
+                denied: 0,
+            });
+        } else if g.denied >= MIN_DENIALS && g.approved == 0 {
+            suggestions.push(RuleSuggestion {
+                action: "deny".to_string(),
+                pattern_kind: "exact".to_string(),
+                pattern: command.clone(),
+                name: format!("suggested deny: {}", short(command, 43)),
+                reason: format!(
+                    "`{}` denied {} times, never approved",
+                    short(command, 80),
+                    g.denied
+                ),
+                approved: 0,
+                denied: g.denied,
+            });
+        }
+    }
+    suggestions.sort_by_key(|s| std::cmp::Reverse(s.approved + s.denied));
+    suggestions.truncate(MAX_SUGGESTIONS);
+
+    // A rule with no recorded match may simply predate tracking.
+    let tracked_whole_window = tracking_since.is_some_and(|t| t <= since);
+    let stale_rules = rules
+        .iter()
+        .filter(|_| tracked_whole_window)
+        .filter(|r| r.enabled && r.kind == "command" && r.created_at < since)
+        .filter_map(|r| {
+            let (count, last) = matches.get(&r.id).copied().unwrap_or((0, None));
+            if last.is_some_and(|t| t >= since) {
+                return None;
+            }
+            Some(StaleRule {
+                id: r.id.clone(),
+                name: r.name.clone(),
+                action: r.action.clone(),
+                pattern: r.pattern.clone(),
+                match_count: count,
+                last_matched_at: last,
+                reason: format!("{} rule matched 0 times in {WINDOW_DAYS} days", r.action),
+            })
+        })
+        .collect();
+
+    Insights {
+        window_days: WINDOW_DAYS,
+        summary,
+        suggestions,
+        stale_rules,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approval_insights_suggest_rules_and_flag_stale_ones() {
+        let now = 1_800_000_000;
+        let approval = |i: usize, command: &str, status: &str| crate::models::Approval {
+            id: format!("appr_{i}"),
+            kind: "command_execution".to_string(),
+            status: status.to_string(),
+            decision: None,
+            workspace_id: None,
+            channel_id: None,
+            thread_ts: None,
+            requested_by_user_id: None,
+            details_json: serde_json::json!({ "command": command }).to_string(),
+            created_at: now - 100,
+            updated_at: now,
+            resolved_at: Some(now - 100 + i as i64),
+        };
+        let mut approvals: Vec<_> = (0..6)
+            .map(|i| approval(i, "cargo test", "approved"))
+            .collect();
+        approvals.extend((6..9).map(|i| approval(i, "rm -rf target", "denied")));
+        approvals.push(approval(9, "git push", "approved"));
+        approvals.extend((10..13).map(|i| approval(i, "cargo build", "approved")));
+        approvals.extend((13..15).map(|i| approval(i, "cargo build --release", "approved")));
+        let rule = |id: &str, created_at: i64| crate::models::GuardrailRule {
+            id: id.to_string(),
+            name: id.to_string(),
+            kind: "command".to_string(),
+            pattern_kind: "exact".to_string(),
+            pattern: format!("{id} --version"),
+            action: "allow".to_string(),
+            priority: 1,
+            enabled: true,
+            created_at,
+            updated_at: created_at,
+        };
+        let rules = vec![rule("old", 0), rule("recent_match", 0), rule("new", now)];
+        let matches = [("recent_match".to_string(), (3, Some(now - 10)))]
+            .into_iter()
+            .collect();
+
+        let window = WINDOW_DAYS * 24 * 60 * 60;
+        let insights = analyze(&approvals, &rules, &matches, Some(now - window - 1), now);
+        assert_eq!(insights.summary.total, 15);
+        assert_eq!(insights.summary.approved, 12);
+        assert_eq!(insights.summary.denied, 3);
+        let by_action: Vec<_> = insights
+            .suggestions
+            .iter()
+            .map(|s| (s.action.as_str(), s.name.as_str()))
+            .collect();
+        assert_eq!(
+            by_action,
+            vec![
+                ("allow", "suggested: cargo test"),
+                ("allow", "suggested: Always cargo build"),
+                ("deny", "suggested deny: rm -rf target"),
+            ]
+        );
+        assert!(insights.suggestions[0].reason.contains("approved 6 times"));
+        // The grouped suggestion is pinned to the subcommand.
+        let build = regex::Regex::new(&insights.suggestions[1].pattern).unwrap();
+        assert!(build.is_match("cargo build --locked"));
+        assert!(!build.is_match("cargo publish"));
+        let stale: Vec<_> = insights.stale_rules.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(stale, vec!["old"]);
+
+        // Until match tracking covers the whole window, no rule is called stale.
+        let recent = analyze(&approvals, &rules, &matches, Some(now - 60), now);
+        assert!(recent.stale_rules.is_empty());
+        assert!(analyze(&approvals, &rules, &matches, None, now)
+            .stale_rules
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn guardrail_match_tracking_starts_at_its_migration() {
+        let db = crate::test_support::TestDb::new().await;
+        let pool = db.pool.clone();
+        let since = crate::db::guardrail_match_tracking_since(&pool)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!((since - chrono::Utc::now().timestamp()).abs() < 60);
+    }
+}
@@ -0,0 +1,525 @@
+//! How proposed commands are shown in approval requests.
+//!
+//! A command is split into shell and unified-diff segments (a patch fed to `git apply` or
+//! `patch` through a heredoc, say). Telegram gets HTML with `language-bash` and
+//! `language-diff` code blocks, which its clients highlight. Slack code blocks have no
+//! highlighting, so diffs are summarized inline and attached as a `.diff` snippet, which
+//! Slack colors. Proposals too long to read in the message are cut and attached whole on
+//! Slack; on Telegram, which gets no attachments, they are cut to fit one message and point
+//! to the admin dashboard.
+
+/// Proposals longer than this (characters or lines) are collapsed behind a snippet.
+pub const INLINE_MAX_CHARS: usize = 1_500;
+pub const INLINE_MAX_LINES: usize = 25;
+/// Telegram's message limit, after entity parsing.
+pub const TELEGRAM_MAX_CHARS: usize = 4_096;
+/// Longest agent-given reason shown in a Telegram request.
+const REASON_MAX_CHARS: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Shell,
+    Diff,
+}
+
+impl Lang {
+    fn telegram_class(self) -> &'static str {
+        match self {
+            Lang::Shell => "language-bash",
+            Lang::Diff => "language-diff",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub lang: Lang,
+    pub text: String,
+}
+
+/// A file to attach next to the approval message (Slack picks highlighting from the
+/// extension).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub filename: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    /// Code blocks for the message body, in the target markup.
+    pub body: String,
+    pub snippets: Vec<Snippet>,
+}
+
+fn is_diff_start(line: &str, next: Option<&str>) -> bool {
+    line.starts_with("diff --git ")
+        || (line.starts_with("--- ") && next.is_some_and(|n| n.starts_with("+++ ")))
+}
+
+fn is_file_header(line: &str) -> bool {
+    const HEADERS: [&str; 11] = [
+        "diff ",
+        "index ",
+        "--- ",
+        "+++ ",
+        "new file",
+        "deleted file",
+        "old mode",
+        "new mode",
+        "similarity",
+        "rename ",
+        "Binary files",
+    ];
+    HEADERS.iter().any(|h| line.starts_with(h))
+}
+
+/// `(old, new)` line counts from a hunk header like `@@ -1,2 +1,3 @@`.
+fn hunk_counts(line: &str) -> Option<(usize, usize)> {
+    let (ranges, _) = line.strip_prefix("@@ -")?.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let count = |range: &str| match range.split_once(',') {
+        Some((_, n)) => n.parse::<usize>().ok(),
+        None => range.parse::<usize>().ok().map(|_| 1),
+    };
+    Some((count(old)?, count(new)?))
+}
+
+/// Where the splitter is relative to a unified diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffState {
+    Outside,
+    /// File headers (`diff --git`, `---`/`+++`, mode lines) before the first hunk.
+    Header,
+    /// Inside a hunk, with the old and new lines it still has to cover.
+    Hunk(usize, usize),
+    /// A hunk just ended; another hunk or file may follow.
+    Between,
+}
+
+fn enter_hunk(line: &str) -> DiffState {
+    hunk_counts(line).map_or(DiffState::Outside, |(old, new)| {
+        if old == 0 && new == 0 {
+            DiffState::Between
+        } else {
+            DiffState::Hunk(old, new)
+        }
+    })
+}
+
+fn hunk_line(old: usize, new: usize, line: &str) -> DiffState {
+    // Some tools strip the space from empty context lines.
+    let (old, new) = match line.chars().next() {
+        None | Some(' ') if old > 0 && new > 0 => (old - 1, new - 1),
+        Some('-') if old > 0 => (old - 1, new),
+        Some('+') if new > 0 => (old, new - 1),
+        Some('\\') => (old, new),
+        _ => return DiffState::Outside,
+    };
+    if old == 0 && new == 0 {
+        DiffState::Between
+    } else {
+        DiffState::Hunk(old, new)
+    }
+}
+
+/// Split `command` into shell and unified-diff segments, in order. Only file headers and
+/// the lines each hunk header accounts for are diff; whatever follows (a heredoc
+/// terminator, more commands) is shell again.
+pub fn segments(command: &str) -> Vec<Segment> {
+    let lines: Vec<&str> = command.lines().collect();
+    let mut out: Vec<Segment> = Vec::new();
+    let mut push = |lang: Lang, line: &str| match out.last_mut() {
+        Some(seg) if seg.lang == lang => {
+            seg.text.push('\n');
+            seg.text.push_str(line);
+        }
+        _ => out.push(Segment {
+            lang,
+            text: line.to_string(),
+        }),
+    };
+    let mut state = DiffState::Outside;
+    for (i, line) in lines.iter().enumerate() {
+        let next = lines.get(i + 1).copied();
+        state = match state {
+            DiffState::Header | DiffState::Between if line.starts_with("@@") => enter_hunk(line),
+            DiffState::Header if is_file_header(line) => DiffState::Header,
+            DiffState::Between if line.starts_with('\\') => DiffState::Between,
+            DiffState::Hunk(old, new) => hunk_line(old, new, line),
+            _ => DiffState::Outside,
+        };
+        if state == DiffState::Outside && is_diff_start(line, next) {
+            state = DiffState::Header;
+        }
+        let lang = if state == DiffState::Outside {
+            Lang::Shell
+        } else {
+            Lang::Diff
+        };
+        push(lang, line);
+    }
+    for seg in &mut out {
+        seg.text = seg.text.trim_matches('\n').to_string();
+    }
+    out.retain(|s| !s.text.trim().is_empty());
+    out
+}
+
+/// `(files, added, removed)` for a unified diff.
+pub fn diff_stat(diff: &str) -> (usize, usize, usize) {
+    let mut files = 0;
+    let mut added = 0;
+    let mut removed = 0;
+    for line in diff.lines() {
+        if line.starts_with("+++ ") {
+            files += 1;
+        } else if line.starts_with('+') {
+            added += 1;
+        } else if line.starts_with('-') && !line.starts_with("--- ") {
+            removed += 1;
+        }
+    }
+    (files, added, removed)
+}
+
+fn is_long(s: &str) -> bool {
+    s.chars().count() > INLINE_MAX_CHARS || s.lines().count() > INLINE_MAX_LINES
+}
+
+/// Up to `max_lines` lines and `max_chars` characters of `s`.
+fn head(s: &str, max_lines: usize, max_chars: usize) -> String {
+    let lines: Vec<&str> = s.lines().take(max_lines).collect();
+    grail_text::head(&lines.join("\n"), max_chars).to_string()
+}
+
+/// Up to `INLINE_MAX_LINES` lines and `INLINE_MAX_CHARS` characters of `s`.
+fn inline_head(s: &str) -> String {
+    head(s, INLINE_MAX_LINES, INLINE_MAX_CHARS)
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Slack mrkdwn: code blocks can't contain triple backticks.
+fn slack_block(s: &str) -> String {
+    format!("```\n{}\n```", s.replace("```", "'''"))
+}
+
+fn telegram_block(lang: Lang, s: &str) -> String {
+    format!(
+        "<pre><code class=\"{}\">{}</code></pre>",
+        lang.telegram_class(),
+        html_escape(s)
+    )
+}
+
+/// Preview for Slack. Diffs are summarized with their first lines and attached whole as
+/// `<name>.diff`; a long command is cut and attached whole as `<name>.sh`.
+pub fn slack_preview(command: &str, name: &str) -> Preview {
+    let segs = segments(command);
+    let mut parts = Vec::new();
+    let mut diffs = Vec::new();
+    for seg in &segs {
+        match seg.lang {
+            Lang::Shell => parts.push(slack_block(&inline_head(&seg.text))),
+            Lang::Diff => {
+                let (files, added, removed) = diff_stat(&seg.text);
+                let head: Vec<&str> = seg.text.lines().take(8).collect();
+                parts.push(format!(
+                    "Patch: {files} file(s), +{added} −{removed} (full diff attached below)\n{}",
+                    slack_block(&head.join("\n"))
+                ));
+                diffs.push(seg.text.as_str());
+            }
+        }
+    }
+    let shell_long = segs
+        .iter()
+        .any(|s| s.lang == Lang::Shell && is_long(&s.text));
+
+    let mut snippets = Vec::new();
+    if !diffs.is_empty() {
+        snippets.push(Snippet {
+            filename: format!("{name}.diff"),
+            content: format!("{}\n", diffs.join("\n")),
+        });
+    }
+    if shell_long {
+        parts.push(":page_facing_up: The full command is attached below.".to_string());
+        snippets.push(Snippet {
+            filename: format!("{name}.sh"),
+            content: format!("{}\n", command.trim_end()),
+        });
+    }
+    Preview {
+        body: parts.join("\n"),
+        snippets,
+    }
+}
+
+/// Preview without attachments: each segment cut to `max_lines`/`max_chars` (0 leaves only
+/// the patch summaries), with a pointer to `full_url` when anything was cut.
+fn chat_preview(
+    markup: Markup,
+    command: &str,
+    full_url: Option<&str>,
+    max_lines: usize,
+    max_chars: usize,
+) -> Preview {
+    let mut parts = Vec::new();
+    let mut cut = false;
+    for seg in segments(command) {
+        if seg.lang == Lang::Diff {
+            let (files, added, removed) = diff_stat(&seg.text);
+            parts.push(format!("Patch: {files} file(s), +{added} −{removed}"));
+        }
+        let shown = head(&seg.text, max_lines, max_chars);
+        let seg_cut = shown.len() < seg.text.len();
+        cut |= seg_cut;
+        if shown.trim().is_empty() {
+            continue;
+        }
+        let shown = if seg_cut {
+            format!("{shown}\n…")
+        } else {
+            shown
+        };
+        parts.push(match markup {
+            Markup::Telegram => telegram_block(seg.lang, &shown),
+            Markup::Slack => slack_block(&shown),
+            Markup::Plain => shown,
+        });
+    }
+    if cut {
+        parts.push(match (full_url, markup) {
+            (Some(url), Markup::Telegram) => format!(
+                "The proposal was cut. Read it in full: {}",
+                html_escape(url)
+            ),
+            (Some(url), _) => format!("The proposal was cut. Read it in full: {url}"),
+            (None, _) => {
+                "The proposal was cut; read it in full in the admin dashboard.".to_string()
+            }
+        });
+    }
+    Preview {
+        body: parts.join("\n"),
+        snippets: Vec::new(),
+    }
+}
+
+/// The Telegram approval request as HTML that fits one message (code blocks shrink until
+/// it does), and as plain text for when Telegram rejects the HTML. Neither mentions
+/// attachments: Telegram requests have none.
+pub fn telegram_request(
+    cwd: &str,
+    command: &str,
+    reason: Option<&str>,
+    options: &[(String, String)],
+    full_url: Option<&str>,
+) -> (String, String) {
+    let reason = reason.map(|r| grail_text::truncate(r.trim(), REASON_MAX_CHARS));
+    let reason = reason.as_deref();
+    let mut html = String::new();
+    for (max_lines, max_chars) in [(INLINE_MAX_LINES, INLINE_MAX_CHARS), (8, 400), (0, 0)] {
+        let preview = chat_preview(Markup::Telegram, command, full_url, max_lines, max_chars);
+        html = request_text(Markup::Telegram, cwd, &preview, reason, options);
+        if html.chars().count() <= TELEGRAM_MAX_CHARS {
+            break;
+        }
+    }
+    let preview = chat_preview(
+        Markup::Plain,
+        command,
+        full_url,
+        INLINE_MAX_LINES,
+        INLINE_MAX_CHARS,
+    );
+    let plain = request_text(Markup::Plain, cwd, &preview, reason, options);
+    (html, plain)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Markup {
+    /// Slack mrkdwn.
+    Slack,
+    /// Telegram HTML parse mode.
+    Telegram,
+    /// No markup at all.
+    Plain,
+}
+
+/// The approval request: what will run, where and why, then the reply options as
+/// `(command, note)` pairs.
+pub fn request_text(
+    markup: Markup,
+    cwd: &str,
+    preview: &Preview,
+    reason: Option<&str>,
+    options: &[(String, String)],
+) -> String {
+    let plain = |s: &str| match markup {
+        Markup::Slack | Markup::Plain => s.to_string(),
+        Markup::Telegram => html_escape(s),
+    };
+    let bold = |s: &str| match markup {
+        Markup::Slack => format!("*{s}*"),
+        Markup::Telegram => format!("<b>{}</b>", html_escape(s)),
+        Markup::Plain => s.to_string(),
+    };
+    let code = |s: &str| match markup {
+        Markup::Slack => format!("`{s}`"),
+        Markup::Telegram => format!("<code>{}</code>", html_escape(s)),
+        Markup::Plain => s.to_string(),
+    };
+    let mut msg = format!(
+        "{}\nProposed command in {}:\n{}\n",
+        bold("Approval required"),
+        code(cwd),
+        preview.body
+    );
+    if let Some(reason) = reason.map(str::trim).filter(|r| !r.is_empty()) {
+        msg.push_str(&format!("Reason: {}\n", plain(reason)));
+    }
+    msg.push_str("Reply:\n");
+    for (command, note) in options {
+        if note.is_empty() {
+            msg.push_str(&format!("- {}\n", code(command)));
+        } else {
+            msg.push_str(&format!("- {} ({})\n", code(command), plain(note)));
+        }
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approval_preview_separates_diffs_and_collapses_long_commands() {
+        let command = [
+            "cd repo && git apply <<'EOF'",
+            "diff --git a/src/a.rs b/src/a.rs",
+            "--- a/src/a.rs",
+            "+++ b/src/a.rs",
+            "@@ -1,2 +1,2 @@",
+            "-let x = 1;",
+            "+let x = 2;",
+            " fn main() {}",
+            "EOF",
+        ]
+        .join("\n");
+        let command = command.as_str();
+        let segs = segments(command);
+        let langs: Vec<Lang> = segs.iter().map(|s| s.lang).collect();
+        assert_eq!(langs, vec![Lang::Shell, Lang::Diff, Lang::Shell]);
+        assert_eq!(segs[2].text, "EOF");
+        assert_eq!(diff_stat(&segs[1].text), (1, 1, 1));
+
+        let slack = slack_preview(command, "proposal-1");
+        assert!(slack.body.contains("Patch: 1 file(s), +1 −1"));
+        assert_eq!(slack.snippets.len(), 1);
+        assert_eq!(slack.snippets[0].filename, "proposal-1.diff");
+        assert!(slack.snippets[0].content.starts_with("diff --git"));
+
+        let long = (0..40)
+            .map(|i| format!("echo {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let slack = slack_preview(&long, "p");
+        assert_eq!(slack.snippets[0].filename, "p.sh");
+        assert!(!slack.body.contains("echo 39"));
+
+        let url = Some("https://x/admin/approvals");
+        let options = vec![
+            ("approve 1".to_string(), "once".to_string()),
+            ("deny 1".to_string(), String::new()),
+        ];
+        let (html, _) = telegram_request("/w", "echo '<b>' && cat a > b", None, &options, url);
+        assert!(html.contains("<pre><code class=\"language-bash\">echo '&lt;b&gt;' &amp;&amp; cat a &gt; b</code></pre>"));
+        let (html, plain) = telegram_request("/w<s>", &long, Some("a & b"), &options, url);
+        assert!(html.contains("Read it in full: https://x/admin/approvals\n"));
+        assert!(html.starts_with(
+            "<b>Approval required</b>\nProposed command in <code>/w&lt;s&gt;</code>:"
+        ));
+        assert!(html.contains("Reason: a &amp; b\n"));
+        assert!(html.ends_with("- <code>approve 1</code> (once)\n- <code>deny 1</code>\n"));
+        // The plain fallback has no markup and points nowhere but the dashboard.
+        assert!(plain.starts_with("Approval required\nProposed command in /w<s>:\necho 0"));
+        assert!(plain.contains("- approve 1 (once)\n"));
+        assert!(!plain.contains("attached"));
+
+        // A patch too big for one Telegram message is cut down until it fits.
+        let big = format!(
+            "git apply <<'EOF'\ndiff --git a/a b/a\n--- a/a\n+++ b/a\n@@ -0,0 +1,300 @@\n{}\nEOF",
+            (0..300)
+                .map(|i| format!("+{}", "x".repeat(60 + i % 7)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        let (html, plain) = telegram_request("/w", &big, None, &options, url);
+        assert!(html.chars().count() <= TELEGRAM_MAX_CHARS);
+        assert!(html.contains("Patch: 1 file(s), +300 −0"));
+        assert!(html.contains("<pre><code class=\"language-bash\">EOF</code></pre>"));
+        assert!(!plain.contains("attached"));
+    }
+
+    #[test]
+    fn approval_preview_bounds_diffs_by_their_hunk_headers() {
+        // The hunk covers two lines; the `-rf` and `+x` lines after it are shell again.
+        let command = [
+            "patch -p1 <<'EOF'",
+            "--- a/run.sh",
+            "+++ b/run.sh",
+            "@@ -1 +1 @@",
+            "-echo old",
+            "+echo new",
+            "EOF",
+            "",
+            "rm -rf /tmp/cache",
+            "-rf",
+            "+x",
+        ]
+        .join("\n");
+        let segs = segments(&command);
+        let langs: Vec<Lang> = segs.iter().map(|s| s.lang).collect();
+        assert_eq!(langs, vec![Lang::Shell, Lang::Diff, Lang::Shell]);
+        assert_eq!(segs[1].text.lines().last(), Some("+echo new"));
+        assert_eq!(segs[2].text, "EOF\n\nrm -rf /tmp/cache\n-rf\n+x");
+        assert!(slack_preview(&command, "p")
+            .body
+            .contains("rm -rf /tmp/cache"));
+
+        // Several hunks and files, a missing-newline marker and a stripped context line.
+        let multi = [
+            "diff --git a/a b/a",
+            "--- a/a",
+            "+++ b/a",
+            "@@ -1,2 +1,2 @@",
+            "-one",
+            "",
+            "+uno",
+            "@@ -9 +9 @@",
+            "-nine",
+            "+nueve",
+            "\\ No newline at end of file",
+            "diff --git a/b b/b",
+            "new file mode 100644",
+            "--- /dev/null",
+            "+++ b/b",
+            "@@ -0,0 +1 @@",
+            "+hello",
+            "echo done",
+        ]
+        .join("\n");
+        let segs = segments(&multi);
+        let langs: Vec<Lang> = segs.iter().map(|s| s.lang).collect();
+        assert_eq!(langs, vec![Lang::Diff, Lang::Shell]);
+        assert_eq!(segs[1].text, "echo done");
+    }
+}
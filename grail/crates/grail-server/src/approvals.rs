@@ -2,29 +2,37 @@ use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use anyhow::Context;
+use grail_text::truncate_line;
 use serde::Deserialize;
 use serde_json::json;
 use tracing::{info, warn};
 
+use crate::approval_preview::{self, Markup};
 use crate::db;
-use crate::guardrails::{evaluate_command_guardrails, validate_rule, Decision};
-use crate::models::{Approval, CronJob, GuardrailRule, PermissionsMode, Settings, Task};
+use crate::guardrails::{
+    evaluate_command_guardrails, suggest_always_scopes, validate_rule, AlwaysScope, Decision,
+};
+use crate::models::{
+    Approval, CronJob, GuardrailRule, PermissionsMode, Settings, Task, TaskArtifact,
+};
 use crate::slack::SlackClient;
 use crate::telegram::TelegramClient;
 use crate::AppState;
 
 const APPROVAL_TIMEOUT_SECS: u64 = 15 * 60;
 
+/// Decide on a command execution request. Returns the app-server response and, when a
+/// person approved the command, the approval id.
 pub async fn handle_command_execution_request(
     state: &AppState,
     settings: &Settings,
     cwd: &Path,
     task: &Task,
     params: &serde_json::Value,
-) -> anyhow::Result<serde_json::Value> {
+) -> anyhow::Result<(serde_json::Value, Option<String>)> {
     // Respect the global permissions switch first.
     if settings.permissions_mode != PermissionsMode::Full {
-        return Ok(json!({ "decision": "decline" }));
+        return Ok((json!({ "decision": "decline" }), None));
     }
 
     // Require commands to run under our configured cwd (avoid touching app code).
@@ -39,10 +47,10 @@ pub async fn handle_command_execution_request(
         cmd_cwd = cwd.join(cmd_cwd);
     }
     let Some(cmd_cwd) = clean_path_no_parent(&cmd_cwd) else {
-        return Ok(json!({ "decision": "decline" }));
+        return Ok((json!({ "decision": "decline" }), None));
     };
     if !cmd_cwd.starts_with(cwd) {
-        return Ok(json!({ "decision": "decline" }));
+        return Ok((json!({ "decision": "decline" }), None));
     }
 
     let command = params
@@ -52,25 +60,49 @@ pub async fn handle_command_execution_request(
         .trim()
         .to_string();
     if command.is_empty() {
-        return Ok(json!({ "decision": "decline" }));
+        return Ok((json!({ "decision": "decline" }), None));
     }
 
     match settings.command_approval_mode.as_str() {
-        "auto" => return Ok(json!({ "decision": "accept" })),
+        "auto" => return Ok((json!({ "decision": "accept" }), None)),
         "always_ask" => {}
         _ => {
             // guardrails (default)
-            let rules = db::list_guardrail_rules(&state.pool, Some("command"), 500).await?;
-            let (decision, matched) = evaluate_command_guardrails(&rules, &command).await?;
+            let sets =
+                crate::guardrail_canary::rule_sets(state, "command", &task.channel_id).await?;
+            let (decision, matched) = evaluate_command_guardrails(&sets.applied, &command).await?;
+            if let Some(shadow) = &sets.shadow {
+                match evaluate_command_guardrails(shadow, &command).await {
+                    Ok((other, other_rule)) => {
+                        crate::guardrail_canary::record(
+                            state,
+                            task,
+                            "command",
+                            &command,
+                            &sets,
+                            (decision, matched.as_ref()),
+                            (other, other_rule.as_ref()),
+                        )
+                        .await
+                    }
+                    Err(err) => warn!(error = %err, "failed to evaluate shadow guardrails"),
+                }
+            }
+            // Match counters track the active rules only.
+            if let Some(rule) = matched.as_ref().filter(|_| !sets.canary_applied) {
+                if let Err(err) = db::record_guardrail_match(&state.pool, &rule.id).await {
+                    warn!(error = %err, rule_id = %rule.id, "failed to record guardrail match");
+                }
+            }
             match decision {
-                Decision::Allow => return Ok(json!({ "decision": "accept" })),
+                Decision::Allow => return Ok((json!({ "decision": "accept" }), None)),
                 Decision::Deny => {
                     warn!(
                         command = %command,
                         matched_rule = matched.as_ref().map(|r| r.id.as_str()).unwrap_or(""),
                         "command denied by guardrail"
                     );
-                    return Ok(json!({ "decision": "decline" }));
+                    return Ok((json!({ "decision": "decline" }), None));
                 }
                 Decision::RequireApproval => {}
             }
@@ -81,10 +113,15 @@ pub async fn handle_command_execution_request(
     let approval_id = random_id("appr");
     let now = chrono::Utc::now().timestamp();
 
+    let scopes = suggest_always_scopes(&command);
     let details = json!({
         "command": command,
         "cwd": cmd_cwd.to_string_lossy(),
         "reason": params.get("reason").cloned().unwrap_or(json!(null)),
+        "always_scopes": scopes
+            .iter()
+            .map(|s| json!({ "scope": s.scope, "label": s.label, "pattern_kind": "regex", "pattern": s.pattern }))
+            .collect::<Vec<_>>(),
     });
 
     let approval = Approval {
@@ -119,40 +156,55 @@ pub async fn handle_command_execution_request(
         format!("deny {}", approval_id)
     };
 
-    let mut msg = String::new();
-    msg.push_str("*Approval required*\n");
-    msg.push_str(&format!(
-        "Proposed command in `{}`:\n```\n{}\n```\n",
-        cmd_cwd.to_string_lossy(),
-        crate::secrets::redact_secrets(
-            details
-                .get("command")
-                .and_then(|v| v.as_str())
-                .unwrap_or(""),
-        )
-        .0
-    ));
-    if let Some(reason) = params.get("reason").and_then(|v| v.as_str()) {
-        if !reason.trim().is_empty() {
-            msg.push_str(&format!("Reason: {reason}\n"));
-        }
+    let mut options = vec![
+        (approve_hint, "once".to_string()),
+        (always_hint, "remember".to_string()),
+    ];
+    for scope in &scopes {
+        let hint = if task.provider == "slack" {
+            format!(
+                "@{} always-{} {}",
+                settings.agent_name, scope.scope, approval_id
+            )
+        } else {
+            format!("always-{} {}", scope.scope, approval_id)
+        };
+        options.push((hint, scope.label.clone()));
     }
-    msg.push_str("Reply:\n");
-    msg.push_str(&format!("- `{}` (once)\n", approve_hint));
-    msg.push_str(&format!("- `{}` (remember)\n", always_hint));
-    msg.push_str(&format!("- `{}`\n", deny_hint));
+    options.push((deny_hint, String::new()));
+    let (shown_command, _) = crate::secrets::redact_secrets(&command);
+    let shown_cwd = cmd_cwd.to_string_lossy();
+    let reason = params.get("reason").and_then(|v| v.as_str());
+    let slack_preview = approval_preview::slack_preview(&shown_command, &approval_id);
+    let msg =
+        approval_preview::request_text(Markup::Slack, &shown_cwd, &slack_preview, reason, &options);
 
-    match task.provider.as_str() {
+    // Synthetic (admin test) tasks resolve approvals from the admin UI only.
+    let notify_provider = if task.is_synthetic {
+        ""
+    } else {
+        task.provider.as_str()
+    };
+    match notify_provider {
         "slack" => {
             if let Ok(Some(token)) = crate::secrets::load_slack_bot_token_opt(state).await {
                 let slack = SlackClient::new(state.http.clone(), token);
+                let mut buttons = vec![
+                    json!({ "type": "button", "text": { "type": "plain_text", "text": "Approve" }, "action_id": "grail_approve", "value": approval_id.clone() }),
+                    json!({ "type": "button", "text": { "type": "plain_text", "text": "Always" }, "style": "primary", "action_id": "grail_always", "value": approval_id.clone() }),
+                ];
+                for scope in &scopes {
+                    buttons.push(json!({
+                        "type": "button",
+                        "text": { "type": "plain_text", "text": truncate_line(&scope.label.replace('`', ""), 70) },
+                        "action_id": format!("grail_always_{}", scope.scope),
+                        "value": approval_id.clone(),
+                    }));
+                }
+                buttons.push(json!({ "type": "button", "text": { "type": "plain_text", "text": "Deny" }, "style": "danger", "action_id": "grail_deny", "value": approval_id.clone() }));
                 let blocks = json!([
                     { "type": "section", "text": { "type": "mrkdwn", "text": msg.trim() } },
-                    { "type": "actions", "elements": [
-                        { "type": "button", "text": { "type": "plain_text", "text": "Approve" }, "action_id": "grail_approve", "value": approval_id.clone() },
-                        { "type": "button", "text": { "type": "plain_text", "text": "Always" }, "style": "primary", "action_id": "grail_always", "value": approval_id.clone() },
-                        { "type": "button", "text": { "type": "plain_text", "text": "Deny" }, "style": "danger", "action_id": "grail_deny", "value": approval_id.clone() }
-                    ] }
+                    { "type": "actions", "elements": buttons }
                 ]);
 
                 if let Err(err) = slack
@@ -169,6 +221,19 @@ pub async fn handle_command_execution_request(
                         .post_message(&task.channel_id, thread_opt(&task.thread_ts), msg.trim())
                         .await;
                 }
+                for snippet in &slack_preview.snippets {
+                    if let Err(err) = slack
+                        .upload_file_content(
+                            &task.channel_id,
+                            thread_opt(&task.thread_ts),
+                            &snippet.filename,
+                            snippet.content.as_bytes(),
+                        )
+                        .await
+                    {
+                        warn!(error = %err, file = %snippet.filename, "failed to attach approval snippet");
+                    }
+                }
             } else {
                 warn!("cannot request approval: SLACK_BOT_TOKEN missing");
             }
@@ -177,9 +242,27 @@ pub async fn handle_command_execution_request(
             if let Ok(Some(token)) = crate::secrets::load_telegram_bot_token_opt(state).await {
                 let tg = TelegramClient::new(state.http.clone(), token);
                 let reply_to = task.thread_ts.parse::<i64>().ok();
-                let _ = tg
-                    .send_message(&task.channel_id, reply_to, msg.trim())
-                    .await;
+                let approvals_url = state
+                    .config
+                    .base_url
+                    .as_deref()
+                    .map(|b| format!("{}/admin/approvals", b.trim_end_matches('/')));
+                let (html, plain) = approval_preview::telegram_request(
+                    &shown_cwd,
+                    &shown_command,
+                    reason,
+                    &options,
+                    approvals_url.as_deref(),
+                );
+                if let Err(err) = tg
+                    .send_message_html(&task.channel_id, reply_to, html.trim())
+                    .await
+                {
+                    warn!(error = %err, "failed to send formatted approval message; falling back to plain text");
+                    let _ = tg
+                        .send_message(&task.channel_id, reply_to, plain.trim())
+                        .await;
+                }
             } else {
                 warn!("cannot request approval: TELEGRAM_BOT_TOKEN missing");
             }
@@ -191,26 +274,28 @@ pub async fn handle_command_execution_request(
     loop {
         if Instant::now() >= deadline {
             db::expire_approval(&state.pool, &approval_id).await?;
-            return Ok(json!({ "decision": "decline" }));
+            return Ok((json!({ "decision": "decline" }), None));
         }
 
         let Some(a) = db::get_approval(&state.pool, &approval_id).await? else {
             // Shouldn't happen, but fail closed.
-            return Ok(json!({ "decision": "decline" }));
+            return Ok((json!({ "decision": "decline" }), None));
         };
 
         match a.status.as_str() {
             "approved" => {
                 let decision = a.decision.unwrap_or_else(|| "approve".to_string());
-                if decision == "always" {
-                    // Persist an allow rule for this exact command.
+                if let Some((pattern_kind, pattern, name)) =
+                    always_rule_for_decision(&decision, &command, &scopes)
+                {
+                    // Persist an allow rule for this command (or the chosen narrower scope).
                     let now = chrono::Utc::now().timestamp();
                     let rule = GuardrailRule {
                         id: random_id("gr"),
-                        name: format!("approved: {}", truncate(&command, 48)),
+                        name,
                         kind: "command".to_string(),
-                        pattern_kind: "exact".to_string(),
-                        pattern: command.clone(),
+                        pattern_kind,
+                        pattern,
                         action: "allow".to_string(),
                         priority: 1,
                         enabled: true,
@@ -225,13 +310,13 @@ pub async fn handle_command_execution_request(
                 }
 
                 info!(approval_id = %approval_id, "approval granted");
-                return Ok(json!({ "decision": "accept" }));
+                return Ok((json!({ "decision": "accept" }), Some(approval_id)));
             }
             "denied" => {
                 info!(approval_id = %approval_id, "approval denied");
-                return Ok(json!({ "decision": "decline" }));
+                return Ok((json!({ "decision": "decline" }), None));
             }
-            "expired" => return Ok(json!({ "decision": "decline" })),
+            "expired" => return Ok((json!({ "decision": "decline" }), None)),
             _ => {}
         }
 
@@ -247,6 +332,8 @@ pub async fn handle_approval_command(
     let decision = match action {
         "approve" => ("approved", "approve"),
         "always" => ("approved", "always"),
+        "always_program" => ("approved", "always_program"),
+        "always_directory" => ("approved", "always_directory"),
         "deny" => ("denied", "deny"),
         "cancel" => ("denied", "deny"),
         _ => return Ok(Some("Unknown approval action.".to_string())),
@@ -269,6 +356,150 @@ pub async fn handle_approval_command(
     Ok(Some(format!("Recorded: {action} {approval_id}")))
 }
 
+/// Longest command output kept as an artifact; longer output keeps its start and end.
+const MAX_OUTPUT_ARTIFACT_CHARS: usize = 512 * 1024;
+
+/// After an approved command finishes, keep its output as a task artifact and post a
+/// short summary to the thread, so approvers can see what their approval did. Both are
+/// redacted; the summary is cut to `approval_output_chars` (0 turns it off) and goes
+/// through the reply content policy like any other reply.
+pub async fn report_command_output(
+    state: &AppState,
+    settings: &Settings,
+    task: &Task,
+    approval_id: &str,
+    item: &serde_json::Value,
+) -> anyhow::Result<()> {
+    let command = item.get("command").and_then(|v| v.as_str()).unwrap_or("");
+    let status = item
+        .get("status")
+        .and_then(|v| v.as_str())
+        .unwrap_or("completed");
+    let exit_code = item.get("exitCode").and_then(|v| v.as_i64());
+    let (output, _) = crate::secrets::redact_secrets(
+        item.get("aggregatedOutput")
+            .and_then(|v| v.as_str())
+            .unwrap_or(""),
+    );
+
+    let artifact = TaskArtifact {
+        id: random_id("art"),
+        task_id: task.id,
+        kind: "command_output".to_string(),
+        name: format!("{approval_id}.log"),
+        content: head_and_tail(&output, MAX_OUTPUT_ARTIFACT_CHARS),
+        approval_id: Some(approval_id.to_string()),
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    db::insert_task_artifact(&state.pool, &artifact).await?;
+    let _ = db::create_task_trace(
+        &state.pool,
+        task.id,
+        "approval.output",
+        "info",
+        "approved command finished",
+        &format!(
+            "approval={approval_id} status={status} exit_code={} artifact={} chars={}",
+            exit_code.map_or_else(|| "none".to_string(), |c| c.to_string()),
+            artifact.id,
+            output.chars().count()
+        ),
+    )
+    .await;
+
+    if settings.approval_output_chars <= 0 {
+        return Ok(());
+    }
+    let summary = command_output_summary(
+        command,
+        status,
+        exit_code,
+        &output,
+        settings.approval_output_chars as usize,
+    );
+    let summary = crate::worker::content_policy_decline(
+        state,
+        settings,
+        task,
+        crate::content_policy::Direction::Reply,
+        &summary,
+    )
+    .await?
+    .unwrap_or(summary);
+    crate::worker::send_user_message(state, task, &summary).await
+}
+
+/// `text` cut to about `max_chars`, keeping its start and end around an omission note.
+fn head_and_tail(text: &str, max_chars: usize) -> String {
+    let total = text.chars().count();
+    if total <= max_chars {
+        return text.to_string();
+    }
+    let head = grail_text::head(text, max_chars / 2);
+    let tail = grail_text::tail(text, max_chars / 2);
+    format!(
+        "{head}\n… ({} characters omitted) …\n{tail}",
+        total - head.chars().count() - tail.chars().count()
+    )
+}
+
+/// Thread message for a finished approved command. Long output keeps its start and end.
+pub fn command_output_summary(
+    command: &str,
+    status: &str,
+    exit_code: Option<i64>,
+    output: &str,
+    max_chars: usize,
+) -> String {
+    let outcome = match exit_code {
+        Some(0) => "exit 0".to_string(),
+        Some(code) => format!("exit {code}, failed"),
+        None => status.to_string(),
+    };
+    let (command, _) = crate::secrets::redact_secrets(command);
+    let mut msg = format!(
+        "*Approved command finished* ({outcome})\n```\n{}\n```\n",
+        truncate_line(&command, 300)
+    );
+    let output = output.trim();
+    if output.is_empty() {
+        msg.push_str("No output.");
+        return msg;
+    }
+    let total = output.chars().count();
+    let shown = head_and_tail(output, max_chars);
+    msg.push_str(&format!("```\n{}\n```", shown.replace("```", "'''")));
+    if total > max_chars {
+        msg.push_str("\nFull output is saved with the task in the admin dashboard.");
+    }
+    msg
+}
+
+/// Map an approval decision to the allow rule it should persist, if any.
+fn always_rule_for_decision(
+    decision: &str,
+    command: &str,
+    scopes: &[AlwaysScope],
+) -> Option<(String, String, String)> {
+    if decision == "always" {
+        return Some((
+            "exact".to_string(),
+            command.to_string(),
+            format!("approved: {}", truncate_line(command, 48)),
+        ));
+    }
+    let scope = decision.strip_prefix("always_")?;
+    let Some(s) = scopes.iter().find(|s| s.scope == scope) else {
+        // The scope wasn't offered for this command; fall back to the exact command.
+        return always_rule_for_decision("always", command, scopes);
+    };
+    Some((
+        "regex".to_string(),
+        s.pattern.clone(),
+        format!("approved: {}", truncate_line(&s.label.replace('`', ""), 48)),
+    ))
+}
+
 async fn apply_approval_side_effects(state: &AppState, approval: &Approval) -> anyhow::Result<()> {
     match approval.kind.as_str() {
         "guardrail_rule_add" => {
@@ -307,6 +538,7 @@ async fn apply_approval_side_effects(state: &AppState, approval: &Approval) -> a
                 channel_id: proposed.channel_id,
                 thread_ts: proposed.thread_ts.unwrap_or_default(),
                 prompt_text: proposed.prompt_text,
+                created_by_user_id: approval.requested_by_user_id.clone(),
                 next_run_at: proposed.next_run_at,
                 last_run_at: None,
                 last_status: None,
@@ -393,14 +625,28 @@ fn random_id(prefix: &str) -> String {
     format!("{}_{}", prefix, hex::encode(bytes))
 }
 
-fn truncate(s: &str, max: usize) -> String {
-    let s = s.trim().replace('\n', " ");
-    if s.len() <= max {
-        s
-    } else {
-        format!(
-            "{}…",
-            s.chars().take(max.saturating_sub(1)).collect::<String>()
-        )
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approved_command_summary_keeps_head_and_tail() {
+        let short = command_output_summary("ls", "completed", Some(0), "a\nb\n", 100);
+        assert!(short.contains("(exit 0)"));
+        assert!(short.contains("```\na\nb\n```"));
+        assert!(!short.contains("Full output"));
+
+        let long = format!("{}{}", "x".repeat(500), "y".repeat(500));
+        let cut = command_output_summary("make", "completed", Some(2), &long, 100);
+        assert!(cut.contains("(exit 2, failed)"));
+        assert!(cut.contains(&format!(
+            "{}\n… (900 characters omitted) …\n{}",
+            "x".repeat(50),
+            "y".repeat(50)
+        )));
+        assert!(cut.contains("Full output"));
+
+        let quiet = command_output_summary("true", "failed", None, "  ", 100);
+        assert!(quiet.contains("(failed)") && quiet.ends_with("No output."));
     }
 }
@@ -0,0 +1,282 @@
+//! Slack channels the bot can no longer post in.
+//!
+//! When a channel is archived or deleted, or the bot is removed from it, nothing queued
+//! for that channel can be delivered. Rather than letting those tasks and cron jobs fail
+//! one after another, the channel's queued tasks are cancelled, its cron jobs disabled
+//! and its usage report turned off, and the admin channel (`slack_admin_channel`) is told
+//! what happened. Running tasks are left to finish; their reply just won't land.
+
+use tracing::{info, warn};
+
+use crate::db;
+use crate::slack::SlackClient;
+use crate::AppState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Archived,
+    Deleted,
+    /// The bot was removed from (or left) the channel.
+    Removed,
+}
+
+impl Reason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Reason::Archived => "channel archived",
+            Reason::Deleted => "channel deleted",
+            Reason::Removed => "bot removed from channel",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cleanup {
+    pub cancelled_tasks: Vec<i64>,
+    pub disabled_cron_jobs: Vec<String>,
+    pub usage_report_disabled: bool,
+}
+
+fn plural(n: usize, one: &str, many: &str) -> String {
+    format!("{n} {}", if n == 1 { one } else { many })
+}
+
+/// The notice posted to the admin channel.
+pub fn admin_notice(
+    channel_id: &str,
+    reason: Reason,
+    actor: Option<&str>,
+    cleanup: &Cleanup,
+) -> String {
+    let what = match reason {
+        Reason::Archived => "the channel was archived",
+        Reason::Deleted => "the channel was deleted",
+        Reason::Removed => "the bot was removed from it",
+    };
+    let by = actor
+        .filter(|a| !a.trim().is_empty())
+        .map(|a| format!(" by <@{a}>"))
+        .unwrap_or_default();
+    let mut msg = format!("Grail can no longer post in <#{channel_id}>: {what}{by}.");
+    let mut done = Vec::new();
+    if !cleanup.cancelled_tasks.is_empty() {
+        let ids: Vec<String> = cleanup
+            .cancelled_tasks
+            .iter()
+            .map(|id| format!("#{id}"))
+            .collect();
+        done.push(format!(
+            "cancelled {} ({})",
+            plural(ids.len(), "queued task", "queued tasks"),
+            ids.join(", ")
+        ));
+    }
+    if !cleanup.disabled_cron_jobs.is_empty() {
+        done.push(format!(
+            "disabled {} ({})",
+            plural(cleanup.disabled_cron_jobs.len(), "cron job", "cron jobs"),
+            cleanup.disabled_cron_jobs.join(", ")
+        ));
+    }
+    if cleanup.usage_report_disabled {
+        done.push("turned off its usage report".to_string());
+    }
+    if done.is_empty() {
+        msg.push_str(" Nothing was queued for it.");
+    } else {
+        msg.push_str(&format!(" I {}.", done.join("; ")));
+    }
+    if !cleanup.disabled_cron_jobs.is_empty() {
+        msg.push_str(" Re-enable the cron jobs from /admin/cron once the channel is back.");
+    }
+    msg
+}
+
+/// Stop work bound to a Slack channel the bot can't post in, then tell the admins.
+pub async fn channel_gone(
+    state: &AppState,
+    workspace_id: &str,
+    channel_id: &str,
+    reason: Reason,
+    actor: Option<&str>,
+) -> anyhow::Result<Cleanup> {
+    let note = reason.as_str();
+    let cleanup = Cleanup {
+        cancelled_tasks: db::cancel_queued_channel_tasks(
+            &state.pool,
+            "slack",
+            workspace_id,
+            channel_id,
+            note,
+        )
+        .await?,
+        disabled_cron_jobs: db::disable_channel_cron_jobs(
+            &state.pool,
+            workspace_id,
+            channel_id,
+            note,
+        )
+        .await?,
+        usage_report_disabled: db::disable_usage_report(
+            &state.pool,
+            "slack",
+            workspace_id,
+            channel_id,
+        )
+        .await?,
+    };
+    for task_id in &cleanup.cancelled_tasks {
+        let details = serde_json::json!({ "channel_id": channel_id, "reason": note });
+        if let Err(err) = db::create_task_trace(
+            &state.pool,
+            *task_id,
+            "task.channel_gone",
+            "warn",
+            &format!("Cancelled: {note}"),
+            &details.to_string(),
+        )
+        .await
+        {
+            warn!(error = %err, task_id, "failed to record channel cleanup trace");
+        }
+    }
+    info!(
+        workspace_id,
+        channel_id,
+        reason = note,
+        cancelled_tasks = cleanup.cancelled_tasks.len(),
+        disabled_cron_jobs = cleanup.disabled_cron_jobs.len(),
+        "stopped work for a slack channel the bot can no longer post in"
+    );
+
+    let settings = db::get_settings(&state.pool).await?;
+    let admin_channel = settings.slack_admin_channel.trim();
+    if !admin_channel.is_empty() && admin_channel != channel_id {
+        if let Some(token) = crate::secrets::load_slack_bot_token_opt(state).await? {
+            let text = admin_notice(channel_id, reason, actor, &cleanup);
+            if let Err(err) = SlackClient::new(state.http.clone(), token)
+                .post_message(admin_channel, None, &text)
+                .await
+            {
+                warn!(error = %err, "failed to notify the slack admin channel");
+            }
+        }
+    }
+    Ok(cleanup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn gone_slack_channel_stops_queued_work() {
+        let env: crate::SlackEnvelope = serde_json::from_str(
+            r#"{"type": "event_callback", "team_id": "T1", "event_id": "Ev1",
+                "event": {"type": "member_left_channel", "user": "UBOT", "channel": "C1",
+                          "channel_type": "C", "team": "T1"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            env,
+            crate::SlackEnvelope::EventCallback { event: crate::SlackEvent::MemberLeftChannel { ref user, .. }, .. }
+                if user == "UBOT"
+        ));
+
+        let db = crate::test_support::TestDb::new().await;
+        let pool = db.pool.clone();
+        let gone = crate::db::enqueue_task(
+            &pool,
+            &crate::db::NewTask {
+                provider: "slack",
+                workspace_id: "T1",
+                channel_id: "C1",
+                thread_ts: "1.1",
+                event_ts: "1.1",
+                requested_by_user_id: "U1",
+                prompt_text: "hi",
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let other = crate::db::enqueue_task(
+            &pool,
+            &crate::db::NewTask {
+                provider: "slack",
+                workspace_id: "T1",
+                channel_id: "C2",
+                thread_ts: "2.1",
+                event_ts: "2.1",
+                requested_by_user_id: "U1",
+                prompt_text: "hi",
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let job = |id: &str, channel: &str| crate::models::CronJob {
+            id: id.to_string(),
+            name: format!("{id} job"),
+            enabled: true,
+            mode: "agent".to_string(),
+            schedule_kind: "every".to_string(),
+            every_seconds: Some(3600),
+            cron_expr: None,
+            at_ts: None,
+            workspace_id: "T1".to_string(),
+            channel_id: channel.to_string(),
+            thread_ts: String::new(),
+            prompt_text: "standup".to_string(),
+            created_by_user_id: None,
+            next_run_at: Some(0),
+            last_run_at: None,
+            last_status: None,
+            last_error: None,
+            created_at: 0,
+            updated_at: 0,
+        };
+        crate::db::insert_cron_job(&pool, &job("daily", "C1"))
+            .await
+            .unwrap();
+        crate::db::insert_cron_job(&pool, &job("weekly", "C2"))
+            .await
+            .unwrap();
+
+        let cancelled =
+            crate::db::cancel_queued_channel_tasks(&pool, "slack", "T1", "C1", "channel archived")
+                .await
+                .unwrap();
+        assert_eq!(cancelled, vec![gone]);
+        let disabled = crate::db::disable_channel_cron_jobs(&pool, "T1", "C1", "channel archived")
+            .await
+            .unwrap();
+        assert_eq!(disabled, vec!["daily job".to_string()]);
+        let task = crate::db::get_task(&pool, gone).await.unwrap().unwrap();
+        assert_eq!(task.status, "cancelled");
+        assert_eq!(task.error_text.as_deref(), Some("channel archived"));
+        assert_eq!(
+            crate::db::get_task_status(&pool, other)
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("queued")
+        );
+        let jobs = crate::db::list_cron_jobs(&pool, 10).await.unwrap();
+        assert!(jobs.iter().all(|j| j.enabled == (j.channel_id == "C2")));
+
+        let cleanup = Cleanup {
+            cancelled_tasks: cancelled,
+            disabled_cron_jobs: disabled,
+            usage_report_disabled: false,
+        };
+        let notice = admin_notice("C1", Reason::Archived, Some("U9"), &cleanup);
+        assert!(notice
+            .starts_with("Grail can no longer post in <#C1>: the channel was archived by <@U9>."));
+        assert!(notice.contains(&format!(
+            "cancelled 1 queued task (#{gone}); disabled 1 cron job (daily job)."
+        )));
+        let empty = admin_notice("C1", Reason::Removed, None, &Cleanup::default());
+        assert!(empty.ends_with("the bot was removed from it. Nothing was queued for it."));
+    }
+}
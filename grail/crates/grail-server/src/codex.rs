@@ -206,6 +206,8 @@ pub struct CodexManager {
     last_env_fingerprint: Option<String>,
     last_config_fingerprint: Option<String>,
     last_auth_fingerprint: Option<String>,
+    /// Per-task thread config overrides (e.g. `shell_environment_policy`).
+    thread_config: serde_json::Value,
 }
 
 impl CodexManager {
@@ -216,18 +218,26 @@ impl CodexManager {
             last_env_fingerprint: None,
             last_config_fingerprint: None,
             last_auth_fingerprint: None,
+            thread_config: serde_json::Value::Null,
         }
     }
 
+    /// Config overrides sent with the next `thread/start` or `thread/resume`.
+    pub fn set_thread_config(&mut self, config: serde_json::Value) {
+        self.thread_config = config;
+    }
+
     pub async fn ensure_started(
         &mut self,
         openai_api_key: Option<&str>,
         slack_bot_token: Option<&str>,
         slack_allow_channels: Option<&str>,
+        slack_team_id: Option<&str>,
         brave_search_api_key: Option<&str>,
         web_allow_domains: Option<&str>,
         web_deny_domains: Option<&str>,
         allow_slack_mcp: bool,
+        slack_read_only: bool,
         allow_web_mcp: bool,
         extra_mcp_config: Option<&str>,
         browser: &BrowserEnvConfig,
@@ -238,13 +248,18 @@ impl CodexManager {
             .with_context(|| format!("create CODEX_HOME dir {}", codex_home.display()))?;
 
         // Write a minimal config.toml for Codex (MCP server + no update checks).
-        let mut cfg = self.render_codex_config(allow_slack_mcp, allow_web_mcp, extra_mcp_config);
+        let mut cfg = render_codex_config(
+            allow_slack_mcp,
+            slack_read_only,
+            allow_web_mcp,
+            extra_mcp_config,
+        );
         if let Err(err) = toml::from_str::<toml::Value>(&cfg) {
             warn!(
                 error = %err,
                 "invalid extra MCP config; ignoring extra_mcp_config"
             );
-            cfg = self.render_codex_config(allow_slack_mcp, allow_web_mcp, None);
+            cfg = render_codex_config(allow_slack_mcp, slack_read_only, allow_web_mcp, None);
         }
         let cfg_fp = sha256_hex(cfg.as_bytes());
         let config_changed = self.last_config_fingerprint.as_deref() != Some(&cfg_fp);
@@ -271,10 +286,11 @@ impl CodexManager {
         // Restart the app-server if the auth inputs changed.
         let env_fp = sha256_hex(
             format!(
-                "openai_api_key={};slack_bot_token={};slack_allow_channels={};brave_search_api_key={};web_allow_domains={};web_deny_domains={};codex_home={};browser_enabled={};browser_cdp_url={};browser_cdp_port={};browser_profile_name={};browser_home={};browser_novnc_enabled={};browser_novnc_url={};browser_novnc_port={}",
+                "openai_api_key={};slack_bot_token={};slack_allow_channels={};slack_team_id={};brave_search_api_key={};web_allow_domains={};web_deny_domains={};codex_home={};browser_enabled={};browser_cdp_url={};browser_cdp_port={};browser_profile_name={};browser_home={};browser_novnc_enabled={};browser_novnc_url={};browser_novnc_port={}",
                 openai_api_key.unwrap_or(""),
                 slack_bot_token.unwrap_or(""),
                 slack_allow_channels.unwrap_or(""),
+                slack_team_id.unwrap_or(""),
                 brave_search_api_key.unwrap_or(""),
                 web_allow_domains.unwrap_or(""),
                 web_deny_domains.unwrap_or(""),
@@ -316,6 +332,7 @@ impl CodexManager {
                 openai_api_key,
                 slack_bot_token,
                 slack_allow_channels,
+                slack_team_id,
                 brave_search_api_key,
                 web_allow_domains,
                 web_deny_domains,
@@ -347,6 +364,7 @@ impl CodexManager {
         settings: &Settings,
         cwd: &Path,
     ) -> anyhow::Result<String> {
+        let thread_config = self.thread_config.clone();
         let Some(proc) = self.proc.as_mut() else {
             anyhow::bail!("codex app-server not started");
         };
@@ -369,7 +387,7 @@ impl CodexManager {
             "cwd": cwd.to_string_lossy(),
             "approvalPolicy": approval_policy,
             "sandbox": sandbox_mode,
-            "config": null,
+            "config": thread_config,
             "baseInstructions": null,
             "developerInstructions": null,
             "personality": "pragmatic",
@@ -467,6 +485,8 @@ impl CodexManager {
         let mut agent_message_final: Option<String> = None;
         let mut last_turn_error: Option<String> = None;
         let mut file_change_paths_by_item: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        // Command item id -> approval id, for commands a person approved.
+        let mut approved_commands: HashMap<String, String> = HashMap::new();
         let mut last_cancel_check = Instant::now();
 
         let emit_trace = |trace_tx: Option<&mpsc::UnboundedSender<CodexTurnEvent>>,
@@ -530,10 +550,16 @@ impl CodexManager {
                             "command execution approval requested",
                             &method,
                         );
-                        let resp = crate::approvals::handle_command_execution_request(
-                            state, settings, cwd, task, &params,
-                        )
-                        .await?;
+                        let (resp, approval_id) =
+                            crate::approvals::handle_command_execution_request(
+                                state, settings, cwd, task, &params,
+                            )
+                            .await?;
+                        if let (Some(approval_id), Some(item_id)) =
+                            (approval_id, params.get("itemId").and_then(|v| v.as_str()))
+                        {
+                            approved_commands.insert(item_id.to_string(), approval_id);
+                        }
                         proc.respond(id, resp).await?;
                     }
                     "item/fileChange/requestApproval" => {
@@ -683,6 +709,25 @@ impl CodexManager {
                         continue;
                     }
                     let item_type = item.get("type").and_then(|v| v.as_str()).unwrap_or("");
+                    if item_type == "commandExecution" {
+                        let approval_id = item
+                            .get("id")
+                            .and_then(|v| v.as_str())
+                            .and_then(|item_id| approved_commands.remove(item_id));
+                        if let Some(approval_id) = approval_id {
+                            if let Err(err) = crate::approvals::report_command_output(
+                                state,
+                                settings,
+                                task,
+                                &approval_id,
+                                &item,
+                            )
+                            .await
+                            {
+                                warn!(error = %err, approval_id = %approval_id, "failed to report approved command output");
+                            }
+                        }
+                    }
                     if item_type == "agentMessage" {
                         if let Some(item_id) = item.get("id").and_then(|v| v.as_str()) {
                             agent_message_item_id = Some(item_id.to_string());
@@ -744,47 +789,93 @@ impl CodexManager {
             agent_message_text: agent_message,
         })
     }
+}
 
-    fn render_codex_config(
-        &self,
-        allow_slack_mcp: bool,
-        allow_web_mcp: bool,
-        extra_mcp_config: Option<&str>,
-    ) -> String {
-        // Keep this minimal; we rely primarily on per-turn overrides.
-        // Avoid placing secrets in this file.
-        let mut out = String::new();
-        out.push_str("check_for_update_on_startup = false\n");
-
-        if allow_slack_mcp {
-            out.push_str("\n[mcp_servers.slack]\n");
-            out.push_str("command = \"grail-slack-mcp\"\n");
-            out.push_str("args = []\n");
-            out.push_str("env_vars = [\"SLACK_BOT_TOKEN\", \"GRAIL_SLACK_ALLOW_CHANNELS\"]\n");
-            out.push_str("startup_timeout_sec = 10\n");
-            out.push_str("tool_timeout_sec = 30\n");
-        }
+/// Environment the Slack MCP server inherits from the Codex process.
+const SLACK_MCP_ENV_VARS: [&str; 26] = [
+    "SLACK_BOT_TOKEN",
+    "SLACK_USER_TOKEN",
+    "SLACK_REFRESH_TOKEN",
+    "SLACK_CLIENT_ID",
+    "SLACK_CLIENT_SECRET",
+    "GRAIL_SLACK_ALLOW_CHANNELS",
+    "GRAIL_SLACK_ALLOW_CHANNELS_FILE",
+    "GRAIL_SLACK_DENY_CHANNELS",
+    "GRAIL_SLACK_DM_USERS",
+    "GRAIL_SLACK_TEAM_ID",
+    "GRAIL_SLACK_WORKSPACE_TOKENS",
+    "GRAIL_SLACK_WORKSPACE_TOKENS_FILE",
+    "GRAIL_SLACK_ALLOW_WRITES",
+    "GRAIL_SLACK_AUTO_JOIN",
+    "GRAIL_SLACK_TOOLS",
+    "GRAIL_SLACK_CACHE_TTL_SECS",
+    "GRAIL_SLACK_MAX_RETRIES",
+    "GRAIL_SLACK_MAX_CONCURRENCY",
+    "GRAIL_SLACK_TIMEOUT_SECS",
+    "GRAIL_SLACK_TOOL_TIMEOUTS",
+    "GRAIL_SLACK_SUBSCRIBE_POLL_SECS",
+    "SLACK_APP_TOKEN",
+    "GRAIL_SLACK_METRICS_LOG_SECS",
+    "GRAIL_SLACK_AUDIT_LOG",
+    "GRAIL_SLACK_TEXT_CONTENT",
+    "GRAIL_SLACK_API_BASE",
+];
+
+/// Codex `config.toml`. A read-only Slack MCP never sees `GRAIL_SLACK_ALLOW_WRITES`, so its
+/// write tools stay off whatever the server environment says.
+pub fn render_codex_config(
+    allow_slack_mcp: bool,
+    slack_read_only: bool,
+    allow_web_mcp: bool,
+    extra_mcp_config: Option<&str>,
+) -> String {
+    // Keep this minimal; we rely primarily on per-turn overrides.
+    // Avoid placing secrets in this file.
+    let mut out = String::new();
+    out.push_str("check_for_update_on_startup = false\n");
+
+    if allow_slack_mcp {
+        out.push_str("\n[mcp_servers.slack]\n");
+        out.push_str("command = \"grail-slack-mcp\"\n");
+        out.push_str("args = []\n");
+        let env_vars = SLACK_MCP_ENV_VARS
+            .iter()
+            .filter(|v| !(slack_read_only && **v == "GRAIL_SLACK_ALLOW_WRITES"))
+            .map(|v| toml_string(v))
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.push_str(&format!("env_vars = [{env_vars}]\n"));
+        out.push_str("startup_timeout_sec = 10\n");
+        // Above the server's own call budget, which covers rate-limit retries.
+        out.push_str("tool_timeout_sec = 150\n");
+    }
 
-        if allow_web_mcp {
-            out.push_str("\n[mcp_servers.web]\n");
-            out.push_str("command = \"grail-web-mcp\"\n");
-            out.push_str("args = []\n");
-            out.push_str("env_vars = [\"BRAVE_SEARCH_API_KEY\", \"GRAIL_WEB_ALLOW_DOMAINS\", \"GRAIL_WEB_DENY_DOMAINS\"]\n");
-            out.push_str("startup_timeout_sec = 10\n");
-            out.push_str("tool_timeout_sec = 45\n");
-        }
+    if allow_web_mcp {
+        let (command, args) = web_mcp_command();
+        out.push_str("\n[mcp_servers.web]\n");
+        out.push_str(&format!("command = {}\n", toml_string(&command)));
+        out.push_str(&format!(
+            "args = [{}]\n",
+            args.iter()
+                .map(|a| toml_string(a))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        out.push_str("env_vars = [\"BRAVE_SEARCH_API_KEY\", \"GRAIL_WEB_ALLOW_DOMAINS\", \"GRAIL_WEB_DENY_DOMAINS\", \"GRAIL_WEB_QUOTA_FILE\", \"GRAIL_WEB_WATCH_DIR\", \"GRAIL_WEB_SEARCH_FALLBACK\", \"GRAIL_WEB_EXTRACT_RULES\", \"GRAIL_WEB_FETCH_FALLBACKS\", \"GRAIL_WEB_GOOGLEBOT_DOMAINS\", \"GRAIL_WEB_READER_URL\", \"GRAIL_WEB_FETCH_RPS\", \"GRAIL_WEB_FETCH_BURST\", \"GRAIL_WEB_FETCH_CACHE_TTL\", \"GRAIL_WEB_CHROMIUM\", \"GRAIL_WEB_CHROMIUM_ARGS\", \"GRAIL_BRAVE_MONTHLY_QUOTA\"]\n");
+        out.push_str("startup_timeout_sec = 10\n");
+        out.push_str("tool_timeout_sec = 45\n");
+    }
 
-        if let Some(extra) = extra_mcp_config {
-            let extra = extra.trim();
-            if !extra.is_empty() {
-                out.push_str("\n\n# Extra MCP config (from Settings.extra_mcp_config)\n");
-                out.push_str(extra);
-                out.push('\n');
-            }
+    if let Some(extra) = extra_mcp_config {
+        let extra = extra.trim();
+        if !extra.is_empty() {
+            out.push_str("\n\n# Extra MCP config (from Settings.extra_mcp_config)\n");
+            out.push_str(extra);
+            out.push('\n');
         }
-
-        out
     }
+
+    out
 }
 
 async fn spawn_codex_app_server(
@@ -793,6 +884,7 @@ async fn spawn_codex_app_server(
     openai_api_key: Option<&str>,
     slack_bot_token: Option<&str>,
     slack_allow_channels: Option<&str>,
+    slack_team_id: Option<&str>,
     brave_search_api_key: Option<&str>,
     web_allow_domains: Option<&str>,
     web_deny_domains: Option<&str>,
@@ -815,6 +907,7 @@ async fn spawn_codex_app_server(
             openai_api_key,
             slack_bot_token,
             slack_allow_channels,
+            slack_team_id,
             brave_search_api_key,
             web_allow_domains,
             web_deny_domains,
@@ -832,6 +925,33 @@ async fn spawn_codex_app_server(
     anyhow::bail!("failed to start codex app-server after trying compatible arg variants")
 }
 
+fn toml_string(s: &str) -> String {
+    toml::Value::String(s.to_string()).to_string()
+}
+
+fn on_path(bin: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(bin).is_file()))
+}
+
+/// The `grail-web-mcp` sidecar when installed; otherwise this binary's built-in
+/// `web-mcp` subcommand (same tools, via the shared `grail-web` crate).
+fn web_mcp_command() -> (String, Vec<String>) {
+    if !on_path("grail-web-mcp") {
+        if let Ok(exe) = std::env::current_exe() {
+            return (
+                exe.to_string_lossy().into_owned(),
+                vec![crate::WEB_MCP_SUBCOMMAND.to_string()],
+            );
+        }
+    }
+    ("grail-web-mcp".to_string(), Vec::new())
+}
+
+pub fn brave_quota_path(codex_home: &Path) -> PathBuf {
+    codex_home.join("brave_quota.json")
+}
+
 async fn spawn_codex_with_args(
     codex_bin: &str,
     args: &[&str],
@@ -839,6 +959,7 @@ async fn spawn_codex_with_args(
     openai_api_key: Option<&str>,
     slack_bot_token: Option<&str>,
     slack_allow_channels: Option<&str>,
+    slack_team_id: Option<&str>,
     brave_search_api_key: Option<&str>,
     web_allow_domains: Option<&str>,
     web_deny_domains: Option<&str>,
@@ -869,6 +990,9 @@ async fn spawn_codex_with_args(
     if let Some(v) = slack_allow_channels {
         cmd.env("GRAIL_SLACK_ALLOW_CHANNELS", v);
     }
+    if let Some(v) = slack_team_id {
+        cmd.env("GRAIL_SLACK_TEAM_ID", v);
+    }
     if let Some(k) = brave_search_api_key {
         cmd.env("BRAVE_SEARCH_API_KEY", k);
     }
@@ -878,6 +1002,10 @@ async fn spawn_codex_with_args(
     if let Some(v) = web_deny_domains {
         cmd.env("GRAIL_WEB_DENY_DOMAINS", v);
     }
+    // Shared Brave quota state, written by grail-web-mcp and shown on /admin/status.
+    cmd.env("GRAIL_WEB_QUOTA_FILE", brave_quota_path(codex_home));
+    // web_watch_diff snapshots persist across tasks (and restarts) next to it.
+    cmd.env("GRAIL_WEB_WATCH_DIR", codex_home.join("web_watch"));
     if browser.enabled {
         cmd.env("GRAIL_BROWSER_ENABLED", "1");
         cmd.env("OPENCLAW_BROWSER_ENABLED", "1");
@@ -1210,3 +1338,20 @@ impl JsonMerge for serde_json::Value {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_slack_mcp_never_gets_write_access() {
+        let normal = render_codex_config(true, false, false, None);
+        let read_only = render_codex_config(true, true, false, None);
+        for cfg in [&normal, &read_only] {
+            toml::from_str::<toml::Value>(cfg).unwrap();
+            assert!(cfg.contains("\"SLACK_BOT_TOKEN\""));
+        }
+        assert!(normal.contains("\"GRAIL_SLACK_ALLOW_WRITES\""));
+        assert!(!read_only.contains("GRAIL_SLACK_ALLOW_WRITES"));
+    }
+}
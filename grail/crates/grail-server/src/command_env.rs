@@ -0,0 +1,249 @@
+//! Environment for sandboxed command execution.
+//!
+//! Commands the agent runs used to inherit whatever Codex inherited from the server. The
+//! `command_env` setting replaces that with an explicit policy: how much of the base
+//! environment to inherit, plus admin-managed variables and named secrets from the
+//! encrypted secret store, per workspace and per repository. Secrets never come from the
+//! server's own environment, which holds Grail's credentials. Guardrail rules of kind `env` (matched against the
+//! variable name) decide which names the agent may read; `deny` and `require_approval`
+//! both drop the variable, since there is no one to approve an environment lookup.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::Context;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::guardrails::Decision;
+use crate::models::GuardrailRule;
+
+static ENV_NAME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").unwrap());
+
+/// How much of the server's environment sandboxed commands start from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Inherit {
+    /// Everything (minus Codex's default `*KEY*`/`*SECRET*`/`*TOKEN*` excludes).
+    All,
+    /// Only basics such as `HOME`, `PATH`, `SHELL`, `USER`, `TMPDIR`.
+    #[default]
+    Core,
+    None,
+}
+
+impl Inherit {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Inherit::All => "all",
+            Inherit::Core => "core",
+            Inherit::None => "none",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnvScope {
+    /// Non-secret values, set as-is.
+    pub vars: BTreeMap<String, String>,
+    /// Names of stored secrets (`custom.NAME`), set under the same name.
+    pub secrets: Vec<String>,
+}
+
+/// Settings.command_env. Repository keys are `owner/repo` (matched against repos the
+/// task mentions); workspace and repository scopes add to and override the defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandEnvConfig {
+    pub inherit: Inherit,
+    pub vars: BTreeMap<String, String>,
+    pub secrets: Vec<String>,
+    pub workspaces: HashMap<String, EnvScope>,
+    pub repos: HashMap<String, EnvScope>,
+}
+
+pub fn parse_command_env(raw: &str) -> anyhow::Result<CommandEnvConfig> {
+    if raw.trim().is_empty() {
+        return Ok(CommandEnvConfig::default());
+    }
+    let cfg: CommandEnvConfig = serde_json::from_str(raw).context("parse command_env JSON")?;
+    let base = EnvScope {
+        vars: cfg.vars.clone(),
+        secrets: cfg.secrets.clone(),
+    };
+    let scopes = std::iter::once(("default".to_string(), &base))
+        .chain(
+            cfg.workspaces
+                .iter()
+                .map(|(k, v)| (format!("workspaces.{k}"), v)),
+        )
+        .chain(cfg.repos.iter().map(|(k, v)| (format!("repos.{k}"), v)));
+    for (scope, s) in scopes {
+        for name in s.vars.keys().chain(s.secrets.iter()) {
+            anyhow::ensure!(
+                ENV_NAME_RE.is_match(name),
+                "command_env.{scope}: invalid variable name {name:?}"
+            );
+        }
+    }
+    Ok(cfg)
+}
+
+/// The environment policy for one task.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolvedEnv {
+    pub inherit: Inherit,
+    /// Variables to set, after guardrails.
+    pub set: BTreeMap<String, String>,
+    /// Inherited names removed by guardrails.
+    pub exclude: Vec<String>,
+    /// Configured names dropped by guardrails or missing from the server environment.
+    pub dropped: Vec<String>,
+}
+
+impl ResolvedEnv {
+    /// Codex `shell_environment_policy` thread config.
+    pub fn to_codex_config(&self) -> serde_json::Value {
+        json!({
+            "shell_environment_policy": {
+                "inherit": self.inherit.as_str(),
+                "exclude": self.exclude,
+                "set": self.set,
+            }
+        })
+    }
+}
+
+/// First matching `env` rule wins (rules are ordered by priority); no match allows.
+pub fn env_name_allowed(rules: &[GuardrailRule], name: &str) -> bool {
+    for r in rules.iter().filter(|r| r.enabled && r.kind == "env") {
+        if crate::guardrails::rule_matches(r, name).unwrap_or(false) {
+            return crate::guardrails::decision_from_action(&r.action) == Decision::Allow;
+        }
+    }
+    true
+}
+
+/// Every secret name a task in `workspace_id` touching `repos` could be given.
+pub fn secret_names(
+    cfg: &CommandEnvConfig,
+    workspace_id: &str,
+    repos: &[String],
+) -> BTreeSet<String> {
+    let mut names: BTreeSet<String> = cfg.secrets.iter().cloned().collect();
+    let scopes = cfg
+        .workspaces
+        .get(workspace_id.trim())
+        .into_iter()
+        .chain(repos.iter().filter_map(|r| cfg.repos.get(r.as_str())));
+    for scope in scopes {
+        names.extend(scope.secrets.iter().cloned());
+    }
+    names
+}
+
+/// Merge default, workspace and repository scopes and apply `env` guardrails.
+/// `lookup` reads a stored secret; `inherited` lists the names the server environment
+/// would pass on when `inherit` is `all`.
+pub fn resolve(
+    cfg: &CommandEnvConfig,
+    workspace_id: &str,
+    repos: &[String],
+    rules: &[GuardrailRule],
+    lookup: impl Fn(&str) -> Option<String>,
+    inherited: impl IntoIterator<Item = String>,
+) -> ResolvedEnv {
+    let mut vars = cfg.vars.clone();
+    let scopes = cfg
+        .workspaces
+        .get(workspace_id.trim())
+        .into_iter()
+        .chain(repos.iter().filter_map(|r| cfg.repos.get(r.as_str())));
+    for scope in scopes {
+        vars.extend(scope.vars.clone());
+    }
+
+    let mut out = ResolvedEnv {
+        inherit: cfg.inherit,
+        ..Default::default()
+    };
+    for (name, value) in vars {
+        if env_name_allowed(rules, &name) {
+            out.set.insert(name, value);
+        } else {
+            out.dropped.push(name);
+        }
+    }
+    for name in secret_names(cfg, workspace_id, repos) {
+        match lookup(&name) {
+            Some(value) if env_name_allowed(rules, &name) => {
+                out.set.insert(name, value);
+            }
+            _ => out.dropped.push(name),
+        }
+    }
+    if cfg.inherit == Inherit::All {
+        out.exclude = inherited
+            .into_iter()
+            .filter(|name| !out.set.contains_key(name) && !env_name_allowed(rules, name))
+            .collect();
+        out.exclude.sort();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_env_merges_scopes_and_applies_env_guardrails() {
+        let cfg = parse_command_env(
+            r#"{"inherit": "all", "vars": {"TZ": "UTC", "STAGE": "dev"}, "secrets": ["NPM_TOKEN"],
+                "workspaces": {"T1": {"vars": {"STAGE": "prod"}}},
+                "repos": {"acme/api": {"secrets": ["API_TOKEN"]}}}"#,
+        )
+        .unwrap();
+        let rule = |pattern: &str, action: &str| crate::models::GuardrailRule {
+            id: format!("gr_{pattern}"),
+            name: pattern.to_string(),
+            kind: "env".to_string(),
+            pattern_kind: "regex".to_string(),
+            pattern: pattern.to_string(),
+            action: action.to_string(),
+            priority: 0,
+            enabled: true,
+            created_at: 0,
+            updated_at: 0,
+        };
+        let rules = vec![rule("^NPM_TOKEN$", "allow"), rule("(TOKEN|SECRET)", "deny")];
+        let lookup = |name: &str| (name != "MISSING").then(|| format!("v-{name}"));
+        let env = resolve(
+            &cfg,
+            "T1",
+            &["acme/api".to_string()],
+            &rules,
+            lookup,
+            ["PATH".to_string(), "SLACK_SIGNING_SECRET".to_string()],
+        );
+        assert_eq!(env.inherit, Inherit::All);
+        assert_eq!(env.set.get("STAGE").map(String::as_str), Some("prod"));
+        assert_eq!(
+            env.set.get("NPM_TOKEN").map(String::as_str),
+            Some("v-NPM_TOKEN")
+        );
+        assert!(!env.set.contains_key("API_TOKEN"));
+        assert_eq!(env.dropped, vec!["API_TOKEN".to_string()]);
+        assert_eq!(env.exclude, vec!["SLACK_SIGNING_SECRET".to_string()]);
+        assert_eq!(
+            secret_names(&cfg, "T1", &["acme/api".to_string()]),
+            ["API_TOKEN".to_string(), "NPM_TOKEN".to_string()].into()
+        );
+        assert_eq!(secret_names(&cfg, "T2", &[]).len(), 1);
+
+        assert!(parse_command_env(r#"{"vars": {"BAD-NAME": "x"}}"#).is_err());
+        assert!(parse_command_env(r#"{"inherit": "some"}"#).is_err());
+    }
+}
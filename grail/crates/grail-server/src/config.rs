@@ -75,6 +75,10 @@ pub struct Config {
     /// Each worker slot maintains its own Codex app-server subprocess.
     #[arg(long, env = "GRAIL_WORKER_CONCURRENCY", default_value = "2")]
     pub worker_concurrency: usize,
+
+    /// Bearer token for `/api/v1/scale-hint`. The endpoint is not served when unset.
+    #[arg(long, env = "GRAIL_SCALE_HINT_TOKEN")]
+    pub scale_hint_token: Option<String>,
 }
 
 impl Config {
@@ -0,0 +1,674 @@
+//! Configuration bundles: everything an admin configures, as one YAML document.
+//!
+//! A bundle holds settings (including per-channel policies such as context sources and
+//! reply language), guardrail rules, cron jobs and saved prompts. Secrets, the Slack
+//! workspace binding and run history are never included. Importing replaces each section
+//! present in the bundle: items are matched by id (saved prompts by name), missing ones
+//! are removed and omitted sections are left alone. Everything is validated before
+//! anything is written, and the same plan is shown as a preview first.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::models::{CronJob, GuardrailRule, SavedPrompt};
+use crate::AppState;
+
+pub const VERSION: u32 = 1;
+const LIST_LIMIT: i64 = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GuardrailRuleSpec {
+    pub id: String,
+    pub name: String,
+    pub kind: String,
+    pub pattern_kind: String,
+    pub pattern: String,
+    pub action: String,
+    #[serde(default)]
+    pub priority: i64,
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CronJobSpec {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+    #[serde(default = "agent_mode")]
+    pub mode: String,
+    pub schedule_kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every_seconds: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron_expr: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at_ts: Option<i64>,
+    /// Slack team id, or the provider name for other providers (`telegram`). Bundles
+    /// without it use this instance's Slack workspace.
+    #[serde(default)]
+    pub workspace_id: String,
+    pub channel_id: String,
+    #[serde(default)]
+    pub thread_ts: String,
+    pub prompt_text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SavedPromptSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub template: String,
+    #[serde(default = "enabled_default")]
+    pub enabled: bool,
+}
+
+fn enabled_default() -> bool {
+    true
+}
+
+fn agent_mode() -> String {
+    "agent".to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigBundle {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exported_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<BTreeMap<String, Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guardrail_rules: Option<Vec<GuardrailRuleSpec>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cron_jobs: Option<Vec<CronJobSpec>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved_prompts: Option<Vec<SavedPromptSpec>>,
+}
+
+impl From<&GuardrailRule> for GuardrailRuleSpec {
+    fn from(r: &GuardrailRule) -> Self {
+        Self {
+            id: r.id.clone(),
+            name: r.name.clone(),
+            kind: r.kind.clone(),
+            pattern_kind: r.pattern_kind.clone(),
+            pattern: r.pattern.clone(),
+            action: r.action.clone(),
+            priority: r.priority,
+            enabled: r.enabled,
+        }
+    }
+}
+
+impl From<&CronJob> for CronJobSpec {
+    fn from(j: &CronJob) -> Self {
+        Self {
+            id: j.id.clone(),
+            name: j.name.clone(),
+            enabled: j.enabled,
+            mode: j.mode.clone(),
+            schedule_kind: j.schedule_kind.clone(),
+            every_seconds: j.every_seconds,
+            cron_expr: j.cron_expr.clone(),
+            at_ts: j.at_ts,
+            workspace_id: j.workspace_id.clone(),
+            channel_id: j.channel_id.clone(),
+            thread_ts: j.thread_ts.clone(),
+            prompt_text: j.prompt_text.clone(),
+        }
+    }
+}
+
+impl From<&SavedPrompt> for SavedPromptSpec {
+    fn from(p: &SavedPrompt) -> Self {
+        Self {
+            name: p.name.clone(),
+            description: p.description.clone(),
+            template: p.template.clone(),
+            enabled: p.enabled,
+        }
+    }
+}
+
+pub async fn export(state: &AppState) -> anyhow::Result<ConfigBundle> {
+    let settings = crate::db::get_settings(&state.pool).await?;
+    let settings = match crate::api::settings_json(&settings) {
+        Value::Object(m) => m.into_iter().collect(),
+        _ => BTreeMap::new(),
+    };
+    let rules = crate::db::list_guardrail_rules(&state.pool, None, LIST_LIMIT).await?;
+    let jobs = crate::db::list_cron_jobs(&state.pool, LIST_LIMIT).await?;
+    let prompts = crate::db::list_saved_prompts(&state.pool).await?;
+    Ok(ConfigBundle {
+        version: VERSION,
+        exported_at: Some(chrono::Utc::now().to_rfc3339()),
+        settings: Some(settings),
+        guardrail_rules: Some(rules.iter().map(Into::into).collect()),
+        cron_jobs: Some(jobs.iter().map(Into::into).collect()),
+        saved_prompts: Some(prompts.iter().map(Into::into).collect()),
+    })
+}
+
+pub fn to_yaml(bundle: &ConfigBundle) -> anyhow::Result<String> {
+    let body = serde_yaml::to_string(bundle).context("serialize config bundle")?;
+    Ok(format!(
+        "# FastClaw configuration bundle. Secrets are not included.\n{body}"
+    ))
+}
+
+pub fn parse(yaml: &str) -> anyhow::Result<ConfigBundle> {
+    let bundle: ConfigBundle = serde_yaml::from_str(yaml).context("parse config bundle YAML")?;
+    anyhow::ensure!(
+        bundle.version == VERSION,
+        "unsupported bundle version {} (expected {VERSION})",
+        bundle.version
+    );
+    Ok(bundle)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub section: &'static str,
+    /// Setting key, or the item's id (name for saved prompts).
+    pub key: String,
+    /// `add`, `change` or `remove`.
+    pub action: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportPlan {
+    pub changes: Vec<Change>,
+    /// Setting keys this instance doesn't know (e.g. from a newer version); skipped.
+    pub ignored: Vec<String>,
+    /// Validation errors; the bundle can't be imported while there are any.
+    pub errors: Vec<String>,
+}
+
+/// Adds, changes and removals going from `current` to `incoming`, keyed by `key`.
+pub fn diff_items<T: Serialize + PartialEq>(
+    section: &'static str,
+    current: &[T],
+    incoming: &[T],
+    key: impl Fn(&T) -> String,
+) -> Vec<Change> {
+    let json = |v: &T| serde_json::to_value(v).ok();
+    let mut out = Vec::new();
+    for item in incoming {
+        match current.iter().find(|c| key(c) == key(item)) {
+            None => out.push(Change {
+                section,
+                key: key(item),
+                action: "add",
+                from: None,
+                to: json(item),
+            }),
+            Some(cur) if cur != item => out.push(Change {
+                section,
+                key: key(item),
+                action: "change",
+                from: json(cur),
+                to: json(item),
+            }),
+            Some(_) => {}
+        }
+    }
+    for cur in current {
+        if !incoming.iter().any(|i| key(i) == key(cur)) {
+            out.push(Change {
+                section,
+                key: key(cur),
+                action: "remove",
+                from: json(cur),
+                to: None,
+            });
+        }
+    }
+    out
+}
+
+fn duplicates<'a>(keys: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut dups: Vec<String> = keys
+        .filter(|k| !seen.insert(*k))
+        .map(str::to_string)
+        .collect();
+    dups.dedup();
+    dups
+}
+
+pub fn to_rule(spec: &GuardrailRuleSpec) -> GuardrailRule {
+    GuardrailRule {
+        id: spec.id.trim().to_string(),
+        name: spec.name.trim().to_string(),
+        kind: spec.kind.trim().to_string(),
+        pattern_kind: spec.pattern_kind.trim().to_string(),
+        pattern: spec.pattern.trim().to_string(),
+        action: spec.action.trim().to_string(),
+        priority: spec.priority.clamp(-10_000, 10_000),
+        enabled: spec.enabled,
+        created_at: 0,
+        updated_at: 0,
+    }
+}
+
+/// `default_workspace` is used for specs that don't name a workspace.
+fn to_job(spec: &CronJobSpec, default_workspace: &str) -> anyhow::Result<CronJob> {
+    let workspace_id = match spec.workspace_id.trim() {
+        "" => default_workspace.trim(),
+        w => w,
+    };
+    anyhow::ensure!(
+        !workspace_id.is_empty(),
+        "workspace_id is not set on the job or on this instance"
+    );
+    anyhow::ensure!(!spec.name.trim().is_empty(), "name is required");
+    anyhow::ensure!(!spec.channel_id.trim().is_empty(), "channel_id is required");
+    anyhow::ensure!(
+        !spec.prompt_text.trim().is_empty(),
+        "prompt_text is required"
+    );
+    anyhow::ensure!(
+        matches!(spec.mode.as_str(), "agent" | "message"),
+        "mode must be agent or message"
+    );
+    let mut job = CronJob {
+        id: spec.id.trim().to_string(),
+        name: spec.name.trim().to_string(),
+        enabled: spec.enabled,
+        mode: spec.mode.clone(),
+        schedule_kind: spec.schedule_kind.trim().to_string(),
+        every_seconds: spec.every_seconds,
+        cron_expr: spec
+            .cron_expr
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string),
+        at_ts: spec.at_ts,
+        workspace_id: workspace_id.to_string(),
+        channel_id: spec.channel_id.trim().to_string(),
+        thread_ts: spec.thread_ts.trim().to_string(),
+        prompt_text: spec.prompt_text.trim().to_string(),
+        created_by_user_id: None,
+        next_run_at: None,
+        last_run_at: None,
+        last_status: None,
+        last_error: None,
+        created_at: 0,
+        updated_at: 0,
+    };
+    job.next_run_at = crate::worker::compute_next_run_at(&job, chrono::Utc::now())?;
+    Ok(job)
+}
+
+/// Validate `bundle` against this instance and list what importing it would change.
+pub async fn plan(state: &AppState, bundle: &ConfigBundle) -> anyhow::Result<ImportPlan> {
+    let mut plan = ImportPlan::default();
+
+    if let Some(incoming) = &bundle.settings {
+        let current = crate::db::get_settings(&state.pool).await?;
+        let before = crate::api::settings_json(&current);
+        plan.ignored = incoming
+            .keys()
+            .filter(|k| before.get(k.as_str()).is_none())
+            .cloned()
+            .collect();
+        let form: Result<crate::api::ApiSettingsPost, _> =
+            serde_json::from_value(Value::Object(incoming.clone().into_iter().collect()));
+        let mut updated = current.clone();
+        match form
+            .map_err(anyhow::Error::from)
+            .and_then(|f| crate::api::apply_settings_form(&mut updated, f))
+        {
+            Ok(()) => {
+                let after = crate::api::settings_json(&updated);
+                for key in incoming.keys() {
+                    let (Some(from), Some(to)) = (before.get(key), after.get(key)) else {
+                        continue;
+                    };
+                    if from != to {
+                        plan.changes.push(Change {
+                            section: "settings",
+                            key: key.clone(),
+                            action: "change",
+                            from: Some(from.clone()),
+                            to: Some(to.clone()),
+                        });
+                    }
+                }
+            }
+            Err(err) => plan.errors.push(format!("settings: {err:#}")),
+        }
+    }
+
+    if let Some(incoming) = &bundle.guardrail_rules {
+        for id in duplicates(incoming.iter().map(|r| r.id.as_str())) {
+            plan.errors
+                .push(format!("guardrail_rules: duplicate id {id}"));
+        }
+        for spec in incoming {
+            if let Err(err) = crate::guardrails::validate_rule(&to_rule(spec)) {
+                plan.errors
+                    .push(format!("guardrail_rules.{}: {err:#}", spec.id));
+            }
+        }
+        let current: Vec<GuardrailRuleSpec> =
+            crate::db::list_guardrail_rules(&state.pool, None, LIST_LIMIT)
+                .await?
+                .iter()
+                .map(Into::into)
+                .collect();
+        plan.changes
+            .extend(diff_items("guardrail_rules", &current, incoming, |r| {
+                r.id.clone()
+            }));
+    }
+
+    if let Some(incoming) = &bundle.cron_jobs {
+        for id in duplicates(incoming.iter().map(|j| j.id.as_str())) {
+            plan.errors.push(format!("cron_jobs: duplicate id {id}"));
+        }
+        let default_workspace = crate::db::get_settings(&state.pool)
+            .await?
+            .workspace_id
+            .unwrap_or_default();
+        for spec in incoming {
+            if spec.id.trim().is_empty() {
+                plan.errors.push("cron_jobs: id is required".to_string());
+            } else if let Err(err) = to_job(spec, &default_workspace) {
+                plan.errors.push(format!("cron_jobs.{}: {err:#}", spec.id));
+            }
+        }
+        // Compare against the workspace each job will actually be stored with.
+        let incoming: Vec<CronJobSpec> = incoming
+            .iter()
+            .map(|spec| CronJobSpec {
+                workspace_id: match spec.workspace_id.trim() {
+                    "" => default_workspace.trim().to_string(),
+                    w => w.to_string(),
+                },
+                ..spec.clone()
+            })
+            .collect();
+        let current: Vec<CronJobSpec> = crate::db::list_cron_jobs(&state.pool, LIST_LIMIT)
+            .await?
+            .iter()
+            .map(Into::into)
+            .collect();
+        plan.changes
+            .extend(diff_items("cron_jobs", &current, &incoming, |j| {
+                j.id.clone()
+            }));
+    }
+
+    if let Some(incoming) = &bundle.saved_prompts {
+        for name in duplicates(incoming.iter().map(|p| p.name.as_str())) {
+            plan.errors
+                .push(format!("saved_prompts: duplicate name {name}"));
+        }
+        for spec in incoming {
+            if let Err(err) = crate::saved_prompts::validate_name(&spec.name) {
+                plan.errors
+                    .push(format!("saved_prompts.{}: {err:#}", spec.name));
+            } else if spec.template.trim().is_empty() {
+                plan.errors
+                    .push(format!("saved_prompts.{}: template is required", spec.name));
+            }
+        }
+        let current: Vec<SavedPromptSpec> = crate::db::list_saved_prompts(&state.pool)
+            .await?
+            .iter()
+            .map(Into::into)
+            .collect();
+        plan.changes
+            .extend(diff_items("saved_prompts", &current, incoming, |p| {
+                p.name.trim().to_ascii_lowercase()
+            }));
+    }
+
+    Ok(plan)
+}
+
+/// Apply `bundle` after validating it, all or nothing. Returns the plan that was applied.
+pub async fn import(state: &AppState, bundle: &ConfigBundle) -> anyhow::Result<ImportPlan> {
+    let plan = plan(state, bundle).await?;
+    if !plan.errors.is_empty() {
+        return Err(crate::errors::bad_request(format!(
+            "config bundle is invalid: {}",
+            plan.errors.join("; ")
+        )));
+    }
+    let pool = &state.pool;
+    let current_prompts = crate::db::list_saved_prompts(pool).await?;
+    let mut tx = pool.begin().await.context("begin tx")?;
+
+    let mut settings = crate::db::get_settings(pool).await?;
+    if let Some(incoming) = &bundle.settings {
+        let form = serde_json::from_value(Value::Object(incoming.clone().into_iter().collect()))?;
+        crate::api::apply_settings_form(&mut settings, form)?;
+        crate::db::update_settings(&mut *tx, &settings).await?;
+    }
+
+    let removed = |section: &str| -> Vec<String> {
+        plan.changes
+            .iter()
+            .filter(|c| c.section == section && c.action == "remove")
+            .map(|c| c.key.clone())
+            .collect()
+    };
+
+    if let Some(incoming) = &bundle.guardrail_rules {
+        for id in removed("guardrail_rules") {
+            crate::db::delete_guardrail_rule(&mut *tx, &id).await?;
+        }
+        for spec in incoming {
+            crate::db::upsert_guardrail_rule(&mut *tx, &to_rule(spec)).await?;
+        }
+    }
+
+    if let Some(incoming) = &bundle.cron_jobs {
+        let default_workspace = settings.workspace_id.clone().unwrap_or_default();
+        for id in removed("cron_jobs") {
+            crate::db::delete_cron_job(&mut *tx, &id).await?;
+        }
+        for spec in incoming {
+            crate::db::upsert_cron_job(&mut *tx, &to_job(spec, &default_workspace)?).await?;
+        }
+    }
+
+    if let Some(incoming) = &bundle.saved_prompts {
+        for name in removed("saved_prompts") {
+            if let Some(p) = current_prompts.iter().find(|p| p.name == name) {
+                crate::db::delete_saved_prompt(&mut *tx, &p.id).await?;
+            }
+        }
+        let now = chrono::Utc::now().timestamp();
+        for spec in incoming {
+            let prompt = SavedPrompt {
+                id: crate::random_id("sp"),
+                name: crate::saved_prompts::validate_name(&spec.name)?,
+                description: spec.description.trim().to_string(),
+                template: spec.template.trim().to_string(),
+                enabled: spec.enabled,
+                created_at: now,
+                updated_at: now,
+            };
+            crate::db::upsert_saved_prompt(&mut *tx, &prompt).await?;
+        }
+    }
+
+    tx.commit().await.context("commit tx")?;
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(id: &str) -> GuardrailRuleSpec {
+        GuardrailRuleSpec {
+            id: id.to_string(),
+            name: id.to_string(),
+            kind: "command".to_string(),
+            pattern_kind: "exact".to_string(),
+            pattern: format!("{id} --version"),
+            action: "allow".to_string(),
+            priority: 1,
+            enabled: true,
+        }
+    }
+
+    fn job(id: &str, workspace_id: &str) -> CronJobSpec {
+        CronJobSpec {
+            id: id.to_string(),
+            name: id.to_string(),
+            enabled: true,
+            mode: "agent".to_string(),
+            schedule_kind: "every".to_string(),
+            every_seconds: Some(3600),
+            cron_expr: None,
+            at_ts: None,
+            workspace_id: workspace_id.to_string(),
+            channel_id: "12345".to_string(),
+            thread_ts: String::new(),
+            prompt_text: "post the daily summary".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn config_import_writes_are_all_or_nothing() {
+        let db = crate::test_support::TestDb::new().await;
+        let state = db.state();
+        let pool = db.pool.clone();
+        crate::db::upsert_guardrail_rule(&pool, &to_rule(&rule("old")))
+            .await
+            .unwrap();
+        // Saved prompts are written last; make the database refuse one of them.
+        sqlx::query(
+            "CREATE TRIGGER refuse_prompt BEFORE INSERT ON saved_prompts
+             WHEN NEW.name = 'boom' BEGIN SELECT RAISE(ABORT, 'refused'); END",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let snapshot = || async {
+            let rules = crate::db::list_guardrail_rules(&pool, None, LIST_LIMIT)
+                .await
+                .unwrap();
+            let jobs = crate::db::list_cron_jobs(&pool, LIST_LIMIT).await.unwrap();
+            let prompts = crate::db::list_saved_prompts(&pool).await.unwrap();
+            (
+                rules.iter().map(|r| r.id.clone()).collect::<Vec<_>>(),
+                jobs.iter().map(|j| j.id.clone()).collect::<Vec<_>>(),
+                prompts.iter().map(|p| p.name.clone()).collect::<Vec<_>>(),
+            )
+        };
+        let before = snapshot().await;
+
+        let bundle = ConfigBundle {
+            version: VERSION,
+            guardrail_rules: Some(vec![rule("new")]),
+            cron_jobs: Some(vec![job("daily", "telegram")]),
+            saved_prompts: Some(vec![SavedPromptSpec {
+                name: "boom".to_string(),
+                description: String::new(),
+                template: "anything".to_string(),
+                enabled: true,
+            }]),
+            ..Default::default()
+        };
+        assert!(plan(&state, &bundle).await.unwrap().errors.is_empty());
+        let err = import(&state, &bundle).await.unwrap_err();
+        assert!(format!("{err:#}").contains("refused"), "{err:#}");
+        assert_eq!(snapshot().await, before);
+    }
+
+    #[tokio::test]
+    async fn cron_jobs_keep_their_workspace_through_export_and_import() {
+        let db = crate::test_support::TestDb::new().await;
+        let state = db.state();
+        // No Slack workspace is bound: a Telegram-only instance.
+        let telegram = to_job(&job("tg", "telegram"), "").unwrap();
+        crate::db::upsert_cron_job(&db.pool, &telegram)
+            .await
+            .unwrap();
+
+        let bundle = parse(&to_yaml(&export(&state).await.unwrap()).unwrap()).unwrap();
+        assert_eq!(
+            bundle.cron_jobs.as_ref().unwrap()[0].workspace_id,
+            "telegram"
+        );
+        let applied = import(&state, &bundle).await.unwrap();
+        assert!(applied.changes.is_empty(), "{:?}", applied.changes);
+        let stored = crate::db::list_cron_jobs(&db.pool, LIST_LIMIT)
+            .await
+            .unwrap();
+        assert_eq!(stored[0].workspace_id, "telegram");
+
+        // A workspace change shows up in the preview; a job without one needs a bound workspace.
+        let moved = ConfigBundle {
+            version: VERSION,
+            cron_jobs: Some(vec![job("tg", "T1"), job("slack", "")]),
+            ..Default::default()
+        };
+        let preview = plan(&state, &moved).await.unwrap();
+        assert_eq!(preview.changes[0].action, "change");
+        assert_eq!(
+            preview.changes[0].to.as_ref().unwrap()["workspace_id"],
+            "T1"
+        );
+        assert_eq!(
+            preview.errors,
+            vec!["cron_jobs.slack: workspace_id is not set on the job or on this instance"]
+        );
+    }
+
+    #[test]
+    fn config_bundle_round_trips_and_diffs_by_key() {
+        let prompt = |name: &str, template: &str| SavedPromptSpec {
+            name: name.to_string(),
+            description: String::new(),
+            template: template.to_string(),
+            enabled: true,
+        };
+        let bundle = ConfigBundle {
+            version: VERSION,
+            saved_prompts: Some(vec![prompt("standup", "Summarize {channel}")]),
+            ..Default::default()
+        };
+        let parsed = parse(&to_yaml(&bundle).unwrap()).unwrap();
+        assert_eq!(parsed.saved_prompts, bundle.saved_prompts);
+        assert!(parsed.settings.is_none());
+        assert!(parse("version: 99\n").is_err());
+        assert!(parse("version: 1\nsecrets: {}\n").is_err());
+
+        let current = vec![prompt("standup", "old"), prompt("retro", "x")];
+        let incoming = vec![prompt("standup", "new"), prompt("triage", "y")];
+        let actions: Vec<_> = diff_items("saved_prompts", &current, &incoming, |p| p.name.clone())
+            .into_iter()
+            .map(|c| (c.key, c.action))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("standup".to_string(), "change"),
+                ("triage".to_string(), "add"),
+                ("retro".to_string(), "remove"),
+            ]
+        );
+    }
+}
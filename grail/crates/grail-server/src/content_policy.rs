@@ -0,0 +1,220 @@
+//! Banned-topics content policy, checked on incoming prompts and outgoing replies.
+//!
+//! Rules are guardrail rules of kind `topic`, matched against the message text in
+//! priority order: the first match decides, `allow` exempts the text (e.g. a narrower
+//! "HR policy FAQ" exception ahead of a broad HR rule) and anything else blocks it. With
+//! `content_policy_moderation` on, text that passes the rules is also sent to the OpenAI
+//! moderation endpoint when a key is configured. Blocked messages are answered with
+//! `content_policy_message` and recorded in the task trace.
+
+use anyhow::Context;
+use serde_json::json;
+use tracing::warn;
+
+use crate::guardrails::Decision;
+use crate::models::{GuardrailRule, Settings, Task};
+use crate::AppState;
+
+pub const RULE_KIND: &str = "topic";
+pub const DEFAULT_MESSAGE: &str =
+    "Sorry, I can't help with that topic here. Please reach out to the appropriate team directly.";
+const MODERATION_MODEL: &str = "omni-moderation-latest";
+
+/// Which side of the conversation was checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Prompt,
+    Reply,
+}
+
+impl Direction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::Prompt => "prompt",
+            Direction::Reply => "reply",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    Rule { id: String, name: String },
+    Moderation { categories: Vec<String> },
+}
+
+impl Violation {
+    /// Trace details; never includes the offending text.
+    pub fn describe(&self) -> String {
+        match self {
+            Violation::Rule { id, name } => format!("rule {name:?} ({id})"),
+            Violation::Moderation { categories } => {
+                format!("moderation: {}", categories.join(", "))
+            }
+        }
+    }
+}
+
+/// The first enabled `topic` rule matching `text`, in priority order.
+pub fn first_match<'a>(rules: &'a [GuardrailRule], text: &str) -> Option<&'a GuardrailRule> {
+    rules
+        .iter()
+        .filter(|r| r.enabled && r.kind == RULE_KIND)
+        .find(|r| match crate::guardrails::rule_matches(r, text) {
+            Ok(m) => m,
+            Err(err) => {
+                warn!(error = %err, rule_id = %r.id, "invalid topic rule; skipping");
+                false
+            }
+        })
+}
+
+/// A topic match's decision; no match lets the text through.
+fn rule_decision(rule: Option<&GuardrailRule>) -> Decision {
+    rule.map_or(Decision::Allow, |r| {
+        crate::guardrails::decision_from_action(&r.action)
+    })
+}
+
+pub fn decline_message(settings: &Settings) -> String {
+    let msg = settings.content_policy_message.trim();
+    if msg.is_empty() {
+        DEFAULT_MESSAGE.to_string()
+    } else {
+        msg.to_string()
+    }
+}
+
+/// Flagged categories from a moderation response, or `None` when not flagged.
+pub fn flagged_categories(v: &serde_json::Value) -> Option<Vec<String>> {
+    let result = v.pointer("/results/0")?;
+    if !result.get("flagged")?.as_bool()? {
+        return None;
+    }
+    let mut categories: Vec<String> = result
+        .get("categories")
+        .and_then(|c| c.as_object())
+        .map(|c| {
+            c.iter()
+                .filter(|(_, on)| on.as_bool() == Some(true))
+                .map(|(k, _)| k.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    categories.sort();
+    Some(categories)
+}
+
+async fn moderate(
+    state: &AppState,
+    api_key: &str,
+    text: &str,
+) -> anyhow::Result<Option<Violation>> {
+    let resp = state
+        .http
+        .post("https://api.openai.com/v1/moderations")
+        .bearer_auth(api_key)
+        .json(&json!({ "model": MODERATION_MODEL, "input": text }))
+        .send()
+        .await
+        .context("openai moderation request")?;
+    let status = resp.status();
+    if !status.is_success() {
+        anyhow::bail!("openai moderation failed with status {}", status.as_u16());
+    }
+    let v: serde_json::Value = resp.json().await.context("decode moderation response")?;
+    Ok(flagged_categories(&v).map(|categories| Violation::Moderation { categories }))
+}
+
+/// Check `text` against the topic rules and, when enabled, the moderation API.
+/// Moderation errors are logged and let the text through.
+pub async fn check(
+    state: &AppState,
+    settings: &Settings,
+    task: &Task,
+    text: &str,
+) -> anyhow::Result<Option<Violation>> {
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    let sets = crate::guardrail_canary::rule_sets(state, RULE_KIND, &task.channel_id).await?;
+    let matched = first_match(&sets.applied, text);
+    if let Some(shadow) = &sets.shadow {
+        let other = first_match(shadow, text);
+        crate::guardrail_canary::record(
+            state,
+            task,
+            RULE_KIND,
+            text,
+            &sets,
+            (rule_decision(matched), matched),
+            (rule_decision(other), other),
+        )
+        .await;
+    }
+    if let Some(rule) = matched {
+        if !sets.canary_applied {
+            let _ = crate::db::record_guardrail_match(&state.pool, &rule.id).await;
+        }
+        // An `allow` match exempts the text from moderation too.
+        if crate::guardrails::decision_from_action(&rule.action) == Decision::Allow {
+            return Ok(None);
+        }
+        return Ok(Some(Violation::Rule {
+            id: rule.id.clone(),
+            name: rule.name.clone(),
+        }));
+    }
+    if !settings.content_policy_moderation {
+        return Ok(None);
+    }
+    match crate::secrets::load_openai_api_key_opt(state).await {
+        Ok(Some(key)) => match moderate(state, &key, text).await {
+            Ok(v) => Ok(v),
+            Err(err) => {
+                warn!(error = %err, "content moderation check failed; allowing");
+                Ok(None)
+            }
+        },
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_policy_first_topic_rule_decides() {
+        let rule =
+            |id: &str, kind: &str, pattern: &str, action: &str| crate::models::GuardrailRule {
+                id: id.to_string(),
+                name: id.to_string(),
+                kind: kind.to_string(),
+                pattern_kind: "regex".to_string(),
+                pattern: pattern.to_string(),
+                action: action.to_string(),
+                priority: 0,
+                enabled: true,
+                created_at: 0,
+                updated_at: 0,
+            };
+        let rules = vec![
+            rule("cmd", "command", "salary", "deny"),
+            rule("faq", "topic", r"(?i)\bPTO policy\b", "allow"),
+            rule("hr", "topic", r"(?i)\b(salary|PTO|termination)\b", "deny"),
+        ];
+        let hit = |text: &str| first_match(&rules, text).map(|r| r.id.as_str());
+        assert_eq!(hit("What is Dana's salary?"), Some("hr"));
+        assert_eq!(hit("Where is the PTO policy doc?"), Some("faq"));
+        assert_eq!(hit("Deploy the API"), None);
+
+        let flagged = serde_json::json!({"results": [{"flagged": true,
+            "categories": {"violence": false, "harassment": true, "hate": true}}]});
+        assert_eq!(
+            flagged_categories(&flagged),
+            Some(vec!["harassment".to_string(), "hate".to_string()])
+        );
+        let clean = serde_json::json!({"results": [{"flagged": false, "categories": {}}]});
+        assert_eq!(flagged_categories(&clean), None);
+    }
+}
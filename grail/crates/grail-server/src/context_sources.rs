@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use serde::Deserialize;
+use tracing::warn;
+
+/// A source of conversation context that can feed a task's prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextSource {
+    ThreadHistory,
+    ChannelHistory,
+    PinnedMessages,
+    LinkedFiles,
+    PriorTasks,
+    /// Pages linked from the prompt, fetched by the worker for backends without tools.
+    LinkedPages,
+}
+
+impl ContextSource {
+    pub const ALL: [ContextSource; 6] = [
+        ContextSource::ThreadHistory,
+        ContextSource::ChannelHistory,
+        ContextSource::PinnedMessages,
+        ContextSource::LinkedFiles,
+        ContextSource::PriorTasks,
+        ContextSource::LinkedPages,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            ContextSource::ThreadHistory => "Thread history (oldest -> newest)",
+            ContextSource::ChannelHistory => "Channel history (oldest -> newest)",
+            ContextSource::PinnedMessages => "Pinned messages",
+            ContextSource::LinkedFiles => "Linked files",
+            ContextSource::PriorTasks => "Prior related tasks (oldest -> newest)",
+            ContextSource::LinkedPages => "Linked web pages",
+        }
+    }
+
+    /// Histories keep the newest content when trimmed; everything else keeps the start.
+    fn keep_tail(self) -> bool {
+        matches!(
+            self,
+            ContextSource::ThreadHistory | ContextSource::ChannelHistory
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SourceOverride {
+    #[serde(default)]
+    enabled: Option<bool>,
+    #[serde(default)]
+    weight: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SourcesOverride {
+    thread_history: Option<SourceOverride>,
+    channel_history: Option<SourceOverride>,
+    pinned_messages: Option<SourceOverride>,
+    linked_files: Option<SourceOverride>,
+    prior_tasks: Option<SourceOverride>,
+    linked_pages: Option<SourceOverride>,
+}
+
+impl SourcesOverride {
+    fn get(&self, source: ContextSource) -> Option<&SourceOverride> {
+        match source {
+            ContextSource::ThreadHistory => self.thread_history.as_ref(),
+            ContextSource::ChannelHistory => self.channel_history.as_ref(),
+            ContextSource::PinnedMessages => self.pinned_messages.as_ref(),
+            ContextSource::LinkedFiles => self.linked_files.as_ref(),
+            ContextSource::PriorTasks => self.prior_tasks.as_ref(),
+            ContextSource::LinkedPages => self.linked_pages.as_ref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ContextSourcesLayer {
+    budget_chars: Option<usize>,
+    channel_history_in_threads: Option<bool>,
+    sources: SourcesOverride,
+}
+
+/// Settings.context_sources: global defaults plus per-channel overrides. The global
+/// fields mirror [`ContextSourcesLayer`]; serde can't combine `flatten` with
+/// `deny_unknown_fields`, so they are spelled out.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContextSourcesConfig {
+    budget_chars: Option<usize>,
+    channel_history_in_threads: Option<bool>,
+    sources: SourcesOverride,
+    channels: HashMap<String, ContextSourcesLayer>,
+}
+
+impl ContextSourcesConfig {
+    fn global(&self) -> ContextSourcesLayer {
+        ContextSourcesLayer {
+            budget_chars: self.budget_chars,
+            channel_history_in_threads: self.channel_history_in_threads,
+            sources: self.sources.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourcePlan {
+    pub enabled: bool,
+    pub weight: f64,
+}
+
+/// The resolved set of sources (and their budget weights) for one task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextPlan {
+    pub budget_chars: usize,
+    /// Also include channel history for tasks that live in a thread.
+    pub channel_history_in_threads: bool,
+    sources: [SourcePlan; 6],
+}
+
+const DEFAULT_BUDGET_CHARS: usize = 24_000;
+const MAX_BUDGET_CHARS: usize = 200_000;
+
+impl Default for ContextPlan {
+    fn default() -> Self {
+        // Mirrors the historical behavior: the thread (or channel) the task came from.
+        let plan = |enabled, weight| SourcePlan { enabled, weight };
+        Self {
+            budget_chars: DEFAULT_BUDGET_CHARS,
+            channel_history_in_threads: false,
+            sources: [
+                plan(true, 3.0),  // thread_history
+                plan(true, 3.0),  // channel_history
+                plan(false, 1.0), // pinned_messages
+                plan(false, 1.0), // linked_files
+                plan(false, 1.0), // prior_tasks
+                plan(true, 1.0),  // linked_pages
+            ],
+        }
+    }
+}
+
+impl ContextPlan {
+    pub fn source(&self, source: ContextSource) -> SourcePlan {
+        self.sources[source as usize]
+    }
+
+    pub fn enabled(&self, source: ContextSource) -> bool {
+        self.source(source).enabled
+    }
+
+    fn apply(&mut self, layer: &ContextSourcesLayer) {
+        if let Some(b) = layer.budget_chars {
+            self.budget_chars = b.clamp(1_000, MAX_BUDGET_CHARS);
+        }
+        if let Some(v) = layer.channel_history_in_threads {
+            self.channel_history_in_threads = v;
+        }
+        for source in ContextSource::ALL {
+            let Some(o) = layer.sources.get(source) else {
+                continue;
+            };
+            let plan = &mut self.sources[source as usize];
+            if let Some(enabled) = o.enabled {
+                plan.enabled = enabled;
+            }
+            if let Some(weight) = o.weight {
+                plan.weight = weight.clamp(0.0, 100.0);
+            }
+        }
+    }
+}
+
+pub fn parse_context_sources(raw: &str) -> anyhow::Result<ContextSourcesConfig> {
+    if raw.trim().is_empty() {
+        return Ok(ContextSourcesConfig::default());
+    }
+    serde_json::from_str(raw).context("parse context_sources JSON")
+}
+
+/// Resolve the context plan for a channel: defaults, then global settings, then the
+/// channel's overrides. Invalid settings fall back to the defaults.
+pub fn resolve_context_plan(raw: &str, channel_id: &str) -> ContextPlan {
+    let mut plan = ContextPlan::default();
+    match parse_context_sources(raw) {
+        Ok(cfg) => {
+            plan.apply(&cfg.global());
+            if let Some(layer) = cfg.channels.get(channel_id.trim()) {
+                plan.apply(layer);
+            }
+        }
+        Err(err) => warn!(error = %err, "invalid context_sources setting; using defaults"),
+    }
+    plan
+}
+
+/// Collects per-source context text and renders it within the plan's budget.
+pub struct ContextBuilder<'a> {
+    plan: &'a ContextPlan,
+    sections: Vec<(ContextSource, String)>,
+}
+
+impl<'a> ContextBuilder<'a> {
+    pub fn new(plan: &'a ContextPlan) -> Self {
+        Self {
+            plan,
+            sections: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, source: ContextSource, text: String) {
+        if self.plan.enabled(source) && !text.trim().is_empty() {
+            self.sections.push((source, text));
+        }
+    }
+
+    /// Split the budget across the sources that produced text, proportionally to their
+    /// weights, and trim each section to its share.
+    pub fn render(self) -> String {
+        let total_weight: f64 = self
+            .sections
+            .iter()
+            .map(|(s, _)| self.plan.source(*s).weight)
+            .sum();
+        let mut out = String::new();
+        for (source, text) in &self.sections {
+            let weight = self.plan.source(*source).weight;
+            if weight <= 0.0 {
+                continue;
+            }
+            let share = if total_weight > 0.0 {
+                ((self.plan.budget_chars as f64) * weight / total_weight) as usize
+            } else {
+                self.plan.budget_chars
+            };
+            let text = if source.keep_tail() {
+                grail_text::truncate_tail(text.trim_end(), share)
+            } else {
+                grail_text::truncate(text.trim_end(), share)
+            };
+            out.push_str(source.title());
+            out.push_str(":\n");
+            out.push_str(&text);
+            out.push_str("\n\n");
+        }
+        out
+    }
+
+    /// Token-budget rendering: sources that need less than their weighted share get all
+    /// they need and the remainder is re-split among the rest, so the budget is packed
+    /// with as many whole messages as fit.
+    pub fn render_tokens(self, budget_tokens: usize) -> String {
+        let needs: Vec<usize> = self
+            .sections
+            .iter()
+            .map(|(_, text)| crate::token_budget::estimate_tokens(text))
+            .collect();
+        let mut alloc = vec![0usize; self.sections.len()];
+        let mut open: Vec<usize> = (0..self.sections.len())
+            .filter(|i| self.plan.source(self.sections[*i].0).weight > 0.0)
+            .collect();
+        // Section titles and "… (N older omitted)" markers come out of the budget too.
+        let overhead: usize = self
+            .sections
+            .iter()
+            .map(|(source, _)| crate::token_budget::estimate_tokens(source.title()) + 8)
+            .sum();
+        let mut remaining = budget_tokens.saturating_sub(overhead);
+        while !open.is_empty() {
+            let weight = |i: usize| self.plan.source(self.sections[i].0).weight;
+            let total: f64 = open.iter().map(|i| weight(*i)).sum();
+            let share = |i: usize| ((remaining as f64) * weight(i) / total) as usize;
+            let (fits, rest): (Vec<usize>, Vec<usize>) =
+                open.iter().partition(|i| needs[**i] <= share(**i));
+            if fits.is_empty() {
+                for i in &rest {
+                    alloc[*i] = share(*i);
+                }
+                break;
+            }
+            for i in fits {
+                alloc[i] = needs[i];
+                remaining -= needs[i];
+            }
+            open = rest;
+        }
+
+        let mut out = String::new();
+        for (i, (source, text)) in self.sections.iter().enumerate() {
+            if alloc[i] == 0 {
+                continue;
+            }
+            let (text, _) =
+                crate::token_budget::pack(text.trim_end(), alloc[i], source.keep_tail());
+            out.push_str(source.title());
+            out.push_str(":\n");
+            out.push_str(text.trim_end());
+            out.push_str("\n\n");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_plan_applies_channel_overrides() {
+        let raw = r#"{
+            "budget_chars": 5000,
+            "sources": {"prior_tasks": {"enabled": true}},
+            "channels": {"C1": {"sources": {"channel_history": {"enabled": false}}}}
+        }"#;
+        let plan = resolve_context_plan(raw, "C1");
+        assert_eq!(plan.budget_chars, 5000);
+        assert!(plan.enabled(ContextSource::PriorTasks));
+        assert!(!plan.enabled(ContextSource::ChannelHistory));
+        assert!(resolve_context_plan(raw, "C2").enabled(ContextSource::ChannelHistory));
+        assert!(parse_context_sources(r#"{"sources":{"dms":{}}}"#).is_err());
+        // Typos at the top level are rejected too, not silently ignored.
+        assert!(parse_context_sources(r#"{"budget_char": 5000}"#).is_err());
+        assert!(parse_context_sources(r#"{"channels": {"C1": {"budget_char": 5000}}}"#).is_err());
+    }
+
+    #[test]
+    fn context_builder_splits_budget_by_weight() {
+        let raw = r#"{"budget_chars": 1000, "sources": {
+            "thread_history": {"weight": 3}, "pinned_messages": {"enabled": true, "weight": 1}
+        }}"#;
+        let plan = resolve_context_plan(raw, "C1");
+        let mut b = ContextBuilder::new(&plan);
+        b.add(
+            ContextSource::ThreadHistory,
+            format!("{}END", "a".repeat(2000)),
+        );
+        b.add(
+            ContextSource::PinnedMessages,
+            format!("START{}", "p".repeat(2000)),
+        );
+        b.add(ContextSource::LinkedFiles, "disabled".to_string());
+        let out = b.render();
+        assert!(out.contains("aEND\n"));
+        assert!(out.contains("START"));
+        assert!(!out.contains("disabled"));
+        assert!(out.matches('a').count() <= 750);
+        assert!(out.matches('p').count() <= 250);
+    }
+}
@@ -3,11 +3,13 @@ use std::time::Duration;
 
 use anyhow::Context;
 use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
-use sqlx::{Row, SqlitePool};
+use sqlx::{Row, SqliteExecutor, SqlitePool};
 
 use crate::models::{
-    Approval, CodexDeviceLogin, CronJob, GithubDeviceLogin, GuardrailRule, ObservationalMemory,
-    PermissionsMode, Session, Settings, Task, TaskTrace, TelegramMessage,
+    Approval, CodexDeviceLogin, CronJob, ElevatedSession, GithubDeviceLogin, GuardrailCanary,
+    GuardrailCanaryDecision, GuardrailRule, ObservationalMemory, PermissionsMode,
+    ResponseCacheEntry, SavedPrompt, Session, Settings, Task, TaskArtifact, TaskClaim, TaskTrace,
+    TelegramMessage, UsageReportChannel, WorkerHeartbeat,
 };
 
 pub async fn init_sqlite(db_path: &Path) -> anyhow::Result<SqlitePool> {
@@ -68,6 +70,30 @@ pub async fn get_settings(pool: &SqlitePool) -> anyhow::Result<Settings> {
           web_allow_domains,
           web_deny_domains,
           github_client_id,
+          context_sources,
+          llm_backends,
+          response_cache_mode,
+          response_cache_ttl_seconds,
+          command_env,
+          slack_long_reply_mode,
+          slack_long_reply_chars,
+          reply_language,
+          task_bump_allow_from,
+          public_status_page,
+          slack_no_retry_after_enqueue,
+          context_token_budget,
+          content_policy_moderation,
+          content_policy_message,
+          approval_output_chars,
+          scale_queue_depth_threshold,
+          scale_wait_seconds_threshold,
+          scale_webhook_url,
+          reply_task_links,
+          usage_report_minutes_per_task,
+          speech_to_text,
+          slack_admin_channel,
+          slack_elevate_allow_from,
+          telegram_elevate_allow_from,
           updated_at
         FROM settings
         WHERE id = 1
@@ -140,11 +166,66 @@ pub async fn get_settings(pool: &SqlitePool) -> anyhow::Result<Settings> {
         github_client_id: row
             .get::<Option<String>, _>("github_client_id")
             .unwrap_or_default(),
+        context_sources: row
+            .get::<Option<String>, _>("context_sources")
+            .unwrap_or_default(),
+        llm_backends: row
+            .get::<Option<String>, _>("llm_backends")
+            .unwrap_or_default(),
+        response_cache_mode: row
+            .get::<Option<String>, _>("response_cache_mode")
+            .unwrap_or_default(),
+        response_cache_ttl_seconds: row.get::<i64, _>("response_cache_ttl_seconds"),
+        command_env: row
+            .get::<Option<String>, _>("command_env")
+            .unwrap_or_default(),
+        slack_long_reply_mode: row
+            .get::<Option<String>, _>("slack_long_reply_mode")
+            .unwrap_or_default(),
+        slack_long_reply_chars: row.get::<i64, _>("slack_long_reply_chars"),
+        reply_language: row
+            .get::<Option<String>, _>("reply_language")
+            .unwrap_or_default(),
+        task_bump_allow_from: row
+            .get::<Option<String>, _>("task_bump_allow_from")
+            .unwrap_or_default(),
+        public_status_page: row.get::<i64, _>("public_status_page") != 0,
+        slack_no_retry_after_enqueue: row.get::<i64, _>("slack_no_retry_after_enqueue") != 0,
+        context_token_budget: row
+            .get::<Option<String>, _>("context_token_budget")
+            .unwrap_or_default(),
+        content_policy_moderation: row.get::<i64, _>("content_policy_moderation") != 0,
+        content_policy_message: row
+            .get::<Option<String>, _>("content_policy_message")
+            .unwrap_or_default(),
+        approval_output_chars: row.get::<i64, _>("approval_output_chars"),
+        scale_queue_depth_threshold: row.get::<i64, _>("scale_queue_depth_threshold"),
+        scale_wait_seconds_threshold: row.get::<i64, _>("scale_wait_seconds_threshold"),
+        scale_webhook_url: row
+            .get::<Option<String>, _>("scale_webhook_url")
+            .unwrap_or_default(),
+        reply_task_links: row.get::<i64, _>("reply_task_links") != 0,
+        usage_report_minutes_per_task: row.get::<i64, _>("usage_report_minutes_per_task"),
+        speech_to_text: row
+            .get::<Option<String>, _>("speech_to_text")
+            .unwrap_or_default(),
+        slack_admin_channel: row
+            .get::<Option<String>, _>("slack_admin_channel")
+            .unwrap_or_default(),
+        slack_elevate_allow_from: row
+            .get::<Option<String>, _>("slack_elevate_allow_from")
+            .unwrap_or_default(),
+        telegram_elevate_allow_from: row
+            .get::<Option<String>, _>("telegram_elevate_allow_from")
+            .unwrap_or_default(),
         updated_at: row.get::<i64, _>("updated_at"),
     })
 }
 
-pub async fn update_settings(pool: &SqlitePool, settings: &Settings) -> anyhow::Result<()> {
+pub async fn update_settings(
+    db: impl SqliteExecutor<'_>,
+    settings: &Settings,
+) -> anyhow::Result<()> {
     sqlx::query(
         r#"
         UPDATE settings
@@ -179,6 +260,30 @@ pub async fn update_settings(pool: &SqlitePool, settings: &Settings) -> anyhow::
             web_allow_domains = ?,
             web_deny_domains = ?,
             github_client_id = ?,
+            context_sources = ?,
+            llm_backends = ?,
+            response_cache_mode = ?,
+            response_cache_ttl_seconds = ?,
+            command_env = ?,
+            slack_long_reply_mode = ?,
+            slack_long_reply_chars = ?,
+            reply_language = ?,
+            task_bump_allow_from = ?,
+            public_status_page = ?,
+            slack_no_retry_after_enqueue = ?,
+            context_token_budget = ?,
+            content_policy_moderation = ?,
+            content_policy_message = ?,
+            approval_output_chars = ?,
+            scale_queue_depth_threshold = ?,
+            scale_wait_seconds_threshold = ?,
+            scale_webhook_url = ?,
+            reply_task_links = ?,
+            usage_report_minutes_per_task = ?,
+            speech_to_text = ?,
+            slack_admin_channel = ?,
+            slack_elevate_allow_from = ?,
+            telegram_elevate_allow_from = ?,
             updated_at = unixepoch()
         WHERE id = 1
         "#,
@@ -222,7 +327,39 @@ pub async fn update_settings(pool: &SqlitePool, settings: &Settings) -> anyhow::
     .bind(settings.web_allow_domains.as_str())
     .bind(settings.web_deny_domains.as_str())
     .bind(settings.github_client_id.as_str())
-    .execute(pool)
+    .bind(settings.context_sources.as_str())
+    .bind(settings.llm_backends.as_str())
+    .bind(settings.response_cache_mode.as_str())
+    .bind(settings.response_cache_ttl_seconds)
+    .bind(settings.command_env.as_str())
+    .bind(settings.slack_long_reply_mode.as_str())
+    .bind(settings.slack_long_reply_chars)
+    .bind(settings.reply_language.as_str())
+    .bind(settings.task_bump_allow_from.as_str())
+    .bind(if settings.public_status_page { 1 } else { 0 })
+    .bind(if settings.slack_no_retry_after_enqueue {
+        1
+    } else {
+        0
+    })
+    .bind(settings.context_token_budget.as_str())
+    .bind(if settings.content_policy_moderation {
+        1
+    } else {
+        0
+    })
+    .bind(settings.content_policy_message.as_str())
+    .bind(settings.approval_output_chars)
+    .bind(settings.scale_queue_depth_threshold)
+    .bind(settings.scale_wait_seconds_threshold)
+    .bind(settings.scale_webhook_url.as_str())
+    .bind(if settings.reply_task_links { 1 } else { 0 })
+    .bind(settings.usage_report_minutes_per_task)
+    .bind(settings.speech_to_text.as_str())
+    .bind(settings.slack_admin_channel.as_str())
+    .bind(settings.slack_elevate_allow_from.as_str())
+    .bind(settings.telegram_elevate_allow_from.as_str())
+    .execute(db)
     .await
     .context("update settings")?;
     Ok(())
@@ -315,6 +452,26 @@ pub async fn try_mark_event_processed(
     Ok(res.rows_affected() == 1)
 }
 
+pub async fn is_event_processed(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    event_id: &str,
+) -> anyhow::Result<bool> {
+    let row = sqlx::query(
+        r#"
+        SELECT 1 AS seen
+        FROM processed_events
+        WHERE workspace_id = ?1 AND event_id = ?2
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(event_id)
+    .fetch_optional(pool)
+    .await
+    .context("check processed event")?;
+    Ok(row.is_some())
+}
+
 pub async fn unmark_event_processed(
     pool: &SqlitePool,
     workspace_id: &str,
@@ -334,45 +491,173 @@ pub async fn unmark_event_processed(
     Ok(())
 }
 
-pub async fn enqueue_task(
-    pool: &SqlitePool,
-    provider: &str,
-    workspace_id: &str,
-    channel_id: &str,
-    thread_ts: &str,
-    event_ts: &str,
-    requested_by_user_id: &str,
-    prompt_text: &str,
-) -> anyhow::Result<i64> {
-    enqueue_task_with_files(
-        pool,
+/// A task to enqueue. Fields left at their default are empty; `on_dependency_failure`
+/// defaults to `fail`.
+#[derive(Debug, Clone)]
+pub struct NewTask<'a> {
+    pub provider: &'a str,
+    pub workspace_id: &'a str,
+    pub channel_id: &'a str,
+    pub thread_ts: &'a str,
+    pub event_ts: &'a str,
+    pub requested_by_user_id: &'a str,
+    pub prompt_text: &'a str,
+    pub files_json: &'a str,
+    pub is_proactive: bool,
+    pub depends_on_task_id: Option<i64>,
+    pub on_dependency_failure: &'a str,
+    pub options_json: &'a str,
+}
+
+impl Default for NewTask<'_> {
+    fn default() -> Self {
+        Self {
+            provider: "",
+            workspace_id: "",
+            channel_id: "",
+            thread_ts: "",
+            event_ts: "",
+            requested_by_user_id: "",
+            prompt_text: "",
+            files_json: "",
+            is_proactive: false,
+            depends_on_task_id: None,
+            on_dependency_failure: "fail",
+            options_json: "",
+        }
+    }
+}
+
+pub async fn enqueue_task(pool: &SqlitePool, task: &NewTask<'_>) -> anyhow::Result<i64> {
+    let on_dependency_failure = task.on_dependency_failure;
+    anyhow::ensure!(
+        matches!(on_dependency_failure, "fail" | "cancel" | "run"),
+        "invalid on_dependency_failure: {on_dependency_failure}"
+    );
+    let conversation_key = compute_conversation_key(
+        task.workspace_id,
+        task.channel_id,
+        task.thread_ts,
+        task.event_ts,
+        task.is_proactive,
+    );
+    let res = sqlx::query(
+        r#"
+        INSERT INTO tasks (
+          provider,
+          status,
+          workspace_id,
+          channel_id,
+          thread_ts,
+          conversation_key,
+          event_ts,
+          requested_by_user_id,
+          prompt_text,
+          files_json,
+          is_proactive,
+          depends_on_task_id,
+          on_dependency_failure,
+          options_json,
+          created_at
+        )
+        VALUES (?1, 'queued', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, unixepoch())
+        "#,
+    )
+    .bind(task.provider)
+    .bind(task.workspace_id)
+    .bind(task.channel_id)
+    .bind(task.thread_ts)
+    .bind(&conversation_key)
+    .bind(task.event_ts)
+    .bind(task.requested_by_user_id)
+    .bind(task.prompt_text)
+    .bind(task.files_json)
+    .bind(if task.is_proactive { 1 } else { 0 })
+    .bind(task.depends_on_task_id)
+    .bind(on_dependency_failure)
+    .bind(task.options_json)
+    .execute(pool)
+    .await
+    .context("insert task")?;
+
+    Ok(res.last_insert_rowid())
+}
+
+/// Enqueue an admin test prompt as if it came from `channel_id`/`requested_by_user_id`.
+/// It gets its own conversation key so it never shares a session with the real thread.
+/// `event_ts` is chosen here; files, dependencies and `is_proactive` don't apply.
+pub async fn enqueue_synthetic_task(pool: &SqlitePool, task: &NewTask<'_>) -> anyhow::Result<i64> {
+    let NewTask {
         provider,
         workspace_id,
         channel_id,
         thread_ts,
-        event_ts,
         requested_by_user_id,
         prompt_text,
-        "",
-        false,
+        options_json,
+        ..
+    } = *task;
+    let now = chrono::Utc::now();
+    // event_ts bounds context fetching: "now" for Slack, the newest message for Telegram.
+    let event_ts = if provider == "telegram" {
+        i64::MAX.to_string()
+    } else {
+        format!("{}.{:06}", now.timestamp(), now.timestamp_subsec_micros())
+    };
+    let conversation_key = format!(
+        "synthetic:{workspace_id}:{channel_id}:{}",
+        now.timestamp_micros()
+    );
+    let res = sqlx::query(
+        r#"
+        INSERT INTO tasks (
+          provider,
+          status,
+          workspace_id,
+          channel_id,
+          thread_ts,
+          conversation_key,
+          event_ts,
+          requested_by_user_id,
+          prompt_text,
+          is_synthetic,
+          options_json,
+          created_at
+        )
+        VALUES (?1, 'queued', ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1, ?9, unixepoch())
+        "#,
     )
+    .bind(provider)
+    .bind(workspace_id)
+    .bind(channel_id)
+    .bind(thread_ts)
+    .bind(&conversation_key)
+    .bind(&event_ts)
+    .bind(requested_by_user_id)
+    .bind(prompt_text)
+    .bind(options_json)
+    .execute(pool)
     .await
+    .context("insert synthetic task")?;
+
+    Ok(res.last_insert_rowid())
 }
 
-pub async fn enqueue_task_with_files(
+/// Enqueue a Slack task submitted from a modal. `thread_ts` is empty for top-level requests;
+/// the event timestamp is "now" so context stops at the moment of submission.
+pub async fn enqueue_modal_task(
     pool: &SqlitePool,
-    provider: &str,
     workspace_id: &str,
     channel_id: &str,
     thread_ts: &str,
-    event_ts: &str,
     requested_by_user_id: &str,
     prompt_text: &str,
-    files_json: &str,
-    is_proactive: bool,
+    options_json: &str,
 ) -> anyhow::Result<i64> {
+    let now = chrono::Utc::now();
+    let event_ts = format!("{}.{:06}", now.timestamp(), now.timestamp_subsec_micros());
     let conversation_key =
-        compute_conversation_key(workspace_id, channel_id, thread_ts, event_ts, is_proactive);
+        compute_conversation_key(workspace_id, channel_id, thread_ts, &event_ts, false);
     let res = sqlx::query(
         r#"
         INSERT INTO tasks (
@@ -385,26 +670,23 @@ pub async fn enqueue_task_with_files(
           event_ts,
           requested_by_user_id,
           prompt_text,
-          files_json,
-          is_proactive,
+          options_json,
           created_at
         )
-        VALUES (?1, 'queued', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, unixepoch())
+        VALUES ('slack', 'queued', ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, unixepoch())
         "#,
     )
-    .bind(provider)
     .bind(workspace_id)
     .bind(channel_id)
     .bind(thread_ts)
     .bind(&conversation_key)
-    .bind(event_ts)
+    .bind(&event_ts)
     .bind(requested_by_user_id)
     .bind(prompt_text)
-    .bind(files_json)
-    .bind(if is_proactive { 1 } else { 0 })
+    .bind(options_json)
     .execute(pool)
     .await
-    .context("insert task")?;
+    .context("insert modal task")?;
 
     Ok(res.last_insert_rowid())
 }
@@ -481,6 +763,32 @@ fn compute_conversation_key(
     }
 }
 
+fn cron_job_from_row(r: &sqlx::sqlite::SqliteRow) -> CronJob {
+    CronJob {
+        id: r.get::<String, _>("id"),
+        name: r.get::<String, _>("name"),
+        enabled: r.get::<i64, _>("enabled") != 0,
+        mode: r
+            .get::<Option<String>, _>("mode")
+            .unwrap_or_else(|| "agent".to_string()),
+        schedule_kind: r.get::<String, _>("schedule_kind"),
+        every_seconds: r.get::<Option<i64>, _>("every_seconds"),
+        cron_expr: r.get::<Option<String>, _>("cron_expr"),
+        at_ts: r.get::<Option<i64>, _>("at_ts"),
+        workspace_id: r.get::<String, _>("workspace_id"),
+        channel_id: r.get::<String, _>("channel_id"),
+        thread_ts: r.get::<String, _>("thread_ts"),
+        prompt_text: r.get::<String, _>("prompt_text"),
+        created_by_user_id: r.get::<Option<String>, _>("created_by_user_id"),
+        next_run_at: r.get::<Option<i64>, _>("next_run_at"),
+        last_run_at: r.get::<Option<i64>, _>("last_run_at"),
+        last_status: r.get::<Option<String>, _>("last_status"),
+        last_error: r.get::<Option<String>, _>("last_error"),
+        created_at: r.get::<i64, _>("created_at"),
+        updated_at: r.get::<i64, _>("updated_at"),
+    }
+}
+
 pub async fn list_cron_jobs(pool: &SqlitePool, limit: i64) -> anyhow::Result<Vec<CronJob>> {
     let rows = sqlx::query(
         r#"
@@ -497,6 +805,7 @@ pub async fn list_cron_jobs(pool: &SqlitePool, limit: i64) -> anyhow::Result<Vec
           channel_id,
           thread_ts,
           prompt_text,
+          created_by_user_id,
           next_run_at,
           last_run_at,
           last_status,
@@ -513,31 +822,50 @@ pub async fn list_cron_jobs(pool: &SqlitePool, limit: i64) -> anyhow::Result<Vec
     .await
     .context("list cron jobs")?;
 
-    Ok(rows
-        .into_iter()
-        .map(|r| CronJob {
-            id: r.get::<String, _>("id"),
-            name: r.get::<String, _>("name"),
-            enabled: r.get::<i64, _>("enabled") != 0,
-            mode: r
-                .get::<Option<String>, _>("mode")
-                .unwrap_or_else(|| "agent".to_string()),
-            schedule_kind: r.get::<String, _>("schedule_kind"),
-            every_seconds: r.get::<Option<i64>, _>("every_seconds"),
-            cron_expr: r.get::<Option<String>, _>("cron_expr"),
-            at_ts: r.get::<Option<i64>, _>("at_ts"),
-            workspace_id: r.get::<String, _>("workspace_id"),
-            channel_id: r.get::<String, _>("channel_id"),
-            thread_ts: r.get::<String, _>("thread_ts"),
-            prompt_text: r.get::<String, _>("prompt_text"),
-            next_run_at: r.get::<Option<i64>, _>("next_run_at"),
-            last_run_at: r.get::<Option<i64>, _>("last_run_at"),
-            last_status: r.get::<Option<String>, _>("last_status"),
-            last_error: r.get::<Option<String>, _>("last_error"),
-            created_at: r.get::<i64, _>("created_at"),
-            updated_at: r.get::<i64, _>("updated_at"),
-        })
-        .collect())
+    Ok(rows.iter().map(cron_job_from_row).collect())
+}
+
+/// Enabled cron jobs `user_id` asked for, soonest first.
+pub async fn list_user_cron_jobs(
+    pool: &SqlitePool,
+    user_id: &str,
+    limit: i64,
+) -> anyhow::Result<Vec<CronJob>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT
+          id,
+          name,
+          enabled,
+          mode,
+          schedule_kind,
+          every_seconds,
+          cron_expr,
+          at_ts,
+          workspace_id,
+          channel_id,
+          thread_ts,
+          prompt_text,
+          created_by_user_id,
+          next_run_at,
+          last_run_at,
+          last_status,
+          last_error,
+          created_at,
+          updated_at
+        FROM cron_jobs
+        WHERE created_by_user_id = ?1 AND enabled = 1
+        ORDER BY next_run_at IS NULL, next_run_at ASC
+        LIMIT ?2
+        "#,
+    )
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("list user cron jobs")?;
+
+    Ok(rows.iter().map(cron_job_from_row).collect())
 }
 
 pub async fn insert_cron_job(pool: &SqlitePool, job: &CronJob) -> anyhow::Result<()> {
@@ -561,9 +889,10 @@ pub async fn insert_cron_job(pool: &SqlitePool, job: &CronJob) -> anyhow::Result
           last_status,
           last_error,
           created_at,
-          updated_at
+          updated_at,
+          created_by_user_id
         )
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)
         "#,
     )
     .bind(&job.id)
@@ -584,16 +913,74 @@ pub async fn insert_cron_job(pool: &SqlitePool, job: &CronJob) -> anyhow::Result
     .bind(job.last_error.as_deref())
     .bind(job.created_at)
     .bind(job.updated_at)
+    .bind(job.created_by_user_id.as_deref())
     .execute(pool)
     .await
     .context("insert cron job")?;
     Ok(())
 }
 
-pub async fn delete_cron_job(pool: &SqlitePool, id: &str) -> anyhow::Result<bool> {
+/// Insert or update a cron job by id, keeping its run history.
+pub async fn upsert_cron_job(db: impl SqliteExecutor<'_>, job: &CronJob) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO cron_jobs (
+          id,
+          name,
+          enabled,
+          mode,
+          schedule_kind,
+          every_seconds,
+          cron_expr,
+          at_ts,
+          workspace_id,
+          channel_id,
+          thread_ts,
+          prompt_text,
+          next_run_at,
+          created_at,
+          updated_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, unixepoch(), unixepoch())
+        ON CONFLICT(id) DO UPDATE SET
+          name = excluded.name,
+          enabled = excluded.enabled,
+          mode = excluded.mode,
+          schedule_kind = excluded.schedule_kind,
+          every_seconds = excluded.every_seconds,
+          cron_expr = excluded.cron_expr,
+          at_ts = excluded.at_ts,
+          workspace_id = excluded.workspace_id,
+          channel_id = excluded.channel_id,
+          thread_ts = excluded.thread_ts,
+          prompt_text = excluded.prompt_text,
+          next_run_at = excluded.next_run_at,
+          updated_at = unixepoch()
+        "#,
+    )
+    .bind(&job.id)
+    .bind(&job.name)
+    .bind(if job.enabled { 1 } else { 0 })
+    .bind(&job.mode)
+    .bind(&job.schedule_kind)
+    .bind(job.every_seconds)
+    .bind(job.cron_expr.as_deref())
+    .bind(job.at_ts)
+    .bind(&job.workspace_id)
+    .bind(&job.channel_id)
+    .bind(&job.thread_ts)
+    .bind(&job.prompt_text)
+    .bind(job.next_run_at)
+    .execute(db)
+    .await
+    .context("upsert cron job")?;
+    Ok(())
+}
+
+pub async fn delete_cron_job(db: impl SqliteExecutor<'_>, id: &str) -> anyhow::Result<bool> {
     let res = sqlx::query("DELETE FROM cron_jobs WHERE id = ?1")
         .bind(id)
-        .execute(pool)
+        .execute(db)
         .await
         .context("delete cron job")?;
     Ok(res.rows_affected() == 1)
@@ -620,6 +1007,34 @@ pub async fn set_cron_job_enabled(
     Ok(res.rows_affected() == 1)
 }
 
+/// Disable the enabled cron jobs that post to a channel, returning their names.
+pub async fn disable_channel_cron_jobs(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    channel_id: &str,
+    reason: &str,
+) -> anyhow::Result<Vec<String>> {
+    let rows = sqlx::query(
+        r#"
+        UPDATE cron_jobs
+        SET enabled = 0,
+            last_error = ?3,
+            updated_at = unixepoch()
+        WHERE workspace_id = ?1 AND channel_id = ?2 AND enabled = 1
+        RETURNING name
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(channel_id)
+    .bind(reason)
+    .fetch_all(pool)
+    .await
+    .context("disable channel cron jobs")?;
+    let mut names: Vec<String> = rows.iter().map(|r| r.get("name")).collect();
+    names.sort();
+    Ok(names)
+}
+
 pub async fn claim_due_cron_jobs(
     pool: &SqlitePool,
     now_ts: i64,
@@ -642,6 +1057,7 @@ pub async fn claim_due_cron_jobs(
           channel_id,
           thread_ts,
           prompt_text,
+          created_by_user_id,
           next_run_at,
           last_run_at,
           last_status,
@@ -684,31 +1100,7 @@ pub async fn claim_due_cron_jobs(
 
     tx.commit().await.context("commit tx")?;
 
-    Ok(rows
-        .into_iter()
-        .map(|r| CronJob {
-            id: r.get::<String, _>("id"),
-            name: r.get::<String, _>("name"),
-            enabled: r.get::<i64, _>("enabled") != 0,
-            mode: r
-                .get::<Option<String>, _>("mode")
-                .unwrap_or_else(|| "agent".to_string()),
-            schedule_kind: r.get::<String, _>("schedule_kind"),
-            every_seconds: r.get::<Option<i64>, _>("every_seconds"),
-            cron_expr: r.get::<Option<String>, _>("cron_expr"),
-            at_ts: r.get::<Option<i64>, _>("at_ts"),
-            workspace_id: r.get::<String, _>("workspace_id"),
-            channel_id: r.get::<String, _>("channel_id"),
-            thread_ts: r.get::<String, _>("thread_ts"),
-            prompt_text: r.get::<String, _>("prompt_text"),
-            next_run_at: r.get::<Option<i64>, _>("next_run_at"),
-            last_run_at: r.get::<Option<i64>, _>("last_run_at"),
-            last_status: r.get::<Option<String>, _>("last_status"),
-            last_error: r.get::<Option<String>, _>("last_error"),
-            created_at: r.get::<i64, _>("created_at"),
-            updated_at: r.get::<i64, _>("updated_at"),
-        })
-        .collect())
+    Ok(rows.iter().map(cron_job_from_row).collect())
 }
 
 pub async fn update_cron_job_next_run_at(
@@ -847,36 +1239,753 @@ pub async fn insert_guardrail_rule(pool: &SqlitePool, rule: &GuardrailRule) -> a
     Ok(())
 }
 
-pub async fn delete_guardrail_rule(pool: &SqlitePool, id: &str) -> anyhow::Result<bool> {
-    let res = sqlx::query("DELETE FROM guardrail_rules WHERE id = ?1")
-        .bind(id)
-        .execute(pool)
-        .await
-        .context("delete guardrail rule")?;
-    Ok(res.rows_affected() == 1)
-}
-
-pub async fn set_guardrail_rule_enabled(
-    pool: &SqlitePool,
-    id: &str,
-    enabled: bool,
-) -> anyhow::Result<bool> {
-    let res = sqlx::query(
+/// Insert or update a guardrail rule by id, keeping its match counters.
+pub async fn upsert_guardrail_rule(
+    db: impl SqliteExecutor<'_>,
+    rule: &GuardrailRule,
+) -> anyhow::Result<()> {
+    sqlx::query(
         r#"
-        UPDATE guardrail_rules
-        SET enabled = ?2,
-            updated_at = unixepoch()
-        WHERE id = ?1
-        "#,
+        INSERT INTO guardrail_rules (
+          id,
+          name,
+          kind,
+          pattern_kind,
+          pattern,
+          action,
+          priority,
+          enabled,
+          created_at,
+          updated_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, unixepoch(), unixepoch())
+        ON CONFLICT(id) DO UPDATE SET
+          name = excluded.name,
+          kind = excluded.kind,
+          pattern_kind = excluded.pattern_kind,
+          pattern = excluded.pattern,
+          action = excluded.action,
+          priority = excluded.priority,
+          enabled = excluded.enabled,
+          updated_at = unixepoch()
+        "#,
+    )
+    .bind(&rule.id)
+    .bind(&rule.name)
+    .bind(&rule.kind)
+    .bind(&rule.pattern_kind)
+    .bind(&rule.pattern)
+    .bind(&rule.action)
+    .bind(rule.priority)
+    .bind(if rule.enabled { 1 } else { 0 })
+    .execute(db)
+    .await
+    .context("upsert guardrail rule")?;
+    Ok(())
+}
+
+pub async fn delete_guardrail_rule(db: impl SqliteExecutor<'_>, id: &str) -> anyhow::Result<bool> {
+    let res = sqlx::query("DELETE FROM guardrail_rules WHERE id = ?1")
+        .bind(id)
+        .execute(db)
+        .await
+        .context("delete guardrail rule")?;
+    Ok(res.rows_affected() == 1)
+}
+
+pub async fn set_guardrail_rule_enabled(
+    pool: &SqlitePool,
+    id: &str,
+    enabled: bool,
+) -> anyhow::Result<bool> {
+    let res = sqlx::query(
+        r#"
+        UPDATE guardrail_rules
+        SET enabled = ?2,
+            updated_at = unixepoch()
+        WHERE id = ?1
+        "#,
+    )
+    .bind(id)
+    .bind(if enabled { 1 } else { 0 })
+    .execute(pool)
+    .await
+    .context("set guardrail rule enabled")?;
+    Ok(res.rows_affected() == 1)
+}
+
+pub async fn get_guardrail_canary(pool: &SqlitePool) -> anyhow::Result<Option<GuardrailCanary>> {
+    let row = sqlx::query(
+        "SELECT channels, rules_json, started_at, ends_at FROM guardrail_canary WHERE id = 1",
+    )
+    .fetch_optional(pool)
+    .await
+    .context("get guardrail canary")?;
+    Ok(row.map(|r| GuardrailCanary {
+        channels: r.get::<String, _>("channels"),
+        rules_json: r.get::<String, _>("rules_json"),
+        started_at: r.get::<i64, _>("started_at"),
+        ends_at: r.get::<i64, _>("ends_at"),
+    }))
+}
+
+/// Start (or replace) the canary; the decision log starts over.
+pub async fn set_guardrail_canary(pool: &SqlitePool, c: &GuardrailCanary) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await.context("begin guardrail canary")?;
+    sqlx::query(
+        r#"
+        INSERT INTO guardrail_canary (id, channels, rules_json, started_at, ends_at)
+        VALUES (1, ?1, ?2, ?3, ?4)
+        ON CONFLICT(id) DO UPDATE SET
+          channels = excluded.channels,
+          rules_json = excluded.rules_json,
+          started_at = excluded.started_at,
+          ends_at = excluded.ends_at
+        "#,
+    )
+    .bind(&c.channels)
+    .bind(&c.rules_json)
+    .bind(c.started_at)
+    .bind(c.ends_at)
+    .execute(&mut *tx)
+    .await
+    .context("set guardrail canary")?;
+    sqlx::query("DELETE FROM guardrail_canary_decisions")
+        .execute(&mut *tx)
+        .await
+        .context("clear guardrail canary decisions")?;
+    tx.commit().await.context("commit guardrail canary")?;
+    Ok(())
+}
+
+pub async fn delete_guardrail_canary(pool: &SqlitePool) -> anyhow::Result<bool> {
+    let res = sqlx::query("DELETE FROM guardrail_canary WHERE id = 1")
+        .execute(pool)
+        .await
+        .context("delete guardrail canary")?;
+    Ok(res.rows_affected() == 1)
+}
+
+pub async fn insert_guardrail_canary_decision(
+    pool: &SqlitePool,
+    d: &GuardrailCanaryDecision,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO guardrail_canary_decisions (
+          task_id, channel_id, kind, subject, applied,
+          active_decision, active_rule_id, canary_decision, canary_rule_id, created_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+        "#,
+    )
+    .bind(d.task_id)
+    .bind(&d.channel_id)
+    .bind(&d.kind)
+    .bind(&d.subject)
+    .bind(&d.applied)
+    .bind(&d.active_decision)
+    .bind(d.active_rule_id.as_deref())
+    .bind(&d.canary_decision)
+    .bind(d.canary_rule_id.as_deref())
+    .bind(d.created_at)
+    .execute(pool)
+    .await
+    .context("insert guardrail canary decision")?;
+    Ok(())
+}
+
+pub async fn list_guardrail_canary_decisions(
+    pool: &SqlitePool,
+    limit: i64,
+) -> anyhow::Result<Vec<GuardrailCanaryDecision>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, task_id, channel_id, kind, subject, applied,
+               active_decision, active_rule_id, canary_decision, canary_rule_id, created_at
+        FROM guardrail_canary_decisions
+        ORDER BY id DESC
+        LIMIT ?1
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("list guardrail canary decisions")?;
+    Ok(rows
+        .iter()
+        .map(|r| GuardrailCanaryDecision {
+            id: r.get::<i64, _>("id"),
+            task_id: r.get::<i64, _>("task_id"),
+            channel_id: r.get::<String, _>("channel_id"),
+            kind: r.get::<String, _>("kind"),
+            subject: r.get::<String, _>("subject"),
+            applied: r.get::<String, _>("applied"),
+            active_decision: r.get::<String, _>("active_decision"),
+            active_rule_id: r.get::<Option<String>, _>("active_rule_id"),
+            canary_decision: r.get::<String, _>("canary_decision"),
+            canary_rule_id: r.get::<Option<String>, _>("canary_rule_id"),
+            created_at: r.get::<i64, _>("created_at"),
+        })
+        .collect())
+}
+
+fn saved_prompt_from_row(r: &sqlx::sqlite::SqliteRow) -> SavedPrompt {
+    SavedPrompt {
+        id: r.get::<String, _>("id"),
+        name: r.get::<String, _>("name"),
+        description: r.get::<String, _>("description"),
+        template: r.get::<String, _>("template"),
+        enabled: r.get::<i64, _>("enabled") != 0,
+        created_at: r.get::<i64, _>("created_at"),
+        updated_at: r.get::<i64, _>("updated_at"),
+    }
+}
+
+pub async fn list_saved_prompts(pool: &SqlitePool) -> anyhow::Result<Vec<SavedPrompt>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, name, description, template, enabled, created_at, updated_at
+        FROM saved_prompts
+        ORDER BY name ASC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("list saved prompts")?;
+    Ok(rows.iter().map(saved_prompt_from_row).collect())
+}
+
+pub async fn get_saved_prompt_by_name(
+    pool: &SqlitePool,
+    name: &str,
+) -> anyhow::Result<Option<SavedPrompt>> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, name, description, template, enabled, created_at, updated_at
+        FROM saved_prompts
+        WHERE name = ?1
+        "#,
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await
+    .context("get saved prompt")?;
+    Ok(row.as_ref().map(saved_prompt_from_row))
+}
+
+/// Insert a prompt, or replace the one with the same name.
+pub async fn upsert_saved_prompt(
+    db: impl SqliteExecutor<'_>,
+    prompt: &SavedPrompt,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO saved_prompts (
+          id, name, description, template, enabled, created_at, updated_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        ON CONFLICT(name) DO UPDATE SET
+          description = excluded.description,
+          template = excluded.template,
+          enabled = excluded.enabled,
+          updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(&prompt.id)
+    .bind(&prompt.name)
+    .bind(&prompt.description)
+    .bind(&prompt.template)
+    .bind(if prompt.enabled { 1 } else { 0 })
+    .bind(prompt.created_at)
+    .bind(prompt.updated_at)
+    .execute(db)
+    .await
+    .context("upsert saved prompt")?;
+    Ok(())
+}
+
+pub async fn delete_saved_prompt(db: impl SqliteExecutor<'_>, id: &str) -> anyhow::Result<bool> {
+    let res = sqlx::query("DELETE FROM saved_prompts WHERE id = ?1")
+        .bind(id)
+        .execute(db)
+        .await
+        .context("delete saved prompt")?;
+    Ok(res.rows_affected() == 1)
+}
+
+fn usage_report_channel_from_row(r: &sqlx::sqlite::SqliteRow) -> UsageReportChannel {
+    UsageReportChannel {
+        provider: r.get("provider"),
+        workspace_id: r.get("workspace_id"),
+        channel_id: r.get("channel_id"),
+        last_period: r.get("last_period"),
+        failures: r.get("failures"),
+        retry_at: r.get("retry_at"),
+    }
+}
+
+pub async fn list_usage_report_channels(
+    pool: &SqlitePool,
+) -> anyhow::Result<Vec<UsageReportChannel>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT provider, workspace_id, channel_id, last_period, failures, retry_at
+        FROM usage_reports
+        ORDER BY created_at ASC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("list usage report channels")?;
+    Ok(rows.iter().map(usage_report_channel_from_row).collect())
+}
+
+/// Opt a channel in. `period` is stored as already reported, so the first report covers a
+/// full month. Returns false when the channel was already opted in.
+pub async fn enable_usage_report(
+    pool: &SqlitePool,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+    user_id: &str,
+    period: &str,
+) -> anyhow::Result<bool> {
+    let res = sqlx::query(
+        r#"
+        INSERT INTO usage_reports (
+          provider, workspace_id, channel_id, enabled_by_user_id, last_period, created_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, unixepoch())
+        ON CONFLICT(provider, workspace_id, channel_id) DO NOTHING
+        "#,
+    )
+    .bind(provider)
+    .bind(workspace_id)
+    .bind(channel_id)
+    .bind(user_id)
+    .bind(period)
+    .execute(pool)
+    .await
+    .context("enable usage report")?;
+    Ok(res.rows_affected() == 1)
+}
+
+pub async fn disable_usage_report(
+    pool: &SqlitePool,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+) -> anyhow::Result<bool> {
+    let res = sqlx::query(
+        "DELETE FROM usage_reports WHERE provider = ?1 AND workspace_id = ?2 AND channel_id = ?3",
+    )
+    .bind(provider)
+    .bind(workspace_id)
+    .bind(channel_id)
+    .execute(pool)
+    .await
+    .context("disable usage report")?;
+    Ok(res.rows_affected() == 1)
+}
+
+pub async fn mark_usage_report_sent(
+    pool: &SqlitePool,
+    channel: &UsageReportChannel,
+    period: &str,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE usage_reports
+        SET last_period = ?4, failures = 0, retry_at = NULL
+        WHERE provider = ?1 AND workspace_id = ?2 AND channel_id = ?3
+        "#,
+    )
+    .bind(&channel.provider)
+    .bind(&channel.workspace_id)
+    .bind(&channel.channel_id)
+    .bind(period)
+    .execute(pool)
+    .await
+    .context("mark usage report sent")?;
+    Ok(())
+}
+
+/// Count a failed attempt at the channel's report and hold off until `retry_at`.
+pub async fn record_usage_report_failure(
+    pool: &SqlitePool,
+    channel: &UsageReportChannel,
+    retry_at: i64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE usage_reports
+        SET failures = failures + 1, retry_at = ?4
+        WHERE provider = ?1 AND workspace_id = ?2 AND channel_id = ?3
+        "#,
+    )
+    .bind(&channel.provider)
+    .bind(&channel.workspace_id)
+    .bind(&channel.channel_id)
+    .bind(retry_at)
+    .execute(pool)
+    .await
+    .context("record usage report failure")?;
+    Ok(())
+}
+
+/// Finished-task counts by status in one channel since `since` (admin test runs excluded).
+pub async fn count_channel_tasks_by_status(
+    pool: &SqlitePool,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+    since: i64,
+) -> anyhow::Result<Vec<(String, i64)>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT status, COUNT(*) AS c
+        FROM tasks
+        WHERE provider = ?1 AND workspace_id = ?2 AND channel_id = ?3
+          AND created_at >= ?4 AND is_synthetic = 0
+        GROUP BY status
+        "#,
+    )
+    .bind(provider)
+    .bind(workspace_id)
+    .bind(channel_id)
+    .bind(since)
+    .fetch_all(pool)
+    .await
+    .context("count channel tasks by status")?;
+    Ok(rows.iter().map(|r| (r.get("status"), r.get("c"))).collect())
+}
+
+/// `(user id, finished tasks)` for the channel's most active requesters since `since`.
+pub async fn top_channel_requesters(
+    pool: &SqlitePool,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+    since: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<(String, i64)>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT requested_by_user_id AS user_id, COUNT(*) AS c
+        FROM tasks
+        WHERE provider = ?1 AND workspace_id = ?2 AND channel_id = ?3
+          AND created_at >= ?4 AND is_synthetic = 0 AND is_proactive = 0
+          AND status IN ('succeeded', 'failed', 'cancelled')
+          AND requested_by_user_id != ''
+        GROUP BY requested_by_user_id
+        ORDER BY c DESC, user_id ASC
+        LIMIT ?5
+        "#,
+    )
+    .bind(provider)
+    .bind(workspace_id)
+    .bind(channel_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("list top channel requesters")?;
+    Ok(rows
+        .iter()
+        .map(|r| (r.get("user_id"), r.get("c")))
+        .collect())
+}
+
+/// `(task id, error text)` of the channel's latest failed tasks since `since`.
+pub async fn recent_channel_failures(
+    pool: &SqlitePool,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+    since: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<(i64, String)>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, COALESCE(error_text, '') AS error_text
+        FROM tasks
+        WHERE provider = ?1 AND workspace_id = ?2 AND channel_id = ?3
+          AND created_at >= ?4 AND is_synthetic = 0 AND status = 'failed'
+        ORDER BY id DESC
+        LIMIT ?5
+        "#,
+    )
+    .bind(provider)
+    .bind(workspace_id)
+    .bind(channel_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("list recent channel failures")?;
+    Ok(rows
+        .iter()
+        .map(|r| (r.get("id"), r.get("error_text")))
+        .collect())
+}
+
+pub async fn count_channel_denied_approvals(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    channel_id: &str,
+    since: i64,
+) -> anyhow::Result<i64> {
+    let row = sqlx::query(
+        r#"
+        SELECT COUNT(*) AS c
+        FROM approvals
+        WHERE workspace_id = ?1 AND channel_id = ?2
+          AND status = 'denied' AND created_at >= ?3
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(channel_id)
+    .bind(since)
+    .fetch_one(pool)
+    .await
+    .context("count channel denied approvals")?;
+    Ok(row.get("c"))
+}
+
+fn elevated_session_from_row(r: &sqlx::sqlite::SqliteRow) -> ElevatedSession {
+    ElevatedSession {
+        id: r.get("id"),
+        provider: r.get("provider"),
+        workspace_id: r.get("workspace_id"),
+        channel_id: r.get("channel_id"),
+        granted_by: r.get("granted_by"),
+        reason: r.get("reason"),
+        started_at: r.get("started_at"),
+        ends_at: r.get("ends_at"),
+        revoked_at: r.get("revoked_at"),
+        revoked_by: r.get("revoked_by"),
+    }
+}
+
+const ELEVATED_SESSION_COLUMNS: &str = "id, provider, workspace_id, channel_id, granted_by, reason, started_at, ends_at, revoked_at, revoked_by";
+
+/// Start an elevated session. Sessions still active in the same channel are revoked by
+/// `session.granted_by`, so a channel has at most one.
+pub async fn insert_elevated_session(
+    pool: &SqlitePool,
+    session: &ElevatedSession,
+) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await.context("begin elevated session")?;
+    sqlx::query(
+        r#"
+        UPDATE elevated_sessions
+        SET revoked_at = ?4, revoked_by = ?5, end_notified = 1
+        WHERE provider = ?1 AND workspace_id = ?2 AND channel_id = ?3
+          AND revoked_at IS NULL AND ends_at > ?4
+        "#,
+    )
+    .bind(&session.provider)
+    .bind(&session.workspace_id)
+    .bind(&session.channel_id)
+    .bind(session.started_at)
+    .bind(&session.granted_by)
+    .execute(&mut *tx)
+    .await
+    .context("replace elevated session")?;
+    sqlx::query(
+        r#"
+        INSERT INTO elevated_sessions (
+          id, provider, workspace_id, channel_id, granted_by, reason, started_at, ends_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        "#,
+    )
+    .bind(&session.id)
+    .bind(&session.provider)
+    .bind(&session.workspace_id)
+    .bind(&session.channel_id)
+    .bind(&session.granted_by)
+    .bind(&session.reason)
+    .bind(session.started_at)
+    .bind(session.ends_at)
+    .execute(&mut *tx)
+    .await
+    .context("insert elevated session")?;
+    tx.commit().await.context("commit elevated session")?;
+    Ok(())
+}
+
+pub async fn active_elevated_session(
+    pool: &SqlitePool,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+    now: i64,
+) -> anyhow::Result<Option<ElevatedSession>> {
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT {ELEVATED_SESSION_COLUMNS}
+        FROM elevated_sessions
+        WHERE provider = ?1 AND workspace_id = ?2 AND channel_id = ?3
+          AND revoked_at IS NULL AND ends_at > ?4
+        ORDER BY started_at DESC
+        LIMIT 1
+        "#
+    ))
+    .bind(provider)
+    .bind(workspace_id)
+    .bind(channel_id)
+    .bind(now)
+    .fetch_optional(pool)
+    .await
+    .context("get active elevated session")?;
+    Ok(row.as_ref().map(elevated_session_from_row))
+}
+
+/// Newest first; active and past sessions alike.
+pub async fn list_elevated_sessions(
+    pool: &SqlitePool,
+    limit: i64,
+) -> anyhow::Result<Vec<ElevatedSession>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {ELEVATED_SESSION_COLUMNS}
+        FROM elevated_sessions
+        ORDER BY started_at DESC
+        LIMIT ?1
+        "#
+    ))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("list elevated sessions")?;
+    Ok(rows.iter().map(elevated_session_from_row).collect())
+}
+
+/// End a session early. Returns the session when it was still active.
+pub async fn revoke_elevated_session(
+    pool: &SqlitePool,
+    id: &str,
+    revoked_by: &str,
+    now: i64,
+) -> anyhow::Result<Option<ElevatedSession>> {
+    let row = sqlx::query(&format!(
+        r#"
+        UPDATE elevated_sessions
+        SET revoked_at = ?2, revoked_by = ?3, end_notified = 1
+        WHERE id = ?1 AND revoked_at IS NULL AND ends_at > ?2
+        RETURNING {ELEVATED_SESSION_COLUMNS}
+        "#
+    ))
+    .bind(id)
+    .bind(now)
+    .bind(revoked_by)
+    .fetch_optional(pool)
+    .await
+    .context("revoke elevated session")?;
+    Ok(row.as_ref().map(elevated_session_from_row))
+}
+
+/// Sessions that ran out on their own and whose channel hasn't been told yet. Marks them
+/// as told, so each is returned once.
+pub async fn take_expired_elevated_sessions(
+    pool: &SqlitePool,
+    now: i64,
+) -> anyhow::Result<Vec<ElevatedSession>> {
+    let rows = sqlx::query(&format!(
+        r#"
+        UPDATE elevated_sessions
+        SET end_notified = 1
+        WHERE end_notified = 0 AND revoked_at IS NULL AND ends_at <= ?1
+        RETURNING {ELEVATED_SESSION_COLUMNS}
+        "#
+    ))
+    .bind(now)
+    .fetch_all(pool)
+    .await
+    .context("take expired elevated sessions")?;
+    Ok(rows.iter().map(elevated_session_from_row).collect())
+}
+
+pub async fn set_saved_prompt_enabled(
+    pool: &SqlitePool,
+    id: &str,
+    enabled: bool,
+) -> anyhow::Result<bool> {
+    let res = sqlx::query(
+        r#"
+        UPDATE saved_prompts
+        SET enabled = ?2,
+            updated_at = unixepoch()
+        WHERE id = ?1
+        "#,
     )
     .bind(id)
     .bind(if enabled { 1 } else { 0 })
     .execute(pool)
     .await
-    .context("set guardrail rule enabled")?;
+    .context("set saved prompt enabled")?;
     Ok(res.rows_affected() == 1)
 }
 
+pub async fn record_guardrail_match(pool: &SqlitePool, id: &str) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE guardrail_rules
+        SET match_count = match_count + 1,
+            last_matched_at = unixepoch()
+        WHERE id = ?1
+        "#,
+    )
+    .bind(id)
+    .execute(pool)
+    .await
+    .context("record guardrail match")?;
+    Ok(())
+}
+
+/// `(rule id, match_count, last_matched_at)` for every guardrail rule.
+pub async fn list_guardrail_rule_matches(
+    pool: &SqlitePool,
+) -> anyhow::Result<Vec<(String, i64, Option<i64>)>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, match_count, last_matched_at
+        FROM guardrail_rules
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("list guardrail rule matches")?;
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            (
+                r.get::<String, _>("id"),
+                r.get::<i64, _>("match_count"),
+                r.get::<Option<i64>, _>("last_matched_at"),
+            )
+        })
+        .collect())
+}
+
+/// When guardrail rule matches started being counted: the time the migration adding
+/// `match_count` ran on this database.
+pub async fn guardrail_match_tracking_since(pool: &SqlitePool) -> anyhow::Result<Option<i64>> {
+    let row = sqlx::query(
+        r#"
+        SELECT CAST(strftime('%s', installed_on) AS INTEGER) AS since
+        FROM _sqlx_migrations
+        WHERE version = 28
+        "#,
+    )
+    .fetch_optional(pool)
+    .await
+    .context("get guardrail match tracking start")?;
+    Ok(row.and_then(|r| r.get::<Option<i64>, _>("since")))
+}
+
 pub async fn insert_approval(pool: &SqlitePool, approval: &Approval) -> anyhow::Result<()> {
     sqlx::query(
         r#"
@@ -1001,6 +2110,111 @@ pub async fn list_recent_approvals(pool: &SqlitePool, limit: i64) -> anyhow::Res
         .collect())
 }
 
+/// Pending approvals for tasks `user_id` asked for, newest first.
+pub async fn list_pending_approvals_for_user(
+    pool: &SqlitePool,
+    user_id: &str,
+    limit: i64,
+) -> anyhow::Result<Vec<Approval>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT
+          id,
+          kind,
+          status,
+          decision,
+          workspace_id,
+          channel_id,
+          thread_ts,
+          requested_by_user_id,
+          details_json,
+          created_at,
+          updated_at,
+          resolved_at
+        FROM approvals
+        WHERE requested_by_user_id = ?1 AND status = 'pending'
+        ORDER BY created_at DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("list user pending approvals")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| Approval {
+            id: r.get::<String, _>("id"),
+            kind: r.get::<String, _>("kind"),
+            status: r.get::<String, _>("status"),
+            decision: r.get::<Option<String>, _>("decision"),
+            workspace_id: r.get::<Option<String>, _>("workspace_id"),
+            channel_id: r.get::<Option<String>, _>("channel_id"),
+            thread_ts: r.get::<Option<String>, _>("thread_ts"),
+            requested_by_user_id: r.get::<Option<String>, _>("requested_by_user_id"),
+            details_json: r.get::<String, _>("details_json"),
+            created_at: r.get::<i64, _>("created_at"),
+            updated_at: r.get::<i64, _>("updated_at"),
+            resolved_at: r.get::<Option<i64>, _>("resolved_at"),
+        })
+        .collect())
+}
+
+/// Command approvals created since `since` (unix seconds), newest first.
+pub async fn list_command_approvals_since(
+    pool: &SqlitePool,
+    since: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<Approval>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT
+          id,
+          kind,
+          status,
+          decision,
+          workspace_id,
+          channel_id,
+          thread_ts,
+          requested_by_user_id,
+          details_json,
+          created_at,
+          updated_at,
+          resolved_at
+        FROM approvals
+        WHERE kind = 'command_execution'
+          AND created_at >= ?1
+        ORDER BY created_at DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("list command approvals")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| Approval {
+            id: r.get::<String, _>("id"),
+            kind: r.get::<String, _>("kind"),
+            status: r.get::<String, _>("status"),
+            decision: r.get::<Option<String>, _>("decision"),
+            workspace_id: r.get::<Option<String>, _>("workspace_id"),
+            channel_id: r.get::<Option<String>, _>("channel_id"),
+            thread_ts: r.get::<Option<String>, _>("thread_ts"),
+            requested_by_user_id: r.get::<Option<String>, _>("requested_by_user_id"),
+            details_json: r.get::<String, _>("details_json"),
+            created_at: r.get::<i64, _>("created_at"),
+            updated_at: r.get::<i64, _>("updated_at"),
+            resolved_at: r.get::<Option<i64>, _>("resolved_at"),
+        })
+        .collect())
+}
+
 pub async fn resolve_approval(
     pool: &SqlitePool,
     id: &str,
@@ -1100,14 +2314,6 @@ pub async fn get_runtime_active_task(pool: &SqlitePool) -> anyhow::Result<Option
     }))
 }
 
-pub async fn clear_runtime_active_tasks(pool: &SqlitePool) -> anyhow::Result<u64> {
-    let res = sqlx::query("DELETE FROM runtime_active_tasks")
-        .execute(pool)
-        .await
-        .context("clear runtime active tasks")?;
-    Ok(res.rows_affected())
-}
-
 pub async fn mark_task_active(pool: &SqlitePool, task_id: i64) -> anyhow::Result<()> {
     sqlx::query(
         r#"
@@ -1153,6 +2359,61 @@ pub async fn list_active_tasks(pool: &SqlitePool, limit: i64) -> anyhow::Result<
         .collect())
 }
 
+/// `(queued, running)` task counts.
+pub async fn count_queued_and_running_tasks(pool: &SqlitePool) -> anyhow::Result<(i64, i64)> {
+    let row = sqlx::query(
+        r#"
+        SELECT
+          SUM(CASE WHEN status = 'queued' THEN 1 ELSE 0 END) AS queued,
+          SUM(CASE WHEN status = 'running' THEN 1 ELSE 0 END) AS running
+        FROM tasks
+        WHERE status IN ('queued', 'running')
+        "#,
+    )
+    .fetch_one(pool)
+    .await
+    .context("count queued and running tasks")?;
+    Ok((
+        row.get::<Option<i64>, _>("queued").unwrap_or(0),
+        row.get::<Option<i64>, _>("running").unwrap_or(0),
+    ))
+}
+
+/// When each provider last received a (non-proactive) task.
+pub async fn last_task_at_by_provider(
+    pool: &SqlitePool,
+) -> anyhow::Result<std::collections::HashMap<String, i64>> {
+    let rows = sqlx::query(
+        "SELECT provider, MAX(created_at) AS last_at FROM tasks WHERE is_proactive = 0 GROUP BY provider",
+    )
+    .fetch_all(pool)
+    .await
+    .context("last task by provider")?;
+    Ok(rows
+        .iter()
+        .map(|r| (r.get::<String, _>("provider"), r.get::<i64, _>("last_at")))
+        .collect())
+}
+
+/// Mean run time of tasks that finished at or after `since`; `None` when there were none.
+pub async fn avg_task_run_seconds_since(
+    pool: &SqlitePool,
+    since: i64,
+) -> anyhow::Result<Option<f64>> {
+    let row = sqlx::query(
+        r#"
+        SELECT AVG(finished_at - started_at) AS avg_run
+        FROM tasks
+        WHERE started_at IS NOT NULL AND finished_at IS NOT NULL AND finished_at >= ?1
+        "#,
+    )
+    .bind(since)
+    .fetch_one(pool)
+    .await
+    .context("average task run time")?;
+    Ok(row.get::<Option<f64>, _>("avg_run"))
+}
+
 pub async fn count_active_tasks(pool: &SqlitePool) -> anyhow::Result<i64> {
     let row = sqlx::query("SELECT COUNT(*) AS c FROM runtime_active_tasks")
         .fetch_one(pool)
@@ -1165,13 +2426,67 @@ pub async fn claim_next_task(
     pool: &SqlitePool,
     owner_id: &str,
     lease_seconds: i64,
-) -> anyhow::Result<Option<Task>> {
+) -> anyhow::Result<Option<(Task, TaskClaim)>> {
     anyhow::ensure!(lease_seconds >= 10, "lease_seconds too small");
     let mut tx = pool.begin().await.context("begin tx")?;
 
+    // Select and mark running in one statement so concurrent claimers (other slots or other
+    // replicas sharing this database) can never claim the same task.
     let row_opt = sqlx::query(
         r#"
-        SELECT
+        UPDATE tasks
+        SET status = 'running',
+            started_at = unixepoch(),
+            worker_id = ?1,
+            claim_generation = claim_generation + 1
+        WHERE id = (
+          SELECT id
+          FROM tasks
+          WHERE status = 'queued'
+            AND conversation_key != ''
+            AND NOT EXISTS (
+              SELECT 1
+              FROM conversation_locks l
+              WHERE l.conversation_key = tasks.conversation_key
+                AND l.lease_until >= unixepoch()
+            )
+            -- One run per reply thread: a cron job and a mention (different
+            -- conversations) must not interleave updates in the same thread.
+            AND (
+              tasks.thread_ts = ''
+              OR tasks.is_synthetic = 1
+              OR NOT EXISTS (
+                SELECT 1
+                FROM tasks r
+                WHERE r.status = 'running'
+                  AND r.channel_id = tasks.channel_id
+                  AND r.thread_ts = tasks.thread_ts
+                  AND r.provider = tasks.provider
+                  AND r.workspace_id = tasks.workspace_id
+                  AND r.is_synthetic = 0
+              )
+            )
+            AND (
+              depends_on_task_id IS NULL
+              OR NOT EXISTS (SELECT 1 FROM tasks d WHERE d.id = tasks.depends_on_task_id)
+              OR EXISTS (
+                SELECT 1
+                FROM tasks d
+                WHERE d.id = tasks.depends_on_task_id
+                  AND (
+                    d.status = 'succeeded'
+                    OR (
+                      tasks.on_dependency_failure = 'run'
+                      AND d.status IN ('failed', 'cancelled', 'ignored')
+                    )
+                  )
+              )
+            )
+          ORDER BY priority DESC, created_at ASC, id ASC
+          LIMIT 1
+        )
+          AND status = 'queued'
+        RETURNING
           id,
           status,
           provider,
@@ -1188,53 +2503,30 @@ pub async fn claim_next_task(
           error_text,
           created_at,
           started_at,
-          finished_at
-        FROM tasks
-        WHERE status = 'queued'
-          AND conversation_key != ''
-          AND NOT EXISTS (
-            SELECT 1
-            FROM conversation_locks l
-            WHERE l.conversation_key = tasks.conversation_key
-              AND l.lease_until >= unixepoch()
-          )
-        ORDER BY created_at ASC, id ASC
-        LIMIT 1
-        "#,
-    )
-    .fetch_optional(&mut *tx)
-    .await
-    .context("select next task")?;
-
-    let Some(row) = row_opt else {
-        tx.commit().await.context("commit tx")?;
-        return Ok(None);
-    };
-
-    let id = row.get::<i64, _>("id");
-    let updated = sqlx::query(
-        r#"
-        UPDATE tasks
-        SET status = 'running',
-            started_at = unixepoch()
-        WHERE id = ?1
-          AND status = 'queued'
+          finished_at,
+          depends_on_task_id,
+          on_dependency_failure,
+          is_synthetic,
+          skip_response_cache,
+          options_json,
+          rerun_of_task_id,
+          claim_generation
         "#,
     )
-    .bind(id)
-    .execute(&mut *tx)
+    .bind(owner_id)
+    .fetch_optional(&mut *tx)
     .await
-    .context("mark task running")?;
+    .context("claim next task")?;
 
-    if updated.rows_affected() != 1 {
+    let Some(row) = row_opt else {
         tx.commit().await.context("commit tx")?;
         return Ok(None);
-    }
+    };
 
     // Acquire a per-conversation lease lock so concurrent workers don't process the same
-    // conversation simultaneously.
+    // conversation simultaneously. Never steal a live lease held by someone else.
     let conversation_key = row.get::<String, _>("conversation_key");
-    sqlx::query(
+    let locked = sqlx::query(
         r#"
         INSERT INTO conversation_locks (conversation_key, owner_id, lease_until, updated_at)
         VALUES (?1, ?2, unixepoch() + ?3, unixepoch())
@@ -1242,6 +2534,7 @@ pub async fn claim_next_task(
           owner_id = excluded.owner_id,
           lease_until = excluded.lease_until,
           updated_at = excluded.updated_at
+        WHERE conversation_locks.lease_until < unixepoch()
         "#,
     )
     .bind(&conversation_key)
@@ -1251,29 +2544,46 @@ pub async fn claim_next_task(
     .await
     .context("acquire conversation lock")?;
 
+    if locked.rows_affected() != 1 {
+        // Another claimer won the conversation; leave the task queued.
+        tx.rollback().await.context("rollback tx")?;
+        return Ok(None);
+    }
+
     tx.commit().await.context("commit tx")?;
 
-    Ok(Some(Task {
-        id,
-        status: "running".to_string(),
-        provider: row
-            .get::<Option<String>, _>("provider")
-            .unwrap_or_else(|| "slack".to_string()),
-        is_proactive: row.get::<i64, _>("is_proactive") != 0,
-        workspace_id: row.get::<String, _>("workspace_id"),
-        channel_id: row.get::<String, _>("channel_id"),
-        thread_ts: row.get::<String, _>("thread_ts"),
-        conversation_key,
-        event_ts: row.get::<String, _>("event_ts"),
-        requested_by_user_id: row.get::<String, _>("requested_by_user_id"),
-        prompt_text: row.get::<String, _>("prompt_text"),
-        files_json: row.get::<String, _>("files_json"),
-        result_text: row.get::<Option<String>, _>("result_text"),
-        error_text: row.get::<Option<String>, _>("error_text"),
-        created_at: row.get::<i64, _>("created_at"),
-        started_at: Some(chrono::Utc::now().timestamp()),
-        finished_at: row.get::<Option<i64>, _>("finished_at"),
-    }))
+    let claim = TaskClaim {
+        worker_id: owner_id.to_string(),
+        generation: row.get::<i64, _>("claim_generation"),
+    };
+    let task = task_from_row(&row);
+    Ok(Some((task, claim)))
+}
+
+/// Whether `claim` still holds the task, i.e. the leader hasn't re-queued it and nobody
+/// re-claimed it since.
+pub async fn task_claim_held(
+    pool: &SqlitePool,
+    task_id: i64,
+    claim: &TaskClaim,
+) -> anyhow::Result<bool> {
+    let row = sqlx::query(
+        r#"
+        SELECT 1
+        FROM tasks
+        WHERE id = ?1
+          AND worker_id = ?2
+          AND claim_generation = ?3
+          AND status IN ('running', 'cancel_requested')
+        "#,
+    )
+    .bind(task_id)
+    .bind(&claim.worker_id)
+    .bind(claim.generation)
+    .fetch_optional(pool)
+    .await
+    .context("check task claim")?;
+    Ok(row.is_some())
 }
 
 pub async fn try_renew_conversation_lock(
@@ -1329,26 +2639,172 @@ pub async fn cleanup_expired_conversation_locks(pool: &SqlitePool) -> anyhow::Re
     Ok(res.rows_affected())
 }
 
-pub async fn clear_all_conversation_locks(pool: &SqlitePool) -> anyhow::Result<u64> {
-    let res = sqlx::query("DELETE FROM conversation_locks")
-        .execute(pool)
+/// Resolve queued tasks whose predecessor finished without succeeding.
+///
+/// Returns the ids of tasks that were marked failed or cancelled. Runs until the whole
+/// chain has been resolved, so failures propagate through multi-step workflows.
+pub async fn propagate_dependency_failures(pool: &SqlitePool) -> anyhow::Result<Vec<i64>> {
+    let mut out = Vec::new();
+    loop {
+        let rows = sqlx::query(
+            r#"
+            UPDATE tasks
+            SET status = CASE WHEN on_dependency_failure = 'cancel' THEN 'cancelled' ELSE 'failed' END,
+                error_text = 'dependency task #' || depends_on_task_id || ' did not succeed',
+                finished_at = unixepoch()
+            WHERE status = 'queued'
+              AND depends_on_task_id IS NOT NULL
+              AND on_dependency_failure != 'run'
+              AND EXISTS (
+                SELECT 1
+                FROM tasks d
+                WHERE d.id = tasks.depends_on_task_id
+                  AND d.status IN ('failed', 'cancelled', 'ignored')
+              )
+            RETURNING id
+            "#,
+        )
+        .fetch_all(pool)
         .await
-        .context("clear conversation locks")?;
-    Ok(res.rows_affected())
+        .context("propagate dependency failures")?;
+        if rows.is_empty() {
+            return Ok(out);
+        }
+        out.extend(rows.into_iter().map(|row| row.get::<i64, _>("id")));
+    }
 }
 
-pub async fn reset_running_tasks(pool: &SqlitePool) -> anyhow::Result<u64> {
+/// Re-queue running tasks whose worker stopped heartbeating (crashed or was redeployed),
+/// and drop the conversation locks and runtime rows they left behind.
+pub async fn requeue_orphaned_tasks(
+    pool: &SqlitePool,
+    stale_after_seconds: i64,
+) -> anyhow::Result<u64> {
+    anyhow::ensure!(stale_after_seconds >= 10, "stale_after_seconds too small");
+    let mut tx = pool.begin().await.context("begin tx")?;
     let res = sqlx::query(
         r#"
         UPDATE tasks
         SET status = 'queued',
-            started_at = NULL
+            started_at = NULL,
+            worker_id = NULL
         WHERE status = 'running'
+          AND (
+            worker_id IS NULL
+            OR NOT EXISTS (
+              SELECT 1
+              FROM worker_heartbeats h
+              WHERE h.worker_id = tasks.worker_id
+                AND h.last_seen_at >= unixepoch() - ?1
+            )
+          )
+        "#,
+    )
+    .bind(stale_after_seconds)
+    .execute(&mut *tx)
+    .await
+    .context("requeue orphaned tasks")?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM conversation_locks
+        WHERE NOT EXISTS (
+          SELECT 1
+          FROM worker_heartbeats h
+          WHERE h.worker_id = conversation_locks.owner_id
+            AND h.last_seen_at >= unixepoch() - ?1
+        )
+        "#,
+    )
+    .bind(stale_after_seconds)
+    .execute(&mut *tx)
+    .await
+    .context("release orphaned conversation locks")?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM runtime_active_tasks
+        WHERE NOT EXISTS (
+          SELECT 1
+          FROM tasks t
+          WHERE t.id = runtime_active_tasks.task_id
+            AND t.status IN ('running', 'cancel_requested')
+        )
+        "#,
+    )
+    .execute(&mut *tx)
+    .await
+    .context("clear orphaned runtime active tasks")?;
+
+    tx.commit().await.context("commit tx")?;
+    Ok(res.rows_affected())
+}
+
+pub async fn upsert_worker_heartbeat(
+    pool: &SqlitePool,
+    worker_id: &str,
+    hostname: &str,
+    is_leader: bool,
+    active_tasks: i64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO worker_heartbeats (
+          worker_id, hostname, pid, is_leader, active_tasks, started_at, last_seen_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, unixepoch(), unixepoch())
+        ON CONFLICT(worker_id) DO UPDATE SET
+          is_leader = excluded.is_leader,
+          active_tasks = excluded.active_tasks,
+          last_seen_at = excluded.last_seen_at
         "#,
     )
+    .bind(worker_id)
+    .bind(hostname)
+    .bind(i64::from(std::process::id()))
+    .bind(if is_leader { 1 } else { 0 })
+    .bind(active_tasks)
     .execute(pool)
     .await
-    .context("reset running tasks")?;
+    .context("upsert worker heartbeat")?;
+    Ok(())
+}
+
+pub async fn list_worker_heartbeats(pool: &SqlitePool) -> anyhow::Result<Vec<WorkerHeartbeat>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT worker_id, hostname, pid, is_leader, active_tasks, started_at, last_seen_at
+        FROM worker_heartbeats
+        ORDER BY started_at ASC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .context("list worker heartbeats")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| WorkerHeartbeat {
+            worker_id: row.get::<String, _>("worker_id"),
+            hostname: row.get::<String, _>("hostname"),
+            pid: row.get::<i64, _>("pid"),
+            is_leader: row.get::<i64, _>("is_leader") != 0,
+            active_tasks: row.get::<i64, _>("active_tasks"),
+            started_at: row.get::<i64, _>("started_at"),
+            last_seen_at: row.get::<i64, _>("last_seen_at"),
+        })
+        .collect())
+}
+
+pub async fn delete_stale_worker_heartbeats(
+    pool: &SqlitePool,
+    max_age_seconds: i64,
+) -> anyhow::Result<u64> {
+    let res = sqlx::query("DELETE FROM worker_heartbeats WHERE last_seen_at < unixepoch() - ?1")
+        .bind(max_age_seconds)
+        .execute(pool)
+        .await
+        .context("delete stale worker heartbeats")?;
     Ok(res.rows_affected())
 }
 
@@ -1373,6 +2829,22 @@ pub async fn cleanup_old_tasks(pool: &SqlitePool, max_age_days: i64) -> anyhow::
 
     let _ = traces_res.rows_affected();
 
+    sqlx::query(
+        r#"
+        DELETE FROM task_artifacts
+        WHERE task_id IN (
+            SELECT id
+            FROM tasks
+            WHERE status IN ('succeeded', 'failed', 'cancelled')
+              AND created_at < unixepoch() - ?1
+        )
+        "#,
+    )
+    .bind(seconds)
+    .execute(pool)
+    .await
+    .context("cleanup old task artifacts")?;
+
     let res = sqlx::query(
         r#"
         DELETE FROM tasks
@@ -1754,51 +3226,69 @@ pub async fn update_github_device_login_status(
     Ok(())
 }
 
+/// Returns false (and changes nothing) when `claim` no longer holds the task.
 pub async fn complete_task_success(
     pool: &SqlitePool,
     task_id: i64,
+    claim: &TaskClaim,
     result_text: &str,
-) -> anyhow::Result<()> {
-    sqlx::query(
+) -> anyhow::Result<bool> {
+    let res = sqlx::query(
         r#"
         UPDATE tasks
         SET status = 'succeeded',
-            result_text = ?2,
+            result_text = ?4,
             finished_at = unixepoch()
         WHERE id = ?1
+          AND worker_id = ?2
+          AND claim_generation = ?3
+          AND status IN ('running', 'cancel_requested')
         "#,
     )
     .bind(task_id)
+    .bind(&claim.worker_id)
+    .bind(claim.generation)
     .bind(result_text)
     .execute(pool)
     .await
     .context("complete task success")?;
-    Ok(())
+    Ok(res.rows_affected() == 1)
 }
 
+/// Returns false (and changes nothing) when `claim` no longer holds the task.
 pub async fn complete_task_failure(
     pool: &SqlitePool,
     task_id: i64,
+    claim: &TaskClaim,
     error_text: &str,
-) -> anyhow::Result<()> {
-    sqlx::query(
+) -> anyhow::Result<bool> {
+    let res = sqlx::query(
         r#"
         UPDATE tasks
         SET status = 'failed',
-            error_text = ?2,
+            error_text = ?4,
             finished_at = unixepoch()
         WHERE id = ?1
+          AND worker_id = ?2
+          AND claim_generation = ?3
+          AND status IN ('running', 'cancel_requested')
         "#,
     )
     .bind(task_id)
+    .bind(&claim.worker_id)
+    .bind(claim.generation)
     .bind(error_text)
     .execute(pool)
     .await
     .context("complete task failure")?;
-    Ok(())
+    Ok(res.rows_affected() == 1)
 }
 
-pub async fn complete_task_cancelled(pool: &SqlitePool, task_id: i64) -> anyhow::Result<bool> {
+pub async fn complete_task_cancelled(
+    pool: &SqlitePool,
+    task_id: i64,
+    claim: &TaskClaim,
+) -> anyhow::Result<bool> {
     let res = sqlx::query(
         r#"
         UPDATE tasks
@@ -1806,16 +3296,50 @@ pub async fn complete_task_cancelled(pool: &SqlitePool, task_id: i64) -> anyhow:
             error_text = 'cancelled by admin',
             finished_at = unixepoch()
         WHERE id = ?1
+          AND worker_id = ?2
+          AND claim_generation = ?3
           AND status IN ('running', 'cancel_requested')
         "#,
     )
     .bind(task_id)
+    .bind(&claim.worker_id)
+    .bind(claim.generation)
     .execute(pool)
     .await
     .context("complete task cancelled")?;
     Ok(res.rows_affected() == 1)
 }
 
+fn task_from_row(row: &sqlx::sqlite::SqliteRow) -> Task {
+    Task {
+        id: row.get::<i64, _>("id"),
+        status: row.get::<String, _>("status"),
+        provider: row
+            .get::<Option<String>, _>("provider")
+            .unwrap_or_else(|| "slack".to_string()),
+        is_proactive: row.get::<i64, _>("is_proactive") != 0,
+        workspace_id: row.get::<String, _>("workspace_id"),
+        channel_id: row.get::<String, _>("channel_id"),
+        thread_ts: row.get::<String, _>("thread_ts"),
+        conversation_key: row.get::<String, _>("conversation_key"),
+        event_ts: row.get::<String, _>("event_ts"),
+        requested_by_user_id: row.get::<String, _>("requested_by_user_id"),
+        prompt_text: row.get::<String, _>("prompt_text"),
+        files_json: row.get::<String, _>("files_json"),
+        result_text: row.get::<Option<String>, _>("result_text"),
+        error_text: row.get::<Option<String>, _>("error_text"),
+        created_at: row.get::<i64, _>("created_at"),
+        started_at: row.get::<Option<i64>, _>("started_at"),
+        finished_at: row.get::<Option<i64>, _>("finished_at"),
+        depends_on_task_id: row.get::<Option<i64>, _>("depends_on_task_id"),
+        on_dependency_failure: row.get::<String, _>("on_dependency_failure"),
+        is_synthetic: row.get::<i64, _>("is_synthetic") != 0,
+        skip_response_cache: row.get::<i64, _>("skip_response_cache") != 0,
+        options_json: row.get("options_json"),
+        rerun_of_task_id: row.get::<Option<i64>, _>("rerun_of_task_id"),
+    }
+}
+
 pub async fn get_task(pool: &SqlitePool, task_id: i64) -> anyhow::Result<Option<Task>> {
     let row_opt = sqlx::query(
         r#"
@@ -1836,7 +3360,13 @@ pub async fn get_task(pool: &SqlitePool, task_id: i64) -> anyhow::Result<Option<
           error_text,
           created_at,
           started_at,
-          finished_at
+          finished_at,
+          depends_on_task_id,
+          on_dependency_failure,
+          is_synthetic,
+          skip_response_cache,
+          options_json,
+          rerun_of_task_id
         FROM tasks
         WHERE id = ?1
         "#,
@@ -1846,27 +3376,7 @@ pub async fn get_task(pool: &SqlitePool, task_id: i64) -> anyhow::Result<Option<
     .await
     .context("get task")?;
 
-    Ok(row_opt.map(|row| Task {
-        id: row.get::<i64, _>("id"),
-        status: row.get::<String, _>("status"),
-        provider: row
-            .get::<Option<String>, _>("provider")
-            .unwrap_or_else(|| "slack".to_string()),
-        is_proactive: row.get::<i64, _>("is_proactive") != 0,
-        workspace_id: row.get::<String, _>("workspace_id"),
-        channel_id: row.get::<String, _>("channel_id"),
-        thread_ts: row.get::<String, _>("thread_ts"),
-        conversation_key: row.get::<String, _>("conversation_key"),
-        event_ts: row.get::<String, _>("event_ts"),
-        requested_by_user_id: row.get::<String, _>("requested_by_user_id"),
-        prompt_text: row.get::<String, _>("prompt_text"),
-        files_json: row.get::<String, _>("files_json"),
-        result_text: row.get::<Option<String>, _>("result_text"),
-        error_text: row.get::<Option<String>, _>("error_text"),
-        created_at: row.get::<i64, _>("created_at"),
-        started_at: row.get::<Option<i64>, _>("started_at"),
-        finished_at: row.get::<Option<i64>, _>("finished_at"),
-    }))
+    Ok(row_opt.as_ref().map(task_from_row))
 }
 
 pub async fn get_task_status(pool: &SqlitePool, task_id: i64) -> anyhow::Result<Option<String>> {
@@ -1933,6 +3443,120 @@ pub async fn list_task_traces(
         .collect())
 }
 
+pub async fn insert_task_artifact(pool: &SqlitePool, a: &TaskArtifact) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO task_artifacts (id, task_id, kind, name, content, approval_id, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        "#,
+    )
+    .bind(&a.id)
+    .bind(a.task_id)
+    .bind(&a.kind)
+    .bind(&a.name)
+    .bind(&a.content)
+    .bind(a.approval_id.as_deref())
+    .bind(a.created_at)
+    .execute(pool)
+    .await
+    .context("insert task artifact")?;
+    Ok(())
+}
+
+fn task_artifact_from_row(row: &sqlx::sqlite::SqliteRow) -> TaskArtifact {
+    TaskArtifact {
+        id: row.get::<String, _>("id"),
+        task_id: row.get::<i64, _>("task_id"),
+        kind: row.get::<String, _>("kind"),
+        name: row.get::<String, _>("name"),
+        content: row.get::<String, _>("content"),
+        approval_id: row.get::<Option<String>, _>("approval_id"),
+        created_at: row.get::<i64, _>("created_at"),
+    }
+}
+
+pub async fn list_task_artifacts(
+    pool: &SqlitePool,
+    task_id: i64,
+) -> anyhow::Result<Vec<TaskArtifact>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, task_id, kind, name, content, approval_id, created_at
+        FROM task_artifacts
+        WHERE task_id = ?1
+        ORDER BY created_at ASC, id ASC
+        "#,
+    )
+    .bind(task_id)
+    .fetch_all(pool)
+    .await
+    .context("list task artifacts")?;
+    Ok(rows.iter().map(task_artifact_from_row).collect())
+}
+
+pub async fn get_task_artifact(
+    pool: &SqlitePool,
+    task_id: i64,
+    id: &str,
+) -> anyhow::Result<Option<TaskArtifact>> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, task_id, kind, name, content, approval_id, created_at
+        FROM task_artifacts
+        WHERE task_id = ?1 AND id = ?2
+        "#,
+    )
+    .bind(task_id)
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .context("get task artifact")?;
+    Ok(row.as_ref().map(task_artifact_from_row))
+}
+
+/// Move a queued task ahead of everything else in the queue. Returns the new priority,
+/// or `None` if the task is no longer queued.
+pub async fn bump_task_priority(pool: &SqlitePool, task_id: i64) -> anyhow::Result<Option<i64>> {
+    let row = sqlx::query(
+        r#"
+        UPDATE tasks
+        SET priority = (SELECT COALESCE(MAX(priority), 0) + 1 FROM tasks WHERE status = 'queued')
+        WHERE id = ?1
+          AND status = 'queued'
+        RETURNING priority
+        "#,
+    )
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await
+    .context("bump task priority")?;
+    Ok(row.map(|r| r.get::<i64, _>("priority")))
+}
+
+/// 1-based position of a queued task in claim order (ignoring locks and dependencies).
+pub async fn queued_task_position(pool: &SqlitePool, task_id: i64) -> anyhow::Result<Option<i64>> {
+    let row = sqlx::query(
+        r#"
+        SELECT (
+          SELECT COUNT(*)
+          FROM tasks o
+          WHERE o.status = 'queued'
+            AND (o.priority > t.priority
+              OR (o.priority = t.priority AND (o.created_at < t.created_at
+                OR (o.created_at = t.created_at AND o.id < t.id))))
+        ) + 1 AS position
+        FROM tasks t
+        WHERE t.id = ?1
+          AND t.status = 'queued'
+        "#,
+    )
+    .bind(task_id)
+    .fetch_optional(pool)
+    .await
+    .context("queued task position")?;
+    Ok(row.map(|r| r.get::<i64, _>("position")))
+}
+
 pub async fn create_task_trace(
     pool: &SqlitePool,
     task_id: i64,
@@ -1955,14 +3579,48 @@ pub async fn create_task_trace(
         "#,
     )
     .bind(task_id)
-    .bind(event_type)
-    .bind(level)
-    .bind(message)
-    .bind(details)
+    .bind(event_type)
+    .bind(level)
+    .bind(message)
+    .bind(details)
+    .execute(pool)
+    .await
+    .context("insert task trace")?;
+    Ok(())
+}
+
+/// Ask a running task to stop because its deadline passed; the worker records the outcome.
+/// Replace a task's prompt and attachment list (after its audio was transcribed).
+pub async fn update_task_prompt(
+    pool: &SqlitePool,
+    task_id: i64,
+    prompt_text: &str,
+    files_json: &str,
+) -> anyhow::Result<()> {
+    sqlx::query("UPDATE tasks SET prompt_text = ?2, files_json = ?3 WHERE id = ?1")
+        .bind(task_id)
+        .bind(prompt_text)
+        .bind(files_json)
+        .execute(pool)
+        .await
+        .context("update task prompt")?;
+    Ok(())
+}
+
+pub async fn request_task_deadline_stop(pool: &SqlitePool, task_id: i64) -> anyhow::Result<bool> {
+    let res = sqlx::query(
+        r#"
+        UPDATE tasks
+        SET status = 'cancel_requested'
+        WHERE id = ?1
+          AND status = 'running'
+        "#,
+    )
+    .bind(task_id)
     .execute(pool)
     .await
-    .context("insert task trace")?;
-    Ok(())
+    .context("request task deadline stop")?;
+    Ok(res.rows_affected() == 1)
 }
 
 pub async fn cancel_task(pool: &SqlitePool, task_id: i64) -> anyhow::Result<bool> {
@@ -1990,6 +3648,37 @@ pub async fn cancel_task(pool: &SqlitePool, task_id: i64) -> anyhow::Result<bool
     Ok(res.rows_affected() == 1)
 }
 
+/// Cancel every queued task for a channel, returning their ids.
+pub async fn cancel_queued_channel_tasks(
+    pool: &SqlitePool,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+    reason: &str,
+) -> anyhow::Result<Vec<i64>> {
+    let rows = sqlx::query(
+        r#"
+        UPDATE tasks
+        SET status = 'cancelled',
+            error_text = ?4,
+            finished_at = unixepoch()
+        WHERE provider = ?1 AND workspace_id = ?2 AND channel_id = ?3
+          AND status = 'queued'
+        RETURNING id
+        "#,
+    )
+    .bind(provider)
+    .bind(workspace_id)
+    .bind(channel_id)
+    .bind(reason)
+    .fetch_all(pool)
+    .await
+    .context("cancel queued channel tasks")?;
+    let mut ids: Vec<i64> = rows.iter().map(|r| r.get("id")).collect();
+    ids.sort_unstable();
+    Ok(ids)
+}
+
 pub async fn retry_task(pool: &SqlitePool, task_id: i64) -> anyhow::Result<bool> {
     let res = sqlx::query(
         r#"
@@ -2030,7 +3719,13 @@ pub async fn list_recent_tasks(pool: &SqlitePool, limit: i64) -> anyhow::Result<
           error_text,
           created_at,
           started_at,
-          finished_at
+          finished_at,
+          depends_on_task_id,
+          on_dependency_failure,
+          is_synthetic,
+          skip_response_cache,
+          options_json,
+          rerun_of_task_id
         FROM tasks
         ORDER BY created_at DESC, id DESC
         LIMIT ?1
@@ -2041,32 +3736,322 @@ pub async fn list_recent_tasks(pool: &SqlitePool, limit: i64) -> anyhow::Result<
     .await
     .context("list tasks")?;
 
+    Ok(rows.iter().map(task_from_row).collect())
+}
+
+/// A Slack user's own tasks (not admin test runs), newest first.
+pub async fn list_user_tasks(
+    pool: &SqlitePool,
+    user_id: &str,
+    limit: i64,
+) -> anyhow::Result<Vec<Task>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT
+          id,
+          status,
+          provider,
+          is_proactive,
+          workspace_id,
+          channel_id,
+          thread_ts,
+          conversation_key,
+          event_ts,
+          requested_by_user_id,
+          prompt_text,
+          files_json,
+          result_text,
+          error_text,
+          created_at,
+          started_at,
+          finished_at,
+          depends_on_task_id,
+          on_dependency_failure,
+          is_synthetic,
+          skip_response_cache,
+          options_json,
+          rerun_of_task_id
+        FROM tasks
+        WHERE provider = 'slack' AND requested_by_user_id = ?1 AND is_synthetic = 0
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("list user tasks")?;
+
+    Ok(rows.iter().map(task_from_row).collect())
+}
+
+/// Non-synthetic tasks created at or after `since`, oldest first (analytics export).
+pub async fn list_tasks_created_since(
+    pool: &SqlitePool,
+    since: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<Task>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT
+          id,
+          status,
+          provider,
+          is_proactive,
+          workspace_id,
+          channel_id,
+          thread_ts,
+          conversation_key,
+          event_ts,
+          requested_by_user_id,
+          prompt_text,
+          files_json,
+          result_text,
+          error_text,
+          created_at,
+          started_at,
+          finished_at,
+          depends_on_task_id,
+          on_dependency_failure,
+          is_synthetic,
+          skip_response_cache,
+          options_json,
+          rerun_of_task_id
+        FROM tasks
+        WHERE created_at >= ?1 AND is_synthetic = 0
+        ORDER BY created_at ASC, id ASC
+        LIMIT ?2
+        "#,
+    )
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("list tasks created since")?;
+
+    Ok(rows.iter().map(task_from_row).collect())
+}
+
+/// Most recent succeeded tasks in a conversation before `before_task_id`, oldest first.
+/// Returns `(id, prompt_text, result_text)`.
+pub async fn list_prior_conversation_tasks(
+    pool: &SqlitePool,
+    conversation_key: &str,
+    before_task_id: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<(i64, String, String)>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, prompt_text, result_text
+        FROM tasks
+        WHERE conversation_key = ?1
+          AND id < ?2
+          AND status = 'succeeded'
+        ORDER BY id DESC
+        LIMIT ?3
+        "#,
+    )
+    .bind(conversation_key)
+    .bind(before_task_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("list prior conversation tasks")?;
+
     Ok(rows
         .into_iter()
-        .map(|row| Task {
-            id: row.get::<i64, _>("id"),
-            status: row.get::<String, _>("status"),
-            provider: row
-                .get::<Option<String>, _>("provider")
-                .unwrap_or_else(|| "slack".to_string()),
-            is_proactive: row.get::<i64, _>("is_proactive") != 0,
+        .rev()
+        .map(|row| {
+            (
+                row.get::<i64, _>("id"),
+                row.get::<String, _>("prompt_text"),
+                row.get::<Option<String>, _>("result_text")
+                    .unwrap_or_default(),
+            )
+        })
+        .collect())
+}
+
+pub async fn insert_response_cache(
+    pool: &SqlitePool,
+    entry: &ResponseCacheEntry,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO response_cache (
+          workspace_id, channel_id, prompt_hash, prompt_text, embedding_json,
+          task_id, response_text, created_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, unixepoch())
+        "#,
+    )
+    .bind(&entry.workspace_id)
+    .bind(&entry.channel_id)
+    .bind(&entry.prompt_hash)
+    .bind(&entry.prompt_text)
+    .bind(entry.embedding_json.as_deref())
+    .bind(entry.task_id)
+    .bind(&entry.response_text)
+    .execute(pool)
+    .await
+    .context("insert response cache entry")?;
+    Ok(())
+}
+
+/// Recent cache entries for a channel, newest first.
+pub async fn list_response_cache(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    channel_id: &str,
+    since: i64,
+    limit: i64,
+) -> anyhow::Result<Vec<ResponseCacheEntry>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT workspace_id, channel_id, prompt_hash, prompt_text, embedding_json,
+               task_id, response_text, created_at
+        FROM response_cache
+        WHERE workspace_id = ?1
+          AND channel_id = ?2
+          AND created_at >= ?3
+        ORDER BY created_at DESC, id DESC
+        LIMIT ?4
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(channel_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .context("list response cache")?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ResponseCacheEntry {
             workspace_id: row.get::<String, _>("workspace_id"),
             channel_id: row.get::<String, _>("channel_id"),
-            thread_ts: row.get::<String, _>("thread_ts"),
-            conversation_key: row.get::<String, _>("conversation_key"),
-            event_ts: row.get::<String, _>("event_ts"),
-            requested_by_user_id: row.get::<String, _>("requested_by_user_id"),
+            prompt_hash: row.get::<String, _>("prompt_hash"),
             prompt_text: row.get::<String, _>("prompt_text"),
-            files_json: row.get::<String, _>("files_json"),
-            result_text: row.get::<Option<String>, _>("result_text"),
-            error_text: row.get::<Option<String>, _>("error_text"),
+            embedding_json: row.get::<Option<String>, _>("embedding_json"),
+            task_id: row.get::<i64, _>("task_id"),
+            response_text: row.get::<String, _>("response_text"),
             created_at: row.get::<i64, _>("created_at"),
-            started_at: row.get::<Option<i64>, _>("started_at"),
-            finished_at: row.get::<Option<i64>, _>("finished_at"),
         })
         .collect())
 }
 
+pub async fn delete_expired_response_cache(
+    pool: &SqlitePool,
+    max_age_seconds: i64,
+) -> anyhow::Result<u64> {
+    let res = sqlx::query("DELETE FROM response_cache WHERE created_at < unixepoch() - ?1")
+        .bind(max_age_seconds)
+        .execute(pool)
+        .await
+        .context("delete expired response cache entries")?;
+    Ok(res.rows_affected())
+}
+
+/// Queue a fresh run of a task's prompt that bypasses the response cache.
+pub async fn enqueue_regenerate_task(
+    pool: &SqlitePool,
+    task_id: i64,
+    requested_by_user_id: &str,
+) -> anyhow::Result<Option<i64>> {
+    let res = sqlx::query(
+        r#"
+        INSERT INTO tasks (
+          provider,
+          status,
+          workspace_id,
+          channel_id,
+          thread_ts,
+          conversation_key,
+          event_ts,
+          requested_by_user_id,
+          prompt_text,
+          files_json,
+          skip_response_cache,
+          created_at
+        )
+        SELECT provider, 'queued', workspace_id, channel_id, thread_ts, conversation_key,
+               event_ts, ?2, prompt_text, files_json, 1, unixepoch()
+        FROM tasks
+        WHERE id = ?1
+        "#,
+    )
+    .bind(task_id)
+    .bind(requested_by_user_id)
+    .execute(pool)
+    .await
+    .context("enqueue regenerate task")?;
+
+    Ok((res.rows_affected() > 0).then(|| res.last_insert_rowid()))
+}
+
+/// Queue a copy of a failed or cancelled task with new options, linked to the original.
+/// Returns `None` when the task doesn't exist or didn't fail.
+pub async fn enqueue_rerun_task(
+    pool: &SqlitePool,
+    task_id: i64,
+    options_json: &str,
+) -> anyhow::Result<Option<i64>> {
+    let res = sqlx::query(
+        r#"
+        INSERT INTO tasks (
+          provider,
+          status,
+          is_proactive,
+          workspace_id,
+          channel_id,
+          thread_ts,
+          conversation_key,
+          event_ts,
+          requested_by_user_id,
+          prompt_text,
+          files_json,
+          is_synthetic,
+          skip_response_cache,
+          options_json,
+          rerun_of_task_id,
+          created_at
+        )
+        SELECT provider, 'queued', is_proactive, workspace_id, channel_id, thread_ts,
+               conversation_key, event_ts, requested_by_user_id, prompt_text, files_json,
+               is_synthetic, 1, ?2, id, unixepoch()
+        FROM tasks
+        WHERE id = ?1
+          AND status IN ('failed', 'cancelled')
+        "#,
+    )
+    .bind(task_id)
+    .bind(options_json)
+    .execute(pool)
+    .await
+    .context("enqueue rerun task")?;
+
+    Ok((res.rows_affected() > 0).then(|| res.last_insert_rowid()))
+}
+
+/// Ids of the re-runs of a task, oldest first.
+pub async fn list_task_reruns(pool: &SqlitePool, task_id: i64) -> anyhow::Result<Vec<i64>> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id
+        FROM tasks
+        WHERE rerun_of_task_id = ?1
+        ORDER BY id ASC
+        "#,
+    )
+    .bind(task_id)
+    .fetch_all(pool)
+    .await
+    .context("list task reruns")?;
+    Ok(rows.into_iter().map(|r| r.get::<i64, _>("id")).collect())
+}
+
 pub async fn get_session(
     pool: &SqlitePool,
     conversation_key: &str,
@@ -2150,6 +4135,23 @@ pub async fn insert_telegram_message(
     Ok(())
 }
 
+/// Replace a stored message's text (a voice message once it has been transcribed).
+pub async fn update_telegram_message_text(
+    pool: &SqlitePool,
+    chat_id: &str,
+    message_id: i64,
+    text: &str,
+) -> anyhow::Result<()> {
+    sqlx::query("UPDATE telegram_messages SET text = ?3 WHERE chat_id = ?1 AND message_id = ?2")
+        .bind(chat_id)
+        .bind(message_id)
+        .bind(text)
+        .execute(pool)
+        .await
+        .context("update telegram message text")?;
+    Ok(())
+}
+
 pub async fn fetch_telegram_context(
     pool: &SqlitePool,
     chat_id: &str,
@@ -2405,3 +4407,162 @@ pub async fn delete_observational_memory(
         .context("delete observational memory")?;
     Ok(res.rows_affected() == 1)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn claim_waits_for_a_busy_reply_thread() {
+        let db = crate::test_support::TestDb::new().await;
+        let pool = db.pool.clone();
+        let enqueue = |thread_ts: &'static str, event_ts: &'static str| {
+            let pool = pool.clone();
+            async move {
+                enqueue_task(
+                    &pool,
+                    &NewTask {
+                        provider: "slack",
+                        workspace_id: "T1",
+                        channel_id: "C1",
+                        thread_ts,
+                        event_ts,
+                        requested_by_user_id: "U1",
+                        prompt_text: "hi",
+                        ..Default::default()
+                    },
+                )
+                .await
+                .unwrap()
+            }
+        };
+        // A top-level mention and a reply in its thread have different conversations but
+        // post into the same thread.
+        let mention = enqueue("100.1", "100.1").await;
+        let follow_up = enqueue("100.1", "100.2").await;
+        let other = enqueue("200.1", "200.2").await;
+
+        let claim = |worker: &'static str| {
+            let pool = pool.clone();
+            async move { claim_next_task(&pool, worker, 60).await.unwrap() }
+        };
+        let (first, first_claim) = claim("w1").await.unwrap();
+        assert_eq!(first.id, mention);
+        assert_eq!(claim("w2").await.map(|(t, _)| t.id), Some(other));
+        assert!(claim("w3").await.is_none());
+        assert!(complete_task_success(&pool, mention, &first_claim, "done")
+            .await
+            .unwrap());
+        assert_eq!(claim("w3").await.map(|(t, _)| t.id), Some(follow_up));
+    }
+
+    #[tokio::test]
+    async fn leader_lock_is_exclusive_until_its_lease_expires() {
+        use super::try_acquire_or_renew_worker_lock as acquire;
+        let db = crate::test_support::TestDb::new().await;
+        let pool = db.pool.clone();
+
+        assert!(acquire(&pool, "w1", 60).await.unwrap());
+        assert!(!acquire(&pool, "w2", 60).await.unwrap());
+        // The holder renews its own lease.
+        assert!(acquire(&pool, "w1", 60).await.unwrap());
+        assert_eq!(
+            get_worker_lock_owner(&pool).await.unwrap().as_deref(),
+            Some("w1")
+        );
+        assert!(acquire(&pool, "w1", 5).await.is_err());
+
+        // w1 stops renewing; once the lease lapses another worker takes over.
+        sqlx::query("UPDATE worker_lock SET lease_until = unixepoch() - 1 WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(acquire(&pool, "w2", 60).await.unwrap());
+        assert!(!acquire(&pool, "w1", 60).await.unwrap());
+        assert_eq!(
+            get_worker_lock_owner(&pool).await.unwrap().as_deref(),
+            Some("w2")
+        );
+    }
+
+    #[tokio::test]
+    async fn stalled_worker_cannot_finish_a_requeued_task() {
+        let db = crate::test_support::TestDb::new().await;
+        let pool = db.pool.clone();
+        let task_id = enqueue_task(
+            &pool,
+            &NewTask {
+                provider: "slack",
+                workspace_id: "T1",
+                channel_id: "C1",
+                thread_ts: "",
+                event_ts: "100.1",
+                requested_by_user_id: "U1",
+                prompt_text: "hi",
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        upsert_worker_heartbeat(&pool, "w1", "host", false, 1)
+            .await
+            .unwrap();
+        let (_, stale) = claim_next_task(&pool, "w1", 60).await.unwrap().unwrap();
+        // A live worker's task is left alone.
+        assert_eq!(requeue_orphaned_tasks(&pool, 45).await.unwrap(), 0);
+        assert!(task_claim_held(&pool, task_id, &stale).await.unwrap());
+
+        // w1 stops heartbeating; the leader hands the task to w2.
+        sqlx::query("UPDATE worker_heartbeats SET last_seen_at = unixepoch() - 60")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(requeue_orphaned_tasks(&pool, 45).await.unwrap(), 1);
+        upsert_worker_heartbeat(&pool, "w2", "host", false, 1)
+            .await
+            .unwrap();
+        let (_, fresh) = claim_next_task(&pool, "w2", 60).await.unwrap().unwrap();
+        assert!(fresh.generation > stale.generation);
+
+        // w1 wakes up: its claim is gone and its result is dropped.
+        assert!(!task_claim_held(&pool, task_id, &stale).await.unwrap());
+        assert!(!complete_task_success(&pool, task_id, &stale, "late")
+            .await
+            .unwrap());
+        assert!(complete_task_success(&pool, task_id, &fresh, "done")
+            .await
+            .unwrap());
+        let task = get_task(&pool, task_id).await.unwrap().unwrap();
+        assert_eq!(task.result_text.as_deref(), Some("done"));
+
+        // A worker that re-claims its own re-queued task still gets a new generation.
+        let again = enqueue_task(
+            &pool,
+            &NewTask {
+                provider: "slack",
+                workspace_id: "T1",
+                channel_id: "C2",
+                thread_ts: "",
+                event_ts: "200.1",
+                requested_by_user_id: "U1",
+                prompt_text: "hi",
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let (_, first) = claim_next_task(&pool, "w2", 60).await.unwrap().unwrap();
+        sqlx::query("UPDATE worker_heartbeats SET last_seen_at = unixepoch() - 60")
+            .execute(&pool)
+            .await
+            .unwrap();
+        requeue_orphaned_tasks(&pool, 45).await.unwrap();
+        upsert_worker_heartbeat(&pool, "w2", "host", false, 1)
+            .await
+            .unwrap();
+        let (_, second) = claim_next_task(&pool, "w2", 60).await.unwrap().unwrap();
+        assert!(!task_claim_held(&pool, again, &first).await.unwrap());
+        assert!(task_claim_held(&pool, again, &second).await.unwrap());
+    }
+}
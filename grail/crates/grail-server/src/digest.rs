@@ -0,0 +1,451 @@
+//! Channel digests: the opt-in monthly usage report.
+//!
+//! Anyone in a Slack channel or Telegram chat can send `usage report on` (or `off`) to
+//! the bot; `usage report` on its own posts one right away. Once a month the leader
+//! posts what the bot did there: tasks completed, failed and cancelled, an estimate of
+//! the time saved (`usage_report_minutes_per_task` per completed task), the top
+//! requesters, notable failures and denied command approvals. Finished tasks are only
+//! kept for 30 days, so each report covers the last 30 days rather than the calendar
+//! month.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tracing::{info, warn};
+
+use crate::models::UsageReportChannel;
+use crate::slack::SlackClient;
+use crate::telegram::TelegramClient;
+use crate::AppState;
+
+/// Days covered by a report (the task retention window).
+pub const WINDOW_DAYS: i64 = 30;
+const TOP_REQUESTERS: usize = 3;
+const NOTABLE_FAILURES: i64 = 3;
+const FAILURE_PREVIEW_CHARS: usize = 120;
+/// Attempts at one month's report before it is given up until the next month.
+pub const MAX_ATTEMPTS: i64 = 5;
+/// Wait after the first failed attempt; doubled after each further one.
+const RETRY_BASE_SECONDS: i64 = 15 * 60;
+
+static COMMAND_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?i)^(?:monthly\s+)?usage\s+reports?(?:\s+(on|off|enable|disable|subscribe|unsubscribe|stop|now))?\s*[.!]*$",
+    )
+    .expect("usage report command regex must compile")
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    On,
+    Off,
+    Now,
+}
+
+pub fn parse_command(text: &str) -> Option<Command> {
+    let caps = COMMAND_RE.captures(text.trim())?;
+    Some(
+        match caps
+            .get(1)
+            .map(|m| m.as_str().to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("on" | "enable" | "subscribe") => Command::On,
+            Some("off" | "disable" | "unsubscribe" | "stop") => Command::Off,
+            _ => Command::Now,
+        },
+    )
+}
+
+/// `YYYY-MM` (UTC) of `ts`; a report is due when this differs from the last one posted.
+pub fn period(ts: i64) -> String {
+    chrono::DateTime::<chrono::Utc>::from_timestamp(ts, 0)
+        .map(|dt| dt.format("%Y-%m").to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UsageReport {
+    pub succeeded: i64,
+    pub failed: i64,
+    pub cancelled: i64,
+    /// `(user id, tasks)`, most active first.
+    pub top_requesters: Vec<(String, i64)>,
+    /// `(task id, error preview)`, newest first.
+    pub failures: Vec<(i64, String)>,
+    pub denied_approvals: i64,
+}
+
+/// Usage in one channel since `since` (admin test runs excluded).
+pub async fn collect(
+    state: &AppState,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+    since: i64,
+) -> anyhow::Result<UsageReport> {
+    let pool = &state.pool;
+    let mut report = UsageReport::default();
+    for (status, count) in
+        crate::db::count_channel_tasks_by_status(pool, provider, workspace_id, channel_id, since)
+            .await?
+    {
+        match status.as_str() {
+            "succeeded" => report.succeeded = count,
+            "failed" => report.failed = count,
+            "cancelled" => report.cancelled = count,
+            _ => {}
+        }
+    }
+    report.top_requesters = crate::db::top_channel_requesters(
+        pool,
+        provider,
+        workspace_id,
+        channel_id,
+        since,
+        TOP_REQUESTERS as i64,
+    )
+    .await?;
+    report.failures = crate::db::recent_channel_failures(
+        pool,
+        provider,
+        workspace_id,
+        channel_id,
+        since,
+        NOTABLE_FAILURES,
+    )
+    .await?
+    .into_iter()
+    .map(|(id, error)| {
+        let (error, _) = crate::secrets::redact_secrets(&error);
+        (id, grail_text::truncate_line(&error, FAILURE_PREVIEW_CHARS))
+    })
+    .collect();
+    report.denied_approvals =
+        crate::db::count_channel_denied_approvals(pool, workspace_id, channel_id, since).await?;
+    Ok(report)
+}
+
+/// `45m`, `3h`, `12h 30m`.
+pub fn format_minutes(minutes: i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h {m}m"),
+    }
+}
+
+/// The report as a chat message. Slack mentions requesters and links failures to the
+/// dashboard; Telegram gets plain text.
+pub fn render(
+    provider: &str,
+    report: &UsageReport,
+    minutes_per_task: i64,
+    base_url: Option<&str>,
+) -> String {
+    let slack = provider == "slack";
+    let mut msg = if slack {
+        format!("*Usage report (last {WINDOW_DAYS} days)*\n")
+    } else {
+        format!("Usage report (last {WINDOW_DAYS} days)\n")
+    };
+    let finished = report.succeeded + report.failed + report.cancelled;
+    if finished == 0 {
+        msg.push_str("No tasks were run in this channel.\n");
+        return msg;
+    }
+    msg.push_str(&format!(
+        "Tasks: {} completed, {} failed, {} cancelled\n",
+        report.succeeded, report.failed, report.cancelled
+    ));
+    if minutes_per_task > 0 && report.succeeded > 0 {
+        msg.push_str(&format!(
+            "Time saved: about {} (estimated at {minutes_per_task} min per completed task)\n",
+            format_minutes(report.succeeded.saturating_mul(minutes_per_task))
+        ));
+    }
+    if !report.top_requesters.is_empty() {
+        let people: Vec<String> = report
+            .top_requesters
+            .iter()
+            .map(|(user, n)| {
+                if slack {
+                    format!("<@{user}> ({n})")
+                } else {
+                    format!("{user} ({n})")
+                }
+            })
+            .collect();
+        msg.push_str(&format!("Top requesters: {}\n", people.join(", ")));
+    }
+    if report.denied_approvals > 0 {
+        msg.push_str(&format!(
+            "Denied command approvals: {}\n",
+            report.denied_approvals
+        ));
+    }
+    if !report.failures.is_empty() {
+        msg.push_str("Notable failures:\n");
+        for (id, error) in &report.failures {
+            let task = match base_url.map(str::trim).filter(|b| !b.is_empty()) {
+                Some(base) if slack => {
+                    format!("<{}|#{id}>", crate::task_links::web_link(Some(base), *id))
+                }
+                _ => format!("#{id}"),
+            };
+            let error = if error.is_empty() {
+                "no error message"
+            } else {
+                error
+            };
+            msg.push_str(&format!("- {task}: {error}\n"));
+        }
+    }
+    msg
+}
+
+async fn report_text(
+    state: &AppState,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+) -> anyhow::Result<String> {
+    let settings = crate::db::get_settings(&state.pool).await?;
+    let since = chrono::Utc::now().timestamp() - WINDOW_DAYS * 86_400;
+    let report = collect(state, provider, workspace_id, channel_id, since).await?;
+    Ok(render(
+        provider,
+        &report,
+        settings.usage_report_minutes_per_task,
+        state.config.base_url.as_deref(),
+    ))
+}
+
+/// Reply to a `usage report` command sent in a channel.
+pub async fn handle_command(
+    state: &AppState,
+    cmd: Command,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+    actor: &str,
+) -> anyhow::Result<String> {
+    match cmd {
+        Command::On => {
+            let now = period(chrono::Utc::now().timestamp());
+            let added = crate::db::enable_usage_report(
+                &state.pool,
+                provider,
+                workspace_id,
+                channel_id,
+                actor,
+                &now,
+            )
+            .await?;
+            if added {
+                info!(
+                    provider,
+                    workspace_id, channel_id, actor, "usage report enabled"
+                );
+            }
+            Ok(if added {
+                "Monthly usage reports are on for this channel. The first one arrives at the start of next month; send `usage report` to see one now, or `usage report off` to stop them."
+            } else {
+                "Monthly usage reports are already on for this channel."
+            }
+            .to_string())
+        }
+        Command::Off => {
+            let removed =
+                crate::db::disable_usage_report(&state.pool, provider, workspace_id, channel_id)
+                    .await?;
+            if removed {
+                info!(
+                    provider,
+                    workspace_id, channel_id, actor, "usage report disabled"
+                );
+            }
+            Ok(if removed {
+                "Monthly usage reports are off for this channel."
+            } else {
+                "Monthly usage reports weren't on for this channel."
+            }
+            .to_string())
+        }
+        Command::Now => report_text(state, provider, workspace_id, channel_id).await,
+    }
+}
+
+async fn post(state: &AppState, channel: &UsageReportChannel, text: &str) -> anyhow::Result<()> {
+    match channel.provider.as_str() {
+        "slack" => {
+            let token = crate::secrets::load_slack_bot_token_opt(state)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("SLACK_BOT_TOKEN missing"))?;
+            SlackClient::new(state.http.clone(), token)
+                .post_message(&channel.channel_id, None, text.trim())
+                .await?;
+        }
+        "telegram" => {
+            let token = crate::secrets::load_telegram_bot_token_opt(state)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("TELEGRAM_BOT_TOKEN missing"))?;
+            TelegramClient::new(state.http.clone(), token)
+                .send_message(&channel.channel_id, None, text.trim())
+                .await?;
+        }
+        other => anyhow::bail!("usage reports are not supported for provider {other}"),
+    }
+    Ok(())
+}
+
+/// Post the reports due this month (only the leader runs this). A report that fails to
+/// post is retried with backoff, and given up for the month after [`MAX_ATTEMPTS`].
+pub async fn send_due_reports(state: &AppState) {
+    let ts = chrono::Utc::now().timestamp();
+    let now = period(ts);
+    let channels = match crate::db::list_usage_report_channels(&state.pool).await {
+        Ok(v) => v,
+        Err(err) => {
+            warn!(error = %err, "failed to list usage report channels");
+            return;
+        }
+    };
+    for channel in channels
+        .iter()
+        .filter(|c| c.last_period != now && c.retry_at.is_none_or(|t| t <= ts))
+    {
+        let sent = async {
+            let text = report_text(
+                state,
+                &channel.provider,
+                &channel.workspace_id,
+                &channel.channel_id,
+            )
+            .await?;
+            post(state, channel, &text).await?;
+            crate::db::mark_usage_report_sent(&state.pool, channel, &now).await
+        }
+        .await;
+        match sent {
+            Ok(()) => info!(
+                provider = %channel.provider,
+                channel_id = %channel.channel_id,
+                period = %now,
+                "posted usage report"
+            ),
+            Err(err) => {
+                let attempts = channel.failures + 1;
+                let recorded = match retry_delay(attempts) {
+                    Some(delay) => {
+                        warn!(
+                            error = %err,
+                            provider = %channel.provider,
+                            channel_id = %channel.channel_id,
+                            attempts,
+                            retry_in_seconds = delay,
+                            "failed to post usage report"
+                        );
+                        crate::db::record_usage_report_failure(&state.pool, channel, ts + delay)
+                            .await
+                    }
+                    None => {
+                        warn!(
+                            error = %err,
+                            provider = %channel.provider,
+                            channel_id = %channel.channel_id,
+                            period = %now,
+                            "failed to post usage report; giving up until next month"
+                        );
+                        crate::db::mark_usage_report_sent(&state.pool, channel, &now).await
+                    }
+                };
+                if let Err(err) = recorded {
+                    warn!(error = %err, channel_id = %channel.channel_id, "failed to record usage report failure");
+                }
+            }
+        }
+    }
+}
+
+/// How long to wait after the `attempts`-th failed attempt, or `None` once the month's
+/// report should be given up.
+pub fn retry_delay(attempts: i64) -> Option<i64> {
+    (attempts < MAX_ATTEMPTS).then(|| RETRY_BASE_SECONDS << (attempts - 1).clamp(0, 16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_report_commands_and_rendering() {
+        assert_eq!(parse_command("usage report on"), Some(Command::On));
+        assert_eq!(
+            parse_command("Monthly usage reports OFF"),
+            Some(Command::Off)
+        );
+        assert_eq!(parse_command("usage report"), Some(Command::Now));
+        assert_eq!(parse_command("usage report for last week"), None);
+        assert_eq!(period(1_775_000_000), "2026-03");
+        assert_eq!(format_minutes(45), "45m");
+        assert_eq!(format_minutes(180), "3h");
+        assert_eq!(format_minutes(750), "12h 30m");
+
+        let empty = render("slack", &UsageReport::default(), 10, None);
+        assert!(empty.ends_with("No tasks were run in this channel.\n"));
+
+        let report = UsageReport {
+            succeeded: 18,
+            failed: 2,
+            cancelled: 1,
+            top_requesters: vec![("U1".to_string(), 12), ("U2".to_string(), 9)],
+            failures: vec![(42, "timed out".to_string()), (40, String::new())],
+            denied_approvals: 3,
+        };
+        let slack = render("slack", &report, 10, Some("https://grail.example.com/"));
+        assert!(slack.contains("Tasks: 18 completed, 2 failed, 1 cancelled\n"));
+        assert!(slack.contains("Time saved: about 3h (estimated at 10 min per completed task)"));
+        assert!(slack.contains("Top requesters: <@U1> (12), <@U2> (9)\n"));
+        assert!(slack.contains("Denied command approvals: 3\n"));
+        assert!(slack.contains("- <https://grail.example.com/admin/tasks/42|#42>: timed out\n"));
+        assert!(
+            slack.contains("- <https://grail.example.com/admin/tasks/40|#40>: no error message\n")
+        );
+
+        let telegram = render("telegram", &report, 0, Some("https://grail.example.com"));
+        assert!(!telegram.contains("Time saved"));
+        assert!(telegram.contains("Top requesters: U1 (12), U2 (9)\n"));
+        assert!(telegram.contains("- #42: timed out\n"));
+    }
+
+    #[tokio::test]
+    async fn failed_usage_reports_back_off_then_give_up_for_the_month() {
+        assert_eq!(retry_delay(1), Some(15 * 60));
+        assert_eq!(retry_delay(2), Some(30 * 60));
+        assert_eq!(retry_delay(MAX_ATTEMPTS), None);
+
+        let db = crate::test_support::TestDb::new().await;
+        let pool = db.pool.clone();
+        crate::db::enable_usage_report(&pool, "slack", "T1", "C1", "U1", "2026-09")
+            .await
+            .unwrap();
+        let channel = crate::db::list_usage_report_channels(&pool).await.unwrap()[0].clone();
+        assert_eq!((channel.failures, channel.retry_at), (0, None));
+
+        crate::db::record_usage_report_failure(&pool, &channel, 1_000)
+            .await
+            .unwrap();
+        crate::db::record_usage_report_failure(&pool, &channel, 2_000)
+            .await
+            .unwrap();
+        let channel = crate::db::list_usage_report_channels(&pool).await.unwrap()[0].clone();
+        assert_eq!((channel.failures, channel.retry_at), (2, Some(2_000)));
+
+        crate::db::mark_usage_report_sent(&pool, &channel, "2026-10")
+            .await
+            .unwrap();
+        let channel = crate::db::list_usage_report_channels(&pool).await.unwrap()[0].clone();
+        assert_eq!(channel.last_period, "2026-10");
+        assert_eq!((channel.failures, channel.retry_at), (0, None));
+    }
+}
@@ -0,0 +1,499 @@
+//! Time-boxed elevated permissions for one channel.
+//!
+//! During an incident a channel can be given `full` permissions for a bounded window
+//! (`elevate 2h db failover`) instead of flipping the global `permissions_mode` and
+//! forgetting to flip it back. Only users in the provider's elevate list
+//! (`slack_elevate_allow_from`, `telegram_elevate_allow_from`) can elevate from chat;
+//! admins can also do it from the dashboard. Tasks in the channel pick up `full` while the
+//! session lasts and are stopped when it ends, so no command runs elevated past the window;
+//! new tasks revert on their own (`end elevation` ends it early). Every session is kept as
+//! an audit entry, each elevated task gets a `task.elevated` trace, and grants and early
+//! ends are posted to `slack_admin_channel`.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tracing::{info, warn};
+
+use crate::digest::format_minutes;
+use crate::models::{ElevatedSession, PermissionsMode, Settings, Task, TaskOptions};
+use crate::slack::SlackClient;
+use crate::telegram::TelegramClient;
+use crate::AppState;
+
+pub const DEFAULT_MINUTES: i64 = 60;
+/// Longer windows have to be granted again; that's the point.
+pub const MAX_HOURS: i64 = 24;
+const MAX_REASON_CHARS: usize = 200;
+
+static GRANT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?is)^elevate(?:\s+(?:for\s+)?(\d{1,4})\s*(m|mins?|minutes?|h|hrs?|hours?)\b(?:\s*[:,\-–]?\s*(.*))?)?\s*[.!]*$",
+    )
+    .expect("elevate command regex must compile")
+});
+
+static END_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(?:(?:end|stop|revoke|cancel)\s+elevation|de-?elevate|unelevate)\s*[.!]*$")
+        .expect("end elevation command regex must compile")
+});
+
+static STATUS_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^elevation(?:\s+status)?\s*[?.!]*$")
+        .expect("elevation status command regex must compile")
+});
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Grant { minutes: i64, reason: String },
+    End,
+    Status,
+}
+
+/// `elevate` alone grants [`DEFAULT_MINUTES`]; anything after it must start with a
+/// duration (`elevate 2h`, `elevate for 90m: failover`), so ordinary prompts that happen to
+/// start with the word aren't taken for the command.
+pub fn parse_command(text: &str) -> Option<Command> {
+    let text = text.trim();
+    if END_RE.is_match(text) {
+        return Some(Command::End);
+    }
+    if STATUS_RE.is_match(text) {
+        return Some(Command::Status);
+    }
+    let caps = GRANT_RE.captures(text)?;
+    let minutes = match (caps.get(1), caps.get(2)) {
+        (Some(n), Some(unit)) => {
+            let n: i64 = n.as_str().parse().ok()?;
+            if unit.as_str().to_ascii_lowercase().starts_with('h') {
+                n * 60
+            } else {
+                n
+            }
+        }
+        _ => DEFAULT_MINUTES,
+    };
+    let reason = caps
+        .get(3)
+        .map(|m| m.as_str().split_whitespace().collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+    Some(Command::Grant {
+        minutes: minutes.clamp(1, MAX_HOURS * 60),
+        reason: grail_text::truncate_line(&reason, MAX_REASON_CHARS),
+    })
+}
+
+/// The channel's active session, if any.
+pub async fn active(
+    state: &AppState,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+) -> anyhow::Result<Option<ElevatedSession>> {
+    crate::db::active_elevated_session(
+        &state.pool,
+        provider,
+        workspace_id,
+        channel_id,
+        chrono::Utc::now().timestamp(),
+    )
+    .await
+}
+
+/// The session a task runs under, if it is elevated: the workspace is in `read` and the
+/// task didn't ask for `read` itself.
+pub async fn for_task(
+    state: &AppState,
+    task: &Task,
+    options: &TaskOptions,
+) -> anyhow::Result<Option<ElevatedSession>> {
+    if options.permissions_mode == Some(PermissionsMode::Read) {
+        return Ok(None);
+    }
+    let settings = crate::db::get_settings(&state.pool).await?;
+    if settings.permissions_mode != PermissionsMode::Read {
+        return Ok(None);
+    }
+    active(state, &task.provider, &task.workspace_id, &task.channel_id).await
+}
+
+/// Slack and Telegram user ids are separate namespaces; each provider has its own list.
+pub fn may_elevate(settings: &Settings, provider: &str, actor: &str) -> bool {
+    let allow_from = match provider {
+        "slack" => &settings.slack_elevate_allow_from,
+        "telegram" => &settings.telegram_elevate_allow_from,
+        _ => return false,
+    };
+    crate::parse_allow_from(allow_from).contains(actor)
+}
+
+fn until(ts: i64) -> String {
+    chrono::DateTime::<chrono::Utc>::from_timestamp(ts, 0)
+        .map(|dt| dt.format("%H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
+fn describe(session: &ElevatedSession, now: i64) -> String {
+    let left = ((session.ends_at - now).max(0) + 59) / 60;
+    let mut msg = format!(
+        "Full permissions are on in this channel until {} ({} left), granted by {}",
+        until(session.ends_at),
+        format_minutes(left),
+        session.granted_by
+    );
+    if !session.reason.is_empty() {
+        msg.push_str(&format!(" for \"{}\"", session.reason));
+    }
+    msg.push('.');
+    msg
+}
+
+/// Grant `full` permissions in one channel for `minutes` (clamped to [`MAX_HOURS`]).
+pub async fn grant(
+    state: &AppState,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+    granted_by: &str,
+    minutes: i64,
+    reason: &str,
+) -> anyhow::Result<ElevatedSession> {
+    let now = chrono::Utc::now().timestamp();
+    let session = ElevatedSession {
+        id: crate::random_id("elev"),
+        provider: provider.to_string(),
+        workspace_id: workspace_id.to_string(),
+        channel_id: channel_id.to_string(),
+        granted_by: granted_by.to_string(),
+        reason: reason.trim().to_string(),
+        started_at: now,
+        ends_at: now + minutes.clamp(1, MAX_HOURS * 60) * 60,
+        revoked_at: None,
+        revoked_by: None,
+    };
+    crate::db::insert_elevated_session(&state.pool, &session).await?;
+    info!(
+        session_id = %session.id,
+        provider,
+        workspace_id,
+        channel_id,
+        granted_by,
+        minutes,
+        reason = %session.reason,
+        "elevated channel permissions"
+    );
+    notify_admins(
+        state,
+        &format!(
+            "Full permissions granted in {} by {} until {}{}.",
+            channel_ref(&session),
+            session.granted_by,
+            until(session.ends_at),
+            if session.reason.is_empty() {
+                String::new()
+            } else {
+                format!(" (\"{}\")", session.reason)
+            }
+        ),
+    )
+    .await;
+    Ok(session)
+}
+
+/// End a session early.
+pub async fn revoke(
+    state: &AppState,
+    id: &str,
+    revoked_by: &str,
+) -> anyhow::Result<Option<ElevatedSession>> {
+    let now = chrono::Utc::now().timestamp();
+    let Some(session) =
+        crate::db::revoke_elevated_session(&state.pool, id, revoked_by, now).await?
+    else {
+        return Ok(None);
+    };
+    info!(
+        session_id = %session.id,
+        channel_id = %session.channel_id,
+        revoked_by,
+        "ended elevated channel permissions early"
+    );
+    notify_admins(
+        state,
+        &format!(
+            "Full permissions in {} were ended early by {revoked_by}.",
+            channel_ref(&session)
+        ),
+    )
+    .await;
+    Ok(Some(session))
+}
+
+/// Reply to an elevation command sent in a channel.
+pub async fn handle_command(
+    state: &AppState,
+    cmd: Command,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+    actor: &str,
+) -> anyhow::Result<String> {
+    let now = chrono::Utc::now().timestamp();
+    let current = active(state, provider, workspace_id, channel_id).await?;
+    match cmd {
+        Command::Status => Ok(match current {
+            Some(session) => describe(&session, now),
+            None => "This channel runs with the workspace's usual permissions.".to_string(),
+        }),
+        Command::Grant { minutes, reason } => {
+            let settings = crate::db::get_settings(&state.pool).await?;
+            if !may_elevate(&settings, provider, actor) {
+                return Ok(
+                    "Only users listed under Elevate Allowed Users in settings can elevate a channel."
+                        .to_string(),
+                );
+            }
+            if settings.permissions_mode == PermissionsMode::Full {
+                return Ok(
+                    "The workspace already runs with full permissions; there is nothing to elevate."
+                        .to_string(),
+                );
+            }
+            let session = grant(
+                state,
+                provider,
+                workspace_id,
+                channel_id,
+                actor,
+                minutes,
+                &reason,
+            )
+            .await?;
+            Ok(format!(
+                "{} Send `end elevation` to end it early.",
+                describe(&session, now)
+            ))
+        }
+        Command::End => {
+            let Some(session) = current else {
+                return Ok("This channel isn't elevated.".to_string());
+            };
+            let settings = crate::db::get_settings(&state.pool).await?;
+            if !may_elevate(&settings, provider, actor) {
+                return Ok(
+                    "Only users listed under Elevate Allowed Users in settings can end an elevation."
+                        .to_string(),
+                );
+            }
+            revoke(state, &session.id, actor).await?;
+            Ok(
+                "Elevation ended; this channel is back to the workspace's usual permissions."
+                    .to_string(),
+            )
+        }
+    }
+}
+
+fn channel_ref(session: &ElevatedSession) -> String {
+    match session.provider.as_str() {
+        "slack" => format!("<#{}>", session.channel_id),
+        other => format!("{other} chat {}", session.channel_id),
+    }
+}
+
+async fn notify_admins(state: &AppState, text: &str) {
+    let admin_channel = match crate::db::get_settings(&state.pool).await {
+        Ok(s) => s.slack_admin_channel.trim().to_string(),
+        Err(err) => {
+            warn!(error = %err, "failed to load settings for the admin notice");
+            return;
+        }
+    };
+    if admin_channel.is_empty() {
+        return;
+    }
+    match crate::secrets::load_slack_bot_token_opt(state).await {
+        Ok(Some(token)) => {
+            if let Err(err) = SlackClient::new(state.http.clone(), token)
+                .post_message(&admin_channel, None, text)
+                .await
+            {
+                warn!(error = %err, "failed to notify the slack admin channel");
+            }
+        }
+        Ok(None) => {}
+        Err(err) => warn!(error = %err, "failed to load the slack bot token"),
+    }
+}
+
+async fn post(state: &AppState, session: &ElevatedSession, text: &str) -> anyhow::Result<()> {
+    match session.provider.as_str() {
+        "slack" => {
+            let token = crate::secrets::load_slack_bot_token_opt(state)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("SLACK_BOT_TOKEN missing"))?;
+            SlackClient::new(state.http.clone(), token)
+                .post_message(&session.channel_id, None, text)
+                .await?;
+        }
+        "telegram" => {
+            let token = crate::secrets::load_telegram_bot_token_opt(state)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("TELEGRAM_BOT_TOKEN missing"))?;
+            TelegramClient::new(state.http.clone(), token)
+                .send_message(&session.channel_id, None, text)
+                .await?;
+        }
+        other => anyhow::bail!("elevation notices are not supported for provider {other}"),
+    }
+    Ok(())
+}
+
+/// Tell channels whose session ran out that they are back to the usual permissions (only
+/// the leader runs this). The reversion itself needs nothing: sessions stop applying at
+/// `ends_at`.
+pub async fn announce_expired(state: &AppState) {
+    let now = chrono::Utc::now().timestamp();
+    let expired = match crate::db::take_expired_elevated_sessions(&state.pool, now).await {
+        Ok(v) => v,
+        Err(err) => {
+            warn!(error = %err, "failed to list expired elevated sessions");
+            return;
+        }
+    };
+    for session in expired {
+        info!(
+            session_id = %session.id,
+            channel_id = %session.channel_id,
+            "elevated channel permissions expired"
+        );
+        if let Err(err) = post(
+            state,
+            &session,
+            "The elevation window ended; this channel is back to the workspace's usual permissions.",
+        )
+        .await
+        {
+            warn!(error = %err, session_id = %session.id, "failed to announce elevation end");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn elevate_lists_are_per_provider() {
+        let db = crate::test_support::TestDb::new().await;
+        let pool = db.pool.clone();
+        let mut settings = crate::db::get_settings(&pool).await.unwrap();
+        settings.slack_elevate_allow_from = "U1, U2".to_string();
+        settings.telegram_elevate_allow_from = "42".to_string();
+        crate::db::update_settings(&pool, &settings).await.unwrap();
+        let settings = crate::db::get_settings(&pool).await.unwrap();
+
+        assert!(may_elevate(&settings, "slack", "U2"));
+        assert!(may_elevate(&settings, "telegram", "42"));
+        // An id on one provider's list grants nothing on the other.
+        assert!(!may_elevate(&settings, "telegram", "U1"));
+        assert!(!may_elevate(&settings, "slack", "42"));
+        assert!(!may_elevate(&settings, "discord", "U1"));
+    }
+
+    #[tokio::test]
+    async fn elevated_sessions_are_time_boxed_and_audited() {
+        assert_eq!(
+            parse_command("elevate 2h db failover"),
+            Some(Command::Grant {
+                minutes: 120,
+                reason: "db failover".to_string()
+            })
+        );
+        assert_eq!(
+            parse_command("Elevate for 90 minutes: incident"),
+            Some(Command::Grant {
+                minutes: 90,
+                reason: "incident".to_string()
+            })
+        );
+        assert!(matches!(
+            parse_command("elevate 100h"),
+            Some(Command::Grant { minutes, .. }) if minutes == MAX_HOURS * 60
+        ));
+        assert!(matches!(
+            parse_command("elevate"),
+            Some(Command::Grant { minutes: 60, .. })
+        ));
+        assert_eq!(parse_command("end elevation"), Some(Command::End));
+        assert_eq!(parse_command("elevation status?"), Some(Command::Status));
+        assert_eq!(parse_command("elevate the log level in prod"), None);
+
+        let db = crate::test_support::TestDb::new().await;
+        let pool = db.pool.clone();
+        let session = |id: &str, started_at: i64, ends_at: i64| crate::models::ElevatedSession {
+            id: id.to_string(),
+            provider: "slack".to_string(),
+            workspace_id: "T1".to_string(),
+            channel_id: "C1".to_string(),
+            granted_by: "U1".to_string(),
+            reason: "incident".to_string(),
+            started_at,
+            ends_at,
+            revoked_at: None,
+            revoked_by: None,
+        };
+        crate::db::insert_elevated_session(&pool, &session("old", 100, 200))
+            .await
+            .unwrap();
+        let active = |now| crate::db::active_elevated_session(&pool, "slack", "T1", "C1", now);
+        assert_eq!(active(150).await.unwrap().unwrap().id, "old");
+        assert!(active(200).await.unwrap().is_none());
+        assert!(
+            crate::db::active_elevated_session(&pool, "slack", "T1", "C2", 150)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // A new grant replaces the active one; an expired one is announced exactly once.
+        crate::db::insert_elevated_session(&pool, &session("new", 150, 400))
+            .await
+            .unwrap();
+        assert_eq!(active(160).await.unwrap().unwrap().id, "new");
+        assert!(crate::db::take_expired_elevated_sessions(&pool, 300)
+            .await
+            .unwrap()
+            .is_empty());
+        let revoked = crate::db::revoke_elevated_session(&pool, "new", "U2", 300)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(revoked.revoked_by.as_deref(), Some("U2"));
+        assert!(active(310).await.unwrap().is_none());
+        crate::db::insert_elevated_session(&pool, &session("later", 500, 600))
+            .await
+            .unwrap();
+        let expired = crate::db::take_expired_elevated_sessions(&pool, 700)
+            .await
+            .unwrap();
+        assert_eq!(
+            expired.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(),
+            vec!["later"]
+        );
+        assert!(crate::db::take_expired_elevated_sessions(&pool, 700)
+            .await
+            .unwrap()
+            .is_empty());
+        let audit = crate::db::list_elevated_sessions(&pool, 10).await.unwrap();
+        assert_eq!(audit.len(), 3);
+        assert_eq!(
+            audit
+                .iter()
+                .find(|s| s.id == "old")
+                .unwrap()
+                .revoked_by
+                .as_deref(),
+            Some("U1")
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Error categories shared by the web handlers and the task worker.
+//!
+//! Internal errors stay `anyhow::Error`; call sites that know what went wrong attach a
+//! [`UserError`] so the category and a user-safe message survive `?`. Anything untagged
+//! is classified from its message chain and gets a generic message for its category.
+
+use axum::http::StatusCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    BadRequest,
+    NotFound,
+    Config,
+    ProviderAuth,
+    RateLimit,
+    Guardrail,
+    Agent,
+    Internal,
+}
+
+impl ErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::BadRequest => "bad_request",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Config => "config",
+            ErrorKind::ProviderAuth => "provider_auth",
+            ErrorKind::RateLimit => "rate_limit",
+            ErrorKind::Guardrail => "guardrail",
+            ErrorKind::Agent => "agent",
+            ErrorKind::Internal => "internal",
+        }
+    }
+
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorKind::BadRequest => StatusCode::BAD_REQUEST,
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::Config => StatusCode::SERVICE_UNAVAILABLE,
+            ErrorKind::ProviderAuth => StatusCode::BAD_GATEWAY,
+            ErrorKind::RateLimit => StatusCode::TOO_MANY_REQUESTS,
+            ErrorKind::Guardrail => StatusCode::FORBIDDEN,
+            ErrorKind::Agent => StatusCode::BAD_GATEWAY,
+            ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Message shown when the error carries no user-safe text of its own.
+    pub fn default_message(self) -> &'static str {
+        match self {
+            ErrorKind::BadRequest => "The request was invalid.",
+            ErrorKind::NotFound => "Not found.",
+            ErrorKind::Config => {
+                "This isn't fully set up yet. An admin needs to finish the configuration."
+            }
+            ErrorKind::ProviderAuth => {
+                "A connected service rejected our credentials. An admin needs to reconnect it."
+            }
+            ErrorKind::RateLimit => {
+                "A connected service is rate limiting us. Please try again in a few minutes."
+            }
+            ErrorKind::Guardrail => "That action was blocked by a guardrail.",
+            ErrorKind::Agent => "The agent couldn't complete this request.",
+            ErrorKind::Internal => "Something went wrong on our side.",
+        }
+    }
+}
+
+/// A categorized error whose message is safe to show to end users.
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct UserError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+pub fn user_error(kind: ErrorKind, message: impl Into<String>) -> anyhow::Error {
+    UserError {
+        kind,
+        message: message.into(),
+    }
+    .into()
+}
+
+pub fn bad_request(message: impl Into<String>) -> anyhow::Error {
+    user_error(ErrorKind::BadRequest, message)
+}
+
+pub fn not_found(message: impl Into<String>) -> anyhow::Error {
+    user_error(ErrorKind::NotFound, message)
+}
+
+pub fn config(message: impl Into<String>) -> anyhow::Error {
+    user_error(ErrorKind::Config, message)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Classified {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+pub fn classify(err: &anyhow::Error) -> Classified {
+    if let Some(e) = err.chain().find_map(|e| e.downcast_ref::<UserError>()) {
+        return Classified {
+            kind: e.kind,
+            message: e.message.clone(),
+        };
+    }
+    if err.chain().any(|e| {
+        matches!(
+            e.downcast_ref::<sqlx::Error>(),
+            Some(sqlx::Error::RowNotFound)
+        )
+    }) {
+        let kind = ErrorKind::NotFound;
+        return Classified {
+            kind,
+            message: kind.default_message().to_string(),
+        };
+    }
+    let kind = classify_text(&format!("{err:#}"));
+    Classified {
+        kind,
+        message: kind.default_message().to_string(),
+    }
+}
+
+/// Best-effort category for errors that were not tagged at the source.
+fn classify_text(text: &str) -> ErrorKind {
+    let t = text.to_ascii_lowercase();
+    let any = |needles: &[&str]| needles.iter().any(|n| t.contains(n));
+    if any(&["is not configured", "is required to store secrets"]) {
+        ErrorKind::Config
+    } else if any(&[
+        "ratelimited",
+        "rate_limited",
+        "rate limit",
+        "status 429",
+        "too many requests",
+    ]) {
+        ErrorKind::RateLimit
+    } else if any(&[
+        "invalid_auth",
+        "not_authed",
+        "token_revoked",
+        "token_expired",
+        "account_inactive",
+        "invalid_api_key",
+        "status 401",
+        "401 unauthorized",
+        "unauthorized",
+    ]) {
+        ErrorKind::ProviderAuth
+    } else if any(&["guardrail", "are not allowed via context_writes"]) {
+        ErrorKind::Guardrail
+    } else if any(&["codex", "agent output", "failed to repair agent output"]) {
+        ErrorKind::Agent
+    } else {
+        ErrorKind::Internal
+    }
+}
+
+/// Short reference admins can search for in logs and task errors.
+pub fn error_ref() -> String {
+    let mut bytes = [0u8; 4];
+    rand::RngCore::fill_bytes(&mut rand::rng(), &mut bytes);
+    format!("ERR-{}", hex::encode_upper(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_classified_with_user_safe_messages() {
+        let tagged = bad_request("prompt_text is empty")
+            .context("create task")
+            .context("api");
+        let c = classify(&tagged);
+        assert_eq!(c.kind, ErrorKind::BadRequest);
+        assert_eq!(c.message, "prompt_text is empty");
+
+        let cases = [
+            (
+                "slack chat.postMessage failed: ratelimited",
+                ErrorKind::RateLimit,
+            ),
+            (
+                "slack auth.test failed: invalid_auth",
+                ErrorKind::ProviderAuth,
+            ),
+            ("codex turn failed: boom", ErrorKind::Agent),
+            ("disk full at /data/secret/path", ErrorKind::Internal),
+        ];
+        for (text, kind) in cases {
+            let c = classify(&anyhow::anyhow!(text.to_string()));
+            assert_eq!(c.kind, kind, "{text}");
+            assert!(!c.message.contains("/data"));
+        }
+        let c = classify(&anyhow::Error::new(sqlx::Error::RowNotFound));
+        assert_eq!(c.kind.status(), StatusCode::NOT_FOUND);
+    }
+}
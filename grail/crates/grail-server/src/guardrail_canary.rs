@@ -0,0 +1,196 @@
+//! Canary rollout for guardrail policies.
+//!
+//! A canary is a complete candidate rule set (the config bundle's `guardrail_rules`
+//! format) plus a list of test channels and an end time. Until then, command, topic and
+//! env checks in those channels use the candidate rules instead of the active ones;
+//! everywhere else the active rules keep deciding. While the canary is live, both rule
+//! sets are evaluated and the pair of decisions is logged: every decision in a canary
+//! channel, and decisions elsewhere where the candidate would have decided differently
+//! (what promotion would change). After the window the canary stops applying and waits
+//! for an admin to promote it (replace the active rules) or discard it.
+
+use tracing::warn;
+
+use crate::config_bundle::GuardrailRuleSpec;
+use crate::guardrails::Decision;
+use crate::models::{GuardrailCanary, GuardrailCanaryDecision, GuardrailRule, Task};
+use crate::AppState;
+
+pub const MAX_HOURS: i64 = 30 * 24;
+const SUBJECT_CHARS: usize = 300;
+
+pub struct Canary {
+    pub channels: Vec<String>,
+    pub specs: Vec<GuardrailRuleSpec>,
+    pub started_at: i64,
+    pub ends_at: i64,
+}
+
+impl Canary {
+    pub fn from_row(row: &GuardrailCanary) -> anyhow::Result<Self> {
+        Ok(Self {
+            channels: parse_channels(&row.channels),
+            specs: serde_json::from_str(&row.rules_json)?,
+            started_at: row.started_at,
+            ends_at: row.ends_at,
+        })
+    }
+
+    pub fn is_live(&self, now: i64) -> bool {
+        now < self.ends_at
+    }
+
+    pub fn covers(&self, channel_id: &str) -> bool {
+        self.channels.iter().any(|c| c == channel_id)
+    }
+
+    /// Candidate rules of `kind`, ordered the way the active rules are loaded.
+    pub fn rules(&self, kind: &str) -> Vec<GuardrailRule> {
+        let mut rules: Vec<GuardrailRule> = self
+            .specs
+            .iter()
+            .filter(|s| s.kind.trim() == kind)
+            .map(crate::config_bundle::to_rule)
+            .collect();
+        rules.sort_by_key(|r| (!r.enabled, r.priority));
+        rules
+    }
+}
+
+pub fn parse_channels(raw: &str) -> Vec<String> {
+    raw.split([',', '\n', ' '])
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The rules deciding a check, and the other set to compare against while a canary is live.
+pub struct RuleSets {
+    pub applied: Vec<GuardrailRule>,
+    pub shadow: Option<Vec<GuardrailRule>>,
+    pub canary_applied: bool,
+}
+
+pub async fn load(state: &AppState) -> anyhow::Result<Option<Canary>> {
+    match crate::db::get_guardrail_canary(&state.pool).await? {
+        Some(row) => Ok(Some(Canary::from_row(&row)?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn rule_sets(state: &AppState, kind: &str, channel_id: &str) -> anyhow::Result<RuleSets> {
+    let active = crate::db::list_guardrail_rules(&state.pool, Some(kind), 500).await?;
+    let canary = match load(state).await {
+        Ok(c) => c.filter(|c| c.is_live(chrono::Utc::now().timestamp())),
+        Err(err) => {
+            warn!(error = %err, "invalid guardrail canary; using active rules");
+            None
+        }
+    };
+    Ok(match canary {
+        None => RuleSets {
+            applied: active,
+            shadow: None,
+            canary_applied: false,
+        },
+        Some(c) if c.covers(channel_id) => RuleSets {
+            applied: c.rules(kind),
+            shadow: Some(active),
+            canary_applied: true,
+        },
+        Some(c) => RuleSets {
+            applied: active,
+            shadow: Some(c.rules(kind)),
+            canary_applied: false,
+        },
+    })
+}
+
+/// Log the applied and shadow decisions for one check (see the module docs for which).
+pub async fn record(
+    state: &AppState,
+    task: &Task,
+    kind: &str,
+    subject: &str,
+    sets: &RuleSets,
+    applied: (Decision, Option<&GuardrailRule>),
+    shadow: (Decision, Option<&GuardrailRule>),
+) {
+    if !sets.canary_applied && applied.0 == shadow.0 {
+        return;
+    }
+    let (active, canary) = if sets.canary_applied {
+        (shadow, applied)
+    } else {
+        (applied, shadow)
+    };
+    let (subject, _) = crate::secrets::redact_secrets(subject);
+    let decision = GuardrailCanaryDecision {
+        id: 0,
+        task_id: task.id,
+        channel_id: task.channel_id.clone(),
+        kind: kind.to_string(),
+        subject: subject.chars().take(SUBJECT_CHARS).collect(),
+        applied: if sets.canary_applied {
+            "canary"
+        } else {
+            "active"
+        }
+        .to_string(),
+        active_decision: active.0.as_str().to_string(),
+        active_rule_id: active.1.map(|r| r.id.clone()),
+        canary_decision: canary.0.as_str().to_string(),
+        canary_rule_id: canary.1.map(|r| r.id.clone()),
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    if let Err(err) = crate::db::insert_guardrail_canary_decision(&state.pool, &decision).await {
+        warn!(error = %err, "failed to log guardrail canary decision");
+    }
+}
+
+/// The config bundle holding only `specs` as its guardrail rules; promotion imports it.
+pub fn as_bundle(specs: &[GuardrailRuleSpec]) -> crate::config_bundle::ConfigBundle {
+    crate::config_bundle::ConfigBundle {
+        version: crate::config_bundle::VERSION,
+        guardrail_rules: Some(specs.to_vec()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guardrail_canary_orders_candidate_rules_by_kind() {
+        use crate::config_bundle::GuardrailRuleSpec;
+        let spec = |id: &str, kind: &str, priority: i64, enabled: bool| GuardrailRuleSpec {
+            id: id.to_string(),
+            name: id.to_string(),
+            kind: kind.to_string(),
+            pattern_kind: "substring".to_string(),
+            pattern: "rm".to_string(),
+            action: "deny".to_string(),
+            priority,
+            enabled,
+        };
+        let canary = Canary {
+            channels: parse_channels("C1, C2\nC3"),
+            specs: vec![
+                spec("late", "command", 50, true),
+                spec("off", "command", 1, false),
+                spec("early", "command", 5, true),
+                spec("hr", "topic", 1, true),
+            ],
+            started_at: 100,
+            ends_at: 200,
+        };
+        assert_eq!(canary.channels, vec!["C1", "C2", "C3"]);
+        assert!(canary.covers("C2") && !canary.covers("C9"));
+        assert!(canary.is_live(199) && !canary.is_live(200));
+        let ids: Vec<_> = canary.rules("command").into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec!["early", "late", "off"]);
+        assert_eq!(canary.rules("topic").len(), 1);
+    }
+}
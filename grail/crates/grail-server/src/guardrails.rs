@@ -10,6 +10,16 @@ pub enum Decision {
     Deny,
 }
 
+impl Decision {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Decision::Allow => "allow",
+            Decision::RequireApproval => "require_approval",
+            Decision::Deny => "deny",
+        }
+    }
+}
+
 pub fn decision_from_action(action: &str) -> Decision {
     match action {
         "allow" => Decision::Allow,
@@ -72,3 +82,272 @@ pub async fn evaluate_command_guardrails(
     }
     Ok((Decision::Allow, None))
 }
+
+/// A narrower-than-"approve once" allow rule offered alongside an approval request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlwaysScope {
+    /// Stable identifier used in decisions and action ids (`program`, `directory`).
+    pub scope: &'static str,
+    pub label: String,
+    /// Regex pattern for the generated allow rule.
+    pub pattern: String,
+}
+
+// Programs where "always allow this program" is equivalent to "always allow anything":
+// shells, wrappers and interpreters, and tools that run scripts or other commands
+// (`git -c core.sshCommand=...`, sed's `e` command, awk's `system()`, Makefile recipes).
+const UNSCOPABLE_PROGRAMS: &[&str] = &[
+    "bash", "sh", "zsh", "dash", "fish", "env", "sudo", "su", "doas", "xargs", "eval", "exec",
+    "nohup", "timeout", "nice", "python", "python3", "node", "perl", "ruby", "php", "deno", "bun",
+    "npx", "find", "git", "sed", "awk", "gawk", "make", "gmake",
+];
+
+// Programs whose every run may destroy data; only the directory scope is offered for them.
+const DESTRUCTIVE_PROGRAMS: &[&str] = &[
+    "rm", "rmdir", "mv", "dd", "shred", "truncate", "mkfs", "chmod", "chown", "chgrp", "kill",
+    "pkill", "killall", "shutdown", "reboot",
+];
+
+// Plain argument: no shell metacharacters, no slash, no leading dot (so no `..`) and no
+// leading `~` (so no home directories).
+const PLAIN_ARG: &str = r"[A-Za-z0-9_\-+=,@%:][A-Za-z0-9_\-+=,@%:~.]*";
+// Flag argument: starts with `-` and names no path.
+const FLAG_ARG: &str = r"-[A-Za-z0-9_\-+=,@%:.]*";
+// Any argument without shell metacharacters or quotes.
+const ANY_ARG: &str = r"[A-Za-z0-9_\-+=,@%:~./]+";
+
+/// Split a command line into argv the way a POSIX shell would for simple commands.
+///
+/// Returns `None` for anything that is not a single simple command (pipes, redirects,
+/// substitutions, unbalanced quotes), since suggesting broad rules for those is unsafe.
+/// Each token is returned with the byte offset where it starts in `command`.
+pub fn split_command_argv(command: &str) -> Option<Vec<(usize, String)>> {
+    let mut out: Vec<(usize, String)> = Vec::new();
+    let mut cur: Option<(usize, String)> = None;
+    let mut chars = command.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            ' ' | '\t' => {
+                if let Some(tok) = cur.take() {
+                    out.push(tok);
+                }
+            }
+            '\'' => {
+                let tok = cur.get_or_insert_with(|| (i, String::new()));
+                loop {
+                    match chars.next() {
+                        Some((_, '\'')) => break,
+                        Some((_, ch)) => tok.1.push(ch),
+                        None => return None,
+                    }
+                }
+            }
+            '"' => {
+                let tok = cur.get_or_insert_with(|| (i, String::new()));
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '$' | '`')) => return None,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, ch)) => tok.1.push(ch),
+                            None => return None,
+                        },
+                        Some((_, ch)) => tok.1.push(ch),
+                        None => return None,
+                    }
+                }
+            }
+            '\\' => {
+                let (_, ch) = chars.next()?;
+                if ch == '\n' {
+                    return None;
+                }
+                cur.get_or_insert_with(|| (i, String::new())).1.push(ch);
+            }
+            ';' | '&' | '|' | '<' | '>' | '`' | '$' | '(' | ')' | '\n' | '\r' | '*' | '?' => {
+                return None
+            }
+            _ => cur.get_or_insert_with(|| (i, String::new())).1.push(c),
+        }
+    }
+    if let Some(tok) = cur.take() {
+        out.push(tok);
+    }
+    if out.is_empty() {
+        None
+    } else {
+        Some(out)
+    }
+}
+
+/// Suggest narrower "always allow" scopes for a command that required approval.
+///
+/// Shell wrappers like `bash -lc '<script>'` are unwrapped so the scope applies to the
+/// inner program. The `program` scope is pinned to the first argument (a subcommand such as
+/// `cargo build`, or a path) and is never offered for destructive programs. Every
+/// suggestion is checked to match the original command.
+pub fn suggest_always_scopes(command: &str) -> Vec<AlwaysScope> {
+    let command = command.trim();
+    let Some(argv) = split_command_argv(command) else {
+        return Vec::new();
+    };
+
+    // Unwrap `bash -c '<script>'` (and friends), keeping the wrapper text verbatim.
+    let (prefix, quote, inner) = match argv.as_slice() {
+        [(_, sh), (_, flag), (start, script)]
+            if matches!(program_name(sh), "bash" | "sh" | "zsh" | "dash")
+                && matches!(flag.as_str(), "-c" | "-lc") =>
+        {
+            let quote = match command[*start..].chars().next() {
+                Some(q @ ('\'' | '"')) if command.ends_with(q) => q.to_string(),
+                _ => return Vec::new(),
+            };
+            let Some(inner) = split_command_argv(script.trim()) else {
+                return Vec::new();
+            };
+            (&command[..*start], quote, inner)
+        }
+        _ => ("", String::new(), argv),
+    };
+
+    let args: Vec<&str> = inner.iter().map(|(_, s)| s.as_str()).collect();
+    let Some(prog) = args.first().copied() else {
+        return Vec::new();
+    };
+    let name = program_name(prog);
+    let prog_ok = Regex::new(r"^[A-Za-z0-9_\-+./]+$").is_ok_and(|re| re.is_match(prog));
+    if !prog_ok || prog.contains("..") || UNSCOPABLE_PROGRAMS.contains(&name) {
+        return Vec::new();
+    }
+
+    let wrap = |body: String| {
+        format!(
+            r"^{}{}\s*{}\s*{}\s*$",
+            regex::escape(prefix),
+            regex::escape(&quote),
+            body,
+            regex::escape(&quote)
+        )
+    };
+
+    let mut out = Vec::new();
+    // Program scope: the program plus its first argument, e.g. `cargo build` or `cat notes.md`.
+    let first = args
+        .get(1)
+        .filter(|a| !a.starts_with('-') && !a.split('/').any(|seg| seg == ".."));
+    if let Some(first) = first.filter(|_| !DESTRUCTIVE_PROGRAMS.contains(&name)) {
+        out.push(AlwaysScope {
+            scope: "program",
+            label: format!("Always `{name} {first}`"),
+            pattern: wrap(format!(
+                r"{}\s+{}(\s+{ANY_ARG})*",
+                regex::escape(prog),
+                regex::escape(first)
+            )),
+        });
+    }
+
+    // Directory scope: the directory of the first path-like argument. Every other
+    // non-flag argument must be inside that directory too.
+    let dir = args
+        .iter()
+        .skip(1)
+        .find(|a| !a.starts_with('-') && a.contains('/'))
+        .and_then(|p| match p.strip_suffix('/') {
+            Some(d) => Some(d),
+            None => p.rsplit_once('/').map(|(d, _)| d),
+        })
+        .filter(|d| {
+            !d.is_empty()
+                && !d.split('/').any(|seg| seg == "..")
+                && Regex::new(r"^[A-Za-z0-9_\-+=,@%:~./]+$").is_ok_and(|re| re.is_match(d))
+        });
+    if let Some(dir) = dir {
+        let path_arg = format!(r"{}(/{PLAIN_ARG})*/?", regex::escape(dir));
+        out.push(AlwaysScope {
+            scope: "directory",
+            label: format!("Always `{name}` in `{dir}/`"),
+            pattern: wrap(format!(
+                r"{}(\s+({FLAG_ARG}|{path_arg}))*",
+                regex::escape(prog)
+            )),
+        });
+    }
+
+    out.retain(|s| Regex::new(&s.pattern).is_ok_and(|re| re.is_match(command)));
+    out
+}
+
+fn program_name(prog: &str) -> &str {
+    prog.rsplit('/').next().unwrap_or(prog)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn always_scopes_for_simple_command() {
+        let scopes = suggest_always_scopes("cat src/lib/main.rs");
+        assert_eq!(scopes.len(), 2);
+        assert_eq!(scopes[0].label, "Always `cat src/lib/main.rs`");
+        let program = regex::Regex::new(&scopes[0].pattern).unwrap();
+        assert!(program.is_match("cat src/lib/main.rs -n"));
+        assert!(!program.is_match("cat README.md"));
+        assert!(!program.is_match("cat src/lib/main.rs; rm -rf /"));
+        let dir = regex::Regex::new(&scopes[1].pattern).unwrap();
+        assert!(dir.is_match("cat -n src/lib/other.rs"));
+        assert!(!dir.is_match("cat src/lib/../../etc/passwd"));
+        assert!(!dir.is_match("cat /etc/passwd"));
+        assert!(!dir.is_match("cat README.md"));
+
+        let scopes = suggest_always_scopes("rm -rf build/out");
+        let dir = regex::Regex::new(&scopes[0].pattern).unwrap();
+        assert!(dir.is_match("rm -rf build/x build/y/"));
+        for cmd in ["rm -rf ~", "rm -rf ~root", "rm -rf src", "rm build/x ../y"] {
+            assert!(!dir.is_match(cmd), "{cmd}");
+        }
+    }
+
+    #[test]
+    fn always_scopes_unwrap_shell_wrapper() {
+        let scopes = suggest_always_scopes("/bin/bash -lc 'cargo build'");
+        assert_eq!(scopes.len(), 1);
+        assert_eq!(scopes[0].label, "Always `cargo build`");
+        let program = regex::Regex::new(&scopes[0].pattern).unwrap();
+        assert!(program.is_match("/bin/bash -lc 'cargo build --release'"));
+        assert!(!program.is_match("/bin/bash -lc 'cargo publish'"));
+        assert!(!program.is_match("/bin/bash -lc 'cargo build && curl x'"));
+    }
+
+    #[test]
+    fn always_scopes_skip_compound_and_interpreters() {
+        assert!(suggest_always_scopes("ls | wc -l").is_empty());
+        assert!(suggest_always_scopes("python3 x.py").is_empty());
+        assert!(suggest_always_scopes("bash -c 'echo $HOME'").is_empty());
+        for cmd in [
+            "git status",
+            "sed -i s/a/b/ notes.txt",
+            "awk -f x.awk data.csv",
+            "make deploy",
+            "xargs rm",
+        ] {
+            assert!(suggest_always_scopes(cmd).is_empty(), "{cmd}");
+        }
+    }
+
+    #[test]
+    fn always_scopes_stay_narrow_for_destructive_and_flag_only_commands() {
+        // Destructive programs only ever get the directory scope.
+        let scopes = suggest_always_scopes("rm -rf build/out");
+        assert_eq!(
+            scopes.iter().map(|s| s.scope).collect::<Vec<_>>(),
+            vec!["directory"]
+        );
+        assert!(suggest_always_scopes("rm notes.txt").is_empty());
+        // Without a subcommand or path there is nothing to pin the program scope to.
+        assert!(suggest_always_scopes("ls -la").is_empty());
+        assert!(suggest_always_scopes("ls").is_empty());
+    }
+}
@@ -0,0 +1,263 @@
+//! Reply language: detect the language of the triggering message and tell the agent to
+//! answer in it.
+//!
+//! Detection is deliberately small: the writing system decides non-Latin languages, and
+//! common function words decide between the major Latin-script ones. The `reply_language`
+//! setting can pin a language (or turn the instruction off) globally or per channel.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use tracing::warn;
+
+/// Slack mentions/links, URLs and code are not evidence of the writer's language.
+static NOISE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?s)<[^>]*>|https?://\S+|```.*?```|`[^`]*`").unwrap());
+
+/// Latin-script words needed before trusting a stopword match.
+const MIN_WORDS: usize = 3;
+
+const STOPWORDS: &[(&str, &str, &[&str])] = &[
+    (
+        "en",
+        "English",
+        &[
+            "the", "and", "is", "are", "to", "of", "what", "how", "can", "you", "please", "this",
+            "that", "with", "for", "it", "we", "do", "does", "why", "our", "my",
+        ],
+    ),
+    (
+        "de",
+        "German",
+        &[
+            "der", "die", "das", "und", "ist", "nicht", "wie", "ich", "wir", "bitte", "kannst",
+            "du", "mit", "für", "ein", "eine", "auf", "was", "warum", "unsere", "sind", "zu",
+        ],
+    ),
+    (
+        "fr",
+        "French",
+        &[
+            "le", "la", "les", "et", "est", "pas", "je", "nous", "vous", "pour", "avec", "une",
+            "des", "que", "qui", "comment", "pourquoi", "peux", "merci", "dans", "du", "sur",
+        ],
+    ),
+    (
+        "es",
+        "Spanish",
+        &[
+            "el", "los", "las", "y", "es", "no", "que", "por", "para", "con", "una", "cómo", "qué",
+            "puedes", "gracias", "favor", "del", "está", "pero", "nuestro", "hay", "son",
+        ],
+    ),
+    (
+        "pt",
+        "Portuguese",
+        &[
+            "o", "os", "as", "e", "não", "que", "para", "com", "uma", "um", "como", "você",
+            "obrigado", "por", "favor", "está", "mas", "nosso", "são", "do", "da", "em",
+        ],
+    ),
+    (
+        "it",
+        "Italian",
+        &[
+            "il", "lo", "gli", "e", "è", "non", "che", "per", "con", "una", "come", "puoi",
+            "grazie", "perché", "della", "del", "sono", "ma", "nostro", "di", "cosa", "questo",
+        ],
+    ),
+    (
+        "nl",
+        "Dutch",
+        &[
+            "de", "het", "een", "en", "is", "niet", "wat", "hoe", "ik", "wij", "je", "kun", "met",
+            "voor", "van", "op", "waarom", "onze", "zijn", "dat", "dit", "graag",
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Language {
+    pub code: &'static str,
+    pub name: &'static str,
+}
+
+fn lang(code: &'static str, name: &'static str) -> Option<Language> {
+    Some(Language { code, name })
+}
+
+fn in_ranges(c: char, ranges: &[(u32, u32)]) -> bool {
+    let c = c as u32;
+    ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c))
+}
+
+/// Best guess at the language of `text`, or `None` when there isn't enough to go on.
+pub fn detect(text: &str) -> Option<Language> {
+    let text = NOISE_RE.replace_all(text, " ");
+    let letters: Vec<char> = text.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.is_empty() {
+        return None;
+    }
+
+    let count = |ranges: &[(u32, u32)]| letters.iter().filter(|c| in_ranges(**c, ranges)).count();
+    let kana = count(&[(0x3040, 0x30FF)]);
+    let hangul = count(&[(0xAC00, 0xD7AF), (0x1100, 0x11FF)]);
+    let han = count(&[(0x4E00, 0x9FFF), (0x3400, 0x4DBF)]);
+    let cyrillic = count(&[(0x0400, 0x04FF)]);
+    let scripts = [
+        (kana, lang("ja", "Japanese")),
+        (hangul, lang("ko", "Korean")),
+        (han, lang("zh", "Chinese")),
+        (count(&[(0x0600, 0x06FF)]), lang("ar", "Arabic")),
+        (count(&[(0x0590, 0x05FF)]), lang("he", "Hebrew")),
+        (count(&[(0x0370, 0x03FF)]), lang("el", "Greek")),
+        (count(&[(0x0E00, 0x0E7F)]), lang("th", "Thai")),
+        (count(&[(0x0900, 0x097F)]), lang("hi", "Hindi")),
+    ];
+    let non_latin =
+        kana + hangul + han + cyrillic + scripts[3..].iter().map(|s| s.0).sum::<usize>();
+    if non_latin * 2 >= letters.len() {
+        // Japanese mixes kanji with kana; any kana decides it.
+        if kana > 0 {
+            return lang("ja", "Japanese");
+        }
+        if cyrillic > 0 && cyrillic >= scripts.iter().map(|s| s.0).max().unwrap_or(0) {
+            let ukrainian = text.chars().any(|c| matches!(c, 'і' | 'ї' | 'є' | 'ґ'));
+            return if ukrainian {
+                lang("uk", "Ukrainian")
+            } else {
+                lang("ru", "Russian")
+            };
+        }
+        return scripts
+            .iter()
+            .filter(|(n, _)| *n > 0)
+            .max_by_key(|(n, _)| *n)
+            .and_then(|(_, l)| *l);
+    }
+
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+    let mut scores: Vec<(usize, &'static str, &'static str)> = STOPWORDS
+        .iter()
+        .map(|(code, name, list)| {
+            let hits = words.iter().filter(|w| list.contains(&w.as_str())).count();
+            (hits, *code, *name)
+        })
+        .collect();
+    scores.sort_by_key(|s| std::cmp::Reverse(s.0));
+    let (best, code, name) = scores[0];
+    // Require at least two function words and a clear winner.
+    if best < 2 || scores[1].0 == best {
+        return None;
+    }
+    lang(code, name)
+}
+
+/// Settings.reply_language: `auto` (detect), `off` (no instruction) or a language to
+/// always reply in, globally and per channel.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReplyLanguageConfig {
+    pub default: String,
+    pub channels: HashMap<String, String>,
+}
+
+impl Default for ReplyLanguageConfig {
+    fn default() -> Self {
+        Self {
+            default: "auto".to_string(),
+            channels: HashMap::new(),
+        }
+    }
+}
+
+pub fn parse_reply_language(raw: &str) -> anyhow::Result<ReplyLanguageConfig> {
+    if raw.trim().is_empty() {
+        return Ok(ReplyLanguageConfig::default());
+    }
+    let cfg: ReplyLanguageConfig =
+        serde_json::from_str(raw).context("parse reply_language JSON")?;
+    for (scope, v) in std::iter::once(("default", &cfg.default))
+        .chain(cfg.channels.iter().map(|(k, v)| (k.as_str(), v)))
+    {
+        anyhow::ensure!(
+            !v.trim().is_empty(),
+            "reply_language.{scope}: use \"auto\", \"off\" or a language name"
+        );
+    }
+    Ok(cfg)
+}
+
+/// The reply-language line for the agent prompt, or `None` when turned off.
+pub fn reply_instruction(raw: &str, channel_id: &str, prompt: &str) -> Option<String> {
+    let cfg = parse_reply_language(raw).unwrap_or_else(|err| {
+        warn!(error = %err, "invalid reply_language setting; using auto");
+        ReplyLanguageConfig::default()
+    });
+    let mode = cfg
+        .channels
+        .get(channel_id.trim())
+        .unwrap_or(&cfg.default)
+        .trim();
+    match mode.to_ascii_lowercase().as_str() {
+        "off" => None,
+        "auto" => Some(match detect(prompt) {
+            Some(l) => format!(
+                "- The request is written in {} ({}). Reply in {} unless the user asks for another language.\n",
+                l.name, l.code, l.name
+            ),
+            None => "- Reply in the same language as the request.\n".to_string(),
+        }),
+        _ => Some(format!(
+            "- Always reply in {mode} (channel setting), even if the request is in another language.\n"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reply_language_detects_and_respects_channel_overrides() {
+        let code = |t: &str| detect(t).map(|l| l.code);
+        assert_eq!(
+            code("<@U123> kannst du bitte die Logs prüfen, warum der Build nicht läuft?"),
+            Some("de")
+        );
+        assert_eq!(
+            code("Peux-tu résumer la discussion pour nous, merci"),
+            Some("fr")
+        );
+        assert_eq!(
+            code("Can you summarize this thread for the team?"),
+            Some("en")
+        );
+        assert_eq!(code("このスレッドを要約してください"), Some("ja"));
+        assert_eq!(code("Можешь проверить логи?"), Some("ru"));
+        assert_eq!(code("ok"), None);
+        let raw = r#"{"default": "auto", "channels": {"C2": "Spanish", "C3": "off"}}"#;
+        assert!(reply_instruction(
+            raw,
+            "C1",
+            "Kannst du bitte die Logs prüfen, warum der Build nicht läuft?"
+        )
+        .unwrap()
+        .contains("German"));
+        assert!(reply_instruction(raw, "C2", "What broke?")
+            .unwrap()
+            .contains("Always reply in Spanish"));
+        assert_eq!(reply_instruction(raw, "C3", "What broke?"), None);
+        assert!(parse_reply_language(r#"{"default": ""}"#).is_err());
+    }
+}
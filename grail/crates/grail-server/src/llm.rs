@@ -0,0 +1,551 @@
+//! Agent backends.
+//!
+//! The worker drives the agent through [`AgentBackend`]. Codex (OpenAI) is the default
+//! and the only backend with tools (MCP servers, shell, approvals). Anthropic and
+//! OpenAI-compatible endpoints (vLLM, Ollama, LM Studio, ...) run plain chat turns:
+//! the conversation is kept in a small JSON file per thread and the reply is asked to
+//! follow the same output schema.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::codex::{CodexManager, CodexTurnEvent, CodexTurnOutput};
+use crate::errors::{user_error, ErrorKind};
+use crate::models::{Settings, Task};
+use crate::AppState;
+
+/// Chat thread ids carry this prefix so they are never handed to Codex (and vice versa).
+const CHAT_THREAD_PREFIX: &str = "llm_";
+/// Messages kept per chat thread (oldest are dropped first).
+const MAX_THREAD_MESSAGES: usize = 40;
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+const DEFAULT_ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
+const DEFAULT_OPENAI_COMPATIBLE_BASE_URL: &str = "http://127.0.0.1:11434/v1";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendProvider {
+    /// OpenAI models through the Codex app-server (tools, MCP, approvals).
+    #[default]
+    Codex,
+    Anthropic,
+    /// Any `/chat/completions` endpoint: vLLM, Ollama, LM Studio, OpenAI itself.
+    OpenaiCompatible,
+}
+
+impl BackendProvider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BackendProvider::Codex => "codex",
+            BackendProvider::Anthropic => "anthropic",
+            BackendProvider::OpenaiCompatible => "openai_compatible",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackendConfig {
+    pub provider: BackendProvider,
+    /// API base URL (Anthropic / OpenAI-compatible only).
+    pub base_url: Option<String>,
+    /// Model for this backend; falls back to the global model setting.
+    pub model: Option<String>,
+    /// When non-empty, only these models may be used.
+    pub allowed_models: Vec<String>,
+    /// Stored secret (`custom.NAME`) holding the API key for OpenAI-compatible endpoints
+    /// (optional for local servers).
+    pub api_key_secret: Option<String>,
+    pub max_tokens: Option<u32>,
+}
+
+/// Settings.llm_backends: a default backend plus per-workspace replacements.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmBackendsConfig {
+    pub default: BackendConfig,
+    pub workspaces: HashMap<String, BackendConfig>,
+}
+
+pub fn parse_llm_backends(raw: &str) -> anyhow::Result<LlmBackendsConfig> {
+    if raw.trim().is_empty() {
+        return Ok(LlmBackendsConfig::default());
+    }
+    let cfg: LlmBackendsConfig = serde_json::from_str(raw).context("parse llm_backends JSON")?;
+    for (scope, b) in std::iter::once(("default", &cfg.default))
+        .chain(cfg.workspaces.iter().map(|(k, v)| (k.as_str(), v)))
+    {
+        if b.provider != BackendProvider::Codex && resolve_model_name(b, None).is_none() {
+            anyhow::bail!(
+                "llm_backends.{scope}: {} needs a model",
+                b.provider.as_str()
+            );
+        }
+        if let Some(name) = b.api_key_secret.as_deref() {
+            if !crate::secrets::valid_custom_secret_name(name.trim()) {
+                anyhow::bail!("llm_backends.{scope}: invalid api_key_secret name {name:?}");
+            }
+        }
+        if let Some(m) = b.model.as_deref() {
+            if !model_allowed(b, m) {
+                anyhow::bail!("llm_backends.{scope}: model {m} is not in allowed_models");
+            }
+        }
+    }
+    Ok(cfg)
+}
+
+/// The backend for a workspace. Invalid settings fall back to Codex.
+pub fn resolve_backend(raw: &str, workspace_id: &str) -> BackendConfig {
+    match parse_llm_backends(raw) {
+        Ok(mut cfg) => cfg
+            .workspaces
+            .remove(workspace_id.trim())
+            .unwrap_or(cfg.default),
+        Err(err) => {
+            warn!(error = %err, "invalid llm_backends setting; using codex");
+            BackendConfig::default()
+        }
+    }
+}
+
+fn model_allowed(b: &BackendConfig, model: &str) -> bool {
+    b.allowed_models.is_empty() || b.allowed_models.iter().any(|m| m.trim() == model.trim())
+}
+
+fn resolve_model_name(b: &BackendConfig, settings_model: Option<&str>) -> Option<String> {
+    b.model
+        .as_deref()
+        .or(settings_model)
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .or_else(|| b.allowed_models.first().map(|m| m.trim()))
+        .map(str::to_string)
+}
+
+/// Settings with the model this backend should use, enforcing its allowlist.
+pub fn settings_for_backend(b: &BackendConfig, settings: &Settings) -> anyhow::Result<Settings> {
+    let model = resolve_model_name(b, settings.model.as_deref());
+    if let Some(m) = model.as_deref() {
+        if !model_allowed(b, m) {
+            return Err(user_error(
+                ErrorKind::Config,
+                format!("Model {m} is not allowed for this workspace."),
+            ));
+        }
+    } else if b.provider != BackendProvider::Codex {
+        return Err(user_error(
+            ErrorKind::Config,
+            format!(
+                "No model is configured for the {} backend.",
+                b.provider.as_str()
+            ),
+        ));
+    }
+    let mut out = settings.clone();
+    out.model = model;
+    Ok(out)
+}
+
+/// One agent turn.
+pub struct AgentTurn<'a> {
+    pub state: &'a AppState,
+    pub task: &'a Task,
+    pub thread_id: &'a str,
+    pub settings: &'a Settings,
+    pub cwd: &'a Path,
+    pub input_text: &'a str,
+    pub output_schema: serde_json::Value,
+    pub trace_tx: Option<&'a mpsc::UnboundedSender<CodexTurnEvent>>,
+}
+
+pub trait AgentBackend {
+    /// Resume `existing` when it belongs to this backend, otherwise start a new thread.
+    async fn resume_or_start_thread(
+        &mut self,
+        existing: Option<&str>,
+        settings: &Settings,
+        cwd: &Path,
+    ) -> anyhow::Result<String>;
+
+    async fn run_turn(&mut self, turn: AgentTurn<'_>) -> anyhow::Result<CodexTurnOutput>;
+
+    /// Drop a one-off thread (observer, reflector) once its turn is done.
+    async fn discard_thread(&mut self, thread_id: &str) -> anyhow::Result<()>;
+
+    /// Whether the agent can call tools (MCP servers, shell, browser).
+    fn supports_tools(&self) -> bool;
+}
+
+impl AgentBackend for CodexManager {
+    async fn resume_or_start_thread(
+        &mut self,
+        existing: Option<&str>,
+        settings: &Settings,
+        cwd: &Path,
+    ) -> anyhow::Result<String> {
+        let existing = existing.filter(|id| !id.starts_with(CHAT_THREAD_PREFIX));
+        CodexManager::resume_or_start_thread(self, existing, settings, cwd).await
+    }
+
+    async fn run_turn(&mut self, turn: AgentTurn<'_>) -> anyhow::Result<CodexTurnOutput> {
+        CodexManager::run_turn(
+            self,
+            turn.state,
+            turn.task,
+            turn.thread_id,
+            turn.settings,
+            turn.cwd,
+            turn.input_text,
+            turn.output_schema,
+            turn.trace_tx,
+        )
+        .await
+    }
+
+    /// Codex keeps its own threads.
+    async fn discard_thread(&mut self, _thread_id: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+/// Tool-less chat backend for Anthropic and OpenAI-compatible endpoints.
+pub struct ChatBackend {
+    provider: BackendProvider,
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    max_tokens: u32,
+    threads_dir: PathBuf,
+}
+
+impl ChatBackend {
+    pub fn new(
+        b: &BackendConfig,
+        http: reqwest::Client,
+        api_key: Option<String>,
+        data_dir: &Path,
+    ) -> Self {
+        let default_base = match b.provider {
+            BackendProvider::Anthropic => DEFAULT_ANTHROPIC_BASE_URL,
+            _ => DEFAULT_OPENAI_COMPATIBLE_BASE_URL,
+        };
+        let base_url = b
+            .base_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|u| !u.is_empty())
+            .unwrap_or(default_base)
+            .trim_end_matches('/')
+            .to_string();
+        Self {
+            provider: b.provider,
+            http,
+            base_url,
+            api_key,
+            max_tokens: b
+                .max_tokens
+                .unwrap_or(DEFAULT_MAX_TOKENS)
+                .clamp(256, 64_000),
+            threads_dir: data_dir.join("llm_threads"),
+        }
+    }
+
+    fn thread_path(&self, thread_id: &str) -> anyhow::Result<PathBuf> {
+        let ok = thread_id.starts_with(CHAT_THREAD_PREFIX)
+            && thread_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !ok {
+            anyhow::bail!("invalid chat thread id: {thread_id}");
+        }
+        Ok(self.threads_dir.join(format!("{thread_id}.json")))
+    }
+
+    async fn load_thread(&self, thread_id: &str) -> anyhow::Result<Vec<ChatMessage>> {
+        let path = self.thread_path(thread_id)?;
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => {
+                serde_json::from_slice(&bytes).with_context(|| format!("parse {}", path.display()))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(err) => Err(err).with_context(|| format!("read {}", path.display())),
+        }
+    }
+
+    async fn save_thread(&self, thread_id: &str, messages: &[ChatMessage]) -> anyhow::Result<()> {
+        let path = self.thread_path(thread_id)?;
+        tokio::fs::create_dir_all(&self.threads_dir)
+            .await
+            .with_context(|| format!("create {}", self.threads_dir.display()))?;
+        // Write to a temp file and rename, so a crash never leaves a truncated thread.
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec(messages)?)
+            .await
+            .with_context(|| format!("write {}", tmp.display()))?;
+        tokio::fs::rename(&tmp, &path)
+            .await
+            .with_context(|| format!("rename {} to {}", tmp.display(), path.display()))
+    }
+
+    async fn complete(
+        &self,
+        model: &str,
+        system: &str,
+        messages: &[ChatMessage],
+    ) -> anyhow::Result<String> {
+        let (url, body) = match self.provider {
+            BackendProvider::Anthropic => (
+                format!("{}/messages", self.base_url),
+                json!({
+                    "model": model,
+                    "max_tokens": self.max_tokens,
+                    "system": system,
+                    "messages": messages,
+                }),
+            ),
+            _ => {
+                let mut all = vec![json!({ "role": "system", "content": system })];
+                all.extend(messages.iter().map(|m| json!(m)));
+                (
+                    format!("{}/chat/completions", self.base_url),
+                    json!({
+                        "model": model,
+                        "max_tokens": self.max_tokens,
+                        "messages": all,
+                    }),
+                )
+            }
+        };
+
+        let mut req = self.http.post(&url).json(&body);
+        match (self.provider, self.api_key.as_deref()) {
+            (BackendProvider::Anthropic, key) => {
+                req = req
+                    .header("x-api-key", key.unwrap_or(""))
+                    .header("anthropic-version", "2023-06-01");
+            }
+            (_, Some(key)) => req = req.bearer_auth(key),
+            (_, None) => {}
+        }
+        let resp = req
+            .send()
+            .await
+            .with_context(|| format!("{} request to {url}", self.provider.as_str()))?;
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        if !status.is_success() {
+            let detail: String = text.chars().take(500).collect();
+            // "status 401" / "status 429" are categorized by crate::errors.
+            anyhow::bail!(
+                "{} returned status {}: {detail}",
+                self.provider.as_str(),
+                status.as_u16()
+            );
+        }
+        let v: serde_json::Value = serde_json::from_str(&text).context("decode model response")?;
+        let out = match self.provider {
+            BackendProvider::Anthropic => {
+                v.get("content").and_then(|c| c.as_array()).map(|parts| {
+                    parts
+                        .iter()
+                        .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                        .collect::<Vec<_>>()
+                        .join("")
+                })
+            }
+            _ => v
+                .pointer("/choices/0/message/content")
+                .and_then(|c| c.as_str())
+                .map(str::to_string),
+        };
+        out.context("model response contained no text")
+    }
+}
+
+impl AgentBackend for ChatBackend {
+    async fn resume_or_start_thread(
+        &mut self,
+        existing: Option<&str>,
+        _settings: &Settings,
+        _cwd: &Path,
+    ) -> anyhow::Result<String> {
+        if let Some(id) = existing {
+            if self.thread_path(id).is_ok() {
+                return Ok(id.to_string());
+            }
+        }
+        Ok(crate::random_id(CHAT_THREAD_PREFIX.trim_end_matches('_')))
+    }
+
+    async fn run_turn(&mut self, turn: AgentTurn<'_>) -> anyhow::Result<CodexTurnOutput> {
+        if crate::db::is_task_cancel_requested(&turn.state.pool, turn.task.id).await? {
+            anyhow::bail!("task cancelled by admin");
+        }
+        let model = turn
+            .settings
+            .model
+            .as_deref()
+            .context("no model configured for chat backend")?;
+        let emit = |event_type: &str, level: &str, message: &str, details: &str| {
+            if let Some(tx) = turn.trace_tx {
+                let _ = tx.send(CodexTurnEvent {
+                    event_type: event_type.to_string(),
+                    level: level.to_string(),
+                    message: message.to_string(),
+                    details: details.to_string(),
+                });
+            }
+        };
+        emit(
+            "turn.start",
+            "info",
+            "turn-started",
+            &format!(
+                "thread={} backend={} model={model}",
+                turn.thread_id,
+                self.provider.as_str()
+            ),
+        );
+
+        let system = format!(
+            "You are {name}, a helpful assistant. You have no tools in this deployment: \
+answer from the provided context and your own knowledge.\n\n\
+Respond with ONLY a single JSON object that matches this JSON Schema:\n{schema}",
+            name = turn.settings.agent_name,
+            schema = turn.output_schema,
+        );
+        let mut messages = self.load_thread(turn.thread_id).await?;
+        messages.push(ChatMessage {
+            role: "user".to_string(),
+            content: turn.input_text.to_string(),
+        });
+        let reply = match self.complete(model, &system, &messages).await {
+            Ok(r) => r,
+            Err(err) => {
+                emit("turn.failed", "error", "turn failed", &format!("{err:#}"));
+                return Err(err);
+            }
+        };
+        messages.push(ChatMessage {
+            role: "assistant".to_string(),
+            content: reply.clone(),
+        });
+        let skip = messages.len().saturating_sub(MAX_THREAD_MESSAGES);
+        if let Err(err) = self.save_thread(turn.thread_id, &messages[skip..]).await {
+            warn!(error = %err, thread_id = turn.thread_id, "failed to save chat thread");
+        }
+        emit("turn.completed", "info", "turn completed", "");
+
+        Ok(CodexTurnOutput {
+            agent_message_text: reply,
+        })
+    }
+
+    async fn discard_thread(&mut self, thread_id: &str) -> anyhow::Result<()> {
+        let path = self.thread_path(thread_id)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).with_context(|| format!("remove {}", path.display())),
+        }
+    }
+
+    fn supports_tools(&self) -> bool {
+        false
+    }
+}
+
+/// The backend selected for one task.
+pub enum Agent<'a> {
+    Codex(&'a mut CodexManager),
+    Chat(ChatBackend),
+}
+
+impl AgentBackend for Agent<'_> {
+    async fn resume_or_start_thread(
+        &mut self,
+        existing: Option<&str>,
+        settings: &Settings,
+        cwd: &Path,
+    ) -> anyhow::Result<String> {
+        match self {
+            Agent::Codex(c) => {
+                AgentBackend::resume_or_start_thread(*c, existing, settings, cwd).await
+            }
+            Agent::Chat(c) => c.resume_or_start_thread(existing, settings, cwd).await,
+        }
+    }
+
+    async fn run_turn(&mut self, turn: AgentTurn<'_>) -> anyhow::Result<CodexTurnOutput> {
+        match self {
+            Agent::Codex(c) => AgentBackend::run_turn(*c, turn).await,
+            Agent::Chat(c) => c.run_turn(turn).await,
+        }
+    }
+
+    async fn discard_thread(&mut self, thread_id: &str) -> anyhow::Result<()> {
+        match self {
+            Agent::Codex(c) => AgentBackend::discard_thread(*c, thread_id).await,
+            Agent::Chat(c) => c.discard_thread(thread_id).await,
+        }
+    }
+
+    fn supports_tools(&self) -> bool {
+        match self {
+            Agent::Codex(c) => c.supports_tools(),
+            Agent::Chat(c) => c.supports_tools(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn llm_backend_resolves_per_workspace_with_allowlist() {
+        let raw = r#"{
+            "default": {"provider": "codex", "allowed_models": ["gpt-5.2", "gpt-5.2-mini"]},
+            "workspaces": {"T1": {"provider": "openai_compatible", "base_url": "http://ollama:11434/v1", "model": "llama3.1"}}
+        }"#;
+        assert_eq!(
+            resolve_backend(raw, "T1").provider,
+            BackendProvider::OpenaiCompatible
+        );
+        assert_eq!(resolve_backend(raw, "T2").provider, BackendProvider::Codex);
+        assert_eq!(
+            resolve_backend("not json", "T1").provider,
+            BackendProvider::Codex
+        );
+        assert!(parse_llm_backends(r#"{"default": {"provider": "anthropic"}}"#).is_err());
+        assert!(parse_llm_backends(
+            r#"{"default": {"model": "gpt-4o", "allowed_models": ["gpt-5.2"]}}"#
+        )
+        .is_err());
+        assert!(parse_llm_backends(
+            r#"{"default": {"provider": "openai_compatible", "model": "m", "api_key_secret": "OLLAMA_KEY"}}"#
+        )
+        .is_ok());
+        assert!(parse_llm_backends(
+            r#"{"default": {"provider": "openai_compatible", "model": "m", "api_key_env": "OPENAI_API_KEY"}}"#
+        )
+        .is_err());
+    }
+}
@@ -1,23 +1,53 @@
+#![recursion_limit = "256"]
+
+mod analytics_export;
 mod api;
+mod approval_insights;
+mod approval_preview;
 mod approvals;
 mod bootstrap;
+mod channel_lifecycle;
 mod codex;
 mod codex_login;
+mod command_env;
 mod config;
+mod config_bundle;
+mod content_policy;
+mod context_sources;
 mod cron_expr;
 mod crypto;
 mod db;
+mod digest;
 mod discord;
+mod elevation;
+mod errors;
 mod github_login;
+mod guardrail_canary;
 mod guardrails;
+mod language;
+mod llm;
 mod models;
 mod msteams;
+mod output_contract;
+mod public_status;
+mod response_cache;
+mod saved_prompts;
+mod scale_hint;
 mod secrets;
 mod slack;
+mod slack_home;
+mod slack_modals;
+mod slack_publish;
+mod stt;
+mod task_links;
 mod telegram;
+mod token_budget;
 mod whatsapp;
 mod worker;
 
+#[cfg(test)]
+mod test_support;
+
 use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -44,15 +74,20 @@ use tracing_subscriber::EnvFilter;
 use crate::config::Config;
 use crate::crypto::{parse_master_key, Crypto};
 use crate::models::PermissionsMode;
+use crate::saved_prompts::Resolved;
 use crate::secrets::{
     brave_search_api_key_configured, github_client_id_configured, github_token_configured,
     openai_api_key_configured, slack_bot_token_configured, slack_signing_secret_configured,
     telegram_bot_token_configured, telegram_webhook_secret_configured,
 };
-use crate::slack::{verify_slack_signature, SlackClient};
+use crate::slack::{SlackClient, SlackRequestGuard};
 
 type AppResult<T> = Result<T, AppError>;
 
+/// `grail-server web-mcp` serves the web tools over stdio, for deployments that don't
+/// ship the `grail-web-mcp` sidecar binary.
+const WEB_MCP_SUBCOMMAND: &str = "web-mcp";
+
 #[derive(Debug)]
 struct AppError(anyhow::Error);
 
@@ -70,8 +105,20 @@ impl From<sqlx::Error> for AppError {
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        error!(error = %self.0, "request failed");
-        (StatusCode::INTERNAL_SERVER_ERROR, "internal error").into_response()
+        let classified = errors::classify(&self.0);
+        let reference = errors::error_ref();
+        let status = classified.kind.status();
+        if status.is_server_error() {
+            error!(error = %format!("{:#}", self.0), kind = classified.kind.as_str(), reference = %reference, "request failed");
+        } else {
+            warn!(error = %format!("{:#}", self.0), kind = classified.kind.as_str(), reference = %reference, "request rejected");
+        }
+        let body = serde_json::json!({
+            "error": classified.message,
+            "kind": classified.kind.as_str(),
+            "reference": reference,
+        });
+        (status, axum::Json(body)).into_response()
     }
 }
 
@@ -84,10 +131,20 @@ struct AppState {
     slack_bot_user_id: Arc<RwLock<Option<String>>>,
     telegram_bot_username: Arc<RwLock<Option<String>>>,
     task_notify: Arc<tokio::sync::Notify>,
+    slack_guard: Arc<SlackRequestGuard>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    if std::env::args().nth(1).as_deref() == Some(WEB_MCP_SUBCOMMAND) {
+        // stdout carries the MCP protocol; log to stderr.
+        tracing_subscriber::fmt()
+            .with_writer(std::io::stderr)
+            .with_env_filter(EnvFilter::from_default_env())
+            .init();
+        return grail_web::serve_stdio().await;
+    }
+
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
         .init();
@@ -122,6 +179,7 @@ async fn main() -> anyhow::Result<()> {
         slack_bot_user_id: Arc::new(RwLock::new(None)),
         telegram_bot_username: Arc::new(RwLock::new(None)),
         task_notify: Arc::new(tokio::sync::Notify::new()),
+        slack_guard: Arc::new(SlackRequestGuard::default()),
     };
 
     // Background worker (configurable concurrency).
@@ -138,9 +196,18 @@ async fn main() -> anyhow::Result<()> {
             post(api::api_set_secret).delete(api::api_delete_secret),
         )
         .route("/tasks", get(api::api_tasks))
+        .route("/tasks/add", post(api::api_task_add))
+        .route("/tasks/test", post(api::api_task_test))
+        .route("/tasks/resolve", get(api::api_task_resolve))
+        .route("/tasks/rerun", post(api::api_tasks_rerun))
         .route("/tasks/{id}", get(api::api_task_details))
+        .route(
+            "/tasks/{id}/artifacts/{artifact_id}",
+            get(api::api_task_artifact),
+        )
         .route("/tasks/{id}/cancel", post(api::api_task_cancel))
         .route("/tasks/{id}/retry", post(api::api_task_retry))
+        .route("/analytics/export", get(api::api_analytics_export))
         .route("/memory", get(api::api_memory))
         .route("/memory/clear", post(api::api_memory_clear))
         .route("/context", get(api::api_context_list))
@@ -153,17 +220,58 @@ async fn main() -> anyhow::Result<()> {
         .route("/cron/{id}/delete", post(api::api_cron_delete))
         .route("/cron/{id}/enable", post(api::api_cron_enable))
         .route("/cron/{id}/disable", post(api::api_cron_disable))
+        .route("/elevations", get(api::api_elevations_list))
+        .route("/elevations/add", post(api::api_elevations_add))
+        .route("/elevations/{id}/revoke", post(api::api_elevations_revoke))
         .route("/guardrails", get(api::api_guardrails_list))
         .route("/guardrails/add", post(api::api_guardrails_add))
+        .route("/guardrails/canary", get(api::api_guardrail_canary_get))
+        .route(
+            "/guardrails/canary/start",
+            post(api::api_guardrail_canary_start),
+        )
+        .route(
+            "/guardrails/canary/promote",
+            post(api::api_guardrail_canary_promote),
+        )
+        .route(
+            "/guardrails/canary/discard",
+            post(api::api_guardrail_canary_discard),
+        )
         .route("/guardrails/{id}/delete", post(api::api_guardrails_delete))
         .route("/guardrails/{id}/enable", post(api::api_guardrails_enable))
         .route(
             "/guardrails/{id}/disable",
             post(api::api_guardrails_disable),
         )
+        .route("/saved-prompts", get(api::api_saved_prompts_list))
+        .route("/saved-prompts/add", post(api::api_saved_prompts_add))
+        .route(
+            "/saved-prompts/{id}/delete",
+            post(api::api_saved_prompts_delete),
+        )
+        .route(
+            "/saved-prompts/{id}/enable",
+            post(api::api_saved_prompts_enable),
+        )
+        .route(
+            "/saved-prompts/{id}/disable",
+            post(api::api_saved_prompts_disable),
+        )
+        .route("/config/export", get(api::api_config_export))
+        .route(
+            "/config/import/preview",
+            post(api::api_config_import_preview),
+        )
+        .route("/config/import", post(api::api_config_import))
         .route("/approvals", get(api::api_approvals_list))
+        .route("/approvals/insights", get(api::api_approvals_insights))
         .route("/approvals/{id}/approve", post(api::api_approval_approve))
         .route("/approvals/{id}/always", post(api::api_approval_always))
+        .route(
+            "/approvals/{id}/always/{scope}",
+            post(api::api_approval_always_scope),
+        )
         .route("/approvals/{id}/deny", post(api::api_approval_deny))
         .route("/auth", get(api::api_auth_get))
         .route("/auth/device/start", post(api::api_auth_device_start))
@@ -181,11 +289,23 @@ async fn main() -> anyhow::Result<()> {
         .route("/diagnostics", get(api::api_diagnostics))
         .route("/diagnostics/codex", post(api::api_diagnostics_codex));
 
+    // Every Slack endpoint is signed; verification (and replay rejection) happens once here.
+    let slack_routes = Router::new()
+        .route("/slack/events", post(slack_events))
+        .route("/slack/actions", post(slack_actions))
+        .route("/slack/commands", post(slack_commands))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            slack_signature_guard,
+        ));
+
     let app = Router::new()
         .route("/", get(|| async { Redirect::to("/admin/status") }))
         .route("/healthz", get(healthz))
-        .route("/slack/events", post(slack_events))
-        .route("/slack/actions", post(slack_actions))
+        .route("/status", get(public_status::status_page))
+        .route("/status.json", get(public_status::status_json))
+        .route("/api/v1/scale-hint", get(scale_hint::scale_hint))
+        .merge(slack_routes)
         .route("/telegram/webhook", post(telegram_webhook))
         .route("/whatsapp/webhook", get(whatsapp_webhook_verify))
         .route("/whatsapp/webhook", post(whatsapp_webhook))
@@ -253,6 +373,47 @@ async fn admin_frontend_missing() -> impl IntoResponse {
     )
 }
 
+async fn slack_signature_guard(
+    State(state): State<AppState>,
+    req: axum::http::Request<axum::body::Body>,
+    next: middleware::Next,
+) -> Response {
+    let secret = match crate::secrets::load_slack_signing_secret_opt(&state).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            let mut resp = (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "slack not configured (missing SLACK_SIGNING_SECRET)",
+            )
+                .into_response();
+            resp.headers_mut().insert(
+                axum::http::header::HeaderName::from_static("x-slack-no-retry"),
+                HeaderValue::from_static("1"),
+            );
+            return resp;
+        }
+        Err(err) => {
+            warn!(error = %err, "failed to load slack signing secret");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "internal error").into_response();
+        }
+    };
+
+    let (parts, body) = req.into_parts();
+    let body = match axum::body::to_bytes(body, 1024 * 1024).await {
+        Ok(b) => b,
+        Err(_) => return (StatusCode::PAYLOAD_TOO_LARGE, "payload too large").into_response(),
+    };
+    if let Err(err) = state.slack_guard.verify(&secret, &parts.headers, &body) {
+        warn!(error = %err, path = %parts.uri.path(), "rejected slack request");
+        return (StatusCode::UNAUTHORIZED, "invalid signature").into_response();
+    }
+    next.run(axum::http::Request::from_parts(
+        parts,
+        axum::body::Body::from(body),
+    ))
+    .await
+}
+
 async fn admin_basic_auth(
     State(state): State<AppState>,
     req: axum::http::Request<axum::body::Body>,
@@ -402,18 +563,25 @@ fn host_from_url(s: &str) -> Option<&str> {
 }
 
 fn task_trace_url(state: &AppState, task_id: i64) -> String {
-    state
-        .config
-        .base_url
-        .as_deref()
-        .map(|base| format!("{}/admin/tasks/{}", base.trim_end_matches('/'), task_id))
-        .unwrap_or_else(|| format!("/admin/tasks/{task_id}"))
+    task_links::web_link(state.config.base_url.as_deref(), task_id)
 }
 
 fn task_link_message(task_id: i64, task_url: &str) -> String {
     format!("Task queued as #{task_id}. Track progress: {task_url}")
 }
 
+fn dependent_task_link_message(task_id: i64, dep: &TaskDependency, task_url: &str) -> String {
+    let when = if dep.on_failure == "run" {
+        "finishes"
+    } else {
+        "succeeds"
+    };
+    format!(
+        "Task queued as #{task_id}; it will start when task #{} {when}. Track progress: {task_url}",
+        dep.task_id
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -526,42 +694,310 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_task_command_bump() {
+        assert_eq!(
+            parse_task_command("bump #54"),
+            Some(TaskCommand::Bump { task_id: 54 })
+        );
+        assert_eq!(
+            parse_task_command("Prioritize task 55!"),
+            Some(TaskCommand::Bump { task_id: 55 })
+        );
+        assert_eq!(
+            parse_task_command("bump task 56 please"),
+            Some(TaskCommand::Show { task_id: 56 })
+        );
+    }
+
     #[test]
     fn parse_task_command_does_not_match_approval() {
         assert_eq!(parse_task_command("cancel appr_123"), None);
     }
+
+    #[test]
+    fn parse_task_dependency_after_syntax() {
+        assert_eq!(
+            parse_task_dependency("after #12 post the report to #leadership"),
+            Some(TaskDependency {
+                task_id: 12,
+                on_failure: "fail",
+                prompt: "post the report to #leadership".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_task_dependency("After task #7 regardless: summarize what happened"),
+            Some(TaskDependency {
+                task_id: 7,
+                on_failure: "run",
+                prompt: "summarize what happened".to_string(),
+            })
+        );
+        assert_eq!(parse_task_dependency("after lunch, ping me"), None);
+        assert_eq!(parse_task_dependency("after #3"), None);
+    }
+
+    #[tokio::test]
+    async fn task_dependency_stays_in_its_channel() {
+        let db = crate::test_support::TestDb::new().await;
+        let pool = db.pool.clone();
+        let task_id = db::enqueue_task(
+            &pool,
+            &db::NewTask {
+                provider: "slack",
+                workspace_id: "T1",
+                channel_id: "C1",
+                thread_ts: "1.1",
+                event_ts: "1.1",
+                requested_by_user_id: "U1",
+                prompt_text: "draft the payroll summary",
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let dep = parse_task_dependency(&format!("after #{task_id} post it here")).unwrap();
+        let check = |provider, workspace, channel| {
+            check_task_dependency(&pool, &dep, provider, workspace, channel)
+        };
+
+        assert!(check("slack", "T1", "C1").await.is_ok());
+        assert!(check("slack", "T1", "C2").await.is_err());
+        assert!(check("slack", "T2", "C1").await.is_err());
+        assert!(check("telegram", "telegram", "C1").await.is_err());
+
+        // A cancelled predecessor can only be followed with `regardless`.
+        assert!(db::cancel_task(&pool, task_id).await.unwrap());
+        assert!(check("slack", "T1", "C1").await.is_err());
+        let regardless =
+            parse_task_dependency(&format!("after #{task_id} regardless post it here")).unwrap();
+        assert!(
+            check_task_dependency(&pool, &regardless, "slack", "T1", "C1")
+                .await
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn parse_approval_command_scoped_always() {
+        assert_eq!(
+            parse_approval_command("always-program appr_1"),
+            Some(("always_program", "appr_1".to_string()))
+        );
+        assert_eq!(
+            parse_approval_command("always-directory appr_1"),
+            Some(("always_directory", "appr_1".to_string()))
+        );
+    }
+
+    #[test]
+    fn slack_grid_workspace_and_channel_scoping() {
+        assert!(slack_workspace_matches("T1", "T1", None));
+        assert!(slack_workspace_matches("E1", "T2", Some("E1")));
+        assert!(!slack_workspace_matches("T1", "T2", Some("E1")));
+
+        let allow = parse_allow_from("C1, T2:C9, T3:*");
+        assert!(slack_channel_allowed(&allow, "T1", "C1"));
+        assert!(slack_channel_allowed(&allow, "T2", "C9"));
+        assert!(!slack_channel_allowed(&allow, "T1", "C9"));
+        assert!(slack_channel_allowed(&allow, "T3", "C42"));
+        assert!(slack_channel_allowed(&parse_allow_from(""), "T1", "C42"));
+    }
+
+    #[test]
+    fn slack_envelope_parses_enterprise_fields() {
+        let env: SlackEnvelope = serde_json::from_str(
+            r#"{"type":"event_callback","team_id":"T1","enterprise_id":"E1",
+                "is_enterprise_install":true,"context_team_id":"T2","event_id":"Ev1",
+                "event":{"type":"app_mention","user":"U1","text":"hi","ts":"1.0","channel":"C1"}}"#,
+        )
+        .unwrap();
+        let SlackEnvelope::EventCallback {
+            enterprise_id,
+            is_enterprise_install,
+            context_team_id,
+            ..
+        } = env
+        else {
+            panic!("expected event_callback");
+        };
+        assert_eq!(enterprise_id.as_deref(), Some("E1"));
+        assert!(is_enterprise_install);
+        assert_eq!(context_team_id.as_deref(), Some("T2"));
+    }
+
+    #[test]
+    fn slack_retry_headers_and_dedupe_key() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(slack_retry_from_headers(&headers), None);
+        headers.insert("x-slack-retry-num", HeaderValue::from_static("2"));
+        headers.insert(
+            "x-slack-retry-reason",
+            HeaderValue::from_static("http_timeout"),
+        );
+        assert_eq!(
+            slack_retry_from_headers(&headers),
+            Some(SlackRetry {
+                num: 2,
+                reason: "http_timeout".to_string(),
+            })
+        );
+
+        let body = br#"{"type":"event_callback","team_id":"T1","enterprise_id":"E1","is_enterprise_install":true,"event_id":"Ev1","event":{"type":"app_mention"}}"#;
+        assert_eq!(
+            slack_event_dedupe_key(body),
+            Some(("E1".to_string(), "Ev1".to_string()))
+        );
+        let body =
+            br#"{"type":"event_callback","team_id":"T1","enterprise_id":"E1","event_id":"Ev2"}"#;
+        assert_eq!(
+            slack_event_dedupe_key(body),
+            Some(("T1".to_string(), "Ev2".to_string()))
+        );
+        assert_eq!(
+            slack_event_dedupe_key(br#"{"type":"url_verification","challenge":"x"}"#),
+            None
+        );
+    }
 }
 
-async fn slack_events(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    body: Bytes,
-) -> impl IntoResponse {
-    let secret = match crate::secrets::load_slack_signing_secret_opt(&state).await {
-        Ok(Some(v)) => v,
-        Ok(None) => {
-            let mut resp = (
-                StatusCode::SERVICE_UNAVAILABLE,
-                "slack not configured (missing SLACK_SIGNING_SECRET)",
-            )
-                .into_response();
-            resp.headers_mut().insert(
-                axum::http::header::HeaderName::from_static("x-slack-no-retry"),
-                HeaderValue::from_static("1"),
-            );
-            return resp;
+/// Delivery attempt info from `X-Slack-Retry-Num` / `X-Slack-Retry-Reason`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SlackRetry {
+    num: u32,
+    /// e.g. `http_timeout`, `http_error`, `too_many_connections`.
+    reason: String,
+}
+
+fn slack_retry_from_headers(headers: &HeaderMap) -> Option<SlackRetry> {
+    let num = headers
+        .get("x-slack-retry-num")?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    let reason = headers
+        .get("x-slack-retry-reason")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .trim()
+        .to_string();
+    Some(SlackRetry { num, reason })
+}
+
+/// Event ids are unique per app install, which spans every workspace of an org-wide
+/// Grid install.
+fn slack_install_id(
+    team_id: &str,
+    enterprise_id: Option<&str>,
+    is_enterprise_install: bool,
+) -> String {
+    match enterprise_id.filter(|e| is_enterprise_install && !e.trim().is_empty()) {
+        Some(e) => e.to_string(),
+        None => team_id.to_string(),
+    }
+}
+
+/// Dedupe key `(install_id, event_id)` of an `event_callback` payload.
+fn slack_event_dedupe_key(body: &[u8]) -> Option<(String, String)> {
+    #[derive(Deserialize)]
+    struct Probe {
+        #[serde(default)]
+        team_id: String,
+        #[serde(default)]
+        enterprise_id: Option<String>,
+        #[serde(default)]
+        is_enterprise_install: bool,
+        event_id: Option<String>,
+    }
+    let p: Probe = serde_json::from_slice(body).ok()?;
+    let event_id = p.event_id.filter(|e| !e.trim().is_empty())?;
+    Some((
+        slack_install_id(
+            &p.team_id,
+            p.enterprise_id.as_deref(),
+            p.is_enterprise_install,
+        ),
+        event_id,
+    ))
+}
+
+fn with_slack_no_retry(mut resp: Response) -> Response {
+    resp.headers_mut().insert(
+        axum::http::header::HeaderName::from_static("x-slack-no-retry"),
+        HeaderValue::from_static("1"),
+    );
+    resp
+}
+
+async fn slack_events(State(state): State<AppState>, headers: HeaderMap, body: Bytes) -> Response {
+    // Redeliveries of events we already accepted are acknowledged without re-parsing them.
+    if let Some(retry) = slack_retry_from_headers(&headers) {
+        if let Some((install_id, event_id)) = slack_event_dedupe_key(&body) {
+            match db::is_event_processed(&state.pool, &install_id, &event_id).await {
+                Ok(true) => {
+                    info!(
+                        %event_id,
+                        retry_num = retry.num,
+                        retry_reason = %retry.reason,
+                        "acknowledged slack retry of an already processed event"
+                    );
+                    return with_slack_no_retry((StatusCode::OK, "").into_response());
+                }
+                Ok(false) => info!(
+                    %event_id,
+                    retry_num = retry.num,
+                    retry_reason = %retry.reason,
+                    "processing slack retry of an unprocessed event"
+                ),
+                Err(err) => warn!(error = %err, "failed to check slack retry against dedupe table"),
+            }
         }
-        Err(err) => {
-            warn!(error = %err, "failed to load slack signing secret");
-            return (StatusCode::INTERNAL_SERVER_ERROR, "internal error").into_response();
+    }
+
+    let resp = handle_slack_event(state.clone(), body)
+        .await
+        .into_response();
+    // A 2xx means the event is recorded (enqueued, answered or deliberately ignored);
+    // errors keep Slack's retries.
+    if resp.status().is_success() {
+        match db::get_settings(&state.pool).await {
+            Ok(s) if s.slack_no_retry_after_enqueue => return with_slack_no_retry(resp),
+            Ok(_) => {}
+            Err(err) => warn!(error = %err, "failed to load settings for slack no-retry"),
         }
-    };
+    }
+    resp
+}
 
-    if let Err(err) = verify_slack_signature(&secret, &headers, &body) {
-        warn!(error = %err, "invalid slack signature");
-        return (StatusCode::UNAUTHORIZED, "invalid signature").into_response();
+/// Archive, delete and bot-removal events: stop the channel's queued work.
+async fn slack_channel_gone(
+    state: &AppState,
+    install_id: &str,
+    event_id: &str,
+    team_id: &str,
+    channel: &str,
+    reason: crate::channel_lifecycle::Reason,
+    actor: Option<&str>,
+) -> Response {
+    match db::try_mark_event_processed(&state.pool, install_id, event_id).await {
+        Ok(true) => {}
+        Ok(false) => return (StatusCode::OK, "").into_response(),
+        Err(err) => {
+            error!(error = %err, "failed to dedupe event");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "db error").into_response();
+        }
     }
+    if let Err(err) =
+        crate::channel_lifecycle::channel_gone(state, team_id, channel, reason, actor).await
+    {
+        warn!(error = %err, channel_id = %channel, "failed to stop work for a gone slack channel");
+    }
+    (StatusCode::OK, "").into_response()
+}
 
+async fn handle_slack_event(state: AppState, body: Bytes) -> impl IntoResponse {
     let env: SlackEnvelope = match serde_json::from_slice(&body) {
         Ok(v) => v,
         Err(err) => {
@@ -576,9 +1012,18 @@ async fn slack_events(
         }
         SlackEnvelope::EventCallback {
             team_id,
+            enterprise_id,
+            is_enterprise_install,
+            context_team_id,
             event_id,
             event,
         } => {
+            let enterprise_id = enterprise_id.filter(|e| !e.trim().is_empty());
+            let install_id =
+                slack_install_id(&team_id, enterprise_id.as_deref(), is_enterprise_install);
+            let team_id = context_team_id
+                .filter(|t| !t.trim().is_empty())
+                .unwrap_or(team_id);
             let (
                 user,
                 text,
@@ -632,8 +1077,9 @@ async fn slack_events(
                             }
                         })
                         .unwrap_or("");
-                    // Ignore bot messages and non-user subtypes to avoid loops.
-                    if bot_id.is_some() || subtype.is_some() {
+                    // Ignore bot messages and non-user subtypes to avoid loops. File
+                    // uploads (`file_share`) are user messages with attachments.
+                    if bot_id.is_some() || subtype.as_deref().is_some_and(|s| s != "file_share") {
                         return (StatusCode::OK, "").into_response();
                     }
                     let Some(user) = user else {
@@ -672,11 +1118,95 @@ async fn slack_events(
                         return (StatusCode::OK, "").into_response();
                     }
                 }
+                SlackEvent::AppHomeOpened { user, channel, tab } => {
+                    if tab == "home"
+                        && slack_interaction_allowed(
+                            &state,
+                            &team_id,
+                            enterprise_id.as_deref(),
+                            &user,
+                            &channel,
+                        )
+                        .await
+                    {
+                        // Publishing takes several queries and an API call; ack first.
+                        tokio::spawn(async move {
+                            if let Err(err) =
+                                crate::slack_home::publish(&state, &user, &channel).await
+                            {
+                                warn!(error = %err, "failed to publish slack app home");
+                            }
+                        });
+                    }
+                    return (StatusCode::OK, "").into_response();
+                }
+                SlackEvent::ChannelArchive { channel, user } => {
+                    let reason = crate::channel_lifecycle::Reason::Archived;
+                    return slack_channel_gone(
+                        &state,
+                        &install_id,
+                        &event_id,
+                        &team_id,
+                        &channel,
+                        reason,
+                        user.as_deref(),
+                    )
+                    .await;
+                }
+                SlackEvent::GroupArchive { channel } => {
+                    let reason = crate::channel_lifecycle::Reason::Archived;
+                    return slack_channel_gone(
+                        &state,
+                        &install_id,
+                        &event_id,
+                        &team_id,
+                        &channel,
+                        reason,
+                        None,
+                    )
+                    .await;
+                }
+                SlackEvent::ChannelDeleted { channel } | SlackEvent::GroupDeleted { channel } => {
+                    let reason = crate::channel_lifecycle::Reason::Deleted;
+                    return slack_channel_gone(
+                        &state,
+                        &install_id,
+                        &event_id,
+                        &team_id,
+                        &channel,
+                        reason,
+                        None,
+                    )
+                    .await;
+                }
+                SlackEvent::MemberLeftChannel { user, channel } => {
+                    let bot_user_id = match crate::secrets::load_slack_bot_token_opt(&state).await {
+                        Ok(Some(token)) => slack_bot_user_id_cached(&state, &token)
+                            .await
+                            .ok()
+                            .flatten(),
+                        _ => None,
+                    };
+                    if bot_user_id.as_deref() != Some(user.as_str()) {
+                        return (StatusCode::OK, "").into_response();
+                    }
+                    let reason = crate::channel_lifecycle::Reason::Removed;
+                    return slack_channel_gone(
+                        &state,
+                        &install_id,
+                        &event_id,
+                        &team_id,
+                        &channel,
+                        reason,
+                        None,
+                    )
+                    .await;
+                }
                 _ => return (StatusCode::OK, "").into_response(),
             };
 
             let processed =
-                match db::try_mark_event_processed(&state.pool, &team_id, &event_id).await {
+                match db::try_mark_event_processed(&state.pool, &install_id, &event_id).await {
                     Ok(v) => v,
                     Err(err) => {
                         error!(error = %err, "failed to dedupe event");
@@ -724,8 +1254,8 @@ async fn slack_events(
                         .map(|s| s.trim())
                         .filter(|s| !s.is_empty())
                     {
-                        if want != team_id {
-                            warn!(want, got = %team_id, "ignoring slack event from unexpected workspace");
+                        if !slack_workspace_matches(want, &team_id, enterprise_id.as_deref()) {
+                            warn!(want, got = %team_id, enterprise_id = ?enterprise_id, "ignoring slack event from unexpected workspace");
                             if is_proactive {
                                 warn!(
                                     workspace_id = %team_id,
@@ -754,8 +1284,8 @@ async fn slack_events(
                             return (StatusCode::OK, "").into_response();
                         }
                     } else {
-                        // Best-effort: pin to the first workspace we see.
-                        let _ = db::set_workspace_id_if_missing(&state.pool, &team_id).await;
+                        // Best-effort: pin to the first workspace (or Grid org) we see.
+                        let _ = db::set_workspace_id_if_missing(&state.pool, &install_id).await;
                     }
 
                     // Optional allow-list (nanobot-style allowFrom).
@@ -805,7 +1335,7 @@ async fn slack_events(
                     // Optional channel allow-list (DMs always allowed).
                     if !is_dm {
                         let channels = parse_allow_from(&settings.slack_allow_channels);
-                        if !channels.is_empty() && !channels.contains(channel.as_str()) {
+                        if !slack_channel_allowed(&channels, &team_id, &channel) {
                             warn!(channel = %channel, "slack channel not in allow list; ignoring");
                             if is_proactive {
                                 warn!(
@@ -930,9 +1460,77 @@ async fn slack_events(
                 4_000,
             );
 
-            if allow_approval_commands {
+            // "after #N ..." makes this task wait for a predecessor.
+            let dependency = if allow_approval_commands {
+                parse_task_dependency(&prompt)
+            } else {
+                None
+            };
+            if let Some(dep) = &dependency {
+                if let Err(msg) =
+                    check_task_dependency(&state.pool, dep, "slack", &team_id, &channel).await
+                {
+                    if let Ok(Some(token)) = crate::secrets::load_slack_bot_token_opt(&state).await
+                    {
+                        let slack = SlackClient::new(state.http.clone(), token);
+                        let _ = slack
+                            .post_message(&channel, thread_opt(&thread_ts), msg.trim())
+                            .await;
+                    }
+                    return (StatusCode::OK, "").into_response();
+                }
+                prompt = dep.prompt.clone();
+            }
+
+            // Set when the message ran a saved prompt (`run <name>`).
+            let mut saved_prompt: Option<String> = None;
+            if allow_approval_commands && dependency.is_none() {
+                if let Some(cmd) = crate::elevation::parse_command(&prompt) {
+                    let response = match crate::elevation::handle_command(
+                        &state, cmd, "slack", &team_id, &channel, &user,
+                    )
+                    .await
+                    {
+                        Ok(msg) => msg,
+                        Err(err) => {
+                            warn!(error = %err, "failed to handle elevation command");
+                            "I couldn't change this channel's permissions right now.".to_string()
+                        }
+                    };
+                    if let Ok(Some(token)) = crate::secrets::load_slack_bot_token_opt(&state).await
+                    {
+                        let slack = SlackClient::new(state.http.clone(), token);
+                        let _ = slack
+                            .post_message(&channel, thread_opt(&thread_ts), response.trim())
+                            .await;
+                    }
+                    return (StatusCode::OK, "").into_response();
+                }
+
+                if let Some(cmd) = crate::digest::parse_command(&prompt) {
+                    let response = match crate::digest::handle_command(
+                        &state, cmd, "slack", &team_id, &channel, &user,
+                    )
+                    .await
+                    {
+                        Ok(msg) => msg,
+                        Err(err) => {
+                            warn!(error = %err, "failed to handle usage report command");
+                            "I couldn't prepare the usage report right now.".to_string()
+                        }
+                    };
+                    if let Ok(Some(token)) = crate::secrets::load_slack_bot_token_opt(&state).await
+                    {
+                        let slack = SlackClient::new(state.http.clone(), token);
+                        let _ = slack
+                            .post_message(&channel, thread_opt(&thread_ts), response.trim())
+                            .await;
+                    }
+                    return (StatusCode::OK, "").into_response();
+                }
+
                 if let Some(cmd) = parse_task_command(&prompt) {
-                    let response = match handle_task_command(&state, cmd).await {
+                    let response = match handle_task_command(&state, cmd, &user).await {
                         Ok(msg) => msg,
                         Err(err) => {
                             warn!(error = %err, "failed to handle task command");
@@ -969,7 +1567,45 @@ async fn slack_events(
                             warn!(error = %err, "failed to handle approval command");
                         }
                     }
-                    return (StatusCode::OK, "").into_response();
+                    return (StatusCode::OK, "").into_response();
+                }
+
+                if let Some(cmd) = crate::saved_prompts::parse_run_command(&prompt) {
+                    let slack = crate::secrets::load_slack_bot_token_opt(&state)
+                        .await
+                        .ok()
+                        .flatten()
+                        .map(|token| SlackClient::new(state.http.clone(), token));
+                    let slots = crate::saved_prompts::Slots {
+                        user: user.clone(),
+                        channel: channel.clone(),
+                        ..Default::default()
+                    };
+                    let thread = slack
+                        .as_ref()
+                        .map(|s| (s, channel.as_str(), thread_ts.as_str(), ts.as_str()));
+                    let reply =
+                        match crate::saved_prompts::resolve(&state, cmd, slots, thread).await {
+                            Ok(Resolved::Prompt { name, text }) => {
+                                saved_prompt = Some(name);
+                                prompt = text;
+                                None
+                            }
+                            Ok(Resolved::Reply(msg)) => Some(msg),
+                            Ok(Resolved::NotSaved) => None,
+                            Err(err) => {
+                                warn!(error = %err, "failed to expand saved prompt");
+                                Some("I couldn't load that saved prompt right now.".to_string())
+                            }
+                        };
+                    if let Some(msg) = reply {
+                        if let Some(slack) = &slack {
+                            let _ = slack
+                                .post_message(&channel, thread_opt(&thread_ts), msg.trim())
+                                .await;
+                        }
+                        return (StatusCode::OK, "").into_response();
+                    }
                 }
             }
 
@@ -984,6 +1620,20 @@ async fn slack_events(
                         let fname = f.name.as_deref().unwrap_or("unknown");
                         let mime = f.mimetype.as_deref().unwrap_or("application/octet-stream");
                         if let Some(url) = f.url_private_download.as_deref() {
+                            if crate::stt::is_audio(mime, f.filetype.as_deref()) {
+                                // Slack wants an answer within three seconds: the worker
+                                // downloads and transcribes audio (stt::transcribe_slack_audio).
+                                files_meta.push(serde_json::json!({
+                                    "id": f.id,
+                                    "name": fname,
+                                    "mimetype": mime,
+                                    "filetype": f.filetype,
+                                    "size": f.size,
+                                    "url_private_download": url,
+                                    "transcribe": true,
+                                }));
+                                continue;
+                            }
                             let dest = download_dir.join(fname);
                             match slack_dl.download_file(url, &dest).await {
                                 Ok(()) => {
@@ -1028,17 +1678,25 @@ async fn slack_events(
                 serde_json::to_string(&files_meta).unwrap_or_default()
             };
 
-            let _task_id = match db::enqueue_task_with_files(
+            let _task_id = match db::enqueue_task(
                 &state.pool,
-                "slack",
-                &team_id,
-                &channel,
-                &thread_ts,
-                &ts,
-                &user,
-                &prompt,
-                &files_json,
-                is_proactive,
+                &db::NewTask {
+                    provider: "slack",
+                    workspace_id: &team_id,
+                    channel_id: &channel,
+                    thread_ts: &thread_ts,
+                    event_ts: &ts,
+                    requested_by_user_id: &user,
+                    prompt_text: &prompt,
+                    files_json: &files_json,
+                    is_proactive,
+                    depends_on_task_id: dependency.as_ref().map(|d| d.task_id),
+                    on_dependency_failure: dependency
+                        .as_ref()
+                        .map(|d| d.on_failure)
+                        .unwrap_or("fail"),
+                    ..Default::default()
+                },
             )
             .await
             {
@@ -1049,6 +1707,10 @@ async fn slack_events(
                 }
             };
 
+            if let Some(name) = &saved_prompt {
+                record_saved_prompt_trace(&state, _task_id, name, &user).await;
+            }
+
             if is_proactive {
                 info!(
                     task_id = _task_id,
@@ -1063,7 +1725,10 @@ async fn slack_events(
 
             if !is_proactive {
                 let task_url = task_trace_url(&state, _task_id);
-                let task_msg = task_link_message(_task_id, &task_url);
+                let task_msg = match &dependency {
+                    Some(dep) => dependent_task_link_message(_task_id, dep, &task_url),
+                    None => task_link_message(_task_id, &task_url),
+                };
                 if let Ok(Some(token)) = crate::secrets::load_slack_bot_token_opt(&state).await {
                     let slack = SlackClient::new(state.http.clone(), token);
                     let _ = slack
@@ -1080,36 +1745,13 @@ async fn slack_events(
     }
 }
 
-async fn slack_actions(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    body: Bytes,
-) -> impl IntoResponse {
-    let secret = match crate::secrets::load_slack_signing_secret_opt(&state).await {
-        Ok(Some(v)) => v,
-        Ok(None) => {
-            let mut resp = (
-                StatusCode::SERVICE_UNAVAILABLE,
-                "slack not configured (missing SLACK_SIGNING_SECRET)",
-            )
-                .into_response();
-            resp.headers_mut().insert(
-                axum::http::header::HeaderName::from_static("x-slack-no-retry"),
-                HeaderValue::from_static("1"),
-            );
-            return resp;
-        }
-        Err(err) => {
-            warn!(error = %err, "failed to load slack signing secret");
-            return (StatusCode::INTERNAL_SERVER_ERROR, "internal error").into_response();
-        }
-    };
-
-    if let Err(err) = verify_slack_signature(&secret, &headers, &body) {
-        warn!(error = %err, "invalid slack signature (actions)");
-        return (StatusCode::UNAUTHORIZED, "invalid signature").into_response();
-    }
+/// Slash commands (`/grail`). Only opens the task modal; the task is enqueued on submit.
+async fn slack_commands(State(state): State<AppState>, body: Bytes) -> impl IntoResponse {
+    let form = parse_urlencoded_form(&body);
+    crate::slack_modals::handle_slash_command(&state, &form).await
+}
 
+async fn slack_actions(State(state): State<AppState>, body: Bytes) -> impl IntoResponse {
     #[derive(Debug, Deserialize)]
     struct SlackActionUser {
         id: String,
@@ -1117,6 +1759,12 @@ async fn slack_actions(
     #[derive(Debug, Deserialize)]
     struct SlackActionTeam {
         id: String,
+        #[serde(default)]
+        enterprise_id: Option<String>,
+    }
+    #[derive(Debug, Deserialize)]
+    struct SlackActionEnterprise {
+        id: String,
     }
     #[derive(Debug, Deserialize)]
     struct SlackActionChannel {
@@ -1141,6 +1789,8 @@ async fn slack_actions(
         user: SlackActionUser,
         #[serde(default)]
         team: Option<SlackActionTeam>,
+        #[serde(default)]
+        enterprise: Option<SlackActionEnterprise>,
         channel: SlackActionChannel,
         message: SlackActionMessage,
         actions: Vec<SlackAction>,
@@ -1151,57 +1801,82 @@ async fn slack_actions(
         return (StatusCode::BAD_REQUEST, "missing payload").into_response();
     };
 
-    let payload: SlackActionPayload = match serde_json::from_str(payload_raw) {
+    let payload: serde_json::Value = match serde_json::from_str(payload_raw) {
         Ok(v) => v,
         Err(err) => {
             warn!(error = %err, "invalid slack actions payload json");
             return (StatusCode::BAD_REQUEST, "invalid payload").into_response();
         }
     };
-
-    if payload.kind != "block_actions" {
-        return (StatusCode::OK, "").into_response();
-    }
-
-    // Enforce single-workspace per deployment (best-effort).
-    if let Ok(settings) = db::get_settings(&state.pool).await {
-        if let (Some(want), Some(team)) = (
-            settings
-                .workspace_id
-                .as_deref()
-                .map(|s| s.trim())
-                .filter(|s| !s.is_empty()),
-            payload.team.as_ref().map(|t| t.id.as_str()),
-        ) {
-            if want != team {
-                warn!(
-                    want,
-                    got = team,
-                    "ignoring slack action from unexpected workspace"
-                );
+    match payload.get("type").and_then(|v| v.as_str()).unwrap_or("") {
+        "message_action" => {
+            return crate::slack_modals::handle_message_shortcut(&state, payload).await;
+        }
+        "view_submission" => {
+            return crate::slack_modals::handle_view_submission(&state, payload).await;
+        }
+        "block_actions"
+            if payload.pointer("/view/type").and_then(|v| v.as_str()) == Some("home") =>
+        {
+            let team_id = payload
+                .pointer("/team/id")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let enterprise_id = payload
+                .pointer("/enterprise/id")
+                .or_else(|| payload.pointer("/team/enterprise_id"))
+                .and_then(|v| v.as_str())
+                .filter(|e| !e.trim().is_empty());
+            let user_id = payload
+                .pointer("/user/id")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let dm_channel = payload
+                .pointer("/view/private_metadata")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            if !slack_interaction_allowed(&state, team_id, enterprise_id, user_id, dm_channel).await
+            {
                 return (StatusCode::OK, "").into_response();
             }
+            return crate::slack_home::handle_block_action(&state, payload).await;
         }
-
-        // Optional allow-list.
-        let allowed = parse_allow_from(&settings.slack_allow_from);
-        if !allowed.is_empty() && !allowed.contains(payload.user.id.as_str()) {
-            warn!(user = %payload.user.id, "slack user not in allow list; ignoring action");
-            return (StatusCode::OK, "").into_response();
+        _ => {}
+    }
+    let payload: SlackActionPayload = match serde_json::from_value(payload) {
+        Ok(v) => v,
+        Err(err) => {
+            warn!(error = %err, "invalid slack actions payload json");
+            return (StatusCode::BAD_REQUEST, "invalid payload").into_response();
         }
+    };
 
-        // Optional channel allow-list (DMs always allowed).
-        let channels = parse_allow_from(&settings.slack_allow_channels);
-        if !channels.is_empty()
-            && !payload.channel.id.starts_with('D')
-            && !channels.contains(payload.channel.id.as_str())
-        {
-            warn!(
-                channel = %payload.channel.id,
-                "slack channel not in allow list; ignoring action"
-            );
-            return (StatusCode::OK, "").into_response();
-        }
+    if payload.kind != "block_actions" {
+        return (StatusCode::OK, "").into_response();
+    }
+
+    let team_id = payload
+        .team
+        .as_ref()
+        .map(|t| t.id.clone())
+        .unwrap_or_default();
+    let enterprise_id = payload
+        .enterprise
+        .as_ref()
+        .map(|e| e.id.clone())
+        .or_else(|| payload.team.as_ref().and_then(|t| t.enterprise_id.clone()))
+        .filter(|e| !e.trim().is_empty());
+
+    if !slack_interaction_allowed(
+        &state,
+        &team_id,
+        enterprise_id.as_deref(),
+        &payload.user.id,
+        &payload.channel.id,
+    )
+    .await
+    {
+        return (StatusCode::OK, "").into_response();
     }
 
     let Some(action) = payload.actions.get(0) else {
@@ -1212,9 +1887,30 @@ async fn slack_actions(
         return (StatusCode::OK, "").into_response();
     }
 
+    if action.action_id == "grail_regenerate" {
+        let Ok(task_id) = action.value.as_deref().unwrap_or("").trim().parse::<i64>() else {
+            return (StatusCode::OK, "").into_response();
+        };
+        match db::enqueue_regenerate_task(&state.pool, task_id, &payload.user.id).await {
+            Ok(Some(new_id)) => {
+                info!(
+                    task_id,
+                    new_task_id = new_id,
+                    "queued regenerate from cached answer"
+                );
+                state.task_notify.notify_waiters();
+            }
+            Ok(None) => warn!(task_id, "regenerate requested for unknown task"),
+            Err(err) => warn!(error = %err, task_id, "failed to queue regenerate task"),
+        }
+        return (StatusCode::OK, "").into_response();
+    }
+
     let action_str = match action.action_id.as_str() {
         "grail_approve" => "approve",
         "grail_always" => "always",
+        "grail_always_program" => "always_program",
+        "grail_always_directory" => "always_directory",
         "grail_deny" => "deny",
         other => {
             warn!(action_id = other, "unknown slack action_id");
@@ -1300,7 +1996,14 @@ async fn telegram_webhook(
     let Some(msg) = update.message.clone().or(update.edited_message.clone()) else {
         return (StatusCode::OK, "").into_response();
     };
-    let Some(text) = msg.text.clone() else {
+    let voice = msg.voice.clone().or_else(|| msg.audio.clone());
+    // Voice messages may come without a caption; the transcript becomes the prompt.
+    let Some(text) = msg
+        .text
+        .clone()
+        .or_else(|| msg.caption.clone())
+        .or_else(|| voice.as_ref().map(|_| String::new()))
+    else {
         return (StatusCode::OK, "").into_response();
     };
     let from_user_id = msg
@@ -1411,13 +2114,79 @@ async fn telegram_webhook(
         return (StatusCode::OK, "").into_response();
     }
 
+    let mut cleaned = cleaned;
+    if let Some(voice) = &voice {
+        let tg = crate::telegram::TelegramClient::new(state.http.clone(), token.clone());
+        let transcript = match tg
+            .download_file(&voice.file_id, crate::stt::MAX_AUDIO_BYTES)
+            .await
+        {
+            Ok(bytes) => {
+                let audio = crate::stt::Audio {
+                    filename: voice
+                        .file_name
+                        .clone()
+                        .unwrap_or_else(|| "voice.ogg".to_string()),
+                    mime: voice
+                        .mime_type
+                        .clone()
+                        .unwrap_or_else(|| "audio/ogg".to_string()),
+                    bytes,
+                };
+                let name = voice
+                    .file_name
+                    .clone()
+                    .unwrap_or_else(|| format!("({}s)", voice.duration));
+                crate::stt::transcribe_for_prompt(&state, &name, audio).await
+            }
+            Err(err) => {
+                warn!(error = %err, "failed to download telegram voice message");
+                Err("I couldn't download that voice message.".to_string())
+            }
+        };
+        match transcript {
+            Ok(note) => {
+                if let Err(err) = db::update_telegram_message_text(
+                    &state.pool,
+                    &stored.chat_id,
+                    msg.message_id,
+                    &clamp_chars(format!("{text}\n{note}").trim().to_string(), 8_000),
+                )
+                .await
+                {
+                    warn!(error = %err, "failed to store telegram voice transcript");
+                }
+                cleaned = format!("{cleaned}\n\n{note}").trim().to_string();
+            }
+            Err(reply) => {
+                let _ = tg
+                    .send_message(&stored.chat_id, Some(msg.message_id), &reply)
+                    .await;
+                return (StatusCode::OK, "").into_response();
+            }
+        }
+    }
+
     let prompt = clamp_chars(cleaned, 4_000);
     if prompt.is_empty() {
         return (StatusCode::OK, "").into_response();
     }
 
-    if let Some(cmd) = parse_task_command(&prompt) {
-        let response = match handle_task_command(&state, cmd).await {
+    let dependency = parse_task_dependency(&prompt);
+    if let Some(dep) = &dependency {
+        if let Err(msg_text) =
+            check_task_dependency(&state.pool, dep, "telegram", "telegram", &stored.chat_id).await
+        {
+            let tg = crate::telegram::TelegramClient::new(state.http.clone(), token);
+            let _ = tg
+                .send_message(&stored.chat_id, Some(msg.message_id), msg_text.trim())
+                .await;
+            return (StatusCode::OK, "").into_response();
+        }
+    }
+
+    if let Some(cmd) = parse_task_command(&prompt).filter(|_| dependency.is_none()) {
+        let response = match handle_task_command(&state, cmd, &from_user_id).await {
             Ok(msg) => msg,
             Err(err) => {
                 warn!(error = %err, "failed to handle telegram task command");
@@ -1432,15 +2201,103 @@ async fn telegram_webhook(
         return (StatusCode::OK, "").into_response();
     }
 
+    if let Some(cmd) = crate::elevation::parse_command(&prompt).filter(|_| dependency.is_none()) {
+        let response = match crate::elevation::handle_command(
+            &state,
+            cmd,
+            "telegram",
+            "telegram",
+            &stored.chat_id,
+            &from_user_id,
+        )
+        .await
+        {
+            Ok(msg) => msg,
+            Err(err) => {
+                warn!(error = %err, "failed to handle telegram elevation command");
+                "I couldn't change this chat's permissions right now.".to_string()
+            }
+        };
+        let tg = crate::telegram::TelegramClient::new(state.http.clone(), token.clone());
+        let _ = tg
+            .send_message(&stored.chat_id, Some(msg.message_id), response.trim())
+            .await;
+        return (StatusCode::OK, "").into_response();
+    }
+
+    if let Some(cmd) = crate::digest::parse_command(&prompt).filter(|_| dependency.is_none()) {
+        let response = match crate::digest::handle_command(
+            &state,
+            cmd,
+            "telegram",
+            "telegram",
+            &stored.chat_id,
+            &from_user_id,
+        )
+        .await
+        {
+            Ok(msg) => msg,
+            Err(err) => {
+                warn!(error = %err, "failed to handle telegram usage report command");
+                "I couldn't prepare the usage report right now.".to_string()
+            }
+        };
+        let tg = crate::telegram::TelegramClient::new(state.http.clone(), token.clone());
+        let _ = tg
+            .send_message(&stored.chat_id, Some(msg.message_id), response.trim())
+            .await;
+        return (StatusCode::OK, "").into_response();
+    }
+
+    let mut prompt = prompt;
+    let mut saved_prompt: Option<String> = None;
+    if let Some(cmd) =
+        crate::saved_prompts::parse_run_command(&prompt).filter(|_| dependency.is_none())
+    {
+        let slots = crate::saved_prompts::Slots {
+            user: from_user_id.clone(),
+            channel: stored.chat_id.clone(),
+            ..Default::default()
+        };
+        let reply = match crate::saved_prompts::resolve(&state, cmd, slots, None).await {
+            Ok(Resolved::Prompt { name, text }) => {
+                saved_prompt = Some(name);
+                prompt = text;
+                None
+            }
+            Ok(Resolved::Reply(msg)) => Some(msg),
+            Ok(Resolved::NotSaved) => None,
+            Err(err) => {
+                warn!(error = %err, "failed to expand telegram saved prompt");
+                Some("I couldn't load that saved prompt right now.".to_string())
+            }
+        };
+        if let Some(reply) = reply {
+            let tg = crate::telegram::TelegramClient::new(state.http.clone(), token.clone());
+            let _ = tg
+                .send_message(&stored.chat_id, Some(msg.message_id), reply.trim())
+                .await;
+            return (StatusCode::OK, "").into_response();
+        }
+    }
+
     let _task_id = match db::enqueue_task(
         &state.pool,
-        "telegram",
-        "telegram",
-        &stored.chat_id,
-        &msg.message_id.to_string(),
-        &msg.message_id.to_string(),
-        &from_user_id,
-        &prompt,
+        &db::NewTask {
+            provider: "telegram",
+            workspace_id: "telegram",
+            channel_id: &stored.chat_id,
+            thread_ts: &msg.message_id.to_string(),
+            event_ts: &msg.message_id.to_string(),
+            requested_by_user_id: &from_user_id,
+            prompt_text: dependency
+                .as_ref()
+                .map(|d| d.prompt.as_str())
+                .unwrap_or(&prompt),
+            depends_on_task_id: dependency.as_ref().map(|d| d.task_id),
+            on_dependency_failure: dependency.as_ref().map(|d| d.on_failure).unwrap_or("fail"),
+            ..Default::default()
+        },
     )
     .await
     {
@@ -1450,9 +2307,15 @@ async fn telegram_webhook(
             return (StatusCode::INTERNAL_SERVER_ERROR, "db error").into_response();
         }
     };
+    if let Some(name) = &saved_prompt {
+        record_saved_prompt_trace(&state, _task_id, name, &from_user_id).await;
+    }
 
     let task_url = task_trace_url(&state, _task_id);
-    let task_msg = task_link_message(_task_id, &task_url);
+    let task_msg = match &dependency {
+        Some(dep) => dependent_task_link_message(_task_id, dep, &task_url),
+        None => task_link_message(_task_id, &task_url),
+    };
     let tg = crate::telegram::TelegramClient::new(state.http.clone(), token);
     let _ = tg
         .send_message(&stored.chat_id, Some(msg.message_id), task_msg.as_str())
@@ -1537,6 +2400,77 @@ fn parse_allow_from(input: &str) -> std::collections::HashSet<String> {
         .collect()
 }
 
+/// Whether a pinned `settings.workspace_id` admits an event. The pin may be a workspace
+/// (`T…`) or, for Enterprise Grid, the whole org (`E…`).
+fn slack_workspace_matches(want: &str, team_id: &str, enterprise_id: Option<&str>) -> bool {
+    want == team_id || enterprise_id == Some(want)
+}
+
+/// Channel allow-list check. Entries are channel ids, or workspace-qualified
+/// `T…:C…` / `T…:*` entries for Enterprise Grid deployments spanning several workspaces.
+fn slack_channel_allowed(
+    allowed: &std::collections::HashSet<String>,
+    team_id: &str,
+    channel: &str,
+) -> bool {
+    allowed.is_empty()
+        || allowed.contains(channel)
+        || (!team_id.is_empty()
+            && (allowed.contains(&format!("{team_id}:{channel}"))
+                || allowed.contains(&format!("{team_id}:*"))))
+}
+
+/// Workspace pin plus user / channel allow-lists for interactive payloads
+/// (buttons, shortcuts, modals, slash commands).
+async fn slack_interaction_allowed(
+    state: &AppState,
+    team_id: &str,
+    enterprise_id: Option<&str>,
+    user_id: &str,
+    channel_id: &str,
+) -> bool {
+    let Ok(settings) = db::get_settings(&state.pool).await else {
+        return true;
+    };
+    // Enforce single-workspace per deployment (best-effort).
+    if let Some(want) = settings
+        .workspace_id
+        .as_deref()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+    {
+        if (!team_id.is_empty() || enterprise_id.is_some())
+            && !slack_workspace_matches(want, team_id, enterprise_id)
+        {
+            warn!(
+                want,
+                got = %team_id,
+                enterprise_id = ?enterprise_id,
+                "ignoring slack interaction from unexpected workspace"
+            );
+            return false;
+        }
+    }
+
+    // Optional allow-list.
+    let allowed = parse_allow_from(&settings.slack_allow_from);
+    if !allowed.is_empty() && !allowed.contains(user_id) {
+        warn!(user = %user_id, "slack user not in allow list; ignoring interaction");
+        return false;
+    }
+
+    // Optional channel allow-list (DMs always allowed).
+    let channels = parse_allow_from(&settings.slack_allow_channels);
+    if !channel_id.starts_with('D') && !slack_channel_allowed(&channels, team_id, channel_id) {
+        warn!(
+            channel = %channel_id,
+            "slack channel not in allow list; ignoring interaction"
+        );
+        return false;
+    }
+    true
+}
+
 fn parse_urlencoded_form(body: &Bytes) -> HashMap<String, String> {
     fn decode(s: &str) -> String {
         // application/x-www-form-urlencoded uses '+' for space.
@@ -1570,6 +2504,24 @@ enum TaskCommand {
     Show { task_id: i64 },
     Cancel { task_id: i64 },
     Retry { task_id: i64 },
+    Bump { task_id: i64 },
+}
+
+/// Audit which saved prompt a task was expanded from.
+async fn record_saved_prompt_trace(state: &AppState, task_id: i64, name: &str, actor: &str) {
+    let details = serde_json::json!({ "name": name, "actor": actor });
+    if let Err(err) = db::create_task_trace(
+        &state.pool,
+        task_id,
+        "task.saved_prompt",
+        "info",
+        &format!("Expanded saved prompt `{name}` for {actor}"),
+        &details.to_string(),
+    )
+    .await
+    {
+        warn!(error = %err, task_id, "failed to record saved prompt trace");
+    }
 }
 
 fn parse_task_command(text: &str) -> Option<TaskCommand> {
@@ -1599,6 +2551,19 @@ fn parse_task_command(text: &str) -> Option<TaskCommand> {
         return Some(TaskCommand::ListRunning);
     }
 
+    static TASK_BUMP_RE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"(?i)^(?:bump|prioriti[sz]e|expedite)\s+(?:task\s*)?#?\s*(\d+)$")
+            .expect("task command bump regex must compile")
+    });
+    if let Some(task_id) = TASK_BUMP_RE
+        .captures(&t)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| i64::from_str(m.as_str()).ok())
+        .filter(|id| *id > 0)
+    {
+        return Some(TaskCommand::Bump { task_id });
+    }
+
     static TASK_ID_RE: Lazy<Regex> = Lazy::new(|| {
         Regex::new(r"(?i)\btask(?:\s+id)?\s*#?\s*(\d+)\b")
             .expect("task command task id regex must compile")
@@ -1629,6 +2594,75 @@ fn parse_task_command(text: &str) -> Option<TaskCommand> {
     Some(TaskCommand::Show { task_id })
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TaskDependency {
+    task_id: i64,
+    on_failure: &'static str,
+    prompt: String,
+}
+
+/// Parse the "after #N <prompt>" chain syntax.
+///
+/// `after #N regardless <prompt>` (or "even if it fails") runs the task once #N finishes,
+/// whatever its outcome; otherwise a failed predecessor fails this task too.
+fn parse_task_dependency(text: &str) -> Option<TaskDependency> {
+    static AFTER_RE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(
+            r"(?is)^\s*after\s+(?:task\s*)?#(\d+)(?:\s+(regardless|even\s+if\s+it\s+fails))?\s*[,:;]?\s+(\S.*)$",
+        )
+        .expect("task dependency regex must compile")
+    });
+
+    let caps = AFTER_RE.captures(text)?;
+    let task_id = caps
+        .get(1)
+        .and_then(|m| i64::from_str(m.as_str()).ok())
+        .filter(|id| *id > 0)?;
+    let on_failure = if caps.get(2).is_some() { "run" } else { "fail" };
+    let prompt = caps.get(3)?.as_str().trim().to_string();
+    Some(TaskDependency {
+        task_id,
+        on_failure,
+        prompt,
+    })
+}
+
+/// Make sure a dependency points at a task in the same channel that can still satisfy it:
+/// the predecessor's prompt and result are shared with the new task, so tasks from other
+/// channels or providers are treated as not found. A predecessor that already failed or
+/// was cancelled is only accepted with `regardless`. Returns a user-facing message on
+/// failure.
+async fn check_task_dependency(
+    pool: &SqlitePool,
+    dep: &TaskDependency,
+    provider: &str,
+    workspace_id: &str,
+    channel_id: &str,
+) -> Result<(), String> {
+    let task = match db::get_task(pool, dep.task_id).await {
+        Ok(task) => task.filter(|t| {
+            t.provider == provider && t.workspace_id == workspace_id && t.channel_id == channel_id
+        }),
+        Err(err) => {
+            warn!(error = %err, "failed to look up task dependency");
+            return Err("I couldn't check that task dependency right now.".to_string());
+        }
+    };
+    let Some(task) = task else {
+        return Err(format!(
+            "Task #{} was not found in this channel, so nothing was queued.",
+            dep.task_id
+        ));
+    };
+    if dep.on_failure != "run" && matches!(task.status.as_str(), "failed" | "cancelled") {
+        return Err(format!(
+            "Task #{} already {}, so nothing was queued. Say `after #{} regardless ...` to run it anyway.",
+            dep.task_id, task.status, dep.task_id
+        ));
+    }
+    Ok(())
+}
+
 fn format_unix_ts(ts: i64) -> String {
     match chrono::DateTime::<chrono::Utc>::from_timestamp(ts, 0) {
         Some(dt) => dt.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
@@ -1640,18 +2674,6 @@ fn format_unix_ts_opt(ts: Option<i64>) -> String {
     ts.map(format_unix_ts).unwrap_or_else(|| "n/a".to_string())
 }
 
-fn truncate_preview(text: &str, max_chars: usize) -> String {
-    let trimmed = text.trim();
-    if trimmed.is_empty() {
-        return String::new();
-    }
-    let mut out: String = trimmed.chars().take(max_chars).collect();
-    if trimmed.chars().count() > max_chars {
-        out.push('…');
-    }
-    out.replace('\n', " ")
-}
-
 fn redact_user_message(text: &str) -> String {
     let (redacted, was_redacted) = crate::secrets::redact_secrets(text);
     if was_redacted {
@@ -1660,7 +2682,12 @@ fn redact_user_message(text: &str) -> String {
     redacted
 }
 
-async fn handle_task_command(state: &AppState, cmd: TaskCommand) -> anyhow::Result<String> {
+/// `actor` is the chat user id that sent the command.
+async fn handle_task_command(
+    state: &AppState,
+    cmd: TaskCommand,
+    actor: &str,
+) -> anyhow::Result<String> {
     match cmd {
         TaskCommand::ListRunning => {
             let active = db::list_active_tasks(&state.pool, 20).await?;
@@ -1714,13 +2741,13 @@ async fn handle_task_command(state: &AppState, cmd: TaskCommand) -> anyhow::Resu
                 task_trace_url(state, task.id),
             );
             if let Some(err) = task.error_text.as_deref() {
-                let preview = truncate_preview(err, 240);
+                let preview = grail_text::truncate_line(err, 240);
                 if !preview.is_empty() {
                     msg.push_str(&format!("\nError: {preview}"));
                 }
             }
             if let Some(result) = task.result_text.as_deref() {
-                let preview = truncate_preview(result, 240);
+                let preview = grail_text::truncate_line(result, 240);
                 if !preview.is_empty() {
                     msg.push_str(&format!("\nResult preview: {preview}"));
                 }
@@ -1786,6 +2813,61 @@ async fn handle_task_command(state: &AppState, cmd: TaskCommand) -> anyhow::Resu
                 "Task #{task_id} could not be retried because its status is now `{current}`."
             ))
         }
+        TaskCommand::Bump { task_id } => {
+            let settings = db::get_settings(&state.pool).await?;
+            if !parse_allow_from(&settings.task_bump_allow_from).contains(actor) {
+                return Ok(
+                    "Only users listed under Task Bump Allowed Users in settings can bump tasks."
+                        .to_string(),
+                );
+            }
+            let Some(task) = db::get_task(&state.pool, task_id).await? else {
+                return Ok(format!("Task #{task_id} was not found."));
+            };
+            if task.status != "queued" {
+                return Ok(format!(
+                    "Task #{task_id} is `{}`; only `queued` tasks can be bumped.",
+                    task.status
+                ));
+            }
+            let before = db::queued_task_position(&state.pool, task_id).await?;
+            let Some(priority) = db::bump_task_priority(&state.pool, task_id).await? else {
+                let current = db::get_task_status(&state.pool, task_id)
+                    .await?
+                    .unwrap_or_else(|| "missing".to_string());
+                return Ok(format!(
+                    "Task #{task_id} could not be bumped because its status is now `{current}`."
+                ));
+            };
+            let after = db::queued_task_position(&state.pool, task_id).await?;
+            let details = serde_json::json!({
+                "actor": actor,
+                "priority": priority,
+                "position_before": before,
+                "position_after": after,
+            });
+            db::create_task_trace(
+                &state.pool,
+                task_id,
+                "task.bumped",
+                "info",
+                &format!("Priority raised to {priority} by {actor}"),
+                &details.to_string(),
+            )
+            .await?;
+            info!(task_id, actor, priority, "task bumped");
+            state.task_notify.notify_waiters();
+            Ok(format!(
+                "Task #{task_id} bumped to the front of the queue (position {} -> {}).\nLink: {}",
+                before
+                    .map(|p| p.to_string())
+                    .unwrap_or_else(|| "?".to_string()),
+                after
+                    .map(|p| p.to_string())
+                    .unwrap_or_else(|| "?".to_string()),
+                task_trace_url(state, task_id),
+            ))
+        }
     }
 }
 
@@ -1803,6 +2885,8 @@ fn parse_approval_command(text: &str) -> Option<(&'static str, String)> {
     match cmd.as_str() {
         "approve" => Some(("approve", id.to_string())),
         "always" => Some(("always", id.to_string())),
+        "always-program" => Some(("always_program", id.to_string())),
+        "always-directory" => Some(("always_directory", id.to_string())),
         "deny" => Some(("deny", id.to_string())),
         "cancel" => Some(("cancel", id.to_string())),
         _ => None,
@@ -2203,6 +3287,14 @@ enum SlackEnvelope {
     EventCallback {
         #[serde(rename = "team_id")]
         team_id: String,
+        /// Set for Enterprise Grid orgs (both org-wide and workspace-level installs).
+        #[serde(default)]
+        enterprise_id: Option<String>,
+        #[serde(default)]
+        is_enterprise_install: bool,
+        /// Workspace the event happened in; differs from `team_id` for org-wide installs.
+        #[serde(default)]
+        context_team_id: Option<String>,
         #[serde(rename = "event_id")]
         event_id: String,
         event: SlackEvent,
@@ -2244,6 +3336,36 @@ enum SlackEvent {
         files: Vec<crate::slack::SlackFile>,
     },
 
+    #[serde(rename = "app_home_opened")]
+    AppHomeOpened {
+        user: String,
+        /// The user's DM with the app.
+        channel: String,
+        #[serde(default)]
+        tab: String,
+    },
+
+    /// Public channel archived (`group_archive` for private channels).
+    #[serde(rename = "channel_archive")]
+    ChannelArchive {
+        channel: String,
+        #[serde(default)]
+        user: Option<String>,
+    },
+
+    #[serde(rename = "group_archive")]
+    GroupArchive { channel: String },
+
+    #[serde(rename = "channel_deleted")]
+    ChannelDeleted { channel: String },
+
+    #[serde(rename = "group_deleted")]
+    GroupDeleted { channel: String },
+
+    /// Someone left or was removed from a channel; only matters when it's the bot.
+    #[serde(rename = "member_left_channel")]
+    MemberLeftChannel { user: String, channel: String },
+
     #[serde(other)]
     Other,
 }
@@ -2382,7 +3504,7 @@ async fn whatsapp_webhook(
                 }
 
                 if let Some(cmd) = parse_task_command(&prompt) {
-                    let response = match handle_task_command(&state, cmd).await {
+                    let response = match handle_task_command(&state, cmd, from).await {
                         Ok(msg) => msg,
                         Err(err) => {
                             warn!(error = %err, "failed to handle whatsapp task command");
@@ -2458,13 +3580,16 @@ async fn whatsapp_webhook(
                 // channel_id = sender phone number (used to reply back).
                 if let Err(err) = db::enqueue_task(
                     &state.pool,
-                    "whatsapp",
-                    wid,
-                    from,
-                    &msg.id,
-                    &msg.id,
-                    from,
-                    &prompt,
+                    &db::NewTask {
+                        provider: "whatsapp",
+                        workspace_id: wid,
+                        channel_id: from,
+                        thread_ts: &msg.id,
+                        event_ts: &msg.id,
+                        requested_by_user_id: from,
+                        prompt_text: &prompt,
+                        ..Default::default()
+                    },
                 )
                 .await
                 {
@@ -2603,7 +3728,7 @@ async fn discord_webhook(
         }
 
         if let Some(cmd) = parse_task_command(&prompt) {
-            let response = match handle_task_command(&state, cmd).await {
+            let response = match handle_task_command(&state, cmd, user_id).await {
                 Ok(msg) => msg,
                 Err(err) => {
                     warn!(error = %err, "failed to handle discord task command");
@@ -2621,13 +3746,16 @@ async fn discord_webhook(
         if !prompt.is_empty() {
             if let Err(err) = db::enqueue_task(
                 &state.pool,
-                "discord",
-                "discord",
-                channel_id,
-                interaction_id,
-                interaction_id,
-                user_id,
-                &prompt,
+                &db::NewTask {
+                    provider: "discord",
+                    workspace_id: "discord",
+                    channel_id,
+                    thread_ts: interaction_id,
+                    event_ts: interaction_id,
+                    requested_by_user_id: user_id,
+                    prompt_text: &prompt,
+                    ..Default::default()
+                },
             )
             .await
             {
@@ -2755,7 +3883,7 @@ async fn msteams_webhook(
     }
 
     if let Some(cmd) = parse_task_command(&prompt) {
-        let response = match handle_task_command(&state, cmd).await {
+        let response = match handle_task_command(&state, cmd, from_id).await {
             Ok(msg) => msg,
             Err(err) => {
                 warn!(error = %err, "failed to handle teams task command");
@@ -2814,13 +3942,16 @@ async fn msteams_webhook(
 
     if let Err(err) = db::enqueue_task(
         &state.pool,
-        "msteams",
-        "msteams",
-        conversation_id,
-        &thread_ts,
-        &thread_ts,
-        from_id,
-        &prompt,
+        &db::NewTask {
+            provider: "msteams",
+            workspace_id: "msteams",
+            channel_id: conversation_id,
+            thread_ts: &thread_ts,
+            event_ts: &thread_ts,
+            requested_by_user_id: from_id,
+            prompt_text: &prompt,
+            ..Default::default()
+        },
     )
     .await
     {
@@ -59,9 +59,41 @@ pub struct Settings {
     pub web_allow_domains: String,
     pub web_deny_domains: String,
     pub github_client_id: String,
+    pub context_sources: String,
+    pub llm_backends: String,
+    pub response_cache_mode: String,
+    pub response_cache_ttl_seconds: i64,
+    pub command_env: String,
+    pub slack_long_reply_mode: String,
+    pub slack_long_reply_chars: i64,
+    pub reply_language: String,
+    pub task_bump_allow_from: String,
+    pub public_status_page: bool,
+    pub slack_no_retry_after_enqueue: bool,
+    pub context_token_budget: String,
+    pub content_policy_moderation: bool,
+    pub content_policy_message: String,
+    pub approval_output_chars: i64,
+    pub scale_queue_depth_threshold: i64,
+    pub scale_wait_seconds_threshold: i64,
+    pub scale_webhook_url: String,
+    pub reply_task_links: bool,
+    pub usage_report_minutes_per_task: i64,
+    pub speech_to_text: String,
+    pub slack_admin_channel: String,
+    pub slack_elevate_allow_from: String,
+    pub telegram_elevate_allow_from: String,
     pub updated_at: i64,
 }
 
+/// A worker's hold on a running task. The leader re-queues tasks whose worker stops
+/// heartbeating; the generation tells a late worker its claim is gone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskClaim {
+    pub worker_id: String,
+    pub generation: i64,
+}
+
 #[derive(Debug, Clone)]
 pub struct Task {
     pub id: i64,
@@ -81,6 +113,110 @@ pub struct Task {
     pub created_at: i64,
     pub started_at: Option<i64>,
     pub finished_at: Option<i64>,
+    pub depends_on_task_id: Option<i64>,
+    pub on_dependency_failure: String,
+    /// Admin test run: executes normally but never posts to the originating channel.
+    pub is_synthetic: bool,
+    /// Re-run requested via "Regenerate": never answer from the response cache.
+    pub skip_response_cache: bool,
+    /// JSON-encoded [`TaskOptions`]; empty when the task was not submitted via the modal.
+    pub options_json: String,
+    /// Set on copies made by an admin bulk re-run.
+    pub rerun_of_task_id: Option<i64>,
+}
+
+/// Most channels a task can attach as extra context.
+pub const MAX_CONTEXT_CHANNELS: usize = 5;
+
+/// Per-task options, picked in the Slack task modal or passed to the task API. The worker
+/// re-checks each one against policy.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Can only narrow the workspace setting, never widen it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions_mode: Option<PermissionsMode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_channels: Vec<String>,
+    /// Unix seconds; the run is cancelled when it is still going at this time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_at: Option<i64>,
+    /// Seconds the run may take, counted from when a worker claims it, so time spent
+    /// queued doesn't eat into it. Applies together with `deadline_at`; the earlier wins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<i64>,
+    /// JSON Schema the answer must conform to (API-created tasks, see `output_contract`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+}
+
+impl TaskOptions {
+    pub fn parse(raw: &str) -> Self {
+        if raw.trim().is_empty() {
+            return Self::default();
+        }
+        serde_json::from_str(raw).unwrap_or_default()
+    }
+
+    /// When a run that started at `started_at` must stop, if ever.
+    pub fn deadline(&self, started_at: Option<i64>) -> Option<i64> {
+        let timeout = self.timeout_seconds.zip(started_at).map(|(t, s)| s + t);
+        match (self.deadline_at, timeout) {
+            (Some(at), Some(timeout)) => Some(at.min(timeout)),
+            (at, timeout) => at.or(timeout),
+        }
+    }
+
+    /// Options for an admin re-run of a task that had these options. `model` replaces the
+    /// model, `read_only` narrows permissions, and `timeout_seconds` replaces the deadline
+    /// with a timeout that starts when the copy is claimed. Without a timeout the old
+    /// deadline is kept only while it is still in the future.
+    pub fn for_rerun(
+        &self,
+        model: Option<&str>,
+        read_only: bool,
+        timeout_seconds: Option<i64>,
+        now: i64,
+    ) -> Self {
+        let mut out = self.clone();
+        if let Some(model) = model.map(str::trim).filter(|m| !m.is_empty()) {
+            out.model = Some(model.to_string());
+        }
+        if read_only {
+            out.permissions_mode = Some(PermissionsMode::Read);
+        }
+        if let Some(t) = timeout_seconds {
+            out.timeout_seconds = Some(t);
+            out.deadline_at = None;
+        } else {
+            out.deadline_at = self.deadline_at.filter(|d| *d > now);
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResponseCacheEntry {
+    pub workspace_id: String,
+    pub channel_id: String,
+    pub prompt_hash: String,
+    pub prompt_text: String,
+    pub embedding_json: Option<String>,
+    pub task_id: i64,
+    pub response_text: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkerHeartbeat {
+    pub worker_id: String,
+    pub hostname: String,
+    pub pid: i64,
+    pub is_leader: bool,
+    pub active_tasks: i64,
+    pub started_at: i64,
+    pub last_seen_at: i64,
 }
 
 #[derive(Debug, Clone)]
@@ -151,6 +287,8 @@ pub struct CronJob {
     pub channel_id: String,
     pub thread_ts: String,
     pub prompt_text: String,
+    /// Slack user who asked for the job; `None` for jobs created by admins or imports.
+    pub created_by_user_id: Option<String>,
     pub next_run_at: Option<i64>,
     pub last_run_at: Option<i64>,
     pub last_status: Option<String>,
@@ -173,6 +311,75 @@ pub struct GuardrailRule {
     pub updated_at: i64,
 }
 
+#[derive(Debug, Clone)]
+pub struct GuardrailCanary {
+    pub channels: String,
+    pub rules_json: String,
+    pub started_at: i64,
+    pub ends_at: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct GuardrailCanaryDecision {
+    pub id: i64,
+    pub task_id: i64,
+    pub channel_id: String,
+    pub kind: String,
+    pub subject: String,
+    pub applied: String, // active | canary
+    pub active_decision: String,
+    pub active_rule_id: Option<String>,
+    pub canary_decision: String,
+    pub canary_rule_id: Option<String>,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SavedPrompt {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub template: String,
+    pub enabled: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// A channel that opted in to the monthly usage report.
+#[derive(Debug, Clone)]
+pub struct UsageReportChannel {
+    pub provider: String,
+    pub workspace_id: String,
+    pub channel_id: String,
+    /// `YYYY-MM` of the last report posted; empty before the first one.
+    pub last_period: String,
+    /// Failed attempts at the current report.
+    pub failures: i64,
+    /// No new attempt before this time after a failure.
+    pub retry_at: Option<i64>,
+}
+
+/// A window in which one channel runs with `full` permissions.
+#[derive(Debug, Clone)]
+pub struct ElevatedSession {
+    pub id: String,
+    pub provider: String,
+    pub workspace_id: String,
+    pub channel_id: String,
+    pub granted_by: String,
+    pub reason: String,
+    pub started_at: i64,
+    pub ends_at: i64,
+    pub revoked_at: Option<i64>,
+    pub revoked_by: Option<String>,
+}
+
+impl ElevatedSession {
+    pub fn is_active(&self, now: i64) -> bool {
+        self.revoked_at.is_none() && now < self.ends_at
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Approval {
     pub id: String,
@@ -189,6 +396,17 @@ pub struct Approval {
     pub resolved_at: Option<i64>,
 }
 
+#[derive(Debug, Clone)]
+pub struct TaskArtifact {
+    pub id: String,
+    pub task_id: i64,
+    pub kind: String, // command_output
+    pub name: String,
+    pub content: String,
+    pub approval_id: Option<String>,
+    pub created_at: i64,
+}
+
 #[derive(Debug, Clone)]
 pub struct TelegramMessage {
     pub chat_id: String,
@@ -198,3 +416,58 @@ pub struct TelegramMessage {
     pub text: Option<String>,
     pub ts: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_options_parse_leniently() {
+        assert_eq!(TaskOptions::parse(""), TaskOptions::default());
+        assert_eq!(TaskOptions::parse("not json"), TaskOptions::default());
+        let o = TaskOptions::parse(
+            r#"{"model":"gpt-5.2","permissions_mode":"read","context_channels":["C1"],"deadline_at":1700000000}"#,
+        );
+        assert_eq!(o.model.as_deref(), Some("gpt-5.2"));
+        assert_eq!(o.permissions_mode, Some(PermissionsMode::Read));
+        assert_eq!(o.context_channels, vec!["C1".to_string()]);
+        assert_eq!(o.deadline_at, Some(1_700_000_000));
+        assert_eq!(
+            serde_json::to_string(&TaskOptions::default()).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn rerun_options_override_model_permissions_and_deadline() {
+        let original = TaskOptions {
+            model: Some("gpt-5.2".to_string()),
+            context_channels: vec!["C1".to_string()],
+            deadline_at: Some(1_000),
+            ..Default::default()
+        };
+        let same = original.for_rerun(Some("  "), false, None, 900);
+        assert_eq!(same, original);
+
+        let rerun = original.for_rerun(Some("gpt-5.2-mini"), true, Some(600), 2_000);
+        assert_eq!(rerun.model.as_deref(), Some("gpt-5.2-mini"));
+        assert_eq!(rerun.permissions_mode, Some(PermissionsMode::Read));
+        assert_eq!(rerun.context_channels, vec!["C1".to_string()]);
+        // The timeout runs from when the copy is claimed, not from when it was queued.
+        assert_eq!(rerun.deadline_at, None);
+        assert_eq!(rerun.timeout_seconds, Some(600));
+        assert_eq!(rerun.deadline(None), None);
+        assert_eq!(rerun.deadline(Some(5_000)), Some(5_600));
+        let both = TaskOptions {
+            deadline_at: Some(5_300),
+            ..rerun.clone()
+        };
+        assert_eq!(both.deadline(Some(5_000)), Some(5_300));
+
+        // A deadline that already passed is dropped rather than failing the copy at once.
+        assert_eq!(
+            original.for_rerun(None, false, None, 2_000).deadline_at,
+            None
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Structured output contracts for API-created tasks.
+//!
+//! A caller of `/api/admin/tasks/add` or `/tasks/test` may pass `output_schema`, a JSON
+//! Schema the final answer must conform to. It travels with the task in
+//! [`TaskOptions`](crate::models::TaskOptions). The agent is told to put a JSON document in
+//! its `reply`; the worker validates it, asks the agent to repair it when it doesn't
+//! conform, and fails the task if it still doesn't. On success `result_text` holds the
+//! compact JSON and the task API returns it parsed as `output`.
+
+use serde_json::Value;
+
+/// Serialized schemas larger than this are rejected; they go into every prompt.
+pub const MAX_SCHEMA_CHARS: usize = 20_000;
+/// Validation errors reported back to the agent (and in the failure message).
+const MAX_ERRORS: usize = 10;
+
+/// Check that `schema` is a usable JSON Schema before a task is queued with it.
+pub fn validate_schema(schema: &Value) -> anyhow::Result<()> {
+    if !schema.is_object() && !schema.is_boolean() {
+        anyhow::bail!("output_schema must be a JSON Schema object");
+    }
+    if schema.to_string().len() > MAX_SCHEMA_CHARS {
+        anyhow::bail!("output_schema is larger than {MAX_SCHEMA_CHARS} characters");
+    }
+    jsonschema::validator_for(schema).map_err(|e| anyhow::anyhow!("invalid output_schema: {e}"))?;
+    Ok(())
+}
+
+fn strip_code_fences(s: &str) -> &str {
+    let s = s.trim();
+    let inner = s
+        .strip_prefix("```json")
+        .or_else(|| s.strip_prefix("```"))
+        .map(|rest| rest.trim().trim_end_matches("```").trim());
+    inner.unwrap_or(s)
+}
+
+/// Parse `reply` as JSON and validate it. `Err` lists what is wrong, one line per problem.
+pub fn check(schema: &Value, reply: &str) -> Result<Value, Vec<String>> {
+    let value: Value = serde_json::from_str(strip_code_fences(reply))
+        .map_err(|e| vec![format!("reply is not valid JSON: {e}")])?;
+    let validator = jsonschema::validator_for(schema)
+        .map_err(|e| vec![format!("invalid output_schema: {e}")])?;
+    let errors: Vec<String> = validator
+        .iter_errors(&value)
+        .take(MAX_ERRORS)
+        .map(|e| {
+            let path = e.instance_path.to_string();
+            if path.is_empty() {
+                e.to_string()
+            } else {
+                format!("{path}: {e}")
+            }
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(value)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Prompt section describing the contract.
+pub fn instructions(schema: &Value) -> String {
+    let pretty = serde_json::to_string_pretty(schema).unwrap_or_else(|_| schema.to_string());
+    format!(
+        "Output contract:\n\
+- This task was created by a program that parses your answer.\n\
+- `reply` must be a single JSON document (as a string, no prose, no code fences) that conforms to this JSON Schema:\n\
+```json\n{pretty}\n```\n\n"
+    )
+}
+
+/// Follow-up turn input when the reply broke the contract.
+pub fn repair_instructions(errors: &[String]) -> String {
+    format!(
+        "Your `reply` did not conform to the output contract's JSON Schema:\n- {}\n\n\
+Return the full JSON object again, with `reply` fixed so it is a JSON document that conforms to the schema.",
+        errors.join("\n- ")
+    )
+}
+
+/// The parsed answer of a succeeded contract task, for the API.
+pub fn parsed_output(
+    options: &crate::models::TaskOptions,
+    result_text: Option<&str>,
+) -> Option<Value> {
+    options.output_schema.as_ref()?;
+    serde_json::from_str(result_text?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_contract_validates_replies_against_the_schema() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["summary", "severity"],
+            "properties": {
+                "summary": { "type": "string" },
+                "severity": { "enum": ["low", "high"] },
+            },
+        });
+        assert!(validate_schema(&schema).is_ok());
+        assert!(validate_schema(&serde_json::json!("object")).is_err());
+        assert!(validate_schema(&serde_json::json!({ "type": "nope" })).is_err());
+
+        let ok = check(
+            &schema,
+            "```json\n{\"summary\": \"db down\", \"severity\": \"high\"}\n```",
+        );
+        assert_eq!(ok.unwrap()["severity"], "high");
+        let errors = check(&schema, r#"{"summary": 3, "severity": "high"}"#).unwrap_err();
+        assert!(errors[0].starts_with("/summary: "), "{errors:?}");
+        assert!(check(&schema, "All good!").unwrap_err()[0].contains("not valid JSON"));
+
+        let options = crate::models::TaskOptions {
+            output_schema: Some(schema),
+            ..Default::default()
+        };
+        assert_eq!(
+            parsed_output(&options, Some(r#"{"a":1}"#)),
+            Some(serde_json::json!({ "a": 1 }))
+        );
+        assert_eq!(parsed_output(&Default::default(), Some(r#"{"a":1}"#)), None);
+    }
+}
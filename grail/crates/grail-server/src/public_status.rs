@@ -0,0 +1,282 @@
+//! Public status page (`/status`, `/status.json`).
+//!
+//! Opt-in via the `public_status_page` setting and served without admin auth, so it only
+//! reports aggregate health: an overall verdict, a queue depth bucket, uptime and which
+//! chat providers are configured and seeing traffic. No task content, ids, user ids,
+//! channel ids or hostnames are included.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::AppState;
+
+/// A provider with a message this recent counts as active.
+const ACTIVE_WINDOW_SECONDS: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProviderStatus {
+    pub name: &'static str,
+    /// `active` (messages in the last 24h) or `idle`.
+    pub status: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PublicStatus {
+    /// `operational`, `degraded` or `down`.
+    pub status: &'static str,
+    /// `empty`, `light`, `busy` or `backlogged`.
+    pub queue: &'static str,
+    pub uptime_seconds: Option<i64>,
+    pub workers_online: usize,
+    pub providers: Vec<ProviderStatus>,
+    pub checked_at: i64,
+}
+
+pub fn queue_bucket(depth: i64) -> &'static str {
+    match depth {
+        i64::MIN..=0 => "empty",
+        1..=5 => "light",
+        6..=20 => "busy",
+        _ => "backlogged",
+    }
+}
+
+pub fn overall(workers_online: usize, queue: &str) -> &'static str {
+    if workers_online == 0 {
+        "down"
+    } else if queue == "backlogged" {
+        "degraded"
+    } else {
+        "operational"
+    }
+}
+
+/// `3d 4h`, `2h 5m`, `7m`.
+pub fn format_uptime(seconds: i64) -> String {
+    let (d, h, m) = (
+        seconds / 86_400,
+        seconds % 86_400 / 3_600,
+        seconds % 3_600 / 60,
+    );
+    if d > 0 {
+        format!("{d}d {h}h")
+    } else if h > 0 {
+        format!("{h}h {m}m")
+    } else {
+        format!("{m}m")
+    }
+}
+
+async fn collect(state: &AppState) -> anyhow::Result<PublicStatus> {
+    let settings = crate::db::get_settings(&state.pool).await?;
+    let now = chrono::Utc::now().timestamp();
+
+    let (depth, _) = crate::db::count_queued_and_running_tasks(&state.pool).await?;
+    let queue = queue_bucket(depth);
+
+    let live: Vec<_> = crate::db::list_worker_heartbeats(&state.pool)
+        .await?
+        .into_iter()
+        .filter(|w| now - w.last_seen_at <= crate::worker::HEARTBEAT_STALE_SECONDS)
+        .collect();
+    let uptime_seconds = live.iter().map(|w| now - w.started_at).max();
+
+    let last_seen = crate::db::last_task_at_by_provider(&state.pool).await?;
+
+    let configured = [
+        (
+            "slack",
+            "Slack",
+            crate::secrets::slack_bot_token_configured(state).await?,
+        ),
+        (
+            "telegram",
+            "Telegram",
+            settings.allow_telegram && crate::secrets::telegram_bot_token_configured(state).await?,
+        ),
+        (
+            "whatsapp",
+            "WhatsApp",
+            settings.allow_whatsapp
+                && crate::secrets::whatsapp_access_token_configured(state).await?,
+        ),
+        (
+            "discord",
+            "Discord",
+            settings.allow_discord && crate::secrets::discord_bot_token_configured(state).await?,
+        ),
+        (
+            "msteams",
+            "Microsoft Teams",
+            settings.allow_msteams && crate::secrets::msteams_app_id_configured(state).await?,
+        ),
+    ];
+    let providers = configured
+        .into_iter()
+        .filter(|(_, _, on)| *on)
+        .map(|(key, name, _)| ProviderStatus {
+            name,
+            status: if last_seen
+                .get(key)
+                .is_some_and(|t| now - t <= ACTIVE_WINDOW_SECONDS)
+            {
+                "active"
+            } else {
+                "idle"
+            },
+        })
+        .collect();
+
+    Ok(PublicStatus {
+        status: overall(live.len(), queue),
+        queue,
+        uptime_seconds,
+        workers_online: live.len(),
+        providers,
+        checked_at: now,
+    })
+}
+
+/// `None` when the page is turned off (served as 404, as if the route didn't exist).
+async fn load(state: &AppState) -> Result<Option<PublicStatus>, Response> {
+    match crate::db::get_settings(&state.pool).await {
+        Ok(s) if !s.public_status_page => return Ok(None),
+        Ok(_) => {}
+        Err(err) => {
+            warn!(error = %err, "public status: failed to load settings");
+            return Err((StatusCode::SERVICE_UNAVAILABLE, "status unavailable").into_response());
+        }
+    }
+    collect(state).await.map(Some).map_err(|err| {
+        warn!(error = %err, "public status: failed to collect health");
+        (StatusCode::SERVICE_UNAVAILABLE, "status unavailable").into_response()
+    })
+}
+
+pub async fn status_json(State(state): State<AppState>) -> Response {
+    match load(&state).await {
+        Ok(Some(status)) => Json(status).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(resp) => resp,
+    }
+}
+
+pub async fn status_page(State(state): State<AppState>) -> Response {
+    match load(&state).await {
+        Ok(Some(status)) => Html(render(&status)).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(resp) => resp,
+    }
+}
+
+/// Every value interpolated here is a fixed string or a number.
+pub fn render(s: &PublicStatus) -> String {
+    let color = match s.status {
+        "operational" => "#1a7f37",
+        "degraded" => "#9a6700",
+        _ => "#cf222e",
+    };
+    let uptime = s
+        .uptime_seconds
+        .map(format_uptime)
+        .unwrap_or_else(|| "—".to_string());
+    let providers = if s.providers.is_empty() {
+        "<li>None configured</li>".to_string()
+    } else {
+        s.providers
+            .iter()
+            .map(|p| format!("<li>{}: {}</li>", p.name, p.status))
+            .collect::<String>()
+    };
+    let checked = chrono::DateTime::from_timestamp(s.checked_at, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default();
+    format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta http-equiv="refresh" content="60">
+<title>FastClaw status</title>
+<style>
+body {{ font-family: system-ui, sans-serif; max-width: 32rem; margin: 3rem auto; padding: 0 1rem; color: #1f2328; }}
+h1 {{ font-size: 1.4rem; }}
+.verdict {{ font-size: 1.2rem; font-weight: 600; color: {color}; }}
+dt {{ font-weight: 600; margin-top: .8rem; }}
+footer {{ margin-top: 2rem; font-size: .85rem; color: #656d76; }}
+</style>
+</head>
+<body>
+<h1>FastClaw status</h1>
+<p class="verdict">{status}</p>
+<dl>
+<dt>Queue</dt><dd>{queue}</dd>
+<dt>Uptime</dt><dd>{uptime}</dd>
+<dt>Workers online</dt><dd>{workers}</dd>
+<dt>Chat providers</dt><dd><ul>{providers}</ul></dd>
+</dl>
+<footer>Checked {checked}. Refreshes every minute.</footer>
+</body>
+</html>
+"#,
+        status = s.status,
+        queue = s.queue,
+        workers = s.workers_online,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_status_reports_only_aggregates() {
+        assert_eq!(queue_bucket(0), "empty");
+        assert_eq!(queue_bucket(3), "light");
+        assert_eq!(queue_bucket(12), "busy");
+        assert_eq!(queue_bucket(500), "backlogged");
+        assert_eq!(overall(0, "empty"), "down");
+        assert_eq!(overall(2, "backlogged"), "degraded");
+        assert_eq!(overall(1, "busy"), "operational");
+        assert_eq!(format_uptime(3 * 86_400 + 4 * 3_600 + 59), "3d 4h");
+        assert_eq!(format_uptime(7_500), "2h 5m");
+
+        let status = PublicStatus {
+            status: "operational",
+            queue: "light",
+            uptime_seconds: Some(420),
+            workers_online: 1,
+            providers: vec![ProviderStatus {
+                name: "Slack",
+                status: "active",
+            }],
+            checked_at: 1_700_000_000,
+        };
+        let html = render(&status);
+        assert!(html.contains("operational") && html.contains("Slack: active"));
+        assert!(html.contains("7m"));
+        let json = serde_json::to_value(&status).unwrap();
+        let keys: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "checked_at",
+                "providers",
+                "queue",
+                "status",
+                "uptime_seconds",
+                "workers_online"
+            ]
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Answer repeated prompts from recent replies in the same channel.
+//!
+//! `exact` matches normalized prompt text; `semantic` also compares OpenAI embeddings
+//! and falls back to exact matching when no key is available. Cached answers are posted
+//! with a "Regenerate" button that queues a fresh run (see `grail_regenerate` in main.rs).
+
+use anyhow::Context;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::db;
+use crate::models::{ResponseCacheEntry, Settings, Task};
+use crate::slack::SlackClient;
+use crate::AppState;
+
+const EMBEDDING_MODEL: &str = "text-embedding-3-small";
+const SEMANTIC_THRESHOLD: f64 = 0.95;
+const MAX_CANDIDATES: i64 = 200;
+/// Slack section blocks hold at most 3000 characters.
+const MAX_INLINE_REPLY_CHARS: usize = 2_900;
+
+pub struct CacheKey {
+    prompt_hash: String,
+    prompt_text: String,
+    embedding: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheHit {
+    pub task_id: i64,
+    pub response_text: String,
+    pub created_at: i64,
+    pub similarity: f64,
+}
+
+/// Only fresh, top-level Slack prompts without attachments are cached: follow-ups in a
+/// thread depend on the conversation, and the regenerate button needs Slack.
+pub fn eligible(settings: &Settings, task: &Task) -> bool {
+    let files = task.files_json.trim();
+    settings.response_cache_mode != "off"
+        && task.provider == "slack"
+        && !task.is_proactive
+        && !task.is_synthetic
+        && !task.skip_response_cache
+        && (task.thread_ts.is_empty() || task.thread_ts == task.event_ts)
+        && (files.is_empty() || files == "[]")
+}
+
+/// Only the leading bot mention is dropped; other user and channel mentions are part of
+/// the question.
+pub fn normalize_prompt(text: &str) -> String {
+    crate::strip_leading_mentions(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .trim_end_matches(['?', '!', '.', ' '])
+        .to_string()
+}
+
+fn prompt_hash(normalized: &str) -> String {
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let (mut dot, mut na, mut nb) = (0.0f64, 0.0f64, 0.0f64);
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = (f64::from(*x), f64::from(*y));
+        dot += x * y;
+        na += x * x;
+        nb += y * y;
+    }
+    if na == 0.0 || nb == 0.0 {
+        0.0
+    } else {
+        dot / (na.sqrt() * nb.sqrt())
+    }
+}
+
+async fn embed(state: &AppState, api_key: &str, text: &str) -> anyhow::Result<Vec<f32>> {
+    let resp = state
+        .http
+        .post("https://api.openai.com/v1/embeddings")
+        .bearer_auth(api_key)
+        .json(&json!({ "model": EMBEDDING_MODEL, "input": text }))
+        .send()
+        .await
+        .context("openai embeddings request")?;
+    let status = resp.status();
+    if !status.is_success() {
+        anyhow::bail!("openai embeddings failed with status {}", status.as_u16());
+    }
+    let v: serde_json::Value = resp.json().await.context("decode embeddings response")?;
+    let arr = v
+        .pointer("/data/0/embedding")
+        .and_then(|e| e.as_array())
+        .context("embeddings response missing data[0].embedding")?;
+    Ok(arr
+        .iter()
+        .filter_map(|x| x.as_f64())
+        .map(|x| x as f32)
+        .collect())
+}
+
+pub async fn prepare(state: &AppState, settings: &Settings, task: &Task) -> CacheKey {
+    let prompt_text = normalize_prompt(&task.prompt_text);
+    let embedding = if settings.response_cache_mode == "semantic" && !prompt_text.is_empty() {
+        match crate::secrets::load_openai_api_key_opt(state).await {
+            Ok(Some(key)) => match embed(state, &key, &prompt_text).await {
+                Ok(v) => Some(v),
+                Err(err) => {
+                    warn!(error = %err, task_id = task.id, "prompt embedding failed; using exact cache match");
+                    None
+                }
+            },
+            _ => None,
+        }
+    } else {
+        None
+    };
+    CacheKey {
+        prompt_hash: prompt_hash(&prompt_text),
+        prompt_text,
+        embedding,
+    }
+}
+
+pub async fn lookup(
+    state: &AppState,
+    settings: &Settings,
+    task: &Task,
+    key: &CacheKey,
+) -> anyhow::Result<Option<CacheHit>> {
+    if key.prompt_text.is_empty() {
+        return Ok(None);
+    }
+    let since = chrono::Utc::now().timestamp() - settings.response_cache_ttl_seconds;
+    let entries = db::list_response_cache(
+        &state.pool,
+        &task.workspace_id,
+        &task.channel_id,
+        since,
+        MAX_CANDIDATES,
+    )
+    .await?;
+
+    let hit = |e: &ResponseCacheEntry, similarity: f64| CacheHit {
+        task_id: e.task_id,
+        response_text: e.response_text.clone(),
+        created_at: e.created_at,
+        similarity,
+    };
+    if let Some(e) = entries.iter().find(|e| e.prompt_hash == key.prompt_hash) {
+        return Ok(Some(hit(e, 1.0)));
+    }
+    let Some(embedding) = key.embedding.as_deref() else {
+        return Ok(None);
+    };
+    let best = entries
+        .iter()
+        .filter_map(|e| {
+            let other: Vec<f32> = serde_json::from_str(e.embedding_json.as_deref()?).ok()?;
+            Some((e, cosine_similarity(embedding, &other)))
+        })
+        .filter(|(_, sim)| *sim >= SEMANTIC_THRESHOLD)
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    Ok(best.map(|(e, sim)| hit(e, sim)))
+}
+
+pub async fn store(
+    state: &AppState,
+    settings: &Settings,
+    task: &Task,
+    key: CacheKey,
+    reply_text: &str,
+) -> anyhow::Result<()> {
+    if key.prompt_text.is_empty() || reply_text.trim().is_empty() {
+        return Ok(());
+    }
+    let entry = ResponseCacheEntry {
+        workspace_id: task.workspace_id.clone(),
+        channel_id: task.channel_id.clone(),
+        prompt_hash: key.prompt_hash,
+        prompt_text: key.prompt_text,
+        embedding_json: key
+            .embedding
+            .map(|e| serde_json::to_string(&e))
+            .transpose()?,
+        task_id: task.id,
+        response_text: reply_text.to_string(),
+        created_at: 0,
+    };
+    db::insert_response_cache(&state.pool, &entry).await?;
+    db::delete_expired_response_cache(&state.pool, settings.response_cache_ttl_seconds).await?;
+    Ok(())
+}
+
+/// Footer blocks for a cached answer; `reply` is inlined when it fits in one section.
+fn cached_reply_blocks(hit: &CacheHit, task_id: i64, reply: Option<&str>) -> serde_json::Value {
+    let age_min = ((chrono::Utc::now().timestamp() - hit.created_at).max(0) / 60).max(1);
+    let how = if hit.similarity >= 1.0 {
+        "the same question".to_string()
+    } else {
+        format!(
+            "a very similar question ({:.0}% match)",
+            hit.similarity * 100.0
+        )
+    };
+    let mut blocks = Vec::new();
+    if let Some(reply) = reply {
+        blocks.push(json!({ "type": "section", "text": { "type": "mrkdwn", "text": reply } }));
+    }
+    blocks.push(json!({
+        "type": "context",
+        "elements": [{
+            "type": "mrkdwn",
+            "text": format!(
+                "Cached answer: {how} was answered {age_min} min ago (task #{}).",
+                hit.task_id
+            ),
+        }],
+    }));
+    blocks.push(json!({
+        "type": "actions",
+        "elements": [{
+            "type": "button",
+            "action_id": "grail_regenerate",
+            "text": { "type": "plain_text", "text": "Regenerate" },
+            "value": task_id.to_string(),
+        }],
+    }));
+    json!(blocks)
+}
+
+/// Post a cached answer in the task's thread with a "Regenerate" button.
+pub async fn post_cached_reply(
+    slack: &SlackClient,
+    task: &Task,
+    hit: &CacheHit,
+) -> anyhow::Result<()> {
+    let thread = Some(task.thread_ts.as_str()).filter(|t| !t.trim().is_empty());
+    let reply = hit.response_text.trim();
+    if reply.chars().count() <= MAX_INLINE_REPLY_CHARS {
+        let blocks = cached_reply_blocks(hit, task.id, Some(reply));
+        slack
+            .post_message_rich(&task.channel_id, thread, reply, blocks)
+            .await
+    } else {
+        slack.post_message(&task.channel_id, thread, reply).await?;
+        let blocks = cached_reply_blocks(hit, task.id, None);
+        slack
+            .post_message_rich(&task.channel_id, thread, "Cached answer", blocks)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_cache_normalizes_prompts() {
+        assert_eq!(
+            normalize_prompt("<@U123>  What is our   PTO policy?? "),
+            "what is our pto policy"
+        );
+        assert_eq!(
+            normalize_prompt("what is our PTO policy"),
+            normalize_prompt("<@U999> What is our PTO policy.")
+        );
+        assert_ne!(
+            normalize_prompt("<@UBOT> summarize <#C1|eng>"),
+            normalize_prompt("<@UBOT> summarize <#C2|sales>")
+        );
+        assert_ne!(
+            normalize_prompt("<@UBOT> what did <@U1> say"),
+            normalize_prompt("<@UBOT> what did <@U2> say")
+        );
+        assert!((cosine_similarity(&[1.0, 2.0], &[2.0, 4.0]) - 1.0).abs() < 1e-9);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 2.0]), 0.0);
+    }
+}
@@ -0,0 +1,220 @@
+//! Saved prompts: admin-curated templates run from chat with `run <name> [input]`.
+//!
+//! Templates can use `{{args}}` (text after the name), `{{thread}}` (the Slack thread
+//! so far), `{{user}}`, `{{channel}}` and `{{date}}`. Input given without an `{{args}}`
+//! slot is appended, so a template never silently drops what the user typed. Unknown
+//! names fall through to a normal task, so "run the tests" still reaches the agent.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::models::SavedPrompt;
+use crate::slack::{SlackClient, SlackMessage};
+use crate::AppState;
+
+static NAME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-z0-9][a-z0-9_-]{0,63}$").unwrap());
+static RUN_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)^run\s+([a-z0-9][a-z0-9_-]*)\b(?:\s*:)?\s*(.*)$").unwrap());
+static SLOT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{(args|thread|user|channel|date)\}\}").unwrap());
+static LIST_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)^(?:run|(?:list\s+|show\s+)?(?:saved\s+)?prompts|list\s+templates)\s*[?.!]*$")
+        .unwrap()
+});
+
+/// Thread messages included in `{{thread}}`.
+pub const THREAD_LIMIT: i64 = 50;
+/// Upper bound on an expanded prompt.
+pub const MAX_EXPANDED_CHARS: usize = 16_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunCommand {
+    List,
+    Run { name: String, args: String },
+}
+
+pub fn parse_run_command(text: &str) -> Option<RunCommand> {
+    let text = text.trim();
+    if LIST_RE.is_match(text) {
+        return Some(RunCommand::List);
+    }
+    let caps = RUN_RE.captures(text)?;
+    Some(RunCommand::Run {
+        name: caps[1].to_ascii_lowercase(),
+        args: caps[2].trim().to_string(),
+    })
+}
+
+/// Normalize and check a template name (lowercase letters, digits, `-` and `_`).
+pub fn validate_name(name: &str) -> anyhow::Result<String> {
+    let name = name.trim().to_ascii_lowercase();
+    anyhow::ensure!(
+        NAME_RE.is_match(&name),
+        "name must be 1-64 lowercase letters, digits, '-' or '_' (e.g. incident-summary)"
+    );
+    Ok(name)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Slots {
+    pub args: String,
+    pub thread: String,
+    pub user: String,
+    pub channel: String,
+    pub date: String,
+}
+
+/// Fill the slots in one pass over `template`, so slot-like text inside a value (a user
+/// typing `{{thread}}` as input, say) is left as typed.
+pub fn expand(template: &str, slots: &Slots) -> String {
+    let mut out = SLOT_RE
+        .replace_all(template, |caps: &regex::Captures| match &caps[1] {
+            "args" => slots.args.trim(),
+            "thread" => slots.thread.trim(),
+            "user" => slots.user.as_str(),
+            "channel" => slots.channel.as_str(),
+            _ => slots.date.as_str(),
+        })
+        .into_owned();
+    if !template.contains("{{args}}") && !slots.args.trim().is_empty() {
+        out = format!(
+            "{}\n\nAdditional input: {}",
+            out.trim_end(),
+            slots.args.trim()
+        );
+    }
+    out.trim().to_string()
+}
+
+/// Plain transcript of a Slack thread for `{{thread}}`.
+pub fn format_thread(messages: &[SlackMessage]) -> String {
+    messages
+        .iter()
+        .filter_map(|m| {
+            let text = m.text.as_deref().unwrap_or("").trim();
+            if text.is_empty() {
+                return None;
+            }
+            let who = m
+                .user
+                .as_deref()
+                .or(m.bot_id.as_deref())
+                .unwrap_or("unknown");
+            Some(format!("<@{who}>: {text}"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Chat reply listing the enabled prompts.
+pub fn list_reply(prompts: &[SavedPrompt]) -> String {
+    let enabled: Vec<&SavedPrompt> = prompts.iter().filter(|p| p.enabled).collect();
+    if enabled.is_empty() {
+        return "No saved prompts yet. Admins can add them on the Saved Prompts page.".to_string();
+    }
+    let mut out = String::from("Saved prompts (use `run <name> [input]`):\n");
+    for p in enabled {
+        if p.description.trim().is_empty() {
+            out.push_str(&format!("- `{}`\n", p.name));
+        } else {
+            out.push_str(&format!("- `{}`: {}\n", p.name, p.description.trim()));
+        }
+    }
+    out
+}
+
+/// Result of a `run` message.
+pub enum Resolved {
+    /// Answer in chat without enqueueing anything.
+    Reply(String),
+    /// Enqueue this expanded prompt.
+    Prompt { name: String, text: String },
+    /// Not a saved prompt; treat the message as a normal request.
+    NotSaved,
+}
+
+/// Expand `cmd`. `slack_thread` is `(client, channel, thread_ts, event_ts)` and is only
+/// fetched when the template uses `{{thread}}`.
+pub async fn resolve(
+    state: &AppState,
+    cmd: RunCommand,
+    mut slots: Slots,
+    slack_thread: Option<(&SlackClient, &str, &str, &str)>,
+) -> anyhow::Result<Resolved> {
+    let (name, args) = match cmd {
+        RunCommand::List => {
+            let prompts = crate::db::list_saved_prompts(&state.pool).await?;
+            return Ok(Resolved::Reply(list_reply(&prompts)));
+        }
+        RunCommand::Run { name, args } => (name, args),
+    };
+    let Some(saved) = crate::db::get_saved_prompt_by_name(&state.pool, &name)
+        .await?
+        .filter(|p| p.enabled)
+    else {
+        return Ok(Resolved::NotSaved);
+    };
+    if saved.template.contains("{{thread}}") {
+        if let Some((slack, channel, thread_ts, event_ts)) = slack_thread {
+            let messages = slack
+                .fetch_thread_replies(channel, thread_ts, event_ts, THREAD_LIMIT)
+                .await?;
+            slots.thread = format_thread(&messages);
+        }
+    }
+    slots.args = args;
+    slots.date = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    let text = crate::clamp_chars(expand(&saved.template, &slots), MAX_EXPANDED_CHARS);
+    Ok(Resolved::Prompt {
+        name: saved.name,
+        text,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_prompt_run_command_expands_slots() {
+        assert_eq!(
+            parse_run_command("run Incident-Summary: api 500s since 09:00"),
+            Some(RunCommand::Run {
+                name: "incident-summary".to_string(),
+                args: "api 500s since 09:00".to_string(),
+            })
+        );
+        assert_eq!(parse_run_command("list prompts"), Some(RunCommand::List));
+        assert_eq!(parse_run_command("please run the tests"), None);
+        assert!(validate_name("Postmortem_v2").is_ok());
+        assert!(validate_name("bad name").is_err());
+
+        let slots = Slots {
+            args: "checkout outage".to_string(),
+            thread: "<@U1>: errors spiking".to_string(),
+            user: "U1".to_string(),
+            channel: "C1".to_string(),
+            date: "2026-01-02".to_string(),
+        };
+        assert_eq!(
+            expand(
+                "Postmortem for {{args}} ({{date}}).\nThread:\n{{thread}}",
+                &slots
+            ),
+            "Postmortem for checkout outage (2026-01-02).\nThread:\n<@U1>: errors spiking"
+        );
+        assert_eq!(
+            expand("Summarize <#{{channel}}>.", &slots),
+            "Summarize <#C1>.\n\nAdditional input: checkout outage"
+        );
+        // Values are never expanded again.
+        let sneaky = Slots {
+            args: "{{thread}} {{date}}".to_string(),
+            ..slots
+        };
+        assert_eq!(
+            expand("Look at {{args}} on {{date}}", &sneaky),
+            "Look at {{thread}} {{date}} on 2026-01-02"
+        );
+    }
+}
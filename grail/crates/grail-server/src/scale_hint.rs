@@ -0,0 +1,246 @@
+//! Autoscaling hints (`/api/v1/scale-hint`).
+//!
+//! Reports queue depth, running tasks, live workers and a predicted wait for the next
+//! queued task, plus a suggested worker count, so an orchestrator (Nomad, KEDA, ...) can
+//! scale replicas. The endpoint only exists when `GRAIL_SCALE_HINT_TOKEN` is set and
+//! callers send it as a bearer token.
+//!
+//! The leader also watches the `scale_queue_depth_threshold` and
+//! `scale_wait_seconds_threshold` settings and emits a `scale.pressure` event when either
+//! is crossed and `scale.recovered` once both are back under. Events are logged and, when
+//! `scale_webhook_url` is set, POSTed there as JSON.
+
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+use subtle::ConstantTimeEq;
+use tracing::{info, warn};
+
+use crate::AppState;
+
+/// Run times are averaged over tasks finished this recently.
+const RUN_WINDOW_SECONDS: i64 = 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScaleHint {
+    pub queue_depth: i64,
+    pub running: i64,
+    pub workers_online: i64,
+    pub slots_per_worker: i64,
+    /// Mean run time of tasks finished in the last hour; `None` when there were none.
+    pub avg_run_seconds: Option<f64>,
+    /// Expected time until the last queued task starts; `None` when no worker is online
+    /// to drain a non-empty queue.
+    pub predicted_wait_seconds: Option<i64>,
+    /// Workers needed to run everything queued and running at once.
+    pub desired_workers: i64,
+    pub over_threshold: bool,
+    pub checked_at: i64,
+}
+
+/// Pure part of the hint, so it can be tested without a database.
+pub fn compute(
+    queue_depth: i64,
+    running: i64,
+    workers_online: i64,
+    slots_per_worker: i64,
+    avg_run_seconds: Option<f64>,
+) -> (Option<i64>, i64) {
+    let slots = slots_per_worker.max(1);
+    let capacity = workers_online.max(0) * slots;
+    let wait = if queue_depth <= 0 {
+        Some(0)
+    } else if capacity == 0 {
+        None
+    } else {
+        // Queued tasks start in waves of `capacity` as running ones finish.
+        let waves = (queue_depth + capacity - 1) / capacity;
+        let avg = avg_run_seconds.unwrap_or(0.0);
+        Some((waves as f64 * avg).round() as i64)
+    };
+    let demand = queue_depth.max(0) + running.max(0);
+    let desired = ((demand + slots - 1) / slots).max(1);
+    (wait, desired)
+}
+
+/// Whether either threshold (0 = off) is reached; an unbounded wait counts as reached.
+pub fn over_threshold(
+    queue_depth: i64,
+    predicted_wait_seconds: Option<i64>,
+    depth_threshold: i64,
+    wait_threshold: i64,
+) -> bool {
+    let depth_hit = depth_threshold > 0 && queue_depth >= depth_threshold;
+    let wait_hit = wait_threshold > 0
+        && queue_depth > 0
+        && predicted_wait_seconds.is_none_or(|w| w >= wait_threshold);
+    depth_hit || wait_hit
+}
+
+pub async fn collect(state: &AppState) -> anyhow::Result<ScaleHint> {
+    let settings = crate::db::get_settings(&state.pool).await?;
+    let now = chrono::Utc::now().timestamp();
+
+    let (queue_depth, running) = crate::db::count_queued_and_running_tasks(&state.pool).await?;
+    let avg_run_seconds =
+        crate::db::avg_task_run_seconds_since(&state.pool, now - RUN_WINDOW_SECONDS).await?;
+    let workers_online = crate::db::list_worker_heartbeats(&state.pool)
+        .await?
+        .iter()
+        .filter(|w| now - w.last_seen_at <= crate::worker::HEARTBEAT_STALE_SECONDS)
+        .count() as i64;
+    let slots_per_worker = state.config.worker_concurrency.max(1) as i64;
+
+    let (predicted_wait_seconds, desired_workers) = compute(
+        queue_depth,
+        running,
+        workers_online,
+        slots_per_worker,
+        avg_run_seconds,
+    );
+    Ok(ScaleHint {
+        queue_depth,
+        running,
+        workers_online,
+        slots_per_worker,
+        avg_run_seconds,
+        predicted_wait_seconds,
+        desired_workers,
+        over_threshold: over_threshold(
+            queue_depth,
+            predicted_wait_seconds,
+            settings.scale_queue_depth_threshold,
+            settings.scale_wait_seconds_threshold,
+        ),
+        checked_at: now,
+    })
+}
+
+fn authorized(expected: &str, headers: &HeaderMap) -> bool {
+    let Some(token) = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    token
+        .trim()
+        .as_bytes()
+        .ct_eq(expected.as_bytes())
+        .unwrap_u8()
+        == 1
+}
+
+pub async fn scale_hint(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let Some(expected) = state
+        .config
+        .scale_hint_token
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !authorized(expected, &headers) {
+        return (StatusCode::UNAUTHORIZED, "unauthorized").into_response();
+    }
+    match collect(&state).await {
+        Ok(hint) => Json(hint).into_response(),
+        Err(err) => {
+            warn!(error = %err, "scale hint: failed to collect");
+            (StatusCode::SERVICE_UNAVAILABLE, "scale hint unavailable").into_response()
+        }
+    }
+}
+
+pub fn parse_webhook_url(raw: &str) -> anyhow::Result<reqwest::Url> {
+    let url = reqwest::Url::parse(raw.trim())
+        .map_err(|e| anyhow::anyhow!("invalid scale_webhook_url: {e}"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        anyhow::bail!("scale_webhook_url must be http or https");
+    }
+    Ok(url)
+}
+
+/// Leader-side threshold watcher; `pressured` carries the last state between calls.
+pub async fn check_thresholds(state: &AppState, pressured: &mut bool) {
+    let hint = match collect(state).await {
+        Ok(h) => h,
+        Err(err) => {
+            warn!(error = %err, "scale hint: failed to collect");
+            return;
+        }
+    };
+    if hint.over_threshold == *pressured {
+        return;
+    }
+    *pressured = hint.over_threshold;
+    let event = if hint.over_threshold {
+        "scale.pressure"
+    } else {
+        "scale.recovered"
+    };
+    info!(
+        event,
+        queue_depth = hint.queue_depth,
+        predicted_wait_seconds = ?hint.predicted_wait_seconds,
+        desired_workers = hint.desired_workers,
+        workers_online = hint.workers_online,
+        "scale threshold crossed"
+    );
+
+    let url = match crate::db::get_settings(&state.pool).await {
+        Ok(s) if !s.scale_webhook_url.trim().is_empty() => s.scale_webhook_url,
+        Ok(_) => return,
+        Err(err) => {
+            warn!(error = %err, "scale hint: failed to load settings");
+            return;
+        }
+    };
+    let url = match parse_webhook_url(&url) {
+        Ok(u) => u,
+        Err(err) => {
+            warn!(error = %err, "scale hint: bad webhook url");
+            return;
+        }
+    };
+    // Delivered in the background so a slow endpoint can't stall the leader loop.
+    let body = serde_json::json!({ "event": event, "hint": hint });
+    let http = state.http.clone();
+    tokio::spawn(async move {
+        let res = http
+            .post(url)
+            .timeout(std::time::Duration::from_secs(10))
+            .json(&body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+        if let Err(err) = res {
+            warn!(error = %err, event, "scale hint: webhook delivery failed");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_hint_predicts_wait_in_waves() {
+        // 5 queued, 2 workers x 2 slots: two waves of 60s.
+        assert_eq!(compute(5, 4, 2, 2, Some(60.0)), (Some(120), 5));
+        assert_eq!(compute(0, 0, 1, 2, None), (Some(0), 1));
+        // Nobody online to drain the queue.
+        assert_eq!(compute(3, 0, 0, 2, Some(30.0)), (None, 2));
+
+        assert!(!over_threshold(50, Some(999), 0, 0));
+        assert!(over_threshold(10, Some(5), 10, 0));
+        assert!(over_threshold(1, Some(300), 0, 300));
+        assert!(!over_threshold(1, Some(299), 0, 300));
+        assert!(over_threshold(1, None, 0, 300));
+        assert!(!over_threshold(0, None, 0, 300));
+    }
+}
@@ -38,6 +38,26 @@ pub async fn openai_api_key_configured(state: &AppState) -> anyhow::Result<bool>
     Ok(load_openai_api_key_opt(state).await?.is_some())
 }
 
+pub async fn load_anthropic_api_key_opt(state: &AppState) -> anyhow::Result<Option<String>> {
+    if let Some(v) = env_nonempty("ANTHROPIC_API_KEY") {
+        return Ok(Some(v));
+    }
+
+    let Some(crypto) = state.crypto.as_deref() else {
+        return Ok(None);
+    };
+    let Some((nonce, ciphertext)) = db::read_secret(&state.pool, "anthropic_api_key").await? else {
+        return Ok(None);
+    };
+    let plaintext = crypto.decrypt(b"anthropic_api_key", &nonce, &ciphertext)?;
+    let s = String::from_utf8(plaintext).context("ANTHROPIC_API_KEY not valid utf-8")?;
+    Ok(normalize_nonempty(s))
+}
+
+pub async fn anthropic_api_key_configured(state: &AppState) -> anyhow::Result<bool> {
+    Ok(load_anthropic_api_key_opt(state).await?.is_some())
+}
+
 pub fn load_github_client_id_from_env() -> Option<String> {
     env_nonempty("GITHUB_CLIENT_ID")
 }
@@ -383,6 +403,37 @@ pub async fn msteams_app_password_configured(state: &AppState) -> anyhow::Result
     Ok(load_msteams_app_password_opt(state).await?.is_some())
 }
 
+/// Named secrets are environment-variable-style names.
+pub fn valid_custom_secret_name(name: &str) -> bool {
+    static NAME_RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").expect("regex"));
+    NAME_RE.is_match(name)
+}
+
+pub fn custom_secret_key(name: &str) -> String {
+    format!("custom.{name}")
+}
+
+/// A named secret stored through the admin API (`custom.NAME`). Unlike the built-in
+/// secrets there is no environment fallback: settings that hand secrets to commands or
+/// other services must not be able to reach the server's own environment.
+pub async fn load_custom_secret_opt(
+    state: &AppState,
+    name: &str,
+) -> anyhow::Result<Option<String>> {
+    let Some(crypto) = state.crypto.as_deref() else {
+        return Ok(None);
+    };
+    let key = custom_secret_key(name);
+    let Some((nonce, ciphertext)) = db::read_secret(&state.pool, &key).await? else {
+        return Ok(None);
+    };
+    let plaintext = crypto.decrypt(key.as_bytes(), &nonce, &ciphertext)?;
+    let s =
+        String::from_utf8(plaintext).with_context(|| format!("secret {name} not valid utf-8"))?;
+    Ok(normalize_nonempty(s))
+}
+
 static SECRET_REDACTIONS: Lazy<Vec<(Regex, &'static str)>> = Lazy::new(|| {
     vec![
         // OpenAI API keys (including newer sk-proj- style).
@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
@@ -20,8 +23,13 @@ pub enum SlackSignatureError {
     TimestampTooOld,
     #[error("signature mismatch")]
     SignatureMismatch,
+    #[error("replayed request")]
+    Replayed,
 }
 
+/// Slack's recommended window for `X-Slack-Request-Timestamp`.
+const SIGNATURE_MAX_SKEW_SECS: i64 = 60 * 5;
+
 pub fn verify_slack_signature(
     signing_secret: &str,
     headers: &HeaderMap,
@@ -50,7 +58,7 @@ pub fn verify_slack_signature(
         .duration_since(UNIX_EPOCH)
         .unwrap_or(Duration::from_secs(0))
         .as_secs() as i64;
-    if (now - ts).abs() > 60 * 5 {
+    if (now - ts).abs() > SIGNATURE_MAX_SKEW_SECS {
         return Err(SlackSignatureError::TimestampTooOld);
     }
 
@@ -69,15 +77,112 @@ pub fn verify_slack_signature(
     Ok(())
 }
 
+/// Signature verification for inbound Slack requests, with replay rejection and
+/// outcome counters (shown on the admin status page).
+#[derive(Debug, Default)]
+pub struct SlackRequestGuard {
+    /// Signatures seen within the timestamp window, with their request timestamp.
+    seen: Mutex<HashMap<String, i64>>,
+    verified: AtomicU64,
+    missing_header: AtomicU64,
+    invalid_header: AtomicU64,
+    timestamp_skew: AtomicU64,
+    signature_mismatch: AtomicU64,
+    replayed: AtomicU64,
+}
+
+impl SlackRequestGuard {
+    pub fn verify(
+        &self,
+        signing_secret: &str,
+        headers: &HeaderMap,
+        body: &Bytes,
+    ) -> Result<(), SlackSignatureError> {
+        let result = verify_slack_signature(signing_secret, headers, body).and_then(|()| {
+            // Both headers were validated above.
+            let signature = headers
+                .get("X-Slack-Signature")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default();
+            let ts = headers
+                .get("X-Slack-Request-Timestamp")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<i64>().ok())
+                .unwrap_or_default();
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or(Duration::from_secs(0))
+                .as_secs() as i64;
+            if self.check_fresh(signature, ts, now) {
+                Ok(())
+            } else {
+                Err(SlackSignatureError::Replayed)
+            }
+        });
+        let counter = match &result {
+            Ok(()) => &self.verified,
+            Err(SlackSignatureError::MissingHeader(_)) => &self.missing_header,
+            Err(SlackSignatureError::InvalidHeader(_)) => &self.invalid_header,
+            Err(SlackSignatureError::TimestampTooOld) => &self.timestamp_skew,
+            Err(SlackSignatureError::SignatureMismatch) => &self.signature_mismatch,
+            Err(SlackSignatureError::Replayed) => &self.replayed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        result
+    }
+
+    /// Record `signature` and report whether it was unseen. Entries outside the timestamp
+    /// window are pruned: those requests fail the skew check anyway.
+    fn check_fresh(&self, signature: &str, ts: i64, now: i64) -> bool {
+        let mut seen = self.seen.lock().unwrap_or_else(|e| e.into_inner());
+        seen.retain(|_, t| (now - *t).abs() <= SIGNATURE_MAX_SKEW_SECS);
+        seen.insert(signature.to_string(), ts).is_none()
+    }
+
+    pub fn metrics(&self) -> serde_json::Value {
+        let get = |c: &AtomicU64| c.load(Ordering::Relaxed);
+        serde_json::json!({
+            "verified": get(&self.verified),
+            "missing_header": get(&self.missing_header),
+            "invalid_header": get(&self.invalid_header),
+            "timestamp_skew": get(&self.timestamp_skew),
+            "signature_mismatch": get(&self.signature_mismatch),
+            "replayed": get(&self.replayed),
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SlackClient {
     http: reqwest::Client,
     bot_token: String,
+    read_only: bool,
 }
 
 impl SlackClient {
     pub fn new(http: reqwest::Client, bot_token: String) -> Self {
-        Self { http, bot_token }
+        Self {
+            http,
+            bot_token,
+            read_only: false,
+        }
+    }
+
+    /// A client that can read history but refuses every call that posts or changes
+    /// anything in Slack. Used for synthetic (admin test) tasks.
+    pub fn read_only(http: reqwest::Client, bot_token: String) -> Self {
+        Self {
+            http,
+            bot_token,
+            read_only: true,
+        }
+    }
+
+    fn ensure_writable(&self, method: &str) -> anyhow::Result<()> {
+        if self.read_only {
+            anyhow::bail!("slack {method} refused: client is read-only");
+        }
+        Ok(())
     }
 
     fn headers(&self) -> HeaderMap {
@@ -133,7 +238,9 @@ impl SlackClient {
         thread_ts: Option<&str>,
         text: &str,
     ) -> anyhow::Result<()> {
-        const SLACK_TEXT_MAX_BYTES: usize = 35_000;
+        self.ensure_writable("chat.postMessage")?;
+        // Slack truncates message text after 40,000 characters.
+        const SLACK_TEXT_MAX_CHARS: usize = 35_000;
 
         #[derive(Serialize)]
         struct Req<'a> {
@@ -143,7 +250,7 @@ impl SlackClient {
             thread_ts: Option<&'a str>,
         }
 
-        for chunk in split_slack_text(text, SLACK_TEXT_MAX_BYTES) {
+        for chunk in split_slack_text(text, SLACK_TEXT_MAX_CHARS) {
             let resp: SlackApiResponse<serde_json::Value> = self
                 .http
                 .post("https://slack.com/api/chat.postMessage")
@@ -177,7 +284,9 @@ impl SlackClient {
         text: &str,
         blocks: serde_json::Value,
     ) -> anyhow::Result<()> {
-        const SLACK_TEXT_MAX_BYTES: usize = 35_000;
+        self.ensure_writable("chat.postMessage")?;
+        // Slack truncates message text after 40,000 characters.
+        const SLACK_TEXT_MAX_CHARS: usize = 35_000;
 
         #[derive(Serialize)]
         struct Req<'a> {
@@ -193,8 +302,8 @@ impl SlackClient {
         if t.is_empty() {
             t = "(empty)".to_string();
         }
-        if t.len() > SLACK_TEXT_MAX_BYTES {
-            t = t.chars().take(SLACK_TEXT_MAX_BYTES).collect();
+        if t.chars().count() > SLACK_TEXT_MAX_CHARS {
+            t = grail_text::truncate(&t, SLACK_TEXT_MAX_CHARS);
         }
 
         let resp: SlackApiResponse<serde_json::Value> = self
@@ -224,12 +333,250 @@ impl SlackClient {
         Ok(())
     }
 
+    /// Post a single short message and return its `ts` so callers can thread under it.
+    pub async fn post_message_ts(
+        &self,
+        channel: &str,
+        thread_ts: Option<&str>,
+        text: &str,
+    ) -> anyhow::Result<String> {
+        self.ensure_writable("chat.postMessage")?;
+        let mut body = serde_json::json!({ "channel": channel, "text": text });
+        if let Some(ts) = thread_ts {
+            body["thread_ts"] = serde_json::json!(ts);
+        }
+        let resp: SlackApiResponse<serde_json::Value> = self
+            .http
+            .post("https://slack.com/api/chat.postMessage")
+            .headers(self.headers())
+            .json(&body)
+            .send()
+            .await
+            .context("slack chat.postMessage request")?
+            .json()
+            .await
+            .context("slack chat.postMessage decode")?;
+
+        if !resp.ok {
+            anyhow::bail!(
+                "slack chat.postMessage failed: {}",
+                resp.error.unwrap_or_else(|| "unknown_error".to_string())
+            );
+        }
+        resp.data
+            .as_ref()
+            .and_then(|d| d.get("ts"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .context("slack chat.postMessage returned no ts")
+    }
+
+    /// Open a modal in response to an interaction (`trigger_id` is valid for 3 seconds).
+    pub async fn views_open(
+        &self,
+        trigger_id: &str,
+        view: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        self.ensure_writable("views.open")?;
+        let resp: SlackApiResponse<serde_json::Value> = self
+            .http
+            .post("https://slack.com/api/views.open")
+            .headers(self.headers())
+            .json(&serde_json::json!({ "trigger_id": trigger_id, "view": view }))
+            .send()
+            .await
+            .context("slack views.open request")?
+            .json()
+            .await
+            .context("slack views.open decode")?;
+
+        if !resp.ok {
+            anyhow::bail!(
+                "slack views.open failed: {}",
+                resp.error.unwrap_or_else(|| "unknown_error".to_string())
+            );
+        }
+        Ok(())
+    }
+
+    /// Publish the App Home tab for `user_id` (replaces whatever it showed before).
+    pub async fn views_publish(
+        &self,
+        user_id: &str,
+        view: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        self.ensure_writable("views.publish")?;
+        let resp: SlackApiResponse<serde_json::Value> = self
+            .http
+            .post("https://slack.com/api/views.publish")
+            .headers(self.headers())
+            .json(&serde_json::json!({ "user_id": user_id, "view": view }))
+            .send()
+            .await
+            .context("slack views.publish request")?
+            .json()
+            .await
+            .context("slack views.publish decode")?;
+
+        if !resp.ok {
+            anyhow::bail!(
+                "slack views.publish failed: {}",
+                resp.error.unwrap_or_else(|| "unknown_error".to_string())
+            );
+        }
+        Ok(())
+    }
+
+    /// Create a standalone canvas from Markdown and return its id (`canvases:write`).
+    pub async fn canvases_create(&self, title: &str, markdown: &str) -> anyhow::Result<String> {
+        self.ensure_writable("canvases.create")?;
+        let resp: SlackApiResponse<serde_json::Value> = self
+            .http
+            .post("https://slack.com/api/canvases.create")
+            .headers(self.headers())
+            .json(&serde_json::json!({
+                "title": title,
+                "document_content": { "type": "markdown", "markdown": markdown },
+            }))
+            .send()
+            .await
+            .context("slack canvases.create request")?
+            .json()
+            .await
+            .context("slack canvases.create decode")?;
+
+        if !resp.ok {
+            anyhow::bail!(
+                "slack canvases.create failed: {}",
+                resp.error.unwrap_or_else(|| "unknown_error".to_string())
+            );
+        }
+        resp.data
+            .as_ref()
+            .and_then(|d| d.get("canvas_id"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .context("slack canvases.create returned no canvas_id")
+    }
+
+    /// Let members of `channel` read a canvas.
+    pub async fn canvases_grant_channel_read(
+        &self,
+        canvas_id: &str,
+        channel: &str,
+    ) -> anyhow::Result<()> {
+        self.ensure_writable("canvases.access.set")?;
+        let resp: SlackApiResponse<serde_json::Value> = self
+            .http
+            .post("https://slack.com/api/canvases.access.set")
+            .headers(self.headers())
+            .json(&serde_json::json!({
+                "canvas_id": canvas_id,
+                "access_level": "read",
+                "channel_ids": [channel],
+            }))
+            .send()
+            .await
+            .context("slack canvases.access.set request")?
+            .json()
+            .await
+            .context("slack canvases.access.set decode")?;
+
+        if !resp.ok {
+            anyhow::bail!(
+                "slack canvases.access.set failed: {}",
+                resp.error.unwrap_or_else(|| "unknown_error".to_string())
+            );
+        }
+        Ok(())
+    }
+
+    /// Delete a canvas this app created (`canvases:write`).
+    pub async fn canvases_delete(&self, canvas_id: &str) -> anyhow::Result<()> {
+        let resp: SlackApiResponse<serde_json::Value> = self
+            .http
+            .post("https://slack.com/api/canvases.delete")
+            .headers(self.headers())
+            .json(&serde_json::json!({ "canvas_id": canvas_id }))
+            .send()
+            .await
+            .context("slack canvases.delete request")?
+            .json()
+            .await
+            .context("slack canvases.delete decode")?;
+
+        if !resp.ok {
+            anyhow::bail!(
+                "slack canvases.delete failed: {}",
+                resp.error.unwrap_or_else(|| "unknown_error".to_string())
+            );
+        }
+        Ok(())
+    }
+
+    /// Permalink of a file (canvases are files too).
+    pub async fn file_permalink(&self, file_id: &str) -> anyhow::Result<String> {
+        let resp: SlackApiResponse<serde_json::Value> = self
+            .http
+            .get("https://slack.com/api/files.info")
+            .headers(self.headers())
+            .query(&[("file", file_id)])
+            .send()
+            .await
+            .context("slack files.info request")?
+            .json()
+            .await
+            .context("slack files.info decode")?;
+
+        if !resp.ok {
+            anyhow::bail!(
+                "slack files.info failed: {}",
+                resp.error.unwrap_or_else(|| "unknown_error".to_string())
+            );
+        }
+        resp.data
+            .as_ref()
+            .and_then(|d| d.pointer("/file/permalink"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .context("slack files.info returned no permalink")
+    }
+
+    /// Permalink of a message (`chat.getPermalink`).
+    pub async fn message_permalink(&self, channel: &str, ts: &str) -> anyhow::Result<String> {
+        let resp: SlackApiResponse<serde_json::Value> = self
+            .http
+            .get("https://slack.com/api/chat.getPermalink")
+            .headers(self.headers())
+            .query(&[("channel", channel), ("message_ts", ts)])
+            .send()
+            .await
+            .context("slack chat.getPermalink request")?
+            .json()
+            .await
+            .context("slack chat.getPermalink decode")?;
+
+        if !resp.ok {
+            anyhow::bail!(
+                "slack chat.getPermalink failed: {}",
+                resp.error.unwrap_or_else(|| "unknown_error".to_string())
+            );
+        }
+        resp.data
+            .as_ref()
+            .and_then(|d| d.get("permalink"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+            .context("slack chat.getPermalink returned no permalink")
+    }
+
     pub async fn fetch_channel_history(
         &self,
         channel: &str,
         latest: &str,
         limit: i64,
     ) -> anyhow::Result<Vec<SlackMessage>> {
+        self.ensure_writable("canvases.delete")?;
         let resp: SlackApiResponse<HistoryResponse> = self
             .http
             .get("https://slack.com/api/conversations.history")
@@ -295,6 +642,35 @@ impl SlackClient {
         }
         Ok(resp.data.map(|d| d.messages).unwrap_or_default())
     }
+
+    /// Messages pinned in a channel (pins.list). Non-message pins are skipped.
+    pub async fn list_pins(&self, channel: &str) -> anyhow::Result<Vec<SlackMessage>> {
+        let resp: SlackApiResponse<PinsResponse> = self
+            .http
+            .get("https://slack.com/api/pins.list")
+            .headers(self.headers())
+            .query(&[("channel", channel)])
+            .send()
+            .await
+            .context("slack pins.list request")?
+            .json()
+            .await
+            .context("slack pins.list decode")?;
+
+        if !resp.ok {
+            anyhow::bail!(
+                "slack pins.list failed: {}",
+                resp.error.unwrap_or_else(|| "unknown_error".to_string())
+            );
+        }
+        Ok(resp
+            .data
+            .map(|d| d.items)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|i| i.message)
+            .collect())
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -315,6 +691,18 @@ struct RepliesResponse {
     messages: Vec<SlackMessage>,
 }
 
+#[derive(Debug, Deserialize)]
+struct PinsResponse {
+    #[serde(default)]
+    items: Vec<PinnedItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PinnedItem {
+    #[serde(default)]
+    message: Option<SlackMessage>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct SlackMessage {
     pub ts: String,
@@ -371,6 +759,54 @@ impl SlackClient {
         Ok(())
     }
 
+    /// Fetch a Slack-hosted text file, keeping at most `max_bytes` (lossy UTF-8).
+    pub async fn fetch_file_text(&self, url: &str, max_bytes: usize) -> anyhow::Result<String> {
+        let resp = self
+            .http
+            .get(url)
+            .header(AUTHORIZATION, format!("Bearer {}", self.bot_token))
+            .send()
+            .await
+            .context("slack file fetch request")?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("slack file fetch failed with status {}", resp.status());
+        }
+
+        let bytes = resp.bytes().await.context("read file bytes")?;
+        let end = bytes.len().min(max_bytes);
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+
+    /// Fetch a Slack-hosted file, failing once it is larger than `max_bytes` (checked
+    /// against `Content-Length` before anything is read).
+    pub async fn fetch_file_bytes(&self, url: &str, max_bytes: usize) -> anyhow::Result<Vec<u8>> {
+        let mut resp = self
+            .http
+            .get(url)
+            .header(AUTHORIZATION, format!("Bearer {}", self.bot_token))
+            .send()
+            .await
+            .context("slack file fetch request")?;
+
+        if !resp.status().is_success() {
+            anyhow::bail!("slack file fetch failed with status {}", resp.status());
+        }
+        if resp.content_length().is_some_and(|n| n > max_bytes as u64) {
+            anyhow::bail!("slack file is larger than {max_bytes} bytes");
+        }
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = resp.chunk().await.context("read file bytes")? {
+            anyhow::ensure!(
+                bytes.len() + chunk.len() <= max_bytes,
+                "slack file is larger than {max_bytes} bytes"
+            );
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes)
+    }
+
     /// Upload file content to a Slack channel/thread using files.uploadV2 flow:
     /// 1. files.getUploadURLExternal
     /// 2. PUT content to the upload URL
@@ -382,6 +818,7 @@ impl SlackClient {
         filename: &str,
         content: &[u8],
     ) -> anyhow::Result<()> {
+        self.ensure_writable("files.uploadV2")?;
         // Step 1: Get upload URL
         #[derive(Deserialize)]
         struct UploadUrlResp {
@@ -485,41 +922,71 @@ impl SlackClient {
     }
 }
 
-fn split_slack_text(text: &str, max_bytes: usize) -> Vec<String> {
-    let t = text.trim();
-    if t.is_empty() {
-        return vec!["(empty)".to_string()];
-    }
-    if t.len() <= max_bytes {
-        return vec![t.to_string()];
+fn split_slack_text(text: &str, max_chars: usize) -> Vec<String> {
+    let chunks = grail_text::split(text, max_chars);
+    if chunks.is_empty() {
+        vec!["(empty)".to_string()]
+    } else {
+        chunks
     }
+}
 
-    let mut out = Vec::new();
-    let mut start = 0usize;
-    while start < t.len() {
-        let mut end = (start + max_bytes).min(t.len());
-        while end > start && !t.is_char_boundary(end) {
-            end -= 1;
-        }
-        if end == start {
-            break;
-        }
-
-        // Prefer splitting at a newline, but don't create tiny chunks.
-        if let Some(pos) = t[start..end].rfind('\n') {
-            let candidate = start + pos + 1;
-            if candidate > start + (max_bytes / 2) {
-                end = candidate;
-            }
-        }
-
-        out.push(t[start..end].trim().to_string());
-        start = end;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slack_guard_rejects_replays_and_stale_timestamps() {
+        use hmac::Mac;
+        let sign = |ts: i64, body: &str| {
+            let mut mac = hmac::Hmac::<sha2::Sha256>::new_from_slice(b"secret").unwrap();
+            mac.update(format!("v0:{ts}:{body}").as_bytes());
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                "X-Slack-Request-Timestamp",
+                HeaderValue::from_str(&ts.to_string()).unwrap(),
+            );
+            headers.insert(
+                "X-Slack-Signature",
+                HeaderValue::from_str(&format!("v0={}", hex::encode(mac.finalize().into_bytes())))
+                    .unwrap(),
+            );
+            headers
+        };
+        let guard = SlackRequestGuard::default();
+        let now = chrono::Utc::now().timestamp();
+        let body = Bytes::from_static(b"payload");
+        let headers = sign(now, "payload");
+        assert!(guard.verify("secret", &headers, &body).is_ok());
+        assert!(matches!(
+            guard.verify("secret", &headers, &body),
+            Err(SlackSignatureError::Replayed)
+        ));
+        assert!(matches!(
+            guard.verify("secret", &sign(now - 600, "payload"), &body),
+            Err(SlackSignatureError::TimestampTooOld)
+        ));
+        assert!(matches!(
+            guard.verify("secret", &sign(now, "other"), &body),
+            Err(SlackSignatureError::SignatureMismatch)
+        ));
+        let m = guard.metrics();
+        assert_eq!(m["verified"], 1);
+        assert_eq!(m["replayed"], 1);
+        assert_eq!(m["timestamp_skew"], 1);
+        assert_eq!(m["signature_mismatch"], 1);
     }
 
-    if out.is_empty() {
-        vec![t.chars().take(1000).collect()]
-    } else {
-        out
+    #[tokio::test]
+    async fn read_only_slack_client_refuses_writes() {
+        // Refused before any request is sent, so no Slack is needed.
+        let slack = SlackClient::read_only(reqwest::Client::new(), "xoxb-test".into());
+        let err = slack.post_message("C1", None, "hello").await.unwrap_err();
+        assert!(err.to_string().contains("read-only"), "{err}");
+        assert!(slack.canvases_create("t", "body").await.is_err());
+        assert!(slack
+            .upload_file_content("C1", None, "a.txt", b"x")
+            .await
+            .is_err());
     }
 }
@@ -0,0 +1,370 @@
+//! Slack App Home tab.
+//!
+//! On `app_home_opened` we publish a personal dashboard for the user: their recent tasks,
+//! pending approvals for tasks they asked for (with Approve/Deny buttons), and quick
+//! actions to start a new task (the ask modal, in their DM with the app) or list their
+//! reminders (enabled cron jobs they asked for). The tab is republished after each action.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+
+use crate::db;
+use crate::models::{Approval, CronJob, Task};
+use crate::slack::SlackClient;
+use crate::AppState;
+
+const RECENT_TASKS: i64 = 10;
+const PENDING_APPROVALS: i64 = 5;
+const REMINDERS: i64 = 20;
+const PREVIEW_CHARS: usize = 150;
+
+const NEW_TASK_ACTION: &str = "grail_home_new_task";
+const REMINDERS_ACTION: &str = "grail_home_reminders";
+const APPROVE_ACTION: &str = "grail_home_approve";
+const DENY_ACTION: &str = "grail_home_deny";
+
+/// `&`, `<` and `>` are control characters in Slack mrkdwn.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn preview(text: &str, max: usize) -> String {
+    escape(&grail_text::truncate_line(text, max))
+}
+
+/// Slack renders this in the viewer's timezone; the fallback is UTC.
+fn slack_date(ts: i64) -> String {
+    let fallback = chrono::DateTime::from_timestamp(ts, 0)
+        .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default();
+    format!("<!date^{ts}^{{date_short_pretty}} {{time}}|{fallback}>")
+}
+
+/// One line describing what an approval is for.
+pub fn approval_summary(a: &Approval) -> String {
+    let details: serde_json::Value = serde_json::from_str(&a.details_json).unwrap_or_default();
+    let field = |k: &str| details.get(k).and_then(|v| v.as_str()).unwrap_or("");
+    match a.kind.as_str() {
+        "command_execution" => format!("Run `{}`", preview(field("command"), PREVIEW_CHARS)),
+        "guardrail_rule_add" => format!("Add guardrail rule *{}*", preview(field("name"), 80)),
+        "cron_job_add" => format!("Schedule *{}*", preview(field("name"), 80)),
+        other => format!("Approve {}", escape(other)),
+    }
+}
+
+fn button(text: &str, action_id: &str, value: &str, style: Option<&str>) -> serde_json::Value {
+    let mut b = json!({
+        "type": "button",
+        "text": { "type": "plain_text", "text": text },
+        "action_id": action_id,
+        "value": value,
+    });
+    if let Some(style) = style {
+        b["style"] = json!(style);
+    }
+    b
+}
+
+fn mrkdwn_section(text: String) -> serde_json::Value {
+    json!({ "type": "section", "text": { "type": "mrkdwn", "text": text } })
+}
+
+fn context(text: &str) -> serde_json::Value {
+    json!({ "type": "context", "elements": [{ "type": "mrkdwn", "text": text }] })
+}
+
+pub fn home_view(
+    agent_name: &str,
+    dm_channel: &str,
+    tasks: &[Task],
+    approvals: &[Approval],
+) -> serde_json::Value {
+    let mut blocks = vec![
+        json!({ "type": "header", "text": { "type": "plain_text", "text": agent_name } }),
+        json!({
+            "type": "actions",
+            "elements": [
+                button("New task", NEW_TASK_ACTION, dm_channel, Some("primary")),
+                button("My reminders", REMINDERS_ACTION, dm_channel, None),
+            ],
+        }),
+        json!({ "type": "divider" }),
+        mrkdwn_section("*Waiting for your approval*".to_string()),
+    ];
+    if approvals.is_empty() {
+        blocks.push(context("Nothing is waiting for you."));
+    }
+    for a in approvals {
+        let place = a
+            .channel_id
+            .as_deref()
+            .filter(|c| !c.is_empty())
+            .map(|c| format!(" in <#{c}>"))
+            .unwrap_or_default();
+        blocks.push(mrkdwn_section(format!(
+            "{}\nRequested{place} {}",
+            approval_summary(a),
+            slack_date(a.created_at)
+        )));
+        blocks.push(json!({
+            "type": "actions",
+            "elements": [
+                button("Approve", APPROVE_ACTION, &a.id, Some("primary")),
+                button("Deny", DENY_ACTION, &a.id, Some("danger")),
+            ],
+        }));
+    }
+
+    blocks.push(json!({ "type": "divider" }));
+    blocks.push(mrkdwn_section("*Your recent tasks*".to_string()));
+    if tasks.is_empty() {
+        blocks.push(context(
+            "No tasks yet. Mention me in a channel, message me here, or use *New task*.",
+        ));
+    }
+    for t in tasks {
+        blocks.push(mrkdwn_section(format!(
+            "*#{}* `{}` in <#{}> · {}\n>{}",
+            t.id,
+            t.status,
+            t.channel_id,
+            slack_date(t.created_at),
+            preview(&t.prompt_text, PREVIEW_CHARS)
+        )));
+    }
+
+    // Actions taken on the tab republish it; they find the DM channel here.
+    json!({ "type": "home", "private_metadata": dm_channel, "blocks": blocks })
+}
+
+pub fn reminders_view(jobs: &[CronJob]) -> serde_json::Value {
+    let mut blocks = Vec::new();
+    if jobs.is_empty() {
+        blocks.push(mrkdwn_section(
+            "You have no reminders. Ask me to remind you about something to set one up."
+                .to_string(),
+        ));
+    }
+    for j in jobs {
+        let schedule = match j.schedule_kind.as_str() {
+            "every" => format!("every {}s", j.every_seconds.unwrap_or_default()),
+            "cron" => format!("`{}`", escape(j.cron_expr.as_deref().unwrap_or(""))),
+            _ => "once".to_string(),
+        };
+        let next = j
+            .next_run_at
+            .map(|t| format!(" · next {}", slack_date(t)))
+            .unwrap_or_default();
+        blocks.push(mrkdwn_section(format!(
+            "*{}* in <#{}>\n{schedule}{next}\n>{}",
+            preview(&j.name, 80),
+            j.channel_id,
+            preview(&j.prompt_text, PREVIEW_CHARS)
+        )));
+    }
+    json!({
+        "type": "modal",
+        "title": { "type": "plain_text", "text": "My reminders" },
+        "close": { "type": "plain_text", "text": "Close" },
+        "blocks": blocks,
+    })
+}
+
+async fn slack_client(state: &AppState) -> Option<SlackClient> {
+    match crate::secrets::load_slack_bot_token_opt(state).await {
+        Ok(Some(token)) => Some(SlackClient::new(state.http.clone(), token)),
+        Ok(None) => {
+            warn!("slack app home needs SLACK_BOT_TOKEN, which is not configured");
+            None
+        }
+        Err(err) => {
+            warn!(error = %err, "failed to load slack bot token");
+            None
+        }
+    }
+}
+
+/// Build and publish the Home tab for `user_id`.
+pub async fn publish(state: &AppState, user_id: &str, dm_channel: &str) -> anyhow::Result<()> {
+    let Some(slack) = slack_client(state).await else {
+        return Ok(());
+    };
+    let settings = db::get_settings(&state.pool).await?;
+    let tasks = db::list_user_tasks(&state.pool, user_id, RECENT_TASKS).await?;
+    let approvals =
+        db::list_pending_approvals_for_user(&state.pool, user_id, PENDING_APPROVALS).await?;
+    slack
+        .views_publish(
+            user_id,
+            home_view(&settings.agent_name, dm_channel, &tasks, &approvals),
+        )
+        .await
+}
+
+#[derive(Debug, Deserialize)]
+struct IdOnly {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HomeAction {
+    action_id: String,
+    #[serde(default)]
+    value: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct HomeViewRef {
+    #[serde(default)]
+    private_metadata: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HomeActionPayload {
+    trigger_id: String,
+    user: IdOnly,
+    #[serde(default)]
+    team: Option<IdOnly>,
+    #[serde(default)]
+    view: HomeViewRef,
+    actions: Vec<HomeAction>,
+}
+
+/// `block_actions` from the Home tab. The caller has already checked the workspace and
+/// user allow-lists.
+pub async fn handle_block_action(state: &AppState, payload: serde_json::Value) -> Response {
+    let ok = (StatusCode::OK, "").into_response();
+    let payload: HomeActionPayload = match serde_json::from_value(payload) {
+        Ok(v) => v,
+        Err(err) => {
+            warn!(error = %err, "invalid slack home action payload");
+            return ok;
+        }
+    };
+    let Some(action) = payload.actions.first() else {
+        return ok;
+    };
+    let user_id = payload.user.id.as_str();
+    let team_id = payload.team.map(|t| t.id).unwrap_or_default();
+
+    match action.action_id.as_str() {
+        NEW_TASK_ACTION if action.value.is_empty() => {
+            warn!("app home new task without a DM channel");
+        }
+        NEW_TASK_ACTION => {
+            crate::slack_modals::open_new_task_modal(
+                state,
+                &payload.trigger_id,
+                &team_id,
+                &action.value,
+            )
+            .await;
+        }
+        REMINDERS_ACTION => {
+            let jobs = match db::list_user_cron_jobs(&state.pool, user_id, REMINDERS).await {
+                Ok(j) => j,
+                Err(err) => {
+                    warn!(error = %err, "failed to list reminders for app home");
+                    return ok;
+                }
+            };
+            if let Some(slack) = slack_client(state).await {
+                if let Err(err) = slack
+                    .views_open(&payload.trigger_id, reminders_view(&jobs))
+                    .await
+                {
+                    warn!(error = %err, "failed to open reminders modal");
+                }
+            }
+        }
+        APPROVE_ACTION | DENY_ACTION => {
+            resolve_approval(state, user_id, &action.action_id, &action.value).await;
+            if let Err(err) = publish(state, user_id, &payload.view.private_metadata).await {
+                warn!(error = %err, "failed to republish slack app home");
+            }
+        }
+        other => warn!(action_id = other, "unknown slack home action_id"),
+    }
+    ok
+}
+
+/// Only the user the approval is addressed to can resolve it from their Home tab. The
+/// outcome is posted to the task's thread, as it would be for a click there.
+async fn resolve_approval(state: &AppState, user_id: &str, action_id: &str, approval_id: &str) {
+    let approval = match db::get_approval(&state.pool, approval_id).await {
+        Ok(Some(a)) if a.requested_by_user_id.as_deref() == Some(user_id) => a,
+        Ok(_) => {
+            warn!(%approval_id, user = %user_id, "app home approval not addressed to this user");
+            return;
+        }
+        Err(err) => {
+            warn!(error = %err, %approval_id, "failed to load approval for app home");
+            return;
+        }
+    };
+    let action = if action_id == APPROVE_ACTION {
+        "approve"
+    } else {
+        "deny"
+    };
+    let msg = match crate::approvals::handle_approval_command(state, action, approval_id).await {
+        Ok(m) => m,
+        Err(err) => {
+            warn!(error = %err, "failed to handle approval from app home");
+            None
+        }
+    };
+    if let (Some(text), Some(channel)) = (msg, approval.channel_id.as_deref()) {
+        if let Some(slack) = slack_client(state).await {
+            let thread_ts = approval.thread_ts.as_deref().filter(|t| !t.is_empty());
+            let _ = slack
+                .post_message(
+                    channel,
+                    thread_ts,
+                    &format!("<@{user_id}>: {}", text.trim()),
+                )
+                .await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slack_home_lists_approvals_with_buttons_and_escapes_text() {
+        let approval = crate::models::Approval {
+            id: "appr_1".to_string(),
+            kind: "command_execution".to_string(),
+            status: "pending".to_string(),
+            decision: None,
+            workspace_id: Some("T1".to_string()),
+            channel_id: Some("C1".to_string()),
+            thread_ts: Some("1.0".to_string()),
+            requested_by_user_id: Some("U1".to_string()),
+            details_json: serde_json::json!({ "command": "echo <!channel> && ls" }).to_string(),
+            created_at: 1_700_000_000,
+            updated_at: 1_700_000_000,
+            resolved_at: None,
+        };
+        assert_eq!(
+            approval_summary(&approval),
+            "Run `echo &lt;!channel&gt; &amp;&amp; ls`"
+        );
+
+        let view = home_view("Grail", "D1", &[], std::slice::from_ref(&approval));
+        assert_eq!(view["type"], "home");
+        assert_eq!(view["private_metadata"], "D1");
+        let text = view.to_string();
+        assert!(text.contains("grail_home_approve") && text.contains("grail_home_deny"));
+        assert!(text.contains(r#""value":"appr_1""#));
+        assert!(text.contains("<#C1>") && text.contains("No tasks yet"));
+        assert!(!text.contains("<!channel>"));
+    }
+}
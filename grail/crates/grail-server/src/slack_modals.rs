@@ -0,0 +1,592 @@
+//! Slack shortcuts, slash commands and modals.
+//!
+//! "Ask Grail" is a message shortcut: it opens a modal for the question and, on submit,
+//! enqueues a task in the message's thread with the message quoted as primary context.
+//! The `/grail` slash command opens the same modal without a source message; its task
+//! starts a new thread in the channel. Both modals offer per-task options (model,
+//! read-only permissions, extra context channels, deadline) stored as [`TaskOptions`].
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::db;
+use crate::models::{PermissionsMode, TaskOptions, MAX_CONTEXT_CHANNELS};
+use crate::slack::SlackClient;
+use crate::AppState;
+
+const ASK_SHORTCUT_CALLBACK_ID: &str = "grail_ask";
+const ASK_VIEW_CALLBACK_ID: &str = "grail_ask_modal";
+/// Slack caps `private_metadata` at 3000 characters.
+const MAX_QUOTED_CHARS: usize = 1_500;
+/// Slack static selects hold at most 100 options.
+const MAX_MODEL_OPTIONS: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct IdOnly {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct InteractionTeam {
+    id: String,
+    #[serde(default)]
+    enterprise_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ShortcutMessage {
+    ts: String,
+    #[serde(default)]
+    thread_ts: Option<String>,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    user: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageShortcutPayload {
+    callback_id: String,
+    trigger_id: String,
+    user: IdOnly,
+    channel: IdOnly,
+    #[serde(default)]
+    team: Option<InteractionTeam>,
+    message: ShortcutMessage,
+}
+
+/// State carried through the modal in `private_metadata`. `thread_ts` is empty when the
+/// modal was opened from the slash command (there is no source message).
+#[derive(Debug, Serialize, Deserialize)]
+struct AskMetadata {
+    team_id: String,
+    channel_id: String,
+    thread_ts: String,
+    message_user: String,
+    message_text: String,
+}
+
+/// Which options the modal offers, derived from the workspace policy.
+#[derive(Debug, Default)]
+struct ModalChoices {
+    /// The backend's `allowed_models`; no model picker when empty.
+    models: Vec<String>,
+    /// Read-only can only be chosen when the workspace runs with full permissions.
+    offer_permissions: bool,
+}
+
+impl ModalChoices {
+    async fn load(state: &AppState, workspace_id: &str) -> (String, Self) {
+        let Ok(settings) = db::get_settings(&state.pool).await else {
+            return ("Grail".to_string(), Self::default());
+        };
+        let backend = crate::llm::resolve_backend(&settings.llm_backends, workspace_id);
+        let models = backend
+            .allowed_models
+            .iter()
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+            .take(MAX_MODEL_OPTIONS)
+            .collect();
+        let choices = Self {
+            models,
+            offer_permissions: settings.permissions_mode == PermissionsMode::Full,
+        };
+        (settings.agent_name, choices)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ViewSubmissionPayload {
+    user: IdOnly,
+    #[serde(default)]
+    team: Option<InteractionTeam>,
+    view: SubmittedView,
+}
+
+#[derive(Debug, Deserialize)]
+struct SubmittedView {
+    callback_id: String,
+    #[serde(default)]
+    private_metadata: String,
+    state: ViewState,
+}
+
+#[derive(Debug, Deserialize)]
+struct ViewState {
+    values: serde_json::Value,
+}
+
+fn ok() -> Response {
+    (StatusCode::OK, "").into_response()
+}
+
+fn team_ids(team: Option<&InteractionTeam>) -> (String, Option<String>) {
+    let team_id = team.map(|t| t.id.clone()).unwrap_or_default();
+    let enterprise_id = team
+        .and_then(|t| t.enterprise_id.clone())
+        .filter(|e| !e.trim().is_empty());
+    (team_id, enterprise_id)
+}
+
+fn clamp_chars(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        let mut out: String = s.chars().take(max.saturating_sub(1)).collect();
+        out.push('…');
+        out
+    }
+}
+
+fn plain_option(text: &str, value: &str) -> serde_json::Value {
+    json!({ "text": { "type": "plain_text", "text": clamp_chars(text, 75) }, "value": value })
+}
+
+fn ask_view(
+    agent_name: &str,
+    meta: &AskMetadata,
+    choices: &ModalChoices,
+    initial_question: &str,
+) -> serde_json::Value {
+    let mut blocks = Vec::new();
+    if !meta.thread_ts.is_empty() {
+        let quoted = meta
+            .message_text
+            .lines()
+            .map(|l| format!("> {l}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let message = if quoted.is_empty() {
+            "(no text)".to_string()
+        } else {
+            quoted
+        };
+        blocks.push(json!({
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": clamp_chars(&format!("*Message*\n{message}"), 2_900),
+            },
+        }));
+    }
+    let mut question = json!({
+        "type": "plain_text_input",
+        "action_id": "value",
+        "multiline": true,
+        "placeholder": { "type": "plain_text", "text": "e.g. Summarize this and list the action items" },
+    });
+    if !initial_question.trim().is_empty() {
+        question["initial_value"] = json!(clamp_chars(initial_question.trim(), 3_000));
+    }
+    blocks.push(json!({
+        "type": "input",
+        "block_id": "question",
+        "label": { "type": "plain_text", "text": "Your question" },
+        "element": question,
+    }));
+
+    if !choices.models.is_empty() {
+        let options: Vec<_> = choices.models.iter().map(|m| plain_option(m, m)).collect();
+        blocks.push(json!({
+            "type": "input",
+            "block_id": "model",
+            "optional": true,
+            "label": { "type": "plain_text", "text": "Model" },
+            "hint": { "type": "plain_text", "text": "Leave empty to use the workspace default." },
+            "element": { "type": "static_select", "action_id": "value", "options": options },
+        }));
+    }
+    if choices.offer_permissions {
+        let full = plain_option("Full (workspace default)", "full");
+        blocks.push(json!({
+            "type": "input",
+            "block_id": "permissions",
+            "label": { "type": "plain_text", "text": "Permissions" },
+            "element": {
+                "type": "static_select",
+                "action_id": "value",
+                "initial_option": full,
+                "options": [full, plain_option("Read-only", "read")],
+            },
+        }));
+    }
+    blocks.push(json!({
+        "type": "input",
+        "block_id": "channels",
+        "optional": true,
+        "label": { "type": "plain_text", "text": "Context channels" },
+        "hint": {
+            "type": "plain_text",
+            "text": format!("Recent history from up to {MAX_CONTEXT_CHANNELS} channels is added as context."),
+        },
+        "element": {
+            "type": "multi_conversations_select",
+            "action_id": "value",
+            "max_selected_items": MAX_CONTEXT_CHANNELS,
+            "filter": { "include": ["public", "private"], "exclude_bot_users": true },
+        },
+    }));
+    blocks.push(json!({
+        "type": "input",
+        "block_id": "deadline",
+        "optional": true,
+        "label": { "type": "plain_text", "text": "Deadline" },
+        "hint": { "type": "plain_text", "text": "The run is stopped if it is still going at this time." },
+        "element": { "type": "datetimepicker", "action_id": "value" },
+    }));
+
+    json!({
+        "type": "modal",
+        "callback_id": ASK_VIEW_CALLBACK_ID,
+        "private_metadata": serde_json::to_string(meta).unwrap_or_default(),
+        "title": { "type": "plain_text", "text": clamp_chars(&format!("Ask {agent_name}"), 24) },
+        "submit": { "type": "plain_text", "text": "Ask" },
+        "close": { "type": "plain_text", "text": "Cancel" },
+        "blocks": blocks,
+    })
+}
+
+/// Read the options block values, re-checking them against the current policy.
+/// Errors are keyed by block_id for a `response_action: errors` reply.
+fn read_task_options(
+    values: &serde_json::Value,
+    choices: &ModalChoices,
+    now: i64,
+) -> Result<TaskOptions, (&'static str, &'static str)> {
+    let selected = |block: &str| {
+        values
+            .pointer(&format!("/{block}/value/selected_option/value"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+    let model = selected("model");
+    if model.as_ref().is_some_and(|m| !choices.models.contains(m)) {
+        return Err(("model", "That model is not allowed for this workspace."));
+    }
+    let permissions_mode = match selected("permissions").as_deref() {
+        Some("read") => Some(PermissionsMode::Read),
+        _ => None,
+    };
+    let context_channels = values
+        .pointer("/channels/value/selected_conversations")
+        .and_then(|v| v.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|c| c.as_str())
+                .take(MAX_CONTEXT_CHANNELS)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    let deadline_at = values
+        .pointer("/deadline/value/selected_date_time")
+        .and_then(|v| v.as_i64());
+    if deadline_at.is_some_and(|d| d <= now) {
+        return Err(("deadline", "Pick a time in the future."));
+    }
+    Ok(TaskOptions {
+        model,
+        permissions_mode,
+        context_channels,
+        deadline_at,
+        timeout_seconds: None,
+        output_schema: None,
+    })
+}
+
+fn modal_error(block_id: &str, message: &str) -> Response {
+    (
+        StatusCode::OK,
+        axum::Json(json!({
+            "response_action": "errors",
+            "errors": { block_id: message },
+        })),
+    )
+        .into_response()
+}
+
+async fn open_ask_modal(
+    state: &AppState,
+    trigger_id: &str,
+    meta: &AskMetadata,
+    initial_question: &str,
+) {
+    let (agent_name, choices) = ModalChoices::load(state, &meta.team_id).await;
+    let token = match crate::secrets::load_slack_bot_token_opt(state).await {
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            warn!("slack interaction received but SLACK_BOT_TOKEN is not configured");
+            return;
+        }
+        Err(err) => {
+            warn!(error = %err, "failed to load slack bot token");
+            return;
+        }
+    };
+    let slack = SlackClient::new(state.http.clone(), token);
+    if let Err(err) = slack
+        .views_open(
+            trigger_id,
+            ask_view(&agent_name, meta, &choices, initial_question),
+        )
+        .await
+    {
+        warn!(error = %err, "failed to open ask modal");
+    }
+}
+
+/// `message_action` payload: open the question modal.
+pub async fn handle_message_shortcut(state: &AppState, payload: serde_json::Value) -> Response {
+    let payload: MessageShortcutPayload = match serde_json::from_value(payload) {
+        Ok(v) => v,
+        Err(err) => {
+            warn!(error = %err, "invalid slack message shortcut payload");
+            return ok();
+        }
+    };
+    if payload.callback_id != ASK_SHORTCUT_CALLBACK_ID {
+        warn!(callback_id = %payload.callback_id, "unknown slack shortcut callback_id");
+        return ok();
+    }
+    let (team_id, enterprise_id) = team_ids(payload.team.as_ref());
+    if !crate::slack_interaction_allowed(
+        state,
+        &team_id,
+        enterprise_id.as_deref(),
+        &payload.user.id,
+        &payload.channel.id,
+    )
+    .await
+    {
+        return ok();
+    }
+
+    let meta = AskMetadata {
+        team_id,
+        channel_id: payload.channel.id.clone(),
+        thread_ts: payload
+            .message
+            .thread_ts
+            .clone()
+            .unwrap_or_else(|| payload.message.ts.clone()),
+        message_user: payload.message.user.clone().unwrap_or_default(),
+        message_text: clamp_chars(payload.message.text.trim(), MAX_QUOTED_CHARS),
+    };
+    open_ask_modal(state, &payload.trigger_id, &meta, "").await;
+    ok()
+}
+
+/// `/grail [question]`: open the modal with the question prefilled. The form has already
+/// passed signature verification.
+pub async fn handle_slash_command(
+    state: &AppState,
+    form: &std::collections::HashMap<String, String>,
+) -> Response {
+    let field = |k: &str| {
+        form.get(k)
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default()
+    };
+    let (trigger_id, user_id, channel_id) =
+        (field("trigger_id"), field("user_id"), field("channel_id"));
+    if trigger_id.is_empty() || user_id.is_empty() || channel_id.is_empty() {
+        warn!("slack slash command missing trigger_id, user_id or channel_id");
+        return ok();
+    }
+    let team_id = field("team_id");
+    let enterprise_id = Some(field("enterprise_id")).filter(|e| !e.is_empty());
+    if !crate::slack_interaction_allowed(
+        state,
+        &team_id,
+        enterprise_id.as_deref(),
+        &user_id,
+        &channel_id,
+    )
+    .await
+    {
+        return ok();
+    }
+
+    let meta = AskMetadata {
+        team_id,
+        channel_id,
+        thread_ts: String::new(),
+        message_user: String::new(),
+        message_text: String::new(),
+    };
+    open_ask_modal(state, &trigger_id, &meta, &field("text")).await;
+    ok()
+}
+
+/// "New task" from the App Home: the same modal, starting a thread in the user's DM with
+/// the app.
+pub async fn open_new_task_modal(
+    state: &AppState,
+    trigger_id: &str,
+    team_id: &str,
+    dm_channel: &str,
+) {
+    let meta = AskMetadata {
+        team_id: team_id.to_string(),
+        channel_id: dm_channel.to_string(),
+        thread_ts: String::new(),
+        message_user: String::new(),
+        message_text: String::new(),
+    };
+    open_ask_modal(state, trigger_id, &meta, "").await;
+}
+
+/// `view_submission` payload: enqueue the task and close the modal.
+pub async fn handle_view_submission(state: &AppState, payload: serde_json::Value) -> Response {
+    let payload: ViewSubmissionPayload = match serde_json::from_value(payload) {
+        Ok(v) => v,
+        Err(err) => {
+            warn!(error = %err, "invalid slack view submission payload");
+            return ok();
+        }
+    };
+    if payload.view.callback_id != ASK_VIEW_CALLBACK_ID {
+        return ok();
+    }
+    let meta: AskMetadata = match serde_json::from_str(&payload.view.private_metadata) {
+        Ok(v) => v,
+        Err(err) => {
+            warn!(error = %err, "invalid ask modal metadata");
+            return ok();
+        }
+    };
+    let (team_id, enterprise_id) = team_ids(payload.team.as_ref());
+    if !crate::slack_interaction_allowed(
+        state,
+        &team_id,
+        enterprise_id.as_deref(),
+        &payload.user.id,
+        &meta.channel_id,
+    )
+    .await
+    {
+        return ok();
+    }
+
+    let question = payload
+        .view
+        .state
+        .values
+        .pointer("/question/value/value")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .trim()
+        .to_string();
+    if question.is_empty() {
+        return modal_error("question", "Please enter a question.");
+    }
+    let workspace_id = if meta.team_id.is_empty() {
+        team_id
+    } else {
+        meta.team_id.clone()
+    };
+    let (_, choices) = ModalChoices::load(state, &workspace_id).await;
+    let options = match read_task_options(
+        &payload.view.state.values,
+        &choices,
+        chrono::Utc::now().timestamp(),
+    ) {
+        Ok(o) => o,
+        Err((block_id, message)) => return modal_error(block_id, message),
+    };
+    let options_json = if options == TaskOptions::default() {
+        String::new()
+    } else {
+        serde_json::to_string(&options).unwrap_or_default()
+    };
+
+    let token = match crate::secrets::load_slack_bot_token_opt(state).await {
+        Ok(Some(t)) => t,
+        _ => {
+            warn!("ask modal submitted but SLACK_BOT_TOKEN is not available");
+            return ok();
+        }
+    };
+    let slack = SlackClient::new(state.http.clone(), token);
+    // Show the question in the channel so the reply has visible context. Without a source
+    // message (slash command) it starts the thread the task replies in.
+    let text = format!(
+        "<@{}> asked: {}",
+        payload.user.id,
+        clamp_chars(&question, 2_000)
+    );
+    let thread_ts = if meta.thread_ts.is_empty() {
+        match slack.post_message_ts(&meta.channel_id, None, &text).await {
+            Ok(ts) => ts,
+            Err(err) => {
+                warn!(error = %err, channel_id = %meta.channel_id, "failed to post ask modal question");
+                return modal_error(
+                    "question",
+                    "I can't post in this channel. Invite me to it and try again.",
+                );
+            }
+        }
+    } else {
+        if let Err(err) = slack
+            .post_message(&meta.channel_id, Some(meta.thread_ts.as_str()), &text)
+            .await
+        {
+            warn!(error = %err, "failed to post ask modal question");
+        }
+        meta.thread_ts.clone()
+    };
+
+    let prompt = compose_ask_prompt(&question, &meta);
+    let task_id = match db::enqueue_modal_task(
+        &state.pool,
+        &workspace_id,
+        &meta.channel_id,
+        &thread_ts,
+        &payload.user.id,
+        &prompt,
+        &options_json,
+    )
+    .await
+    {
+        Ok(id) => id,
+        Err(err) => {
+            warn!(error = %err, "failed to enqueue task from ask modal");
+            return ok();
+        }
+    };
+    state.task_notify.notify_waiters();
+    info!(
+        task_id,
+        channel_id = %meta.channel_id,
+        thread_ts = %thread_ts,
+        requested_by = %payload.user.id,
+        options = %options_json,
+        "enqueued slack task from modal"
+    );
+    ok()
+}
+
+fn compose_ask_prompt(question: &str, meta: &AskMetadata) -> String {
+    if meta.thread_ts.is_empty() {
+        return question.to_string();
+    }
+    let author = if meta.message_user.is_empty() {
+        "someone".to_string()
+    } else {
+        format!("<@{}>", meta.message_user)
+    };
+    let quoted = meta
+        .message_text
+        .lines()
+        .map(|l| format!("> {l}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "{question}\n\n(Asked via the message shortcut about this message from {author}; \
+treat it and its thread as the primary context.)\n{quoted}"
+    )
+}
@@ -0,0 +1,161 @@
+//! Publish long Slack replies out of the thread.
+//!
+//! With `slack_long_reply_mode` set to `canvas` or `file`, replies longer than
+//! `slack_long_reply_chars` are published as a canvas (or an uploaded Markdown file) and
+//! the thread gets a short summary that links to it. Canvases need the `canvases:write`
+//! scope and a paid workspace, so canvas failures fall back to a file upload; a canvas that
+//! was created but never announced is deleted again.
+
+use tracing::warn;
+
+use crate::models::{Settings, Task};
+use crate::slack::SlackClient;
+
+/// Length of the in-thread summary.
+const SUMMARY_CHARS: usize = 600;
+
+/// Leading paragraphs of `reply`, up to [`SUMMARY_CHARS`].
+pub fn summarize(reply: &str) -> String {
+    let mut out = String::new();
+    for para in reply.trim().split("\n\n") {
+        let para = para.trim();
+        if para.is_empty() {
+            continue;
+        }
+        if !out.is_empty() && out.chars().count() + para.chars().count() > SUMMARY_CHARS {
+            break;
+        }
+        if !out.is_empty() {
+            out.push_str("\n\n");
+        }
+        out.push_str(para);
+        if out.chars().count() >= SUMMARY_CHARS / 2 {
+            break;
+        }
+    }
+    grail_text::truncate(&out, SUMMARY_CHARS)
+}
+
+fn title_for(task: &Task) -> String {
+    let first_line = task
+        .prompt_text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("");
+    if first_line.is_empty() {
+        format!("Task #{}", task.id)
+    } else {
+        format!(
+            "Task #{}: {}",
+            task.id,
+            grail_text::truncate_line(first_line, 80)
+        )
+    }
+}
+
+/// What [`post_long_reply`] put in the thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LongReply {
+    /// Nothing: the reply is short enough or the setting is off. Post it normally.
+    Inline,
+    /// The summary and a link to (or attachment of) the full reply.
+    Published,
+    /// Only the summary; the full reply could not be attached. Don't post the reply again.
+    SummaryOnly,
+}
+
+/// Create the canvas and share it with the channel. A canvas that can't be shared is
+/// deleted again so it doesn't linger unreachable.
+async fn publish_canvas(
+    slack: &SlackClient,
+    task: &Task,
+    reply: &str,
+) -> anyhow::Result<(String, String)> {
+    let canvas_id = slack.canvases_create(&title_for(task), reply).await?;
+    let shared = match slack
+        .canvases_grant_channel_read(&canvas_id, &task.channel_id)
+        .await
+    {
+        Ok(()) => slack.file_permalink(&canvas_id).await,
+        Err(err) => Err(err),
+    };
+    match shared {
+        Ok(link) => Ok((canvas_id, link)),
+        Err(err) => {
+            delete_canvas(slack, task, &canvas_id).await;
+            Err(err)
+        }
+    }
+}
+
+async fn delete_canvas(slack: &SlackClient, task: &Task, canvas_id: &str) {
+    if let Err(err) = slack.canvases_delete(canvas_id).await {
+        warn!(error = %err, task_id = task.id, canvas_id, "failed to delete unused canvas");
+    }
+}
+
+/// Post `reply` as a summary plus canvas/file when it is long enough and the setting is
+/// on. An error means nothing was posted, so the caller can still post the reply inline.
+pub async fn post_long_reply(
+    slack: &SlackClient,
+    settings: &Settings,
+    task: &Task,
+    reply: &str,
+) -> anyhow::Result<LongReply> {
+    let mode = settings.slack_long_reply_mode.as_str();
+    let threshold = usize::try_from(settings.slack_long_reply_chars).unwrap_or(usize::MAX);
+    if mode == "off" || reply.chars().count() <= threshold {
+        return Ok(LongReply::Inline);
+    }
+    let thread = Some(task.thread_ts.as_str()).filter(|t| !t.trim().is_empty());
+    let summary = summarize(reply);
+
+    if mode == "canvas" {
+        match publish_canvas(slack, task, reply).await {
+            Ok((canvas_id, link)) => {
+                let text =
+                    format!("{summary}\n\n:page_facing_up: Full report: <{link}|open canvas>");
+                if let Err(err) = slack.post_message(&task.channel_id, thread, &text).await {
+                    delete_canvas(slack, task, &canvas_id).await;
+                    return Err(err);
+                }
+                return Ok(LongReply::Published);
+            }
+            Err(err) => {
+                warn!(error = %err, task_id = task.id, "canvas publish failed; uploading markdown instead");
+            }
+        }
+    }
+
+    let text = format!("{summary}\n\n:page_facing_up: Full report attached below.");
+    slack.post_message(&task.channel_id, thread, &text).await?;
+    let filename = format!("task-{}-report.md", task.id);
+    let content = format!("# {}\n\n{}\n", title_for(task), reply.trim());
+    if let Err(err) = slack
+        .upload_file_content(&task.channel_id, thread, &filename, content.as_bytes())
+        .await
+    {
+        warn!(error = %err, task_id = task.id, "failed to attach the full report after its summary");
+        return Ok(LongReply::SummaryOnly);
+    }
+    Ok(LongReply::Published)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_reply_summary_keeps_leading_paragraphs() {
+        let reply = format!(
+            "Short answer: yes.\n\nDetails follow.\n\n{}",
+            "x".repeat(5000)
+        );
+        assert_eq!(summarize(&reply), "Short answer: yes.\n\nDetails follow.");
+        let one = "y".repeat(5000);
+        let s = summarize(&one);
+        assert_eq!(s.chars().count(), 600);
+        assert!(s.ends_with('…'));
+    }
+}
@@ -0,0 +1,507 @@
+//! Speech-to-text for voice messages.
+//!
+//! Telegram voice notes and audio files, and audio files attached to Slack messages, are
+//! transcribed through [`SpeechToText`] and the transcript becomes (part of) the prompt.
+//! Slack audio is transcribed by the worker when the task starts, not while Slack waits
+//! for the events request to be answered.
+//! The `speech_to_text` setting picks the provider; empty turns transcription off:
+//!
+//! ```json
+//! {"provider": "openai", "model": "gpt-4o-mini-transcribe", "language": "de"}
+//! {"provider": "whisper_cpp", "base_url": "http://whisper:8080"}
+//! ```
+//!
+//! `openai` calls `/audio/transcriptions` (any OpenAI-compatible server works through
+//! `base_url`, with the key from the stored secret named by `api_key_secret` or the OpenAI
+//! API key). `whisper_cpp` calls
+//! the `/inference` endpoint of a whisper.cpp server, which runs locally and needs no key.
+
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::models::Task;
+use crate::slack::{SlackClient, SlackFile};
+use crate::AppState;
+
+const DEFAULT_OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_OPENAI_MODEL: &str = "whisper-1";
+const DEFAULT_WHISPER_CPP_BASE_URL: &str = "http://127.0.0.1:8080";
+/// Larger recordings are not sent for transcription (OpenAI's upload limit).
+pub const MAX_AUDIO_BYTES: usize = 25 * 1024 * 1024;
+/// Transcripts are cut to this many characters before they reach the prompt.
+pub const MAX_TRANSCRIPT_CHARS: usize = 8_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SttProvider {
+    /// OpenAI (or OpenAI-compatible) `/audio/transcriptions`.
+    #[default]
+    Openai,
+    /// A whisper.cpp server (`whisper-server`), usually on the same host.
+    WhisperCpp,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SttConfig {
+    pub provider: SttProvider,
+    pub base_url: Option<String>,
+    /// OpenAI only; whisper.cpp uses the model it was started with.
+    pub model: Option<String>,
+    /// ISO-639-1 language hint (e.g. `de`); detected from the audio when unset.
+    pub language: Option<String>,
+    /// Stored secret (`custom.NAME`) holding the API key (OpenAI only; defaults to the
+    /// OpenAI API key).
+    pub api_key_secret: Option<String>,
+}
+
+/// `None` when speech-to-text is off.
+pub fn parse_config(raw: &str) -> anyhow::Result<Option<SttConfig>> {
+    if raw.trim().is_empty() {
+        return Ok(None);
+    }
+    let cfg: SttConfig = serde_json::from_str(raw).context("parse speech_to_text JSON")?;
+    if let Some(url) = cfg.base_url.as_deref() {
+        reqwest::Url::parse(url.trim())
+            .with_context(|| format!("speech_to_text.base_url is not a valid URL: {url}"))?;
+    }
+    if cfg.provider == SttProvider::WhisperCpp
+        && (cfg.model.is_some() || cfg.api_key_secret.is_some())
+    {
+        anyhow::bail!("speech_to_text: model and api_key_secret only apply to the openai provider");
+    }
+    if let Some(name) = cfg.api_key_secret.as_deref() {
+        anyhow::ensure!(
+            crate::secrets::valid_custom_secret_name(name.trim()),
+            "speech_to_text: invalid api_key_secret name {name:?}"
+        );
+    }
+    Ok(Some(cfg))
+}
+
+/// Whether an attachment is something to transcribe.
+pub fn is_audio(mime: &str, filetype: Option<&str>) -> bool {
+    const AUDIO_FILETYPES: [&str; 9] = [
+        "m4a", "mp3", "mpga", "ogg", "oga", "opus", "wav", "webm", "flac",
+    ];
+    mime.starts_with("audio/")
+        || filetype.is_some_and(|t| AUDIO_FILETYPES.contains(&t.to_ascii_lowercase().as_str()))
+}
+
+/// How a transcript is shown to the agent.
+pub fn transcript_note(name: &str, transcript: &str) -> String {
+    format!(
+        "[Voice message {name}, transcribed]\n{}",
+        grail_text::truncate(transcript, MAX_TRANSCRIPT_CHARS)
+    )
+}
+
+pub struct Audio {
+    pub filename: String,
+    pub mime: String,
+    pub bytes: Vec<u8>,
+}
+
+pub trait SpeechToText {
+    /// The text spoken in `audio`. Fails when nothing was recognized.
+    async fn transcribe(&self, audio: Audio) -> anyhow::Result<String>;
+
+    fn name(&self) -> &'static str;
+}
+
+pub struct OpenAiTranscriber {
+    http: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    language: Option<String>,
+}
+
+pub struct WhisperCppTranscriber {
+    http: reqwest::Client,
+    base_url: String,
+    language: Option<String>,
+}
+
+pub enum Transcriber {
+    OpenAi(OpenAiTranscriber),
+    WhisperCpp(WhisperCppTranscriber),
+}
+
+fn file_part(audio: Audio) -> anyhow::Result<reqwest::multipart::Part> {
+    anyhow::ensure!(
+        audio.bytes.len() <= MAX_AUDIO_BYTES,
+        "audio is too large to transcribe ({} MB max)",
+        MAX_AUDIO_BYTES / (1024 * 1024)
+    );
+    reqwest::multipart::Part::bytes(audio.bytes)
+        .file_name(audio.filename)
+        .mime_str(&audio.mime)
+        .context("invalid audio mime type")
+}
+
+async fn send_form(
+    request: reqwest::RequestBuilder,
+    form: reqwest::multipart::Form,
+    what: &str,
+) -> anyhow::Result<String> {
+    #[derive(Deserialize)]
+    struct Resp {
+        text: String,
+    }
+    let resp = request
+        .multipart(form)
+        .send()
+        .await
+        .with_context(|| format!("{what} request"))?;
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        anyhow::bail!(
+            "{what} failed with status {status}: {}",
+            grail_text::truncate_line(&body, 300)
+        );
+    }
+    let resp: Resp = resp
+        .json()
+        .await
+        .with_context(|| format!("{what} decode"))?;
+    let text = resp.text.trim();
+    anyhow::ensure!(!text.is_empty(), "no speech was recognized");
+    Ok(text.to_string())
+}
+
+impl SpeechToText for OpenAiTranscriber {
+    async fn transcribe(&self, audio: Audio) -> anyhow::Result<String> {
+        let mut form = reqwest::multipart::Form::new()
+            .part("file", file_part(audio)?)
+            .text("model", self.model.clone())
+            .text("response_format", "json");
+        if let Some(lang) = &self.language {
+            form = form.text("language", lang.clone());
+        }
+        let mut req = self
+            .http
+            .post(format!("{}/audio/transcriptions", self.base_url));
+        if let Some(key) = &self.api_key {
+            req = req.bearer_auth(key);
+        }
+        send_form(req, form, "openai transcription").await
+    }
+
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+}
+
+impl SpeechToText for WhisperCppTranscriber {
+    async fn transcribe(&self, audio: Audio) -> anyhow::Result<String> {
+        let mut form = reqwest::multipart::Form::new()
+            .part("file", file_part(audio)?)
+            .text("response_format", "json");
+        if let Some(lang) = &self.language {
+            form = form.text("language", lang.clone());
+        }
+        let req = self.http.post(format!("{}/inference", self.base_url));
+        send_form(req, form, "whisper.cpp transcription").await
+    }
+
+    fn name(&self) -> &'static str {
+        "whisper_cpp"
+    }
+}
+
+impl SpeechToText for Transcriber {
+    async fn transcribe(&self, audio: Audio) -> anyhow::Result<String> {
+        let (bytes, started) = (audio.bytes.len(), std::time::Instant::now());
+        let text = match self {
+            Transcriber::OpenAi(t) => t.transcribe(audio).await,
+            Transcriber::WhisperCpp(t) => t.transcribe(audio).await,
+        }?;
+        info!(
+            provider = self.name(),
+            bytes,
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            chars = text.chars().count(),
+            "transcribed voice message"
+        );
+        Ok(text)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Transcriber::OpenAi(t) => t.name(),
+            Transcriber::WhisperCpp(t) => t.name(),
+        }
+    }
+}
+
+fn base_url(cfg: &SttConfig, default: &str) -> String {
+    cfg.base_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .unwrap_or(default)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// The configured transcriber, or `None` when speech-to-text is off.
+pub async fn transcriber(state: &AppState) -> anyhow::Result<Option<Transcriber>> {
+    let settings = crate::db::get_settings(&state.pool).await?;
+    let Some(cfg) = parse_config(&settings.speech_to_text)? else {
+        return Ok(None);
+    };
+    let language = cfg
+        .language
+        .as_deref()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string);
+    Ok(Some(match cfg.provider {
+        SttProvider::Openai => {
+            let api_key = match cfg.api_key_secret.as_deref() {
+                Some(name) => crate::secrets::load_custom_secret_opt(state, name.trim()).await?,
+                None => crate::secrets::load_openai_api_key_opt(state).await?,
+            };
+            Transcriber::OpenAi(OpenAiTranscriber {
+                http: state.http.clone(),
+                base_url: base_url(&cfg, DEFAULT_OPENAI_BASE_URL),
+                api_key,
+                model: cfg
+                    .model
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|m| !m.is_empty())
+                    .unwrap_or(DEFAULT_OPENAI_MODEL)
+                    .to_string(),
+                language,
+            })
+        }
+        SttProvider::WhisperCpp => Transcriber::WhisperCpp(WhisperCppTranscriber {
+            http: state.http.clone(),
+            base_url: base_url(&cfg, DEFAULT_WHISPER_CPP_BASE_URL),
+            language,
+        }),
+    }))
+}
+
+/// Transcribe `audio` (named `name` in the prompt) for a chat message. On failure the
+/// error is a reply for the user.
+pub async fn transcribe_for_prompt(
+    state: &AppState,
+    name: &str,
+    audio: Audio,
+) -> Result<String, String> {
+    let transcriber = match transcriber(state).await {
+        Ok(Some(t)) => t,
+        Ok(None) => {
+            return Err(
+                "I can't listen to voice messages yet: speech-to-text isn't set up (Settings → Speech-to-Text)."
+                    .to_string(),
+            )
+        }
+        Err(err) => {
+            warn!(error = %err, "failed to load speech-to-text settings");
+            return Err("I couldn't transcribe that voice message right now.".to_string());
+        }
+    };
+    match transcriber.transcribe(audio).await {
+        Ok(text) => Ok(transcript_note(name, &text)),
+        Err(err) => {
+            warn!(error = %err, provider = transcriber.name(), "failed to transcribe voice message");
+            Err(format!(
+                "I couldn't transcribe that voice message ({}).",
+                grail_text::truncate_line(&format!("{err:#}"), 200)
+            ))
+        }
+    }
+}
+
+/// Download and transcribe the audio a Slack message came with. The events handler only
+/// records it (`"transcribe": true` in `files_json`) because Slack expects an answer within
+/// three seconds. Transcripts are appended to the prompt and stored on the task, so a
+/// retry doesn't transcribe again. `None` when the task has no such audio.
+pub async fn transcribe_slack_audio(state: &AppState, task: &Task) -> Option<Task> {
+    let mut files: Vec<Value> = serde_json::from_str(&task.files_json).ok()?;
+    if !files.iter().any(|f| f["transcribe"] == true) {
+        return None;
+    }
+    let slack = match crate::secrets::load_slack_bot_token_opt(state).await {
+        Ok(Some(token)) => Some(SlackClient::new(state.http.clone(), token)),
+        Ok(None) => None,
+        Err(err) => {
+            warn!(error = %err, task_id = task.id, "failed to load slack bot token");
+            None
+        }
+    };
+    let download_dir = state.config.data_dir.join("downloads").join(&task.event_ts);
+    let mut prompt = task.prompt_text.clone();
+    for f in files.iter_mut().filter(|f| f["transcribe"] == true) {
+        let Ok(file) = serde_json::from_value::<SlackFile>(f.clone()) else {
+            continue;
+        };
+        let fname = file.name.as_deref().unwrap_or("unknown");
+        let mime = file
+            .mimetype
+            .as_deref()
+            .unwrap_or("application/octet-stream");
+        let dest = download_dir.join(fname);
+        let downloaded = match (&slack, file.url_private_download.as_deref()) {
+            _ if file.size.is_some_and(|n| n > MAX_AUDIO_BYTES as u64) => Err(format!(
+                "larger than {} MB",
+                MAX_AUDIO_BYTES / (1024 * 1024)
+            )),
+            (None, _) => Err("SLACK_BOT_TOKEN is not configured".to_string()),
+            (_, None) => Err("no download URL".to_string()),
+            (Some(slack), Some(url)) => match slack.fetch_file_bytes(url, MAX_AUDIO_BYTES).await {
+                Ok(bytes) => {
+                    let saved = match tokio::fs::create_dir_all(&download_dir).await {
+                        Ok(()) => tokio::fs::write(&dest, &bytes).await,
+                        Err(err) => Err(err),
+                    };
+                    if let Err(err) = saved {
+                        warn!(error = %err, file = fname, "failed to save slack audio");
+                    }
+                    Ok(bytes)
+                }
+                Err(err) => {
+                    warn!(error = %err, file = fname, "failed to download slack audio");
+                    Err(format!("{err:#}"))
+                }
+            },
+        };
+        let note = match downloaded {
+            Ok(bytes) => {
+                let audio = Audio {
+                    filename: fname.to_string(),
+                    mime: mime.to_string(),
+                    bytes,
+                };
+                let dest_str = dest.display().to_string();
+                f["local_path"] = json!(dest_str);
+                match transcribe_for_prompt(state, fname, audio).await {
+                    Ok(note) => note,
+                    Err(reason) => format!(
+                        "[Attached audio: {fname} — downloaded to {dest_str}; not transcribed: {reason}]"
+                    ),
+                }
+            }
+            Err(reason) => {
+                format!("[Attached audio: {fname} ({mime}) — not transcribed: {reason}]")
+            }
+        };
+        prompt.push_str(&format!("\n{note}"));
+        if let Some(f) = f.as_object_mut() {
+            f.remove("transcribe");
+            f.remove("url_private_download");
+        }
+    }
+
+    let files_json = serde_json::to_string(&files).unwrap_or_default();
+    if let Err(err) =
+        crate::db::update_task_prompt(&state.pool, task.id, &prompt, &files_json).await
+    {
+        warn!(error = %err, task_id = task.id, "failed to store slack audio transcript");
+    }
+    Some(Task {
+        prompt_text: prompt,
+        files_json,
+        ..task.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn speech_to_text_config_and_voice_updates() {
+        assert!(parse_config("  ").unwrap().is_none());
+        let cfg = parse_config(r#"{"provider": "whisper_cpp", "base_url": "http://w:8080"}"#)
+            .unwrap()
+            .unwrap();
+        assert_eq!(cfg.provider, SttProvider::WhisperCpp);
+        let cfg = parse_config(r#"{"language": "de"}"#).unwrap().unwrap();
+        assert_eq!(cfg.provider, SttProvider::Openai);
+        assert!(parse_config(r#"{"provider": "whisper_cpp", "model": "large"}"#).is_err());
+        assert!(parse_config(r#"{"provider": "azure"}"#).is_err());
+        assert!(parse_config(r#"{"base_url": "not a url"}"#).is_err());
+        assert!(parse_config(r#"{"api_key_secret": "STT_KEY"}"#).is_ok());
+        assert!(parse_config(r#"{"api_key_secret": "NOT-A-NAME"}"#).is_err());
+        assert!(parse_config(r#"{"api_key_env": "OPENAI_API_KEY"}"#).is_err());
+
+        assert!(is_audio("audio/webm", None));
+        assert!(is_audio("application/octet-stream", Some("M4A")));
+        assert!(!is_audio("video/mp4", Some("mp4")));
+        assert_eq!(
+            transcript_note("(4s)", "turn off the lights"),
+            "[Voice message (4s), transcribed]\nturn off the lights"
+        );
+
+        let update: crate::telegram::TelegramUpdate = serde_json::from_str(
+            r#"{"update_id": 1, "message": {"message_id": 7, "date": 0,
+                "chat": {"id": 5, "type": "private"},
+                "voice": {"file_id": "abc", "duration": 4, "mime_type": "audio/ogg"}}}"#,
+        )
+        .unwrap();
+        let msg = update.message.unwrap();
+        assert!(msg.text.is_none() && msg.caption.is_none());
+        let voice = msg.voice.unwrap();
+        assert_eq!((voice.file_id.as_str(), voice.duration), ("abc", 4));
+    }
+
+    #[tokio::test]
+    async fn oversized_slack_audio_is_never_downloaded() {
+        let db = crate::test_support::TestDb::new().await;
+        let state = db.state();
+        let files = json!([{
+            "id": "F1",
+            "name": "standup.m4a",
+            "mimetype": "audio/mp4",
+            "size": MAX_AUDIO_BYTES + 1,
+            // Unroutable: fetching it would fail with a different reason.
+            "url_private_download": "http://192.0.2.1/standup.m4a",
+            "transcribe": true,
+        }]);
+        let task_id = crate::db::enqueue_task(
+            &db.pool,
+            &crate::db::NewTask {
+                provider: "slack",
+                workspace_id: "T1",
+                channel_id: "C1",
+                thread_ts: "1.1",
+                event_ts: "1.1",
+                requested_by_user_id: "U1",
+                prompt_text: "what did I say",
+                files_json: &files.to_string(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let task = crate::db::get_task(&db.pool, task_id)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let updated = transcribe_slack_audio(&state, &task).await.unwrap();
+        assert!(
+            updated.prompt_text.ends_with(
+                "[Attached audio: standup.m4a (audio/mp4) — not transcribed: larger than 25 MB]"
+            ),
+            "{}",
+            updated.prompt_text
+        );
+        let stored = crate::db::get_task(&db.pool, task_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.prompt_text, updated.prompt_text);
+        assert!(!stored.files_json.contains("transcribe"));
+        // Nothing left to do on a retry.
+        assert!(transcribe_slack_audio(&state, &stored).await.is_none());
+    }
+}
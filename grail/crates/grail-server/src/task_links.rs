@@ -0,0 +1,150 @@
+//! Stable, provider-agnostic links to tasks.
+//!
+//! Every task has an app link, `grail://task/<id>`, and a dashboard link,
+//! `<BASE_URL>/admin/tasks/<id>` (relative when `BASE_URL` is unset). Both resolve to the
+//! task detail view: the dashboard's "Open link" box and `GET /api/admin/tasks/resolve`
+//! accept either form, as well as `#<id>`. With the `reply_task_links` setting on, replies
+//! end with a short footer linking back to the task.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+
+pub const APP_SCHEME: &str = "grail://task/";
+
+pub fn app_link(task_id: i64) -> String {
+    format!("{APP_SCHEME}{task_id}")
+}
+
+pub fn web_link(base_url: Option<&str>, task_id: i64) -> String {
+    let base = base_url
+        .map(|b| b.trim().trim_end_matches('/'))
+        .unwrap_or("");
+    format!("{base}/admin/tasks/{task_id}")
+}
+
+/// The task id behind `grail://task/<id>`, a dashboard URL, `#<id>` or a bare id.
+pub fn parse(link: &str) -> Option<i64> {
+    let link = link.trim();
+    let rest = if let Some(rest) = link.strip_prefix(APP_SCHEME) {
+        rest
+    } else if let Some((_, rest)) = link.split_once("/admin/tasks/") {
+        rest
+    } else {
+        link.strip_prefix('#').unwrap_or(link)
+    };
+    let id = rest
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or("")
+        .parse::<i64>()
+        .ok()?;
+    (id > 0).then_some(id)
+}
+
+/// Footer appended to replies. Slack gets a mrkdwn link; other providers get plain text,
+/// which they linkify themselves. Without `BASE_URL` there is nothing clickable to offer,
+/// so only the app link is shown.
+pub fn reply_footer(provider: &str, base_url: Option<&str>, task_id: i64) -> String {
+    let app = app_link(task_id);
+    match base_url.map(str::trim).filter(|b| !b.is_empty()) {
+        Some(base) if provider == "slack" => {
+            format!(
+                "Task <{}|#{task_id}> · `{app}`",
+                web_link(Some(base), task_id)
+            )
+        }
+        Some(base) => format!("Task #{task_id}: {} ({app})", web_link(Some(base), task_id)),
+        None => format!("Task #{task_id} ({app})"),
+    }
+}
+
+/// Slack permalinks never change, and the dashboard polls task details, so they are
+/// remembered per task (the map is cleared when it gets large).
+static SLACK_PERMALINKS: Lazy<std::sync::Mutex<HashMap<i64, String>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+const MAX_CACHED_PERMALINKS: usize = 1000;
+
+/// Permalink of the Slack message a task came from. Best-effort: `None` for other
+/// providers, synthetic tasks, or when Slack can't be reached.
+pub async fn slack_permalink(
+    state: &crate::AppState,
+    task: &crate::models::Task,
+) -> Option<String> {
+    if task.provider != "slack" || task.is_synthetic || task.event_ts.is_empty() {
+        return None;
+    }
+    if let Some(link) = SLACK_PERMALINKS.lock().ok()?.get(&task.id) {
+        return Some(link.clone());
+    }
+    let token = crate::secrets::load_slack_bot_token_opt(state)
+        .await
+        .ok()
+        .flatten()?;
+    let slack = crate::slack::SlackClient::new(state.http.clone(), token);
+    match slack
+        .message_permalink(&task.channel_id, &task.event_ts)
+        .await
+    {
+        Ok(link) => {
+            if let Ok(mut cache) = SLACK_PERMALINKS.lock() {
+                if cache.len() >= MAX_CACHED_PERMALINKS {
+                    cache.clear();
+                }
+                cache.insert(task.id, link.clone());
+            }
+            Some(link)
+        }
+        Err(err) => {
+            tracing::warn!(error = %err, task_id = task.id, "failed to fetch slack permalink");
+            None
+        }
+    }
+}
+
+/// The `links` object of the task API.
+pub async fn links_json(state: &crate::AppState, task: &crate::models::Task) -> serde_json::Value {
+    serde_json::json!({
+        "app": app_link(task.id),
+        "web": web_link(state.config.base_url.as_deref(), task.id),
+        "slack": slack_permalink(state, task).await,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_links_round_trip_and_render_per_provider() {
+        assert_eq!(app_link(42), "grail://task/42");
+        assert_eq!(
+            web_link(Some("https://grail.example.com/"), 42),
+            "https://grail.example.com/admin/tasks/42"
+        );
+        assert_eq!(web_link(None, 42), "/admin/tasks/42");
+        for link in [
+            "grail://task/42",
+            " https://grail.example.com/admin/tasks/42?tab=trace ",
+            "/admin/tasks/42/",
+            "#42",
+            "42",
+        ] {
+            assert_eq!(parse(link), Some(42), "{link}");
+        }
+        assert_eq!(parse("grail://task/abc"), None);
+        assert_eq!(parse("#0"), None);
+        assert_eq!(parse("https://example.com/"), None);
+
+        let base = Some("https://grail.example.com");
+        assert_eq!(
+            reply_footer("slack", base, 7),
+            "Task <https://grail.example.com/admin/tasks/7|#7> · `grail://task/7`"
+        );
+        assert_eq!(
+            reply_footer("telegram", base, 7),
+            "Task #7: https://grail.example.com/admin/tasks/7 (grail://task/7)"
+        );
+        assert_eq!(reply_footer("slack", None, 7), "Task #7 (grail://task/7)");
+    }
+}
@@ -37,6 +37,55 @@ impl TelegramClient {
         resp.result.context("telegram getMe missing result")
     }
 
+    /// Download a file sent to the bot (the Bot API serves files up to 20 MB).
+    pub async fn download_file(&self, file_id: &str, max_bytes: usize) -> anyhow::Result<Vec<u8>> {
+        let resp: TelegramApiResponse<TelegramFile> = self
+            .http
+            .get(self.api_url("getFile"))
+            .query(&[("file_id", file_id)])
+            .send()
+            .await
+            .context("telegram getFile request")?
+            .json()
+            .await
+            .context("telegram getFile decode")?;
+        if !resp.ok {
+            anyhow::bail!(
+                "telegram getFile failed: {}",
+                resp.description
+                    .unwrap_or_else(|| "unknown_error".to_string())
+            );
+        }
+        let file = resp.result.context("telegram getFile missing result")?;
+        if file.file_size.is_some_and(|n| n as usize > max_bytes) {
+            anyhow::bail!("telegram file is larger than {max_bytes} bytes");
+        }
+        let path = file
+            .file_path
+            .context("telegram getFile missing file_path")?;
+        let resp = self
+            .http
+            .get(format!(
+                "https://api.telegram.org/file/bot{}/{}",
+                self.bot_token, path
+            ))
+            .send()
+            .await
+            .context("telegram file download request")?;
+        if !resp.status().is_success() {
+            anyhow::bail!(
+                "telegram file download failed with status {}",
+                resp.status()
+            );
+        }
+        let bytes = resp.bytes().await.context("read telegram file bytes")?;
+        anyhow::ensure!(
+            bytes.len() <= max_bytes,
+            "telegram file is larger than {max_bytes} bytes"
+        );
+        Ok(bytes.to_vec())
+    }
+
     pub async fn send_message(
         &self,
         chat_id: &str,
@@ -88,45 +137,72 @@ impl TelegramClient {
         }
         Ok(ids)
     }
-}
 
-fn split_telegram_text(text: &str, max_chars: usize) -> Vec<String> {
-    let t = text.trim();
-    if t.is_empty() {
-        return vec!["(empty)".to_string()];
-    }
-    if t.chars().count() <= max_chars {
-        return vec![t.to_string()];
-    }
-
-    let mut out = Vec::new();
-    let mut buf = String::new();
-    for line in t.split_inclusive('\n') {
-        if buf.chars().count() + line.chars().count() > max_chars && !buf.is_empty() {
-            out.push(buf.trim().to_string());
-            buf.clear();
+    /// Send one message in HTML parse mode. Unlike [`Self::send_message`] this does not
+    /// split: cutting through markup would make Telegram reject it, so callers keep
+    /// `html` under the 4096-character limit.
+    pub async fn send_message_html(
+        &self,
+        chat_id: &str,
+        reply_to_message_id: Option<i64>,
+        html: &str,
+    ) -> anyhow::Result<i64> {
+        #[derive(Serialize)]
+        struct Req<'a> {
+            chat_id: &'a str,
+            text: &'a str,
+            parse_mode: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            reply_to_message_id: Option<i64>,
+            allow_sending_without_reply: bool,
+            disable_web_page_preview: bool,
         }
-        if line.chars().count() > max_chars {
-            // Hard split long lines.
-            let mut current = String::new();
-            for ch in line.chars() {
-                current.push(ch);
-                if current.chars().count() >= max_chars {
-                    out.push(current.trim().to_string());
-                    current.clear();
-                }
-            }
-            if !current.trim().is_empty() {
-                buf.push_str(&current);
-            }
-            continue;
+
+        let resp: TelegramApiResponse<TelegramMessage> = self
+            .http
+            .post(self.api_url("sendMessage"))
+            .json(&Req {
+                chat_id,
+                text: html,
+                parse_mode: "HTML",
+                reply_to_message_id,
+                allow_sending_without_reply: true,
+                disable_web_page_preview: true,
+            })
+            .send()
+            .await
+            .context("telegram sendMessage request")?
+            .json()
+            .await
+            .context("telegram sendMessage decode")?;
+
+        if !resp.ok {
+            anyhow::bail!(
+                "telegram sendMessage failed: {}",
+                resp.description
+                    .unwrap_or_else(|| "unknown_error".to_string())
+            );
         }
-        buf.push_str(line);
+        Ok(resp
+            .result
+            .context("telegram sendMessage missing result")?
+            .message_id)
     }
-    if !buf.trim().is_empty() {
-        out.push(buf.trim().to_string());
+}
+
+fn split_telegram_text(text: &str, max_chars: usize) -> Vec<String> {
+    let chunks = grail_text::split(text, max_chars);
+    if chunks.is_empty() {
+        vec!["(empty)".to_string()]
+    } else {
+        chunks
     }
-    out
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelegramFile {
+    pub file_size: Option<u64>,
+    pub file_path: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -153,6 +229,21 @@ pub struct TelegramInboundMessage {
     #[serde(default)]
     pub entities: Vec<TelegramEntity>,
     pub reply_to_message: Option<Box<TelegramReplyToMessage>>,
+    /// Text sent along with a voice note, audio file or other media.
+    pub caption: Option<String>,
+    pub voice: Option<TelegramAudio>,
+    pub audio: Option<TelegramAudio>,
+}
+
+/// A voice note or audio file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelegramAudio {
+    pub file_id: String,
+    #[serde(default)]
+    pub duration: i64,
+    pub mime_type: Option<String>,
+    /// Audio files only.
+    pub file_name: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -0,0 +1,45 @@
+//! Helpers shared by the unit tests.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::Parser;
+use sqlx::SqlitePool;
+use tokio::sync::RwLock;
+
+/// A migrated SQLite database in the temp dir, deleted (with its WAL files) on drop.
+pub struct TestDb {
+    pub pool: SqlitePool,
+    path: PathBuf,
+}
+
+impl TestDb {
+    pub async fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("{}.db", crate::random_id("test")));
+        let pool = crate::db::init_sqlite(&path).await.unwrap();
+        Self { pool, path }
+    }
+
+    /// An `AppState` over this database, with defaults for everything else.
+    pub fn state(&self) -> crate::AppState {
+        let config = crate::Config::parse_from(["grail-server", "--admin-password", "test"]);
+        crate::AppState {
+            config: Arc::new(config),
+            pool: self.pool.clone(),
+            http: reqwest::Client::new(),
+            crypto: None,
+            slack_bot_user_id: Arc::new(RwLock::new(None)),
+            telegram_bot_username: Arc::new(RwLock::new(None)),
+            task_notify: Arc::new(tokio::sync::Notify::new()),
+            slack_guard: Arc::new(crate::slack::SlackRequestGuard::default()),
+        }
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", self.path.display()));
+        }
+    }
+}
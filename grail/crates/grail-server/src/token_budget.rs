@@ -0,0 +1,284 @@
+//! Token-budgeted context packing.
+//!
+//! The `context_token_budget` setting replaces the character budget and the fixed
+//! `context_last_n` message count with a token budget per model: the worker fetches up
+//! to [`FETCH_LIMIT`] recent messages and keeps as many whole messages, files and
+//! memories as fit. Tokens are estimated the way cl100k/o200k-style BPE tokenizers split
+//! text (words, digit groups of three, punctuation runs, one token per CJK character),
+//! which is close enough for budgeting without shipping the vocabularies.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use serde::Deserialize;
+use tracing::warn;
+
+/// Messages fetched per history source when a token budget is active.
+pub const FETCH_LIMIT: i64 = 200;
+/// Memories may use at most this share of the budget; the rest goes to context.
+pub const MEMORY_SHARE: f64 = 0.3;
+const MIN_BUDGET: usize = 500;
+const MAX_BUDGET: usize = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Run {
+    Word,
+    Digits,
+    Punct,
+    Wide,
+    Other,
+}
+
+fn classify(c: char) -> Option<Run> {
+    if c.is_whitespace() {
+        None
+    } else if c.is_ascii_digit() {
+        Some(Run::Digits)
+    } else if matches!(c as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF)
+    {
+        Some(Run::Wide)
+    } else if c.is_ascii_alphabetic() || c == '\'' {
+        Some(Run::Word)
+    } else if c.is_alphabetic() {
+        Some(Run::Other)
+    } else {
+        Some(Run::Punct)
+    }
+}
+
+fn run_tokens(kind: Run, len: usize) -> usize {
+    match kind {
+        // Common words are one token; long ones split every ~6 characters.
+        Run::Word => len.div_ceil(6),
+        Run::Digits => len.div_ceil(3),
+        Run::Punct => len.div_ceil(2),
+        Run::Wide => len,
+        // Accented/Cyrillic/Greek/... words average about two characters per token.
+        Run::Other => len.div_ceil(2),
+    }
+}
+
+/// Estimated token count of `text`.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut total = 0;
+    let mut run: Option<(Run, usize)> = None;
+    let mut newline_run = false;
+    for c in text.chars() {
+        let kind = classify(c);
+        if c == '\n' {
+            if !newline_run {
+                total += 1;
+            }
+            newline_run = true;
+        } else if !c.is_whitespace() {
+            newline_run = false;
+        }
+        match (run, kind) {
+            (Some((k, n)), Some(kind)) if k == kind && kind != Run::Wide => {
+                run = Some((k, n + 1));
+            }
+            (prev, kind) => {
+                if let Some((k, n)) = prev {
+                    total += run_tokens(k, n);
+                }
+                run = kind.map(|k| (k, 1));
+            }
+        }
+    }
+    if let Some((k, n)) = run {
+        total += run_tokens(k, n);
+    }
+    total
+}
+
+/// Settings.context_token_budget: a number, or `{"default": N, "models": {"<model>": N}}`.
+/// Empty turns token budgeting off.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TokenBudgetConfig {
+    pub default: Option<usize>,
+    pub models: HashMap<String, usize>,
+}
+
+pub fn parse_token_budget(raw: &str) -> anyhow::Result<Option<TokenBudgetConfig>> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    let cfg = match raw.parse::<usize>() {
+        Ok(n) => TokenBudgetConfig {
+            default: Some(n),
+            ..Default::default()
+        },
+        Err(_) => serde_json::from_str(raw).context("parse context_token_budget JSON")?,
+    };
+    for (scope, n) in cfg
+        .default
+        .iter()
+        .map(|n| ("default", n))
+        .chain(cfg.models.iter().map(|(k, n)| (k.as_str(), n)))
+    {
+        anyhow::ensure!(
+            (MIN_BUDGET..=MAX_BUDGET).contains(n),
+            "context_token_budget.{scope}: must be between {MIN_BUDGET} and {MAX_BUDGET} tokens"
+        );
+    }
+    Ok(Some(cfg))
+}
+
+impl TokenBudgetConfig {
+    /// Exact model match, then the longest configured prefix (`gpt-5` covers
+    /// `gpt-5-mini`), then the default.
+    pub fn for_model(&self, model: &str) -> Option<usize> {
+        let model = model.trim();
+        if let Some(n) = self.models.get(model) {
+            return Some(*n);
+        }
+        self.models
+            .iter()
+            .filter(|(k, _)| !k.is_empty() && model.starts_with(k.as_str()))
+            .max_by_key(|(k, _)| k.len())
+            .map(|(_, n)| *n)
+            .or(self.default)
+    }
+}
+
+/// The budget for `model`, or `None` when token budgeting is off.
+pub fn budget_for_model(raw: &str, model: &str) -> Option<usize> {
+    match parse_token_budget(raw) {
+        Ok(cfg) => cfg.and_then(|c| c.for_model(model)),
+        Err(err) => {
+            warn!(error = %err, "invalid context_token_budget setting; using character budget");
+            None
+        }
+    }
+}
+
+/// Split formatted context into entries: a line plus the indented lines under it (a
+/// message and its attachments, a task and its result).
+fn entries(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    for (i, _) in text.match_indices('\n') {
+        let next = &text[i + 1..];
+        if !next.is_empty() && !next.starts_with([' ', '\t']) {
+            out.push(&text[start..=i]);
+            start = i + 1;
+        }
+    }
+    if start < text.len() {
+        out.push(&text[start..]);
+    }
+    out
+}
+
+/// Keep as many whole entries as fit in `max_tokens`, newest (last) first when
+/// `keep_tail`, otherwise from the start. A single entry larger than the budget is cut.
+/// Returns the packed text and its estimated tokens.
+pub fn pack(text: &str, max_tokens: usize, keep_tail: bool) -> (String, usize) {
+    let all = entries(text);
+    let mut kept: Vec<&str> = Vec::new();
+    let mut used = 0;
+    let ordered: Box<dyn Iterator<Item = &&str>> = if keep_tail {
+        Box::new(all.iter().rev())
+    } else {
+        Box::new(all.iter())
+    };
+    for entry in ordered {
+        let t = estimate_tokens(entry);
+        if used + t > max_tokens {
+            if kept.is_empty() {
+                let cut = cut_to_tokens(entry, max_tokens, keep_tail);
+                used = estimate_tokens(&cut);
+                return (cut, used);
+            }
+            break;
+        }
+        used += t;
+        kept.push(entry);
+    }
+    if keep_tail {
+        kept.reverse();
+    }
+    let mut out: String = kept.concat();
+    if kept.len() < all.len() {
+        out = if keep_tail {
+            format!("… ({} older omitted)\n{out}", all.len() - kept.len())
+        } else {
+            format!("{out}… ({} more omitted)\n", all.len() - kept.len())
+        };
+    }
+    (out, used)
+}
+
+fn cut_to_tokens(s: &str, max_tokens: usize, keep_tail: bool) -> String {
+    // ~4 characters per token, then shrink until the estimate fits.
+    let chars: Vec<char> = s.chars().collect();
+    let mut n = (max_tokens * 4).min(chars.len());
+    loop {
+        let cut: String = if keep_tail {
+            chars[chars.len() - n..].iter().collect()
+        } else {
+            chars[..n].iter().collect()
+        };
+        if n == 0 || estimate_tokens(&cut) < max_tokens {
+            return if keep_tail {
+                format!("…{cut}")
+            } else {
+                format!("{cut}…")
+            };
+        }
+        n = n * 9 / 10;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_budget_packs_whole_recent_messages() {
+        use crate::context_sources::{resolve_context_plan, ContextBuilder, ContextSource};
+
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("Deploy the api"), 3);
+        assert_eq!(estimate_tokens("2024"), 2);
+        assert_eq!(estimate_tokens("数据库"), 3);
+
+        let raw = r#"{"default": 8000, "models": {"gpt-5": 32000, "gpt-5-mini": 4000}}"#;
+        assert_eq!(budget_for_model(raw, "gpt-5-mini"), Some(4000));
+        assert_eq!(budget_for_model(raw, "gpt-5.2"), Some(32000));
+        assert_eq!(budget_for_model(raw, "llama3.1"), Some(8000));
+        assert_eq!(budget_for_model("6000", "any"), Some(6000));
+        assert_eq!(budget_for_model("", "any"), None);
+        assert!(parse_token_budget(r#"{"default": 10}"#).is_err());
+
+        let history: String = (1..=50)
+            .map(|i| {
+                format!("{i:02}. 1.{i} U1: message number {i}\n    [file: f{i}.txt (text/plain)]\n")
+            })
+            .collect();
+        let (packed, used) = pack(&history, 60, true);
+        assert!(used <= 60);
+        assert!(packed.starts_with("… (") && packed.contains("older omitted"));
+        assert!(packed.trim_end().ends_with("[file: f50.txt (text/plain)]"));
+        // Whole messages only: every kept message still has its file line.
+        assert_eq!(
+            packed.matches(": message number").count(),
+            packed.matches("[file:").count()
+        );
+
+        // A small source leaves its unused share to the others.
+        let plan = resolve_context_plan(r#"{"sources": {"prior_tasks": {"enabled": true}}}"#, "C1");
+        let mut b = ContextBuilder::new(&plan);
+        b.add(ContextSource::ThreadHistory, history.clone());
+        b.add(
+            ContextSource::PriorTasks,
+            "- Task #1 request: hi\n".to_string(),
+        );
+        let out = b.render_tokens(400);
+        assert!(out.contains("Task #1 request: hi"));
+        assert!(estimate_tokens(&out) <= 400);
+        assert!(out.matches(": message number").count() > 10);
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::str::FromStr;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
@@ -17,183 +17,277 @@ use tokio::sync::mpsc;
 use tracing::{info, warn};
 
 use crate::codex::CodexManager;
+use crate::context_sources::{ContextBuilder, ContextSource};
 use crate::db;
+use crate::llm::{Agent, AgentBackend, AgentTurn, ChatBackend};
 use crate::models::{ObservationalMemory, Session};
 use crate::slack::SlackClient;
 use crate::telegram::TelegramClient;
 use crate::AppState;
 
+/// A worker that hasn't heartbeated for this long is considered dead; its running tasks
+/// are re-queued by the leader.
+pub const HEARTBEAT_STALE_SECONDS: i64 = 45;
+
 pub async fn worker_loop(state: AppState) {
-    const WORKER_LOCK_LEASE_SECONDS: i64 = 60;
-    const WORKER_LOCK_RENEW_EVERY_SECONDS: u64 = 20;
+    const LEADER_LOCK_LEASE_SECONDS: i64 = 60;
+    const LEADER_LOCK_RENEW_EVERY_SECONDS: u64 = 20;
+    const LEADER_LOCK_RETRY_EVERY_SECONDS: u64 = 2;
+    const HEARTBEAT_EVERY_SECONDS: u64 = 10;
     const CONVERSATION_LOCK_LEASE_SECONDS: i64 = 60 * 15;
     const CONVERSATION_LOCK_RENEW_EVERY_SECONDS: u64 = 30;
 
     let worker_id = random_id("worker");
+    let hostname = local_hostname();
     let concurrency = std::cmp::max(1, state.config.worker_concurrency);
 
+    // Heartbeat before claiming anything so the leader never mistakes our tasks for orphans.
+    if let Err(err) =
+        db::upsert_worker_heartbeat(&state.pool, &worker_id, &hostname, false, 0).await
+    {
+        warn!(error = %err, %worker_id, "failed to record initial worker heartbeat");
+    }
+    info!(%worker_id, %hostname, concurrency, "worker started");
+
+    let is_leader = Arc::new(AtomicBool::new(false));
+    // Cleared when heartbeats stop landing, so we stop claiming tasks the leader may
+    // consider orphaned.
+    let healthy = Arc::new(AtomicBool::new(true));
+    let active_tasks = Arc::new(AtomicUsize::new(0));
+
+    {
+        let pool = state.pool.clone();
+        let worker_id = worker_id.clone();
+        let hostname = hostname.clone();
+        let is_leader = is_leader.clone();
+        let healthy = healthy.clone();
+        let active_tasks = active_tasks.clone();
+        tokio::spawn(async move {
+            let mut last_ok = Instant::now();
+            loop {
+                tokio::time::sleep(Duration::from_secs(HEARTBEAT_EVERY_SECONDS)).await;
+                let res = db::upsert_worker_heartbeat(
+                    &pool,
+                    &worker_id,
+                    &hostname,
+                    is_leader.load(Ordering::SeqCst),
+                    active_tasks.load(Ordering::SeqCst) as i64,
+                )
+                .await;
+                match res {
+                    Ok(()) => {
+                        last_ok = Instant::now();
+                        if !healthy.swap(true, Ordering::SeqCst) {
+                            info!(%worker_id, "worker heartbeat recovered");
+                        }
+                    }
+                    Err(err) => {
+                        warn!(error = %err, %worker_id, "failed to record worker heartbeat");
+                        let limit = Duration::from_secs((HEARTBEAT_STALE_SECONDS / 2) as u64);
+                        if last_ok.elapsed() >= limit && healthy.swap(false, Ordering::SeqCst) {
+                            warn!(%worker_id, "heartbeats failing; pausing task claims");
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    // Every instance processes tasks. Each worker keeps its own Codex subprocess.
+    for slot in 0..concurrency {
+        let st = state.clone();
+        let wid = worker_id.clone();
+        let healthy = healthy.clone();
+        let active_tasks = active_tasks.clone();
+        tokio::spawn(async move {
+            task_worker_loop(
+                st,
+                wid,
+                slot,
+                healthy,
+                active_tasks,
+                CONVERSATION_LOCK_LEASE_SECONDS,
+                CONVERSATION_LOCK_RENEW_EVERY_SECONDS,
+            )
+            .await;
+        });
+    }
+
+    // Leader election: the worker_lock holder runs cron scheduling and DB hygiene so
+    // replicas don't duplicate work.
+    let mut last_lock_attempt: Option<Instant> = None;
+    let mut last_cleanup = Instant::now();
+    let mut last_cron_check = Instant::now();
+    let mut last_orphan_check = Instant::now();
+    let mut last_conv_lock_cleanup = Instant::now();
+    let mut last_scale_check = Instant::now();
+    let mut last_digest_check = Instant::now();
+    let mut last_elevation_check = Instant::now();
+    let mut scale_pressured = false;
     loop {
-        // Acquire the worker lock so only one instance processes tasks at a time.
-        loop {
+        let leading = is_leader.load(Ordering::SeqCst);
+        let attempt_every = if leading {
+            LEADER_LOCK_RENEW_EVERY_SECONDS
+        } else {
+            LEADER_LOCK_RETRY_EVERY_SECONDS
+        };
+        if last_lock_attempt.is_none_or(|t| t.elapsed() >= Duration::from_secs(attempt_every)) {
+            last_lock_attempt = Some(Instant::now());
             match db::try_acquire_or_renew_worker_lock(
                 &state.pool,
                 &worker_id,
-                WORKER_LOCK_LEASE_SECONDS,
+                LEADER_LOCK_LEASE_SECONDS,
             )
             .await
             {
                 Ok(true) => {
-                    info!(%worker_id, "acquired worker lock");
-                    break;
+                    if !is_leader.swap(true, Ordering::SeqCst) {
+                        info!(%worker_id, "acquired leader lock");
+                        run_leader_hygiene(&state, HEARTBEAT_STALE_SECONDS).await;
+                        last_cleanup = Instant::now();
+                        last_orphan_check = Instant::now();
+                    }
                 }
                 Ok(false) => {
-                    tokio::time::sleep(Duration::from_secs(2)).await;
+                    if is_leader.swap(false, Ordering::SeqCst) {
+                        warn!(%worker_id, "lost leader lock");
+                    }
                 }
                 Err(err) => {
-                    warn!(error = %err, "failed to acquire worker lock");
-                    tokio::time::sleep(Duration::from_secs(2)).await;
+                    warn!(error = %err, %worker_id, "failed to acquire leader lock");
                 }
             }
         }
 
-        // Reset tasks left in-flight after restart.
-        match db::reset_running_tasks(&state.pool).await {
-            Ok(n) if n > 0 => {
-                warn!(
-                    count = n,
-                    "re-queued tasks left in running state after worker restart"
-                );
-            }
-            Ok(_) => {}
-            Err(err) => {
-                warn!(error = %err, "failed to reset running tasks after acquiring lock");
-            }
+        if !is_leader.load(Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            continue;
         }
 
-        // Clear any stale per-conversation locks / runtime state.
-        let _ = db::clear_all_conversation_locks(&state.pool).await;
-        let _ = db::clear_runtime_active_tasks(&state.pool).await;
-
-        // Periodic DB hygiene (only the lock-holder runs this).
-        match db::cleanup_old_tasks(&state.pool, 30).await {
-            Ok(n) if n > 0 => info!(count = n, "cleaned up old tasks"),
-            Ok(_) => {}
-            Err(err) => warn!(error = %err, "failed to cleanup old tasks"),
-        }
-        match db::cleanup_old_processed_events(&state.pool, 7).await {
-            Ok(n) if n > 0 => info!(count = n, "cleaned up old processed events"),
-            Ok(_) => {}
-            Err(err) => warn!(error = %err, "failed to cleanup old processed events"),
+        if last_cleanup.elapsed() >= Duration::from_secs(60 * 60) {
+            run_leader_hygiene(&state, HEARTBEAT_STALE_SECONDS).await;
+            last_cleanup = Instant::now();
         }
 
-        let has_lock = Arc::new(AtomicBool::new(true));
-        let has_lock2 = has_lock.clone();
-        let pool = state.pool.clone();
-        let worker_id2 = worker_id.clone();
-        tokio::spawn(async move {
-            loop {
-                tokio::time::sleep(Duration::from_secs(WORKER_LOCK_RENEW_EVERY_SECONDS)).await;
-                match db::try_acquire_or_renew_worker_lock(
-                    &pool,
-                    &worker_id2,
-                    WORKER_LOCK_LEASE_SECONDS,
-                )
-                .await
-                {
-                    Ok(true) => {}
-                    Ok(false) => {
-                        warn!(%worker_id2, "lost worker lock");
-                        has_lock2.store(false, Ordering::SeqCst);
-                        break;
-                    }
-                    Err(err) => {
-                        warn!(error = %err, %worker_id2, "failed to renew worker lock");
-                    }
+        // Re-queue work from replicas that died mid-task.
+        if last_orphan_check.elapsed() >= Duration::from_secs(15) {
+            last_orphan_check = Instant::now();
+            match db::requeue_orphaned_tasks(&state.pool, HEARTBEAT_STALE_SECONDS).await {
+                Ok(n) if n > 0 => {
+                    warn!(
+                        count = n,
+                        "re-queued tasks from workers that stopped heartbeating"
+                    );
+                    state.task_notify.notify_waiters();
                 }
+                Ok(_) => {}
+                Err(err) => warn!(error = %err, "failed to re-queue orphaned tasks"),
             }
-        });
+        }
 
-        // Spawn task workers. Each worker keeps its own Codex subprocess.
-        let mut workers: Vec<tokio::task::JoinHandle<()>> = Vec::new();
-        for slot in 0..concurrency {
-            let st = state.clone();
-            let wid = worker_id.clone();
-            let has = has_lock.clone();
-            workers.push(tokio::spawn(async move {
-                task_worker_loop(
-                    st,
-                    wid,
-                    slot,
-                    has,
-                    CONVERSATION_LOCK_LEASE_SECONDS,
-                    CONVERSATION_LOCK_RENEW_EVERY_SECONDS,
-                )
-                .await;
-            }));
-        }
-
-        let mut last_cleanup = Instant::now();
-        let mut last_cron_check = Instant::now();
-        let mut last_conv_lock_cleanup = Instant::now();
-        while has_lock.load(Ordering::SeqCst) {
-            if last_cleanup.elapsed() >= Duration::from_secs(60 * 60) {
-                match db::cleanup_old_tasks(&state.pool, 30).await {
-                    Ok(n) if n > 0 => info!(count = n, "cleaned up old tasks"),
-                    Ok(_) => {}
-                    Err(err) => warn!(error = %err, "failed to cleanup old tasks"),
-                }
-                match db::cleanup_old_processed_events(&state.pool, 7).await {
-                    Ok(n) if n > 0 => info!(count = n, "cleaned up old processed events"),
-                    Ok(_) => {}
-                    Err(err) => warn!(error = %err, "failed to cleanup old processed events"),
-                }
-                last_cleanup = Instant::now();
-            }
+        // Clear expired conversation locks so backlog doesn't get stuck after crashes.
+        if last_conv_lock_cleanup.elapsed() >= Duration::from_secs(30) {
+            last_conv_lock_cleanup = Instant::now();
+            let _ = db::cleanup_expired_conversation_locks(&state.pool).await;
+        }
 
-            // Clear expired conversation locks so backlog doesn't get stuck after crashes.
-            if last_conv_lock_cleanup.elapsed() >= Duration::from_secs(30) {
-                last_conv_lock_cleanup = Instant::now();
-                let _ = db::cleanup_expired_conversation_locks(&state.pool).await;
-            }
+        // Emit scale events when queue thresholds are crossed.
+        if last_scale_check.elapsed() >= Duration::from_secs(30) {
+            last_scale_check = Instant::now();
+            crate::scale_hint::check_thresholds(&state, &mut scale_pressured).await;
+        }
 
-            // Enqueue due cron jobs. This is done by the lock-holder so replicas don't duplicate work.
-            if last_cron_check.elapsed() >= Duration::from_secs(2) {
-                last_cron_check = Instant::now();
-                if let Ok(settings) = db::get_settings(&state.pool).await {
-                    if settings.allow_cron {
-                        if let Err(err) = enqueue_due_cron_jobs(&state).await {
-                            warn!(error = %err, "failed to enqueue due cron jobs");
-                        }
+        // Post monthly usage reports once the month turns over.
+        if last_digest_check.elapsed() >= Duration::from_secs(15 * 60) {
+            last_digest_check = Instant::now();
+            crate::digest::send_due_reports(&state).await;
+        }
+
+        // Tell channels whose elevated permissions ran out.
+        if last_elevation_check.elapsed() >= Duration::from_secs(60) {
+            last_elevation_check = Instant::now();
+            crate::elevation::announce_expired(&state).await;
+        }
+
+        // Enqueue due cron jobs. This is done by the leader so replicas don't duplicate work.
+        if last_cron_check.elapsed() >= Duration::from_secs(2) {
+            last_cron_check = Instant::now();
+            if let Err(err) = propagate_dependency_failures(&state).await {
+                warn!(error = %err, "failed to propagate task dependency failures");
+            }
+            if let Ok(settings) = db::get_settings(&state.pool).await {
+                if settings.allow_cron {
+                    if let Err(err) = enqueue_due_cron_jobs(&state).await {
+                        warn!(error = %err, "failed to enqueue due cron jobs");
                     }
                 }
             }
-
-            tokio::time::sleep(Duration::from_millis(250)).await;
         }
 
-        // Lock was lost; abort workers so no more tasks run in this instance.
-        for h in workers {
-            h.abort();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// Periodic DB hygiene (only the leader runs this).
+async fn run_leader_hygiene(state: &AppState, heartbeat_stale_seconds: i64) {
+    match db::requeue_orphaned_tasks(&state.pool, heartbeat_stale_seconds).await {
+        Ok(n) if n > 0 => {
+            warn!(
+                count = n,
+                "re-queued tasks left running by a stopped worker"
+            );
+            state.task_notify.notify_waiters();
         }
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        Ok(_) => {}
+        Err(err) => warn!(error = %err, "failed to re-queue orphaned tasks"),
+    }
+    match db::cleanup_old_tasks(&state.pool, 30).await {
+        Ok(n) if n > 0 => info!(count = n, "cleaned up old tasks"),
+        Ok(_) => {}
+        Err(err) => warn!(error = %err, "failed to cleanup old tasks"),
+    }
+    match db::cleanup_old_processed_events(&state.pool, 7).await {
+        Ok(n) if n > 0 => info!(count = n, "cleaned up old processed events"),
+        Ok(_) => {}
+        Err(err) => warn!(error = %err, "failed to cleanup old processed events"),
     }
+    match db::delete_stale_worker_heartbeats(&state.pool, 24 * 60 * 60).await {
+        Ok(n) if n > 0 => info!(count = n, "removed stale worker heartbeats"),
+        Ok(_) => {}
+        Err(err) => warn!(error = %err, "failed to remove stale worker heartbeats"),
+    }
+}
+
+fn local_hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
 }
 
 async fn task_worker_loop(
     state: AppState,
     worker_id: String,
     slot: usize,
-    has_lock: Arc<AtomicBool>,
+    healthy: Arc<AtomicBool>,
+    active_tasks: Arc<AtomicUsize>,
     conversation_lease_seconds: i64,
     conversation_renew_every_seconds: u64,
 ) {
     let mut codex = CodexManager::new(state.config.clone());
 
-    while has_lock.load(Ordering::SeqCst) {
+    loop {
+        if !healthy.load(Ordering::SeqCst) {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            continue;
+        }
         match db::claim_next_task(&state.pool, &worker_id, conversation_lease_seconds).await {
-            Ok(Some(task)) => {
+            Ok(Some((task, claim))) => {
                 let task_id = task.id;
                 let conversation_key = task.conversation_key.clone();
+                active_tasks.fetch_add(1, Ordering::SeqCst);
 
                 if let Err(err) = db::mark_task_active(&state.pool, task_id).await {
                     warn!(error = %err, task_id, "failed to mark task active");
@@ -205,10 +299,8 @@ async fn task_worker_loop(
                 let pool = state.pool.clone();
                 let worker_id2 = worker_id.clone();
                 let conversation_key2 = conversation_key.clone();
-                let has_lock2 = has_lock.clone();
                 let renew_handle = tokio::spawn(async move {
-                    while has_lock2.load(Ordering::SeqCst) && keep_renewing2.load(Ordering::SeqCst)
-                    {
+                    while keep_renewing2.load(Ordering::SeqCst) {
                         tokio::time::sleep(Duration::from_secs(conversation_renew_every_seconds))
                             .await;
                         let _ = db::try_renew_conversation_lock(
@@ -221,33 +313,113 @@ async fn task_worker_loop(
                     }
                 });
 
-                let result = process_task(&state, &mut codex, &task).await;
+                // Modal tasks may carry a deadline and re-runs a timeout, which starts now that
+                // the task is claimed: stop the run (like an admin cancel) when it passes.
+                let options = crate::models::TaskOptions::parse(&task.options_json);
+                // An elevated run is stopped the same way when the channel's window ends, so
+                // nothing it started keeps `full` permissions past it.
+                let elevation = match crate::elevation::for_task(&state, &task, &options).await {
+                    Ok(session) => session,
+                    Err(err) => {
+                        warn!(error = %err, task_id, "failed to check channel elevation");
+                        None
+                    }
+                };
+                let elevation_ends_at = elevation.as_ref().map(|s| s.ends_at);
+                let deadline = match (options.deadline(task.started_at), elevation_ends_at) {
+                    (Some(d), Some(e)) => Some(d.min(e)),
+                    (d, e) => d.or(e),
+                };
+                let deadline_handle = deadline.map(|deadline| {
+                    let pool = state.pool.clone();
+                    tokio::spawn(async move {
+                        let wait = (deadline - chrono::Utc::now().timestamp()).max(0) as u64;
+                        tokio::time::sleep(Duration::from_secs(wait)).await;
+                        let _ = db::request_task_deadline_stop(&pool, task_id).await;
+                    })
+                });
+
+                let result =
+                    process_task(&state, &mut codex, &task, &claim, elevation.as_ref()).await;
+                if let Some(handle) = deadline_handle {
+                    handle.abort();
+                }
+                // The leader re-queued the task while we were stalled; whoever holds it now
+                // owns the outcome, so record nothing and tell nobody.
+                let claim_held = db::task_claim_held(&state.pool, task_id, &claim)
+                    .await
+                    .unwrap_or(true);
                 match result {
+                    _ if !claim_held => {
+                        warn!(
+                            task_id,
+                            worker_slot = slot,
+                            "lost task claim; dropping result"
+                        );
+                    }
                     Ok(text) => {
-                        if let Err(err) =
-                            db::complete_task_success(&state.pool, task_id, &text).await
-                        {
-                            warn!(error = %err, task_id, "failed to mark task succeeded");
+                        match db::complete_task_success(&state.pool, task_id, &claim, &text).await {
+                            Ok(true) => {}
+                            Ok(false) => {
+                                warn!(task_id, "lost task claim before recording success")
+                            }
+                            Err(err) => {
+                                warn!(error = %err, task_id, "failed to mark task succeeded")
+                            }
                         }
+                        // Dependent tasks may now be claimable.
+                        state.task_notify.notify_waiters();
                     }
                     Err(err) => {
-                        let msg = format!("{err:#}");
-                        warn!(error = %msg, task_id, worker_slot = slot, "task failed");
+                        let classified = crate::errors::classify(&err);
+                        let reference = crate::errors::error_ref();
+                        let msg = format!("[{reference}] {err:#}");
+                        warn!(
+                            error = %msg,
+                            kind = classified.kind.as_str(),
+                            task_id,
+                            worker_slot = slot,
+                            "task failed"
+                        );
 
                         let was_cancel_requested =
                             db::is_task_cancel_requested(&state.pool, task_id)
                                 .await
                                 .unwrap_or(false);
-                        if was_cancel_requested {
-                            let _ = db::complete_task_cancelled(&state.pool, task_id).await;
+                        let deadline_reached =
+                            deadline.is_some_and(|d| chrono::Utc::now().timestamp() >= d);
+                        if was_cancel_requested && deadline_reached {
+                            let (stop_reason, why) = if deadline == elevation_ends_at {
+                                (
+                                    "elevation ended",
+                                    "this channel's elevated permissions ended",
+                                )
+                            } else {
+                                ("deadline reached", "it reached its deadline")
+                            };
+                            let _ = db::complete_task_failure(
+                                &state.pool,
+                                task_id,
+                                &claim,
+                                &format!("[{reference}] stopped: {stop_reason}"),
+                            )
+                            .await;
+                            let user_msg = format!(
+                                "I stopped task #{task_id} because {why} ({}).",
+                                format_deadline(deadline.unwrap_or_default())
+                            );
+                            let _ = send_user_message(&state, &task, &user_msg).await;
+                        } else if was_cancel_requested {
+                            let _ = db::complete_task_cancelled(&state.pool, task_id, &claim).await;
                         } else {
-                            let _ = db::complete_task_failure(&state.pool, task_id, &msg).await;
+                            let _ =
+                                db::complete_task_failure(&state.pool, task_id, &claim, &msg).await;
 
                             // Proactive tasks should never spam the channel on failure.
                             if !task.is_proactive {
                                 let user_msg = format!(
-                                    "Task #{task_id} failed. Check /admin/tasks for details.\n\nError: {short}",
-                                    short = shorten_error(&msg)
+                                    "Sorry, I couldn't finish task #{task_id}. {detail}\n\nReference for admins: {reference}",
+                                    detail = classified.message
                                 );
                                 let _ = send_user_message(&state, &task, &user_msg).await;
                             }
@@ -261,6 +433,7 @@ async fn task_worker_loop(
                 let _ =
                     db::release_conversation_lock(&state.pool, &conversation_key, &worker_id).await;
                 let _ = db::mark_task_inactive(&state.pool, task_id).await;
+                active_tasks.fetch_sub(1, Ordering::SeqCst);
             }
             Ok(None) => {
                 tokio::select! {
@@ -274,8 +447,28 @@ async fn task_worker_loop(
             }
         }
     }
+}
 
-    codex.stop().await;
+async fn propagate_dependency_failures(state: &AppState) -> anyhow::Result<()> {
+    for task_id in db::propagate_dependency_failures(&state.pool).await? {
+        let Some(task) = db::get_task(&state.pool, task_id).await? else {
+            continue;
+        };
+        info!(
+            task_id,
+            depends_on = task.depends_on_task_id.unwrap_or_default(),
+            status = %task.status,
+            "resolved task with unsuccessful dependency"
+        );
+        if task.status == "failed" && !task.is_proactive {
+            let user_msg = format!(
+                "Task #{task_id} was not started because task #{} did not succeed.",
+                task.depends_on_task_id.unwrap_or_default()
+            );
+            let _ = send_user_message(state, &task, &user_msg).await;
+        }
+    }
+    Ok(())
 }
 
 async fn enqueue_due_cron_jobs(state: &AppState) -> anyhow::Result<()> {
@@ -429,13 +622,16 @@ async fn enqueue_due_cron_jobs(state: &AppState) -> anyhow::Result<()> {
         // Enqueue a regular task so the existing worker pipeline handles it.
         let _task_id = db::enqueue_task(
             &state.pool,
-            provider,
-            &job.workspace_id,
-            &job.channel_id,
-            &job.thread_ts,
-            &event_ts,
-            "cron",
-            &prompt,
+            &db::NewTask {
+                provider,
+                workspace_id: &job.workspace_id,
+                channel_id: &job.channel_id,
+                thread_ts: &job.thread_ts,
+                event_ts: &event_ts,
+                requested_by_user_id: "cron",
+                prompt_text: &prompt,
+                ..Default::default()
+            },
         )
         .await?;
         state.task_notify.notify_waiters();
@@ -482,7 +678,7 @@ async fn enqueue_due_cron_jobs(state: &AppState) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn compute_next_run_at(
+pub fn compute_next_run_at(
     job: &crate::models::CronJob,
     now: chrono::DateTime<chrono::Utc>,
 ) -> anyhow::Result<Option<i64>> {
@@ -590,6 +786,65 @@ fn extract_github_repo_pairs(text: &str) -> Vec<(String, String)> {
     out
 }
 
+/// Environment policy for the task's sandboxed commands (see `command_env`).
+async fn resolve_command_env(
+    state: &AppState,
+    settings: &crate::models::Settings,
+    task: &crate::models::Task,
+) -> crate::command_env::ResolvedEnv {
+    let cfg = crate::command_env::parse_command_env(&settings.command_env).unwrap_or_else(|err| {
+        warn!(error = %err, "invalid command_env setting; injecting nothing");
+        Default::default()
+    });
+    let rules = crate::guardrail_canary::rule_sets(state, "env", &task.channel_id)
+        .await
+        .map(|sets| sets.applied)
+        .unwrap_or_else(|err| {
+            warn!(error = %err, "failed to load env guardrail rules");
+            Vec::new()
+        });
+    let repos: Vec<String> = extract_github_repo_pairs(&task.prompt_text)
+        .into_iter()
+        .map(|(owner, repo)| format!("{owner}/{repo}"))
+        .collect();
+    let mut secrets = HashMap::new();
+    for name in crate::command_env::secret_names(&cfg, &task.workspace_id, &repos) {
+        match crate::secrets::load_custom_secret_opt(state, &name).await {
+            Ok(Some(value)) => {
+                secrets.insert(name, value);
+            }
+            Ok(None) => {}
+            Err(err) => warn!(error = %err, secret = %name, "failed to load command_env secret"),
+        }
+    }
+    let env = crate::command_env::resolve(
+        &cfg,
+        &task.workspace_id,
+        &repos,
+        &rules,
+        |name| secrets.get(name).cloned(),
+        std::env::vars_os().filter_map(|(k, _)| k.into_string().ok()),
+    );
+    if !env.set.is_empty() || !env.dropped.is_empty() || !env.exclude.is_empty() {
+        let _ = db::create_task_trace(
+            &state.pool,
+            task.id,
+            "env.policy",
+            "info",
+            "command environment resolved",
+            &format!(
+                "inherit={} set=[{}] excluded=[{}] dropped=[{}]",
+                env.inherit.as_str(),
+                env.set.keys().cloned().collect::<Vec<_>>().join(", "),
+                env.exclude.join(", "),
+                env.dropped.join(", ")
+            ),
+        )
+        .await;
+    }
+    env
+}
+
 async fn repo_lock_for_key(key: &str) -> Arc<tokio::sync::Mutex<()>> {
     let mut locks = REPO_CLONE_LOCKS.lock().await;
     locks
@@ -782,8 +1037,53 @@ async fn process_task(
     state: &AppState,
     codex: &mut CodexManager,
     task: &crate::models::Task,
+    claim: &crate::models::TaskClaim,
+    elevation: Option<&crate::models::ElevatedSession>,
 ) -> anyhow::Result<String> {
-    let settings = db::get_settings(&state.pool).await?;
+    let transcribed = crate::stt::transcribe_slack_audio(state, task).await;
+    let task = transcribed.as_ref().unwrap_or(task);
+    let mut settings = db::get_settings(&state.pool).await?;
+
+    // Options from the Slack task modal can only narrow what the workspace allows.
+    let options = crate::models::TaskOptions::parse(&task.options_json);
+    if options
+        .deadline(task.started_at)
+        .is_some_and(|d| chrono::Utc::now().timestamp() >= d)
+    {
+        return Err(crate::errors::bad_request(
+            "The deadline for this task passed before it could start.",
+        ));
+    }
+    if options.permissions_mode == Some(crate::models::PermissionsMode::Read) {
+        settings.permissions_mode = crate::models::PermissionsMode::Read;
+    } else if settings.permissions_mode == crate::models::PermissionsMode::Read {
+        // A time-boxed elevation lifts the channel to `full` (the worker stops the run when
+        // it ends).
+        if let Some(session) = elevation {
+            settings.permissions_mode = crate::models::PermissionsMode::Full;
+            let details = serde_json::json!({
+                "session_id": session.id,
+                "granted_by": session.granted_by,
+                "reason": session.reason,
+                "ends_at": session.ends_at,
+            });
+            if let Err(err) = db::create_task_trace(
+                &state.pool,
+                task.id,
+                "task.elevated",
+                "warn",
+                &format!(
+                    "Running with full permissions (elevated by {})",
+                    session.granted_by
+                ),
+                &details.to_string(),
+            )
+            .await
+            {
+                warn!(error = %err, task_id = task.id, "failed to record elevation trace");
+            }
+        }
+    }
 
     let provider = task.provider.trim().to_ascii_lowercase();
     let mut slack: Option<SlackClient> = None;
@@ -793,40 +1093,103 @@ async fn process_task(
     let mut msteams: Option<crate::msteams::TeamsClient> = None;
     let mut slack_bot_token_for_mcp: Option<String> = None;
 
-    let context_text = match provider.as_str() {
+    // Resolve the model first: the token budget below depends on it.
+    let mut backend = crate::llm::resolve_backend(&settings.llm_backends, &task.workspace_id);
+    if let Some(model) = options.model.as_deref() {
+        // Checked against the backend's allowed_models.
+        backend.model = Some(model.to_string());
+    }
+    let settings = crate::llm::settings_for_backend(&backend, &settings)?;
+
+    let context_plan =
+        crate::context_sources::resolve_context_plan(&settings.context_sources, &task.channel_id);
+    let mut context = ContextBuilder::new(&context_plan);
+    // With a token budget for this model, fetch deeper and let packing decide what fits.
+    let history_limit = match crate::token_budget::budget_for_model(
+        &settings.context_token_budget,
+        settings.model.as_deref().unwrap_or(""),
+    ) {
+        Some(_) => crate::token_budget::FETCH_LIMIT,
+        None => settings.context_last_n,
+    };
+
+    match provider.as_str() {
         "slack" => {
             let Some(slack_bot_token) = crate::secrets::load_slack_bot_token_opt(state).await?
             else {
-                anyhow::bail!("SLACK_BOT_TOKEN is not configured");
+                return Err(crate::errors::config("SLACK_BOT_TOKEN is not configured"));
+            };
+            // Synthetic (admin test) tasks read context like any other but can't post.
+            let client = if task.is_synthetic {
+                SlackClient::read_only(state.http.clone(), slack_bot_token.clone())
+            } else {
+                SlackClient::new(state.http.clone(), slack_bot_token.clone())
             };
-            let client = SlackClient::new(state.http.clone(), slack_bot_token.clone());
 
-            let ctx = if !task.thread_ts.is_empty() && task.thread_ts != task.event_ts {
-                client
+            let in_thread = !task.thread_ts.is_empty() && task.thread_ts != task.event_ts;
+            let mut seen: Vec<crate::slack::SlackMessage> = Vec::new();
+            if in_thread && context_plan.enabled(ContextSource::ThreadHistory) {
+                let ctx = client
                     .fetch_thread_replies(
                         &task.channel_id,
                         &task.thread_ts,
                         &task.event_ts,
-                        settings.context_last_n,
+                        history_limit,
                     )
-                    .await?
-            } else {
-                client
-                    .fetch_channel_history(
-                        &task.channel_id,
-                        &task.event_ts,
-                        settings.context_last_n,
-                    )
-                    .await?
-            };
+                    .await?;
+                context.add(ContextSource::ThreadHistory, format_slack_context(&ctx));
+                seen.extend(ctx);
+            }
+            if (!in_thread || context_plan.channel_history_in_threads)
+                && context_plan.enabled(ContextSource::ChannelHistory)
+            {
+                // For threaded tasks, channel history is what led up to the thread.
+                let latest = if in_thread {
+                    &task.thread_ts
+                } else {
+                    &task.event_ts
+                };
+                let ctx = client
+                    .fetch_channel_history(&task.channel_id, latest, history_limit)
+                    .await?;
+                context.add(ContextSource::ChannelHistory, format_slack_context(&ctx));
+                seen.extend(ctx);
+            }
+            if context_plan.enabled(ContextSource::PinnedMessages) {
+                match client.list_pins(&task.channel_id).await {
+                    Ok(pins) => {
+                        context.add(ContextSource::PinnedMessages, format_slack_context(&pins));
+                        seen.extend(pins);
+                    }
+                    Err(err) => {
+                        warn!(error = %err, task_id = task.id, "failed to fetch pinned messages");
+                    }
+                }
+            }
+            if context_plan.enabled(ContextSource::LinkedFiles) {
+                context.add(
+                    ContextSource::LinkedFiles,
+                    fetch_slack_linked_files(&client, &seen, task.id).await,
+                );
+            }
+            if !options.context_channels.is_empty()
+                && context_plan.enabled(ContextSource::ChannelHistory)
+            {
+                context.add(
+                    ContextSource::ChannelHistory,
+                    fetch_attached_channels(&client, &settings, task, &options.context_channels)
+                        .await,
+                );
+            }
 
             slack = Some(client);
             slack_bot_token_for_mcp = Some(slack_bot_token);
-            format_slack_context(&ctx)
         }
         "telegram" => {
             let Some(token) = crate::secrets::load_telegram_bot_token_opt(state).await? else {
-                anyhow::bail!("TELEGRAM_BOT_TOKEN is not configured");
+                return Err(crate::errors::config(
+                    "TELEGRAM_BOT_TOKEN is not configured",
+                ));
             };
             let client = TelegramClient::new(state.http.clone(), token);
 
@@ -838,21 +1201,25 @@ async fn process_task(
                 &state.pool,
                 &task.channel_id,
                 before_message_id,
-                settings.context_last_n,
+                history_limit,
             )
             .await?;
 
             telegram = Some(client);
-            format_telegram_context(&ctx)
+            context.add(ContextSource::ThreadHistory, format_telegram_context(&ctx));
         }
         "whatsapp" => {
             let Some(access_token) = crate::secrets::load_whatsapp_access_token_opt(state).await?
             else {
-                anyhow::bail!("WHATSAPP_ACCESS_TOKEN is not configured");
+                return Err(crate::errors::config(
+                    "WHATSAPP_ACCESS_TOKEN is not configured",
+                ));
             };
             let Some(phone_id) = crate::secrets::load_whatsapp_phone_number_id_opt(state).await?
             else {
-                anyhow::bail!("WHATSAPP_PHONE_NUMBER_ID is not configured");
+                return Err(crate::errors::config(
+                    "WHATSAPP_PHONE_NUMBER_ID is not configured",
+                ));
             };
             whatsapp = Some(crate::whatsapp::WhatsAppClient::new(
                 state.http.clone(),
@@ -860,26 +1227,26 @@ async fn process_task(
                 phone_id,
             ));
             // No context fetching for WhatsApp yet.
-            String::new()
         }
         "discord" => {
             let Some(bot_token) = crate::secrets::load_discord_bot_token_opt(state).await? else {
-                anyhow::bail!("DISCORD_BOT_TOKEN is not configured");
+                return Err(crate::errors::config("DISCORD_BOT_TOKEN is not configured"));
             };
             discord = Some(crate::discord::DiscordClient::new(
                 state.http.clone(),
                 bot_token,
             ));
             // No context fetching for Discord yet.
-            String::new()
         }
         "msteams" => {
             let Some(app_id) = crate::secrets::load_msteams_app_id_opt(state).await? else {
-                anyhow::bail!("MSTEAMS_APP_ID is not configured");
+                return Err(crate::errors::config("MSTEAMS_APP_ID is not configured"));
             };
             let Some(app_password) = crate::secrets::load_msteams_app_password_opt(state).await?
             else {
-                anyhow::bail!("MSTEAMS_APP_PASSWORD is not configured");
+                return Err(crate::errors::config(
+                    "MSTEAMS_APP_PASSWORD is not configured",
+                ));
             };
             msteams = Some(crate::msteams::TeamsClient::new(
                 state.http.clone(),
@@ -887,60 +1254,153 @@ async fn process_task(
                 app_password,
             ));
             // No context fetching for MS Teams yet.
-            String::new()
         }
         other => anyhow::bail!("unknown task provider: {other}"),
-    };
+    }
 
-    let openai_api_key = crate::secrets::load_openai_api_key_opt(state).await?;
-    if openai_api_key.is_none() {
-        let codex_home = state.config.effective_codex_home();
-        let auth_summary = crate::codex_login::read_auth_summary(&codex_home).await?;
-        if !auth_summary.file_present {
-            anyhow::bail!(
-                "OpenAI auth not configured. Set OPENAI_API_KEY (env), store it in /admin/settings, or log in via /admin/auth."
-            );
+    // Banned topics: decline before any agent or cache work.
+    if !task.is_proactive {
+        if let Some(decline) = content_policy_decline(
+            state,
+            &settings,
+            task,
+            crate::content_policy::Direction::Prompt,
+            &task.prompt_text,
+        )
+        .await?
+        {
+            send_user_message(state, task, &decline).await?;
+            return Ok(decline);
         }
     }
 
-    let allow_slack_mcp = provider == "slack" && settings.allow_slack_mcp;
-    let allow_web_mcp = settings.allow_web_mcp;
-    let browser = crate::codex::BrowserEnvConfig::from_env();
-    let brave_search_api_key = crate::secrets::load_brave_search_api_key_opt(state).await?;
-    codex
-        .ensure_started(
-            openai_api_key.as_deref(),
-            if allow_slack_mcp {
-                slack_bot_token_for_mcp.as_deref()
-            } else {
-                None
-            },
-            if allow_slack_mcp {
-                Some(settings.slack_allow_channels.as_str())
-            } else {
-                None
-            },
-            if allow_web_mcp {
-                brave_search_api_key.as_deref()
-            } else {
-                None
-            },
-            if allow_web_mcp {
-                Some(settings.web_allow_domains.as_str())
-            } else {
-                None
-            },
-            if allow_web_mcp {
-                Some(settings.web_deny_domains.as_str())
-            } else {
-                None
-            },
-            allow_slack_mcp,
-            allow_web_mcp,
-            Some(settings.extra_mcp_config.as_str()),
-            &browser,
-        )
-        .await?;
+    // Repeated prompt: answer from the response cache instead of running the agent.
+    let mut cache_key = None;
+    if options.output_schema.is_none() && crate::response_cache::eligible(&settings, task) {
+        let key = crate::response_cache::prepare(state, &settings, task).await;
+        match crate::response_cache::lookup(state, &settings, task, &key).await {
+            Ok(Some(hit)) => {
+                let sl = slack.as_ref().context("slack client missing")?;
+                crate::response_cache::post_cached_reply(sl, task, &hit).await?;
+                let _ = db::create_task_trace(
+                    &state.pool,
+                    task.id,
+                    "cache.hit",
+                    "info",
+                    "answered from response cache",
+                    &format!("task #{} similarity={:.3}", hit.task_id, hit.similarity),
+                )
+                .await;
+                info!(
+                    task_id = task.id,
+                    cached_task_id = hit.task_id,
+                    "replied from response cache"
+                );
+                return Ok(hit.response_text);
+            }
+            Ok(None) => cache_key = Some(key),
+            Err(err) => warn!(error = %err, task_id = task.id, "response cache lookup failed"),
+        }
+    }
+
+    let mut browser = crate::codex::BrowserEnvConfig::from_env();
+    let mut agent = match backend.provider {
+        crate::llm::BackendProvider::Codex => {
+            let openai_api_key = crate::secrets::load_openai_api_key_opt(state).await?;
+            if openai_api_key.is_none() {
+                let codex_home = state.config.effective_codex_home();
+                let auth_summary = crate::codex_login::read_auth_summary(&codex_home).await?;
+                if !auth_summary.file_present {
+                    anyhow::bail!(
+                        "OpenAI auth not configured. Set OPENAI_API_KEY (env), store it in /admin/settings, or log in via /admin/auth."
+                    );
+                }
+            }
+
+            let allow_slack_mcp = provider == "slack" && settings.allow_slack_mcp;
+            let allow_web_mcp = settings.allow_web_mcp;
+            let brave_search_api_key = crate::secrets::load_brave_search_api_key_opt(state).await?;
+            codex
+                .ensure_started(
+                    openai_api_key.as_deref(),
+                    if allow_slack_mcp {
+                        slack_bot_token_for_mcp.as_deref()
+                    } else {
+                        None
+                    },
+                    if allow_slack_mcp {
+                        Some(settings.slack_allow_channels.as_str())
+                    } else {
+                        None
+                    },
+                    if allow_slack_mcp {
+                        Some(task.workspace_id.as_str()).filter(|t| !t.trim().is_empty())
+                    } else {
+                        None
+                    },
+                    if allow_web_mcp {
+                        brave_search_api_key.as_deref()
+                    } else {
+                        None
+                    },
+                    if allow_web_mcp {
+                        Some(settings.web_allow_domains.as_str())
+                    } else {
+                        None
+                    },
+                    if allow_web_mcp {
+                        Some(settings.web_deny_domains.as_str())
+                    } else {
+                        None
+                    },
+                    allow_slack_mcp,
+                    task.is_synthetic,
+                    allow_web_mcp,
+                    Some(settings.extra_mcp_config.as_str()),
+                    &browser,
+                )
+                .await?;
+            let env = resolve_command_env(state, &settings, task).await;
+            codex.set_thread_config(env.to_codex_config());
+            Agent::Codex(codex)
+        }
+        crate::llm::BackendProvider::Anthropic => {
+            let Some(key) = crate::secrets::load_anthropic_api_key_opt(state).await? else {
+                return Err(crate::errors::config("ANTHROPIC_API_KEY is not configured"));
+            };
+            Agent::Chat(ChatBackend::new(
+                &backend,
+                state.http.clone(),
+                Some(key),
+                &state.config.data_dir,
+            ))
+        }
+        crate::llm::BackendProvider::OpenaiCompatible => {
+            let key = match backend.api_key_secret.as_deref() {
+                Some(name) => {
+                    let key = crate::secrets::load_custom_secret_opt(state, name.trim()).await?;
+                    if key.is_none() {
+                        return Err(crate::errors::config(format!(
+                            "llm_backends secret {} is not stored",
+                            name.trim()
+                        )));
+                    }
+                    key
+                }
+                None => None,
+            };
+            Agent::Chat(ChatBackend::new(
+                &backend,
+                state.http.clone(),
+                key,
+                &state.config.data_dir,
+            ))
+        }
+    };
+    // Chat backends have no tools; keep the prompt from advertising them.
+    let allow_slack_mcp = agent.supports_tools() && provider == "slack" && settings.allow_slack_mcp;
+    let allow_web_mcp = agent.supports_tools() && settings.allow_web_mcp;
+    browser.enabled = browser.enabled && agent.supports_tools();
 
     let conversation_key = if !task.conversation_key.trim().is_empty() {
         task.conversation_key.clone()
@@ -956,6 +1416,21 @@ async fn process_task(
             last_used_at: chrono::Utc::now().timestamp(),
         });
 
+    if !agent.supports_tools()
+        && settings.allow_web_mcp
+        && context_plan.enabled(ContextSource::LinkedPages)
+    {
+        context.add(
+            ContextSource::LinkedPages,
+            fetch_linked_pages(&settings, task).await,
+        );
+    }
+    if context_plan.enabled(ContextSource::PriorTasks) {
+        let prior =
+            db::list_prior_conversation_tasks(&state.pool, &conversation_key, task.id, 5).await?;
+        context.add(ContextSource::PriorTasks, format_prior_tasks(&prior));
+    }
+
     let cwd = state.config.data_dir.join("context");
     let cwd = tokio::fs::canonicalize(&cwd).await.unwrap_or(cwd);
 
@@ -973,7 +1448,15 @@ async fn process_task(
             }
         };
 
-    let thread_id = codex
+    let dependency_context_text = match task.depends_on_task_id {
+        Some(dep_id) => match db::get_task(&state.pool, dep_id).await? {
+            Some(dep) => format_dependency_context(&dep),
+            None => String::new(),
+        },
+        None => String::new(),
+    };
+
+    let thread_id = agent
         .resume_or_start_thread(session.codex_thread_id.as_deref(), &settings, &cwd)
         .await?;
     session.codex_thread_id = Some(thread_id.clone());
@@ -1000,6 +1483,19 @@ async fn process_task(
     };
     let observational_memory_text =
         format_observational_memory_for_prompt(thread_mem.as_ref(), resource_mem.as_ref());
+    let token_budget = crate::token_budget::budget_for_model(
+        &settings.context_token_budget,
+        settings.model.as_deref().unwrap_or(""),
+    );
+    let (observational_memory_text, context_text) = match token_budget {
+        Some(budget) => pack_context_for_budget(
+            budget,
+            observational_memory_text,
+            &session.memory_summary,
+            context,
+        ),
+        None => (observational_memory_text, context.render()),
+    };
 
     let input = build_turn_input(
         task,
@@ -1008,6 +1504,7 @@ async fn process_task(
         &session.memory_summary,
         &context_text,
         &repo_context_text,
+        &dependency_context_text,
         allow_slack_mcp,
         allow_web_mcp,
         &browser,
@@ -1039,17 +1536,17 @@ async fn process_task(
 
     let output_schema = agent_output_schema();
 
-    let out = codex
-        .run_turn(
+    let out = agent
+        .run_turn(AgentTurn {
             state,
             task,
-            &thread_id,
-            &settings,
-            &cwd,
-            &input,
-            output_schema.clone(),
-            Some(&trace_tx),
-        )
+            thread_id: &thread_id,
+            settings: &settings,
+            cwd: &cwd,
+            input_text: &input,
+            output_schema: output_schema.clone(),
+            trace_tx: Some(&trace_tx),
+        })
         .await?;
     drop(trace_tx);
     let _ = trace_writer.await;
@@ -1065,13 +1562,13 @@ async fn process_task(
     if parsed.is_none() {
         if let Ok(v) = repair_agent_output(
             state,
-            codex,
+            &mut agent,
             task,
             &thread_id,
             &settings,
             &cwd,
             &out.agent_message_text,
-            output_schema,
+            output_schema.clone(),
         )
         .await
         {
@@ -1079,10 +1576,31 @@ async fn process_task(
         }
     }
 
+    // API tasks with an output contract: the reply must be JSON matching the caller's schema.
+    if options.output_schema.is_some() {
+        let Some(p) = parsed.as_mut() else {
+            anyhow::bail!("agent output did not match the expected JSON format");
+        };
+        p.reply = enforce_output_contract(
+            state, &mut agent, task, &thread_id, &settings, &cwd, &p.reply,
+        )
+        .await?;
+    }
+
+    // The turn can outlast our heartbeat (a stalled process, a long GC pause). If the leader
+    // re-queued the task meanwhile, another worker is running it: apply nothing.
+    if !db::task_claim_held(&state.pool, task.id, claim).await? {
+        anyhow::bail!("task #{} was re-queued while this worker ran it", task.id);
+    }
+
     let mut should_post_message = true;
     let mut should_persist_session = true;
+    // Plain answers (no side effects) may be reused for repeated prompts.
+    let mut cacheable = false;
+    // Whether the content policy already saw the reply (with everything it publishes).
+    let mut reply_checked = false;
 
-    let reply_text = if let Some(parsed) = parsed {
+    let mut reply_text = if let Some(parsed) = parsed {
         let mut should_reply = if task.is_proactive {
             parsed.should_reply.unwrap_or(false)
         } else {
@@ -1108,18 +1626,36 @@ async fn process_task(
             should_post_message = false;
             should_persist_session = false;
             "(proactive: skipped)".to_string()
+        } else if let Some(decline) = content_policy_decline(
+            state,
+            &settings,
+            task,
+            crate::content_policy::Direction::Reply,
+            &agent_output_text(&cwd, &parsed).await,
+        )
+        .await?
+        {
+            // Checked before any side effect: nothing the agent asked for is written,
+            // uploaded or scheduled when its output is blocked.
+            reply_checked = true;
+            decline
         } else {
+            reply_checked = true;
+            // Synthetic (admin test) tasks skip every durable side effect below.
+            let apply_side_effects = !is_browser_login_needed && !task.is_synthetic;
+            cacheable = !is_browser_login_needed && !requested_side_effects;
+
             // Apply durable updates.
             if settings.permissions_mode == crate::models::PermissionsMode::Full
                 && settings.allow_context_writes
-                && !is_browser_login_needed
+                && apply_side_effects
             {
                 apply_context_writes(&cwd, &parsed.context_writes).await?;
             }
 
             // --- Auto-upload files to Slack ---
             // Upload context_writes + agent-requested upload_files to the originating thread.
-            if provider == "slack" && !is_browser_login_needed {
+            if provider == "slack" && apply_side_effects {
                 if let Some(ref sl) = slack {
                     let thread = thread_opt(&task.thread_ts);
 
@@ -1185,14 +1721,14 @@ async fn process_task(
             }
             session.memory_summary = clamp_len(mem, 6_000);
 
-            if settings.allow_cron && !is_browser_login_needed {
+            if settings.allow_cron && apply_side_effects {
                 if let Err(err) =
                     apply_agent_cron_jobs(state, task, &settings, &parsed.cron_jobs).await
                 {
                     warn!(error = %err, "failed to apply agent cron jobs");
                 }
             }
-            if !is_browser_login_needed {
+            if apply_side_effects {
                 if let Err(err) =
                     apply_agent_guardrail_rules(state, task, &settings, &parsed.guardrail_rules)
                         .await
@@ -1226,41 +1762,90 @@ async fn process_task(
             "I finished, but returned an empty response.".to_string()
         } else {
             let (raw, _) = crate::secrets::redact_secrets(raw);
-            let raw = clamp_len(raw, 6_000);
+            let raw = grail_text::truncate(&raw, 6_000);
             format!(
                 "I generated a response, but it did not match the expected JSON format, so I couldn't safely update memory/context.\n\nRaw output:\n{raw}"
             )
         }
     };
 
+    if should_post_message && !reply_checked {
+        if let Some(decline) = content_policy_decline(
+            state,
+            &settings,
+            task,
+            crate::content_policy::Direction::Reply,
+            &reply_text,
+        )
+        .await?
+        {
+            reply_text = decline;
+            cacheable = false;
+        }
+    }
+
     if should_persist_session {
         session.last_used_at = chrono::Utc::now().timestamp();
         db::upsert_session(&state.pool, &session).await?;
     }
 
-    if should_post_message {
+    if should_post_message && task.is_synthetic {
+        // Admin test runs: the reply is only visible as the task result in the admin UI.
+        info!(task_id = task.id, provider = %provider, "synthetic task; reply not posted");
+    } else if should_post_message {
         // Reply in the originating channel.
+        let posted_text = if settings.reply_task_links {
+            format!(
+                "{}\n\n{}",
+                reply_text.trim_end(),
+                crate::task_links::reply_footer(
+                    &provider,
+                    state.config.base_url.as_deref(),
+                    task.id
+                )
+            )
+        } else {
+            reply_text.clone()
+        };
         match provider.as_str() {
             "slack" => {
                 let slack = slack.context("slack client missing")?;
-                slack
-                    .post_message(&task.channel_id, thread_opt(&task.thread_ts), &reply_text)
-                    .await?;
+                use crate::slack_publish::LongReply;
+                let published = match crate::slack_publish::post_long_reply(
+                    &slack,
+                    &settings,
+                    task,
+                    &posted_text,
+                )
+                .await
+                {
+                    Ok(published) => published,
+                    Err(err) => {
+                        warn!(error = %err, task_id = task.id, "failed to publish long reply; posting inline");
+                        LongReply::Inline
+                    }
+                };
+                // The summary already in the thread stands in for the reply.
+                if published == LongReply::Inline {
+                    slack
+                        .post_message(&task.channel_id, thread_opt(&task.thread_ts), &posted_text)
+                        .await?;
+                }
             }
             "telegram" => {
                 let tg = telegram.context("telegram client missing")?;
                 let reply_to_message_id = task.thread_ts.parse::<i64>().ok();
                 let _ids = tg
-                    .send_message(&task.channel_id, reply_to_message_id, &reply_text)
+                    .send_message(&task.channel_id, reply_to_message_id, &posted_text)
                     .await?;
             }
             "whatsapp" => {
                 let wa = whatsapp.context("whatsapp client missing")?;
-                wa.send_message(&task.channel_id, &reply_text).await?;
+                wa.send_message(&task.channel_id, &posted_text).await?;
             }
             "discord" => {
                 let dc = discord.context("discord client missing")?;
-                dc.send_message(&task.channel_id, &reply_text).await?;
+                dc.send_message(&task.channel_id, &posted_text).await?;
             }
             "msteams" => {
                 let teams = msteams.context("msteams client missing")?;
@@ -1268,7 +1853,7 @@ async fn process_task(
                 let parts: Vec<&str> = task.thread_ts.splitn(2, '|').collect();
                 if parts.len() == 2 {
                     teams
-                        .reply_to_activity(parts[0], &task.channel_id, parts[1], &reply_text)
+                        .reply_to_activity(parts[0], &task.channel_id, parts[1], &posted_text)
                         .await?;
                 } else {
                     // Fallback: post to conversation directly.
@@ -1278,22 +1863,29 @@ async fn process_task(
                         "https://smba.trafficmanager.net/teams"
                     };
                     teams
-                        .send_message(service_url, &task.channel_id, &reply_text)
+                        .send_message(service_url, &task.channel_id, &posted_text)
                         .await?;
                 }
             }
             _ => {}
         }
         info!(task_id = task.id, provider = %provider, "replied");
+        if let Some(key) = cache_key.filter(|_| cacheable) {
+            if let Err(err) =
+                crate::response_cache::store(state, &settings, task, key, &reply_text).await
+            {
+                warn!(error = %err, task_id = task.id, "failed to store response cache entry");
+            }
+        }
     } else {
         info!(task_id = task.id, provider = %provider, "skipped reply");
     }
 
     // Best-effort: update observational memory after a successful reply.
-    if should_post_message {
+    if should_post_message && !task.is_synthetic {
         if let Err(err) = update_observational_memory_for_turn(
             state,
-            codex,
+            &mut agent,
             task,
             &settings,
             &cwd,
@@ -1313,7 +1905,7 @@ async fn process_task(
 
 async fn update_observational_memory_for_turn(
     state: &AppState,
-    codex: &mut CodexManager,
+    agent: &mut Agent<'_>,
     task: &crate::models::Task,
     settings: &crate::models::Settings,
     cwd: &std::path::Path,
@@ -1330,7 +1922,7 @@ async fn update_observational_memory_for_turn(
 
     observe_and_maybe_reflect(
         state,
-        codex,
+        agent,
         task,
         &mem_settings,
         cwd,
@@ -1344,7 +1936,7 @@ async fn update_observational_memory_for_turn(
     if let Some(k) = resource_memory_key {
         observe_and_maybe_reflect(
             state,
-            codex,
+            agent,
             task,
             &mem_settings,
             cwd,
@@ -1361,7 +1953,7 @@ async fn update_observational_memory_for_turn(
 
 async fn observe_and_maybe_reflect(
     state: &AppState,
-    codex: &mut CodexManager,
+    agent: &mut Agent<'_>,
     task: &crate::models::Task,
     settings: &crate::models::Settings,
     cwd: &std::path::Path,
@@ -1395,19 +1987,23 @@ async fn observe_and_maybe_reflect(
         reply_text,
     );
     let observer_schema = observer_output_schema();
-    let observer_thread_id = codex.resume_or_start_thread(None, settings, cwd).await?;
-    let out = codex
-        .run_turn(
+    let observer_thread_id = agent.resume_or_start_thread(None, settings, cwd).await?;
+    let out = agent
+        .run_turn(AgentTurn {
             state,
             task,
-            &observer_thread_id,
+            thread_id: &observer_thread_id,
             settings,
             cwd,
-            &observer_input,
-            observer_schema,
-            None,
-        )
-        .await?;
+            input_text: &observer_input,
+            output_schema: observer_schema,
+            trace_tx: None,
+        })
+        .await;
+    if let Err(err) = agent.discard_thread(&observer_thread_id).await {
+        warn!(error = %err, "failed to discard the observer thread");
+    }
+    let out = out?;
     let obs = parse_observer_json(&out.agent_message_text)?;
     let mut append = obs.append.trim().to_string();
     if append.is_empty() {
@@ -1433,19 +2029,23 @@ async fn observe_and_maybe_reflect(
 
     let reflector_input = build_reflector_input(scope, memory_key, &updated);
     let reflector_schema = reflector_output_schema();
-    let reflector_thread_id = codex.resume_or_start_thread(None, settings, cwd).await?;
-    let out = codex
-        .run_turn(
+    let reflector_thread_id = agent.resume_or_start_thread(None, settings, cwd).await?;
+    let out = agent
+        .run_turn(AgentTurn {
             state,
             task,
-            &reflector_thread_id,
+            thread_id: &reflector_thread_id,
             settings,
             cwd,
-            &reflector_input,
-            reflector_schema,
-            None,
-        )
-        .await?;
+            input_text: &reflector_input,
+            output_schema: reflector_schema,
+            trace_tx: None,
+        })
+        .await;
+    if let Err(err) = agent.discard_thread(&reflector_thread_id).await {
+        warn!(error = %err, "failed to discard the reflector thread");
+    }
+    let out = out?;
     let mut refl = parse_reflector_json(&out.agent_message_text)?;
 
     let (rs, redacted) = crate::secrets::redact_secrets(&refl.reflection_summary);
@@ -1514,7 +2114,17 @@ fn build_observer_input(
         "- requested_by_user_id: {}\n",
         task.requested_by_user_id
     ));
-    s.push_str(&format!("- event_ts: {}\n\n", task.event_ts));
+    s.push_str(&format!("- event_ts: {}\n", task.event_ts));
+    if let Some(deadline) = crate::models::TaskOptions::parse(&task.options_json)
+        .deadline(task.started_at)
+        .and_then(|d| chrono::DateTime::from_timestamp(d, 0))
+    {
+        s.push_str(&format!(
+            "- deadline: {} (the run is stopped at this time; keep the work scoped to finish before it)\n",
+            deadline.format("%Y-%m-%d %H:%M UTC")
+        ));
+    }
+    s.push('\n');
 
     let (recent_context, rc_redacted) = crate::secrets::redact_secrets(recent_context.trim());
     let (prompt_text, p_redacted) = crate::secrets::redact_secrets(task.prompt_text.trim());
@@ -1523,7 +2133,7 @@ fn build_observer_input(
         warn!(memory_key, "redacted secrets from observer input");
     }
 
-    s.push_str("Recent chat context (oldest -> newest):\n");
+    s.push_str("Conversation context:\n");
     s.push_str(recent_context.trim());
     s.push_str("\n\n");
 
@@ -1688,6 +2298,31 @@ fn tail_chars(s: &str, max: usize) -> String {
     format!("...{}", take_last_chars(s, max))
 }
 
+/// Memories first (up to [`crate::token_budget::MEMORY_SHARE`] of the budget, newest
+/// observations kept), then context sources in whatever is left.
+fn pack_context_for_budget(
+    budget: usize,
+    observational_memory_text: String,
+    memory_summary: &str,
+    context: ContextBuilder<'_>,
+) -> (String, String) {
+    use crate::token_budget::{estimate_tokens, pack, MEMORY_SHARE};
+
+    let memory_cap = (budget as f64 * MEMORY_SHARE) as usize;
+    let summary_tokens = estimate_tokens(memory_summary);
+    let obs_cap = memory_cap.saturating_sub(summary_tokens);
+    let obs_tokens = estimate_tokens(&observational_memory_text);
+    let (observational, obs_tokens) = if obs_tokens <= obs_cap {
+        (observational_memory_text, obs_tokens)
+    } else if obs_cap == 0 {
+        (String::new(), 0)
+    } else {
+        pack(&observational_memory_text, obs_cap, true)
+    };
+    let context_budget = budget.saturating_sub(summary_tokens + obs_tokens);
+    (observational, context.render_tokens(context_budget))
+}
+
 fn format_observational_memory_for_prompt(
     thread_mem: Option<&ObservationalMemory>,
     resource_mem: Option<&ObservationalMemory>,
@@ -1742,6 +2377,14 @@ fn format_observational_memory_for_prompt(
     out.trim().to_string()
 }
 
+/// Slack date token that renders in the reader's timezone, with a UTC fallback.
+fn format_deadline(ts: i64) -> String {
+    let fallback = chrono::DateTime::from_timestamp(ts, 0)
+        .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| ts.to_string());
+    format!("<!date^{ts}^{{date_short_pretty}} {{time}}|{fallback}>")
+}
+
 fn thread_opt(thread_ts: &str) -> Option<&str> {
     let t = thread_ts.trim();
     if t.is_empty() {
@@ -1884,11 +2527,40 @@ pub fn agent_output_schema() -> serde_json::Value {
     })
 }
 
-async fn send_user_message(
+/// The decline message when `text` violates the content policy; the violation is
+/// recorded in the task trace (without the text itself).
+pub async fn content_policy_decline(
+    state: &AppState,
+    settings: &crate::models::Settings,
+    task: &crate::models::Task,
+    direction: crate::content_policy::Direction,
+    text: &str,
+) -> anyhow::Result<Option<String>> {
+    let Some(violation) = crate::content_policy::check(state, settings, task, text).await? else {
+        return Ok(None);
+    };
+    let details = format!("{}: {}", direction.as_str(), violation.describe());
+    warn!(task_id = task.id, violation = %details, "content policy violation; declining");
+    let _ = db::create_task_trace(
+        &state.pool,
+        task.id,
+        "policy.blocked",
+        "warn",
+        &format!("{} blocked by content policy", direction.as_str()),
+        &details,
+    )
+    .await;
+    Ok(Some(crate::content_policy::decline_message(settings)))
+}
+
+pub async fn send_user_message(
     state: &AppState,
     task: &crate::models::Task,
     text: &str,
 ) -> anyhow::Result<()> {
+    if task.is_synthetic {
+        return Ok(());
+    }
     let (text, redacted) = crate::secrets::redact_secrets(text);
     if redacted {
         warn!("redacted secrets from user-facing message");
@@ -1896,7 +2568,7 @@ async fn send_user_message(
     match task.provider.as_str() {
         "slack" => {
             let Some(token) = crate::secrets::load_slack_bot_token_opt(state).await? else {
-                anyhow::bail!("SLACK_BOT_TOKEN is not configured");
+                return Err(crate::errors::config("SLACK_BOT_TOKEN is not configured"));
             };
             let slack = SlackClient::new(state.http.clone(), token);
             slack
@@ -1905,7 +2577,9 @@ async fn send_user_message(
         }
         "telegram" => {
             let Some(token) = crate::secrets::load_telegram_bot_token_opt(state).await? else {
-                anyhow::bail!("TELEGRAM_BOT_TOKEN is not configured");
+                return Err(crate::errors::config(
+                    "TELEGRAM_BOT_TOKEN is not configured",
+                ));
             };
             let tg = TelegramClient::new(state.http.clone(), token);
             let reply_to_message_id = task.thread_ts.parse::<i64>().ok();
@@ -1939,6 +2613,182 @@ fn format_slack_context(messages: &[crate::slack::SlackMessage]) -> String {
     out
 }
 
+fn format_prior_tasks(tasks: &[(i64, String, String)]) -> String {
+    let mut out = String::new();
+    for (id, prompt, result) in tasks {
+        out.push_str(&format!(
+            "- Task #{id} request: {}\n",
+            clamp_len(prompt.trim().replace('\n', " "), 500)
+        ));
+        if !result.trim().is_empty() {
+            out.push_str(&format!(
+                "  Result: {}\n",
+                clamp_len(result.trim().replace('\n', " "), 2_000)
+            ));
+        }
+    }
+    out
+}
+
+/// Recent history from channels attached in the task modal, limited by the channel
+/// allow-list. DMs other than the task's own channel are never read.
+async fn fetch_attached_channels(
+    client: &SlackClient,
+    settings: &crate::models::Settings,
+    task: &crate::models::Task,
+    channels: &[String],
+) -> String {
+    let allowed = crate::parse_allow_from(&settings.slack_allow_channels);
+    let mut out = String::new();
+    for channel in channels.iter().take(crate::models::MAX_CONTEXT_CHANNELS) {
+        let channel = channel.trim();
+        if channel.is_empty()
+            || channel == task.channel_id
+            || channel.starts_with('D')
+            || !crate::slack_channel_allowed(&allowed, &task.workspace_id, channel)
+        {
+            continue;
+        }
+        match client
+            .fetch_channel_history(channel, &task.event_ts, settings.context_last_n)
+            .await
+        {
+            Ok(messages) => {
+                out.push_str(&format!("Attached channel <#{channel}>:\n"));
+                out.push_str(&format_slack_context(&messages));
+            }
+            Err(err) => {
+                warn!(error = %err, task_id = task.id, channel, "failed to fetch attached channel");
+            }
+        }
+    }
+    out
+}
+
+/// Inline the contents of small text files shared in the fetched messages.
+async fn fetch_slack_linked_files(
+    client: &SlackClient,
+    messages: &[crate::slack::SlackMessage],
+    task_id: i64,
+) -> String {
+    const MAX_FILES: usize = 5;
+    const MAX_FILE_BYTES: u64 = 64 * 1024;
+
+    let mut out = String::new();
+    let mut seen_ids: Vec<&str> = Vec::new();
+    for f in messages.iter().flat_map(|m| m.files.iter()) {
+        if seen_ids.len() >= MAX_FILES || seen_ids.contains(&f.id.as_str()) {
+            continue;
+        }
+        let mime = f.mimetype.as_deref().unwrap_or("");
+        let is_text = mime.starts_with("text/")
+            || matches!(
+                mime,
+                "application/json" | "application/xml" | "application/x-yaml"
+            );
+        let Some(url) = f.url_private_download.as_deref() else {
+            continue;
+        };
+        if !is_text || f.size.unwrap_or(0) > MAX_FILE_BYTES {
+            continue;
+        }
+        seen_ids.push(f.id.as_str());
+        let name = f.name.as_deref().unwrap_or("unknown");
+        match client.fetch_file_text(url, MAX_FILE_BYTES as usize).await {
+            Ok(text) => {
+                out.push_str(&format!("--- {name} ({mime}) ---\n"));
+                out.push_str(text.trim());
+                out.push('\n');
+            }
+            Err(err) => {
+                warn!(error = %err, task_id, file = name, "failed to fetch linked file");
+            }
+        }
+    }
+    out
+}
+
+static RE_PROMPT_URL: Lazy<Regex> = Lazy::new(|| Regex::new(r#"https?://[^\s<>|"']+"#).unwrap());
+
+/// Read pages linked from the prompt with the built-in web tools, for backends that
+/// can't call `web_fetch` themselves. Honors the web allow/deny domain settings.
+async fn fetch_linked_pages(
+    settings: &crate::models::Settings,
+    task: &crate::models::Task,
+) -> String {
+    const MAX_PAGES: usize = 3;
+    const MAX_PAGE_CHARS: usize = 20_000;
+
+    let mut urls: Vec<&str> = Vec::new();
+    for m in RE_PROMPT_URL.find_iter(&task.prompt_text) {
+        let url = m
+            .as_str()
+            .trim_end_matches(['.', ',', ')', ';', ':', '!', '?']);
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+        if urls.len() >= MAX_PAGES {
+            break;
+        }
+    }
+    if urls.is_empty() {
+        return String::new();
+    }
+    let web = match grail_web::WebMcpServer::new() {
+        Ok(w) => w.with_domain_lists(&settings.web_allow_domains, &settings.web_deny_domains),
+        Err(err) => {
+            warn!(error = %err, task_id = task.id, "failed to set up web fetch");
+            return String::new();
+        }
+    };
+
+    let mut out = String::new();
+    for url in urls {
+        let page = match reqwest::Url::parse(url) {
+            Ok(u) => web
+                .fetch_url(&u, "markdown", MAX_PAGE_CHARS)
+                .await
+                .map_err(|e| e.message.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        match page {
+            Ok(page) => {
+                let status = page.get("status").and_then(|v| v.as_u64()).unwrap_or(0);
+                let text = page.get("text").and_then(|v| v.as_str()).unwrap_or("");
+                out.push_str(&format!("--- {url} (http {status}) ---\n"));
+                out.push_str(text.trim());
+                out.push('\n');
+            }
+            Err(err) => {
+                warn!(error = %err, task_id = task.id, url, "failed to fetch linked page");
+                out.push_str(&format!("--- {url} ---\n(could not fetch: {err})\n"));
+            }
+        }
+    }
+    out
+}
+
+fn format_dependency_context(dep: &crate::models::Task) -> String {
+    let mut s = format!(
+        "This task was queued to run after task #{} (status: {}).\n",
+        dep.id, dep.status
+    );
+    s.push_str(&format!("Task #{} request:\n", dep.id));
+    s.push_str(&clamp_len(dep.prompt_text.trim().to_string(), 2_000));
+    s.push('\n');
+    if let Some(result) = dep.result_text.as_deref().filter(|r| !r.trim().is_empty()) {
+        s.push_str(&format!("Task #{} result:\n", dep.id));
+        s.push_str(&clamp_len(result.trim().to_string(), 8_000));
+        s.push('\n');
+    }
+    if let Some(err) = dep.error_text.as_deref().filter(|e| !e.trim().is_empty()) {
+        s.push_str(&format!("Task #{} error:\n", dep.id));
+        s.push_str(&clamp_len(err.trim().to_string(), 2_000));
+        s.push('\n');
+    }
+    s
+}
+
 fn format_telegram_context(messages: &[crate::models::TelegramMessage]) -> String {
     let mut out = String::new();
     for (i, m) in messages.iter().enumerate() {
@@ -1962,6 +2812,7 @@ fn build_turn_input(
     memory_summary: &str,
     recent_context: &str,
     repo_context: &str,
+    dependency_context: &str,
     allow_slack_mcp: bool,
     allow_web_mcp: bool,
     browser: &crate::codex::BrowserEnvConfig,
@@ -2024,9 +2875,12 @@ fn build_turn_input(
         s.push_str("\n\n");
     }
 
-    s.push_str("Recent chat context (oldest -> newest):\n");
-    s.push_str(recent_context);
-    s.push_str("\n");
+    s.push_str("Conversation context:\n");
+    if recent_context.trim().is_empty() {
+        s.push_str("(none)\n\n");
+    } else {
+        s.push_str(recent_context);
+    }
 
     s.push_str("Permissions:\n");
     s.push_str(&format!(
@@ -2101,6 +2955,11 @@ fn build_turn_input(
     s.push_str(task.prompt_text.trim());
     s.push_str("\n\n");
 
+    if !dependency_context.trim().is_empty() {
+        s.push_str(dependency_context.trim());
+        s.push_str("\n\n");
+    }
+
     // Include file attachment info if present.
     if !task.files_json.is_empty() {
         if let Ok(files) = serde_json::from_str::<Vec<serde_json::Value>>(&task.files_json) {
@@ -2151,6 +3010,20 @@ fn build_turn_input(
     s.push_str("- Slack only: files written via `context_writes` are auto-uploaded, except files under `repos/`.\n");
     s.push_str("- To upload specific repo files (or a patch/diff you generated), list them in `upload_files` (relative paths under the context directory).\n\n");
 
+    if let Some(line) = crate::language::reply_instruction(
+        &settings.reply_language,
+        &task.channel_id,
+        &task.prompt_text,
+    ) {
+        s.push_str("Reply language:\n");
+        s.push_str(&line);
+        s.push('\n');
+    }
+
+    if let Some(schema) = crate::models::TaskOptions::parse(&task.options_json).output_schema {
+        s.push_str(&crate::output_contract::instructions(&schema));
+    }
+
     s.push_str("Reply control:\n");
     s.push_str("- Always include `should_reply`.\n");
     s.push_str("- For normal (non-proactive) tasks, set `should_reply=true`.\n");
@@ -2374,9 +3247,69 @@ fn rewrite_browser_login_refusal_if_needed(
     out
 }
 
+/// Validate `reply` against the task's output contract, asking the agent to fix it up to
+/// twice. Returns the compact JSON; fails the task when it still doesn't conform.
+async fn enforce_output_contract(
+    state: &AppState,
+    agent: &mut Agent<'_>,
+    task: &crate::models::Task,
+    thread_id: &str,
+    settings: &crate::models::Settings,
+    cwd: &std::path::Path,
+    reply: &str,
+) -> anyhow::Result<String> {
+    let Some(contract) = crate::models::TaskOptions::parse(&task.options_json).output_schema else {
+        return Ok(reply.to_string());
+    };
+    let mut errors = match crate::output_contract::check(&contract, reply) {
+        Ok(v) => return Ok(v.to_string()),
+        Err(errors) => errors,
+    };
+    for attempt in 1..=2 {
+        warn!(
+            task_id = task.id,
+            attempt, "reply broke the output contract; asking for a repair"
+        );
+        let _ = db::create_task_trace(
+            &state.pool,
+            task.id,
+            "output_contract.invalid",
+            "warn",
+            "reply did not conform to output_schema",
+            &errors.join("\n"),
+        )
+        .await;
+        let out = agent
+            .run_turn(AgentTurn {
+                state,
+                task,
+                thread_id,
+                settings,
+                cwd,
+                input_text: &crate::output_contract::repair_instructions(&errors),
+                output_schema: agent_output_schema(),
+                trace_tx: None,
+            })
+            .await?;
+        errors = match parse_agent_json(&out.agent_message_text) {
+            Ok(parsed) => match crate::output_contract::check(&contract, &parsed.reply) {
+                Ok(v) => return Ok(v.to_string()),
+                Err(errors) => errors,
+            },
+            Err(err) => vec![format!(
+                "response was not the expected JSON object: {err:#}"
+            )],
+        };
+    }
+    anyhow::bail!(
+        "the answer did not conform to output_schema: {}",
+        errors.join("; ")
+    )
+}
+
 async fn repair_agent_output(
     state: &AppState,
-    codex: &mut CodexManager,
+    agent: &mut Agent<'_>,
     task: &crate::models::Task,
     thread_id: &str,
     settings: &crate::models::Settings,
@@ -2392,17 +3325,17 @@ Attempt: {attempt}\n\n\
 Previous output:\n```text\n{last}\n```\n\n\
 Return ONLY a single JSON object that matches the schema.",
         );
-        let out = codex
-            .run_turn(
+        let out = agent
+            .run_turn(AgentTurn {
                 state,
                 task,
                 thread_id,
                 settings,
                 cwd,
-                &repair_input,
-                output_schema.clone(),
-                None,
-            )
+                input_text: &repair_input,
+                output_schema: output_schema.clone(),
+                trace_tx: None,
+            })
             .await?;
         match parse_agent_json(&out.agent_message_text) {
             Ok(v) => return Ok(v),
@@ -2479,6 +3412,7 @@ async fn apply_agent_cron_jobs(
             channel_id: task.channel_id.clone(),
             thread_ts,
             prompt_text,
+            created_by_user_id: Some(task.requested_by_user_id.clone()).filter(|u| !u.is_empty()),
             next_run_at: None,
             last_run_at: None,
             last_status: None,
@@ -2867,6 +3801,27 @@ fn sanitize_rel_path(path: &str) -> anyhow::Result<std::path::PathBuf> {
     Ok(p)
 }
 
+/// Most of an upload_files file the content policy reads.
+const MAX_POLICY_UPLOAD_BYTES: usize = 256 * 1024;
+
+/// The reply plus everything the agent asked to write or upload, for the content policy.
+async fn agent_output_text(cwd: &std::path::Path, parsed: &AgentJson) -> String {
+    let mut text = parsed.reply.clone();
+    for w in &parsed.context_writes {
+        text.push_str("\n\n");
+        text.push_str(&w.content);
+    }
+    for rel in &parsed.upload_files {
+        let Ok(bytes) = tokio::fs::read(cwd.join(rel)).await else {
+            continue;
+        };
+        let bytes = &bytes[..bytes.len().min(MAX_POLICY_UPLOAD_BYTES)];
+        text.push_str("\n\n");
+        text.push_str(&String::from_utf8_lossy(bytes));
+    }
+    text
+}
+
 fn is_under_repos_dir(path: &str) -> bool {
     use std::path::Component;
 
@@ -2878,18 +3833,13 @@ fn is_under_repos_dir(path: &str) -> bool {
 }
 
 fn clamp_len(s: String, max: usize) -> String {
-    if s.len() <= max {
+    if s.chars().count() <= max {
         s
     } else {
-        s.chars().take(max).collect()
+        grail_text::head(&s, max).to_string()
     }
 }
 
 fn shorten_error(s: &str) -> String {
-    let s = s.trim().replace('\n', " ");
-    if s.len() <= 400 {
-        s
-    } else {
-        format!("{}…", s.chars().take(399).collect::<String>())
-    }
+    grail_text::truncate_line(s, 400)
 }
@@ -0,0 +1,257 @@
+//! The channel allow-list (`GRAIL_SLACK_ALLOW_CHANNELS`).
+//!
+//! `GRAIL_SLACK_ALLOW_CHANNELS_FILE` points it at a file instead, which is re-read every
+//! few seconds so operators can widen or narrow a long-running server's access without a
+//! restart. The file takes the place of the env var and uses the same format (ids separated
+//! by commas or whitespace), plus `#` comments. An empty list allows every channel, as
+//! with the env var. A file that can't be read at startup stops the server; one that
+//! becomes unreadable later keeps the last list it had.
+//!
+//! Entries like `#eng-*` are channel name patterns (`*` matches anything, case-insensitive).
+//! The server resolves them against `conversations.list` at startup and every few minutes,
+//! and the matching channels' ids are allowed alongside the plain entries. A `#` followed
+//! by a space (or at the end of a line) still starts a comment.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::Context;
+use tracing::{info, warn};
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct ChannelAllowlist {
+    /// The configured entries, `#name` patterns included.
+    configured: RwLock<Arc<HashSet<String>>>,
+    /// Channel ids the patterns matched at the last resolution.
+    resolved: RwLock<Arc<HashSet<String>>>,
+    /// Both of the above: what channels are checked against.
+    entries: RwLock<Arc<HashSet<String>>>,
+    file: Option<PathBuf>,
+}
+
+fn is_separator(c: char) -> bool {
+    c == ',' || c.is_whitespace()
+}
+
+/// `line` up to its comment, if any. A `#` starting an entry and followed by a name is a
+/// pattern; any other `#` starts a comment.
+fn strip_comment(line: &str) -> &str {
+    for (i, c) in line.char_indices() {
+        if c != '#' {
+            continue;
+        }
+        let starts_entry = line[..i].chars().next_back().is_none_or(is_separator);
+        let names = line[i + 1..]
+            .chars()
+            .next()
+            .is_some_and(|n| !is_separator(n) && n != '#');
+        if !(starts_entry && names) {
+            return &line[..i];
+        }
+    }
+    line
+}
+
+/// Entries in `raw`, ignoring comments.
+pub fn parse(raw: &str) -> HashSet<String> {
+    raw.lines()
+        .map(strip_comment)
+        .flat_map(|line| line.split(is_separator))
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Glob match of a channel name against a pattern, `*` matching any run of characters.
+pub fn name_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of the name it has taken so far.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == name[n] {
+            p += 1;
+            n += 1;
+        } else if let Some((sp, sn)) = star {
+            p = sp + 1;
+            n = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+impl ChannelAllowlist {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let file = std::env::var("GRAIL_SLACK_ALLOW_CHANNELS_FILE")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from);
+        let entries = match &file {
+            Some(path) => {
+                let raw = std::fs::read_to_string(path).with_context(|| {
+                    format!("read GRAIL_SLACK_ALLOW_CHANNELS_FILE {}", path.display())
+                })?;
+                parse(&raw)
+            }
+            None => parse(&std::env::var("GRAIL_SLACK_ALLOW_CHANNELS").unwrap_or_default()),
+        };
+        let entries = Arc::new(entries);
+        Ok(Self {
+            configured: RwLock::new(entries.clone()),
+            resolved: RwLock::new(Arc::new(HashSet::new())),
+            entries: RwLock::new(entries),
+            file,
+        })
+    }
+
+    fn read(lock: &RwLock<Arc<HashSet<String>>>) -> Arc<HashSet<String>> {
+        lock.read()
+            .map(|e| e.clone())
+            .unwrap_or_else(|poisoned| poisoned.into_inner().clone())
+    }
+
+    fn write(lock: &RwLock<Arc<HashSet<String>>>, next: Arc<HashSet<String>>) {
+        *lock
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = next;
+    }
+
+    pub fn current(&self) -> Arc<HashSet<String>> {
+        Self::read(&self.entries)
+    }
+
+    fn merge(&self) {
+        let configured = Self::read(&self.configured);
+        let resolved = Self::read(&self.resolved);
+        let merged = if resolved.is_empty() {
+            configured
+        } else {
+            Arc::new(configured.union(&resolved).cloned().collect())
+        };
+        Self::write(&self.entries, merged);
+    }
+
+    /// The `#name` patterns, without the `#`.
+    pub fn patterns(&self) -> Vec<String> {
+        Self::read(&self.configured)
+            .iter()
+            .filter_map(|e| e.strip_prefix('#'))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Replace the ids the patterns matched.
+    pub fn set_resolved(&self, ids: HashSet<String>) {
+        if *Self::read(&self.resolved) == ids {
+            return;
+        }
+        info!(
+            channels = ids.len(),
+            "resolved channel name patterns in the allow-list"
+        );
+        Self::write(&self.resolved, Arc::new(ids));
+        self.merge();
+    }
+
+    /// No entries: every channel is allowed.
+    pub fn is_open(&self) -> bool {
+        self.current().is_empty()
+    }
+
+    pub fn is_watched(&self) -> bool {
+        self.file.is_some()
+    }
+
+    fn reload(&self, path: &PathBuf) {
+        let raw = match std::fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(err) => {
+                warn!(path = %path.display(), error = %err, "failed to re-read the channel allow-list; keeping the current one");
+                return;
+            }
+        };
+        let next = parse(&raw);
+        if *Self::read(&self.configured) == next {
+            return;
+        }
+        info!(path = %path.display(), channels = next.len(), "reloaded the channel allow-list");
+        Self::write(&self.configured, Arc::new(next));
+        self.merge();
+    }
+
+    /// Re-read the file until the process exits.
+    pub async fn watch(self: Arc<Self>) {
+        let Some(path) = self.file.clone() else {
+            return;
+        };
+        loop {
+            tokio::time::sleep(RELOAD_INTERVAL).await;
+            self.reload(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_keeps_patterns_and_drops_comments() {
+        let raw = "C1, C2 # the usual\n#eng-*\n# a comment\nT1:C3 ## trailing\n#";
+        assert_eq!(parse(raw), set(&["C1", "C2", "#eng-*", "T1:C3"]));
+    }
+
+    #[test]
+    fn name_matches_globs_case_insensitively() {
+        assert!(name_matches("eng-*", "ENG-infra"));
+        assert!(name_matches("*-alerts", "prod-alerts"));
+        assert!(name_matches("a*b*c", "a-b-b-c"));
+        assert!(name_matches("general", "general"));
+        assert!(!name_matches("eng-*", "engineering"));
+        assert!(!name_matches("a*c", "abcd"));
+    }
+
+    #[test]
+    fn reload_picks_up_file_changes_and_keeps_resolved_ids() {
+        let path = std::env::temp_dir().join(format!(
+            "grail-slack-allowlist-{}-{:?}.txt",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        std::fs::write(&path, "C1\n#eng-*").unwrap();
+        let list = ChannelAllowlist {
+            configured: RwLock::new(Arc::new(parse("C1\n#eng-*"))),
+            resolved: RwLock::new(Arc::new(HashSet::new())),
+            entries: RwLock::new(Arc::new(parse("C1\n#eng-*"))),
+            file: Some(path.clone()),
+        };
+        assert_eq!(list.patterns(), vec!["eng-*".to_string()]);
+        list.set_resolved(set(&["C2"]));
+        assert_eq!(*list.current(), set(&["C1", "#eng-*", "C2"]));
+
+        std::fs::write(&path, "C4 #eng-*").unwrap();
+        list.reload(&path);
+        assert_eq!(*list.current(), set(&["C4", "#eng-*", "C2"]));
+
+        // An unreadable file keeps the last list.
+        std::fs::remove_file(&path).unwrap();
+        list.reload(&path);
+        assert_eq!(*list.current(), set(&["C4", "#eng-*", "C2"]));
+    }
+}
@@ -0,0 +1,144 @@
+//! Opt-in audit log of tool calls (`GRAIL_SLACK_AUDIT_LOG=path`), one JSON object per line,
+//! so security teams can review what the agent read and wrote.
+//!
+//! Each line has the time, tool, arguments, channel, outcome and duration. Argument values
+//! under secret-looking keys and anything shaped like a Slack token are redacted; message
+//! text is kept, since what was posted is part of what needs reviewing.
+
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use rmcp::model::{CallToolResult, JsonObject};
+use rmcp::ErrorData as McpError;
+use serde_json::{json, Value};
+use tracing::warn;
+
+const REDACTED: &str = "[redacted]";
+const SECRET_KEYS: [&str; 5] = ["token", "secret", "password", "authorization", "cookie"];
+const TOKEN_PREFIXES: [&str; 6] = ["xoxb-", "xoxp-", "xoxa-", "xoxe-", "xoxr-", "xapp-"];
+
+pub struct AuditLog {
+    file: Mutex<File>,
+}
+
+fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let key = k.to_ascii_lowercase();
+                    if SECRET_KEYS.iter().any(|s| key.contains(s)) {
+                        (k.clone(), json!(REDACTED))
+                    } else {
+                        (k.clone(), redact(v))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        Value::String(s) if TOKEN_PREFIXES.iter().any(|p| s.contains(p)) => {
+            let words: Vec<&str> = s
+                .split(' ')
+                .map(|w| {
+                    if TOKEN_PREFIXES.iter().any(|p| w.contains(p)) {
+                        REDACTED
+                    } else {
+                        w
+                    }
+                })
+                .collect();
+            json!(words.join(" "))
+        }
+        other => other.clone(),
+    }
+}
+
+impl AuditLog {
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Some(path) = std::env::var("GRAIL_SLACK_AUDIT_LOG")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+        else {
+            return Ok(None);
+        };
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("open GRAIL_SLACK_AUDIT_LOG {path}"))?;
+        Ok(Some(Self {
+            file: Mutex::new(file),
+        }))
+    }
+
+    pub fn record(
+        &self,
+        tool: &str,
+        arguments: Option<&JsonObject>,
+        result: &Result<CallToolResult, McpError>,
+        elapsed: Duration,
+    ) {
+        let args = arguments.map(|a| redact(&Value::Object(a.clone())));
+        let structured = result
+            .as_ref()
+            .ok()
+            .and_then(|r| r.structured_content.as_ref());
+        let channel = args
+            .as_ref()
+            .and_then(|a| a.get("channel"))
+            .or_else(|| structured.and_then(|s| s.get("channel")))
+            .cloned();
+        let (outcome, error) = match result {
+            Ok(r) if r.is_error == Some(true) => ("error", None),
+            Ok(_) => ("ok", None),
+            Err(err) => ("error", Some(err.message.to_string())),
+        };
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+        let line = json!({
+            "ts": ts,
+            "tool": tool,
+            "arguments": args,
+            "channel": channel,
+            "outcome": outcome,
+            "error": error,
+            "elapsed_ms": u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX),
+        });
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(err) = writeln!(file, "{line}") {
+            warn!(error = %err, "failed to write the slack tool audit log");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_hides_secret_keys_and_tokens() {
+        let args = json!({
+            "channel": "C1",
+            "text": "use xoxp-1-2 here",
+            "Authorization": "Bearer abc",
+            "nested": [{ "client_secret": "s3cr3t", "note": "xapp-1" }],
+            "limit": 5,
+        });
+        assert_eq!(
+            redact(&args),
+            json!({
+                "channel": "C1",
+                "text": "use [redacted] here",
+                "Authorization": REDACTED,
+                "nested": [{ "client_secret": REDACTED, "note": REDACTED }],
+                "limit": 5,
+            })
+        );
+    }
+}
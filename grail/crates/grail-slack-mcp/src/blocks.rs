@@ -0,0 +1,171 @@
+//! Flatten Block Kit `blocks` and legacy `attachments` into plain text, for tools called
+//! with `flatten_blocks: true`. Bot and app messages often carry everything there and
+//! leave `text` empty or as a one-line fallback.
+//!
+//! Mentions stay in Slack's `<@U123>` / `<#C123>` form so `resolve_entities` can rewrite
+//! them afterwards.
+
+use serde_json::Value;
+
+fn str_at<'a>(v: &'a Value, key: &str) -> Option<&'a str> {
+    v.get(key)?
+        .as_str()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn array_at<'a>(v: &'a Value, key: &str) -> &'a [Value] {
+    v.get(key)
+        .and_then(|a| a.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// A text object (`{"type": "mrkdwn", "text": ...}`) or a plain string.
+fn text_object(v: Option<&Value>) -> Option<&str> {
+    let v = v?;
+    v.as_str()
+        .or_else(|| v.get("text").and_then(|t| t.as_str()))
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+fn rich_text_element(el: &Value, out: &mut String) {
+    match el.get("type").and_then(|t| t.as_str()).unwrap_or_default() {
+        "text" => out.push_str(el.get("text").and_then(|t| t.as_str()).unwrap_or_default()),
+        "link" => match (str_at(el, "url"), str_at(el, "text")) {
+            (Some(url), Some(text)) => out.push_str(&format!("<{url}|{text}>")),
+            (Some(url), None) => out.push_str(&format!("<{url}>")),
+            _ => {}
+        },
+        "user" => out.push_str(&format!("<@{}>", str_at(el, "user_id").unwrap_or_default())),
+        "channel" => out.push_str(&format!(
+            "<#{}>",
+            str_at(el, "channel_id").unwrap_or_default()
+        )),
+        "usergroup" => out.push_str(&format!(
+            "<!subteam^{}>",
+            str_at(el, "usergroup_id").unwrap_or_default()
+        )),
+        "broadcast" => out.push_str(&format!("<!{}>", str_at(el, "range").unwrap_or("here"))),
+        "emoji" => out.push_str(&format!(":{}:", str_at(el, "name").unwrap_or_default())),
+        "date" => out.push_str(str_at(el, "fallback").unwrap_or_default()),
+        _ => {}
+    }
+}
+
+fn rich_text(block: &Value, lines: &mut Vec<String>) {
+    for part in array_at(block, "elements") {
+        let kind = part
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or_default();
+        if kind == "rich_text_list" {
+            for item in array_at(part, "elements") {
+                let mut line = String::from("- ");
+                for el in array_at(item, "elements") {
+                    rich_text_element(el, &mut line);
+                }
+                lines.push(line);
+            }
+            continue;
+        }
+        let mut text = String::new();
+        for el in array_at(part, "elements") {
+            rich_text_element(el, &mut text);
+        }
+        match kind {
+            "rich_text_quote" => lines.extend(text.lines().map(|l| format!("> {l}"))),
+            "rich_text_preformatted" => lines.push(format!("```\n{text}\n```")),
+            _ => lines.push(text),
+        }
+    }
+}
+
+fn block(block: &Value, lines: &mut Vec<String>) {
+    match block
+        .get("type")
+        .and_then(|t| t.as_str())
+        .unwrap_or_default()
+    {
+        "rich_text" => rich_text(block, lines),
+        "header" | "section" => {
+            lines.extend(text_object(block.get("text")).map(str::to_string));
+            for field in array_at(block, "fields") {
+                lines.extend(text_object(Some(field)).map(str::to_string));
+            }
+        }
+        "context" => {
+            let parts: Vec<&str> = array_at(block, "elements")
+                .iter()
+                .filter_map(|el| text_object(Some(el)).or_else(|| str_at(el, "alt_text")))
+                .collect();
+            if !parts.is_empty() {
+                lines.push(parts.join(" · "));
+            }
+        }
+        "image" => {
+            if let Some(alt) = text_object(block.get("title")).or_else(|| str_at(block, "alt_text"))
+            {
+                lines.push(format!("[image: {alt}]"));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn attachment(att: &Value, lines: &mut Vec<String>) {
+    let before = lines.len();
+    for key in ["pretext", "author_name"] {
+        lines.extend(str_at(att, key).map(str::to_string));
+    }
+    match (str_at(att, "title"), str_at(att, "title_link")) {
+        (Some(title), Some(link)) => lines.push(format!("<{link}|{title}>")),
+        (Some(title), None) => lines.push(title.to_string()),
+        _ => {}
+    }
+    lines.extend(str_at(att, "text").map(str::to_string));
+    for field in array_at(att, "fields") {
+        match (str_at(field, "title"), str_at(field, "value")) {
+            (Some(t), Some(v)) => lines.push(format!("{t}: {v}")),
+            (None, Some(v)) => lines.push(v.to_string()),
+            _ => {}
+        }
+    }
+    for b in array_at(att, "blocks") {
+        block(b, lines);
+    }
+    lines.extend(str_at(att, "footer").map(str::to_string));
+    if lines.len() == before {
+        lines.extend(str_at(att, "fallback").map(str::to_string));
+    }
+}
+
+/// The text of a message's blocks and attachments, or `None` when it has neither.
+pub fn flatten(message: &Value) -> Option<String> {
+    let mut lines = Vec::new();
+    for b in array_at(message, "blocks") {
+        block(b, &mut lines);
+    }
+    for att in array_at(message, "attachments") {
+        attachment(att, &mut lines);
+    }
+    lines.retain(|l| !l.trim().is_empty());
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// Replace each message's `text` with its flattened blocks and attachments (when they
+/// have any text) and drop the raw structures.
+pub fn flatten_messages(messages: &mut [Value]) {
+    for msg in messages.iter_mut() {
+        let flat = flatten(msg);
+        let Some(obj) = msg.as_object_mut() else {
+            continue;
+        };
+        if let Some(flat) = flat {
+            obj.insert("text".to_string(), Value::String(flat));
+        }
+        obj.remove("blocks");
+        obj.remove("attachments");
+    }
+}
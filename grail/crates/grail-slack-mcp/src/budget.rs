@@ -0,0 +1,70 @@
+//! `max_chars`: keep a page of messages within a size budget, so a long thread doesn't fill
+//! the model's context.
+//!
+//! Sizes are measured on the serialized JSON. Over budget, the heavy fields (`blocks`,
+//! `attachments`, `files`) go first; if that isn't enough, message texts are cut to a common
+//! length, longest first, so short messages survive intact.
+
+use serde_json::Value;
+
+const HEAVY_FIELDS: [&str; 3] = ["blocks", "attachments", "files"];
+const ELLIPSIS: char = '…';
+
+fn size(messages: &[Value]) -> usize {
+    messages.iter().map(|m| m.to_string().chars().count()).sum()
+}
+
+fn text_len(message: &Value) -> usize {
+    message
+        .get("text")
+        .and_then(|v| v.as_str())
+        .map_or(0, |t| t.chars().count())
+}
+
+/// Shrink `messages` to about `max_chars`; true when anything was dropped or cut.
+pub fn fit(messages: &mut [Value], max_chars: Option<usize>) -> bool {
+    let Some(max_chars) = max_chars else {
+        return false;
+    };
+    if size(messages) <= max_chars {
+        return false;
+    }
+    for message in messages.iter_mut() {
+        if let Some(obj) = message.as_object_mut() {
+            for field in HEAVY_FIELDS {
+                obj.remove(field);
+            }
+        }
+    }
+    let total = size(messages);
+    if total <= max_chars {
+        return true;
+    }
+
+    // The largest per-message text length that fits what's left after everything else.
+    let lens: Vec<usize> = messages.iter().map(text_len).collect();
+    let available = max_chars.saturating_sub(total - lens.iter().sum::<usize>());
+    let fits = |cap: usize| lens.iter().map(|l| (*l).min(cap)).sum::<usize>() <= available;
+    let (mut lo, mut hi) = (0, lens.iter().copied().max().unwrap_or(0));
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        if fits(mid) {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    let cap = lo;
+    for (message, len) in messages.iter_mut().zip(lens) {
+        if len <= cap {
+            continue;
+        }
+        let Some(text) = message.get("text").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let mut cut: String = text.chars().take(cap.saturating_sub(1)).collect();
+        cut.push(ELLIPSIS);
+        message["text"] = Value::String(cut);
+    }
+    true
+}
@@ -0,0 +1,143 @@
+//! Slack canvases as text.
+//!
+//! There is no method that returns a canvas's content; a canvas is a file (`filetype`
+//! `quip`/`canvas`) whose download is HTML. A channel's own canvas is found through
+//! `conversations.info` (`properties.canvas.file_id`, or a canvas tab).
+
+/// The file id of a channel's canvas, from its `conversations.info` entry.
+pub fn channel_canvas_id(channel: &serde_json::Value) -> Option<String> {
+    let props = channel.get("properties")?;
+    props
+        .get("canvas")
+        .and_then(|c| c.get("file_id"))
+        .and_then(|v| v.as_str())
+        .or_else(|| {
+            props
+                .get("tabs")?
+                .as_array()?
+                .iter()
+                .find(|t| t.get("type").and_then(|v| v.as_str()) == Some("canvas"))?
+                .get("data")?
+                .get("file_id")?
+                .as_str()
+        })
+        .map(str::to_string)
+}
+
+pub fn is_canvas(file: &serde_json::Value) -> bool {
+    matches!(
+        file.get("filetype").and_then(|v| v.as_str()),
+        Some("quip" | "canvas")
+    )
+}
+
+const BLOCK_TAGS: [&str; 16] = [
+    "p",
+    "div",
+    "br",
+    "li",
+    "tr",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "pre",
+    "blockquote",
+    "ul",
+    "ol",
+    "hr",
+];
+
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let num = entity.strip_prefix('#')?;
+            let code = match num.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => num.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+/// Canvas HTML as plain text: block elements become line breaks, list items get a
+/// `- ` prefix, headings a `#`, and everything else is dropped.
+pub fn html_to_text(html: &str) -> String {
+    let mut out = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    let mut skip_until: Option<&str> = None;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let Some(end) = rest.find('>') else {
+                break;
+            };
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+            let closing = tag.starts_with('/');
+            let name = tag
+                .trim_start_matches('/')
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            if let Some(until) = skip_until {
+                if closing && name == until {
+                    skip_until = None;
+                }
+                continue;
+            }
+            match name.as_str() {
+                "script" | "style" if !closing => {
+                    skip_until = Some(if name == "script" { "script" } else { "style" })
+                }
+                "li" if !closing => out.push_str("\n- "),
+                // The next item (or the list's end) starts a new line.
+                "li" => {}
+                "h1" | "h2" | "h3" if !closing => out.push_str("\n# "),
+                n if BLOCK_TAGS.contains(&n) => out.push('\n'),
+                _ => {}
+            }
+            continue;
+        }
+        rest = &rest[c.len_utf8()..];
+        if skip_until.is_some() {
+            continue;
+        }
+        if c == '&' {
+            if let Some(semi) = rest.find(';').filter(|i| *i <= 10) {
+                if let Some(decoded) = decode_entity(&rest[..semi]) {
+                    out.push(decoded);
+                    rest = &rest[semi + 1..];
+                    continue;
+                }
+            }
+        }
+        out.push(c);
+    }
+
+    // Collapse runs of blank lines and trailing spaces left by the markup.
+    let mut text = String::with_capacity(out.len());
+    let mut blank = 0;
+    for line in out.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank += 1;
+            if blank > 1 || text.is_empty() {
+                continue;
+            }
+        } else {
+            blank = 0;
+        }
+        text.push_str(line);
+        text.push('\n');
+    }
+    text.trim_end().to_string()
+}
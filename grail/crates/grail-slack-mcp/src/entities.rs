@@ -0,0 +1,91 @@
+//! Rewrite Slack's message markup (`<@U123>`, `<#C123|general>`, `<!here>`,
+//! `<https://x|label>`) into the plain text a person would see in the client, for tools
+//! called with `resolve_entities: true`.
+
+use std::collections::HashMap;
+
+/// What a client shows for a user: display name, else real name, else handle.
+pub fn display_name(user: &serde_json::Value) -> Option<String> {
+    let profile = user.get("profile");
+    [
+        profile.and_then(|p| p.get("display_name")),
+        profile.and_then(|p| p.get("real_name")),
+        user.get("real_name"),
+        user.get("name"),
+    ]
+    .into_iter()
+    .flatten()
+    .filter_map(|v| v.as_str())
+    .map(str::trim)
+    .find(|v| !v.is_empty())
+    .map(str::to_string)
+}
+
+/// Every `<...>` token in `text`, without the brackets.
+fn tokens(text: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        let start = pos + text[pos..].find('<')?;
+        let end = start + text[start..].find('>')?;
+        pos = end + 1;
+        Some((start, end + 1, &text[start + 1..end]))
+    })
+}
+
+/// User ids mentioned in `text`.
+pub fn mentioned_users(text: &str) -> Vec<String> {
+    tokens(text)
+        .filter_map(|(_, _, inner)| inner.strip_prefix('@'))
+        .map(|rest| rest.split('|').next().unwrap_or(rest).to_string())
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+/// `text` with markup replaced; `names` maps user ids to display names. Users not in
+/// `names` fall back to the mention's own label, then the id.
+pub fn rewrite(text: &str, names: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end, inner) in tokens(text) {
+        out.push_str(&text[last..start]);
+        last = end;
+        let (target, label) = match inner.split_once('|') {
+            Some((t, l)) => (t, Some(l).filter(|l| !l.is_empty())),
+            None => (inner, None),
+        };
+        if let Some(id) = target.strip_prefix('@') {
+            let name = names
+                .get(id)
+                .map(String::as_str)
+                .or(label.map(|l| l.trim_start_matches('@')))
+                .unwrap_or(id);
+            out.push('@');
+            out.push_str(name);
+        } else if let Some(id) = target.strip_prefix('#') {
+            out.push('#');
+            out.push_str(label.unwrap_or(id));
+        } else if let Some(special) = target.strip_prefix('!') {
+            // `<!here>`, `<!channel>`, `<!subteam^S123|@team>`, `<!date^...|fallback>`.
+            match label {
+                Some(l) => out.push_str(l),
+                None => {
+                    out.push('@');
+                    out.push_str(special.split('^').next().unwrap_or(special));
+                }
+            }
+        } else {
+            let url = target.strip_prefix("mailto:").unwrap_or(target);
+            match label {
+                Some(l) if l != url => {
+                    out.push_str(l);
+                    out.push_str(" (");
+                    out.push_str(url);
+                    out.push(')');
+                }
+                _ => out.push_str(url),
+            }
+        }
+    }
+    out.push_str(&text[last..]);
+    out
+}
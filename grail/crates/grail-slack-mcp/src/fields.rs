@@ -0,0 +1,33 @@
+//! `fields`: keep only the message fields a caller asked for (e.g. `user`, `text`, `ts`,
+//! `thread_ts`), so reactions, blocks, attachments and the like don't cost tokens when
+//! nobody reads them.
+//!
+//! `user_name` (added by `resolve_entities`) stays whenever `user` is kept.
+
+use serde_json::Value;
+
+/// Fields kept along with another one.
+const COMPANIONS: [(&str, &str); 1] = [("user", "user_name")];
+
+/// Strip each message down to `fields`; no fields keeps everything.
+pub fn project(messages: &mut [Value], fields: &[String]) {
+    let fields: Vec<&str> = fields
+        .iter()
+        .map(|f| f.trim())
+        .filter(|f| !f.is_empty())
+        .collect();
+    if fields.is_empty() {
+        return;
+    }
+    let keep = |key: &str| {
+        fields.contains(&key)
+            || COMPANIONS
+                .iter()
+                .any(|(field, companion)| *companion == key && fields.contains(field))
+    };
+    for message in messages.iter_mut() {
+        if let Some(obj) = message.as_object_mut() {
+            obj.retain(|key, _| keep(key));
+        }
+    }
+}
@@ -1,25 +1,121 @@
 use std::borrow::Cow;
-use std::collections::HashSet;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
 
 use anyhow::Context;
 use rmcp::handler::server::ServerHandler;
 use rmcp::model::CallToolRequestParam;
 use rmcp::model::CallToolResult;
+use rmcp::model::GetPromptRequestParam;
+use rmcp::model::GetPromptResult;
 use rmcp::model::JsonObject;
+use rmcp::model::ListPromptsResult;
+use rmcp::model::ListResourceTemplatesResult;
+use rmcp::model::ListResourcesResult;
 use rmcp::model::ListToolsResult;
 use rmcp::model::PaginatedRequestParam;
+use rmcp::model::ReadResourceRequestParam;
+use rmcp::model::ReadResourceResult;
+use rmcp::model::ResourceContents;
 use rmcp::model::ServerCapabilities;
 use rmcp::model::ServerInfo;
+use rmcp::model::SubscribeRequestParam;
 use rmcp::model::Tool;
+use rmcp::model::UnsubscribeRequestParam;
 use rmcp::ErrorData as McpError;
 use rmcp::ServiceExt;
 use serde::Deserialize;
 use serde_json::json;
 use tokio::task;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 
+mod allowlist;
+mod audit;
+mod blocks;
+mod budget;
+mod cache;
+mod canvas;
+mod entities;
+mod fields;
+mod prompts;
+mod render;
+mod resources;
+mod socket;
+mod telemetry;
+mod timeouts;
+mod tokens;
+mod workspaces;
+
+use allowlist::ChannelAllowlist;
+use audit::AuditLog;
+use cache::TtlCache;
+use telemetry::Telemetry;
+use tokens::BotToken;
+
+/// `GRAIL_SLACK_API_BASE`, for pointing the server at a proxy or a mock Slack server.
+static API_BASE: LazyLock<String> = LazyLock::new(|| {
+    std::env::var("GRAIL_SLACK_API_BASE")
+        .ok()
+        .map(|v| v.trim().trim_end_matches('/').to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| DEFAULT_API_BASE.to_string())
+});
+const DEFAULT_API_BASE: &str = "https://slack.com/api";
+
+/// The URL of a Slack Web API method.
+fn slack_api(method: &str) -> String {
+    format!("{}/{method}", *API_BASE)
+}
+
+/// Slack's answers when an org-wide token calls a workspace-scoped method without `team_id`.
+const TEAM_REQUIRED_ERRORS: [&str; 3] = [
+    "missing_argument",
+    "team_access_not_granted",
+    "team_not_found",
+];
+
+/// Tools only offered with `GRAIL_SLACK_ALLOW_WRITES`.
+const WRITE_TOOLS: [&str; 7] = [
+    "post_message",
+    "schedule_message",
+    "list_scheduled_messages",
+    "add_reaction",
+    "open_dm",
+    "add_reminder",
+    "list_reminders",
+];
+/// Slack requests in flight at once unless `GRAIL_SLACK_MAX_CONCURRENCY` says otherwise;
+/// more wait their turn.
+const DEFAULT_MAX_CONCURRENCY: usize = 4;
+/// Retries on HTTP 429 unless `GRAIL_SLACK_MAX_RETRIES` says otherwise.
+const DEFAULT_MAX_RETRIES: u32 = 3;
+/// Tries for a read whose request fails transiently (connection error, timeout, 5xx),
+/// with jittered exponential backoff from `TRANSIENT_BACKOFF_BASE` between them.
+const MAX_TRANSIENT_TRIES: u32 = 3;
+const TRANSIENT_BACKOFF_BASE: Duration = Duration::from_millis(300);
+/// Longer waits than this are reported to the agent instead; a tool call shouldn't stall
+/// past Codex's tool timeout.
+const MAX_RETRY_AFTER_SECS: u64 = 20;
+/// `download_file_text` reads this much unless asked for less (or more, up to the max).
+const DEFAULT_FILE_BYTES: usize = 200 * 1024;
+const MAX_FILE_BYTES: usize = 1024 * 1024;
+/// `resolve_channel` gives up after this many `conversations.list` pages (of 1000).
+const MAX_RESOLVE_PAGES: usize = 20;
+/// How often `#name` patterns in the channel allow-list are resolved again.
+const CHANNEL_PATTERN_REFRESH: Duration = Duration::from_secs(5 * 60);
+/// `get_thread_full` stops after this many messages unless asked for fewer (or more, up
+/// to the max).
+const DEFAULT_THREAD_MESSAGES: usize = 500;
+const MAX_THREAD_MESSAGES: usize = 2_000;
+const THREAD_PAGE_SIZE: usize = 200;
+/// `get_users` looks up at most this many users per call, this many at a time.
+const MAX_BATCH_USERS: usize = 100;
+/// Slack only accepts `chat.scheduleMessage` up to 120 days ahead.
+const MAX_SCHEDULE_AHEAD_SECS: i64 = 120 * 24 * 60 * 60;
+const USER_LOOKUP_CONCURRENCY: usize = 8;
+
 fn stdio() -> (tokio::io::Stdin, tokio::io::Stdout) {
     (tokio::io::stdin(), tokio::io::stdout())
 }
@@ -28,26 +124,193 @@ fn stdio() -> (tokio::io::Stdin, tokio::io::Stdout) {
 struct SlackMcpServer {
     tools: Arc<Vec<Tool>>,
     http: reqwest::Client,
-    allowed_channels: Arc<HashSet<String>>,
+    bot_token: Arc<BotToken>,
+    telemetry: Arc<Telemetry>,
+    /// `GRAIL_SLACK_AUDIT_LOG`: every tool call is appended there when set.
+    audit: Option<Arc<AuditLog>>,
+    allowed_channels: Arc<ChannelAllowlist>,
+    /// `GRAIL_SLACK_DENY_CHANNELS`: never readable or writable, even when the allow-list is
+    /// empty (open) or lists them.
+    denied_channels: Arc<HashSet<String>>,
+    /// `GRAIL_SLACK_DM_USERS`: users `open_dm` may open a conversation with. Empty means
+    /// anyone, but only while the channel allow-list is open too.
+    dm_users: Arc<HashSet<String>>,
+    /// Workspace of the current conversation. Org-wide (Enterprise Grid) tokens need it
+    /// for workspace-scoped methods, and it resolves `T…:C…` allow-list entries.
+    team_id: Option<String>,
+    /// `GRAIL_SLACK_WORKSPACE_TOKENS`: bot tokens of other workspaces, picked by a tool's
+    /// `workspace` argument.
+    workspaces: Arc<HashMap<String, Arc<BotToken>>>,
+    /// `auth.test` for the bot token, fetched when first needed.
+    identity: Arc<tokio::sync::OnceCell<Identity>>,
+    /// `SLACK_USER_TOKEN`, for methods most plans only allow with a user token (search).
+    /// It belongs to the default workspace, so calls routed to another one go without.
+    user_token: Option<String>,
+    /// Who `SLACK_USER_TOKEN` belongs to (`auth.test`), fetched when first needed.
+    user_token_owner: Arc<tokio::sync::OnceCell<String>>,
+    /// `GRAIL_SLACK_ALLOW_WRITES`: write tools (`post_message`, `schedule_message`,
+    /// `add_reaction`, `open_dm`, `add_reminder`) are only listed and callable when set, so read-only deployments stay read-only.
+    allow_writes: bool,
+    /// `GRAIL_SLACK_AUTO_JOIN`: a read that fails because the bot isn't in a public channel
+    /// joins it and tries again.
+    auto_join: bool,
+    /// `users.info` results by user id.
+    users: Arc<TtlCache<serde_json::Value>>,
+    /// `conversations.list` pages (before allow-list filtering) by request.
+    channel_pages: Arc<TtlCache<(Vec<serde_json::Value>, Option<String>)>>,
+    /// `emoji.list` (custom emoji name → image URL or `alias:other`) by workspace.
+    emoji: Arc<TtlCache<serde_json::Map<String, serde_json::Value>>>,
+    /// `usergroups.list` (enabled groups) by workspace.
+    usergroups: Arc<TtlCache<Vec<serde_json::Value>>>,
+    /// `chat.getPermalink` results by workspace, channel and message ts.
+    permalinks: Arc<TtlCache<String>>,
+    /// Limits concurrent Slack requests, so a burst of parallel tool calls queues here
+    /// instead of tripping Slack's rate limits.
+    api_permits: Arc<tokio::sync::Semaphore>,
+    /// Retries after a rate-limited (HTTP 429) response.
+    max_retries: u32,
+    /// Per-request and per-tool call time limits.
+    timeouts: Arc<timeouts::Timeouts>,
+    /// `GRAIL_SLACK_TEXT_CONTENT`: how results are rendered into `content`.
+    text_content: render::TextContent,
+    /// Channel resources a client subscribed to, each with its polling task.
+    subscriptions: Arc<std::sync::Mutex<HashMap<String, task::JoinHandle<()>>>>,
+    poll_interval: Duration,
+    /// New messages from the Socket Mode bridge, when `SLACK_APP_TOKEN` is set.
+    live_events: Option<socket::LiveEvents>,
 }
 
 impl SlackMcpServer {
     fn new() -> anyhow::Result<Self> {
-        let tools = vec![
+        let mut tools = vec![
             Self::tool_get_channel_history()?,
             Self::tool_get_thread()?,
+            Self::tool_get_thread_full()?,
             Self::tool_get_permalink()?,
+            Self::tool_resolve_permalink()?,
             Self::tool_get_user()?,
+            Self::tool_get_users()?,
+            Self::tool_list_users()?,
+            Self::tool_list_usergroups()?,
+            Self::tool_get_usergroup()?,
             Self::tool_list_channels()?,
+            Self::tool_resolve_channel()?,
+            Self::tool_get_channel_info()?,
+            Self::tool_list_channel_members()?,
+            Self::tool_list_pins()?,
+            Self::tool_list_bookmarks()?,
             Self::tool_search_messages()?,
+            Self::tool_search_files()?,
+            Self::tool_list_reactions()?,
+            Self::tool_get_message_reactions()?,
+            Self::tool_list_emoji()?,
+            Self::tool_get_file_info()?,
+            Self::tool_download_file_text()?,
+            Self::tool_get_canvas()?,
         ];
+        let allow_writes = env_flag("GRAIL_SLACK_ALLOW_WRITES");
+        let cache_ttl = cache::ttl_from_env();
+        if allow_writes {
+            tools.push(Self::tool_post_message()?);
+            tools.push(Self::tool_schedule_message()?);
+            tools.push(Self::tool_list_scheduled_messages()?);
+            tools.push(Self::tool_add_reaction()?);
+            tools.push(Self::tool_open_dm()?);
+            tools.push(Self::tool_add_reminder()?);
+            tools.push(Self::tool_list_reminders()?);
+        }
+        // `GRAIL_SLACK_TOOLS` narrows the set; it can't turn on write tools by itself.
+        let enabled = allowlist::parse(&std::env::var("GRAIL_SLACK_TOOLS").unwrap_or_default());
+        if !enabled.is_empty() {
+            for name in &enabled {
+                if !tools.iter().any(|t| t.name == name.as_str()) {
+                    warn!(tool = %name, "GRAIL_SLACK_TOOLS names a tool that is unknown or needs GRAIL_SLACK_ALLOW_WRITES");
+                }
+            }
+            tools.retain(|t| enabled.contains(t.name.as_ref()));
+        }
+
+        let workspaces: HashMap<String, Arc<BotToken>> = workspaces::from_env()?
+            .into_iter()
+            .map(|(team, token)| (team, Arc::new(BotToken::fixed(token))))
+            .collect();
+        if !workspaces.is_empty() {
+            let mut known: Vec<&str> = workspaces.keys().map(String::as_str).collect();
+            known.sort_unstable();
+            let description = format!(
+                "Slack workspace (team id) to act in: one of {}. Defaults to the server's own workspace.",
+                known.join(", ")
+            );
+            for tool in &mut tools {
+                let schema = Arc::make_mut(&mut tool.input_schema);
+                if let Some(properties) =
+                    schema.get_mut("properties").and_then(|p| p.as_object_mut())
+                {
+                    properties.insert(
+                        "workspace".to_string(),
+                        json!({ "type": "string", "description": description }),
+                    );
+                }
+            }
+        }
 
-        let allowed_channels = parse_allowlist_env("GRAIL_SLACK_ALLOW_CHANNELS");
+        let allowed_channels = ChannelAllowlist::from_env()?;
+        let team_id = std::env::var("GRAIL_SLACK_TEAM_ID")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        let max_retries = std::env::var("GRAIL_SLACK_MAX_RETRIES")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(DEFAULT_MAX_RETRIES);
 
         Ok(Self {
             tools: Arc::new(tools),
             http: reqwest::Client::new(),
+            bot_token: Arc::new(BotToken::from_env()),
+            telemetry: Arc::new(Telemetry::default()),
+            audit: AuditLog::from_env()?.map(Arc::new),
             allowed_channels: Arc::new(allowed_channels),
+            denied_channels: Arc::new(allowlist::parse(
+                &std::env::var("GRAIL_SLACK_DENY_CHANNELS").unwrap_or_default(),
+            )),
+            dm_users: Arc::new(allowlist::parse(
+                &std::env::var("GRAIL_SLACK_DM_USERS").unwrap_or_default(),
+            )),
+            team_id,
+            workspaces: Arc::new(workspaces),
+            identity: Arc::new(tokio::sync::OnceCell::new()),
+            user_token: std::env::var("SLACK_USER_TOKEN")
+                .ok()
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty()),
+            user_token_owner: Arc::new(tokio::sync::OnceCell::new()),
+            allow_writes,
+            auto_join: env_flag("GRAIL_SLACK_AUTO_JOIN"),
+            users: Arc::new(TtlCache::new(cache_ttl)),
+            channel_pages: Arc::new(TtlCache::new(cache_ttl)),
+            emoji: Arc::new(TtlCache::new(cache_ttl)),
+            usergroups: Arc::new(TtlCache::new(cache_ttl)),
+            permalinks: Arc::new(TtlCache::new(if cache_ttl.is_zero() {
+                cache_ttl
+            } else {
+                cache::PERMALINK_TTL
+            })),
+            api_permits: Arc::new(tokio::sync::Semaphore::new(
+                std::env::var("GRAIL_SLACK_MAX_CONCURRENCY")
+                    .ok()
+                    .and_then(|v| v.trim().parse::<usize>().ok())
+                    .filter(|n| *n > 0)
+                    .unwrap_or(DEFAULT_MAX_CONCURRENCY),
+            )),
+            max_retries,
+            timeouts: Arc::new(timeouts::Timeouts::from_env(Duration::from_secs(
+                MAX_RETRY_AFTER_SECS * u64::from(max_retries),
+            ))),
+            text_content: render::mode_from_env(),
+            subscriptions: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            poll_interval: resources::poll_interval_from_env(),
+            live_events: socket::app_token_from_env().map(|_| socket::live_events()),
         })
     }
 
@@ -57,7 +320,13 @@ impl SlackMcpServer {
             "properties": {
                 "channel": { "type": "string", "description": "Slack channel ID (e.g. C123...)." },
                 "before_ts": { "type": "string", "description": "Fetch messages earlier than this ts." },
-                "limit": { "type": "integer", "minimum": 1, "maximum": 200, "default": 20 }
+                "oldest_ts": { "type": "string", "description": "Only messages at or after this ts. With before_ts, fetches exactly the window between them." },
+                "cursor": { "type": "string", "description": "next_cursor from a previous call, to fetch the next page." },
+                "limit": { "type": "integer", "minimum": 1, "maximum": 200, "default": 20 },
+                "resolve_entities": { "type": "boolean", "default": false, "description": "Replace <@U123> mentions, channel links and URLs in message text with readable names." },
+                "flatten_blocks": { "type": "boolean", "default": false, "description": "Replace message text with the plain text of its blocks and attachments (bot and app messages often have little else)." },
+                "fields": { "type": "array", "items": { "type": "string" }, "description": "Only return these message fields, e.g. [\"user\", \"text\", \"ts\", \"thread_ts\"] (user_name comes with user). Leaves out reactions, blocks, attachments and the rest. Default: all fields." },
+                "max_chars": { "type": "integer", "minimum": 1, "description": "Keep the messages within about this many characters: blocks, attachments and files are dropped first, then the longest texts are cut. `truncated` is true when anything was." }
             },
             "required": ["channel"],
             "additionalProperties": false
@@ -66,7 +335,9 @@ impl SlackMcpServer {
 
         Ok(Tool::new(
             Cow::Borrowed("get_channel_history"),
-            Cow::Borrowed("Fetch recent messages from a channel, optionally before a timestamp."),
+            Cow::Borrowed(
+                "Fetch recent messages from a channel, optionally within a time window (oldest_ts..before_ts). Pass next_cursor back as cursor for older messages.",
+            ),
             Arc::new(schema),
         ))
     }
@@ -78,7 +349,12 @@ impl SlackMcpServer {
                 "channel": { "type": "string" },
                 "thread_ts": { "type": "string" },
                 "before_ts": { "type": "string", "description": "Fetch replies up to this ts (inclusive)." },
-                "limit": { "type": "integer", "minimum": 1, "maximum": 200, "default": 50 }
+                "cursor": { "type": "string", "description": "next_cursor from a previous call, to fetch the next page." },
+                "limit": { "type": "integer", "minimum": 1, "maximum": 200, "default": 50 },
+                "resolve_entities": { "type": "boolean", "default": false, "description": "Replace <@U123> mentions, channel links and URLs in message text with readable names." },
+                "flatten_blocks": { "type": "boolean", "default": false, "description": "Replace message text with the plain text of its blocks and attachments (bot and app messages often have little else)." },
+                "fields": { "type": "array", "items": { "type": "string" }, "description": "Only return these message fields, e.g. [\"user\", \"text\", \"ts\", \"thread_ts\"] (user_name comes with user). Leaves out reactions, blocks, attachments and the rest. Default: all fields." },
+                "max_chars": { "type": "integer", "minimum": 1, "description": "Keep the messages within about this many characters: blocks, attachments and files are dropped first, then the longest texts are cut. `truncated` is true when anything was." }
             },
             "required": ["channel", "thread_ts"],
             "additionalProperties": false
@@ -92,6 +368,32 @@ impl SlackMcpServer {
         ))
     }
 
+    fn tool_get_thread_full() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string" },
+                "thread_ts": { "type": "string" },
+                "max_messages": { "type": "integer", "minimum": 1, "maximum": MAX_THREAD_MESSAGES, "default": DEFAULT_THREAD_MESSAGES },
+                "resolve_entities": { "type": "boolean", "default": false, "description": "Replace <@U123> mentions, channel links and URLs in message text with readable names." },
+                "flatten_blocks": { "type": "boolean", "default": false, "description": "Replace message text with the plain text of its blocks and attachments (bot and app messages often have little else)." },
+                "fields": { "type": "array", "items": { "type": "string" }, "description": "Only return these message fields, e.g. [\"user\", \"text\", \"ts\", \"thread_ts\"] (user_name comes with user). Leaves out reactions, blocks, attachments and the rest. Default: all fields." },
+                "max_chars": { "type": "integer", "minimum": 1, "description": "Keep the messages within about this many characters: blocks, attachments and files are dropped first, then the longest texts are cut. `truncated` is true when anything was." }
+            },
+            "required": ["channel", "thread_ts"],
+            "additionalProperties": false
+        }))
+        .context("deserialize get_thread_full schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("get_thread_full"),
+            Cow::Borrowed(
+                "Fetch a whole Slack thread in one call, following pages up to max_messages. `truncated` is true when the thread is longer (or max_chars cut it).",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
     fn tool_get_permalink() -> anyhow::Result<Tool> {
         let schema: JsonObject = serde_json::from_value(json!({
             "type": "object",
@@ -111,6 +413,26 @@ impl SlackMcpServer {
         ))
     }
 
+    fn tool_resolve_permalink() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string", "description": "Slack message link, e.g. https://acme.slack.com/archives/C123/p1700000000123456" }
+            },
+            "required": ["url"],
+            "additionalProperties": false
+        }))
+        .context("deserialize resolve_permalink schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("resolve_permalink"),
+            Cow::Borrowed(
+                "Turn a pasted Slack message link into its channel ID, message ts and thread ts, ready for get_thread.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
     fn tool_get_user() -> anyhow::Result<Tool> {
         let schema: JsonObject = serde_json::from_value(json!({
             "type": "object",
@@ -129,10 +451,95 @@ impl SlackMcpServer {
         ))
     }
 
+    fn tool_get_users() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "user_ids": {
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "minItems": 1,
+                    "maxItems": MAX_BATCH_USERS
+                },
+                "full_profiles": { "type": "boolean", "default": false, "description": "Return the complete users.info objects instead of names, titles and time zones." }
+            },
+            "required": ["user_ids"],
+            "additionalProperties": false
+        }))
+        .context("deserialize get_users schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("get_users"),
+            Cow::Borrowed(
+                "Look up several Slack users at once, e.g. everyone who posted in a thread. Use this instead of repeated get_user calls.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_list_users() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "cursor": { "type": "string", "description": "next_cursor from a previous call, to fetch the next page." },
+                "limit": { "type": "integer", "minimum": 1, "maximum": 1000, "default": 200 },
+                "exclude_bots": { "type": "boolean", "default": true },
+                "exclude_deactivated": { "type": "boolean", "default": true }
+            },
+            "additionalProperties": false
+        }))
+        .context("deserialize list_users schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("list_users"),
+            Cow::Borrowed(
+                "List workspace members with their names, titles and time zones. A page can come back with fewer users than `limit` after filtering; keep going while has_more is true.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_list_usergroups() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": false
+        }))
+        .context("deserialize list_usergroups schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("list_usergroups"),
+            Cow::Borrowed(
+                "List the workspace's user groups (e.g. @oncall) with their handles and member counts.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_get_usergroup() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "usergroup": { "type": "string", "description": "User group ID (S123...), handle (@oncall) or a <!subteam^S123> mention." },
+                "include_profiles": { "type": "boolean", "default": false, "description": "Return members' names, titles and time zones instead of just their IDs." }
+            },
+            "required": ["usergroup"],
+            "additionalProperties": false
+        }))
+        .context("deserialize get_usergroup schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("get_usergroup"),
+            Cow::Borrowed("Expand a user group such as @oncall into its members."),
+            Arc::new(schema),
+        ))
+    }
+
     fn tool_list_channels() -> anyhow::Result<Tool> {
         let schema: JsonObject = serde_json::from_value(json!({
             "type": "object",
             "properties": {
+                "cursor": { "type": "string", "description": "next_cursor from a previous call, to fetch the next page." },
                 "limit": { "type": "integer", "minimum": 1, "maximum": 1000, "default": 200 }
             },
             "additionalProperties": false
@@ -146,163 +553,1728 @@ impl SlackMcpServer {
         ))
     }
 
-    fn tool_search_messages() -> anyhow::Result<Tool> {
+    fn tool_resolve_channel() -> anyhow::Result<Tool> {
         let schema: JsonObject = serde_json::from_value(json!({
             "type": "object",
             "properties": {
-                "query": { "type": "string", "description": "Slack search query. Tip: use `in:<channel_id>` to restrict." },
-                "count": { "type": "integer", "minimum": 1, "maximum": 20, "default": 10 }
+                "name": { "type": "string", "description": "Channel name, with or without # (e.g. #general)." }
             },
-            "required": ["query"],
+            "required": ["name"],
             "additionalProperties": false
         }))
-        .context("deserialize search_messages schema")?;
+        .context("deserialize resolve_channel schema")?;
 
         Ok(Tool::new(
-            Cow::Borrowed("search_messages"),
-            Cow::Borrowed("Search Slack messages (requires Slack scope search:read)."),
+            Cow::Borrowed("resolve_channel"),
+            Cow::Borrowed("Look up a Slack channel ID (C123...) by its name."),
             Arc::new(schema),
         ))
     }
 
-    fn slack_token() -> Result<String, McpError> {
-        std::env::var("SLACK_BOT_TOKEN").map_err(|_| {
-            McpError::invalid_params("missing SLACK_BOT_TOKEN env var", Some(json!({})))
-        })
-    }
+    fn tool_get_channel_info() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string", "description": "Slack channel ID (e.g. C123...)." }
+            },
+            "required": ["channel"],
+            "additionalProperties": false
+        }))
+        .context("deserialize get_channel_info schema")?;
 
-    fn channel_allowed(&self, channel: &str) -> bool {
-        // Mirror server-side behavior: DMs are always allowed.
-        if channel.starts_with('D') {
-            return true;
-        }
-        if self.allowed_channels.is_empty() {
-            return true;
-        }
-        self.allowed_channels.contains(channel)
+        Ok(Tool::new(
+            Cow::Borrowed("get_channel_info"),
+            Cow::Borrowed(
+                "Fetch a channel's name, topic, purpose, member count and whether it is private or archived.",
+            ),
+            Arc::new(schema),
+        ))
     }
 
-    async fn slack_api_get<T: for<'de> Deserialize<'de>>(
-        &self,
-        url: &str,
-        query: &[(&str, String)],
-    ) -> Result<T, McpError> {
-        let token = Self::slack_token()?;
-        let resp = self
-            .http
-            .get(url)
-            .header("Authorization", format!("Bearer {token}"))
-            .query(query)
-            .send()
-            .await
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+    fn tool_list_channel_members() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string", "description": "Slack channel ID (e.g. C123...)." },
+                "cursor": { "type": "string", "description": "next_cursor from a previous call, to fetch the next page." },
+                "limit": { "type": "integer", "minimum": 1, "maximum": 1000, "default": 200 }
+            },
+            "required": ["channel"],
+            "additionalProperties": false
+        }))
+        .context("deserialize list_channel_members schema")?;
 
-        let value = resp
-            .json::<serde_json::Value>()
-            .await
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        Ok(Tool::new(
+            Cow::Borrowed("list_channel_members"),
+            Cow::Borrowed("List the user IDs of a channel's members. Look them up with get_user."),
+            Arc::new(schema),
+        ))
+    }
 
-        let ok = value.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
-        if !ok {
-            let err = value
-                .get("error")
-                .and_then(|v| v.as_str())
-                .unwrap_or("unknown_error");
-            return Err(McpError::internal_error(
-                format!("slack api error: {err}"),
-                Some(value),
-            ));
-        }
+    fn tool_list_pins() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string", "description": "Slack channel ID (e.g. C123...)." }
+            },
+            "required": ["channel"],
+            "additionalProperties": false
+        }))
+        .context("deserialize list_pins schema")?;
 
-        serde_json::from_value(value).map_err(|e| McpError::internal_error(e.to_string(), None))
+        Ok(Tool::new(
+            Cow::Borrowed("list_pins"),
+            Cow::Borrowed(
+                "List the messages and files pinned in a channel (often runbooks and decisions).",
+            ),
+            Arc::new(schema),
+        ))
     }
-}
 
-#[derive(Deserialize)]
-struct SlackOkWrapper<T> {
-    ok: bool,
-    #[allow(dead_code)]
-    error: Option<String>,
-    #[serde(flatten)]
-    inner: T,
-}
+    fn tool_list_bookmarks() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string", "description": "Slack channel ID (e.g. C123...)." }
+            },
+            "required": ["channel"],
+            "additionalProperties": false
+        }))
+        .context("deserialize list_bookmarks schema")?;
 
-#[derive(Deserialize)]
-struct HistoryResponse {
-    messages: Vec<serde_json::Value>,
-    #[allow(dead_code)]
-    has_more: Option<bool>,
-}
+        Ok(Tool::new(
+            Cow::Borrowed("list_bookmarks"),
+            Cow::Borrowed(
+                "List a channel's bookmarks (the links under the channel name, e.g. dashboards and docs).",
+            ),
+            Arc::new(schema),
+        ))
+    }
 
-#[derive(Deserialize)]
-struct RepliesResponse {
-    messages: Vec<serde_json::Value>,
-}
+    fn tool_search_messages() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Slack search query. Tip: use `in:<channel_id>` to restrict." },
+                "count": { "type": "integer", "minimum": 1, "maximum": 20, "default": 10 },
+                "max_chars": { "type": "integer", "minimum": 1, "description": "Keep the messages within about this many characters: blocks, attachments and files are dropped first, then the longest texts are cut. `truncated` is true when anything was." }
+            },
+            "required": ["query"],
+            "additionalProperties": false
+        }))
+        .context("deserialize search_messages schema")?;
 
-#[derive(Deserialize)]
-struct PermalinkResponse {
-    permalink: String,
-}
+        Ok(Tool::new(
+            Cow::Borrowed("search_messages"),
+            Cow::Borrowed(
+                "Search Slack messages (requires a user token with Slack scope search:read).",
+            ),
+            Arc::new(schema),
+        ))
+    }
 
-#[derive(Deserialize)]
-struct UserInfoResponse {
-    user: serde_json::Value,
-}
+    fn tool_search_files() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Slack search query (file names, titles and contents). Tip: use `in:<channel_id>` or `type:pdfs` to narrow it." },
+                "count": { "type": "integer", "minimum": 1, "maximum": 20, "default": 10 }
+            },
+            "required": ["query"],
+            "additionalProperties": false
+        }))
+        .context("deserialize search_files schema")?;
 
-#[derive(Deserialize)]
-struct ListChannelsResponse {
-    channels: Vec<serde_json::Value>,
-    #[allow(dead_code)]
-    response_metadata: Option<serde_json::Value>,
-}
+        Ok(Tool::new(
+            Cow::Borrowed("search_files"),
+            Cow::Borrowed(
+                "Search files uploaded to Slack (requires a user token with Slack scope search:read). Read one with download_file_text.",
+            ),
+            Arc::new(schema),
+        ))
+    }
 
-#[derive(Deserialize)]
-struct ArgsGetChannelHistory {
-    channel: String,
-    #[serde(default)]
-    before_ts: Option<String>,
-    #[serde(default)]
-    limit: Option<i64>,
-}
+    fn tool_post_message() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string", "description": "Slack channel ID (e.g. C123...)." },
+                "thread_ts": { "type": "string", "description": "Reply in this thread instead of the channel." },
+                "text": { "type": "string", "description": "Message text (Slack mrkdwn)." }
+            },
+            "required": ["channel", "text"],
+            "additionalProperties": false
+        }))
+        .context("deserialize post_message schema")?;
 
-#[derive(Deserialize)]
-struct ArgsGetThread {
-    channel: String,
-    thread_ts: String,
-    #[serde(default)]
-    before_ts: Option<String>,
-    #[serde(default)]
-    limit: Option<i64>,
-}
+        Ok(Tool::new(
+            Cow::Borrowed("post_message"),
+            Cow::Borrowed("Post a message to a Slack channel, or reply in a thread."),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_schedule_message() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string", "description": "Slack channel ID (e.g. C123...)." },
+                "post_at": { "type": "integer", "description": "When to post, as Unix time in seconds (at most 120 days ahead)." },
+                "thread_ts": { "type": "string", "description": "Reply in this thread instead of the channel." },
+                "text": { "type": "string", "description": "Message text (Slack mrkdwn)." }
+            },
+            "required": ["channel", "post_at", "text"],
+            "additionalProperties": false
+        }))
+        .context("deserialize schedule_message schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("schedule_message"),
+            Cow::Borrowed(
+                "Schedule a message to be posted to a channel or thread later, e.g. a follow-up or reminder.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_list_scheduled_messages() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string", "description": "Only messages scheduled for this channel." },
+                "cursor": { "type": "string", "description": "next_cursor from a previous call, to fetch the next page." }
+            },
+            "additionalProperties": false
+        }))
+        .context("deserialize list_scheduled_messages schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("list_scheduled_messages"),
+            Cow::Borrowed("List messages the bot has scheduled that haven't been posted yet."),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_add_reaction() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string" },
+                "message_ts": { "type": "string" },
+                "name": { "type": "string", "description": "Emoji name without colons (e.g. eyes, white_check_mark)." }
+            },
+            "required": ["channel", "message_ts", "name"],
+            "additionalProperties": false
+        }))
+        .context("deserialize add_reaction schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("add_reaction"),
+            Cow::Borrowed("React to a Slack message with an emoji."),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_open_dm() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "user_id": { "type": "string", "description": "Slack user ID (e.g. U123...)." }
+            },
+            "required": ["user_id"],
+            "additionalProperties": false
+        }))
+        .context("deserialize open_dm schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("open_dm"),
+            Cow::Borrowed(
+                "Open (or find) the direct-message channel with a user and return its ID, to continue a sensitive follow-up privately with post_message. Only users allowed by GRAIL_SLACK_DM_USERS (anyone when no channel or DM allow-list is set).",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_add_reminder() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "user_id": { "type": "string", "description": "Slack user ID of the person who asked for the reminder. It must be the user SLACK_USER_TOKEN belongs to: the reminder goes to them." },
+                "text": { "type": "string", "description": "What to be reminded about." },
+                "time": { "type": "string", "description": "Unix time in seconds, or Slack's natural language (e.g. \"in 15 minutes\", \"tomorrow at 9am\", \"every Monday\")." }
+            },
+            "required": ["user_id", "text", "time"],
+            "additionalProperties": false
+        }))
+        .context("deserialize add_reminder schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("add_reminder"),
+            Cow::Borrowed(
+                "Create a native Slack reminder for the user whose token the server uses (SLACK_USER_TOKEN, scope reminders:write). Refused when someone else asked for it.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_list_reminders() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": false
+        }))
+        .context("deserialize list_reminders schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("list_reminders"),
+            Cow::Borrowed(
+                "List Slack reminders of the user whose token the server uses (SLACK_USER_TOKEN, scope reminders:read).",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_list_reactions() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string" },
+                "message_ts": { "type": "string" }
+            },
+            "required": ["channel", "message_ts"],
+            "additionalProperties": false
+        }))
+        .context("deserialize list_reactions schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("list_reactions"),
+            Cow::Borrowed(
+                "List the emoji reactions on a Slack message and who reacted (requires Slack scope reactions:read).",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_get_message_reactions() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string" },
+                "message_ts": { "type": "string" },
+                "emoji": { "type": "array", "items": { "type": "string" }, "description": "Only count these reactions (e.g. [\"white_check_mark\", \"x\"]), the options of the vote." },
+                "resolve_users": { "type": "boolean", "default": false, "description": "Add the voters' display names." }
+            },
+            "required": ["channel", "message_ts"],
+            "additionalProperties": false
+        }))
+        .context("deserialize get_message_reactions schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("get_message_reactions"),
+            Cow::Borrowed(
+                "Tally the reactions on a Slack message as votes: count and voters per emoji (skin tones merged), most votes first, plus anyone who voted for more than one option (requires Slack scope reactions:read).",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_list_emoji() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Only emoji whose name contains this." },
+                "limit": { "type": "integer", "minimum": 1, "maximum": 5000, "default": 500 }
+            },
+            "additionalProperties": false
+        }))
+        .context("deserialize list_emoji schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("list_emoji"),
+            Cow::Borrowed(
+                "List the workspace's custom emoji (image URL, or the emoji an alias points to), to make sense of unfamiliar reaction names.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_get_file_info() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "file_id": { "type": "string", "description": "Slack file ID (e.g. F123...), from a message's files." }
+            },
+            "required": ["file_id"],
+            "additionalProperties": false
+        }))
+        .context("deserialize get_file_info schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("get_file_info"),
+            Cow::Borrowed(
+                "Fetch metadata (name, type, size, where it was shared) for a Slack file.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_get_canvas() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "channel": { "type": "string", "description": "Read this channel's canvas." },
+                "canvas_id": { "type": "string", "description": "Canvas file ID (F123...), for standalone canvases." },
+                "max_bytes": { "type": "integer", "minimum": 1, "maximum": MAX_FILE_BYTES, "default": DEFAULT_FILE_BYTES }
+            },
+            "additionalProperties": false
+        }))
+        .context("deserialize get_canvas schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("get_canvas"),
+            Cow::Borrowed(
+                "Read a Slack canvas as plain text: a channel's canvas (pass channel) or any canvas by ID (pass canvas_id).",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_download_file_text() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "file_id": { "type": "string" },
+                "max_bytes": { "type": "integer", "minimum": 1, "maximum": MAX_FILE_BYTES, "default": DEFAULT_FILE_BYTES }
+            },
+            "required": ["file_id"],
+            "additionalProperties": false
+        }))
+        .context("deserialize download_file_text schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("download_file_text"),
+            Cow::Borrowed(
+                "Read the contents of a text file or snippet shared in Slack (logs, configs, code). Binary files are refused; long files are cut at max_bytes.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    /// This server acting in `workspace`, with that workspace's bot token.
+    fn for_workspace(&self, workspace: &str) -> Result<Self, McpError> {
+        if self.team_id.as_deref() == Some(workspace) {
+            return Ok(self.clone());
+        }
+        let Some(token) = self.workspaces.get(workspace) else {
+            return Err(McpError::invalid_params(
+                format!("unknown workspace {workspace}; it has no token in GRAIL_SLACK_WORKSPACE_TOKENS"),
+                None,
+            ));
+        };
+        let mut scoped = self.clone();
+        scoped.bot_token = token.clone();
+        scoped.identity = Arc::new(tokio::sync::OnceCell::new());
+        scoped.team_id = Some(workspace.to_string());
+        scoped.user_token = None;
+        Ok(scoped)
+    }
+
+    fn channel_allowed(&self, channel: &str) -> bool {
+        self.channel_allowed_in(self.team_id.as_deref(), channel)
+    }
+
+    fn ensure_channel_allowed(&self, channel: &str) -> Result<(), McpError> {
+        if self.channel_denied_in(self.team_id.as_deref(), channel) {
+            return Err(McpError::invalid_params(
+                "channel blocked by GRAIL_SLACK_DENY_CHANNELS",
+                Some(json!({ "channel": channel })),
+            ));
+        }
+        if self.channel_allowed(channel) {
+            Ok(())
+        } else {
+            Err(McpError::invalid_params(
+                "channel not allowed by GRAIL_SLACK_ALLOW_CHANNELS",
+                Some(json!({ "channel": channel })),
+            ))
+        }
+    }
+
+    /// DMs get past the channel allow-list, so who the bot may open one with is checked
+    /// here: a listed (or, with no lists at all, any) active human user.
+    async fn ensure_dm_allowed(&self, user_id: &str) -> Result<(), McpError> {
+        let listed = if self.dm_users.is_empty() {
+            self.allowed_channels.is_open()
+        } else {
+            self.dm_users.contains(user_id)
+        };
+        if !listed {
+            return Err(McpError::invalid_params(
+                "user not allowed by GRAIL_SLACK_DM_USERS",
+                Some(json!({ "user_id": user_id })),
+            ));
+        }
+        let user = self.user_info(user_id).await?;
+        if is_bot_user(&user) || user.get("deleted").and_then(|v| v.as_bool()) == Some(true) {
+            return Err(McpError::invalid_params(
+                "open_dm only opens conversations with active people",
+                Some(json!({ "user_id": user_id })),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The channel of a `slack://C123` resource URI, if it is allowed.
+    fn resource_channel<'a>(&self, uri: &'a str) -> Result<&'a str, McpError> {
+        let channel = resources::parse_channel_uri(uri).ok_or_else(|| {
+            McpError::resource_not_found(
+                "not a slack channel resource",
+                Some(json!({ "uri": uri })),
+            )
+        })?;
+        self.ensure_channel_allowed(channel)?;
+        Ok(channel)
+    }
+
+    /// A file is readable when it was shared in at least one allowed channel and in no
+    /// denied one.
+    fn file_allowed(&self, file: &serde_json::Value) -> bool {
+        let channels: Vec<&str> = ["channels", "groups", "ims"]
+            .iter()
+            .filter_map(|k| file.get(*k).and_then(|v| v.as_array()))
+            .flatten()
+            .filter_map(|c| c.as_str())
+            .collect();
+        let team_id = self.team_id.as_deref();
+        if channels.iter().any(|c| self.channel_denied_in(team_id, c)) {
+            return false;
+        }
+        self.allowed_channels.is_open() || channels.iter().any(|c| self.channel_allowed(c))
+    }
+
+    async fn file_info(&self, file_id: &str) -> Result<serde_json::Value, McpError> {
+        let file = self.file_info_unchecked(file_id).await?;
+        if !self.file_allowed(&file) {
+            return Err(McpError::invalid_params(
+                "file was not shared in a channel allowed by GRAIL_SLACK_ALLOW_CHANNELS",
+                Some(json!({ "file_id": file_id })),
+            ));
+        }
+        Ok(file)
+    }
+
+    /// For files reached through a channel that was already checked (its canvas).
+    async fn file_info_unchecked(&self, file_id: &str) -> Result<serde_json::Value, McpError> {
+        let query = vec![("file", file_id.to_string())];
+        let SlackOkWrapper { inner, .. }: SlackOkWrapper<FileInfoResponse> =
+            self.slack_api_get(&slack_api("files.info"), &query).await?;
+        Ok(inner.file)
+    }
+
+    /// Up to `max_bytes` of a private file URL, and whether it was cut.
+    async fn download_private(
+        &self,
+        url: &str,
+        max_bytes: usize,
+    ) -> Result<(Vec<u8>, bool), McpError> {
+        let token = self.bot_token.get(&self.http).await?;
+        let _permit = self.api_permit().await?;
+        let mut resp = self
+            .with_request_timeout(self.http.get(url))
+            .header("Authorization", format!("Bearer {token}"))
+            .send()
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        if !resp.status().is_success() {
+            return Err(McpError::internal_error(
+                format!("slack file download failed with status {}", resp.status()),
+                None,
+            ));
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+        {
+            bytes.extend_from_slice(&chunk);
+            if bytes.len() > max_bytes {
+                bytes.truncate(max_bytes);
+                return Ok((bytes, true));
+            }
+        }
+        Ok((bytes, false))
+    }
+
+    /// Entries take the same forms as allow-list entries. Deny wins over allow, as with
+    /// the web tools' domain lists.
+    fn channel_denied_in(&self, team_id: Option<&str>, channel: &str) -> bool {
+        let denied = &self.denied_channels;
+        denied.contains(channel)
+            || team_id.filter(|t| !t.is_empty()).is_some_and(|t| {
+                denied.contains(&format!("{t}:{channel}")) || denied.contains(&format!("{t}:*"))
+            })
+    }
+
+    /// Whether the bot itself can read `channel`: not a DM or group DM, and public or a
+    /// private channel it is a member of. `known` remembers answers within one call.
+    async fn readable_by_bot(&self, channel: &str, known: &mut HashMap<String, bool>) -> bool {
+        if channel.is_empty() || channel.starts_with('D') {
+            return false;
+        }
+        if let Some(readable) = known.get(channel) {
+            return *readable;
+        }
+        let query = vec![("channel", channel.to_string())];
+        let readable = match self
+            .slack_api_get::<SlackOkWrapper<ChannelInfoResponse>>(
+                &slack_api("conversations.info"),
+                &query,
+            )
+            .await
+        {
+            Ok(SlackOkWrapper { inner, .. }) => {
+                let flag = |k: &str| inner.channel.get(k).and_then(|v| v.as_bool());
+                !flag("is_im").unwrap_or(false)
+                    && !flag("is_mpim").unwrap_or(false)
+                    && (!flag("is_private").unwrap_or(false) || flag("is_member").unwrap_or(false))
+            }
+            // Private channels the bot isn't in come back as `channel_not_found`.
+            Err(_) => false,
+        };
+        known.insert(channel.to_string(), readable);
+        readable
+    }
+
+    /// Search with `SLACK_USER_TOKEN` sees everything its owner can, DMs included. Keep
+    /// only matches the bot could read itself: no DMs or group DMs, and private channels
+    /// only when the bot is a member.
+    async fn user_search_match_allowed(
+        &self,
+        channel: &serde_json::Value,
+        known: &mut HashMap<String, bool>,
+    ) -> bool {
+        let flag = |k: &str| channel.get(k).and_then(|v| v.as_bool()).unwrap_or(false);
+        let id = channel.get("id").and_then(|v| v.as_str()).unwrap_or("");
+        if flag("is_im") || flag("is_mpim") || id.starts_with('D') {
+            return false;
+        }
+        if flag("is_private") || flag("is_group") {
+            return self.readable_by_bot(id, known).await;
+        }
+        !id.is_empty()
+    }
+
+    /// `file_allowed` for files found with `SLACK_USER_TOKEN`: shares in DMs don't count,
+    /// and private shares only when the bot is in that channel.
+    async fn user_search_file_allowed(
+        &self,
+        file: &serde_json::Value,
+        known: &mut HashMap<String, bool>,
+    ) -> bool {
+        let ids = |k: &str| -> Vec<String> {
+            file.get(k)
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|c| c.as_str())
+                .map(str::to_string)
+                .collect()
+        };
+        let mut readable = ids("channels");
+        for group in ids("groups") {
+            if self.readable_by_bot(&group, known).await {
+                readable.push(group);
+            }
+        }
+        // Deny-list checks still see every share.
+        let team_id = self.team_id.as_deref();
+        let denied = ["channels", "groups", "ims"]
+            .iter()
+            .flat_map(|k| ids(k))
+            .any(|c| self.channel_denied_in(team_id, &c));
+        !denied
+            && !readable.is_empty()
+            && (self.allowed_channels.is_open() || readable.iter().any(|c| self.channel_allowed(c)))
+    }
+
+    /// Mirror server-side behavior: the bot's own DMs are always allowed (unless denied),
+    /// whatever the allow-list says, so `open_dm` checks its target against
+    /// `GRAIL_SLACK_DM_USERS` before opening one;
+    /// entries may be plain channel ids or workspace-qualified (`T…:C…`, `T…:*`). Results
+    /// found with the user token go through `user_search_match_allowed` first, since its
+    /// DMs aren't the bot's.
+    fn channel_allowed_in(&self, team_id: Option<&str>, channel: &str) -> bool {
+        if self.channel_denied_in(team_id, channel) {
+            return false;
+        }
+        if channel.starts_with('D') {
+            return true;
+        }
+        let allowed = self.allowed_channels.current();
+        if allowed.is_empty() || allowed.contains(channel) {
+            return true;
+        }
+        team_id.filter(|t| !t.is_empty()).is_some_and(|t| {
+            allowed.contains(&format!("{t}:{channel}")) || allowed.contains(&format!("{t}:*"))
+        })
+    }
+
+    async fn user_info(&self, user_id: &str) -> Result<serde_json::Value, McpError> {
+        if let Some(user) = self.users.get(user_id) {
+            return Ok(user);
+        }
+        let query = vec![("user", user_id.to_string())];
+        let SlackOkWrapper { inner, .. }: SlackOkWrapper<UserInfoResponse> =
+            self.slack_api_get(&slack_api("users.info"), &query).await?;
+        self.users.insert(user_id.to_string(), inner.user.clone());
+        Ok(inner.user)
+    }
+
+    /// The message at `ts` with its `reactions` (every reacting user, not just the first few).
+    async fn reacted_message(
+        &self,
+        channel: &str,
+        ts: &str,
+    ) -> Result<serde_json::Value, McpError> {
+        let query = vec![
+            ("channel", channel.to_string()),
+            ("timestamp", ts.to_string()),
+            ("full", "true".to_string()),
+        ];
+        let SlackOkWrapper { inner, .. }: SlackOkWrapper<ReactionsGetResponse> = self
+            .slack_api_get(&slack_api("reactions.get"), &query)
+            .await?;
+        Ok(inner.message)
+    }
+
+    async fn identity(&self) -> Result<Identity, McpError> {
+        self.identity
+            .get_or_try_init(|| async {
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<Identity> =
+                    self.slack_api_get(&slack_api("auth.test"), &[]).await?;
+                Ok(inner)
+            })
+            .await
+            .cloned()
+    }
+
+    async fn user_token_owner(&self, token: &str) -> Result<String, McpError> {
+        self.user_token_owner
+            .get_or_try_init(|| async {
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<TokenOwner> = self
+                    .with_transient_retries(|| {
+                        self.slack_api_send(
+                            self.http.get(slack_api("auth.test")),
+                            token.to_string(),
+                        )
+                    })
+                    .await?;
+                Ok(inner.user_id)
+            })
+            .await
+            .cloned()
+    }
+
+    /// Org-wide (Enterprise Grid) tokens need a workspace for many methods; say so instead of
+    /// passing on Slack's bare error code.
+    async fn explain_grid_error(&self, err: McpError) -> McpError {
+        if self.team_id.is_some() || !TEAM_REQUIRED_ERRORS.iter().any(|e| err.message.contains(e)) {
+            return err;
+        }
+        match self.identity().await {
+            Ok(identity) if identity.is_enterprise_install => {
+                let or_workspace = if self.workspaces.is_empty() {
+                    ""
+                } else {
+                    " or pass workspace"
+                };
+                McpError::invalid_params(
+                    format!(
+                        "{}: the Slack app is installed org-wide (Enterprise Grid {}), so this call needs a workspace; set GRAIL_SLACK_TEAM_ID{or_workspace}",
+                        err.message,
+                        identity.enterprise_id.as_deref().unwrap_or("org"),
+                    ),
+                    err.data,
+                )
+            }
+            _ => err,
+        }
+    }
+
+    /// Display names by user id; users that can't be looked up are left out.
+    async fn user_names(&self, mut ids: Vec<String>) -> HashMap<String, String> {
+        ids.sort();
+        ids.dedup();
+        let mut names = HashMap::new();
+        for id in ids {
+            match self.user_info(&id).await {
+                Ok(user) => {
+                    if let Some(name) = entities::display_name(&user) {
+                        names.insert(id, name);
+                    }
+                }
+                Err(err) => warn!(user = %id, error = %err.message, "failed to resolve user"),
+            }
+        }
+        names
+    }
+
+    /// Rewrite mentions and links in each message's `text` and add the author's
+    /// `user_name`. Users that can't be looked up keep their id.
+    async fn resolve_entities(&self, messages: &mut [serde_json::Value]) {
+        let mut ids: Vec<String> = Vec::new();
+        for msg in messages.iter() {
+            if let Some(user) = msg.get("user").and_then(|v| v.as_str()) {
+                ids.push(user.to_string());
+            }
+            if let Some(text) = msg.get("text").and_then(|v| v.as_str()) {
+                ids.extend(entities::mentioned_users(text));
+            }
+        }
+        let names = self.user_names(ids).await;
+        for msg in messages.iter_mut() {
+            let Some(obj) = msg.as_object_mut() else {
+                continue;
+            };
+            if let Some(text) = obj.get("text").and_then(|v| v.as_str()) {
+                let text = entities::rewrite(text, &names);
+                obj.insert("text".to_string(), json!(text));
+            }
+            if let Some(name) = obj
+                .get("user")
+                .and_then(|v| v.as_str())
+                .and_then(|id| names.get(id))
+            {
+                let name = name.clone();
+                obj.insert("user_name".to_string(), json!(name));
+            }
+        }
+    }
+
+    async fn enabled_usergroups(&self) -> Result<Vec<serde_json::Value>, McpError> {
+        let key = self.team_id.clone().unwrap_or_default();
+        if let Some(groups) = self.usergroups.get(&key) {
+            return Ok(groups);
+        }
+        let mut query = vec![("include_count", "true".to_string())];
+        if let Some(team_id) = &self.team_id {
+            query.push(("team_id", team_id.clone()));
+        }
+        let SlackOkWrapper { inner, .. }: SlackOkWrapper<UsergroupsResponse> = self
+            .slack_api_get(&slack_api("usergroups.list"), &query)
+            .await?;
+        self.usergroups.insert(key, inner.usergroups.clone());
+        Ok(inner.usergroups)
+    }
+
+    async fn custom_emoji(&self) -> Result<serde_json::Map<String, serde_json::Value>, McpError> {
+        let key = self.team_id.clone().unwrap_or_default();
+        if let Some(emoji) = self.emoji.get(&key) {
+            return Ok(emoji);
+        }
+        let mut query = Vec::new();
+        if let Some(team_id) = &self.team_id {
+            query.push(("team_id", team_id.clone()));
+        }
+        let SlackOkWrapper { inner, .. }: SlackOkWrapper<EmojiListResponse> =
+            self.slack_api_get(&slack_api("emoji.list"), &query).await?;
+        self.emoji.insert(key, inner.emoji.clone());
+        Ok(inner.emoji)
+    }
+
+    /// One `conversations.list` page, before allow-list filtering, and its next cursor.
+    async fn channel_page(
+        &self,
+        limit: i64,
+        cursor: Option<String>,
+    ) -> Result<(Vec<serde_json::Value>, Option<String>), McpError> {
+        let key = format!(
+            "{}|{limit}|{}",
+            self.team_id.as_deref().unwrap_or(""),
+            cursor.as_deref().unwrap_or("")
+        );
+        if let Some(page) = self.channel_pages.get(&key) {
+            return Ok(page);
+        }
+        let mut query = vec![
+            ("limit", limit.to_string()),
+            ("types", "public_channel,private_channel".to_string()),
+            ("exclude_archived", "true".to_string()),
+        ];
+        if let Some(team_id) = &self.team_id {
+            query.push(("team_id", team_id.clone()));
+        }
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor));
+        }
+        let SlackOkWrapper { inner, .. }: SlackOkWrapper<ListChannelsResponse> = self
+            .slack_api_get(&slack_api("conversations.list"), &query)
+            .await?;
+        let page = (
+            inner.channels,
+            next_cursor(inner.response_metadata.as_ref()),
+        );
+        self.channel_pages.insert(key, page.clone());
+        Ok(page)
+    }
+
+    /// Resolve the allow-list's `#name` patterns to channel ids, walking (cached)
+    /// `conversations.list` pages.
+    async fn resolve_channel_patterns(&self) -> Result<(), McpError> {
+        let patterns = self.allowed_channels.patterns();
+        let mut ids = HashSet::new();
+        let mut cursor = None;
+        for _ in 0..MAX_RESOLVE_PAGES {
+            if patterns.is_empty() {
+                break;
+            }
+            let (channels, next) = self.channel_page(1000, cursor).await?;
+            for channel in &channels {
+                let (Some(id), Some(name)) = (
+                    channel.get("id").and_then(|v| v.as_str()),
+                    channel.get("name").and_then(|v| v.as_str()),
+                ) else {
+                    continue;
+                };
+                if patterns.iter().any(|p| allowlist::name_matches(p, name)) {
+                    ids.insert(id.to_string());
+                }
+            }
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        self.allowed_channels.set_resolved(ids);
+        Ok(())
+    }
+
+    /// Re-resolve the `#name` patterns until the process exits, picking up new channels
+    /// (and patterns added to `GRAIL_SLACK_ALLOW_CHANNELS_FILE`).
+    async fn refresh_channel_patterns(self) {
+        loop {
+            tokio::time::sleep(CHANNEL_PATTERN_REFRESH).await;
+            if let Err(err) = self.resolve_channel_patterns().await {
+                warn!(error = %err.message, "failed to resolve channel name patterns; keeping the last result");
+            }
+        }
+    }
+
+    /// Find an allowed channel by name, walking (cached) `conversations.list` pages.
+    async fn find_channel(&self, name: &str) -> Result<Option<serde_json::Value>, McpError> {
+        let mut cursor = None;
+        for _ in 0..MAX_RESOLVE_PAGES {
+            let (channels, next) = self.channel_page(1000, cursor).await?;
+            let found = channels.into_iter().find(|c| {
+                ["name", "name_normalized"].iter().any(|k| {
+                    c.get(*k)
+                        .and_then(|v| v.as_str())
+                        .is_some_and(|n| n.eq_ignore_ascii_case(name))
+                })
+            });
+            if let Some(channel) = found {
+                let id = channel.get("id").and_then(|v| v.as_str()).unwrap_or("");
+                return Ok(self.channel_allowed(id).then_some(channel));
+            }
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        Ok(None)
+    }
+
+    async fn api_permit(&self) -> Result<tokio::sync::SemaphorePermit<'_>, McpError> {
+        self.api_permits
+            .acquire()
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))
+    }
+
+    async fn slack_api_get<T: for<'de> Deserialize<'de>>(
+        &self,
+        url: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, McpError> {
+        self.with_transient_retries(|| self.slack_bot_send(self.http.get(url).query(query)))
+            .await
+    }
+
+    /// Run an idempotent request until it succeeds, fails for a non-transient reason, or
+    /// has been tried [`MAX_TRANSIENT_TRIES`] times. Writes don't go through this, so a
+    /// message is never posted twice.
+    async fn with_transient_retries<T, F, Fut>(&self, mut send: F) -> Result<T, McpError>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T, McpError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match send().await {
+                Err(err) if is_transient(&err) && attempt < MAX_TRANSIENT_TRIES => {
+                    let delay = backoff_delay(attempt);
+                    warn!(attempt, delay_ms = delay.as_millis() as u64, error = %err.message, "transient slack api failure; retrying");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                other => return other,
+            }
+        }
+    }
+
+    async fn slack_api_post<T: for<'de> Deserialize<'de>>(
+        &self,
+        url: &str,
+        body: &serde_json::Value,
+    ) -> Result<T, McpError> {
+        self.slack_bot_send(self.http.post(url).json(body)).await
+    }
+
+    /// Send with the bot token; with token rotation, an expired token is refreshed and the
+    /// request sent once more.
+    async fn slack_bot_send<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, McpError> {
+        let token = self.bot_token.get(&self.http).await?;
+        let retry = request.try_clone();
+        match self.slack_api_send(request, token.clone()).await {
+            Err(err) if self.bot_token.rotates() && tokens::is_expired_error(&err) => {
+                let Some(retry) = retry else {
+                    return Err(err);
+                };
+                self.bot_token.invalidate(&token).await;
+                let token = self.bot_token.get(&self.http).await?;
+                self.slack_api_send(retry, token).await
+            }
+            other => other,
+        }
+    }
+
+    /// Search needs a user token on most plans; the bot token is only a fallback.
+    async fn slack_search<T: for<'de> Deserialize<'de>>(
+        &self,
+        url: &str,
+        query: &[(&str, String)],
+        tool: &str,
+    ) -> Result<T, McpError> {
+        let user_token = self.user_token.clone();
+        let has_user_token = user_token.is_some();
+        let token = match user_token {
+            Some(t) => t,
+            None => self.bot_token.get(&self.http).await?,
+        };
+        match self
+            .with_transient_retries(|| {
+                self.slack_api_send(self.http.get(url).query(query), token.clone())
+            })
+            .await
+        {
+            Err(err)
+                if !has_user_token
+                    && ["not_allowed_token_type", "missing_scope"]
+                        .iter()
+                        .any(|e| err.message.contains(e)) =>
+            {
+                Err(McpError::invalid_params(
+                    format!(
+                        "{tool} needs a Slack user token: set SLACK_USER_TOKEN (xoxp-..., scope search:read)"
+                    ),
+                    None,
+                ))
+            }
+            other => other,
+        }
+    }
+
+    /// Methods Slack only offers to user tokens (reminders).
+    fn require_user_token(&self, tool: &str, scope: &str) -> Result<String, McpError> {
+        self.user_token.clone().ok_or_else(|| {
+            McpError::invalid_params(
+                format!("{tool} needs a Slack user token: set SLACK_USER_TOKEN (xoxp-..., scope {scope})"),
+                None,
+            )
+        })
+    }
+
+    fn with_request_timeout(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.timeouts.request() {
+            Some(limit) => request.timeout(limit),
+            None => request,
+        }
+    }
+
+    async fn slack_api_send<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: reqwest::RequestBuilder,
+        token: String,
+    ) -> Result<T, McpError> {
+        let request = self
+            .with_request_timeout(request)
+            .header("Authorization", format!("Bearer {token}"));
+        // A write that timed out may still have gone through; retrying it could post
+        // twice, so only reads are reported as transient then.
+        let idempotent = request
+            .try_clone()
+            .and_then(|r| r.build().ok())
+            .is_some_and(|r| r.method() == reqwest::Method::GET);
+        let mut attempt = 0;
+        let resp = loop {
+            let Some(this) = request.try_clone() else {
+                return Err(McpError::internal_error(
+                    "slack request is not retryable",
+                    None,
+                ));
+            };
+            let permit = self.api_permit().await?;
+            let resp = this.send().await.map_err(|e| {
+                let transient =
+                    e.is_connect() || (idempotent && (e.is_timeout() || e.is_request()));
+                McpError::internal_error(e.to_string(), Some(json!({ "transient": transient })))
+            })?;
+            drop(permit);
+            self.telemetry.record_http_status(resp.status().as_u16());
+            if resp.status().is_server_error() {
+                return Err(McpError::internal_error(
+                    format!("slack api returned HTTP {}", resp.status()),
+                    Some(json!({ "transient": true })),
+                ));
+            }
+            if resp.status() != reqwest::StatusCode::TOO_MANY_REQUESTS {
+                break resp;
+            }
+            let retry_after = resp
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .unwrap_or(1);
+            if attempt >= self.max_retries || retry_after > MAX_RETRY_AFTER_SECS {
+                return Err(McpError::internal_error(
+                    format!("slack api rate limited (HTTP 429); retry after {retry_after}s"),
+                    Some(json!({ "retry_after_secs": retry_after, "attempts": attempt + 1 })),
+                ));
+            }
+            attempt += 1;
+            warn!(retry_after, attempt, "slack api rate limited; retrying");
+            tokio::time::sleep(Duration::from_secs(retry_after.max(1))).await;
+        };
+
+        let value = resp.json::<serde_json::Value>().await.map_err(|e| {
+            McpError::internal_error(e.to_string(), Some(json!({ "transient": e.is_body() })))
+        })?;
+
+        let ok = value.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
+        if !ok {
+            let err = value
+                .get("error")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown_error");
+            self.telemetry.record_api_error(err);
+            return Err(McpError::internal_error(
+                format!("slack api error: {err}"),
+                Some(value),
+            ));
+        }
+
+        serde_json::from_value(value).map_err(|e| McpError::internal_error(e.to_string(), None))
+    }
+}
+
+#[derive(Deserialize)]
+struct SlackOkWrapper<T> {
+    ok: bool,
+    #[allow(dead_code)]
+    error: Option<String>,
+    #[serde(flatten)]
+    inner: T,
+}
+
+#[derive(Deserialize)]
+struct HistoryResponse {
+    messages: Vec<serde_json::Value>,
+    has_more: Option<bool>,
+    response_metadata: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct RepliesResponse {
+    messages: Vec<serde_json::Value>,
+    has_more: Option<bool>,
+    response_metadata: Option<serde_json::Value>,
+}
+
+/// Errors Slack reports for its own hiccups rather than for the request.
+const TRANSIENT_API_ERRORS: [&str; 4] = [
+    "internal_error",
+    "fatal_error",
+    "service_unavailable",
+    "request_timeout",
+];
+
+fn is_transient(err: &McpError) -> bool {
+    let data = err.data.as_ref();
+    data.and_then(|d| d.get("transient"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+        || data
+            .and_then(|d| d.get("error"))
+            .and_then(|v| v.as_str())
+            .is_some_and(|code| TRANSIENT_API_ERRORS.contains(&code))
+}
+
+/// Exponential backoff with equal jitter: half the step is fixed, the other half random.
+fn backoff_delay(attempt: u32) -> Duration {
+    let step = TRANSIENT_BACKOFF_BASE * 2u32.pow(attempt.saturating_sub(1));
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let half = step / 2;
+    half + half.mul_f64(f64::from(nanos % 1_000) / 1_000.0)
+}
+
+/// Slack's `response_metadata.next_cursor`; an empty cursor means there are no more pages.
+fn next_cursor(response_metadata: Option<&serde_json::Value>) -> Option<String> {
+    response_metadata
+        .and_then(|m| m.get("next_cursor"))
+        .and_then(|c| c.as_str())
+        .filter(|c| !c.is_empty())
+        .map(str::to_string)
+}
+
+#[derive(Deserialize)]
+struct PermalinkResponse {
+    permalink: String,
+}
+
+#[derive(Deserialize)]
+struct UserInfoResponse {
+    user: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct ListUsersResponse {
+    members: Vec<serde_json::Value>,
+    response_metadata: Option<serde_json::Value>,
+}
+
+/// The fields of a Slack user worth showing; full profiles are large.
+fn user_summary(user: &serde_json::Value) -> serde_json::Value {
+    let profile = user.get("profile");
+    json!({
+        "id": user.get("id"),
+        "name": user.get("name"),
+        "real_name": user.get("real_name").or_else(|| profile.and_then(|p| p.get("real_name"))),
+        "display_name": profile.and_then(|p| p.get("display_name")),
+        "title": profile.and_then(|p| p.get("title")),
+        "tz": user.get("tz"),
+        "is_admin": user.get("is_admin"),
+        "is_bot": is_bot_user(user),
+        "deleted": user.get("deleted"),
+    })
+}
+
+/// Slackbot is not flagged `is_bot`.
+fn is_bot_user(user: &serde_json::Value) -> bool {
+    user.get("is_bot")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+        || user.get("id").and_then(|v| v.as_str()) == Some("USLACKBOT")
+}
+
+#[derive(Deserialize)]
+struct ListChannelsResponse {
+    channels: Vec<serde_json::Value>,
+    response_metadata: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct PostMessageResponse {
+    channel: String,
+    ts: String,
+}
+
+#[derive(Deserialize)]
+struct ReactionsGetResponse {
+    message: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct EmojiListResponse {
+    emoji: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct FileInfoResponse {
+    file: serde_json::Value,
+}
+
+/// Metadata worth showing the agent; `files.info` also returns thumbnails, share details
+/// and private URLs.
+fn file_summary(file: &serde_json::Value) -> serde_json::Value {
+    let mut out = serde_json::Map::new();
+    for key in [
+        "id",
+        "name",
+        "title",
+        "mimetype",
+        "filetype",
+        "pretty_type",
+        "mode",
+        "size",
+        "user",
+        "created",
+        "channels",
+        "groups",
+        "ims",
+        "permalink",
+        "preview",
+    ] {
+        if let Some(v) = file.get(key) {
+            out.insert(key.to_string(), v.clone());
+        }
+    }
+    serde_json::Value::Object(out)
+}
+
+/// Text files and snippets; anything else is refused by `download_file_text`.
+fn is_text_file(file: &serde_json::Value) -> bool {
+    const TEXT_FILETYPES: [&str; 24] = [
+        "text",
+        "markdown",
+        "post",
+        "csv",
+        "tsv",
+        "json",
+        "yaml",
+        "xml",
+        "html",
+        "css",
+        "javascript",
+        "typescript",
+        "python",
+        "rust",
+        "go",
+        "java",
+        "shell",
+        "sql",
+        "diff",
+        "log",
+        "toml",
+        "ini",
+        "c",
+        "cpp",
+    ];
+    let field = |k: &str| file.get(k).and_then(|v| v.as_str()).unwrap_or("");
+    let mime = field("mimetype");
+    field("mode") == "snippet"
+        || mime.starts_with("text/")
+        || matches!(
+            mime,
+            "application/json" | "application/xml" | "application/x-yaml" | "application/yaml"
+        )
+        || TEXT_FILETYPES.contains(&field("filetype"))
+}
+
+#[derive(Deserialize)]
+struct ArgsGetChannelHistory {
+    channel: String,
+    #[serde(default)]
+    before_ts: Option<String>,
+    #[serde(default)]
+    oldest_ts: Option<String>,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    limit: Option<i64>,
+    #[serde(default)]
+    resolve_entities: bool,
+    #[serde(default)]
+    flatten_blocks: bool,
+    #[serde(default)]
+    max_chars: Option<usize>,
+    #[serde(default)]
+    fields: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ArgsGetThread {
+    channel: String,
+    thread_ts: String,
+    #[serde(default)]
+    before_ts: Option<String>,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    limit: Option<i64>,
+    #[serde(default)]
+    resolve_entities: bool,
+    #[serde(default)]
+    flatten_blocks: bool,
+    #[serde(default)]
+    max_chars: Option<usize>,
+    #[serde(default)]
+    fields: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ArgsGetThreadFull {
+    channel: String,
+    thread_ts: String,
+    #[serde(default)]
+    max_messages: Option<usize>,
+    #[serde(default)]
+    resolve_entities: bool,
+    #[serde(default)]
+    flatten_blocks: bool,
+    #[serde(default)]
+    max_chars: Option<usize>,
+    #[serde(default)]
+    fields: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ArgsGetPermalink {
+    channel: String,
+    message_ts: String,
+}
+
+#[derive(Deserialize)]
+struct ArgsResolvePermalink {
+    url: String,
+}
+
+struct Permalink {
+    workspace: Option<String>,
+    channel: String,
+    message_ts: String,
+    /// The parent's ts when the link points at a reply.
+    thread_ts: Option<String>,
+}
+
+/// `https://acme.slack.com/archives/C123/p1700000000123456?thread_ts=1700000000.000100`.
+fn parse_permalink(input: &str) -> Option<Permalink> {
+    let url =
+        reqwest::Url::parse(input.trim().trim_start_matches('<').trim_end_matches('>')).ok()?;
+    let host = url.host_str()?;
+    if !(host == "slack.com" || host.ends_with(".slack.com")) {
+        return None;
+    }
+    let mut segments = url
+        .path_segments()?
+        .skip_while(|s| *s != "archives")
+        .skip(1);
+    let channel = segments.next().filter(|c| !c.is_empty())?.to_string();
+    let digits = segments.next()?.strip_prefix('p')?;
+    if digits.len() <= 6 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (secs, micros) = digits.split_at(digits.len() - 6);
+    let message_ts = format!("{secs}.{micros}");
+    let thread_ts = url
+        .query_pairs()
+        .find(|(k, _)| k == "thread_ts")
+        .map(|(_, v)| v.into_owned())
+        .filter(|t| !t.is_empty() && *t != message_ts);
+    let workspace = host
+        .strip_suffix(".slack.com")
+        .filter(|w| !w.is_empty() && *w != "app")
+        .map(str::to_string);
+    Some(Permalink {
+        workspace,
+        channel,
+        message_ts,
+        thread_ts,
+    })
+}
+
+#[derive(Deserialize)]
+struct ChannelInfoResponse {
+    channel: serde_json::Value,
+}
+
+/// The parts of `auth.test` that matter on Enterprise Grid.
+#[derive(Clone, Deserialize)]
+struct Identity {
+    #[serde(default)]
+    enterprise_id: Option<String>,
+    #[serde(default)]
+    is_enterprise_install: bool,
+}
+
+#[derive(Deserialize)]
+struct TokenOwner {
+    user_id: String,
+}
+
+#[derive(Deserialize)]
+struct ArgsChannel {
+    channel: String,
+}
+
+#[derive(Deserialize)]
+struct MembersResponse {
+    members: Vec<String>,
+    response_metadata: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ArgsListChannelMembers {
+    channel: String,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct PinsResponse {
+    #[serde(default)]
+    items: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct BookmarksResponse {
+    #[serde(default)]
+    bookmarks: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ArgsGetUser {
+    user_id: String,
+}
+
+#[derive(Deserialize)]
+struct UsergroupsResponse {
+    usergroups: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct UsergroupUsersResponse {
+    users: Vec<String>,
+}
+
+fn usergroup_summary(group: &serde_json::Value) -> serde_json::Value {
+    json!({
+        "id": group.get("id"),
+        "handle": group.get("handle"),
+        "name": group.get("name"),
+        "description": group.get("description"),
+        "user_count": group.get("user_count"),
+    })
+}
+
+/// `S123`, `@oncall`, `oncall` or `<!subteam^S123|@oncall>` → (id, handle); one of them.
+fn parse_usergroup_ref(input: &str) -> (Option<String>, Option<String>) {
+    let input = input.trim();
+    if let Some(inner) = input
+        .strip_prefix("<!subteam^")
+        .and_then(|s| s.strip_suffix('>'))
+    {
+        let (id, handle) = inner.split_once('|').unwrap_or((inner, ""));
+        let handle = handle.trim_start_matches('@');
+        return (
+            Some(id.to_string()),
+            (!handle.is_empty()).then(|| handle.to_string()),
+        );
+    }
+    let is_id = input.starts_with('S')
+        && input.len() > 1
+        && input
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+    if is_id {
+        (Some(input.to_string()), None)
+    } else {
+        (None, Some(input.trim_start_matches('@').to_string()))
+    }
+}
+
+#[derive(Deserialize)]
+struct ArgsGetUsergroup {
+    usergroup: String,
+    #[serde(default)]
+    include_profiles: bool,
+}
+
+#[derive(Deserialize)]
+struct ArgsGetUsers {
+    user_ids: Vec<String>,
+    #[serde(default)]
+    full_profiles: bool,
+}
+
+#[derive(Deserialize)]
+struct ArgsListUsers {
+    #[serde(default)]
+    limit: Option<i64>,
+    #[serde(default)]
+    cursor: Option<String>,
+    #[serde(default)]
+    exclude_bots: Option<bool>,
+    #[serde(default)]
+    exclude_deactivated: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct ArgsListChannels {
+    #[serde(default)]
+    limit: Option<i64>,
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+/// `#general`, `general` or a `<#C123|general>` mention → (`general`, mention id).
+fn parse_channel_ref(input: &str) -> (String, Option<String>) {
+    let input = input.trim();
+    if let Some(inner) = input.strip_prefix("<#").and_then(|s| s.strip_suffix('>')) {
+        let (id, name) = inner.split_once('|').unwrap_or((inner, ""));
+        return (name.to_string(), Some(id.to_string()));
+    }
+    (input.trim_start_matches('#').trim().to_string(), None)
+}
 
 #[derive(Deserialize)]
-struct ArgsGetPermalink {
+struct ArgsResolveChannel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct ArgsSearchMessages {
+    query: String,
+    #[serde(default)]
+    count: Option<i64>,
+    #[serde(default)]
+    max_chars: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct SearchFilesResponse {
+    files: SearchFilesInner,
+}
+
+#[derive(Deserialize)]
+struct SearchFilesInner {
+    matches: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ArgsAddReaction {
     channel: String,
     message_ts: String,
+    name: String,
 }
 
 #[derive(Deserialize)]
-struct ArgsGetUser {
+struct ScheduleMessageResponse {
+    channel: String,
+    scheduled_message_id: String,
+    post_at: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct ScheduledMessagesResponse {
+    scheduled_messages: Vec<serde_json::Value>,
+    response_metadata: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ArgsScheduleMessage {
+    channel: String,
+    post_at: i64,
+    #[serde(default)]
+    thread_ts: Option<String>,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct ArgsListScheduledMessages {
+    #[serde(default)]
+    channel: Option<String>,
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ReminderResponse {
+    reminder: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct RemindersResponse {
+    reminders: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ArgsAddReminder {
     user_id: String,
+    text: String,
+    time: String,
 }
 
 #[derive(Deserialize)]
-struct ArgsListChannels {
+struct OpenDmResponse {
+    channel: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct ArgsListReactions {
+    channel: String,
+    message_ts: String,
+}
+
+#[derive(Deserialize)]
+struct ArgsGetMessageReactions {
+    channel: String,
+    message_ts: String,
     #[serde(default)]
-    limit: Option<i64>,
+    emoji: Vec<String>,
+    #[serde(default)]
+    resolve_users: bool,
 }
 
 #[derive(Deserialize)]
-struct ArgsSearchMessages {
-    query: String,
+struct ArgsListEmoji {
     #[serde(default)]
-    count: Option<i64>,
+    query: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ArgsGetCanvas {
+    #[serde(default)]
+    channel: Option<String>,
+    #[serde(default)]
+    canvas_id: Option<String>,
+    #[serde(default)]
+    max_bytes: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ArgsFile {
+    file_id: String,
+    #[serde(default)]
+    max_bytes: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ArgsPostMessage {
+    channel: String,
+    #[serde(default)]
+    thread_ts: Option<String>,
+    text: String,
 }
 
 impl ServerHandler for SlackMcpServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             capabilities: ServerCapabilities::builder()
+                .enable_prompts()
+                .enable_resources()
+                .enable_resources_subscribe()
                 .enable_tools()
                 .enable_tool_list_changed()
                 .build(),
@@ -325,143 +2297,825 @@ impl ServerHandler for SlackMcpServer {
         }
     }
 
+    async fn list_resources(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        _context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let cursor = request.and_then(|r| r.cursor).filter(|c| !c.is_empty());
+        let (channels, next_cursor) = self.channel_page(200, cursor).await?;
+        let resources = channels
+            .iter()
+            .filter(|c| {
+                c.get("id")
+                    .and_then(|v| v.as_str())
+                    .is_some_and(|id| self.channel_allowed(id))
+            })
+            .filter_map(resources::channel_resource)
+            .collect();
+        Ok(ListResourcesResult {
+            meta: None,
+            next_cursor,
+            resources,
+        })
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        Ok(ListPromptsResult::with_all_items(prompts::list(|tool| {
+            self.tools.iter().any(|t| t.name == tool)
+        })))
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        prompts::get(&request.name, request.arguments.as_ref(), |tool| {
+            self.tools.iter().any(|t| t.name == tool)
+        })
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        Ok(ListResourceTemplatesResult::with_all_items(vec![
+            resources::channel_template(),
+        ]))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let channel = self.resource_channel(&request.uri)?;
+        let query = vec![
+            ("channel", channel.to_string()),
+            ("limit", resources::READ_LIMIT.to_string()),
+        ];
+        let SlackOkWrapper { inner, .. }: SlackOkWrapper<HistoryResponse> = self
+            .slack_api_get(&slack_api("conversations.history"), &query)
+            .await?;
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri.clone(),
+                mime_type: Some("application/json".to_string()),
+                text: resources::channel_contents(channel, inner.messages),
+                meta: None,
+            }],
+        })
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
+    ) -> Result<(), McpError> {
+        let channel = self.resource_channel(&request.uri)?.to_string();
+        let mut subscriptions = self.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        if let std::collections::hash_map::Entry::Vacant(entry) = subscriptions.entry(request.uri) {
+            info!(uri = %entry.key(), "subscribed to slack channel");
+            let watch = resources::watch(self.clone(), context.peer.clone(), channel);
+            entry.insert(tokio::spawn(watch));
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
+    ) -> Result<(), McpError> {
+        let removed = self
+            .subscriptions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&request.uri);
+        if let Some(handle) = removed {
+            handle.abort();
+            info!(uri = %request.uri, "unsubscribed from slack channel");
+        }
+        Ok(())
+    }
+
     async fn call_tool(
         &self,
         request: CallToolRequestParam,
         _context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
     ) -> Result<CallToolResult, McpError> {
+        let started = std::time::Instant::now();
+        let tool = request.name.to_string();
+        let arguments = self.audit.as_ref().and(request.arguments.clone());
+        let mut result = match self.timeouts.for_tool(&tool) {
+            Some(limit) => tokio::time::timeout(limit, self.run_tool(request))
+                .await
+                .unwrap_or_else(|_| {
+                    // A write cut off mid-call may have happened anyway.
+                    Err(McpError::internal_error(
+                        format!("{tool} timed out after {}s", limit.as_secs()),
+                        Some(json!({ "transient": !WRITE_TOOLS.contains(&tool.as_str()) })),
+                    ))
+                }),
+            None => self.run_tool(request).await,
+        };
+        if let Ok(result) = &mut result {
+            render::fill(self.text_content, result);
+        }
+        let ok = matches!(&result, Ok(r) if r.is_error != Some(true));
+        self.telemetry.record_call(&tool, started.elapsed(), ok);
+        if let Some(audit) = &self.audit {
+            audit.record(&tool, arguments.as_ref(), &result, started.elapsed());
+        }
+        result
+    }
+}
+
+impl SlackMcpServer {
+    async fn run_tool(
+        &self,
+        mut request: CallToolRequestParam,
+    ) -> Result<CallToolResult, McpError> {
+        if !self.tools.iter().any(|t| t.name == request.name) {
+            let message = if !self.allow_writes && WRITE_TOOLS.contains(&request.name.as_ref()) {
+                format!(
+                    "{} is disabled; set GRAIL_SLACK_ALLOW_WRITES=1 to enable it",
+                    request.name
+                )
+            } else {
+                format!("unknown or disabled tool: {}", request.name)
+            };
+            return Err(McpError::invalid_params(message, None));
+        }
+        let workspace = request
+            .arguments
+            .as_mut()
+            .and_then(|a| a.remove("workspace"))
+            .and_then(|w| w.as_str().map(|w| w.trim().to_string()))
+            .filter(|w| !w.is_empty());
+        match workspace {
+            Some(workspace) => {
+                self.for_workspace(&workspace)?
+                    .dispatch_joining(request)
+                    .await
+            }
+            None => match self.dispatch_joining(request).await {
+                Err(err) => Err(self.explain_grid_error(err).await),
+                ok => ok,
+            },
+        }
+    }
+
+    /// [`Self::dispatch`], explaining `not_in_channel` instead of passing it on, and with
+    /// `GRAIL_SLACK_AUTO_JOIN` joining the (public) channel and trying a read once more.
+    async fn dispatch_joining(
+        &self,
+        request: CallToolRequestParam,
+    ) -> Result<CallToolResult, McpError> {
+        let err = match self.dispatch(request.clone()).await {
+            Err(err) if err.message.contains("not_in_channel") => err,
+            other => return other,
+        };
+        let Some(channel) = request
+            .arguments
+            .as_ref()
+            .and_then(|a| a.get("channel"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+        else {
+            return Err(err);
+        };
+        let reason = if !self.auto_join {
+            "invite it with /invite, or set GRAIL_SLACK_AUTO_JOIN=1 to let it join public channels on its own".to_string()
+        } else if WRITE_TOOLS.contains(&request.name.as_ref()) {
+            "invite it with /invite; it only joins channels by itself to read them".to_string()
+        } else {
+            match self.join_channel(&channel).await {
+                Ok(()) => return self.dispatch(request).await,
+                Err(reason) => reason,
+            }
+        };
+        Err(McpError::invalid_params(
+            format!("the bot is not a member of channel {channel}; {reason}"),
+            err.data,
+        ))
+    }
+
+    /// Join a public channel; why not otherwise.
+    async fn join_channel(&self, channel: &str) -> Result<(), String> {
+        let query = vec![("channel", channel.to_string())];
+        let SlackOkWrapper { inner, .. }: SlackOkWrapper<ChannelInfoResponse> = self
+            .slack_api_get(&slack_api("conversations.info"), &query)
+            .await
+            .map_err(|err| format!("looking the channel up failed ({})", err.message))?;
+        let flag = |k: &str| {
+            inner
+                .channel
+                .get(k)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+        };
+        if flag("is_private") || flag("is_im") || flag("is_mpim") {
+            return Err("it is private, so a member has to invite it with /invite".to_string());
+        }
+        if flag("is_archived") {
+            return Err("the channel is archived".to_string());
+        }
+        let body = json!({ "channel": channel });
+        let _: SlackOkWrapper<serde_json::Value> = self
+            .slack_api_post(&slack_api("conversations.join"), &body)
+            .await
+            .map_err(|err| {
+                let hint = if err.message.contains("missing_scope") {
+                    "; the bot needs the channels:join scope"
+                } else {
+                    ""
+                };
+                format!("joining failed ({}){hint}", err.message)
+            })?;
+        info!(channel = %channel, "joined slack channel to read it");
+        Ok(())
+    }
+
+    async fn dispatch(&self, request: CallToolRequestParam) -> Result<CallToolResult, McpError> {
         match request.name.as_ref() {
             "get_channel_history" => {
                 let args = parse_args::<ArgsGetChannelHistory>(&request, "get_channel_history")?;
-                if !self.channel_allowed(args.channel.as_str()) {
-                    return Err(McpError::invalid_params(
-                        "channel not allowed by GRAIL_SLACK_ALLOW_CHANNELS",
-                        Some(json!({ "channel": args.channel })),
-                    ));
-                }
+                self.ensure_channel_allowed(&args.channel)?;
                 let limit = args.limit.unwrap_or(20).clamp(1, 200);
                 let mut query = vec![
                     ("channel", args.channel.clone()),
                     ("limit", limit.to_string()),
                 ];
+                let before_ts = args.before_ts.filter(|t| !t.is_empty());
+                let oldest_ts = args.oldest_ts.filter(|t| !t.is_empty());
+                if let Some(ts) = &before_ts {
+                    query.push(("latest", ts.clone()));
+                }
+                if let Some(ts) = &oldest_ts {
+                    query.push(("oldest", ts.clone()));
+                }
+                // `inclusive` applies to both ends: with a window, keep the message at
+                // `oldest_ts` and drop the one at `before_ts` below.
+                let inclusive = oldest_ts.is_some();
+                query.push(("inclusive", inclusive.to_string()));
+                if let Some(cursor) = args.cursor.filter(|c| !c.is_empty()) {
+                    query.push(("cursor", cursor));
+                }
+                let SlackOkWrapper { mut inner, .. }: SlackOkWrapper<HistoryResponse> = self
+                    .slack_api_get(&slack_api("conversations.history"), &query)
+                    .await?;
+                if let (true, Some(before)) = (inclusive, &before_ts) {
+                    inner
+                        .messages
+                        .retain(|m| m.get("ts").and_then(|v| v.as_str()) != Some(before));
+                }
+                if args.flatten_blocks {
+                    blocks::flatten_messages(&mut inner.messages);
+                }
+                if args.resolve_entities {
+                    self.resolve_entities(&mut inner.messages).await;
+                }
+                fields::project(&mut inner.messages, &args.fields);
+                let truncated = budget::fit(&mut inner.messages, args.max_chars);
+
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": args.channel,
+                        "messages": inner.messages,
+                        "has_more": inner.has_more.unwrap_or(false),
+                        "next_cursor": next_cursor(inner.response_metadata.as_ref()),
+                        "truncated": truncated,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "get_thread" => {
+                let args = parse_args::<ArgsGetThread>(&request, "get_thread")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let limit = args.limit.unwrap_or(50).clamp(1, 200);
+                let mut query = vec![
+                    ("channel", args.channel.clone()),
+                    ("ts", args.thread_ts.clone()),
+                    ("limit", limit.to_string()),
+                    ("inclusive", "true".to_string()),
+                ];
                 if let Some(ts) = args.before_ts {
                     query.push(("latest", ts));
-                    query.push(("inclusive", "false".to_string()));
                 }
-                let SlackOkWrapper { inner, .. }: SlackOkWrapper<HistoryResponse> = self
-                    .slack_api_get("https://slack.com/api/conversations.history", &query)
+                if let Some(cursor) = args.cursor.filter(|c| !c.is_empty()) {
+                    query.push(("cursor", cursor));
+                }
+                let SlackOkWrapper { mut inner, .. }: SlackOkWrapper<RepliesResponse> = self
+                    .slack_api_get(&slack_api("conversations.replies"), &query)
+                    .await?;
+                if args.flatten_blocks {
+                    blocks::flatten_messages(&mut inner.messages);
+                }
+                if args.resolve_entities {
+                    self.resolve_entities(&mut inner.messages).await;
+                }
+                fields::project(&mut inner.messages, &args.fields);
+                let truncated = budget::fit(&mut inner.messages, args.max_chars);
+
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": args.channel,
+                        "thread_ts": args.thread_ts,
+                        "messages": inner.messages,
+                        "has_more": inner.has_more.unwrap_or(false),
+                        "next_cursor": next_cursor(inner.response_metadata.as_ref()),
+                        "truncated": truncated,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "get_thread_full" => {
+                let args = parse_args::<ArgsGetThreadFull>(&request, "get_thread_full")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let max = args
+                    .max_messages
+                    .unwrap_or(DEFAULT_THREAD_MESSAGES)
+                    .clamp(1, MAX_THREAD_MESSAGES);
+                let mut messages = Vec::new();
+                let mut cursor: Option<String> = None;
+                let truncated = loop {
+                    let mut query = vec![
+                        ("channel", args.channel.clone()),
+                        ("ts", args.thread_ts.clone()),
+                        ("limit", THREAD_PAGE_SIZE.to_string()),
+                    ];
+                    if let Some(cursor) = cursor.take() {
+                        query.push(("cursor", cursor));
+                    }
+                    let SlackOkWrapper { inner, .. }: SlackOkWrapper<RepliesResponse> = self
+                        .slack_api_get(&slack_api("conversations.replies"), &query)
+                        .await?;
+                    messages.extend(inner.messages);
+                    cursor = next_cursor(inner.response_metadata.as_ref());
+                    if messages.len() >= max {
+                        break messages.len() > max || cursor.is_some();
+                    }
+                    if cursor.is_none() {
+                        break false;
+                    }
+                };
+                messages.truncate(max);
+                if args.flatten_blocks {
+                    blocks::flatten_messages(&mut messages);
+                }
+                if args.resolve_entities {
+                    self.resolve_entities(&mut messages).await;
+                }
+                fields::project(&mut messages, &args.fields);
+                let truncated = budget::fit(&mut messages, args.max_chars) || truncated;
+
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": args.channel,
+                        "thread_ts": args.thread_ts,
+                        "messages": messages,
+                        "truncated": truncated,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "get_permalink" => {
+                let args = parse_args::<ArgsGetPermalink>(&request, "get_permalink")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let key = format!(
+                    "{}|{}|{}",
+                    self.team_id.as_deref().unwrap_or(""),
+                    args.channel,
+                    args.message_ts
+                );
+                let permalink = match self.permalinks.get(&key) {
+                    Some(permalink) => permalink,
+                    None => {
+                        let query = vec![
+                            ("channel", args.channel.clone()),
+                            ("message_ts", args.message_ts.clone()),
+                        ];
+                        let SlackOkWrapper { inner, .. }: SlackOkWrapper<PermalinkResponse> = self
+                            .slack_api_get(&slack_api("chat.getPermalink"), &query)
+                            .await?;
+                        self.permalinks.insert(key, inner.permalink.clone());
+                        inner.permalink
+                    }
+                };
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": args.channel,
+                        "message_ts": args.message_ts,
+                        "permalink": permalink,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "resolve_permalink" => {
+                let args = parse_args::<ArgsResolvePermalink>(&request, "resolve_permalink")?;
+                let link = parse_permalink(&args.url).ok_or_else(|| {
+                    McpError::invalid_params(
+                        "not a Slack message link (expected https://<workspace>.slack.com/archives/<channel>/p<ts>)",
+                        Some(json!({ "url": args.url })),
+                    )
+                })?;
+                self.ensure_channel_allowed(&link.channel)?;
+                let is_reply = link.thread_ts.is_some();
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "workspace": link.workspace,
+                        "channel": link.channel,
+                        "message_ts": link.message_ts,
+                        // For get_thread: the parent of a reply, else the message itself.
+                        "thread_ts": link.thread_ts.unwrap_or_else(|| link.message_ts.clone()),
+                        "is_reply": is_reply,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "get_channel_info" => {
+                let args = parse_args::<ArgsChannel>(&request, "get_channel_info")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let query = vec![
+                    ("channel", args.channel.clone()),
+                    ("include_num_members", "true".to_string()),
+                ];
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<ChannelInfoResponse> = self
+                    .slack_api_get(&slack_api("conversations.info"), &query)
+                    .await?;
+                let c = &inner.channel;
+                let text = |k: &str| c.get(k).and_then(|v| v.get("value")).cloned();
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": args.channel,
+                        "name": c.get("name"),
+                        "topic": text("topic"),
+                        "purpose": text("purpose"),
+                        "num_members": c.get("num_members"),
+                        "is_private": c.get("is_private"),
+                        "is_archived": c.get("is_archived"),
+                        "is_im": c.get("is_im"),
+                        "is_shared": c.get("is_ext_shared").or_else(|| c.get("is_shared")),
+                        "created": c.get("created"),
+                        "creator": c.get("creator"),
+                        // Enterprise Grid: the owning workspace, and whether it's shared org-wide.
+                        "team_id": c.get("context_team_id"),
+                        "is_org_shared": c.get("is_org_shared"),
+                        "shared_team_ids": c.get("shared_team_ids"),
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "list_channel_members" => {
+                let args = parse_args::<ArgsListChannelMembers>(&request, "list_channel_members")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let limit = args.limit.unwrap_or(200).clamp(1, 1000);
+                let mut query = vec![
+                    ("channel", args.channel.clone()),
+                    ("limit", limit.to_string()),
+                ];
+                if let Some(cursor) = args.cursor.filter(|c| !c.is_empty()) {
+                    query.push(("cursor", cursor));
+                }
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<MembersResponse> = self
+                    .slack_api_get(&slack_api("conversations.members"), &query)
+                    .await?;
+                let cursor = next_cursor(inner.response_metadata.as_ref());
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": args.channel,
+                        "members": inner.members,
+                        "has_more": cursor.is_some(),
+                        "next_cursor": cursor,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "list_pins" => {
+                let args = parse_args::<ArgsChannel>(&request, "list_pins")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let query = vec![("channel", args.channel.clone())];
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<PinsResponse> =
+                    self.slack_api_get(&slack_api("pins.list"), &query).await?;
+                let pins: Vec<serde_json::Value> = inner
+                    .items
+                    .iter()
+                    .map(|item| {
+                        json!({
+                            "type": item.get("type"),
+                            "pinned_at": item.get("created"),
+                            "pinned_by": item.get("created_by"),
+                            "message": item.get("message"),
+                            "file": item.get("file").map(file_summary),
+                        })
+                    })
+                    .collect();
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": args.channel,
+                        "pins": pins,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "list_bookmarks" => {
+                let args = parse_args::<ArgsChannel>(&request, "list_bookmarks")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let query = vec![("channel_id", args.channel.clone())];
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<BookmarksResponse> = self
+                    .slack_api_get(&slack_api("bookmarks.list"), &query)
                     .await?;
-
+                let bookmarks: Vec<serde_json::Value> = inner
+                    .bookmarks
+                    .iter()
+                    .map(|b| {
+                        json!({
+                            "id": b.get("id"),
+                            "title": b.get("title"),
+                            "link": b.get("link"),
+                            "type": b.get("type"),
+                            "emoji": b.get("emoji"),
+                            "created": b.get("date_created"),
+                        })
+                    })
+                    .collect();
                 Ok(CallToolResult {
                     content: Vec::new(),
                     structured_content: Some(json!({
                         "channel": args.channel,
-                        "messages": inner.messages,
+                        "bookmarks": bookmarks,
                     })),
                     is_error: Some(false),
                     meta: None,
                 })
             }
-            "get_thread" => {
-                let args = parse_args::<ArgsGetThread>(&request, "get_thread")?;
-                if !self.channel_allowed(args.channel.as_str()) {
+            "get_user" => {
+                let args = parse_args::<ArgsGetUser>(&request, "get_user")?;
+                let user = self.user_info(&args.user_id).await?;
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "user_id": args.user_id,
+                        "user": user,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "get_users" => {
+                let args = parse_args::<ArgsGetUsers>(&request, "get_users")?;
+                let mut ids: Vec<String> = Vec::new();
+                for id in args.user_ids.iter().map(|id| id.trim()) {
+                    if !id.is_empty() && !ids.iter().any(|seen| seen == id) {
+                        ids.push(id.to_string());
+                    }
+                }
+                if ids.len() > MAX_BATCH_USERS {
                     return Err(McpError::invalid_params(
-                        "channel not allowed by GRAIL_SLACK_ALLOW_CHANNELS",
-                        Some(json!({ "channel": args.channel })),
+                        format!("get_users takes at most {MAX_BATCH_USERS} user ids"),
+                        None,
                     ));
                 }
-                let limit = args.limit.unwrap_or(50).clamp(1, 200);
-                let mut query = vec![
-                    ("channel", args.channel.clone()),
-                    ("ts", args.thread_ts.clone()),
-                    ("limit", limit.to_string()),
-                    ("inclusive", "true".to_string()),
-                ];
-                if let Some(ts) = args.before_ts {
-                    query.push(("latest", ts));
+                let permits = Arc::new(tokio::sync::Semaphore::new(USER_LOOKUP_CONCURRENCY));
+                let mut lookups = task::JoinSet::new();
+                for (i, id) in ids.iter().enumerate() {
+                    let server = self.clone();
+                    let permits = permits.clone();
+                    let id = id.clone();
+                    lookups.spawn(async move {
+                        let _permit = permits.acquire_owned().await;
+                        (i, server.user_info(&id).await)
+                    });
+                }
+                let mut results: Vec<Option<Result<serde_json::Value, McpError>>> =
+                    ids.iter().map(|_| None).collect();
+                while let Some(joined) = lookups.join_next().await {
+                    if let Ok((i, result)) = joined {
+                        results[i] = Some(result);
+                    }
+                }
+                let mut users = Vec::new();
+                let mut errors = Vec::new();
+                for (id, result) in ids.iter().zip(results) {
+                    match result {
+                        Some(Ok(user)) if args.full_profiles => users.push(user),
+                        Some(Ok(user)) => users.push(user_summary(&user)),
+                        Some(Err(err)) => {
+                            errors.push(json!({ "user_id": id, "error": err.message }))
+                        }
+                        None => errors.push(json!({ "user_id": id, "error": "lookup failed" })),
+                    }
                 }
-                let SlackOkWrapper { inner, .. }: SlackOkWrapper<RepliesResponse> = self
-                    .slack_api_get("https://slack.com/api/conversations.replies", &query)
-                    .await?;
-
                 Ok(CallToolResult {
                     content: Vec::new(),
                     structured_content: Some(json!({
-                        "channel": args.channel,
-                        "thread_ts": args.thread_ts,
-                        "messages": inner.messages,
+                        "users": users,
+                        "errors": errors,
                     })),
                     is_error: Some(false),
                     meta: None,
                 })
             }
-            "get_permalink" => {
-                let args = parse_args::<ArgsGetPermalink>(&request, "get_permalink")?;
-                if !self.channel_allowed(args.channel.as_str()) {
+            "list_usergroups" => {
+                let groups = self.enabled_usergroups().await?;
+                let groups: Vec<serde_json::Value> = groups.iter().map(usergroup_summary).collect();
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({ "usergroups": groups })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "get_usergroup" => {
+                let args = parse_args::<ArgsGetUsergroup>(&request, "get_usergroup")?;
+                let (id, handle) = parse_usergroup_ref(&args.usergroup);
+                let groups = self.enabled_usergroups().await?;
+                let group = groups.iter().find(|g| {
+                    let field = |k: &str| g.get(k).and_then(|v| v.as_str());
+                    match (&id, &handle) {
+                        (Some(id), _) => field("id") == Some(id.as_str()),
+                        (None, Some(handle)) => {
+                            field("handle").is_some_and(|h| h.eq_ignore_ascii_case(handle))
+                        }
+                        (None, None) => false,
+                    }
+                });
+                let Some(group_id) = group
+                    .and_then(|g| g.get("id"))
+                    .and_then(|v| v.as_str())
+                    .map(str::to_string)
+                    .or(id)
+                else {
                     return Err(McpError::invalid_params(
-                        "channel not allowed by GRAIL_SLACK_ALLOW_CHANNELS",
-                        Some(json!({ "channel": args.channel })),
+                        "no enabled user group with that handle",
+                        Some(json!({ "usergroup": args.usergroup })),
                     ));
+                };
+                let mut query = vec![("usergroup", group_id.clone())];
+                if let Some(team_id) = &self.team_id {
+                    query.push(("team_id", team_id.clone()));
                 }
-                let query = vec![
-                    ("channel", args.channel.clone()),
-                    ("message_ts", args.message_ts.clone()),
-                ];
-                let SlackOkWrapper { inner, .. }: SlackOkWrapper<PermalinkResponse> = self
-                    .slack_api_get("https://slack.com/api/chat.getPermalink", &query)
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<UsergroupUsersResponse> = self
+                    .slack_api_get(&slack_api("usergroups.users.list"), &query)
                     .await?;
+                let members: Vec<serde_json::Value> = if args.include_profiles {
+                    let mut members = Vec::with_capacity(inner.users.len());
+                    for user_id in &inner.users {
+                        members.push(match self.user_info(user_id).await {
+                            Ok(user) => user_summary(&user),
+                            Err(_) => json!({ "id": user_id }),
+                        });
+                    }
+                    members
+                } else {
+                    inner.users.iter().map(|u| json!(u)).collect()
+                };
                 Ok(CallToolResult {
                     content: Vec::new(),
                     structured_content: Some(json!({
-                        "channel": args.channel,
-                        "message_ts": args.message_ts,
-                        "permalink": inner.permalink,
+                        "usergroup": group.map(usergroup_summary).unwrap_or_else(|| json!({ "id": group_id })),
+                        "members": members,
                     })),
                     is_error: Some(false),
                     meta: None,
                 })
             }
-            "get_user" => {
-                let args = parse_args::<ArgsGetUser>(&request, "get_user")?;
-                let query = vec![("user", args.user_id.clone())];
-                let SlackOkWrapper { inner, .. }: SlackOkWrapper<UserInfoResponse> = self
-                    .slack_api_get("https://slack.com/api/users.info", &query)
-                    .await?;
+            "list_users" => {
+                let args =
+                    parse_args::<ArgsListUsers>(&request, "list_users").unwrap_or(ArgsListUsers {
+                        limit: None,
+                        cursor: None,
+                        exclude_bots: None,
+                        exclude_deactivated: None,
+                    });
+                let limit = args.limit.unwrap_or(200).clamp(1, 1000);
+                let mut query = vec![("limit", limit.to_string())];
+                if let Some(team_id) = &self.team_id {
+                    query.push(("team_id", team_id.clone()));
+                }
+                if let Some(cursor) = args.cursor.filter(|c| !c.is_empty()) {
+                    query.push(("cursor", cursor));
+                }
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<ListUsersResponse> =
+                    self.slack_api_get(&slack_api("users.list"), &query).await?;
+                let exclude_bots = args.exclude_bots.unwrap_or(true);
+                let exclude_deactivated = args.exclude_deactivated.unwrap_or(true);
+                let mut users = Vec::new();
+                for member in &inner.members {
+                    if let Some(id) = member.get("id").and_then(|v| v.as_str()) {
+                        self.users.insert(id.to_string(), member.clone());
+                    }
+                    if exclude_bots && is_bot_user(member) {
+                        continue;
+                    }
+                    let deleted = member
+                        .get("deleted")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
+                    if exclude_deactivated && deleted {
+                        continue;
+                    }
+                    users.push(user_summary(member));
+                }
+                let cursor = next_cursor(inner.response_metadata.as_ref());
                 Ok(CallToolResult {
                     content: Vec::new(),
                     structured_content: Some(json!({
-                        "user_id": args.user_id,
-                        "user": inner.user,
+                        "users": users,
+                        "has_more": cursor.is_some(),
+                        "next_cursor": cursor,
                     })),
                     is_error: Some(false),
                     meta: None,
                 })
             }
             "list_channels" => {
-                let args = parse_args::<ArgsListChannels>(&request, "list_channels")
-                    .unwrap_or(ArgsListChannels { limit: None });
+                let args = parse_args::<ArgsListChannels>(&request, "list_channels").unwrap_or(
+                    ArgsListChannels {
+                        limit: None,
+                        cursor: None,
+                    },
+                );
                 let limit = args.limit.unwrap_or(200).clamp(1, 1000);
-                let query = vec![
-                    ("limit", limit.to_string()),
-                    ("types", "public_channel,private_channel".to_string()),
-                    ("exclude_archived", "true".to_string()),
-                ];
-                let SlackOkWrapper { inner, .. }: SlackOkWrapper<ListChannelsResponse> = self
-                    .slack_api_get("https://slack.com/api/conversations.list", &query)
+                let (mut channels, cursor) = self
+                    .channel_page(limit, args.cursor.filter(|c| !c.is_empty()))
                     .await?;
-                let mut channels = inner.channels;
-                if !self.allowed_channels.is_empty() {
+                if !self.allowed_channels.is_open() || !self.denied_channels.is_empty() {
                     channels.retain(|c| {
                         c.get("id")
                             .and_then(|v| v.as_str())
-                            .map(|id| self.allowed_channels.contains(id))
+                            .map(|id| self.channel_allowed(id))
                             .unwrap_or(false)
                     });
                 }
                 Ok(CallToolResult {
                     content: Vec::new(),
                     structured_content: Some(json!({
+                        "team_id": self.team_id,
                         "channels": channels,
+                        "has_more": cursor.is_some(),
+                        "next_cursor": cursor,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "resolve_channel" => {
+                let args = parse_args::<ArgsResolveChannel>(&request, "resolve_channel")?;
+                let (name, mention_id) = parse_channel_ref(&args.name);
+                if let Some(id) = mention_id {
+                    self.ensure_channel_allowed(&id)?;
+                    return Ok(CallToolResult {
+                        content: Vec::new(),
+                        structured_content: Some(json!({
+                            "query": args.name,
+                            "channel_id": id,
+                            "name": name,
+                        })),
+                        is_error: Some(false),
+                        meta: None,
+                    });
+                }
+                if name.is_empty() {
+                    return Err(McpError::invalid_params("name is required", None));
+                }
+                let Some(channel) = self.find_channel(&name).await? else {
+                    return Err(McpError::invalid_params(
+                        format!("no channel named #{name} is visible to the bot"),
+                        Some(json!({ "name": name })),
+                    ));
+                };
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "query": args.name,
+                        "channel_id": channel.get("id"),
+                        "name": channel.get("name"),
+                        "is_private": channel.get("is_private"),
+                        "num_members": channel.get("num_members"),
                     })),
                     is_error: Some(false),
                     meta: None,
@@ -474,12 +3128,15 @@ impl ServerHandler for SlackMcpServer {
                     return Err(McpError::invalid_params("query is required", None));
                 }
                 let count = args.count.unwrap_or(10).clamp(1, 20);
-                let query = vec![
+                let mut query = vec![
                     ("query", q.to_string()),
                     ("count", count.to_string()),
                     ("sort", "timestamp".to_string()),
                     ("sort_dir", "desc".to_string()),
                 ];
+                if let Some(team_id) = &self.team_id {
+                    query.push(("team_id", team_id.clone()));
+                }
 
                 #[derive(Deserialize)]
                 struct SearchInner {
@@ -495,26 +3152,597 @@ impl ServerHandler for SlackMcpServer {
                 }
 
                 let SlackOkWrapper { inner, .. }: SlackOkWrapper<SearchResp> = self
-                    .slack_api_get("https://slack.com/api/search.messages", &query)
+                    .slack_search(&slack_api("search.messages"), &query, "search_messages")
                     .await?;
 
                 let mut matches = inner.messages.matches;
-                if !self.allowed_channels.is_empty() {
+                if self.user_token.is_some() {
+                    let mut known = HashMap::new();
+                    let mut visible = Vec::with_capacity(matches.len());
+                    for m in matches {
+                        let channel = m.get("channel").cloned().unwrap_or_default();
+                        if self.user_search_match_allowed(&channel, &mut known).await {
+                            visible.push(m);
+                        }
+                    }
+                    matches = visible;
+                }
+                if !self.allowed_channels.is_open() || !self.denied_channels.is_empty() {
                     matches.retain(|m| {
                         let ch = m
                             .get("channel")
                             .and_then(|c| c.get("id"))
                             .and_then(|v| v.as_str())
                             .unwrap_or("");
-                        self.channel_allowed(ch)
+                        // In Grid orgs, matches can come from other workspaces.
+                        let team = m
+                            .get("team")
+                            .and_then(|v| v.as_str())
+                            .or(self.team_id.as_deref());
+                        self.channel_allowed_in(team, ch)
                     });
                 }
+                let truncated = budget::fit(&mut matches, args.max_chars);
 
                 Ok(CallToolResult {
                     content: Vec::new(),
                     structured_content: Some(json!({
                         "query": q,
                         "matches": matches,
+                        "truncated": truncated,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "search_files" => {
+                let args = parse_args::<ArgsSearchMessages>(&request, "search_files")?;
+                let q = args.query.trim();
+                if q.is_empty() {
+                    return Err(McpError::invalid_params("query is required", None));
+                }
+                let count = args.count.unwrap_or(10).clamp(1, 20);
+                let mut query = vec![
+                    ("query", q.to_string()),
+                    ("count", count.to_string()),
+                    ("sort", "timestamp".to_string()),
+                    ("sort_dir", "desc".to_string()),
+                ];
+                if let Some(team_id) = &self.team_id {
+                    query.push(("team_id", team_id.clone()));
+                }
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<SearchFilesResponse> = self
+                    .slack_search(&slack_api("search.files"), &query, "search_files")
+                    .await?;
+
+                let mut files: Vec<serde_json::Value> = Vec::new();
+                let mut known = HashMap::new();
+                for f in &inner.files.matches {
+                    let allowed = if self.user_token.is_some() {
+                        self.user_search_file_allowed(f, &mut known).await
+                    } else {
+                        self.file_allowed(f)
+                    };
+                    if allowed {
+                        files.push(file_summary(f));
+                    }
+                }
+
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "query": q,
+                        "files": files,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "post_message" if self.allow_writes => {
+                let args = parse_args::<ArgsPostMessage>(&request, "post_message")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let text = args.text.trim();
+                if text.is_empty() {
+                    return Err(McpError::invalid_params("text is required", None));
+                }
+                let mut body = json!({
+                    "channel": args.channel,
+                    "text": text,
+                });
+                if let Some(ts) = args.thread_ts.filter(|t| !t.trim().is_empty()) {
+                    body["thread_ts"] = json!(ts);
+                }
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<PostMessageResponse> = self
+                    .slack_api_post(&slack_api("chat.postMessage"), &body)
+                    .await?;
+                info!(channel = %inner.channel, ts = %inner.ts, "posted slack message");
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": inner.channel,
+                        "ts": inner.ts,
+                        "thread_ts": body.get("thread_ts"),
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "schedule_message" if self.allow_writes => {
+                let args = parse_args::<ArgsScheduleMessage>(&request, "schedule_message")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let text = args.text.trim();
+                if text.is_empty() {
+                    return Err(McpError::invalid_params("text is required", None));
+                }
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs() as i64)
+                    .unwrap_or_default();
+                if args.post_at <= now || args.post_at > now + MAX_SCHEDULE_AHEAD_SECS {
+                    return Err(McpError::invalid_params(
+                        "post_at must be in the future and at most 120 days ahead",
+                        Some(json!({ "post_at": args.post_at, "now": now })),
+                    ));
+                }
+                let mut body = json!({
+                    "channel": args.channel,
+                    "post_at": args.post_at,
+                    "text": text,
+                });
+                if let Some(ts) = args.thread_ts.filter(|t| !t.trim().is_empty()) {
+                    body["thread_ts"] = json!(ts);
+                }
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<ScheduleMessageResponse> = self
+                    .slack_api_post(&slack_api("chat.scheduleMessage"), &body)
+                    .await?;
+                info!(
+                    channel = %inner.channel,
+                    id = %inner.scheduled_message_id,
+                    post_at = args.post_at,
+                    "scheduled slack message"
+                );
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": inner.channel,
+                        "scheduled_message_id": inner.scheduled_message_id,
+                        "post_at": inner.post_at,
+                        "thread_ts": body.get("thread_ts"),
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "list_scheduled_messages" if self.allow_writes => {
+                let args =
+                    parse_args::<ArgsListScheduledMessages>(&request, "list_scheduled_messages")
+                        .unwrap_or(ArgsListScheduledMessages {
+                            channel: None,
+                            cursor: None,
+                        });
+                let channel = args.channel.filter(|c| !c.trim().is_empty());
+                let mut body = json!({ "limit": 100 });
+                if let Some(channel) = &channel {
+                    self.ensure_channel_allowed(channel)?;
+                    body["channel"] = json!(channel);
+                }
+                if let Some(team_id) = &self.team_id {
+                    body["team_id"] = json!(team_id);
+                }
+                if let Some(cursor) = args.cursor.filter(|c| !c.is_empty()) {
+                    body["cursor"] = json!(cursor);
+                }
+                let SlackOkWrapper { mut inner, .. }: SlackOkWrapper<ScheduledMessagesResponse> =
+                    self.slack_api_post(&slack_api("chat.scheduledMessages.list"), &body)
+                        .await?;
+                inner.scheduled_messages.retain(|m| {
+                    m.get("channel_id")
+                        .and_then(|v| v.as_str())
+                        .is_some_and(|c| self.channel_allowed(c))
+                });
+                let cursor = next_cursor(inner.response_metadata.as_ref());
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "scheduled_messages": inner.scheduled_messages,
+                        "has_more": cursor.is_some(),
+                        "next_cursor": cursor,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "add_reaction" if self.allow_writes => {
+                let args = parse_args::<ArgsAddReaction>(&request, "add_reaction")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let name = args.name.trim().trim_matches(':');
+                if name.is_empty() {
+                    return Err(McpError::invalid_params("name is required", None));
+                }
+                let body = json!({
+                    "channel": args.channel,
+                    "timestamp": args.message_ts,
+                    "name": name,
+                });
+                let result: Result<SlackOkWrapper<serde_json::Value>, McpError> = self
+                    .slack_api_post(&slack_api("reactions.add"), &body)
+                    .await;
+                // Reacting twice with the same emoji is not worth failing the turn over.
+                let already = match result {
+                    Ok(_) => false,
+                    Err(err) if err.message.contains("already_reacted") => true,
+                    Err(err) => return Err(err),
+                };
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": args.channel,
+                        "message_ts": args.message_ts,
+                        "name": name,
+                        "already_reacted": already,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "open_dm" if self.allow_writes => {
+                let args = parse_args::<ArgsGetUser>(&request, "open_dm")?;
+                let user_id = args.user_id.trim();
+                if user_id.is_empty() {
+                    return Err(McpError::invalid_params("user_id is required", None));
+                }
+                self.ensure_dm_allowed(user_id).await?;
+                let body = json!({ "users": user_id, "return_im": true });
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<OpenDmResponse> = self
+                    .slack_api_post(&slack_api("conversations.open"), &body)
+                    .await?;
+                let channel = inner
+                    .channel
+                    .get("id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                self.ensure_channel_allowed(&channel)?;
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "user_id": user_id,
+                        "channel": channel,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "add_reminder" if self.allow_writes => {
+                let args = parse_args::<ArgsAddReminder>(&request, "add_reminder")?;
+                let (text, time) = (args.text.trim(), args.time.trim());
+                if text.is_empty() || time.is_empty() {
+                    return Err(McpError::invalid_params("text and time are required", None));
+                }
+                let token = self.require_user_token("add_reminder", "reminders:write")?;
+                // The reminder lands on the token owner's account whoever asked, so only
+                // they may set one.
+                let owner = self.user_token_owner(&token).await?;
+                if args.user_id.trim() != owner {
+                    return Err(McpError::invalid_params(
+                        "add_reminder only sets reminders for the user SLACK_USER_TOKEN belongs to",
+                        Some(json!({ "user_id": args.user_id.trim(), "token_owner": owner })),
+                    ));
+                }
+                let mut body = json!({ "text": text, "time": time });
+                if let Some(team_id) = &self.team_id {
+                    body["team_id"] = json!(team_id);
+                }
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<ReminderResponse> = self
+                    .slack_api_send(
+                        self.http.post(slack_api("reminders.add")).json(&body),
+                        token,
+                    )
+                    .await?;
+                info!(
+                    id = ?inner.reminder.get("id"),
+                    time = ?inner.reminder.get("time"),
+                    "added slack reminder"
+                );
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({ "reminder": inner.reminder })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "list_reminders" if self.allow_writes => {
+                let token = self.require_user_token("list_reminders", "reminders:read")?;
+                let mut query = Vec::new();
+                if let Some(team_id) = &self.team_id {
+                    query.push(("team_id", team_id.clone()));
+                }
+                let SlackOkWrapper { inner, .. }: SlackOkWrapper<RemindersResponse> = self
+                    .with_transient_retries(|| {
+                        self.slack_api_send(
+                            self.http.get(slack_api("reminders.list")).query(&query),
+                            token.clone(),
+                        )
+                    })
+                    .await?;
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({ "reminders": inner.reminders })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "list_reactions" => {
+                let args = parse_args::<ArgsListReactions>(&request, "list_reactions")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let message = self
+                    .reacted_message(&args.channel, &args.message_ts)
+                    .await?;
+                let reactions = message
+                    .get("reactions")
+                    .cloned()
+                    .unwrap_or_else(|| json!([]));
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": args.channel,
+                        "message_ts": args.message_ts,
+                        "reactions": reactions,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "get_message_reactions" => {
+                let args =
+                    parse_args::<ArgsGetMessageReactions>(&request, "get_message_reactions")?;
+                self.ensure_channel_allowed(&args.channel)?;
+                let message = self
+                    .reacted_message(&args.channel, &args.message_ts)
+                    .await?;
+                let options: HashSet<String> = args
+                    .emoji
+                    .iter()
+                    .map(|e| e.trim().trim_matches(':').to_string())
+                    .filter(|e| !e.is_empty())
+                    .collect();
+
+                // Votes by emoji, with `+1::skin-tone-2` counted as `+1`.
+                let mut votes: Vec<(String, Vec<String>)> = Vec::new();
+                let reactions = message
+                    .get("reactions")
+                    .and_then(|v| v.as_array())
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                for reaction in reactions {
+                    let Some(name) = reaction.get("name").and_then(|v| v.as_str()) else {
+                        continue;
+                    };
+                    let base = name.split("::").next().unwrap_or(name);
+                    if !options.is_empty() && !options.contains(base) {
+                        continue;
+                    }
+                    let users = reaction
+                        .get("users")
+                        .and_then(|v| v.as_array())
+                        .map(Vec::as_slice)
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|u| u.as_str().map(str::to_string));
+                    match votes.iter_mut().find(|(n, _)| n == base) {
+                        Some((_, voters)) => {
+                            voters.extend(users);
+                            voters.sort();
+                            voters.dedup();
+                        }
+                        None => votes.push((base.to_string(), users.collect())),
+                    }
+                }
+                votes.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+
+                let mut per_user: HashMap<&str, usize> = HashMap::new();
+                for (_, voters) in &votes {
+                    for voter in voters {
+                        *per_user.entry(voter.as_str()).or_default() += 1;
+                    }
+                }
+                let mut multiple: Vec<&str> = per_user
+                    .iter()
+                    .filter(|(_, n)| **n > 1)
+                    .map(|(u, _)| *u)
+                    .collect();
+                multiple.sort_unstable();
+                let names = if args.resolve_users {
+                    self.user_names(per_user.keys().map(|u| u.to_string()).collect())
+                        .await
+                } else {
+                    HashMap::new()
+                };
+
+                let tally: Vec<serde_json::Value> = votes
+                    .iter()
+                    .map(|(name, voters)| {
+                        let mut entry = json!({
+                            "name": name,
+                            "count": voters.len(),
+                            "users": voters,
+                        });
+                        if args.resolve_users {
+                            entry["user_names"] = json!(voters
+                                .iter()
+                                .map(|u| names.get(u).map_or(u.as_str(), String::as_str))
+                                .collect::<Vec<_>>());
+                        }
+                        entry
+                    })
+                    .collect();
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "channel": args.channel,
+                        "message_ts": args.message_ts,
+                        "text": message.get("text"),
+                        "reactions": tally,
+                        "voters": per_user.len(),
+                        "multiple_votes": multiple,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "list_emoji" => {
+                let args =
+                    parse_args::<ArgsListEmoji>(&request, "list_emoji").unwrap_or(ArgsListEmoji {
+                        query: None,
+                        limit: None,
+                    });
+                let limit = args.limit.unwrap_or(500).clamp(1, 5000);
+                let query = args
+                    .query
+                    .map(|q| q.trim().trim_matches(':').to_ascii_lowercase())
+                    .filter(|q| !q.is_empty());
+                let all = self.custom_emoji().await?;
+                let mut names: Vec<&String> = all
+                    .keys()
+                    .filter(|name| query.as_ref().is_none_or(|q| name.contains(q.as_str())))
+                    .collect();
+                names.sort();
+                let total = names.len();
+                let emoji: Vec<serde_json::Value> = names
+                    .into_iter()
+                    .take(limit)
+                    .map(|name| {
+                        let value = all[name].as_str().unwrap_or_default();
+                        match value.strip_prefix("alias:") {
+                            Some(target) => json!({ "name": name, "alias_of": target }),
+                            None => json!({ "name": name, "url": value }),
+                        }
+                    })
+                    .collect();
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "emoji": emoji,
+                        "total": total,
+                        "truncated": total > limit,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "get_file_info" => {
+                let args = parse_args::<ArgsFile>(&request, "get_file_info")?;
+                let file = self.file_info(args.file_id.trim()).await?;
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "file_id": args.file_id,
+                        "file": file_summary(&file),
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "download_file_text" => {
+                let args = parse_args::<ArgsFile>(&request, "download_file_text")?;
+                let file = self.file_info(args.file_id.trim()).await?;
+                if !is_text_file(&file) {
+                    return Err(McpError::invalid_params(
+                        "not a text file; use get_file_info for its metadata",
+                        Some(file_summary(&file)),
+                    ));
+                }
+                let url = file
+                    .get("url_private_download")
+                    .or_else(|| file.get("url_private"))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        McpError::internal_error(
+                            "file has no download url",
+                            Some(file_summary(&file)),
+                        )
+                    })?;
+                let max_bytes = args
+                    .max_bytes
+                    .unwrap_or(DEFAULT_FILE_BYTES)
+                    .clamp(1, MAX_FILE_BYTES);
+                let (bytes, truncated) = self.download_private(url, max_bytes).await?;
+                let mut text = String::from_utf8_lossy(&bytes).into_owned();
+                if truncated {
+                    // The cut may have split a multi-byte character.
+                    while text.ends_with('\u{FFFD}') {
+                        text.pop();
+                    }
+                }
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "file_id": args.file_id,
+                        "name": file.get("name"),
+                        "size": file.get("size"),
+                        "truncated": truncated,
+                        "text": text,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "get_canvas" => {
+                let args = parse_args::<ArgsGetCanvas>(&request, "get_canvas")?;
+                let channel = args.channel.filter(|c| !c.trim().is_empty());
+                let canvas_id = args.canvas_id.filter(|c| !c.trim().is_empty());
+                let file = match (&channel, canvas_id) {
+                    (_, Some(id)) => self.file_info(id.trim()).await?,
+                    (Some(channel), None) => {
+                        self.ensure_channel_allowed(channel)?;
+                        let query = vec![("channel", channel.clone())];
+                        let SlackOkWrapper { inner, .. }: SlackOkWrapper<ChannelInfoResponse> =
+                            self.slack_api_get(&slack_api("conversations.info"), &query)
+                                .await?;
+                        let Some(id) = canvas::channel_canvas_id(&inner.channel) else {
+                            return Err(McpError::invalid_params(
+                                "this channel has no canvas",
+                                Some(json!({ "channel": channel })),
+                            ));
+                        };
+                        self.file_info_unchecked(&id).await?
+                    }
+                    (None, None) => {
+                        return Err(McpError::invalid_params("pass channel or canvas_id", None))
+                    }
+                };
+                if !canvas::is_canvas(&file) {
+                    return Err(McpError::invalid_params(
+                        "not a canvas; use download_file_text for other files",
+                        Some(file_summary(&file)),
+                    ));
+                }
+                let url = file
+                    .get("url_private_download")
+                    .or_else(|| file.get("url_private"))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        McpError::internal_error(
+                            "canvas has no download url",
+                            Some(file_summary(&file)),
+                        )
+                    })?;
+                let max_bytes = args
+                    .max_bytes
+                    .unwrap_or(DEFAULT_FILE_BYTES)
+                    .clamp(1, MAX_FILE_BYTES);
+                let (bytes, truncated) = self.download_private(url, max_bytes).await?;
+                let text = canvas::html_to_text(&String::from_utf8_lossy(&bytes));
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "canvas_id": file.get("id"),
+                        "title": file.get("title"),
+                        "channel": channel,
+                        "permalink": file.get("permalink"),
+                        "updated": file.get("updated"),
+                        "truncated": truncated,
+                        "text": text,
                     })),
                     is_error: Some(false),
                     meta: None,
@@ -544,13 +3772,13 @@ fn parse_args<T: for<'de> Deserialize<'de>>(
     }
 }
 
-fn parse_allowlist_env(key: &str) -> HashSet<String> {
-    let raw = std::env::var(key).unwrap_or_default();
-    raw.split(|c: char| c == ',' || c == '\n' || c == '\r' || c == '\t' || c == ' ')
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .map(|s| s.to_string())
-        .collect()
+fn env_flag(key: &str) -> bool {
+    std::env::var(key).is_ok_and(|v| {
+        matches!(
+            v.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes" | "on"
+        )
+    })
 }
 
 #[tokio::main]
@@ -561,10 +3789,29 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     let service = SlackMcpServer::new()?;
+    if service.allowed_channels.is_watched() {
+        tokio::spawn(service.allowed_channels.clone().watch());
+    }
+    if service.allowed_channels.is_watched() || !service.allowed_channels.patterns().is_empty() {
+        if let Err(err) = service.resolve_channel_patterns().await {
+            warn!(error = %err.message, "failed to resolve channel name patterns; they match nothing for now");
+        }
+        tokio::spawn(service.clone().refresh_channel_patterns());
+    }
+    if let Some(app_token) = socket::app_token_from_env() {
+        tokio::spawn(socket::run(service.clone(), app_token));
+    }
+    let telemetry = service.telemetry.clone();
+    if let Some(every) = telemetry::log_interval_from_env() {
+        let telemetry = telemetry.clone();
+        tokio::spawn(async move { telemetry.report_periodically(every).await });
+    }
     info!("starting grail-slack-mcp (stdio)");
 
     let running = service.serve(stdio()).await?;
-    if let Err(err) = running.waiting().await {
+    let waited = running.waiting().await;
+    telemetry.log_summary();
+    if let Err(err) = waited {
         error!(error = %err, "mcp server exiting");
         return Err(anyhow::Error::new(err));
     }
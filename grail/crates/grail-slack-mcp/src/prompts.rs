@@ -0,0 +1,146 @@
+//! Canned Slack workflows as MCP prompts, for thin clients that don't plan tool sequences
+//! well on their own.
+//!
+//! Each prompt expands to a single user message naming the tools to call, with which
+//! arguments and in what order, and what to write at the end. A prompt is only offered when
+//! every tool it relies on is enabled.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rmcp::model::{
+    GetPromptResult, JsonObject, Prompt, PromptArgument, PromptMessage, PromptMessageRole,
+};
+use rmcp::ErrorData as McpError;
+
+const DEFAULT_HOURS: u64 = 24;
+const MAX_HOURS: u64 = 24 * 30;
+
+struct Workflow {
+    name: &'static str,
+    description: &'static str,
+    /// Name, description and whether it's required.
+    arguments: &'static [(&'static str, &'static str, bool)],
+    tools: &'static [&'static str],
+}
+
+const WORKFLOWS: [Workflow; 2] = [
+    Workflow {
+        name: "summarize_channel",
+        description: "Summarize a Slack channel's recent activity: topics, decisions, open questions and action items.",
+        arguments: &[
+            ("channel", "Slack channel ID (e.g. C123...).", true),
+            ("hours", "How far back to look, in hours (default 24).", false),
+        ],
+        tools: &["get_channel_history", "get_thread_full"],
+    },
+    Workflow {
+        name: "incident_timeline",
+        description: "Build a timeline of an incident from its Slack thread: detection, escalation, mitigation, resolution and follow-ups.",
+        arguments: &[(
+            "thread_permalink",
+            "Permalink of the incident thread (any message in it).",
+            true,
+        )],
+        tools: &["resolve_permalink", "get_thread_full"],
+    },
+];
+
+fn argument(arguments: Option<&JsonObject>, name: &str) -> Option<String> {
+    let value = arguments?.get(name)?;
+    value
+        .as_str()
+        .map(|s| s.trim().to_string())
+        .or_else(|| value.as_number().map(|n| n.to_string()))
+        .filter(|s| !s.is_empty())
+}
+
+fn required(arguments: Option<&JsonObject>, prompt: &str, name: &str) -> Result<String, McpError> {
+    argument(arguments, name)
+        .ok_or_else(|| McpError::invalid_params(format!("{prompt} needs {name}"), None))
+}
+
+/// The prompts whose tools are all `enabled`.
+pub fn list(enabled: impl Fn(&str) -> bool) -> Vec<Prompt> {
+    WORKFLOWS
+        .iter()
+        .filter(|w| w.tools.iter().all(|t| enabled(t)))
+        .map(|w| {
+            let arguments = w
+                .arguments
+                .iter()
+                .map(|(name, description, required)| PromptArgument {
+                    name: name.to_string(),
+                    title: None,
+                    description: Some(description.to_string()),
+                    required: Some(*required),
+                })
+                .collect();
+            Prompt::new(w.name, Some(w.description), Some(arguments))
+        })
+        .collect()
+}
+
+pub fn get(
+    name: &str,
+    arguments: Option<&JsonObject>,
+    enabled: impl Fn(&str) -> bool,
+) -> Result<GetPromptResult, McpError> {
+    let Some(workflow) = WORKFLOWS
+        .iter()
+        .find(|w| w.name == name && w.tools.iter().all(|t| enabled(t)))
+    else {
+        return Err(McpError::invalid_params(
+            format!("unknown or disabled prompt: {name}"),
+            None,
+        ));
+    };
+    let text = match workflow.name {
+        "summarize_channel" => {
+            let channel = required(arguments, name, "channel")?;
+            let hours = argument(arguments, "hours")
+                .and_then(|h| h.parse::<u64>().ok())
+                .unwrap_or(DEFAULT_HOURS)
+                .clamp(1, MAX_HOURS);
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            let oldest = now.saturating_sub(hours * 60 * 60);
+            format!(
+                "Summarize what happened in Slack channel {channel} over the last {hours} hours.\n\n\
+                 1. Call get_channel_history with channel \"{channel}\", oldest_ts \"{oldest}.000000\", \
+                 limit 200, resolve_entities true and flatten_blocks true. While has_more is true, call \
+                 it again with next_cursor as cursor.\n\
+                 2. For each message with replies (reply_count above 0), call get_thread_full with its \
+                 ts as thread_ts, resolve_entities true and max_chars 8000.\n\
+                 3. Write the summary: the main topics, decisions made (and by whom), open questions, and \
+                 action items with their owners. Mention message times, and say so if the channel was \
+                 quiet."
+            )
+        }
+        "incident_timeline" => {
+            let permalink = required(arguments, name, "thread_permalink")?;
+            format!(
+                "Build an incident timeline from the Slack thread at {permalink}.\n\n\
+                 1. Call resolve_permalink with url \"{permalink}\" to get the channel and the \
+                 thread's ts (thread_ts, or message_ts when the link is to the parent message).\n\
+                 2. Call get_thread_full with that channel and thread_ts, resolve_entities true and \
+                 flatten_blocks true. If truncated is true, say that the timeline may be incomplete.\n\
+                 3. Write a chronological timeline in UTC, one line per event: when the problem was \
+                 detected, who was paged or joined, what was tried, when it was mitigated and resolved. \
+                 Then list the impact as described in the thread, the suspected root cause, and open \
+                 follow-ups with their owners. Only state what the thread supports."
+            )
+        }
+        other => {
+            return Err(McpError::internal_error(
+                format!("prompt {other} has no template"),
+                None,
+            ))
+        }
+    };
+    Ok(GetPromptResult {
+        description: Some(workflow.description.to_string()),
+        messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+    })
+}
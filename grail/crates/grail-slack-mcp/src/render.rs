@@ -0,0 +1,106 @@
+//! Text `content` for tool results, for MCP clients that ignore `structured_content`.
+//!
+//! `GRAIL_SLACK_TEXT_CONTENT` picks the rendering: `summary` (default) puts each field on
+//! its own line and each item of a list (messages, channels, users, ...) on one line of its
+//! most telling fields; `json` is the structured result as compact JSON; `off` leaves
+//! `content` empty, as before.
+
+use rmcp::model::{CallToolResult, Content};
+use serde_json::Value;
+
+/// Item fields shown in a list line, in order; `text` goes last, after a colon.
+const ITEM_FIELDS: [&str; 6] = ["ts", "id", "name", "real_name", "user_name", "title"];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TextContent {
+    Summary,
+    Json,
+    Off,
+}
+
+pub fn mode_from_env() -> TextContent {
+    match std::env::var("GRAIL_SLACK_TEXT_CONTENT")
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "json" => TextContent::Json,
+        "off" | "0" | "false" | "none" => TextContent::Off,
+        _ => TextContent::Summary,
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn item_line(item: &Value) -> String {
+    let Value::Object(map) = item else {
+        return scalar(item).unwrap_or_else(|| item.to_string());
+    };
+    let mut parts: Vec<String> = ITEM_FIELDS
+        .iter()
+        .filter_map(|k| map.get(*k).and_then(scalar))
+        .filter(|s| !s.is_empty())
+        .collect();
+    // Messages carry the author's id in `user`; only show it when there's no resolved name.
+    if !map.contains_key("user_name") {
+        if let Some(user) = map.get("user").and_then(scalar) {
+            parts.push(user);
+        }
+    }
+    if let Some(n) = map.get("reply_count").and_then(|v| v.as_u64()) {
+        parts.push(format!("({n} replies)"));
+    }
+    let text = map
+        .get("text")
+        .and_then(|v| v.as_str())
+        .filter(|t| !t.is_empty());
+    match (parts.is_empty(), text) {
+        (true, None) => item.to_string(),
+        (true, Some(text)) => text.replace('\n', "\n  "),
+        (false, None) => parts.join(" "),
+        (false, Some(text)) => format!("{}: {}", parts.join(" "), text.replace('\n', "\n  ")),
+    }
+}
+
+/// A compact, line-oriented rendering of a tool's structured result.
+pub fn summary(value: &Value) -> String {
+    let Value::Object(map) = value else {
+        return value.to_string();
+    };
+    let mut lines = Vec::new();
+    for (key, v) in map {
+        match v {
+            Value::Null => {}
+            Value::Array(items) => {
+                lines.push(format!("{key} ({}):", items.len()));
+                lines.extend(items.iter().map(|item| format!("- {}", item_line(item))));
+            }
+            Value::Object(_) => lines.push(format!("{key}: {v}")),
+            _ => lines.push(format!("{key}: {}", scalar(v).unwrap_or_default())),
+        }
+    }
+    lines.join("\n")
+}
+
+/// Fill an empty `content` from `structured_content`.
+pub fn fill(mode: TextContent, result: &mut CallToolResult) {
+    if mode == TextContent::Off || !result.content.is_empty() {
+        return;
+    }
+    let Some(structured) = &result.structured_content else {
+        return;
+    };
+    let text = match mode {
+        TextContent::Json => structured.to_string(),
+        _ => summary(structured),
+    };
+    result.content.push(Content::text(text));
+}
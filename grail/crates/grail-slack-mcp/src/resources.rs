@@ -0,0 +1,147 @@
+//! Allowed channels as MCP resources (`slack://C123`).
+//!
+//! Clients can browse channels with `resources/list` and read a channel's recent messages
+//! with `resources/read` instead of calling tools. A subscribed channel is polled for new
+//! messages (`GRAIL_SLACK_SUBSCRIBE_POLL_SECS`, default 60) and the client is sent
+//! `notifications/resources/updated` when its newest message changes.
+
+use std::time::Duration;
+
+use rmcp::model::{
+    AnnotateAble, RawResource, RawResourceTemplate, Resource, ResourceTemplate,
+    ResourceUpdatedNotificationParam,
+};
+use rmcp::service::{Peer, RoleServer};
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, warn};
+
+use crate::{HistoryResponse, SlackMcpServer, SlackOkWrapper};
+
+pub const SCHEME: &str = "slack://";
+/// Messages returned by `resources/read`.
+pub const READ_LIMIT: i64 = 50;
+const DEFAULT_POLL: Duration = Duration::from_secs(60);
+
+pub fn channel_uri(channel_id: &str) -> String {
+    format!("{SCHEME}{channel_id}")
+}
+
+/// The channel id of a `slack://C123` URI.
+pub fn parse_channel_uri(uri: &str) -> Option<&str> {
+    uri.strip_prefix(SCHEME)
+        .map(|id| id.trim_end_matches('/'))
+        .filter(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// A `conversations.list` entry as a resource.
+pub fn channel_resource(channel: &serde_json::Value) -> Option<Resource> {
+    let id = channel.get("id")?.as_str()?;
+    let name = channel.get("name").and_then(|v| v.as_str()).unwrap_or(id);
+    let mut raw = RawResource::new(channel_uri(id), format!("#{name}"));
+    raw.description = ["purpose", "topic"]
+        .iter()
+        .filter_map(|k| channel.get(*k)?.get("value")?.as_str())
+        .find(|v| !v.trim().is_empty())
+        .map(str::to_string);
+    raw.mime_type = Some("application/json".to_string());
+    Some(raw.no_annotation())
+}
+
+/// For channels `resources/list` doesn't show (DMs, channels on later pages).
+pub fn channel_template() -> ResourceTemplate {
+    RawResourceTemplate {
+        uri_template: format!("{SCHEME}{{channel_id}}"),
+        name: "Slack channel".to_string(),
+        title: None,
+        description: Some("Recent messages in a Slack channel, by channel ID.".to_string()),
+        mime_type: Some("application/json".to_string()),
+    }
+    .no_annotation()
+}
+
+pub fn poll_interval_from_env() -> Duration {
+    std::env::var("GRAIL_SLACK_SUBSCRIBE_POLL_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|s| *s > 0)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_POLL)
+}
+
+async fn latest_ts(server: &SlackMcpServer, channel: &str) -> Option<String> {
+    let query = vec![("channel", channel.to_string()), ("limit", "1".to_string())];
+    match server
+        .slack_api_get::<SlackOkWrapper<HistoryResponse>>(
+            &crate::slack_api("conversations.history"),
+            &query,
+        )
+        .await
+    {
+        Ok(SlackOkWrapper { inner, .. }) => inner
+            .messages
+            .first()
+            .and_then(|m| m.get("ts"))
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        Err(err) => {
+            warn!(channel, error = %err.message, "failed to poll subscribed channel");
+            None
+        }
+    }
+}
+
+/// The next new message on the Socket Mode bridge; never resolves without one.
+async fn next_live_event(
+    live: &mut Option<tokio::sync::broadcast::Receiver<(String, String)>>,
+) -> Result<(String, String), RecvError> {
+    match live {
+        Some(rx) => {
+            let event = rx.recv().await;
+            if matches!(event, Err(RecvError::Closed)) {
+                *live = None;
+            }
+            event
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Poll `channel` (and listen on the Socket Mode bridge, if any) until the task is aborted
+/// (unsubscribe) or the client goes away.
+pub async fn watch(server: SlackMcpServer, peer: Peer<RoleServer>, channel: String) {
+    let uri = channel_uri(&channel);
+    let mut live = server.live_events.as_ref().map(|tx| tx.subscribe());
+    let mut last = latest_ts(&server, &channel).await;
+    loop {
+        let ts = tokio::select! {
+            _ = tokio::time::sleep(server.poll_interval) => latest_ts(&server, &channel).await,
+            event = next_live_event(&mut live) => match event {
+                Ok((c, ts)) if c == channel => Some(ts),
+                Ok(_) | Err(RecvError::Closed) => continue,
+                // Missed some events: check the channel itself.
+                Err(RecvError::Lagged(_)) => latest_ts(&server, &channel).await,
+            },
+        };
+        let Some(ts) = ts else {
+            continue;
+        };
+        if last.as_deref() == Some(ts.as_str()) {
+            continue;
+        }
+        last = Some(ts);
+        debug!(uri, "subscribed channel has new messages");
+        if let Err(err) = peer
+            .notify_resource_updated(ResourceUpdatedNotificationParam { uri: uri.clone() })
+            .await
+        {
+            warn!(uri, error = %err, "client is gone; stopping channel subscription");
+            return;
+        }
+    }
+}
+
+/// The body of `resources/read` for a channel.
+pub fn channel_contents(channel: &str, messages: Vec<serde_json::Value>) -> String {
+    json!({ "channel": channel, "messages": messages }).to_string()
+}
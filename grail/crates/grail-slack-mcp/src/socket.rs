@@ -0,0 +1,186 @@
+//! Socket Mode bridge (`SLACK_APP_TOKEN`).
+//!
+//! With an app-level token (`xapp-…`, scope `connections:write`) the server keeps a Socket
+//! Mode connection open and turns `message` events in allowed channels into
+//! `notifications/resources/updated` for subscribed `slack://C123` resources right away,
+//! instead of on the next poll. Polling keeps running as a fallback.
+//!
+//! Slack sends an app's events over Socket Mode *instead of* to its Request URL, so this
+//! needs its own Slack app (subscribed to the `message.*` events), not the one grail-server
+//! receives webhooks for.
+//!
+//! The WebSocket upgrade goes through the server's reqwest client, so it shares its TLS
+//! setup; tokio-tungstenite speaks the protocol from there.
+
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use tokio_tungstenite::tungstenite::protocol::{Role, WebSocketConfig};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tracing::{debug, info, warn};
+
+use crate::SlackMcpServer;
+
+/// New messages (channel id, ts) seen on the socket.
+pub type LiveEvents = tokio::sync::broadcast::Sender<(String, String)>;
+
+const LIVE_EVENTS_CAPACITY: usize = 256;
+const MAX_MESSAGE_BYTES: usize = 16 * 1024 * 1024;
+const MIN_RECONNECT: Duration = Duration::from_secs(1);
+const MAX_RECONNECT: Duration = Duration::from_secs(60);
+
+pub fn app_token_from_env() -> Option<String> {
+    std::env::var("SLACK_APP_TOKEN")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| v.starts_with("xapp-"))
+}
+
+pub fn live_events() -> LiveEvents {
+    tokio::sync::broadcast::channel(LIVE_EVENTS_CAPACITY).0
+}
+
+#[derive(Deserialize)]
+struct ConnectionsOpen {
+    ok: bool,
+    error: Option<String>,
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(rename = "type")]
+    kind: String,
+    envelope_id: Option<String>,
+    payload: Option<serde_json::Value>,
+}
+
+async fn connect(
+    http: &reqwest::Client,
+    app_token: &str,
+) -> anyhow::Result<WebSocketStream<reqwest::Upgraded>> {
+    let opened: ConnectionsOpen = http
+        .post(crate::slack_api("apps.connections.open"))
+        .bearer_auth(app_token)
+        .send()
+        .await?
+        .json()
+        .await?;
+    let url = match (opened.ok, opened.url) {
+        (true, Some(url)) => url,
+        _ => bail!(
+            "apps.connections.open failed: {}",
+            opened.error.as_deref().unwrap_or("unknown_error")
+        ),
+    };
+    let url = url
+        .strip_prefix("wss://")
+        .map(|rest| format!("https://{rest}"))
+        .context("socket mode url is not wss://")?;
+    let key: [u8; 16] = rand::random();
+    let resp = http
+        .get(url)
+        .version(reqwest::Version::HTTP_11)
+        .header(reqwest::header::CONNECTION, "Upgrade")
+        .header(reqwest::header::UPGRADE, "websocket")
+        .header(reqwest::header::SEC_WEBSOCKET_VERSION, "13")
+        .header(
+            reqwest::header::SEC_WEBSOCKET_KEY,
+            base64::engine::general_purpose::STANDARD.encode(key),
+        )
+        .send()
+        .await?;
+    if resp.status() != reqwest::StatusCode::SWITCHING_PROTOCOLS {
+        bail!("socket mode upgrade refused: HTTP {}", resp.status());
+    }
+    let config = WebSocketConfig::default().max_message_size(Some(MAX_MESSAGE_BYTES));
+    Ok(WebSocketStream::from_raw_socket(resp.upgrade().await?, Role::Client, Some(config)).await)
+}
+
+/// A new message in a channel, from an `events_api` payload.
+fn new_message(payload: &serde_json::Value) -> Option<(String, String)> {
+    let event = payload.get("event")?;
+    if event.get("type").and_then(|v| v.as_str()) != Some("message") {
+        return None;
+    }
+    let channel = event.get("channel")?.as_str()?;
+    let ts = event
+        .get("ts")
+        .or_else(|| event.get("event_ts"))?
+        .as_str()?;
+    Some((channel.to_string(), ts.to_string()))
+}
+
+/// Forward events until Slack asks to reconnect or the connection drops.
+async fn relay(
+    server: &SlackMcpServer,
+    events: &LiveEvents,
+    app_token: &str,
+) -> anyhow::Result<()> {
+    let mut stream = connect(&server.http, app_token).await?;
+    info!("connected to slack socket mode");
+    // Pings are answered by tungstenite itself.
+    while let Some(message) = stream.next().await {
+        let text = match message? {
+            Message::Text(text) => text,
+            Message::Close(_) => return Ok(()),
+            _ => continue,
+        };
+        let envelope: Envelope = match serde_json::from_str(&text) {
+            Ok(envelope) => envelope,
+            Err(err) => {
+                warn!(error = %err, "unreadable socket mode message");
+                continue;
+            }
+        };
+        // Every envelope must be acknowledged, or Slack retries it.
+        if let Some(id) = &envelope.envelope_id {
+            let ack = json!({ "envelope_id": id }).to_string();
+            stream.send(Message::Text(ack.into())).await?;
+        }
+        match envelope.kind.as_str() {
+            "disconnect" => {
+                debug!("slack asked to reconnect the socket");
+                let _ = stream.close(None).await;
+                return Ok(());
+            }
+            "events_api" => {
+                let Some((channel, ts)) = envelope.payload.as_ref().and_then(new_message) else {
+                    continue;
+                };
+                if server.channel_allowed(&channel) {
+                    // No receivers just means nothing is subscribed right now.
+                    let _ = events.send((channel, ts));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Keep a Socket Mode connection open until the process exits.
+pub async fn run(server: SlackMcpServer, app_token: String) {
+    let Some(events) = server.live_events.clone() else {
+        return;
+    };
+    let mut delay = MIN_RECONNECT;
+    loop {
+        match relay(&server, &events, &app_token).await {
+            Ok(()) => {
+                delay = MIN_RECONNECT;
+                tokio::time::sleep(delay).await;
+            }
+            Err(err) => {
+                warn!(error = %err, retry_in_secs = delay.as_secs(), "slack socket mode connection failed");
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RECONNECT);
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Per-tool call counts, errors and latency, plus the HTTP statuses and error codes Slack
+//! answered with, so operators can see which tools are hot and which are failing.
+//!
+//! Each call is logged at debug level; a summary of everything since startup is logged
+//! every `GRAIL_SLACK_METRICS_LOG_SECS` (default 300, 0 disables) when there was activity,
+//! and once more on shutdown. The server only speaks stdio, so there is no `/metrics`
+//! endpoint; the summary goes to stderr with the rest of the logs.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde_json::json;
+use tracing::{debug, info};
+
+const DEFAULT_LOG_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Upper bounds of the latency histogram buckets, in milliseconds; slower calls land in a
+/// final overflow bucket.
+const BUCKETS_MS: [u64; 8] = [50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+#[derive(Default)]
+struct ToolStats {
+    calls: u64,
+    errors: u64,
+    total_ms: u64,
+    max_ms: u64,
+    buckets: [u64; BUCKETS_MS.len() + 1],
+}
+
+#[derive(Default)]
+struct Counters {
+    tools: BTreeMap<String, ToolStats>,
+    http_statuses: BTreeMap<u16, u64>,
+    api_errors: BTreeMap<String, u64>,
+    /// Total calls at the last periodic summary, to skip idle intervals.
+    reported_calls: u64,
+}
+
+#[derive(Default)]
+pub struct Telemetry {
+    counters: Mutex<Counters>,
+}
+
+pub fn log_interval_from_env() -> Option<Duration> {
+    match std::env::var("GRAIL_SLACK_METRICS_LOG_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+    {
+        Some(0) => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None => Some(DEFAULT_LOG_INTERVAL),
+    }
+}
+
+impl Telemetry {
+    fn counters(&self) -> std::sync::MutexGuard<'_, Counters> {
+        self.counters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn record_call(&self, tool: &str, elapsed: Duration, ok: bool) {
+        let ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+        debug!(tool, elapsed_ms = ms, ok, "tool call");
+        let mut counters = self.counters();
+        let stats = counters.tools.entry(tool.to_string()).or_default();
+        stats.calls += 1;
+        if !ok {
+            stats.errors += 1;
+        }
+        stats.total_ms = stats.total_ms.saturating_add(ms);
+        stats.max_ms = stats.max_ms.max(ms);
+        let bucket = BUCKETS_MS
+            .iter()
+            .position(|b| ms <= *b)
+            .unwrap_or(BUCKETS_MS.len());
+        stats.buckets[bucket] += 1;
+    }
+
+    pub fn record_http_status(&self, status: u16) {
+        *self.counters().http_statuses.entry(status).or_default() += 1;
+    }
+
+    /// An `ok: false` answer, by its `error` code.
+    pub fn record_api_error(&self, code: &str) {
+        *self
+            .counters()
+            .api_errors
+            .entry(code.to_string())
+            .or_default() += 1;
+    }
+
+    pub fn summary(&self) -> serde_json::Value {
+        let counters = self.counters();
+        let tools: serde_json::Map<String, serde_json::Value> = counters
+            .tools
+            .iter()
+            .map(|(name, s)| {
+                let histogram: serde_json::Map<String, serde_json::Value> = s
+                    .buckets
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, n)| **n > 0)
+                    .map(|(i, n)| {
+                        let label = match BUCKETS_MS.get(i) {
+                            Some(b) => format!("le_{b}ms"),
+                            None => "slower".to_string(),
+                        };
+                        (label, json!(n))
+                    })
+                    .collect();
+                (
+                    name.clone(),
+                    json!({
+                        "calls": s.calls,
+                        "errors": s.errors,
+                        "avg_ms": s.total_ms / s.calls.max(1),
+                        "max_ms": s.max_ms,
+                        "latency": histogram,
+                    }),
+                )
+            })
+            .collect();
+        json!({
+            "tools": tools,
+            "http_statuses": counters.http_statuses,
+            "api_errors": counters.api_errors,
+        })
+    }
+
+    pub fn log_summary(&self) {
+        info!(metrics = %self.summary(), "slack tool metrics");
+    }
+
+    /// Log the summary every `every` until the process exits, skipping idle intervals.
+    pub async fn report_periodically(&self, every: Duration) {
+        loop {
+            tokio::time::sleep(every).await;
+            let active = {
+                let mut counters = self.counters();
+                let calls: u64 = counters.tools.values().map(|s| s.calls).sum();
+                let active = calls != counters.reported_calls;
+                counters.reported_calls = calls;
+                active
+            };
+            if active {
+                self.log_summary();
+            }
+        }
+    }
+}
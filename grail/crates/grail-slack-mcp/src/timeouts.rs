@@ -0,0 +1,124 @@
+//! How long Slack requests and tool calls may take, so a hung Slack request fails promptly
+//! instead of stalling the agent's whole turn.
+//!
+//! `GRAIL_SLACK_TIMEOUT_SECS` bounds each HTTP request to Slack (default 15). A whole tool
+//! call is allowed the rate-limit retry budget plus a few requests' worth on top, so a
+//! call waiting out `Retry-After` isn't cut off mid-retry. Paginating tools
+//! (`get_thread_full`, `get_users`) have no whole-call limit by default: each page is
+//! bounded by the request limit already. `GRAIL_SLACK_TOOL_TIMEOUTS` sets the whole-call
+//! limit per tool (`get_thread_full=60,download_file_text=30`). 0 means no limit.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use tracing::warn;
+
+use crate::allowlist;
+
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// Requests' worth of time a call gets on top of the retry budget.
+const CALL_REQUESTS: u32 = 4;
+/// Tools that page through results until done; a whole-call limit would cut them short.
+const PAGINATING_TOOLS: [&str; 2] = ["get_thread_full", "get_users"];
+
+pub struct Timeouts {
+    request: Option<Duration>,
+    call: Option<Duration>,
+    per_tool: HashMap<String, Option<Duration>>,
+}
+
+fn limit(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+impl Timeouts {
+    /// `retry_budget` is the longest a call may spend waiting out rate limits.
+    pub fn from_env(retry_budget: Duration) -> Self {
+        let request = match std::env::var("GRAIL_SLACK_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+        {
+            Some(secs) => limit(secs),
+            None => Some(DEFAULT_REQUEST_TIMEOUT),
+        };
+        let per_tool =
+            parse_per_tool(&std::env::var("GRAIL_SLACK_TOOL_TIMEOUTS").unwrap_or_default());
+        Self::new(request, retry_budget, per_tool)
+    }
+
+    fn new(
+        request: Option<Duration>,
+        retry_budget: Duration,
+        per_tool: HashMap<String, Option<Duration>>,
+    ) -> Self {
+        let call = request.map(|r| retry_budget + r * CALL_REQUESTS);
+        Self {
+            request,
+            call,
+            per_tool,
+        }
+    }
+
+    /// Limit for a single HTTP request to Slack.
+    pub fn request(&self) -> Option<Duration> {
+        self.request
+    }
+
+    /// Limit for a whole tool call, retries and rate-limit waits included.
+    pub fn for_tool(&self, tool: &str) -> Option<Duration> {
+        match self.per_tool.get(tool) {
+            Some(limit) => *limit,
+            None if PAGINATING_TOOLS.contains(&tool) => None,
+            None => self.call,
+        }
+    }
+}
+
+fn parse_per_tool(raw: &str) -> HashMap<String, Option<Duration>> {
+    let mut per_tool = HashMap::new();
+    for entry in allowlist::parse(raw) {
+        match entry
+            .split_once('=')
+            .and_then(|(tool, secs)| Some((tool, secs.parse::<u64>().ok()?)))
+        {
+            Some((tool, secs)) => {
+                per_tool.insert(tool.to_string(), limit(secs));
+            }
+            None => {
+                warn!(entry = %entry, "ignoring GRAIL_SLACK_TOOL_TIMEOUTS entry; expected tool=seconds")
+            }
+        }
+    }
+    per_tool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RETRY_BUDGET: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn call_budget_outlasts_rate_limit_retries() {
+        let t = Timeouts::new(Some(DEFAULT_REQUEST_TIMEOUT), RETRY_BUDGET, HashMap::new());
+        assert_eq!(t.request(), Some(DEFAULT_REQUEST_TIMEOUT));
+        let call = t.for_tool("get_channel_history").unwrap();
+        assert!(call > RETRY_BUDGET + DEFAULT_REQUEST_TIMEOUT);
+    }
+
+    #[test]
+    fn paginating_tools_have_no_call_limit_unless_configured() {
+        let per_tool = parse_per_tool("get_users=90, bogus, post_message=0");
+        let t = Timeouts::new(Some(DEFAULT_REQUEST_TIMEOUT), RETRY_BUDGET, per_tool);
+        assert_eq!(t.for_tool("get_thread_full"), None);
+        assert_eq!(t.for_tool("get_users"), Some(Duration::from_secs(90)));
+        assert_eq!(t.for_tool("post_message"), None);
+    }
+
+    #[test]
+    fn zero_request_timeout_disables_both_limits() {
+        let t = Timeouts::new(None, RETRY_BUDGET, HashMap::new());
+        assert_eq!(t.request(), None);
+        assert_eq!(t.for_tool("search_messages"), None);
+    }
+}
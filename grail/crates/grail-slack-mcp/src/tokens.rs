@@ -0,0 +1,176 @@
+//! The bot token, with optional Slack token rotation.
+//!
+//! Apps with token rotation turned on get access tokens that expire after 12 hours. When
+//! `SLACK_REFRESH_TOKEN`, `SLACK_CLIENT_ID` and `SLACK_CLIENT_SECRET` are set, the server
+//! exchanges the refresh token at `oauth.v2.access` shortly before the access token
+//! expires (or when Slack reports it expired) and keeps the new pair in memory.
+//! `SLACK_BOT_TOKEN` is then optional and only used until the first refresh.
+
+use std::time::{Duration, Instant};
+
+use rmcp::ErrorData as McpError;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::Mutex;
+use tracing::info;
+
+/// Refresh this long before the access token expires.
+const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+struct Rotation {
+    client_id: String,
+    client_secret: String,
+}
+
+struct Current {
+    access_token: Option<String>,
+    /// `None` for a token we didn't mint (the initial `SLACK_BOT_TOKEN`).
+    expires_at: Option<Instant>,
+    refresh_token: String,
+}
+
+pub struct BotToken {
+    static_token: Option<String>,
+    rotation: Option<Rotation>,
+    current: Mutex<Current>,
+}
+
+#[derive(Deserialize)]
+struct RefreshResponse {
+    ok: bool,
+    error: Option<String>,
+    access_token: Option<String>,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+}
+
+fn env(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Slack's answer when an access token has run out (or was revoked by a newer one).
+pub fn is_expired_error(err: &McpError) -> bool {
+    ["token_expired", "invalid_auth"]
+        .iter()
+        .any(|e| err.message.contains(e))
+}
+
+impl BotToken {
+    pub fn from_env() -> Self {
+        let static_token = env("SLACK_BOT_TOKEN");
+        let (rotation, refresh_token) = match (
+            env("SLACK_REFRESH_TOKEN"),
+            env("SLACK_CLIENT_ID"),
+            env("SLACK_CLIENT_SECRET"),
+        ) {
+            (Some(refresh_token), Some(client_id), Some(client_secret)) => (
+                Some(Rotation {
+                    client_id,
+                    client_secret,
+                }),
+                refresh_token,
+            ),
+            _ => (None, String::new()),
+        };
+        Self {
+            current: Mutex::new(Current {
+                access_token: static_token.clone(),
+                expires_at: None,
+                refresh_token,
+            }),
+            static_token,
+            rotation,
+        }
+    }
+
+    /// A token that is used as is, for one of the `GRAIL_SLACK_WORKSPACE_TOKENS` workspaces.
+    pub fn fixed(token: String) -> Self {
+        Self {
+            current: Mutex::new(Current {
+                access_token: Some(token.clone()),
+                expires_at: None,
+                refresh_token: String::new(),
+            }),
+            static_token: Some(token),
+            rotation: None,
+        }
+    }
+
+    pub fn rotates(&self) -> bool {
+        self.rotation.is_some()
+    }
+
+    pub async fn get(&self, http: &reqwest::Client) -> Result<String, McpError> {
+        let Some(rotation) = &self.rotation else {
+            return self.static_token.clone().ok_or_else(|| {
+                McpError::invalid_params("missing SLACK_BOT_TOKEN env var", Some(json!({})))
+            });
+        };
+        let mut current = self.current.lock().await;
+        let fresh = current
+            .expires_at
+            .is_none_or(|at| Instant::now() + REFRESH_MARGIN < at);
+        if let (Some(token), true) = (&current.access_token, fresh) {
+            return Ok(token.clone());
+        }
+        refresh(http, rotation, &mut current).await?;
+        current
+            .access_token
+            .clone()
+            .ok_or_else(|| McpError::internal_error("slack token refresh returned no token", None))
+    }
+
+    /// Drop `stale` so the next [`BotToken::get`] refreshes, unless another call already
+    /// replaced it.
+    pub async fn invalidate(&self, stale: &str) {
+        let mut current = self.current.lock().await;
+        if current.access_token.as_deref() == Some(stale) {
+            current.access_token = None;
+        }
+    }
+}
+
+async fn refresh(
+    http: &reqwest::Client,
+    rotation: &Rotation,
+    current: &mut Current,
+) -> Result<(), McpError> {
+    let resp: RefreshResponse = http
+        .post(crate::slack_api("oauth.v2.access"))
+        .form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", current.refresh_token.as_str()),
+            ("client_id", rotation.client_id.as_str()),
+            ("client_secret", rotation.client_secret.as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?
+        .json()
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+    let access_token = match (resp.ok, resp.access_token) {
+        (true, Some(token)) => token,
+        _ => {
+            return Err(McpError::internal_error(
+                format!(
+                    "slack token refresh failed: {}",
+                    resp.error.as_deref().unwrap_or("unknown_error")
+                ),
+                None,
+            ))
+        }
+    };
+    let expires_in = resp.expires_in.unwrap_or(12 * 60 * 60);
+    info!(expires_in, "refreshed the slack bot token");
+    current.access_token = Some(access_token);
+    current.expires_at = Some(Instant::now() + Duration::from_secs(expires_in));
+    // Refresh tokens are single-use when rotation is on; keep the new one.
+    if let Some(refresh_token) = resp.refresh_token.filter(|t| !t.is_empty()) {
+        current.refresh_token = refresh_token;
+    }
+    Ok(())
+}
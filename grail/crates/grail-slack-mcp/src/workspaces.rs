@@ -0,0 +1,39 @@
+//! Bot tokens for more than one workspace, so a single server can serve several Slack
+//! workspaces instead of one process each.
+//!
+//! `GRAIL_SLACK_WORKSPACE_TOKENS` is a JSON object of team id → bot token
+//! (`{"T0123": "xoxb-…"}`); `GRAIL_SLACK_WORKSPACE_TOKENS_FILE` names a file with the same
+//! object instead. Tools then take a `workspace` argument picking the token; without it they
+//! use `SLACK_BOT_TOKEN` as before. The file is only read at startup.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+
+fn env(key: &str) -> Option<String> {
+    std::env::var(key)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+pub fn from_env() -> anyhow::Result<HashMap<String, String>> {
+    let (raw, source) = match env("GRAIL_SLACK_WORKSPACE_TOKENS_FILE") {
+        Some(path) => (
+            std::fs::read_to_string(&path)
+                .with_context(|| format!("read GRAIL_SLACK_WORKSPACE_TOKENS_FILE {path}"))?,
+            "GRAIL_SLACK_WORKSPACE_TOKENS_FILE",
+        ),
+        None => match env("GRAIL_SLACK_WORKSPACE_TOKENS") {
+            Some(raw) => (raw, "GRAIL_SLACK_WORKSPACE_TOKENS"),
+            None => return Ok(HashMap::new()),
+        },
+    };
+    let tokens: HashMap<String, String> = serde_json::from_str(&raw)
+        .with_context(|| format!("parse {source} as a JSON object of team id to bot token"))?;
+    Ok(tokens
+        .into_iter()
+        .map(|(team, token)| (team.trim().to_string(), token.trim().to_string()))
+        .filter(|(team, token)| !team.is_empty() && !token.is_empty())
+        .collect())
+}
@@ -0,0 +1,457 @@
+//! An in-process mock of the Slack Web API methods the tools call, serving one small fixed
+//! workspace: channels `C1` (#general, with a canvas), `C2` (#eng-infra, public), `C3`
+//! (#secret, private) and `C4` (#ops, private), users `U1` (alice) and `U2` (bob), a bot,
+//! a text file `F1` and the canvas `F_CANVAS`. The bot is a member of `C1` and `C4` until
+//! it joins `C2`. Search, which runs with the user token, also finds messages and files in
+//! the user's DMs and in `C3`.
+//!
+//! Every call is recorded with its parameters (query string and JSON or form body merged)
+//! and token, so tests can check what the server sent. Tests can make the next calls to a
+//! method fail ([`MockSlack::fail_next`]) and slow every answer down
+//! ([`MockSlack::set_delay`]) to see how the server copes.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{any, get};
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+pub const PARENT_TS: &str = "1700000000.000100";
+pub const SECOND_TS: &str = "1700000001.000200";
+pub const REPLY_TS: &str = "1700000002.000300";
+pub const FILE_TEXT: &str = "hello from a file\n";
+
+#[derive(Clone, Debug)]
+pub struct Call {
+    pub method: String,
+    pub params: serde_json::Map<String, Value>,
+    pub token: Option<String>,
+}
+
+/// How a call the test set up to fail fails.
+#[derive(Clone, Copy, Debug)]
+pub enum Fault {
+    /// HTTP 429 with this `Retry-After` (seconds).
+    RateLimited(u64),
+    /// HTTP 503.
+    Unavailable,
+    /// `{"ok": false, "error": …}`.
+    Error(&'static str),
+}
+
+#[derive(Clone, Default)]
+struct Shared {
+    calls: Arc<Mutex<Vec<Call>>>,
+    faults: Arc<Mutex<HashMap<String, VecDeque<Fault>>>>,
+    delay: Arc<Mutex<Duration>>,
+    in_flight: Arc<AtomicUsize>,
+    max_in_flight: Arc<AtomicUsize>,
+}
+
+#[derive(Clone)]
+struct Mock {
+    base: String,
+    shared: Shared,
+    /// Channels the bot is in.
+    joined: Arc<Mutex<HashSet<String>>>,
+}
+
+pub struct MockSlack {
+    /// What `GRAIL_SLACK_API_BASE` should be set to.
+    pub api_base: String,
+    shared: Shared,
+}
+
+impl MockSlack {
+    pub async fn start() -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock slack");
+        let base = format!("http://{}", listener.local_addr().expect("mock address"));
+        let shared = Shared::default();
+        let mock = Mock {
+            base: base.clone(),
+            shared: shared.clone(),
+            joined: Arc::new(Mutex::new(HashSet::from([
+                "C1".to_string(),
+                "C4".to_string(),
+            ]))),
+        };
+        let app = Router::new()
+            .route("/api/{method}", any(api))
+            .route("/files/{id}", get(download))
+            .with_state(mock);
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("serve mock slack");
+        });
+        Self {
+            api_base: format!("{base}/api"),
+            shared,
+        }
+    }
+
+    pub fn calls(&self) -> Vec<Call> {
+        self.shared.calls.lock().unwrap().clone()
+    }
+
+    /// Fail the next call to `method` with `fault`; queued faults apply in order.
+    pub fn fail_next(&self, method: &str, fault: Fault) {
+        self.shared
+            .faults
+            .lock()
+            .unwrap()
+            .entry(method.to_string())
+            .or_default()
+            .push_back(fault);
+    }
+
+    /// Wait this long before answering any API call.
+    pub fn set_delay(&self, delay: Duration) {
+        *self.shared.delay.lock().unwrap() = delay;
+    }
+
+    /// The most API calls that were being answered at once.
+    pub fn max_in_flight(&self) -> usize {
+        self.shared.max_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// The calls made to one Slack method.
+    pub fn calls_to(&self, method: &str) -> Vec<Call> {
+        self.calls()
+            .into_iter()
+            .filter(|c| c.method == method)
+            .collect()
+    }
+}
+
+fn user(id: &str, name: &str, display: &str) -> Value {
+    json!({
+        "id": id,
+        "name": name,
+        "real_name": display,
+        "deleted": false,
+        "is_bot": false,
+        "tz": "Europe/Berlin",
+        "profile": { "display_name": display, "real_name": display, "title": "Engineer" },
+    })
+}
+
+fn parent_message() -> Value {
+    json!({
+        "type": "message",
+        "user": "U1",
+        "text": "deploy is done <@U2>",
+        "ts": PARENT_TS,
+        "thread_ts": PARENT_TS,
+        "reply_count": 1,
+        "blocks": [{ "type": "section", "text": { "type": "mrkdwn", "text": "deploy is done" } }],
+    })
+}
+
+fn second_message() -> Value {
+    json!({ "type": "message", "user": "U2", "text": "thanks", "ts": SECOND_TS })
+}
+
+fn reply_message() -> Value {
+    json!({
+        "type": "message",
+        "user": "U2",
+        "text": "looks good",
+        "ts": REPLY_TS,
+        "thread_ts": PARENT_TS,
+    })
+}
+
+/// A search match in `channel` that only the user token can see.
+fn search_match(channel: Value) -> Value {
+    json!({ "channel": channel, "user": "U2", "text": "deploy password", "ts": SECOND_TS })
+}
+
+fn file(base: &str, id: &str) -> Option<Value> {
+    match id {
+        "F1" => Some(json!({
+            "id": "F1",
+            "name": "notes.txt",
+            "title": "notes.txt",
+            "filetype": "text",
+            "mimetype": "text/plain",
+            "size": FILE_TEXT.len(),
+            "channels": ["C1"],
+            "url_private_download": format!("{base}/files/F1"),
+        })),
+        "F_CANVAS" => Some(json!({
+            "id": "F_CANVAS",
+            "name": "Plan",
+            "title": "Plan",
+            "filetype": "canvas",
+            "mimetype": "application/vnd.slack-docs",
+            "channels": ["C1"],
+            "url_private": format!("{base}/files/F_CANVAS"),
+        })),
+        _ => None,
+    }
+}
+
+fn channel(id: &str) -> Option<Value> {
+    let (name, is_private) = match id {
+        "C1" => ("general", false),
+        "C2" => ("eng-infra", false),
+        "C3" => ("secret", true),
+        "C4" => ("ops", true),
+        _ => return None,
+    };
+    let mut channel = json!({
+        "id": id,
+        "name": name,
+        "topic": { "value": "" },
+        "purpose": { "value": "" },
+        "num_members": 2,
+        "is_private": is_private,
+        "is_archived": false,
+        "created": 1600000000,
+        "creator": "U1",
+    });
+    if id == "C1" {
+        channel["topic"]["value"] = json!("Deploys and chatter");
+        channel["purpose"]["value"] = json!("Company-wide");
+        channel["properties"] = json!({ "canvas": { "file_id": "F_CANVAS" } });
+    }
+    Some(channel)
+}
+
+fn answer(
+    mock: &Mock,
+    method: &str,
+    params: &serde_json::Map<String, Value>,
+    token: Option<&str>,
+) -> Value {
+    let base = mock.base.as_str();
+    let param = |k: &str| params.get(k).and_then(|v| v.as_str()).unwrap_or_default();
+    let no_more = json!({ "next_cursor": "" });
+    let member = mock.joined.lock().unwrap().contains(param("channel"));
+    match method {
+        "conversations.history" | "conversations.replies" if !member => {
+            json!({ "ok": false, "error": "not_in_channel" })
+        }
+        "conversations.join" => match channel(param("channel")) {
+            Some(c) if c["is_private"] == true => {
+                json!({ "ok": false, "error": "method_not_supported_for_channel_type" })
+            }
+            Some(c) => {
+                mock.joined
+                    .lock()
+                    .unwrap()
+                    .insert(param("channel").to_string());
+                json!({ "channel": c })
+            }
+            None => json!({ "ok": false, "error": "channel_not_found" }),
+        },
+        "auth.test" if token == Some("xoxp-test") => json!({ "team_id": "T1", "user_id": "U1" }),
+        "auth.test" => json!({ "team_id": "T1", "user_id": "UBOT" }),
+        "oauth.v2.access" => json!({
+            "access_token": "xoxb-rotated",
+            "refresh_token": "xoxe-2",
+            "expires_in": 43200,
+        }),
+        "conversations.history" => json!({
+            "messages": [second_message(), parent_message()],
+            "has_more": false,
+            "response_metadata": no_more,
+        }),
+        "conversations.replies" => json!({
+            "messages": [parent_message(), reply_message()],
+            "has_more": false,
+            "response_metadata": no_more,
+        }),
+        "conversations.info" => match channel(param("channel")) {
+            Some(mut c) => {
+                c["is_member"] = json!(member);
+                json!({ "channel": c })
+            }
+            None => json!({ "ok": false, "error": "channel_not_found" }),
+        },
+        "conversations.list" => json!({
+            "channels": [
+                { "id": "C1", "name": "general", "is_private": false },
+                { "id": "C2", "name": "eng-infra", "is_private": false },
+            ],
+            "response_metadata": no_more,
+        }),
+        "conversations.members" => json!({ "members": ["U1", "U2"], "response_metadata": no_more }),
+        "conversations.open" => json!({ "channel": { "id": "D1" } }),
+        "chat.getPermalink" => json!({
+            "permalink": format!(
+                "https://acme.slack.com/archives/{}/p{}",
+                param("channel"),
+                param("message_ts").replace('.', "")
+            ),
+        }),
+        "chat.postMessage" => json!({ "channel": param("channel"), "ts": "1700000100.000100" }),
+        "chat.scheduleMessage" => json!({
+            "channel": param("channel"),
+            "scheduled_message_id": "Q1",
+            "post_at": params.get("post_at"),
+        }),
+        "chat.scheduledMessages.list" => json!({
+            "scheduled_messages": [
+                { "id": "Q1", "channel_id": "C1", "post_at": 1900000000, "text": "later" },
+            ],
+            "response_metadata": no_more,
+        }),
+        "reactions.get" => json!({ "message": {
+            "text": "ship it?",
+            "ts": PARENT_TS,
+            "reactions": [
+                { "name": "white_check_mark", "users": ["U1", "U2"], "count": 2 },
+                { "name": "x", "users": ["U2"], "count": 1 },
+                { "name": "+1::skin-tone-2", "users": ["U1"], "count": 1 },
+            ],
+        }}),
+        "reactions.add" => json!({}),
+        "emoji.list" => json!({ "emoji": {
+            "party_parrot": "https://emoji.example/party_parrot.gif",
+            "pp": "alias:party_parrot",
+        }}),
+        "users.info" => match param("user") {
+            "U1" => json!({ "user": user("U1", "alice", "Alice") }),
+            "U2" => json!({ "user": user("U2", "bob", "Bob") }),
+            "UBOT" => {
+                json!({ "user": { "id": "UBOT", "name": "grail", "is_bot": true, "deleted": false, "profile": {} } })
+            }
+            _ => json!({ "ok": false, "error": "user_not_found" }),
+        },
+        "users.list" => json!({
+            "members": [
+                user("U1", "alice", "Alice"),
+                user("U2", "bob", "Bob"),
+                { "id": "UBOT", "name": "grail", "is_bot": true, "deleted": false, "profile": {} },
+            ],
+            "response_metadata": no_more,
+        }),
+        "usergroups.list" => json!({ "usergroups": [
+            { "id": "S1", "handle": "oncall", "name": "On-call", "user_count": 2 },
+        ]}),
+        "usergroups.users.list" => json!({ "users": ["U1", "U2"] }),
+        "pins.list" => json!({ "items": [
+            { "type": "message", "channel": "C1", "message": parent_message() },
+        ]}),
+        "bookmarks.list" => json!({ "bookmarks": [
+            { "id": "Bk1", "title": "Runbook", "link": "https://docs.example/runbook", "type": "link" },
+        ]}),
+        "search.messages" => json!({ "messages": {
+            "matches": [
+                {
+                    "channel": { "id": "C1", "name": "general" },
+                    "user": "U1",
+                    "text": "deploy is done",
+                    "ts": PARENT_TS,
+                    "permalink": "https://acme.slack.com/archives/C1/p1700000000000100",
+                },
+                search_match(json!({ "id": "D9", "is_im": true })),
+                search_match(json!({ "id": "G5", "is_mpim": true, "is_private": true })),
+                search_match(json!({ "id": "C3", "name": "secret", "is_private": true })),
+                search_match(json!({ "id": "C4", "name": "ops", "is_private": true })),
+            ],
+            "total": 5,
+        }}),
+        "search.files" => json!({ "files": { "matches": [
+            file(base, "F1"),
+            { "id": "F_DM", "name": "dm.txt", "ims": ["D9"] },
+            { "id": "F_SECRET", "name": "secret.txt", "groups": ["C3"] },
+            { "id": "F_OPS", "name": "ops.txt", "groups": ["C4"], "ims": ["D9"] },
+        ] } }),
+        "files.info" => match file(base, param("file")) {
+            Some(file) => json!({ "file": file }),
+            None => json!({ "ok": false, "error": "file_not_found" }),
+        },
+        "reminders.add" => json!({ "reminder": {
+            "id": "Rm1",
+            "text": param("text"),
+            "time": 1900000000,
+            "user": "U1",
+        }}),
+        "reminders.list" => json!({ "reminders": [
+            { "id": "Rm1", "text": "standup", "time": 1900000000, "user": "U1" },
+        ]}),
+        _ => json!({ "ok": false, "error": "unknown_method" }),
+    }
+}
+
+async fn api(
+    State(mock): State<Mock>,
+    Path(method): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let mut params: serde_json::Map<String, Value> = query
+        .into_iter()
+        .map(|(k, v)| (k, Value::String(v)))
+        .collect();
+    match serde_json::from_slice::<Value>(&body) {
+        Ok(Value::Object(fields)) => params.extend(fields),
+        // `oauth.v2.access` takes a form; nothing the tests send needs decoding.
+        _ => params.extend(
+            String::from_utf8_lossy(&body)
+                .split('&')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(k, v)| (k.to_string(), Value::String(v.to_string()))),
+        ),
+    }
+    let token = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let shared = &mock.shared;
+    let in_flight = shared.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+    shared.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+    let delay = *shared.delay.lock().unwrap();
+    tokio::time::sleep(delay).await;
+    shared.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+    let fault = shared
+        .faults
+        .lock()
+        .unwrap()
+        .get_mut(&method)
+        .and_then(VecDeque::pop_front);
+    let reply = match fault {
+        Some(Fault::RateLimited(secs)) => (
+            StatusCode::TOO_MANY_REQUESTS,
+            [("retry-after", secs.to_string())],
+        )
+            .into_response(),
+        Some(Fault::Unavailable) => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        Some(Fault::Error(code)) => Json(json!({ "ok": false, "error": code })).into_response(),
+        None => {
+            let mut reply = answer(&mock, &method, &params, token.as_deref());
+            if let Some(obj) = reply.as_object_mut() {
+                obj.entry("ok").or_insert(json!(true));
+            }
+            Json(reply).into_response()
+        }
+    };
+    shared.calls.lock().unwrap().push(Call {
+        method,
+        params,
+        token,
+    });
+    reply
+}
+
+async fn download(Path(id): Path<String>) -> String {
+    match id.as_str() {
+        "F1" => FILE_TEXT.to_string(),
+        "F_CANVAS" => "<h1>Plan</h1><p>Ship on <b>Friday</b>.</p><ul><li>one</li><li>two</li></ul>"
+            .to_string(),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,1011 @@
+//! Every tool, end to end: the real binary over stdio, talking to the mock Slack in
+//! `mock_slack`. Run with `cargo test -p grail-slack-mcp --features test-server`.
+
+#![cfg(feature = "test-server")]
+
+mod mock_slack;
+
+use std::process::Stdio;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+use mock_slack::{Fault, MockSlack, FILE_TEXT, PARENT_TS, REPLY_TS, SECOND_TS};
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A `grail-slack-mcp` process pointed at the mock, with write tools on.
+struct McpClient {
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    next_id: u64,
+}
+
+impl McpClient {
+    async fn start(mock: &MockSlack, extra_env: &[(&str, &str)]) -> Self {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_grail-slack-mcp"));
+        command
+            .env_clear()
+            .env("SLACK_BOT_TOKEN", "xoxb-test")
+            .env("SLACK_USER_TOKEN", "xoxp-test")
+            .env("GRAIL_SLACK_API_BASE", &mock.api_base)
+            .env("GRAIL_SLACK_ALLOW_WRITES", "1")
+            .env("GRAIL_SLACK_METRICS_LOG_SECS", "0")
+            .envs(extra_env.iter().copied())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        let mut child = command.spawn().expect("spawn grail-slack-mcp");
+        let stdin = child.stdin.take().expect("child stdin");
+        let stdout = BufReader::new(child.stdout.take().expect("child stdout")).lines();
+        let mut client = Self {
+            _child: child,
+            stdin,
+            stdout,
+            next_id: 1,
+        };
+        client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": "2025-03-26",
+                    "capabilities": {},
+                    "clientInfo": { "name": "grail-slack-mcp-tests", "version": "0" },
+                }),
+            )
+            .await
+            .expect("initialize");
+        client
+            .send(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await;
+        client
+    }
+
+    async fn send(&mut self, message: Value) {
+        let mut line = message.to_string();
+        line.push('\n');
+        self.stdin
+            .write_all(line.as_bytes())
+            .await
+            .expect("write to server");
+        self.stdin.flush().await.expect("flush to server");
+    }
+
+    /// The result of a JSON-RPC request, or its error object.
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value, Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await;
+        loop {
+            let line = tokio::time::timeout(REPLY_TIMEOUT, self.stdout.next_line())
+                .await
+                .expect("server reply timed out")
+                .expect("read from server")
+                .expect("server closed stdout");
+            let message: Value = serde_json::from_str(&line).expect("server wrote JSON");
+            // Skip notifications and anything else that isn't our reply.
+            if message.get("id") != Some(&json!(id)) {
+                continue;
+            }
+            return match message.get("error") {
+                Some(error) => Err(error.clone()),
+                None => Ok(message["result"].clone()),
+            };
+        }
+    }
+
+    /// A successful tool call's structured content.
+    async fn call(&mut self, tool: &str, arguments: Value) -> Value {
+        let result = self
+            .request(
+                "tools/call",
+                json!({ "name": tool, "arguments": arguments }),
+            )
+            .await
+            .unwrap_or_else(|err| panic!("{tool} failed: {err}"));
+        assert_eq!(result["isError"], json!(false), "{tool}: {result}");
+        result["structuredContent"].clone()
+    }
+
+    /// Several tool calls sent at once; their structured content in the same order.
+    async fn call_all(&mut self, calls: &[(&str, Value)]) -> Vec<Value> {
+        let first = self.next_id;
+        for (tool, arguments) in calls {
+            let id = self.next_id;
+            self.next_id += 1;
+            self.send(json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "tools/call",
+                "params": { "name": tool, "arguments": arguments },
+            }))
+            .await;
+        }
+        let mut results = vec![Value::Null; calls.len()];
+        let mut pending = calls.len();
+        while pending > 0 {
+            let line = tokio::time::timeout(REPLY_TIMEOUT, self.stdout.next_line())
+                .await
+                .expect("server reply timed out")
+                .expect("read from server")
+                .expect("server closed stdout");
+            let message: Value = serde_json::from_str(&line).expect("server wrote JSON");
+            let Some(slot) = message
+                .get("id")
+                .and_then(Value::as_u64)
+                .and_then(|id| id.checked_sub(first))
+                .and_then(|i| results.get_mut(i as usize))
+            else {
+                continue;
+            };
+            assert_eq!(message["result"]["isError"], json!(false), "{message}");
+            *slot = message["result"]["structuredContent"].clone();
+            pending -= 1;
+        }
+        results
+    }
+
+    /// A failed tool call's error message.
+    async fn call_err(&mut self, tool: &str, arguments: Value) -> String {
+        match self
+            .request(
+                "tools/call",
+                json!({ "name": tool, "arguments": arguments }),
+            )
+            .await
+        {
+            Ok(result) => panic!("{tool} unexpectedly succeeded: {result}"),
+            Err(err) => err["message"].as_str().unwrap_or_default().to_string(),
+        }
+    }
+}
+
+async fn setup() -> (MockSlack, McpClient) {
+    let mock = MockSlack::start().await;
+    let client = McpClient::start(&mock, &[]).await;
+    (mock, client)
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn param<'a>(call: &'a mock_slack::Call, key: &str) -> Option<&'a str> {
+    call.params.get(key).and_then(|v| v.as_str())
+}
+
+#[tokio::test]
+async fn lists_every_tool() {
+    let (_mock, mut client) = setup().await;
+    let listed = client.request("tools/list", json!({})).await.unwrap();
+    let mut names: Vec<&str> = listed["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|t| t["name"].as_str())
+        .collect();
+    names.sort_unstable();
+    assert_eq!(
+        names,
+        [
+            "add_reaction",
+            "add_reminder",
+            "download_file_text",
+            "get_canvas",
+            "get_channel_history",
+            "get_channel_info",
+            "get_file_info",
+            "get_message_reactions",
+            "get_permalink",
+            "get_thread",
+            "get_thread_full",
+            "get_user",
+            "get_usergroup",
+            "get_users",
+            "list_bookmarks",
+            "list_channel_members",
+            "list_channels",
+            "list_emoji",
+            "list_pins",
+            "list_reactions",
+            "list_reminders",
+            "list_scheduled_messages",
+            "list_usergroups",
+            "list_users",
+            "open_dm",
+            "post_message",
+            "resolve_channel",
+            "resolve_permalink",
+            "schedule_message",
+            "search_files",
+            "search_messages",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn get_channel_history() {
+    let (mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "get_channel_history",
+            json!({ "channel": "C1", "limit": 5, "resolve_entities": true }),
+        )
+        .await;
+    assert_eq!(out["channel"], "C1");
+    let messages = out["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0]["ts"], SECOND_TS);
+    assert_eq!(messages[1]["user_name"], "Alice");
+    assert_eq!(out["has_more"], false);
+    assert_eq!(out["truncated"], false);
+
+    let call = &mock.calls_to("conversations.history")[0];
+    assert_eq!(param(call, "channel"), Some("C1"));
+    assert_eq!(param(call, "limit"), Some("5"));
+    assert_eq!(call.token.as_deref(), Some("xoxb-test"));
+}
+
+#[tokio::test]
+async fn get_thread() {
+    let (mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "get_thread",
+            json!({ "channel": "C1", "thread_ts": PARENT_TS }),
+        )
+        .await;
+    assert_eq!(out["thread_ts"], PARENT_TS);
+    assert_eq!(out["messages"][1]["ts"], REPLY_TS);
+    assert_eq!(
+        param(&mock.calls_to("conversations.replies")[0], "ts"),
+        Some(PARENT_TS)
+    );
+}
+
+#[tokio::test]
+async fn get_thread_full() {
+    let (_mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "get_thread_full",
+            json!({ "channel": "C1", "thread_ts": PARENT_TS, "flatten_blocks": true, "max_chars": 5 }),
+        )
+        .await;
+    let messages = out["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(out["truncated"], true);
+    assert!(messages[0].get("blocks").is_none());
+}
+
+#[tokio::test]
+async fn get_permalink_is_cached() {
+    let (mock, mut client) = setup().await;
+    for _ in 0..2 {
+        let out = client
+            .call(
+                "get_permalink",
+                json!({ "channel": "C1", "message_ts": PARENT_TS }),
+            )
+            .await;
+        assert_eq!(
+            out["permalink"],
+            "https://acme.slack.com/archives/C1/p1700000000000100"
+        );
+    }
+    assert_eq!(mock.calls_to("chat.getPermalink").len(), 1);
+}
+
+#[tokio::test]
+async fn resolve_permalink() {
+    let (mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "resolve_permalink",
+            json!({ "url": "https://acme.slack.com/archives/C1/p1700000002000300?thread_ts=1700000000.000100" }),
+        )
+        .await;
+    assert_eq!(out["workspace"], "acme");
+    assert_eq!(out["channel"], "C1");
+    assert_eq!(out["message_ts"], REPLY_TS);
+    assert_eq!(out["thread_ts"], PARENT_TS);
+    assert_eq!(out["is_reply"], true);
+    // Parsed locally.
+    assert!(mock.calls().is_empty());
+}
+
+#[tokio::test]
+async fn get_channel_info() {
+    let (_mock, mut client) = setup().await;
+    let out = client
+        .call("get_channel_info", json!({ "channel": "C1" }))
+        .await;
+    assert_eq!(out["name"], "general");
+    assert_eq!(out["topic"], "Deploys and chatter");
+    assert_eq!(out["num_members"], 2);
+}
+
+#[tokio::test]
+async fn list_channel_members() {
+    let (_mock, mut client) = setup().await;
+    let out = client
+        .call("list_channel_members", json!({ "channel": "C1" }))
+        .await;
+    assert_eq!(out["members"], json!(["U1", "U2"]));
+    assert_eq!(out["has_more"], false);
+}
+
+#[tokio::test]
+async fn list_pins() {
+    let (_mock, mut client) = setup().await;
+    let out = client.call("list_pins", json!({ "channel": "C1" })).await;
+    assert_eq!(out["pins"][0]["type"], "message");
+    assert_eq!(out["pins"][0]["message"]["ts"], PARENT_TS);
+}
+
+#[tokio::test]
+async fn list_bookmarks() {
+    let (mock, mut client) = setup().await;
+    let out = client
+        .call("list_bookmarks", json!({ "channel": "C1" }))
+        .await;
+    assert_eq!(out["bookmarks"][0]["title"], "Runbook");
+    assert_eq!(
+        param(&mock.calls_to("bookmarks.list")[0], "channel_id"),
+        Some("C1")
+    );
+}
+
+#[tokio::test]
+async fn get_user() {
+    let (_mock, mut client) = setup().await;
+    let out = client.call("get_user", json!({ "user_id": "U1" })).await;
+    assert_eq!(out["user"]["name"], "alice");
+}
+
+#[tokio::test]
+async fn get_users() {
+    let (_mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "get_users",
+            json!({ "user_ids": ["U1", "U2", "U1", "U404"] }),
+        )
+        .await;
+    let users = out["users"].as_array().unwrap();
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[1]["display_name"], "Bob");
+    assert_eq!(out["errors"][0]["user_id"], "U404");
+}
+
+#[tokio::test]
+async fn list_usergroups() {
+    let (_mock, mut client) = setup().await;
+    let out = client.call("list_usergroups", json!({})).await;
+    assert_eq!(out["usergroups"][0]["handle"], "oncall");
+}
+
+#[tokio::test]
+async fn get_usergroup() {
+    let (mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "get_usergroup",
+            json!({ "usergroup": "@oncall", "include_profiles": true }),
+        )
+        .await;
+    assert_eq!(out["usergroup"]["id"], "S1");
+    assert_eq!(out["members"][0]["name"], "alice");
+    assert_eq!(
+        param(&mock.calls_to("usergroups.users.list")[0], "usergroup"),
+        Some("S1")
+    );
+}
+
+#[tokio::test]
+async fn list_users() {
+    let (_mock, mut client) = setup().await;
+    let out = client.call("list_users", json!({})).await;
+    let names: Vec<&str> = out["users"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|u| u["name"].as_str())
+        .collect();
+    // The bot is left out by default.
+    assert_eq!(names, ["alice", "bob"]);
+}
+
+#[tokio::test]
+async fn list_channels() {
+    let (_mock, mut client) = setup().await;
+    let out = client.call("list_channels", json!({})).await;
+    assert_eq!(out["channels"].as_array().unwrap().len(), 2);
+    assert_eq!(out["has_more"], false);
+}
+
+#[tokio::test]
+async fn resolve_channel() {
+    let (_mock, mut client) = setup().await;
+    let out = client
+        .call("resolve_channel", json!({ "name": "#eng-infra" }))
+        .await;
+    assert_eq!(out["channel_id"], "C2");
+}
+
+#[tokio::test]
+async fn search_messages() {
+    let (mock, mut client) = setup().await;
+    let out = client
+        .call("search_messages", json!({ "query": "deploy" }))
+        .await;
+    assert_eq!(out["matches"][0]["ts"], PARENT_TS);
+    // The user token's DMs, group DMs and private channels the bot isn't in are dropped.
+    let channels: Vec<&str> = out["matches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|m| m["channel"]["id"].as_str())
+        .collect();
+    assert_eq!(channels, ["C1", "C4"]);
+    // Search goes out with the user token.
+    let call = &mock.calls_to("search.messages")[0];
+    assert_eq!(call.token.as_deref(), Some("xoxp-test"));
+    assert_eq!(param(call, "query"), Some("deploy"));
+}
+
+#[tokio::test]
+async fn search_files() {
+    let (_mock, mut client) = setup().await;
+    let out = client
+        .call("search_files", json!({ "query": "notes" }))
+        .await;
+    let ids: Vec<&str> = out["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|f| f["id"].as_str())
+        .collect();
+    assert_eq!(ids, ["F1", "F_OPS"]);
+}
+
+#[tokio::test]
+async fn post_message() {
+    let (mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "post_message",
+            json!({ "channel": "C1", "thread_ts": PARENT_TS, "text": "on it" }),
+        )
+        .await;
+    assert_eq!(out["channel"], "C1");
+    assert_eq!(out["thread_ts"], PARENT_TS);
+    let call = &mock.calls_to("chat.postMessage")[0];
+    assert_eq!(param(call, "text"), Some("on it"));
+}
+
+#[tokio::test]
+async fn schedule_message() {
+    let (mock, mut client) = setup().await;
+    let post_at = now() + 3600;
+    let out = client
+        .call(
+            "schedule_message",
+            json!({ "channel": "C1", "post_at": post_at, "text": "reminder" }),
+        )
+        .await;
+    assert_eq!(out["scheduled_message_id"], "Q1");
+    assert_eq!(
+        mock.calls_to("chat.scheduleMessage")[0].params["post_at"],
+        json!(post_at)
+    );
+
+    let err = client
+        .call_err(
+            "schedule_message",
+            json!({ "channel": "C1", "post_at": now() - 60, "text": "too late" }),
+        )
+        .await;
+    assert!(err.contains("post_at"), "{err}");
+}
+
+#[tokio::test]
+async fn list_scheduled_messages() {
+    let (_mock, mut client) = setup().await;
+    let out = client.call("list_scheduled_messages", json!({})).await;
+    assert_eq!(out["scheduled_messages"][0]["id"], "Q1");
+}
+
+#[tokio::test]
+async fn add_reaction() {
+    let (mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "add_reaction",
+            json!({ "channel": "C1", "message_ts": PARENT_TS, "name": ":eyes:" }),
+        )
+        .await;
+    assert_eq!(out["name"], "eyes");
+    assert_eq!(out["already_reacted"], false);
+    let call = &mock.calls_to("reactions.add")[0];
+    assert_eq!(param(call, "timestamp"), Some(PARENT_TS));
+}
+
+#[tokio::test]
+async fn open_dm() {
+    let (_mock, mut client) = setup().await;
+    let out = client.call("open_dm", json!({ "user_id": "U2" })).await;
+    assert_eq!(out["channel"], "D1");
+}
+
+#[tokio::test]
+async fn open_dm_checks_the_dm_allow_list() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(&mock, &[("GRAIL_SLACK_ALLOW_CHANNELS", "C1")]).await;
+    let err = client.call_err("open_dm", json!({ "user_id": "U2" })).await;
+    assert!(err.contains("GRAIL_SLACK_DM_USERS"), "{err}");
+
+    let mut client = McpClient::start(
+        &mock,
+        &[
+            ("GRAIL_SLACK_ALLOW_CHANNELS", "C1"),
+            ("GRAIL_SLACK_DM_USERS", "U2, UBOT"),
+        ],
+    )
+    .await;
+    let out = client.call("open_dm", json!({ "user_id": "U2" })).await;
+    assert_eq!(out["channel"], "D1");
+    let err = client
+        .call_err("open_dm", json!({ "user_id": "UBOT" }))
+        .await;
+    assert!(err.contains("active people"), "{err}");
+    assert_eq!(mock.calls_to("conversations.open").len(), 1);
+}
+
+#[tokio::test]
+async fn add_reminder() {
+    let (mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "add_reminder",
+            json!({ "user_id": "U1", "text": "check the deploy", "time": "in 10 minutes" }),
+        )
+        .await;
+    assert_eq!(out["reminder"]["text"], "check the deploy");
+    let call = &mock.calls_to("reminders.add")[0];
+    assert_eq!(call.token.as_deref(), Some("xoxp-test"));
+}
+
+#[tokio::test]
+async fn add_reminder_refuses_other_users() {
+    let (mock, mut client) = setup().await;
+    let err = client
+        .call_err(
+            "add_reminder",
+            json!({ "user_id": "U2", "text": "check the deploy", "time": "in 10 minutes" }),
+        )
+        .await;
+    assert!(err.contains("SLACK_USER_TOKEN"), "{err}");
+    assert!(mock.calls_to("reminders.add").is_empty());
+}
+
+#[tokio::test]
+async fn list_reminders() {
+    let (_mock, mut client) = setup().await;
+    let out = client.call("list_reminders", json!({})).await;
+    assert_eq!(out["reminders"][0]["id"], "Rm1");
+}
+
+#[tokio::test]
+async fn list_reactions() {
+    let (_mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "list_reactions",
+            json!({ "channel": "C1", "message_ts": PARENT_TS }),
+        )
+        .await;
+    assert_eq!(out["reactions"].as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn get_message_reactions() {
+    let (_mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "get_message_reactions",
+            json!({ "channel": "C1", "message_ts": PARENT_TS, "resolve_users": true }),
+        )
+        .await;
+    assert_eq!(out["reactions"][0]["name"], "white_check_mark");
+    assert_eq!(out["reactions"][0]["user_names"], json!(["Alice", "Bob"]));
+    // The skin-tone variant counts as +1.
+    assert!(out["reactions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|r| r["name"] == "+1"));
+    assert_eq!(out["voters"], 2);
+    assert_eq!(out["multiple_votes"], json!(["U1", "U2"]));
+}
+
+#[tokio::test]
+async fn list_emoji() {
+    let (_mock, mut client) = setup().await;
+    let out = client.call("list_emoji", json!({ "query": "p" })).await;
+    assert_eq!(out["total"], 2);
+    assert_eq!(out["emoji"][1]["alias_of"], "party_parrot");
+}
+
+#[tokio::test]
+async fn get_file_info() {
+    let (_mock, mut client) = setup().await;
+    let out = client
+        .call("get_file_info", json!({ "file_id": "F1" }))
+        .await;
+    assert_eq!(out["file"]["name"], "notes.txt");
+}
+
+#[tokio::test]
+async fn download_file_text() {
+    let (_mock, mut client) = setup().await;
+    let out = client
+        .call("download_file_text", json!({ "file_id": "F1" }))
+        .await;
+    assert_eq!(out["text"], FILE_TEXT);
+    assert_eq!(out["truncated"], false);
+
+    let out = client
+        .call(
+            "download_file_text",
+            json!({ "file_id": "F1", "max_bytes": 5 }),
+        )
+        .await;
+    assert_eq!(out["text"], &FILE_TEXT[..5]);
+    assert_eq!(out["truncated"], true);
+}
+
+#[tokio::test]
+async fn get_canvas() {
+    let (_mock, mut client) = setup().await;
+    let out = client.call("get_canvas", json!({ "channel": "C1" })).await;
+    assert_eq!(out["canvas_id"], "F_CANVAS");
+    let text = out["text"].as_str().unwrap();
+    assert!(text.contains("Ship on Friday."), "{text}");
+}
+
+#[tokio::test]
+async fn allow_list_blocks_other_channels() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(&mock, &[("GRAIL_SLACK_ALLOW_CHANNELS", "C1")]).await;
+    let err = client
+        .call_err("get_channel_history", json!({ "channel": "C2" }))
+        .await;
+    assert!(err.contains("C2") || err.contains("allow"), "{err}");
+    assert!(mock.calls_to("conversations.history").is_empty());
+
+    let out = client.call("list_channels", json!({})).await;
+    assert_eq!(out["channels"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn write_tools_need_opt_in() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(&mock, &[("GRAIL_SLACK_ALLOW_WRITES", "0")]).await;
+    let err = client
+        .call_err("post_message", json!({ "channel": "C1", "text": "hi" }))
+        .await;
+    assert!(!err.is_empty());
+    assert!(mock.calls_to("chat.postMessage").is_empty());
+}
+
+#[tokio::test]
+async fn not_in_channel_is_explained() {
+    let (mock, mut client) = setup().await;
+    let err = client
+        .call_err("get_channel_history", json!({ "channel": "C2" }))
+        .await;
+    assert!(err.contains("not a member of channel C2"), "{err}");
+    assert!(err.contains("GRAIL_SLACK_AUTO_JOIN"), "{err}");
+    assert!(mock.calls_to("conversations.join").is_empty());
+}
+
+#[tokio::test]
+async fn auto_join_joins_public_channels() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(&mock, &[("GRAIL_SLACK_AUTO_JOIN", "1")]).await;
+    let out = client
+        .call(
+            "get_thread",
+            json!({ "channel": "C2", "thread_ts": PARENT_TS }),
+        )
+        .await;
+    assert_eq!(out["messages"].as_array().unwrap().len(), 2);
+    let joins = mock.calls_to("conversations.join");
+    assert_eq!(joins.len(), 1);
+    assert_eq!(param(&joins[0], "channel"), Some("C2"));
+    assert_eq!(mock.calls_to("conversations.replies").len(), 2);
+}
+
+#[tokio::test]
+async fn auto_join_leaves_private_channels_alone() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(&mock, &[("GRAIL_SLACK_AUTO_JOIN", "1")]).await;
+    let err = client
+        .call_err("get_channel_history", json!({ "channel": "C3" }))
+        .await;
+    assert!(err.contains("private"), "{err}");
+    assert!(mock.calls_to("conversations.join").is_empty());
+}
+
+#[tokio::test]
+async fn fields_projects_messages() {
+    let (_mock, mut client) = setup().await;
+    let out = client
+        .call(
+            "get_channel_history",
+            json!({ "channel": "C1", "resolve_entities": true, "fields": ["user", "text", "ts", "thread_ts"] }),
+        )
+        .await;
+    let parent = &out["messages"][1];
+    let mut keys: Vec<&str> = parent
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect();
+    keys.sort_unstable();
+    assert_eq!(keys, ["text", "thread_ts", "ts", "user", "user_name"]);
+
+    let out = client
+        .call(
+            "get_thread_full",
+            json!({ "channel": "C1", "thread_ts": PARENT_TS, "fields": ["ts"] }),
+        )
+        .await;
+    assert_eq!(out["messages"][1], json!({ "ts": REPLY_TS }));
+}
+
+#[tokio::test]
+async fn rate_limited_calls_wait_and_retry() {
+    let (mock, mut client) = setup().await;
+    mock.fail_next("conversations.info", Fault::RateLimited(1));
+    let out = client
+        .call("get_channel_info", json!({ "channel": "C1" }))
+        .await;
+    assert_eq!(out["name"], "general");
+    assert_eq!(mock.calls_to("conversations.info").len(), 2);
+}
+
+#[tokio::test]
+async fn long_or_repeated_rate_limits_are_reported() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(&mock, &[("GRAIL_SLACK_MAX_RETRIES", "1")]).await;
+    mock.fail_next("conversations.info", Fault::RateLimited(60));
+    let err = client
+        .call_err("get_channel_info", json!({ "channel": "C1" }))
+        .await;
+    assert!(err.contains("retry after 60s"), "{err}");
+    assert_eq!(mock.calls_to("conversations.info").len(), 1);
+
+    mock.fail_next("conversations.info", Fault::RateLimited(1));
+    mock.fail_next("conversations.info", Fault::RateLimited(1));
+    let err = client
+        .call_err("get_channel_info", json!({ "channel": "C1" }))
+        .await;
+    assert!(err.contains("rate limited"), "{err}");
+    assert_eq!(mock.calls_to("conversations.info").len(), 3);
+}
+
+#[tokio::test]
+async fn transient_read_failures_back_off_and_retry() {
+    let (mock, mut client) = setup().await;
+    mock.fail_next("conversations.info", Fault::Unavailable);
+    mock.fail_next("conversations.info", Fault::Error("service_unavailable"));
+    let out = client
+        .call("get_channel_info", json!({ "channel": "C1" }))
+        .await;
+    assert_eq!(out["name"], "general");
+    assert_eq!(mock.calls_to("conversations.info").len(), 3);
+
+    for _ in 0..3 {
+        mock.fail_next("conversations.info", Fault::Unavailable);
+    }
+    let err = client
+        .call_err("get_channel_info", json!({ "channel": "C1" }))
+        .await;
+    assert!(err.contains("503"), "{err}");
+    assert_eq!(mock.calls_to("conversations.info").len(), 6);
+}
+
+#[tokio::test]
+async fn failed_writes_are_not_retried() {
+    let (mock, mut client) = setup().await;
+    mock.fail_next("chat.postMessage", Fault::Unavailable);
+    client
+        .call_err("post_message", json!({ "channel": "C1", "text": "hi" }))
+        .await;
+    assert_eq!(mock.calls_to("chat.postMessage").len(), 1);
+}
+
+#[tokio::test]
+async fn concurrent_calls_share_the_request_limit() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(&mock, &[("GRAIL_SLACK_MAX_CONCURRENCY", "2")]).await;
+    mock.set_delay(Duration::from_millis(200));
+    let calls: Vec<(&str, Value)> = ["C1", "C2", "C4", "C1", "C2", "C4"]
+        .into_iter()
+        .map(|channel| ("get_channel_info", json!({ "channel": channel })))
+        .collect();
+    let results = client.call_all(&calls).await;
+    assert_eq!(results[1]["name"], "eng-infra");
+    assert_eq!(mock.max_in_flight(), 2);
+}
+
+#[tokio::test]
+async fn expired_tokens_are_refreshed_once() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(
+        &mock,
+        &[
+            ("SLACK_REFRESH_TOKEN", "xoxe-1"),
+            ("SLACK_CLIENT_ID", "client"),
+            ("SLACK_CLIENT_SECRET", "secret"),
+        ],
+    )
+    .await;
+    mock.fail_next("conversations.info", Fault::Error("token_expired"));
+    let out = client
+        .call("get_channel_info", json!({ "channel": "C1" }))
+        .await;
+    assert_eq!(out["name"], "general");
+
+    let refreshes = mock.calls_to("oauth.v2.access");
+    assert_eq!(refreshes.len(), 1);
+    assert_eq!(param(&refreshes[0], "refresh_token"), Some("xoxe-1"));
+    let tokens: Vec<_> = mock
+        .calls_to("conversations.info")
+        .into_iter()
+        .map(|c| c.token)
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            Some("xoxb-test".to_string()),
+            Some("xoxb-rotated".to_string())
+        ]
+    );
+}
+
+#[tokio::test]
+async fn deny_list_wins_over_the_allow_list() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(&mock, &[("GRAIL_SLACK_DENY_CHANNELS", "C4")]).await;
+    let err = client
+        .call_err("get_channel_history", json!({ "channel": "C4" }))
+        .await;
+    assert!(err.contains("GRAIL_SLACK_DENY_CHANNELS"), "{err}");
+    assert!(mock.calls_to("conversations.history").is_empty());
+
+    let out = client
+        .call("search_messages", json!({ "query": "deploy" }))
+        .await;
+    let channels: Vec<&str> = out["matches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|m| m["channel"]["id"].as_str())
+        .collect();
+    assert_eq!(channels, ["C1"]);
+}
+
+#[tokio::test]
+async fn tool_list_can_be_narrowed() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(
+        &mock,
+        &[
+            ("GRAIL_SLACK_TOOLS", "get_channel_info, post_message"),
+            ("GRAIL_SLACK_ALLOW_WRITES", "0"),
+        ],
+    )
+    .await;
+    let listed = client.request("tools/list", json!({})).await.unwrap();
+    let names: Vec<&str> = listed["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|t| t["name"].as_str())
+        .collect();
+    assert_eq!(names, ["get_channel_info"]);
+    client
+        .call_err("get_channel_history", json!({ "channel": "C1" }))
+        .await;
+    assert!(mock.calls_to("conversations.history").is_empty());
+}
+
+#[tokio::test]
+async fn audit_log_redacts_tokens() {
+    let path = std::env::temp_dir().join(format!(
+        "grail-slack-audit-{}-{}.jsonl",
+        std::process::id(),
+        now()
+    ));
+    let mock = MockSlack::start().await;
+    let mut client =
+        McpClient::start(&mock, &[("GRAIL_SLACK_AUDIT_LOG", path.to_str().unwrap())]).await;
+    client
+        .call(
+            "post_message",
+            json!({ "channel": "C1", "text": "rotate xoxb-123-abc now" }),
+        )
+        .await;
+    let log = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    let line: Value = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+    assert_eq!(line["tool"], "post_message");
+    assert_eq!(line["channel"], "C1");
+    assert_eq!(line["outcome"], "ok");
+    assert_eq!(line["arguments"]["text"], "rotate [redacted] now");
+    assert!(!log.contains("xoxb-123"), "{log}");
+}
+
+#[tokio::test]
+async fn workspace_argument_picks_the_token() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(
+        &mock,
+        &[("GRAIL_SLACK_WORKSPACE_TOKENS", r#"{"T2": "xoxb-other"}"#)],
+    )
+    .await;
+    client
+        .call(
+            "get_channel_info",
+            json!({ "channel": "C1", "workspace": "T2" }),
+        )
+        .await;
+    client
+        .call("get_channel_info", json!({ "channel": "C1" }))
+        .await;
+    let tokens: Vec<_> = mock
+        .calls_to("conversations.info")
+        .into_iter()
+        .map(|c| c.token)
+        .collect();
+    assert_eq!(
+        tokens,
+        [
+            Some("xoxb-other".to_string()),
+            Some("xoxb-test".to_string())
+        ]
+    );
+    let err = client
+        .call_err(
+            "get_channel_info",
+            json!({ "channel": "C1", "workspace": "T9" }),
+        )
+        .await;
+    assert!(err.contains("T9"), "{err}");
+}
+
+#[tokio::test]
+async fn channel_name_patterns_allow_matching_channels() {
+    let mock = MockSlack::start().await;
+    let mut client = McpClient::start(&mock, &[("GRAIL_SLACK_ALLOW_CHANNELS", "#ENG-*")]).await;
+    let out = client
+        .call("get_channel_info", json!({ "channel": "C2" }))
+        .await;
+    assert_eq!(out["name"], "eng-infra");
+    let err = client
+        .call_err("get_channel_info", json!({ "channel": "C1" }))
+        .await;
+    assert!(err.contains("GRAIL_SLACK_ALLOW_CHANNELS"), "{err}");
+}
@@ -0,0 +1,313 @@
+//! Boundary-aware truncation and splitting for text sent to chat providers and models.
+//!
+//! Everything here counts `char`s, never cuts inside a UTF-8 sequence, and prefers to cut
+//! at a paragraph break, then a line break, then the end of a sentence, then whitespace.
+//! A boundary is only used when it keeps at least [`MIN_KEEP_PERCENT`] of the budget;
+//! otherwise the cut is made at the limit. Markdown code fences are never left open: a cut
+//! inside a fenced block closes it, and [`split`] reopens it (with the same info string)
+//! at the start of the next chunk.
+
+/// Appended by [`truncate`]; prepended by [`truncate_tail`].
+pub const ELLIPSIS: char = '…';
+/// A boundary earlier than this share of the budget is not worth the lost text.
+pub const MIN_KEEP_PERCENT: usize = 60;
+
+const FENCE: &str = "```";
+const CLOSE_FENCE: &str = "\n```";
+const CLOSE_FENCE_CHARS: usize = 4;
+
+/// Byte offset of the `n`th char, or `s.len()` when `s` is shorter.
+fn byte_at(s: &str, n: usize) -> usize {
+    s.char_indices().nth(n).map(|(i, _)| i).unwrap_or(s.len())
+}
+
+fn is_fence_line(line: &str) -> bool {
+    line.trim_start().starts_with(FENCE)
+}
+
+/// The fence still open at the end of `s`, as (byte offset of its opening line within `s`,
+/// opening line). `open` is the fence already open where `s` starts; its offset is 0.
+fn open_fence<'a>(s: &'a str, open: Option<&'a str>) -> Option<(usize, &'a str)> {
+    let mut state = open.map(|f| (0, f));
+    let mut offset = 0;
+    for line in s.split_inclusive('\n') {
+        if is_fence_line(line) {
+            state = match state {
+                Some(_) => None,
+                None => Some((offset, line.trim())),
+            };
+        }
+        offset += line.len();
+    }
+    state
+}
+
+/// Where to cut `s` to keep at most `max_chars`: a byte offset on a char boundary, and
+/// greater than zero whenever `s` and `max_chars` are non-zero.
+fn cut_point(s: &str, max_chars: usize, open: Option<&str>) -> usize {
+    let limit = byte_at(s, max_chars);
+    if limit == s.len() {
+        return limit;
+    }
+    let floor = byte_at(s, (max_chars * MIN_KEEP_PERCENT / 100).max(1));
+    let window = &s[..limit];
+
+    // Never cut through a fence line: half of "```rust" is neither fence nor prose.
+    let line_start = window.rfind('\n').map_or(0, |p| p + 1);
+    if line_start > 0 && is_fence_line(&window[line_start..]) {
+        return line_start;
+    }
+
+    // Don't start a code block that can't be finished; cut just before it.
+    if let Some((start, _)) = open_fence(window, open) {
+        if start >= floor {
+            return start;
+        }
+    }
+    if let Some(pos) = window.rfind("\n\n").filter(|p| *p >= floor) {
+        return pos;
+    }
+    if let Some(pos) = window.rfind('\n').filter(|p| *p >= floor) {
+        return pos;
+    }
+    let sentence_end = window
+        .char_indices()
+        .zip(window.chars().skip(1))
+        .filter(|((_, c), next)| matches!(c, '.' | '!' | '?' | '。') && next.is_whitespace())
+        .map(|((i, c), _)| i + c.len_utf8())
+        .filter(|p| *p >= floor)
+        .last();
+    if let Some(pos) = sentence_end {
+        return pos;
+    }
+    if let Some((pos, _)) = window
+        .char_indices()
+        .rev()
+        .take_while(|(i, _)| *i >= floor)
+        .find(|(_, c)| c.is_whitespace())
+    {
+        return pos;
+    }
+    limit
+}
+
+/// The longest boundary-aware prefix of `s` with at most `max_chars` chars, without any
+/// marker. Code fences are not closed; use [`truncate`] for text that is shown as markdown.
+pub fn head(s: &str, max_chars: usize) -> &str {
+    if s.chars().count() <= max_chars {
+        return s;
+    }
+    s[..cut_point(s, max_chars, None)].trim_end()
+}
+
+/// The boundary-aware suffix of `s` with at most `max_chars` chars, preferring to start
+/// at the beginning of a paragraph, line or word.
+pub fn tail(s: &str, max_chars: usize) -> &str {
+    let count = s.chars().count();
+    if count <= max_chars {
+        return s;
+    }
+    let limit = byte_at(s, count - max_chars);
+    let ceiling = byte_at(s, count - max_chars * MIN_KEEP_PERCENT / 100);
+    let window = &s[limit..ceiling];
+    let start = window
+        .find("\n\n")
+        .map(|p| p + 2)
+        .or_else(|| window.find('\n').map(|p| p + 1))
+        .or_else(|| {
+            window
+                .char_indices()
+                .find(|(_, c)| c.is_whitespace())
+                .map(|(i, c)| i + c.len_utf8())
+        })
+        .map(|p| limit + p)
+        .unwrap_or(limit);
+    s[start..].trim_start()
+}
+
+/// `text` (trimmed) cut to at most `max_chars` chars, ending with [`ELLIPSIS`] when cut.
+/// A cut inside a code block closes the block after the ellipsis.
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+    let budget = max_chars - 1;
+    let kept = text[..cut_point(text, budget, None)].trim_end();
+    if open_fence(kept, None).is_none() {
+        return format!("{kept}{ELLIPSIS}");
+    }
+    if budget <= CLOSE_FENCE_CHARS {
+        // No room to close the block; don't start it at all.
+        return ELLIPSIS.to_string();
+    }
+    let kept = text[..cut_point(text, budget - CLOSE_FENCE_CHARS, None)].trim_end();
+    if open_fence(kept, None).is_some() {
+        format!("{kept}{ELLIPSIS}{CLOSE_FENCE}")
+    } else {
+        format!("{kept}{ELLIPSIS}")
+    }
+}
+
+/// Like [`truncate`], but for one-line previews: whitespace (including newlines) is
+/// collapsed to single spaces first.
+pub fn truncate_line(text: &str, max_chars: usize) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    truncate(&line, max_chars)
+}
+
+/// `text` cut to its last `max_chars` chars, starting with [`ELLIPSIS`] when cut.
+pub fn truncate_tail(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+    format!("{ELLIPSIS}{}", tail(text, max_chars - 1))
+}
+
+/// Split `text` (trimmed) into chunks of at most `max_chars` chars for providers with a
+/// message size limit. Chunks break at the same boundaries as [`truncate`]; a code block
+/// that spans chunks is closed at the end of one and reopened at the start of the next.
+/// Empty text yields no chunks.
+pub fn split(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut rest = text.trim();
+    let mut out = Vec::new();
+    let mut reopen: Option<&str> = None;
+    while !rest.is_empty() {
+        // Re-opening and closing a fence costs its opening line plus `CLOSE_FENCE`; when
+        // that doesn't leave a useful budget the block is split as plain text.
+        let prefix = reopen.map(|f| format!("{f}\n")).unwrap_or_default();
+        let overhead = prefix.chars().count() + CLOSE_FENCE_CHARS;
+        let fences = overhead * 2 <= max_chars;
+        let prefix = if fences { prefix } else { String::new() };
+        let prefix_chars = prefix.chars().count();
+
+        if prefix_chars + rest.chars().count() <= max_chars {
+            out.push(format!("{prefix}{rest}"));
+            break;
+        }
+        let (budget, open) = if fences {
+            (max_chars - overhead, reopen)
+        } else {
+            (max_chars, None)
+        };
+        let end = cut_point(rest, budget, open);
+        let chunk = rest[..end].trim_end();
+        let still_open = if fences {
+            open_fence(chunk, open).map(|(_, line)| line)
+        } else {
+            None
+        };
+        let mut piece = format!("{prefix}{chunk}");
+        if still_open.is_some() {
+            piece.push_str(CLOSE_FENCE);
+        }
+        if !chunk.is_empty() {
+            out.push(piece);
+        }
+        rest = &rest[end..];
+        // Indentation is content inside a code block.
+        rest = if still_open.is_some() {
+            rest.trim_start_matches(['\n', '\r'])
+        } else {
+            rest.trim_start()
+        };
+        reopen = still_open;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncation_prefers_boundaries_and_closes_code_fences() {
+        let prose = "First paragraph here.\n\nSecond one is quite a bit longer than that.";
+        assert_eq!(truncate(prose, 30), "First paragraph here.…");
+        assert_eq!(
+            truncate(prose, 40),
+            "First paragraph here.\n\nSecond one is…"
+        );
+        assert_eq!(
+            truncate("One two three. Four five six", 20),
+            "One two three.…"
+        );
+        assert_eq!(truncate("héllo wörld", 8), "héllo…");
+        assert_eq!(truncate_line("a\n  b\tc", 10), "a b c");
+        assert_eq!(head("xxxxxxxxxx", 4), "xxxx");
+        assert_eq!(tail("alpha beta gamma delta", 12), "gamma delta");
+        assert_eq!(truncate_tail("alpha beta gamma delta", 13), "…gamma delta");
+
+        let reply = format!("Run this:\n```sh\n{}```", "echo hi\n".repeat(20));
+        let cut = truncate(&reply, 60);
+        assert!(cut.chars().count() <= 60);
+        assert!(cut.ends_with("…\n```"), "{cut}");
+
+        let chunks = split(&reply, 60);
+        assert!(chunks.len() > 2);
+        assert!(chunks[1].starts_with("```sh\necho hi"), "{chunks:?}");
+        assert!(split("  ", 10).is_empty());
+    }
+
+    fn fence_count(s: &str) -> usize {
+        s.lines()
+            .filter(|l| l.trim_start().starts_with("```"))
+            .count()
+    }
+
+    fn markdown_text() -> impl proptest::strategy::Strategy<Value = String> {
+        use proptest::prelude::*;
+        let piece = prop_oneof![
+            // No info strings, so added fences are just backticks.
+            Just("```\n"),
+            Just("word "),
+            Just("été "),
+            Just("漢字🙂"),
+            Just("done. "),
+            Just("\n"),
+            Just("\n\n"),
+            Just("    indented\n"),
+        ];
+        proptest::collection::vec(piece, 0..120).prop_map(|p| p.concat())
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn truncate_fits_and_keeps_a_prefix(text in markdown_text(), max in 0usize..300) {
+            let out = truncate(&text, max);
+            proptest::prop_assert!(out.chars().count() <= max);
+            let kept = out.trim_end_matches("\n```").trim_end_matches('…');
+            proptest::prop_assert!(text.trim().starts_with(kept));
+            if out != text.trim() {
+                proptest::prop_assert_eq!(fence_count(&out) % 2, 0, "{:?}", out);
+            }
+        }
+
+        #[test]
+        fn split_fits_balances_fences_and_loses_nothing(text in markdown_text(), max in 1usize..200) {
+            let chunks = split(&text, max);
+            for (i, chunk) in chunks.iter().enumerate() {
+                proptest::prop_assert!(chunk.chars().count() <= max, "{:?}", chunk);
+                proptest::prop_assert!(!chunk.trim().is_empty());
+                // The last chunk is as balanced as the input.
+                if max >= 24 && i + 1 < chunks.len() {
+                    proptest::prop_assert_eq!(fence_count(chunk) % 2, 0, "{:?}", chunk);
+                }
+            }
+            // Only whitespace and the reopened/closed fences are added or dropped.
+            let squash = |s: &str| {
+                s.chars()
+                    .filter(|c| !c.is_whitespace() && *c != '`')
+                    .collect::<String>()
+            };
+            proptest::prop_assert_eq!(squash(&chunks.concat()), squash(&text));
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! `web_fetch` response cache. Successful responses are kept in memory by URL for
+//! `GRAIL_WEB_FETCH_CACHE_TTL` seconds (default 300, `0` turns caching off) and served
+//! without a request while fresh. Stale entries with an `ETag` or `Last-Modified` are
+//! revalidated with `If-None-Match` / `If-Modified-Since`; a 304 serves the cached body
+//! again. `Cache-Control: no-store` responses are never kept.
+//!
+//! Entries are per process and capped at `MAX_BYTES` in total, oldest dropped first.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use reqwest::header::{HeaderMap, CACHE_CONTROL, ETAG, LAST_MODIFIED};
+use tokio::sync::Mutex;
+
+use crate::RawPage;
+
+const DEFAULT_TTL_SECS: u64 = 300;
+const MAX_BYTES: usize = 64_000_000;
+
+struct Entry {
+    page: RawPage,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    stored: Instant,
+}
+
+/// A cached response and what is needed to revalidate it.
+pub struct Cached {
+    pub page: RawPage,
+    pub fresh: bool,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+pub struct FetchCache {
+    /// `None` when caching is off.
+    ttl: Option<Duration>,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl FetchCache {
+    pub fn from_env() -> Self {
+        let ttl = std::env::var("GRAIL_WEB_FETCH_CACHE_TTL")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        Self {
+            ttl: (ttl > 0).then(|| Duration::from_secs(ttl)),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cache key: the URL, plus the user agent when it isn't the default (fallback
+    /// fetches as Googlebot may get a different page).
+    pub fn key(url: &reqwest::Url, user_agent: Option<&str>) -> String {
+        match user_agent {
+            Some(ua) => format!("{url} {ua}"),
+            None => url.to_string(),
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> Option<Cached> {
+        let ttl = self.ttl?;
+        let entries = self.entries.lock().await;
+        let entry = entries.get(key)?;
+        let fresh = entry.stored.elapsed() < ttl;
+        if !fresh && entry.etag.is_none() && entry.last_modified.is_none() {
+            return None;
+        }
+        Some(Cached {
+            page: entry.page.clone(),
+            fresh,
+            etag: entry.etag.clone(),
+            last_modified: entry.last_modified.clone(),
+        })
+    }
+
+    /// After a 304: the cached page, fresh for another TTL.
+    pub async fn revalidated(&self, key: &str) -> Option<RawPage> {
+        let mut entries = self.entries.lock().await;
+        let entry = entries.get_mut(key)?;
+        entry.stored = Instant::now();
+        Some(entry.page.clone())
+    }
+
+    /// Keep `page` when it is a complete 200 the server allows storing.
+    pub async fn store(&self, key: &str, page: &RawPage, headers: &HeaderMap) {
+        if self.ttl.is_none() || page.status != 200 || page.truncated {
+            return;
+        }
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        if header(CACHE_CONTROL).is_some_and(|v| v.to_ascii_lowercase().contains("no-store")) {
+            return;
+        }
+        let mut entries = self.entries.lock().await;
+        entries.insert(
+            key.to_string(),
+            Entry {
+                page: page.clone(),
+                etag: header(ETAG),
+                last_modified: header(LAST_MODIFIED),
+                stored: Instant::now(),
+            },
+        );
+        let mut total: usize = entries.values().map(|e| e.page.body.len()).sum();
+        while total > MAX_BYTES {
+            let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, e)| e.stored)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            if let Some(e) = entries.remove(&oldest) {
+                total -= e.page.body.len();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn cache(ttl: Option<Duration>) -> FetchCache {
+        FetchCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn page(status: u16, body: &str) -> RawPage {
+        RawPage {
+            status,
+            final_url: "https://example.com/".to_string(),
+            content_type: "text/html".to_string(),
+            body: body.as_bytes().to_vec(),
+            truncated: false,
+            cache: "miss",
+            rendered: false,
+        }
+    }
+
+    fn headers(pairs: &[(reqwest::header::HeaderName, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(name.clone(), HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn key_includes_non_default_user_agent() {
+        let url = reqwest::Url::parse("https://example.com/a").unwrap();
+        assert_eq!(FetchCache::key(&url, None), "https://example.com/a");
+        assert_eq!(
+            FetchCache::key(&url, Some("Googlebot")),
+            "https://example.com/a Googlebot"
+        );
+    }
+
+    #[tokio::test]
+    async fn fresh_entries_are_served() {
+        let cache = cache(Some(Duration::from_secs(60)));
+        cache
+            .store("k", &page(200, "hello"), &headers(&[(ETAG, "\"v1\"")]))
+            .await;
+        let cached = cache.get("k").await.unwrap();
+        assert!(cached.fresh);
+        assert_eq!(cached.page.body, b"hello");
+        assert_eq!(cached.etag.as_deref(), Some("\"v1\""));
+        assert!(cache.get("other").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stale_entries_need_a_validator() {
+        let cache = cache(Some(Duration::ZERO));
+        cache
+            .store(
+                "validated",
+                &page(200, "a"),
+                &headers(&[(LAST_MODIFIED, "Mon, 01 Jan 2024 00:00:00 GMT")]),
+            )
+            .await;
+        cache
+            .store("plain", &page(200, "b"), &HeaderMap::new())
+            .await;
+        let stale = cache.get("validated").await.unwrap();
+        assert!(!stale.fresh);
+        assert!(stale.last_modified.is_some());
+        assert!(cache.get("plain").await.is_none());
+        assert_eq!(cache.revalidated("validated").await.unwrap().body, b"a");
+        assert!(cache.revalidated("missing").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn only_complete_storable_200s_are_kept() {
+        let cache = cache(Some(Duration::from_secs(60)));
+        cache.store("404", &page(404, "x"), &HeaderMap::new()).await;
+        let mut truncated = page(200, "x");
+        truncated.truncated = true;
+        cache
+            .store("truncated", &truncated, &HeaderMap::new())
+            .await;
+        cache
+            .store(
+                "no-store",
+                &page(200, "x"),
+                &headers(&[(CACHE_CONTROL, "private, No-Store")]),
+            )
+            .await;
+        for key in ["404", "truncated", "no-store"] {
+            assert!(cache.get(key).await.is_none(), "{key}");
+        }
+    }
+
+    #[tokio::test]
+    async fn disabled_cache_keeps_nothing() {
+        let cache = cache(None);
+        cache.store("k", &page(200, "x"), &HeaderMap::new()).await;
+        assert!(cache.get("k").await.is_none());
+        assert!(cache.entries.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn oldest_entries_are_dropped_over_the_size_cap() {
+        let cache = cache(Some(Duration::from_secs(60)));
+        let big = "x".repeat(MAX_BYTES / 2);
+        for key in ["a", "b", "c"] {
+            cache.store(key, &page(200, &big), &HeaderMap::new()).await;
+        }
+        assert!(cache.get("a").await.is_none());
+        assert!(cache.get("b").await.is_some());
+        assert!(cache.get("c").await.is_some());
+    }
+}
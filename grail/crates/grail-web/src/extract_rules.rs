@@ -0,0 +1,236 @@
+//! Per-domain extraction rules for `web_fetch` (`GRAIL_WEB_EXTRACT_RULES`).
+//!
+//! The variable points at a JSON file mapping domains to hints for the sites agents read
+//! most, e.g. internal docs:
+//!
+//! ```json
+//! {"docs.example.com": {"selector": "main article", "strip": ["nav", ".cookie-banner"], "extractMode": "text"}}
+//! ```
+//!
+//! A domain also covers its subdomains; the longest matching domain wins. `strip`
+//! removes matching elements before extraction, `selector` keeps only the matching
+//! elements (the whole page is used when nothing matches) and `extractMode` is used when
+//! the caller doesn't ask for one.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Context;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+use tracing::warn;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+#[allow(non_snake_case)]
+struct RawRule {
+    #[serde(default)]
+    selector: Option<String>,
+    #[serde(default)]
+    strip: Vec<String>,
+    #[serde(default)]
+    extractMode: Option<String>,
+}
+
+pub struct ExtractRule {
+    pub domain: String,
+    selector: Option<Selector>,
+    strip: Vec<Selector>,
+    pub extract_mode: Option<String>,
+}
+
+/// Result of applying a rule to an HTML page.
+pub struct Applied {
+    pub html: String,
+    /// Whether `selector` matched anything (`None` when the rule has no selector).
+    pub selector_matched: Option<bool>,
+    pub stripped: usize,
+}
+
+fn parse_selector(domain: &str, s: &str) -> anyhow::Result<Selector> {
+    Selector::parse(s.trim()).map_err(|e| anyhow::anyhow!("{domain}: invalid selector {s:?}: {e}"))
+}
+
+impl ExtractRule {
+    pub fn apply(&self, html: &str) -> Applied {
+        let mut doc = Html::parse_document(html);
+        let ids: Vec<_> = self
+            .strip
+            .iter()
+            .flat_map(|sel| doc.select(sel).map(|el| el.id()).collect::<Vec<_>>())
+            .collect();
+        let mut stripped = 0;
+        for id in ids {
+            // Skip nodes an earlier selector already detached.
+            if let Some(mut node) = doc.tree.get_mut(id) {
+                if node.parent().is_some() {
+                    node.detach();
+                    stripped += 1;
+                }
+            }
+        }
+
+        let Some(selector) = &self.selector else {
+            return Applied {
+                html: doc.html(),
+                selector_matched: None,
+                stripped,
+            };
+        };
+        let parts: Vec<String> = doc.select(selector).map(|el| el.html()).collect();
+        if parts.is_empty() {
+            return Applied {
+                html: doc.html(),
+                selector_matched: Some(false),
+                stripped,
+            };
+        }
+        Applied {
+            html: format!("<html><body>{}</body></html>", parts.join("\n")),
+            selector_matched: Some(true),
+            stripped,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ExtractRules {
+    rules: Vec<ExtractRule>,
+}
+
+impl ExtractRules {
+    /// Rules from the file named by `GRAIL_WEB_EXTRACT_RULES`; none when unset. A file
+    /// that can't be read or parsed is logged and ignored rather than stopping startup.
+    pub fn from_env() -> Self {
+        match std::env::var("GRAIL_WEB_EXTRACT_RULES") {
+            Ok(v) if !v.trim().is_empty() => {
+                Self::load(Path::new(v.trim())).unwrap_or_else(|err| {
+                    warn!(
+                        error = format!("{err:#}"),
+                        "ignoring GRAIL_WEB_EXTRACT_RULES"
+                    );
+                    Self::default()
+                })
+            }
+            _ => Self::default(),
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("read extraction rules {}", path.display()))?;
+        Self::parse(&raw).with_context(|| format!("parse extraction rules {}", path.display()))
+    }
+
+    pub fn parse(raw: &str) -> anyhow::Result<Self> {
+        let map: HashMap<String, RawRule> = serde_json::from_str(raw)?;
+        let mut rules = Vec::with_capacity(map.len());
+        for (domain, r) in map {
+            let domain = domain.trim().trim_matches('.').to_ascii_lowercase();
+            anyhow::ensure!(!domain.is_empty(), "empty domain in extraction rules");
+            let extract_mode = r.extractMode.map(|m| m.trim().to_ascii_lowercase());
+            if let Some(mode) = extract_mode.as_deref() {
+                anyhow::ensure!(
+                    mode == "markdown" || mode == "text",
+                    "{domain}: extractMode must be markdown or text"
+                );
+            }
+            rules.push(ExtractRule {
+                selector: r
+                    .selector
+                    .filter(|s| !s.trim().is_empty())
+                    .map(|s| parse_selector(&domain, &s))
+                    .transpose()?,
+                strip: r
+                    .strip
+                    .iter()
+                    .map(|s| parse_selector(&domain, s))
+                    .collect::<anyhow::Result<_>>()?,
+                extract_mode,
+                domain,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    /// The rule for `host`: exact or parent domain, longest match first.
+    pub fn for_host(&self, host: &str) -> Option<&ExtractRule> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.rules
+            .iter()
+            .filter(|r| crate::domain_matches(&host, &r.domain))
+            .max_by_key(|r| r.domain.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><body>
+        <nav>Home | Docs</nav>
+        <div class="cookie-banner">We use cookies</div>
+        <main><article><h1>Title</h1><p>Body text</p></article></main>
+        <footer>Footer</footer>
+    </body></html>"#;
+
+    fn rules(raw: &str) -> ExtractRules {
+        ExtractRules::parse(raw).unwrap()
+    }
+
+    #[test]
+    fn apply_strips_then_selects() {
+        let rules = rules(
+            r#"{"docs.example.com": {"selector": "main article", "strip": ["nav", ".cookie-banner"]}}"#,
+        );
+        let applied = rules.for_host("docs.example.com").unwrap().apply(PAGE);
+        assert_eq!(applied.selector_matched, Some(true));
+        assert_eq!(applied.stripped, 2);
+        assert!(applied.html.contains("Body text"));
+        assert!(!applied.html.contains("Footer"));
+        assert!(!applied.html.contains("cookies"));
+    }
+
+    #[test]
+    fn apply_keeps_whole_page_when_selector_misses() {
+        let rules = rules(r#"{"example.com": {"selector": ".missing", "strip": ["nav"]}}"#);
+        let applied = rules.for_host("example.com").unwrap().apply(PAGE);
+        assert_eq!(applied.selector_matched, Some(false));
+        assert_eq!(applied.stripped, 1);
+        assert!(applied.html.contains("Footer"));
+        assert!(!applied.html.contains("Home | Docs"));
+    }
+
+    #[test]
+    fn apply_counts_duplicate_matches_once() {
+        let rules = rules(r#"{"example.com": {"strip": ["article", "main article"]}}"#);
+        let applied = rules.for_host("example.com").unwrap().apply(PAGE);
+        assert_eq!(applied.selector_matched, None);
+        assert_eq!(applied.stripped, 1);
+        assert!(!applied.html.contains("Body text"));
+    }
+
+    #[test]
+    fn for_host_prefers_longest_domain() {
+        let rules = rules(
+            r#"{"example.com": {"extractMode": "text"}, "docs.example.com.": {"extractMode": "Markdown"}}"#,
+        );
+        let rule = rules.for_host("DOCS.example.com.").unwrap();
+        assert_eq!(rule.domain, "docs.example.com");
+        assert_eq!(rule.extract_mode.as_deref(), Some("markdown"));
+        assert_eq!(
+            rules.for_host("api.example.com").unwrap().domain,
+            "example.com"
+        );
+        assert!(rules.for_host("notexample.com").is_none());
+        assert!(rules.for_host("example.org").is_none());
+    }
+
+    #[test]
+    fn parse_rejects_bad_rules() {
+        assert!(ExtractRules::parse(r#"{"example.com": {"selector": "[["}}"#).is_err());
+        assert!(ExtractRules::parse(r#"{"example.com": {"extractMode": "pdf"}}"#).is_err());
+        assert!(ExtractRules::parse(r#"{"example.com": {"selectr": "main"}}"#).is_err());
+        assert!(ExtractRules::parse(r#"{" . ": {}}"#).is_err());
+    }
+}
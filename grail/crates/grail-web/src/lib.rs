@@ -0,0 +1,1759 @@
+//! Web search and fetch tools shared by the `grail-web-mcp` sidecar and grail-server.
+//!
+//! [`WebMcpServer`] is the MCP server behind both `grail-web-mcp` and
+//! `grail-server web-mcp`; grail-server also calls [`WebMcpServer::fetch_url`] directly
+//! to read linked pages for backends without tools.
+
+mod cache;
+mod extract_rules;
+mod markdown;
+mod pdf;
+mod quota;
+mod ratelimit;
+mod readability;
+#[cfg(feature = "render")]
+mod render;
+mod sessions;
+mod walls;
+mod watch;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rmcp::handler::server::ServerHandler;
+use rmcp::model::CallToolRequestParam;
+use rmcp::model::CallToolResult;
+use rmcp::model::JsonObject;
+use rmcp::model::ListToolsResult;
+use rmcp::model::PaginatedRequestParam;
+use rmcp::model::ServerCapabilities;
+use rmcp::model::ServerInfo;
+use rmcp::model::Tool;
+use rmcp::ErrorData as McpError;
+use rmcp::ServiceExt;
+use scraper::Html;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::task;
+use tracing::{error, info, warn};
+
+const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_7_2) AppleWebKit/537.36";
+const MAX_REDIRECTS: usize = 5;
+const MAX_FETCH_BYTES: usize = 2_500_000; // hard limit for safety regardless of maxChars
+/// Request headers `web_fetch` callers may set.
+const ALLOWED_REQUEST_HEADERS: &[&str] = &["accept", "accept-language", "content-type"];
+const MAX_REQUEST_BODY_BYTES: usize = 100_000;
+/// Rate-limit resets this close are waited out instead of failing the search.
+const MAX_RATE_LIMIT_WAIT_SECS: i64 = 2;
+
+static DDG_RESULT_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?s)<a([^>]*class="result__a"[^>]*)>(.*?)</a>"#).unwrap());
+static DDG_SNIPPET_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?s)class="result__snippet"[^>]*>(.*?)</a>"#).unwrap());
+static HREF_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"href="([^"]*)""#).unwrap());
+static TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+static SITE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[a-z0-9]([a-z0-9-]*[a-z0-9])?(\.[a-z0-9]([a-z0-9-]*[a-z0-9])?)+$").unwrap()
+});
+static FRESHNESS_RANGE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\d{4}-\d{2}-\d{2}to\d{4}-\d{2}-\d{2}$").unwrap());
+/// Regional language codes Brave accepts as-is; other `xx-YY` locales are split into
+/// `search_lang` and `country`.
+const BRAVE_REGIONAL_LANGS: &[&str] = &["en-gb", "pt-br", "pt-pt", "zh-hans", "zh-hant"];
+
+fn stdio() -> (tokio::io::Stdin, tokio::io::Stdout) {
+    (tokio::io::stdin(), tokio::io::stdout())
+}
+
+#[derive(Clone)]
+pub struct WebMcpServer {
+    tools: Arc<Vec<Tool>>,
+    http: reqwest::Client,
+    quota: Arc<quota::QuotaTracker>,
+    watch: Arc<watch::WatchStore>,
+    extract_rules: Arc<extract_rules::ExtractRules>,
+    fallbacks: Arc<walls::Fallbacks>,
+    cache: Arc<cache::FetchCache>,
+    rate_limit: Arc<ratelimit::HostLimiter>,
+    sessions: Arc<sessions::Sessions>,
+    allow_domains: Vec<String>,
+    deny_domains: Vec<String>,
+}
+
+impl WebMcpServer {
+    /// Configured from the `GRAIL_WEB_*` / `BRAVE_*` environment variables.
+    pub fn new() -> anyhow::Result<Self> {
+        let tools = vec![
+            Self::tool_web_search()?,
+            Self::tool_web_fetch()?,
+            Self::tool_web_quota()?,
+            Self::tool_web_watch_diff()?,
+        ];
+
+        Ok(Self {
+            tools: Arc::new(tools),
+            http: http_client(None)?,
+            quota: Arc::new(quota::QuotaTracker::from_env()),
+            watch: Arc::new(watch::WatchStore::from_env()),
+            extract_rules: Arc::new(extract_rules::ExtractRules::from_env()),
+            fallbacks: Arc::new(walls::Fallbacks::from_env()),
+            cache: Arc::new(cache::FetchCache::from_env()),
+            rate_limit: Arc::new(ratelimit::HostLimiter::from_env()),
+            sessions: Arc::new(sessions::Sessions::default()),
+            allow_domains: parse_domain_list_env("GRAIL_WEB_ALLOW_DOMAINS"),
+            deny_domains: parse_domain_list_env("GRAIL_WEB_DENY_DOMAINS"),
+        })
+    }
+
+    /// Override the allow/deny domain lists (same format as `GRAIL_WEB_ALLOW_DOMAINS`).
+    pub fn with_domain_lists(mut self, allow: &str, deny: &str) -> Self {
+        self.allow_domains = parse_domain_list(allow);
+        self.deny_domains = parse_domain_list(deny);
+        self
+    }
+
+    fn tool_web_search() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Search query." },
+                "count": { "type": "integer", "minimum": 1, "maximum": 10, "default": 5 },
+                "site": { "type": "string", "description": "Restrict results to this domain (and its subdomains), e.g. docs.rs." },
+                "country": { "type": "string", "description": "Two-letter country code for result localization, e.g. DE." },
+                "search_lang": { "type": "string", "description": "Result language, e.g. de or en-gb. A locale such as de-DE also sets country." },
+                "freshness": { "type": "string", "description": "Only results from the past day (pd), week (pw), month (pm), year (py), or a YYYY-MM-DDtoYYYY-MM-DD range." }
+            },
+            "required": ["query"],
+            "additionalProperties": false
+        }))
+        .context("deserialize web_search schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("web_search"),
+            Cow::Borrowed(
+                "Search the web via Brave Search API. Returns titles, URLs, and snippets.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_web_fetch() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string", "description": "URL to fetch (http/https only)." },
+                "extractMode": { "type": "string", "enum": ["markdown", "text"], "description": "markdown keeps the headings, links, lists and code of the page's main content; text is the same content as plain text. Defaults to markdown, or the site's configured mode." },
+                "maxChars": { "type": "integer", "minimum": 100, "maximum": 200000, "default": 50000 },
+                "method": { "type": "string", "enum": ["GET", "POST"], "default": "GET" },
+                "headers": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" },
+                    "description": "Request headers; only Accept, Accept-Language and Content-Type may be set."
+                },
+                "body": { "description": "POST body: a string is sent as-is, anything else as JSON (Content-Type defaults to application/json)." },
+                "session_id": {
+                    "type": "string",
+                    "description": "Keep cookies between fetches that pass the same id (e.g. accept a consent wall, then fetch the page). Without it no cookies are kept."
+                },
+                "render": {
+                    "type": "boolean",
+                    "description": "Render the page in headless Chromium first, for sites that build their content with JavaScript. Slower; only available when the server was built with the render feature."
+                }
+            },
+            "required": ["url"],
+            "additionalProperties": false
+        }))
+        .context("deserialize web_fetch schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("web_fetch"),
+            Cow::Borrowed("Fetch a URL and extract its main content, leaving out navigation, sidebars and ads. Pages behind a cookie, bot-check, JavaScript or subscription wall are retried via AMP/print/reader versions; `strategy` says which one produced the text. PDFs are returned as their text, with `pages` set to the page count. `method`, `headers` and `body` allow simple API calls such as a JSON POST; those responses aren't cached or retried. `session_id` keeps cookies across calls. Returns JSON with text."),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_web_quota() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": false
+        }))
+        .context("deserialize web_quota schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("web_quota"),
+            Cow::Borrowed(
+                "Show remaining Brave Search quota, when it resets, and whether web_search is currently available.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    fn tool_web_watch_diff() -> anyhow::Result<Tool> {
+        let schema: JsonObject = serde_json::from_value(json!({
+            "type": "object",
+            "properties": {
+                "url": { "type": "string", "description": "URL to watch (http/https only)." },
+                "scope": { "type": "string", "description": "Who the snapshot belongs to, e.g. `cron:<job_id>` for a scheduled job or the conversation. Each scope compares against its own previous snapshot." },
+                "maxChars": { "type": "integer", "minimum": 100, "maximum": 50000, "default": 8000 }
+            },
+            "required": ["url"],
+            "additionalProperties": false
+        }))
+        .context("deserialize web_watch_diff schema")?;
+
+        Ok(Tool::new(
+            Cow::Borrowed("web_watch_diff"),
+            Cow::Borrowed(
+                "Fetch a URL, compare its extracted text with the snapshot from the previous call in the same scope, and store the new snapshot. Returns whether the page changed and a line diff. Scheduled jobs should pass scope `cron:<job_id>`.",
+            ),
+            Arc::new(schema),
+        ))
+    }
+
+    /// Fallback search provider used while Brave is unavailable (`GRAIL_WEB_SEARCH_FALLBACK`).
+    fn search_fallback() -> &'static str {
+        match std::env::var("GRAIL_WEB_SEARCH_FALLBACK")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "duckduckgo" | "ddg" => "duckduckgo",
+            _ => "none",
+        }
+    }
+
+    fn brave_api_key() -> Result<String, McpError> {
+        // Prefer our env var name; accept nanobot-compatible BRAVE_API_KEY too.
+        if let Ok(v) = std::env::var("BRAVE_SEARCH_API_KEY") {
+            if !v.trim().is_empty() {
+                return Ok(v);
+            }
+        }
+        if let Ok(v) = std::env::var("BRAVE_API_KEY") {
+            if !v.trim().is_empty() {
+                return Ok(v);
+            }
+        }
+        Err(McpError::invalid_params(
+            "missing BRAVE_SEARCH_API_KEY (or BRAVE_API_KEY) env var",
+            Some(json!({})),
+        ))
+    }
+
+    /// Search via Brave, degrading to the fallback provider (or a clear error) while the
+    /// Brave quota is exhausted. Returns `(provider, notice, results)`.
+    async fn web_search(
+        &self,
+        query: &str,
+        count: i64,
+        filters: &SearchFilters,
+    ) -> Result<(&'static str, Option<String>, Vec<serde_json::Value>), McpError> {
+        let query = filters.apply_site(query);
+        let query = query.as_str();
+        let blocked_until = match self.quota.blocked_until().await {
+            Some(until) => until,
+            None => match self.brave_search(query, count, filters).await? {
+                Some(value) => {
+                    let results = value
+                        .get("web")
+                        .and_then(|v| v.get("results"))
+                        .and_then(|v| v.as_array())
+                        .cloned()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .take(count as usize)
+                        .map(|item| {
+                            json!({
+                                "title": item.get("title").and_then(|v| v.as_str()).unwrap_or(""),
+                                "url": item.get("url").and_then(|v| v.as_str()).unwrap_or(""),
+                                "description": item.get("description").and_then(|v| v.as_str()).unwrap_or(""),
+                            })
+                        })
+                        .collect();
+                    return Ok(("brave", None, results));
+                }
+                None => self
+                    .quota
+                    .blocked_until()
+                    .await
+                    .unwrap_or_else(|| chrono::Utc::now().timestamp() + 60),
+            },
+        };
+
+        let until = quota::format_until(blocked_until);
+        if Self::search_fallback() == "duckduckgo" {
+            let results = self.duckduckgo_search(query, count, filters).await?;
+            self.quota.record_fallback().await;
+            let notice =
+                format!("Brave Search quota exhausted until {until}; results are from DuckDuckGo.");
+            return Ok(("duckduckgo", Some(notice), results));
+        }
+        Err(McpError::internal_error(
+            format!(
+                "web search unavailable until {until} (Brave Search quota exhausted); use web_fetch on known URLs meanwhile"
+            ),
+            Some(json!({ "blockedUntil": blocked_until })),
+        ))
+    }
+
+    /// Query Brave. `Ok(None)` means the request was rate limited and the quota tracker
+    /// now knows when searches become available again.
+    async fn brave_search(
+        &self,
+        query: &str,
+        count: i64,
+        filters: &SearchFilters,
+    ) -> Result<Option<serde_json::Value>, McpError> {
+        let key = Self::brave_api_key()?;
+        let count = count.to_string();
+        let mut params = vec![("q", query), ("count", count.as_str())];
+        params.extend(filters.brave_params());
+
+        let mut retried = false;
+        loop {
+            let resp = self
+                .http
+                .get("https://api.search.brave.com/res/v1/web/search")
+                .query(&params)
+                .header("Accept", "application/json")
+                .header("X-Subscription-Token", key.as_str())
+                .send()
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+            let status = resp.status();
+            let windows = quota::parse_rate_headers(resp.headers());
+            let blocked_until = self.quota.record_response(status.as_u16(), windows).await;
+
+            if status.as_u16() == 429 {
+                let wait = blocked_until
+                    .map(|t| t - chrono::Utc::now().timestamp())
+                    .unwrap_or(i64::MAX);
+                if !retried && wait <= MAX_RATE_LIMIT_WAIT_SECS {
+                    retried = true;
+                    tokio::time::sleep(Duration::from_secs(wait.max(1) as u64)).await;
+                    continue;
+                }
+                warn!(blocked_until = ?blocked_until, "brave search rate limited");
+                return Ok(None);
+            }
+
+            let value = resp
+                .json::<serde_json::Value>()
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+            if !status.is_success() {
+                return Err(McpError::internal_error(
+                    format!("brave search http error: {}", status.as_u16()),
+                    Some(value),
+                ));
+            }
+
+            return Ok(Some(value));
+        }
+    }
+
+    async fn duckduckgo_search(
+        &self,
+        query: &str,
+        count: i64,
+        filters: &SearchFilters,
+    ) -> Result<Vec<serde_json::Value>, McpError> {
+        let mut params = vec![("q", query.to_string())];
+        params.extend(filters.duckduckgo_params());
+        let resp = self
+            .http
+            .get("https://html.duckduckgo.com/html/")
+            .query(&params)
+            .send()
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(McpError::internal_error(
+                format!("duckduckgo search http error: {}", status.as_u16()),
+                None,
+            ));
+        }
+        let body = resp
+            .text()
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        Ok(parse_duckduckgo_results(&body, count as usize))
+    }
+
+    async fn validate_fetch_url(&self, url: &reqwest::Url) -> Result<(), McpError> {
+        let scheme = url.scheme();
+        if scheme != "http" && scheme != "https" {
+            return Err(McpError::invalid_params(
+                format!("only http/https urls allowed (got {scheme})"),
+                None,
+            ));
+        }
+        if !url.username().is_empty() || url.password().is_some() {
+            return Err(McpError::invalid_params(
+                "userinfo in URL is not allowed",
+                None,
+            ));
+        }
+
+        let host = url.host_str().unwrap_or("");
+        if host.is_empty() {
+            return Err(McpError::invalid_params("missing host", None));
+        }
+
+        // Block common local hostnames early.
+        let h = host.to_ascii_lowercase();
+        if h == "localhost" || h.ends_with(".localhost") || h.ends_with(".local") {
+            return Err(McpError::invalid_params(
+                "local hostnames are not allowed",
+                None,
+            ));
+        }
+
+        // Optional allow/deny domain lists (role-based restrictions).
+        // Deny takes precedence over allow.
+        if self.deny_domains.iter().any(|d| domain_matches(&h, d)) {
+            return Err(McpError::invalid_params(
+                "domain blocked by GRAIL_WEB_DENY_DOMAINS",
+                Some(json!({ "host": h })),
+            ));
+        }
+        if !self.allow_domains.is_empty()
+            && !self.allow_domains.iter().any(|d| domain_matches(&h, d))
+        {
+            return Err(McpError::invalid_params(
+                "domain not allowed by GRAIL_WEB_ALLOW_DOMAINS",
+                Some(json!({ "host": h })),
+            ));
+        }
+
+        let port = url.port_or_known_default().unwrap_or(0);
+        let expected = match scheme {
+            "http" => 80,
+            "https" => 443,
+            _ => 0,
+        };
+        if port != expected {
+            return Err(McpError::invalid_params(
+                format!("only default ports are allowed (expected {expected}, got {port})"),
+                None,
+            ));
+        }
+
+        // Resolve and block private/reserved IPs to mitigate SSRF.
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            if !is_public_ip(&ip) {
+                return Err(McpError::invalid_params(
+                    "private/reserved IPs are not allowed",
+                    None,
+                ));
+            }
+            return Ok(());
+        }
+
+        let addrs = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        for addr in addrs {
+            if !is_public_ip(&addr.ip()) {
+                return Err(McpError::invalid_params(
+                    "host resolves to private/reserved IP; blocked for safety",
+                    None,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Wait for a request slot on `url`'s host (see `ratelimit`).
+    async fn acquire_host(&self, url: &reqwest::Url) -> Result<(), McpError> {
+        let Some(host) = url.host_str() else {
+            return Ok(());
+        };
+        self.rate_limit.acquire(host).await.map_err(|wait| {
+            McpError::internal_error(
+                format!(
+                    "too many fetches to {host}; retry in {}s (GRAIL_WEB_FETCH_RPS)",
+                    wait.as_secs().max(1)
+                ),
+                Some(json!({ "host": host, "retryAfterSecs": wait.as_secs().max(1) })),
+            )
+        })
+    }
+
+    /// Fetch `url` (every hop after the SSRF and domain checks and its host's rate
+    /// limit), reading at most `MAX_FETCH_BYTES` (`pdf::MAX_BYTES` for PDFs). Fresh cached copies of plain
+    /// GETs are served without a request unless `revalidate` is set.
+    async fn fetch_raw(
+        &self,
+        url: &reqwest::Url,
+        user_agent: Option<&str>,
+        request: &FetchRequest,
+        revalidate: bool,
+    ) -> Result<RawPage, McpError> {
+        self.validate_fetch_url(url).await?;
+        let key = cache::FetchCache::key(url, user_agent);
+        let cached = if request.cacheable() {
+            self.cache.get(&key).await
+        } else {
+            None
+        };
+        if let Some(cached) = &cached {
+            if cached.fresh && !revalidate {
+                return Ok(RawPage {
+                    cache: "hit",
+                    ..cached.page.clone()
+                });
+            }
+        }
+        let http = match &request.session_id {
+            Some(id) => self
+                .sessions
+                .client(id, |jar| http_client(Some(jar)))
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?,
+            None => self.http.clone(),
+        };
+        // Redirects are followed here rather than by the client, so every hop gets the
+        // same SSRF and domain checks (and rate limit) as the first.
+        let mut target = url.clone();
+        let mut method = request.method.clone();
+        let mut body = request.body.clone();
+        let mut redirects = 0;
+        let mut resp = loop {
+            self.acquire_host(&target).await?;
+            let mut req = http.request(method.clone(), target.clone());
+            if let Some(ua) = user_agent {
+                req = req.header(reqwest::header::USER_AGENT, ua);
+            }
+            for (name, value) in &request.headers {
+                req = req.header(name, value);
+            }
+            if let Some(body) = &body {
+                req = req.body(body.clone());
+            }
+            if let Some(cached) = &cached {
+                if let Some(etag) = &cached.etag {
+                    req = req.header(reqwest::header::IF_NONE_MATCH, etag);
+                }
+                if let Some(modified) = &cached.last_modified {
+                    req = req.header(reqwest::header::IF_MODIFIED_SINCE, modified);
+                }
+            }
+            let resp = req
+                .send()
+                .await
+                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+            let status = resp.status();
+            let location = resp
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|loc| target.join(loc.trim()).ok());
+            let Some(next) = location
+                .filter(|_| status.is_redirection() && status != reqwest::StatusCode::NOT_MODIFIED)
+            else {
+                break resp;
+            };
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                return Err(McpError::internal_error(
+                    format!("more than {MAX_REDIRECTS} redirects"),
+                    Some(json!({ "url": target.to_string() })),
+                ));
+            }
+            self.validate_fetch_url(&next).await?;
+            // 301/302/303 turn a POST into a GET, as browsers do; 307/308 resend it.
+            if matches!(status.as_u16(), 301..=303) && method != reqwest::Method::HEAD {
+                method = reqwest::Method::GET;
+                body = None;
+            }
+            target = next;
+        };
+        if resp.status() == reqwest::StatusCode::NOT_MODIFIED && cached.is_some() {
+            if let Some(page) = self.cache.revalidated(&key).await {
+                return Ok(RawPage {
+                    cache: "revalidated",
+                    ..page
+                });
+            }
+        }
+
+        let status = resp.status().as_u16();
+        let final_url = resp.url().to_string();
+        let headers = resp.headers().clone();
+        let content_type = headers
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("")
+            .to_string();
+        let limit = if pdf::is_pdf(&content_type, &final_url) {
+            pdf::MAX_BYTES
+        } else {
+            MAX_FETCH_BYTES
+        };
+
+        let mut body: Vec<u8> = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?
+        {
+            if body.len() + chunk.len() > limit {
+                let remaining = limit.saturating_sub(body.len());
+                body.extend_from_slice(&chunk[..remaining]);
+                truncated = true;
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        let page = RawPage {
+            status,
+            final_url,
+            content_type,
+            body,
+            truncated,
+            cache: "miss",
+            rendered: false,
+        };
+        if request.cacheable() {
+            self.cache.store(&key, &page, &headers).await;
+        }
+        Ok(page)
+    }
+
+    /// Replace an HTML page's body with the DOM Chromium renders for `url`.
+    #[cfg(feature = "render")]
+    async fn render_page(&self, url: &reqwest::Url, page: RawPage) -> Result<RawPage, McpError> {
+        let html = page.content_type.to_ascii_lowercase().contains("text/html");
+        if !html || !(200..300).contains(&page.status) {
+            return Ok(page);
+        }
+        let allow = |u: reqwest::Url| async move { self.validate_fetch_url(&u).await.is_ok() };
+        let rendered = render::render(url, allow)
+            .await
+            .map_err(|e| McpError::internal_error(format!("render: {e:#}"), None))?;
+        Ok(RawPage {
+            final_url: rendered.final_url,
+            content_type: "text/html; charset=utf-8".to_string(),
+            body: rendered.html.into_bytes(),
+            truncated: false,
+            rendered: true,
+            ..page
+        })
+    }
+
+    async fn extract_page(
+        &self,
+        page: &RawPage,
+        extract_mode: &str,
+    ) -> Result<Extracted<'_>, McpError> {
+        // A `.pdf` URL alone isn't enough here: error pages there are usually text.
+        if page.body.starts_with(b"%PDF-")
+            || page
+                .content_type
+                .to_ascii_lowercase()
+                .contains("application/pdf")
+        {
+            return extract_pdf(page).await;
+        }
+        // Rules follow the final host, so a redirect to a docs site still gets its hints.
+        let rule = resp_host(&page.final_url).and_then(|h| self.extract_rules.for_host(&h));
+        let base = reqwest::Url::parse(&page.final_url).ok();
+        extract_bytes(
+            &page.body,
+            &page.content_type,
+            extract_mode,
+            rule,
+            base.as_ref(),
+        )
+        .map_err(|e| McpError::internal_error(e.to_string(), None))
+    }
+
+    /// Alternate ways to get past a wall on `url`, in the order they're tried.
+    fn fallback_attempts(
+        &self,
+        url: &reqwest::Url,
+        page: &RawPage,
+    ) -> Vec<(walls::Strategy, reqwest::Url, Option<&'static str>)> {
+        let base = reqwest::Url::parse(&page.final_url).unwrap_or_else(|_| url.clone());
+        let mut out: Vec<_> = walls::alternate_links(&String::from_utf8_lossy(&page.body), &base)
+            .into_iter()
+            .map(|(strategy, u)| (strategy, u, None))
+            .collect();
+        if url
+            .host_str()
+            .is_some_and(|h| self.fallbacks.googlebot_allowed(h))
+        {
+            out.push((
+                walls::Strategy::Googlebot,
+                url.clone(),
+                Some(walls::GOOGLEBOT_USER_AGENT),
+            ));
+        }
+        if let Some(reader) = self.fallbacks.reader_url(url) {
+            out.push((walls::Strategy::Reader, reader, None));
+        }
+        out
+    }
+
+    pub async fn fetch_url(
+        &self,
+        url: &reqwest::Url,
+        extract_mode: &str,
+        max_chars: usize,
+    ) -> Result<serde_json::Value, McpError> {
+        self.fetch_page(
+            url,
+            extract_mode,
+            max_chars,
+            &FetchRequest::default(),
+            false,
+        )
+        .await
+    }
+
+    async fn fetch_page(
+        &self,
+        url: &reqwest::Url,
+        extract_mode: &str,
+        max_chars: usize,
+        request: &FetchRequest,
+        revalidate: bool,
+    ) -> Result<serde_json::Value, McpError> {
+        let mut page = self.fetch_raw(url, None, request, revalidate).await?;
+        #[cfg(feature = "render")]
+        if request.render {
+            page = self.render_page(url, page).await?;
+        }
+        let mut extracted = self.extract_page(&page, extract_mode).await?;
+        let mut strategy = walls::Strategy::Direct;
+
+        let wall = if self.fallbacks.enabled
+            && request.is_plain_get()
+            && matches!(extracted.extractor, "readability" | "html")
+        {
+            walls::detect(&extracted.text)
+        } else {
+            None
+        };
+        let mut tried = Vec::new();
+        if wall.is_some() {
+            for (candidate, alt_url, user_agent) in self.fallback_attempts(url, &page) {
+                tried.push(candidate.as_str());
+                let alt = match self
+                    .fetch_raw(&alt_url, user_agent, request, revalidate)
+                    .await
+                {
+                    Ok(alt) if (200..300).contains(&alt.status) => alt,
+                    Ok(alt) => {
+                        info!(
+                            strategy = candidate.as_str(),
+                            status = alt.status,
+                            "web_fetch fallback failed"
+                        );
+                        continue;
+                    }
+                    Err(err) => {
+                        info!(strategy = candidate.as_str(), error = %err.message, "web_fetch fallback failed");
+                        continue;
+                    }
+                };
+                let alt_extracted = self.extract_page(&alt, extract_mode).await?;
+                if walls::detect(&alt_extracted.text).is_none() {
+                    page = alt;
+                    extracted = alt_extracted;
+                    strategy = candidate;
+                    break;
+                }
+            }
+        }
+
+        let mut text = extracted.text;
+        let mut truncated = page.truncated;
+        if text.chars().count() > max_chars {
+            text = grail_text::head(&text, max_chars).to_string();
+            truncated = true;
+        }
+
+        let mut out = json!({
+            "url": url.to_string(),
+            "method": request.method.as_str(),
+            "sessionId": request.session_id,
+            "finalUrl": page.final_url,
+            "status": page.status,
+            "contentType": page.content_type,
+            "extractMode": extract_mode,
+            "extractor": extracted.extractor,
+            "title": extracted.title,
+            "pages": extracted.pages,
+            "strategy": strategy.as_str(),
+            "cache": page.cache,
+            "rendered": page.rendered,
+            "truncated": truncated,
+            "length": text.chars().count(),
+            "text": text,
+        });
+        if let (Some(rule), Some(applied)) = (extracted.rule, extracted.applied) {
+            out["extractRule"] = json!({
+                "domain": rule.domain,
+                "selectorMatched": applied.selector_matched,
+                "stripped": applied.stripped,
+            });
+        }
+        if let Some(wall) = wall {
+            out["wall"] = json!({
+                "kind": wall.as_str(),
+                "bypassed": strategy != walls::Strategy::Direct,
+                "tried": tried,
+            });
+        }
+        Ok(out)
+    }
+
+    /// Fetch `url`, diff it against `scope`'s stored snapshot and replace the snapshot.
+    /// Pages are compared on extracted text, so markup-only changes don't count. Cached
+    /// copies are always revalidated first.
+    async fn watch_diff(
+        &self,
+        url: &reqwest::Url,
+        scope: &str,
+        max_chars: usize,
+    ) -> Result<serde_json::Value, McpError> {
+        let page = self
+            .fetch_page(url, "markdown", 200_000, &FetchRequest::default(), true)
+            .await?;
+        let status = page.get("status").and_then(|v| v.as_u64()).unwrap_or(0);
+        if !(200..300).contains(&status) {
+            return Err(McpError::internal_error(
+                format!("watched page returned http {status}; snapshot not updated"),
+                Some(json!({ "url": url.to_string(), "status": status })),
+            ));
+        }
+        let text = page
+            .get("text")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        let key = url.to_string();
+        let now = chrono::Utc::now().timestamp();
+        let snapshot = watch::Snapshot {
+            scope: scope.to_string(),
+            url: key.clone(),
+            hash: watch::content_hash(&text),
+            fetched_at: now,
+            text,
+        };
+
+        let previous = self.watch.load(scope, &key).await;
+        let mut out = json!({
+            "url": key,
+            "scope": scope,
+            "hash": snapshot.hash,
+            "fetchedAt": now,
+            "length": snapshot.text.chars().count(),
+        });
+        match &previous {
+            None => {
+                out["firstSnapshot"] = json!(true);
+                out["changed"] = json!(false);
+            }
+            Some(prev) => {
+                out["firstSnapshot"] = json!(false);
+                out["previousFetchedAt"] = json!(prev.fetched_at);
+                out["changed"] = json!(prev.hash != snapshot.hash);
+                if prev.hash != snapshot.hash {
+                    let diff = watch::diff_lines(&prev.text, &snapshot.text);
+                    let truncated = diff.text.chars().count() > max_chars;
+                    let text = grail_text::head(&diff.text, max_chars);
+                    out["added"] = json!(diff.added);
+                    out["removed"] = json!(diff.removed);
+                    out["truncated"] = json!(truncated);
+                    out["diff"] = json!(text);
+                }
+            }
+        }
+
+        if previous.as_ref().map(|p| p.hash.as_str()) != Some(snapshot.hash.as_str()) {
+            self.watch.save(&snapshot).await.map_err(|e| {
+                McpError::internal_error(format!("store watch snapshot: {e}"), None)
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Clone)]
+struct RawPage {
+    status: u16,
+    final_url: String,
+    content_type: String,
+    body: Vec<u8>,
+    truncated: bool,
+    /// `hit`, `revalidated` (304) or `miss`.
+    cache: &'static str,
+    /// Whether `body` is the DOM rendered by headless Chromium.
+    rendered: bool,
+}
+
+struct Extracted<'a> {
+    /// `readability` (main content), `html` (whole page), `pdf`, `json` or `raw`.
+    extractor: &'static str,
+    text: String,
+    title: Option<String>,
+    /// Page count, for PDFs.
+    pages: Option<usize>,
+    rule: Option<&'a extract_rules::ExtractRule>,
+    applied: Option<extract_rules::Applied>,
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct ArgsWebWatchDiff {
+    url: String,
+    #[serde(default)]
+    scope: Option<String>,
+    #[serde(default)]
+    maxChars: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct ArgsWebSearch {
+    query: String,
+    #[serde(default)]
+    count: Option<i64>,
+    #[serde(default)]
+    site: Option<String>,
+    #[serde(default)]
+    country: Option<String>,
+    #[serde(default)]
+    search_lang: Option<String>,
+    #[serde(default)]
+    freshness: Option<String>,
+}
+
+/// Optional web_search restrictions. `site` becomes a `site:` query operator (understood
+/// by both providers); the rest map onto Brave query parameters.
+#[derive(Debug, Default)]
+struct SearchFilters {
+    site: Option<String>,
+    country: Option<String>,
+    search_lang: Option<String>,
+    freshness: Option<String>,
+}
+
+impl SearchFilters {
+    fn parse(args: &ArgsWebSearch) -> Result<Self, McpError> {
+        let field = |v: &Option<String>| {
+            v.as_deref()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        let mut out = Self::default();
+
+        if let Some(site) = field(&args.site) {
+            let host = site
+                .split("://")
+                .last()
+                .unwrap_or_default()
+                .split(['/', '?', '#'])
+                .next()
+                .unwrap_or_default()
+                .trim_matches('.')
+                .to_ascii_lowercase();
+            if !SITE_RE.is_match(&host) {
+                return Err(McpError::invalid_params(
+                    format!("site must be a domain such as docs.rs (got {site:?})"),
+                    None,
+                ));
+            }
+            out.site = Some(host);
+        }
+
+        if let Some(lang) = field(&args.search_lang) {
+            let lang = lang.to_ascii_lowercase().replace('_', "-");
+            let (base, region) = lang.split_once('-').unwrap_or((lang.as_str(), ""));
+            let valid = base.len() == 2
+                && base.chars().all(|c| c.is_ascii_lowercase())
+                && region.chars().all(|c| c.is_ascii_alphanumeric());
+            if !valid {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "search_lang must be a language code such as de or en-gb (got {lang:?})"
+                    ),
+                    None,
+                ));
+            }
+            if BRAVE_REGIONAL_LANGS.contains(&lang.as_str()) {
+                out.search_lang = Some(lang.clone());
+            } else {
+                // Brave's code for Japanese is `jp`, not ISO 639's `ja`.
+                let base = if base == "ja" { "jp" } else { base };
+                out.search_lang = Some(base.to_string());
+                if !region.is_empty() {
+                    out.country = Some(region.to_ascii_uppercase());
+                }
+            }
+        }
+
+        if let Some(country) = field(&args.country) {
+            let country = country.to_ascii_uppercase();
+            if country.len() != 2 || !country.chars().all(|c| c.is_ascii_uppercase()) {
+                return Err(McpError::invalid_params(
+                    format!(
+                        "country must be a two-letter country code such as DE (got {country:?})"
+                    ),
+                    None,
+                ));
+            }
+            out.country = Some(country);
+        }
+        if let Some(country) = out.country.as_deref() {
+            if country.len() != 2 {
+                return Err(McpError::invalid_params(
+                    format!("unsupported search_lang region {country:?}"),
+                    None,
+                ));
+            }
+        }
+
+        if let Some(freshness) = field(&args.freshness) {
+            let freshness = match freshness.to_ascii_lowercase().as_str() {
+                "pd" | "day" => "pd".to_string(),
+                "pw" | "week" => "pw".to_string(),
+                "pm" | "month" => "pm".to_string(),
+                "py" | "year" => "py".to_string(),
+                other if FRESHNESS_RANGE_RE.is_match(other) => other.to_string(),
+                _ => {
+                    return Err(McpError::invalid_params(
+                        format!(
+                            "freshness must be pd, pw, pm, py, or YYYY-MM-DDtoYYYY-MM-DD (got {freshness:?})"
+                        ),
+                        None,
+                    ))
+                }
+            };
+            out.freshness = Some(freshness);
+        }
+
+        Ok(out)
+    }
+
+    fn apply_site(&self, query: &str) -> String {
+        match self.site.as_deref() {
+            Some(site) => format!("{query} site:{site}"),
+            None => query.to_string(),
+        }
+    }
+
+    fn brave_params(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("country", self.country.as_deref()),
+            ("search_lang", self.search_lang.as_deref()),
+            ("freshness", self.freshness.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(k, v)| Some((k, v?)))
+        .collect()
+    }
+
+    /// DuckDuckGo only knows region (`kl`, e.g. `de-de`) and coarse time ranges (`df`).
+    fn duckduckgo_params(&self) -> Vec<(&'static str, String)> {
+        let mut out = Vec::new();
+        if let Some(country) = self.country.as_deref() {
+            let lang = self
+                .search_lang
+                .as_deref()
+                .and_then(|l| l.split('-').next())
+                .unwrap_or("en");
+            out.push(("kl", format!("{}-{lang}", country.to_ascii_lowercase())));
+        }
+        let df = match self.freshness.as_deref() {
+            Some("pd") => Some("d"),
+            Some("pw") => Some("w"),
+            Some("pm") => Some("m"),
+            Some("py") => Some("y"),
+            _ => None,
+        };
+        if let Some(df) = df {
+            out.push(("df", df.to_string()));
+        }
+        out
+    }
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct ArgsWebFetch {
+    url: String,
+    #[serde(default)]
+    extractMode: Option<String>,
+    #[serde(default)]
+    maxChars: Option<usize>,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    body: Option<serde_json::Value>,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    render: Option<bool>,
+}
+
+/// What `web_fetch` sends beyond the URL; the default is a plain GET.
+#[derive(Default)]
+struct FetchRequest {
+    method: reqwest::Method,
+    headers: Vec<(reqwest::header::HeaderName, reqwest::header::HeaderValue)>,
+    body: Option<Vec<u8>>,
+    /// Cookie session shared with other fetches naming it.
+    session_id: Option<String>,
+    /// Render the page in headless Chromium before extraction.
+    #[cfg(feature = "render")]
+    render: bool,
+}
+
+impl FetchRequest {
+    fn parse(args: &ArgsWebFetch) -> Result<Self, McpError> {
+        let method = match args
+            .method
+            .as_deref()
+            .map(|m| m.trim().to_ascii_uppercase())
+            .as_deref()
+        {
+            None | Some("") | Some("GET") => reqwest::Method::GET,
+            Some("POST") => reqwest::Method::POST,
+            Some(other) => {
+                return Err(McpError::invalid_params(
+                    format!("unsupported method: {other} (use GET or POST)"),
+                    None,
+                ))
+            }
+        };
+
+        let mut headers = Vec::new();
+        for (name, value) in &args.headers {
+            let name = name.trim().to_ascii_lowercase();
+            if !ALLOWED_REQUEST_HEADERS.contains(&name.as_str()) {
+                return Err(McpError::invalid_params(
+                    format!("header not allowed: {name}"),
+                    Some(json!({ "allowed": ALLOWED_REQUEST_HEADERS })),
+                ));
+            }
+            let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+            let value = reqwest::header::HeaderValue::from_str(value.trim()).map_err(|_| {
+                McpError::invalid_params(format!("invalid value for header {name}"), None)
+            })?;
+            headers.push((name, value));
+        }
+
+        let body = match &args.body {
+            None | Some(serde_json::Value::Null) => None,
+            Some(_) if method == reqwest::Method::GET => {
+                return Err(McpError::invalid_params("body requires method POST", None))
+            }
+            Some(serde_json::Value::String(s)) => Some(s.clone().into_bytes()),
+            Some(value) => {
+                if !headers
+                    .iter()
+                    .any(|(name, _)| name == reqwest::header::CONTENT_TYPE)
+                {
+                    headers.push((
+                        reqwest::header::CONTENT_TYPE,
+                        reqwest::header::HeaderValue::from_static("application/json"),
+                    ));
+                }
+                Some(value.to_string().into_bytes())
+            }
+        };
+        if body
+            .as_ref()
+            .is_some_and(|b| b.len() > MAX_REQUEST_BODY_BYTES)
+        {
+            return Err(McpError::invalid_params(
+                format!("body is larger than {MAX_REQUEST_BODY_BYTES} bytes"),
+                None,
+            ));
+        }
+
+        let session_id = args
+            .session_id
+            .as_deref()
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::to_string);
+        if session_id
+            .as_ref()
+            .is_some_and(|id| id.chars().count() > sessions::MAX_ID_CHARS)
+        {
+            return Err(McpError::invalid_params(
+                format!(
+                    "session_id is longer than {} characters",
+                    sessions::MAX_ID_CHARS
+                ),
+                None,
+            ));
+        }
+
+        let render = args.render.unwrap_or(false);
+        if render && !cfg!(feature = "render") {
+            return Err(McpError::invalid_params(
+                "render is not available: grail-web-mcp was built without the render feature",
+                None,
+            ));
+        }
+
+        let request = Self {
+            method,
+            headers,
+            body,
+            session_id,
+            #[cfg(feature = "render")]
+            render,
+        };
+        // The browser makes its own requests, without the arguments or the cookie jar.
+        if render && !request.cacheable() {
+            return Err(McpError::invalid_params(
+                "render can't be combined with method, headers, body or session_id",
+                None,
+            ));
+        }
+        Ok(request)
+    }
+
+    /// Only plain GETs are retried past walls.
+    fn is_plain_get(&self) -> bool {
+        self.method == reqwest::Method::GET && self.headers.is_empty() && self.body.is_none()
+    }
+
+    /// Responses depend on cookies in a session, so only session-less plain GETs are
+    /// cached.
+    fn cacheable(&self) -> bool {
+        self.is_plain_get() && self.session_id.is_none()
+    }
+}
+
+impl ServerHandler for WebMcpServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_tool_list_changed()
+                .build(),
+            ..ServerInfo::default()
+        }
+    }
+
+    fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
+    ) -> impl std::future::Future<Output = Result<ListToolsResult, McpError>> + Send + '_ {
+        let tools = self.tools.clone();
+        async move {
+            Ok(ListToolsResult {
+                tools: (*tools).clone(),
+                next_cursor: None,
+                meta: None,
+            })
+        }
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        _context: rmcp::service::RequestContext<rmcp::service::RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        match request.name.as_ref() {
+            "web_search" => {
+                let args = parse_args::<ArgsWebSearch>(&request, "web_search")?;
+                let q = args.query.trim();
+                if q.is_empty() {
+                    return Err(McpError::invalid_params("query is required", None));
+                }
+                let count = args.count.unwrap_or(5).clamp(1, 10);
+                let filters = SearchFilters::parse(&args)?;
+
+                let (provider, notice, results) = self.web_search(q, count, &filters).await?;
+
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(json!({
+                        "query": q,
+                        "count": count,
+                        "site": filters.site,
+                        "country": filters.country,
+                        "search_lang": filters.search_lang,
+                        "freshness": filters.freshness,
+                        "provider": provider,
+                        "notice": notice,
+                        "results": results,
+                    })),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "web_fetch" => {
+                let args = parse_args::<ArgsWebFetch>(&request, "web_fetch")?;
+                let url = reqwest::Url::parse(args.url.trim())
+                    .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+                let extract_mode = match args.extractMode.as_deref().map(str::trim) {
+                    Some(mode) if !mode.is_empty() => mode.to_string(),
+                    _ => url
+                        .host_str()
+                        .and_then(|h| self.extract_rules.for_host(h))
+                        .and_then(|r| r.extract_mode.clone())
+                        .unwrap_or_else(|| "markdown".to_string()),
+                };
+                let max_chars = args.maxChars.unwrap_or(50_000).clamp(100, 200_000);
+                let fetch_request = FetchRequest::parse(&args)?;
+
+                let data = self
+                    .fetch_page(&url, &extract_mode, max_chars, &fetch_request, false)
+                    .await?;
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(data),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "web_quota" => {
+                let state = self.quota.snapshot().await;
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(quota::quota_json(&state, Self::search_fallback())),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            "web_watch_diff" => {
+                let args = parse_args::<ArgsWebWatchDiff>(&request, "web_watch_diff")?;
+                let url = reqwest::Url::parse(args.url.trim())
+                    .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+                let scope = args.scope.as_deref().unwrap_or("").trim();
+                let max_chars = args.maxChars.unwrap_or(8_000).clamp(100, 50_000);
+
+                let data = self.watch_diff(&url, scope, max_chars).await?;
+                Ok(CallToolResult {
+                    content: Vec::new(),
+                    structured_content: Some(data),
+                    is_error: Some(false),
+                    meta: None,
+                })
+            }
+            other => Err(McpError::invalid_params(
+                format!("unknown tool: {other}"),
+                None,
+            )),
+        }
+    }
+}
+
+/// The fetch client; `jar` keeps cookies for a `session_id`.
+fn http_client(jar: Option<Arc<reqwest::cookie::Jar>>) -> anyhow::Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        // `fetch_raw` follows redirects itself, validating each hop.
+        .redirect(reqwest::redirect::Policy::none())
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(30));
+    if let Some(jar) = jar {
+        builder = builder.cookie_provider(jar);
+    }
+    builder.build().context("build http client")
+}
+
+fn parse_args<T: for<'de> Deserialize<'de>>(
+    request: &CallToolRequestParam,
+    tool_name: &'static str,
+) -> Result<T, McpError> {
+    match request.arguments.as_ref() {
+        Some(arguments) => serde_json::from_value(serde_json::Value::Object(
+            arguments.clone().into_iter().collect(),
+        ))
+        .map_err(|err| McpError::invalid_params(err.to_string(), None)),
+        None => Err(McpError::invalid_params(
+            format!("missing arguments for {tool_name} tool"),
+            None,
+        )),
+    }
+}
+
+fn resp_host(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(str::to_string)
+}
+
+/// Extract a PDF's text on the blocking pool. A PDF cut off at `pdf::MAX_BYTES` can't
+/// be parsed, so that is an error rather than a truncated result.
+async fn extract_pdf(page: &RawPage) -> Result<Extracted<'static>, McpError> {
+    if page.truncated {
+        return Err(McpError::internal_error(
+            format!(
+                "PDF is larger than {} MB; only whole files can be read",
+                pdf::MAX_BYTES / 1_000_000
+            ),
+            Some(json!({ "url": page.final_url })),
+        ));
+    }
+    let body = page.body.clone();
+    let parsed = task::spawn_blocking(move || pdf::extract(&body))
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?
+        .map_err(|e| McpError::internal_error(format!("read PDF: {e:#}"), None))?;
+    Ok(Extracted {
+        extractor: "pdf",
+        text: normalize_whitespace(&parsed.text),
+        title: None,
+        pages: Some(parsed.pages),
+        rule: None,
+        applied: None,
+    })
+}
+
+/// HTML is narrowed to its main content (unless the site's rule selected it already)
+/// and converted to Markdown; `text` mode drops the Markdown syntax. Links are resolved
+/// against `base`.
+fn extract_bytes<'a>(
+    body: &[u8],
+    content_type: &str,
+    extract_mode: &str,
+    rule: Option<&'a extract_rules::ExtractRule>,
+    base: Option<&reqwest::Url>,
+) -> anyhow::Result<Extracted<'a>> {
+    let ct = content_type.to_ascii_lowercase();
+    if ct.contains("application/json") {
+        if let Ok(v) = serde_json::from_slice::<serde_json::Value>(body) {
+            let pretty = serde_json::to_string_pretty(&v)?;
+            return Ok(Extracted {
+                extractor: "json",
+                text: pretty,
+                title: None,
+                pages: None,
+                rule: None,
+                applied: None,
+            });
+        }
+    }
+
+    let s = String::from_utf8_lossy(body).to_string();
+    let head = s.chars().take(256).collect::<String>().to_ascii_lowercase();
+    if ct.contains("text/html")
+        || head.trim_start().starts_with("<!doctype")
+        || head.contains("<html")
+    {
+        let page = Html::parse_document(&s);
+        let title = readability::title(&page);
+        let applied = rule.map(|r| r.apply(&s));
+        let doc = match &applied {
+            Some(applied) => Html::parse_document(&applied.html),
+            None => page,
+        };
+        let selected = applied.as_ref().and_then(|a| a.selector_matched) == Some(true);
+        let article = (!selected).then(|| readability::extract(&doc)).flatten();
+        let plain = extract_mode == "text";
+        let (extractor, text) = match article {
+            Some(article) => (
+                "readability",
+                markdown::render(&Html::parse_document(&article), base, plain),
+            ),
+            None => ("html", markdown::render(&doc, base, plain)),
+        };
+        return Ok(Extracted {
+            extractor,
+            text: normalize_whitespace(&text),
+            title,
+            pages: None,
+            rule,
+            applied,
+        });
+    }
+
+    Ok(Extracted {
+        extractor: "raw",
+        text: normalize_whitespace(&s),
+        title: None,
+        pages: None,
+        rule: None,
+        applied: None,
+    })
+}
+
+/// Extract results from DuckDuckGo's HTML endpoint. Result links are redirects that
+/// carry the target URL in the `uddg` query parameter.
+fn parse_duckduckgo_results(body: &str, count: usize) -> Vec<serde_json::Value> {
+    let anchors: Vec<_> = DDG_RESULT_RE.captures_iter(body).collect();
+    let mut out = Vec::new();
+    for (i, cap) in anchors.iter().enumerate() {
+        if out.len() >= count {
+            break;
+        }
+        let Some(href) = HREF_RE
+            .captures(&cap[1])
+            .map(|c| decode_html_entities(&c[1]))
+        else {
+            continue;
+        };
+        let href = if href.starts_with("//") {
+            format!("https:{href}")
+        } else {
+            href
+        };
+        let Ok(parsed) = reqwest::Url::parse(&href) else {
+            continue;
+        };
+        let url = parsed
+            .query_pairs()
+            .find(|(k, _)| k == "uddg")
+            .map(|(_, v)| v.into_owned())
+            .unwrap_or_else(|| parsed.to_string());
+        if !url.starts_with("http") || url.contains("duckduckgo.com/y.js") {
+            continue;
+        }
+
+        let end = cap.get(0).map(|m| m.end()).unwrap_or(0);
+        let next = anchors
+            .get(i + 1)
+            .and_then(|c| c.get(0))
+            .map(|m| m.start())
+            .unwrap_or(body.len());
+        let description = DDG_SNIPPET_RE
+            .captures(&body[end..next])
+            .map(|c| strip_tags(&c[1]))
+            .unwrap_or_default();
+
+        out.push(json!({
+            "title": strip_tags(&cap[2]),
+            "url": url,
+            "description": description,
+        }));
+    }
+    out
+}
+
+fn strip_tags(s: &str) -> String {
+    decode_html_entities(TAG_RE.replace_all(s, "").trim())
+}
+
+fn decode_html_entities(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn normalize_whitespace(input: &str) -> String {
+    let s = input.replace("\r\n", "\n").replace('\r', "\n");
+    let mut out = String::with_capacity(s.len());
+    let mut last_was_nl = false;
+    let mut nl_run = 0usize;
+    for ch in s.chars() {
+        if ch == '\n' {
+            if last_was_nl {
+                nl_run += 1;
+            } else {
+                nl_run = 1;
+            }
+            // cap newlines at 2
+            if nl_run <= 2 {
+                out.push('\n');
+            }
+            last_was_nl = true;
+            continue;
+        }
+        last_was_nl = false;
+        nl_run = 0;
+        out.push(ch);
+    }
+    out.trim().to_string()
+}
+
+fn is_public_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_multicast()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_unspecified())
+        }
+        IpAddr::V6(v6) => {
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || v6.is_unique_local()
+                || v6.is_unicast_link_local()
+                || is_ipv6_documentation(v6))
+        }
+    }
+}
+
+fn parse_domain_list_env(key: &str) -> Vec<String> {
+    std::env::var(key)
+        .map(|v| parse_domain_list(&v))
+        .unwrap_or_default()
+}
+
+fn parse_domain_list(v: &str) -> Vec<String> {
+    v.split(|c: char| c == ',' || c == '\n' || c == '\r' || c == '\t' || c == ' ')
+        .map(|s| s.trim().trim_matches('.').to_ascii_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn domain_matches(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    // Allow subdomains.
+    host.ends_with(&format!(".{domain}"))
+}
+
+fn is_ipv6_documentation(v6: &std::net::Ipv6Addr) -> bool {
+    // 2001:db8::/32 is reserved for documentation.
+    let seg = v6.segments();
+    seg[0] == 0x2001 && seg[1] == 0x0db8
+}
+
+/// Serve the web tools over stdio until the client disconnects.
+pub async fn serve_stdio() -> anyhow::Result<()> {
+    let service = WebMcpServer::new()?;
+    info!("starting web mcp server (stdio)");
+
+    let running = service.serve(stdio()).await?;
+    if let Err(err) = running.waiting().await {
+        error!(error = %err, "mcp server exiting");
+        return Err(anyhow::Error::new(err));
+    }
+
+    task::yield_now().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters(args: serde_json::Value) -> Result<SearchFilters, McpError> {
+        SearchFilters::parse(&serde_json::from_value::<ArgsWebSearch>(args).unwrap())
+    }
+
+    #[test]
+    fn search_filters_normalize_site_lang_and_freshness() {
+        let f = filters(json!({
+            "query": "q",
+            "site": "https://Docs.RS/tokio/latest",
+            "search_lang": "de_DE",
+            "freshness": "week",
+        }))
+        .unwrap();
+        assert_eq!(f.site.as_deref(), Some("docs.rs"));
+        assert_eq!(f.search_lang.as_deref(), Some("de"));
+        assert_eq!(f.country.as_deref(), Some("DE"));
+        assert_eq!(f.freshness.as_deref(), Some("pw"));
+        assert_eq!(f.apply_site("tokio"), "tokio site:docs.rs");
+    }
+
+    #[test]
+    fn search_filters_keep_brave_regional_langs() {
+        let f = filters(json!({ "query": "q", "search_lang": "en-GB", "country": "us" })).unwrap();
+        assert_eq!(f.search_lang.as_deref(), Some("en-gb"));
+        assert_eq!(f.country.as_deref(), Some("US"));
+    }
+
+    #[test]
+    fn search_filters_use_brave_code_for_japanese() {
+        let f = filters(json!({ "query": "q", "search_lang": "ja-JP" })).unwrap();
+        assert_eq!(f.search_lang.as_deref(), Some("jp"));
+        assert_eq!(f.country.as_deref(), Some("JP"));
+        let f = filters(json!({ "query": "q", "search_lang": "ja" })).unwrap();
+        assert_eq!(f.search_lang.as_deref(), Some("jp"));
+        assert_eq!(f.country, None);
+    }
+
+    #[test]
+    fn search_filters_reject_invalid_values() {
+        for args in [
+            json!({ "query": "q", "site": "not a domain" }),
+            json!({ "query": "q", "search_lang": "deu" }),
+            json!({ "query": "q", "search_lang": "de-bavaria" }),
+            json!({ "query": "q", "country": "DEU" }),
+            json!({ "query": "q", "freshness": "fortnight" }),
+        ] {
+            assert!(filters(args.clone()).is_err(), "{args}");
+        }
+        let f = filters(json!({ "query": "q", "freshness": "2024-01-01to2024-02-01" })).unwrap();
+        assert_eq!(f.freshness.as_deref(), Some("2024-01-01to2024-02-01"));
+    }
+
+    #[test]
+    fn brave_params_skip_unset_filters() {
+        let f =
+            filters(json!({ "query": "q", "search_lang": "fr-CA", "freshness": "pd" })).unwrap();
+        assert_eq!(
+            f.brave_params(),
+            vec![
+                ("country", "CA"),
+                ("search_lang", "fr"),
+                ("freshness", "pd")
+            ]
+        );
+        assert!(SearchFilters::default().brave_params().is_empty());
+    }
+
+    #[test]
+    fn duckduckgo_params_map_region_and_time() {
+        let f =
+            filters(json!({ "query": "q", "search_lang": "de-AT", "freshness": "month" })).unwrap();
+        assert_eq!(
+            f.duckduckgo_params(),
+            vec![("kl", "at-de".to_string()), ("df", "m".to_string())]
+        );
+        let f = filters(
+            json!({ "query": "q", "country": "GB", "freshness": "2024-01-01to2024-02-01" }),
+        )
+        .unwrap();
+        assert_eq!(f.duckduckgo_params(), vec![("kl", "gb-en".to_string())]);
+    }
+
+    #[test]
+    fn parse_duckduckgo_results_decodes_redirects() {
+        let body = r#"
+            <a rel="nofollow" class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fdocs.rs%2Ftokio&amp;rut=x">Tokio &amp; <b>docs</b></a>
+            <a class="result__snippet" href="x">An <b>async</b> runtime</a>
+            <a rel="nofollow" class="result__a" href="https://duckduckgo.com/y.js?ad=1">Ad</a>
+            <a rel="nofollow" class="result__a" href="https://example.com/direct">Direct</a>
+        "#;
+        let results = parse_duckduckgo_results(body, 10);
+        assert_eq!(results.len(), 2, "{results:?}");
+        assert_eq!(results[0]["url"], "https://docs.rs/tokio");
+        assert_eq!(results[0]["title"], "Tokio & docs");
+        assert_eq!(results[0]["description"], "An async runtime");
+        assert_eq!(results[1]["url"], "https://example.com/direct");
+        assert_eq!(parse_duckduckgo_results(body, 1).len(), 1);
+    }
+}
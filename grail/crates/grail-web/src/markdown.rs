@@ -0,0 +1,514 @@
+//! HTML → Markdown for `web_fetch`. Headings, links (resolved against the page URL),
+//! emphasis, inline code, code blocks, quotes, nested lists, images and tables are kept;
+//! scripts, styles and form controls are dropped.
+//!
+//! `text` mode renders the same structure without Markdown syntax: headings and list
+//! items stay on their own lines, links and images keep only their text.
+
+use scraper::{ElementRef, Html, Node};
+
+/// Content that is never shown.
+const SKIPPED_TAGS: [&str; 13] = [
+    "head", "script", "style", "noscript", "template", "iframe", "svg", "canvas", "object",
+    "button", "input", "select", "textarea",
+];
+const BLOCK_TAGS: [&str; 20] = [
+    "p",
+    "div",
+    "section",
+    "article",
+    "main",
+    "header",
+    "footer",
+    "nav",
+    "aside",
+    "figure",
+    "figcaption",
+    "address",
+    "details",
+    "summary",
+    "dl",
+    "dt",
+    "dd",
+    "form",
+    "fieldset",
+    "center",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Break {
+    None,
+    Line,
+    Blank,
+}
+
+struct List {
+    /// The next item's number; `None` for bullets.
+    next: Option<usize>,
+    /// Indent of the current item's continuation lines.
+    indent: usize,
+}
+
+struct Writer<'a> {
+    out: String,
+    plain: bool,
+    base: Option<&'a reqwest::Url>,
+    pending: Break,
+    space: bool,
+    /// Set right after a list marker, so the item's first block starts on its line.
+    after_marker: bool,
+    lists: Vec<List>,
+    quotes: usize,
+    /// Quote depth of the last line written, so a blank line leaving or entering a quote
+    /// isn't marked as part of it.
+    line_quotes: usize,
+}
+
+/// Render `doc` as Markdown, or as plain text when `plain`.
+pub fn render(doc: &Html, base: Option<&reqwest::Url>, plain: bool) -> String {
+    let mut w = Writer::new(base, plain);
+    w.children(doc.root_element());
+    w.out.trim_end().to_string()
+}
+
+impl<'a> Writer<'a> {
+    fn new(base: Option<&'a reqwest::Url>, plain: bool) -> Self {
+        Self {
+            out: String::new(),
+            plain,
+            base,
+            pending: Break::None,
+            space: false,
+            after_marker: false,
+            lists: Vec::new(),
+            quotes: 0,
+            line_quotes: 0,
+        }
+    }
+
+    fn prefix(&self) -> String {
+        let indent: usize = self.lists.iter().map(|l| l.indent).sum();
+        let quote = if self.plain { "" } else { "> " };
+        format!("{}{}", quote.repeat(self.quotes), " ".repeat(indent))
+    }
+
+    fn brk(&mut self, kind: Break) {
+        if !self.after_marker && kind > self.pending {
+            self.pending = kind;
+        }
+    }
+
+    /// Write `s` after any pending line break or space.
+    fn emit(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+        if self.out.is_empty() {
+            self.out.push_str(&self.prefix());
+        } else if self.pending != Break::None {
+            self.out.truncate(self.out.trim_end_matches(' ').len());
+            self.out.push('\n');
+            if self.pending == Break::Blank {
+                let quote = if self.plain { "" } else { ">" };
+                self.out
+                    .push_str(&quote.repeat(self.quotes.min(self.line_quotes)));
+                self.out.push('\n');
+            }
+            self.out.push_str(&self.prefix());
+        } else if self.space && !self.out.ends_with([' ', '\n']) {
+            self.out.push(' ');
+        }
+        self.pending = Break::None;
+        self.space = false;
+        self.after_marker = false;
+        self.line_quotes = self.quotes;
+        self.out.push_str(s);
+    }
+
+    fn text(&mut self, text: &str) {
+        if text.starts_with(char::is_whitespace) {
+            self.space = true;
+        }
+        let mut words = text.split_whitespace().peekable();
+        while let Some(word) = words.next() {
+            self.emit(word);
+            if words.peek().is_some() {
+                self.space = true;
+            }
+        }
+        if text.ends_with(char::is_whitespace) {
+            self.space = true;
+        }
+    }
+
+    fn children(&mut self, el: ElementRef) {
+        for child in el.children() {
+            match child.value() {
+                Node::Text(text) => self.text(text),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// `el`'s content on one line, and whether whitespace surrounds it.
+    fn inline(&self, el: ElementRef) -> (String, bool, bool) {
+        let mut w = Writer::new(self.base, self.plain);
+        w.children(el);
+        let text = w.out.split_whitespace().collect::<Vec<_>>().join(" ");
+        let raw: String = el.text().collect();
+        (
+            text,
+            raw.starts_with(char::is_whitespace),
+            raw.ends_with(char::is_whitespace),
+        )
+    }
+
+    /// `el`'s content wrapped in `open`/`close` (Markdown only).
+    fn wrapped(&mut self, el: ElementRef, open: &str, close: &str) {
+        let (text, before, after) = self.inline(el);
+        if text.is_empty() {
+            self.space |= before || after;
+            return;
+        }
+        self.space |= before;
+        if self.plain {
+            self.emit(&text);
+        } else {
+            self.emit(&format!("{open}{text}{close}"));
+        }
+        self.space = after;
+    }
+
+    fn url(&self, href: &str) -> Option<String> {
+        let href = href.trim();
+        if href.is_empty() || href.starts_with('#') || href.starts_with("javascript:") {
+            return None;
+        }
+        match self.base {
+            Some(base) => base.join(href).ok().map(|u| u.to_string()),
+            None => Some(href.to_string()),
+        }
+    }
+
+    fn element(&mut self, el: ElementRef) {
+        let name = el.value().name();
+        if SKIPPED_TAGS.contains(&name) {
+            return;
+        }
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let (text, _, _) = self.inline(el);
+                if text.is_empty() {
+                    return;
+                }
+                self.brk(Break::Blank);
+                if self.plain {
+                    self.emit(&text);
+                } else {
+                    let level = usize::from(name.as_bytes()[1] - b'0');
+                    self.emit(&format!("{} {text}", "#".repeat(level)));
+                }
+                self.brk(Break::Blank);
+            }
+            "br" => {
+                self.brk(Break::Line);
+                self.space = false;
+            }
+            "hr" => {
+                self.brk(Break::Blank);
+                if !self.plain {
+                    self.emit("---");
+                }
+                self.brk(Break::Blank);
+            }
+            "pre" => self.code_block(el),
+            "code" | "kbd" | "samp" | "tt" => {
+                let text: String = el.text().collect();
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if text.is_empty() {
+                    return;
+                }
+                if self.plain {
+                    self.emit(&text);
+                } else {
+                    let ticks = if text.contains('`') { "``" } else { "`" };
+                    self.emit(&format!("{ticks}{text}{ticks}"));
+                }
+            }
+            "strong" | "b" => self.wrapped(el, "**", "**"),
+            "em" | "i" => self.wrapped(el, "*", "*"),
+            "del" | "s" | "strike" => self.wrapped(el, "~~", "~~"),
+            "a" => {
+                let (text, before, after) = self.inline(el);
+                let href = el.attr("href").and_then(|h| self.url(h));
+                self.space |= before;
+                match (text.is_empty(), href) {
+                    (true, _) => {}
+                    (false, Some(href)) if !self.plain && href != text => {
+                        self.emit(&format!("[{text}]({href})"))
+                    }
+                    (false, _) => self.emit(&text),
+                }
+                self.space |= after;
+            }
+            "img" => {
+                if self.plain {
+                    return;
+                }
+                let alt = el.attr("alt").unwrap_or_default().trim();
+                if let Some(src) = el.attr("src").and_then(|s| self.url(s)) {
+                    if !src.starts_with("data:") {
+                        self.emit(&format!("![{alt}]({src})"));
+                    }
+                }
+            }
+            "ul" | "ol" => {
+                if self.lists.is_empty() {
+                    self.brk(Break::Blank);
+                }
+                let next = (name == "ol").then(|| {
+                    el.attr("start")
+                        .and_then(|s| s.trim().parse().ok())
+                        .unwrap_or(1)
+                });
+                self.lists.push(List { next, indent: 0 });
+                self.children(el);
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.brk(Break::Blank);
+                } else {
+                    self.brk(Break::Line);
+                }
+            }
+            "li" => {
+                self.after_marker = false;
+                self.brk(Break::Line);
+                let marker = match self.lists.last_mut() {
+                    Some(list) => {
+                        list.indent = 0;
+                        match &mut list.next {
+                            Some(n) => {
+                                *n += 1;
+                                format!("{}.", *n - 1)
+                            }
+                            None => "-".to_string(),
+                        }
+                    }
+                    None => "-".to_string(),
+                };
+                self.emit(&marker);
+                self.space = true;
+                if let Some(list) = self.lists.last_mut() {
+                    list.indent = marker.len() + 1;
+                }
+                self.after_marker = true;
+                self.children(el);
+                self.after_marker = false;
+                // A paragraph ending the item doesn't leave a blank line before the next.
+                self.pending = self.pending.min(Break::Line);
+                self.brk(Break::Line);
+            }
+            "blockquote" => {
+                self.brk(Break::Blank);
+                self.quotes += 1;
+                self.children(el);
+                self.brk(Break::Blank);
+                self.quotes -= 1;
+            }
+            "table" => self.table(el),
+            _ if BLOCK_TAGS.contains(&name) => {
+                self.brk(Break::Blank);
+                self.children(el);
+                self.brk(Break::Blank);
+            }
+            _ => self.children(el),
+        }
+    }
+
+    fn code_block(&mut self, el: ElementRef) {
+        let code: String = el.text().collect();
+        let code = code.trim_matches('\n').trim_end();
+        if code.is_empty() {
+            return;
+        }
+        self.brk(Break::Blank);
+        let lang = el
+            .child_elements()
+            .chain(std::iter::once(el))
+            .filter_map(|e| e.attr("class"))
+            .flat_map(str::split_whitespace)
+            .find_map(|c| c.strip_prefix("language-").or(c.strip_prefix("lang-")))
+            .unwrap_or_default()
+            .to_string();
+        let fence = if code.contains("```") { "~~~" } else { "```" };
+        let mut lines = code.lines();
+        if self.plain {
+            self.emit(lines.next().unwrap_or_default());
+        } else {
+            self.emit(&format!("{fence}{lang}"));
+        }
+        let prefix = self.prefix();
+        for line in lines {
+            self.out.push('\n');
+            self.out.push_str(&prefix);
+            self.out.push_str(line);
+        }
+        if !self.plain {
+            self.out.push('\n');
+            self.out.push_str(&prefix);
+            self.out.push_str(fence);
+        }
+        self.after_marker = false;
+        self.brk(Break::Blank);
+    }
+
+    fn table(&mut self, table: ElementRef) {
+        let rows: Vec<Vec<String>> = table
+            .descendent_elements()
+            .filter(|e| e.value().name() == "tr")
+            // Rows of nested tables belong to those tables.
+            .filter(|tr| {
+                tr.ancestors()
+                    .filter_map(ElementRef::wrap)
+                    .find(|a| a.value().name() == "table")
+                    .is_some_and(|t| t.id() == table.id())
+            })
+            .map(|tr| {
+                tr.child_elements()
+                    .filter(|c| matches!(c.value().name(), "td" | "th"))
+                    .map(|c| {
+                        let text = self.inline(c).0;
+                        if self.plain {
+                            text
+                        } else {
+                            text.replace('|', "\\|")
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|cells| cells.iter().any(|c| !c.is_empty()))
+            .collect();
+        if rows.is_empty() {
+            return;
+        }
+        self.brk(Break::Blank);
+        if self.plain {
+            for row in &rows {
+                self.brk(Break::Line);
+                self.emit(&row.join(" | "));
+            }
+        } else {
+            let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+            for (i, row) in rows.iter().enumerate() {
+                let mut cells = row.clone();
+                cells.resize(width, String::new());
+                self.brk(Break::Line);
+                self.emit(&format!("| {} |", cells.join(" | ")));
+                if i == 0 {
+                    self.brk(Break::Line);
+                    self.emit(&format!("|{}", " --- |".repeat(width)));
+                }
+            }
+        }
+        self.brk(Break::Blank);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn md(html: &str) -> String {
+        let base = reqwest::Url::parse("https://blog.example.com/posts/2.0").unwrap();
+        render(&Html::parse_document(html), Some(&base), false)
+    }
+
+    fn text(html: &str) -> String {
+        render(&Html::parse_document(html), None, true)
+    }
+
+    #[test]
+    fn renders_article_fixture() {
+        let html = include_str!("../tests/fixtures/article.html");
+        let article = crate::readability::extract(&Html::parse_document(html)).unwrap();
+        assert_eq!(
+            md(&article),
+            include_str!("../tests/fixtures/article.md").trim_end()
+        );
+    }
+
+    #[test]
+    fn headings_and_paragraphs() {
+        assert_eq!(
+            md("<h1>Title</h1><p>One  two\nthree.</p><h3>Sub</h3><p>Four.</p>"),
+            "# Title\n\nOne two three.\n\n### Sub\n\nFour."
+        );
+        assert_eq!(text("<h1>Title</h1><p>Body</p>"), "Title\n\nBody");
+    }
+
+    #[test]
+    fn links_resolve_against_base() {
+        assert_eq!(
+            md(
+                r##"<p>See <a href="../docs">the docs</a>, <a href="https://x.example/">https://x.example/</a> and <a href="#top">top</a>.</p>"##
+            ),
+            "See [the docs](https://blog.example.com/docs), https://x.example/ and top."
+        );
+        assert_eq!(
+            text(r#"<p>See <a href="/docs">the docs</a>.</p>"#),
+            "See the docs."
+        );
+    }
+
+    #[test]
+    fn inline_formatting() {
+        assert_eq!(
+            md("<p><strong>bold</strong>, <em>it</em>, <del>old</del> and <code>a`b</code></p>"),
+            "**bold**, *it*, ~~old~~ and ``a`b``"
+        );
+        assert_eq!(
+            md(r#"<p><img src="/a.png" alt="A"><img src="data:image/png;base64,x"></p>"#),
+            "![A](https://blog.example.com/a.png)"
+        );
+    }
+
+    #[test]
+    fn nested_and_ordered_lists() {
+        assert_eq!(
+            md("<ol start=\"3\"><li>three<ul><li>a</li><li>b</li></ul></li><li><p>four</p></li></ol><p>after</p>"),
+            "3. three\n   - a\n   - b\n4. four\n\nafter"
+        );
+    }
+
+    #[test]
+    fn code_blocks_and_quotes() {
+        assert_eq!(
+            md("<pre><code class=\"language-rust\">fn main() {\n    run();\n}</code></pre>"),
+            "```rust\nfn main() {\n    run();\n}\n```"
+        );
+        assert_eq!(
+            md("<blockquote><p>one</p><p>two</p></blockquote><p>out</p>"),
+            "> one\n>\n> two\n\nout"
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let html = "<table><tr><th>a|b</th><th>c</th></tr><tr><td>1</td></tr><tr><td></td><td></td></tr></table>";
+        assert_eq!(md(html), "| a\\|b | c |\n| --- | --- |\n| 1 |  |");
+        assert_eq!(text(html), "a|b | c\n1");
+    }
+
+    #[test]
+    fn skips_scripts_and_controls() {
+        assert_eq!(
+            md("<p>Keep<script>drop()</script><style>p{}</style><button>Click</button></p>"),
+            "Keep"
+        );
+    }
+}
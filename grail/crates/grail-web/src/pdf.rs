@@ -0,0 +1,84 @@
+//! PDF text for `web_fetch`. A PDF is only readable whole, so PDFs get a larger download
+//! cap than pages; text is extracted page by page and the pages joined with blank lines.
+
+use anyhow::Context;
+
+/// Download cap for PDFs (pages use `MAX_FETCH_BYTES`).
+pub const MAX_BYTES: usize = 20_000_000;
+
+pub struct PdfText {
+    pub text: String,
+    pub pages: usize,
+}
+
+/// Whether a response looks like a PDF before its body is read: by content type, or by
+/// a `.pdf` path that isn't served as HTML (a landing page at a `.pdf` URL is a page).
+pub fn is_pdf(content_type: &str, url: &str) -> bool {
+    let ct = content_type.to_ascii_lowercase();
+    if ct.contains("application/pdf") {
+        return true;
+    }
+    let path_is_pdf =
+        reqwest::Url::parse(url).is_ok_and(|u| u.path().to_ascii_lowercase().ends_with(".pdf"));
+    path_is_pdf && !ct.contains("text/html")
+}
+
+/// Extract the text of every page. Blocking and CPU-bound; run it off the async runtime.
+pub fn extract(body: &[u8]) -> anyhow::Result<PdfText> {
+    if !body.starts_with(b"%PDF-") {
+        anyhow::bail!("not a PDF (no %PDF- header)");
+    }
+    // The parser panics on some malformed files instead of returning an error.
+    let pages = std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(body))
+        .map_err(|_| anyhow::anyhow!("PDF parser crashed on this file"))?
+        .context("parse PDF")?;
+    let text = pages
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Ok(PdfText {
+        text,
+        pages: pages.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_pdf_by_content_type_or_path() {
+        assert!(is_pdf("application/pdf", "https://example.com/download"));
+        assert!(is_pdf(
+            "Application/PDF; charset=binary",
+            "https://example.com/x"
+        ));
+        assert!(is_pdf(
+            "application/octet-stream",
+            "https://example.com/paper.PDF?dl=1"
+        ));
+        assert!(is_pdf("", "https://example.com/paper.pdf"));
+        assert!(!is_pdf(
+            "text/html; charset=utf-8",
+            "https://example.com/paper.pdf"
+        ));
+        assert!(!is_pdf("text/plain", "https://example.com/paper.pdf.txt"));
+        assert!(!is_pdf("", "not a url.pdf"));
+    }
+
+    #[test]
+    fn extract_joins_pages() {
+        let pdf = extract(include_bytes!("../tests/fixtures/two_pages.pdf")).unwrap();
+        assert_eq!(pdf.pages, 2);
+        assert_eq!(pdf.text, "Hello from page one\n\nSecond page text");
+    }
+
+    #[test]
+    fn extract_rejects_non_pdfs() {
+        let err = extract(b"<html>not a pdf</html>").err().unwrap();
+        assert!(err.to_string().contains("%PDF-"));
+        assert!(extract(b"%PDF-1.4\ngarbage").is_err());
+    }
+}
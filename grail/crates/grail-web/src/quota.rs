@@ -0,0 +1,343 @@
+//! Brave Search quota tracking.
+//!
+//! Usage is recorded from Brave's `X-RateLimit-*` response headers plus a local request
+//! counter. The state lives in a small JSON file (`GRAIL_WEB_QUOTA_FILE`) so every
+//! web MCP process shares it and the admin status page can display it.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Windows shorter than this (Brave's per-second limit) never block searches up front.
+const SHORT_WINDOW_SECS: i64 = 60;
+/// Without a reset hint, assume the local counter covers a 30-day plan period.
+const LOCAL_PERIOD_SECS: i64 = 30 * 24 * 3600;
+/// How long to back off after a 429 that carries no usable reset header.
+const DEFAULT_BACKOFF_SECS: i64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuotaWindow {
+    pub limit: i64,
+    pub remaining: i64,
+    pub reset_seconds: i64,
+    pub resets_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QuotaState {
+    pub updated_at: i64,
+    pub windows: Vec<QuotaWindow>,
+    pub local_requests: i64,
+    pub local_resets_at: i64,
+    pub blocked_until: Option<i64>,
+    pub last_error: Option<String>,
+    pub fallback_searches: i64,
+}
+
+pub struct QuotaTracker {
+    path: Option<PathBuf>,
+    /// Plan quota used with the local counter when Brave sends no rate-limit headers.
+    local_quota: Option<i64>,
+    state: Mutex<QuotaState>,
+}
+
+impl QuotaTracker {
+    pub fn from_env() -> Self {
+        let path = std::env::var("GRAIL_WEB_QUOTA_FILE")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from);
+        let local_quota = std::env::var("GRAIL_BRAVE_MONTHLY_QUOTA")
+            .ok()
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .filter(|v| *v > 0);
+        Self {
+            path,
+            local_quota,
+            state: Mutex::new(QuotaState::default()),
+        }
+    }
+
+    /// Current state, refreshed from the shared file when configured.
+    pub async fn snapshot(&self) -> QuotaState {
+        let mut guard = self.state.lock().await;
+        if let Some(s) = self.read_file().await {
+            *guard = s;
+        }
+        guard.clone()
+    }
+
+    /// When searches are blocked, the unix time they become available again.
+    pub async fn blocked_until(&self) -> Option<i64> {
+        let now = now();
+        self.snapshot()
+            .await
+            .blocked_until
+            .filter(|until| *until > now)
+    }
+
+    /// Record a Brave response. Returns the time searches are blocked until, if any.
+    pub async fn record_response(&self, status: u16, windows: Vec<QuotaWindow>) -> Option<i64> {
+        let local_quota = self.local_quota;
+        self.update(|s| {
+            let now = now();
+            if now >= s.local_resets_at {
+                s.local_requests = 0;
+                s.local_resets_at = now + LOCAL_PERIOD_SECS;
+            }
+            s.local_requests += 1;
+            if !windows.is_empty() {
+                // Align the local counter with the plan period Brave reports.
+                if let Some(longest) = windows.iter().max_by_key(|w| w.reset_seconds) {
+                    if longest.reset_seconds >= SHORT_WINDOW_SECS {
+                        s.local_resets_at = longest.resets_at;
+                    }
+                }
+                s.windows = windows;
+            }
+
+            let exhausted = s
+                .windows
+                .iter()
+                .filter(|w| w.remaining <= 0)
+                .map(|w| w.resets_at)
+                .max();
+            s.blocked_until = if status == 429 {
+                s.last_error = Some("brave search returned 429 (rate limited)".to_string());
+                Some(exhausted.unwrap_or(now + DEFAULT_BACKOFF_SECS).max(now + 1))
+            } else {
+                s.last_error = None;
+                // Stop before hitting the wall once a long window is used up.
+                let long_exhausted = s
+                    .windows
+                    .iter()
+                    .filter(|w| w.remaining <= 0 && w.reset_seconds >= SHORT_WINDOW_SECS)
+                    .map(|w| w.resets_at)
+                    .max();
+                let local_exhausted = local_quota
+                    .filter(|q| s.windows.is_empty() && s.local_requests >= *q)
+                    .map(|_| s.local_resets_at);
+                long_exhausted.or(local_exhausted)
+            };
+        })
+        .await
+        .blocked_until
+    }
+
+    pub async fn record_fallback(&self) {
+        self.update(|s| s.fallback_searches += 1).await;
+    }
+
+    async fn update(&self, f: impl FnOnce(&mut QuotaState)) -> QuotaState {
+        let mut guard = self.state.lock().await;
+        if let Some(s) = self.read_file().await {
+            *guard = s;
+        }
+        f(&mut guard);
+        guard.updated_at = now();
+        if let Some(path) = &self.path {
+            if let Err(err) = write_atomic(path, &guard).await {
+                warn!(error = %err, path = %path.display(), "failed to write quota state");
+            }
+        }
+        guard.clone()
+    }
+
+    async fn read_file(&self) -> Option<QuotaState> {
+        let path = self.path.as_ref()?;
+        let raw = tokio::fs::read(path).await.ok()?;
+        serde_json::from_slice(&raw).ok()
+    }
+}
+
+async fn write_atomic(path: &std::path::Path, state: &QuotaState) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    tokio::fs::write(&tmp, serde_json::to_vec_pretty(state)?).await?;
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+/// Parse Brave's comma-separated `X-RateLimit-{Limit,Remaining,Reset}` headers
+/// (one entry per window, e.g. per-second and per-month).
+pub fn parse_rate_headers(headers: &reqwest::header::HeaderMap) -> Vec<QuotaWindow> {
+    let list = |name: &str| -> Vec<i64> {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| {
+                v.split(',')
+                    .filter_map(|p| p.trim().parse::<i64>().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let limits = list("x-ratelimit-limit");
+    let remaining = list("x-ratelimit-remaining");
+    let resets = list("x-ratelimit-reset");
+    let now = now();
+    limits
+        .into_iter()
+        .zip(remaining)
+        .zip(resets)
+        .map(|((limit, remaining), reset_seconds)| QuotaWindow {
+            limit,
+            remaining,
+            reset_seconds,
+            resets_at: now + reset_seconds,
+        })
+        .collect()
+}
+
+/// Format a unix time as `HH:MM UTC`, or with the date when it is more than a day away.
+pub fn format_until(ts: i64) -> String {
+    let Some(dt) = chrono::DateTime::<chrono::Utc>::from_timestamp(ts, 0) else {
+        return ts.to_string();
+    };
+    if ts - now() > 24 * 3600 {
+        dt.format("%Y-%m-%d %H:%M UTC").to_string()
+    } else {
+        dt.format("%H:%M UTC").to_string()
+    }
+}
+
+/// JSON view of the quota state for the `web_quota` tool.
+pub fn quota_json(s: &QuotaState, fallback: &str) -> serde_json::Value {
+    let now = now();
+    let blocked_until = s.blocked_until.filter(|t| *t > now);
+    let windows: Vec<serde_json::Value> = s
+        .windows
+        .iter()
+        .map(|w| {
+            json!({
+                "limit": w.limit,
+                "remaining": w.remaining,
+                "resetsAt": w.resets_at,
+                "resetsAtUtc": format_until(w.resets_at),
+            })
+        })
+        .collect();
+    json!({
+        "provider": "brave",
+        "available": blocked_until.is_none(),
+        "blockedUntil": blocked_until,
+        "blockedUntilUtc": blocked_until.map(format_until),
+        "windows": windows,
+        "localRequests": s.local_requests,
+        "localResetsAt": s.local_resets_at,
+        "fallbackProvider": fallback,
+        "fallbackSearches": s.fallback_searches,
+        "lastError": s.last_error,
+        "updatedAt": s.updated_at,
+    })
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    fn tracker(local_quota: Option<i64>) -> QuotaTracker {
+        QuotaTracker {
+            path: None,
+            local_quota,
+            state: Mutex::new(QuotaState::default()),
+        }
+    }
+
+    fn window(limit: i64, remaining: i64, reset_seconds: i64) -> QuotaWindow {
+        QuotaWindow {
+            limit,
+            remaining,
+            reset_seconds,
+            resets_at: now() + reset_seconds,
+        }
+    }
+
+    #[test]
+    fn parse_rate_headers_pairs_windows() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", HeaderValue::from_static("1, 2000"));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0, 1500"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1, 86400"));
+        let windows = parse_rate_headers(&headers);
+        assert_eq!(windows.len(), 2);
+        assert_eq!(
+            (
+                windows[0].limit,
+                windows[0].remaining,
+                windows[0].reset_seconds
+            ),
+            (1, 0, 1)
+        );
+        assert_eq!(
+            (
+                windows[1].limit,
+                windows[1].remaining,
+                windows[1].reset_seconds
+            ),
+            (2000, 1500, 86400)
+        );
+        assert!(windows[1].resets_at >= now() + 86_399);
+    }
+
+    #[test]
+    fn parse_rate_headers_ignores_missing_or_bad_values() {
+        assert!(parse_rate_headers(&HeaderMap::new()).is_empty());
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", HeaderValue::from_static("1, x"));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("1"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1, 2"));
+        assert_eq!(parse_rate_headers(&headers).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn short_window_exhaustion_does_not_block() {
+        let t = tracker(None);
+        let blocked = t
+            .record_response(200, vec![window(1, 0, 1), window(2000, 10, 86_400)])
+            .await;
+        assert_eq!(blocked, None);
+        assert_eq!(t.snapshot().await.local_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn long_window_exhaustion_blocks_until_reset() {
+        let t = tracker(None);
+        let month = window(2000, 0, 86_400);
+        let blocked = t
+            .record_response(200, vec![window(1, 1, 1), month.clone()])
+            .await;
+        assert_eq!(blocked, Some(month.resets_at));
+        assert_eq!(t.snapshot().await.local_resets_at, month.resets_at);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_without_headers_backs_off() {
+        let t = tracker(None);
+        let blocked = t.record_response(429, Vec::new()).await.unwrap();
+        assert!(blocked > now() && blocked <= now() + DEFAULT_BACKOFF_SECS);
+        assert!(t.snapshot().await.last_error.is_some());
+        assert_eq!(t.record_response(200, Vec::new()).await, None);
+        assert!(t.snapshot().await.last_error.is_none());
+    }
+
+    #[tokio::test]
+    async fn local_quota_applies_without_headers() {
+        let t = tracker(Some(2));
+        assert_eq!(t.record_response(200, Vec::new()).await, None);
+        let blocked = t.record_response(200, Vec::new()).await;
+        assert_eq!(blocked, Some(t.snapshot().await.local_resets_at));
+    }
+}
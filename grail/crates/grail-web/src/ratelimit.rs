@@ -0,0 +1,144 @@
+//! Per-host rate limiting for `web_fetch`: a token bucket per host, refilled at
+//! `GRAIL_WEB_FETCH_RPS` requests per second (default 1, `0` turns limiting off) and
+//! holding at most `GRAIL_WEB_FETCH_BURST` tokens (default 5). A fetch waits for its
+//! host's next token, so a research loop slows down instead of getting the sidecar's IP
+//! banned; waits longer than `MAX_WAIT` fail instead.
+//!
+//! Buckets are per process: every clone of a `WebMcpServer` shares them.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+const DEFAULT_RPS: f64 = 1.0;
+const DEFAULT_BURST: f64 = 5.0;
+/// Longest a fetch waits for its host's token.
+const MAX_WAIT: Duration = Duration::from_secs(20);
+/// Idle buckets are full again after `burst / rps` seconds, so they are dropped once
+/// there are this many.
+const MAX_BUCKETS: usize = 1024;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub struct HostLimiter {
+    /// Tokens per second; `None` when limiting is off.
+    rps: Option<f64>,
+    burst: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl HostLimiter {
+    pub fn from_env() -> Self {
+        let rps = env_f64("GRAIL_WEB_FETCH_RPS").unwrap_or(DEFAULT_RPS);
+        let burst = env_f64("GRAIL_WEB_FETCH_BURST")
+            .unwrap_or(DEFAULT_BURST)
+            .max(1.0);
+        Self {
+            rps: (rps > 0.0).then_some(rps),
+            burst,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait for a request slot on `host`. Errors with the wait it would take when that
+    /// is longer than `MAX_WAIT`.
+    pub async fn acquire(&self, host: &str) -> Result<(), Duration> {
+        let Some(rps) = self.rps else {
+            return Ok(());
+        };
+        let wait = {
+            let mut buckets = self.buckets.lock().await;
+            let now = Instant::now();
+            if buckets.len() >= MAX_BUCKETS {
+                let full_after =
+                    Duration::try_from_secs_f64(self.burst / rps).unwrap_or(Duration::MAX);
+                buckets.retain(|_, b| now.duration_since(b.updated) < full_after);
+            }
+            let bucket = buckets.entry(host.to_ascii_lowercase()).or_insert(Bucket {
+                tokens: self.burst,
+                updated: now,
+            });
+            let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+            let tokens = (bucket.tokens + elapsed * rps).min(self.burst);
+            // Tokens go negative while fetches are queued; each waits its turn.
+            let wait = Duration::try_from_secs_f64(((1.0 - tokens) / rps).max(0.0))
+                .unwrap_or(Duration::MAX);
+            if wait > MAX_WAIT {
+                return Err(wait);
+            }
+            bucket.tokens = tokens - 1.0;
+            bucket.updated = now;
+            wait
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+}
+
+fn env_f64(key: &str) -> Option<f64> {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v >= 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(rps: Option<f64>, burst: f64) -> HostLimiter {
+        HostLimiter {
+            rps,
+            burst,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[tokio::test]
+    async fn burst_is_free_then_fetches_wait() {
+        let limiter = limiter(Some(20.0), 2.0);
+        let start = Instant::now();
+        limiter.acquire("example.com").await.unwrap();
+        limiter.acquire("example.com").await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(20));
+        limiter.acquire("example.com").await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(45));
+    }
+
+    #[tokio::test]
+    async fn hosts_have_separate_buckets() {
+        let limiter = limiter(Some(20.0), 1.0);
+        let start = Instant::now();
+        limiter.acquire("a.example").await.unwrap();
+        limiter.acquire("b.example").await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(20));
+        // Host names are case-insensitive.
+        limiter.acquire("A.EXAMPLE").await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(45));
+    }
+
+    #[tokio::test]
+    async fn long_waits_fail() {
+        let limiter = limiter(Some(0.01), 1.0);
+        limiter.acquire("example.com").await.unwrap();
+        let wait = limiter.acquire("example.com").await.unwrap_err();
+        assert!(wait > MAX_WAIT);
+        // A refused fetch doesn't take a token.
+        assert!(limiter.buckets.lock().await["example.com"].tokens > -0.5);
+    }
+
+    #[tokio::test]
+    async fn disabled_never_waits() {
+        let limiter = limiter(None, 1.0);
+        for _ in 0..100 {
+            limiter.acquire("example.com").await.unwrap();
+        }
+        assert!(limiter.buckets.lock().await.is_empty());
+    }
+}
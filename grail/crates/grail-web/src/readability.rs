@@ -0,0 +1,320 @@
+//! Main-content extraction for `web_fetch`, after Mozilla's Readability: elements that
+//! hold paragraphs score points for their ancestors, the best-scoring container wins
+//! (with the siblings that look like part of the same article), and navigation,
+//! sidebars, comments, share bars and cookie banners are left out.
+//!
+//! Pages where no container holds enough text (search results, link lists, app shells)
+//! have no article; `web_fetch` converts the whole page instead.
+
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+
+/// Never part of an article.
+const JUNK_TAGS: [&str; 12] = [
+    "script", "style", "noscript", "template", "iframe", "svg", "form", "button", "nav", "aside",
+    "footer", "dialog",
+];
+const JUNK_ROLES: [&str; 6] = [
+    "navigation",
+    "banner",
+    "complementary",
+    "contentinfo",
+    "dialog",
+    "alertdialog",
+];
+/// Kept even when their class or id looks unlikely.
+const KEEP_TAGS: [&str; 4] = ["html", "body", "article", "main"];
+/// A `div` with none of these inside is scored like a paragraph.
+const BLOCK_TAGS: [&str; 12] = [
+    "p",
+    "div",
+    "pre",
+    "table",
+    "ul",
+    "ol",
+    "blockquote",
+    "section",
+    "article",
+    "h1",
+    "h2",
+    "h3",
+];
+const MIN_PARAGRAPH_CHARS: usize = 25;
+/// Less text than this in the chosen elements means the page has no article.
+const MIN_ARTICLE_CHARS: usize = 250;
+
+static UNLIKELY_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)-ad-|agegate|banner|breadcrumbs|combx|comment|community|consent|cookie|disqus|extra|footer|gdpr|header|menu|newsletter|pager|pagination|popup|related|remark|replies|rss|share|shoutbox|sidebar|skyscraper|social|sponsor|subscribe|supplemental").unwrap()
+});
+static MAYBE_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)and|article|body|column|content|main|shadow").unwrap());
+static POSITIVE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)article|blog|body|content|entry|hentry|h-entry|main|page|post|story|text")
+        .unwrap()
+});
+static NEGATIVE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)-ad-|banner|combx|comment|com-|contact|foot|footnote|gdpr|masthead|media|meta|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|shopping|tags|tool|widget").unwrap()
+});
+static LINK_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("a").unwrap());
+static TITLE_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("title").unwrap());
+static OG_TITLE_SELECTOR: Lazy<Selector> =
+    Lazy::new(|| Selector::parse(r#"meta[property="og:title"]"#).unwrap());
+
+/// The page title: `og:title`, else `<title>`.
+pub fn title(doc: &Html) -> Option<String> {
+    doc.select(&OG_TITLE_SELECTOR)
+        .find_map(|m| m.attr("content").map(collapse))
+        .or_else(|| doc.select(&TITLE_SELECTOR).next().map(text_of))
+        .filter(|t| !t.is_empty())
+}
+
+/// The article in `doc`, as `<html><body>` around its elements.
+pub fn extract(doc: &Html) -> Option<String> {
+    let mut doc = doc.clone();
+    let junk: Vec<_> = doc
+        .root_element()
+        .descendent_elements()
+        .filter(|el| is_junk(*el))
+        .map(|el| el.id())
+        .collect();
+    for id in junk {
+        if let Some(mut node) = doc.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    let mut scores = HashMap::new();
+    for el in doc.root_element().descendent_elements() {
+        if !is_paragraph(el) {
+            continue;
+        }
+        let text = text_of(el);
+        let len = text.chars().count();
+        if len < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (len / 100).min(3) as f64;
+        for (level, ancestor) in el
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .take(3)
+            .enumerate()
+        {
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                n => n as f64 * 3.0,
+            };
+            *scores
+                .entry(ancestor.id())
+                .or_insert_with(|| initial_score(ancestor)) += score / divider;
+        }
+    }
+    let scores: HashMap<_, f64> = scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let el = ElementRef::wrap(doc.tree.get(id)?)?;
+            Some((id, score * (1.0 - link_density(el))))
+        })
+        .collect();
+    let (top_id, top_score) = scores
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(id, score)| (*id, *score))?;
+    let top = ElementRef::wrap(doc.tree.get(top_id)?)?;
+
+    let parent = top
+        .parent()
+        .and_then(ElementRef::wrap)
+        .filter(|p| p.value().name() != "html");
+    let parts: Vec<ElementRef> = match parent {
+        Some(parent) => {
+            let threshold = (top_score * 0.2).max(10.0);
+            let top_class = top.attr("class").unwrap_or_default();
+            parent
+                .child_elements()
+                .filter(|sibling| {
+                    if sibling.id() == top_id {
+                        return true;
+                    }
+                    let bonus = if !top_class.is_empty() && sibling.attr("class") == Some(top_class)
+                    {
+                        top_score * 0.2
+                    } else {
+                        0.0
+                    };
+                    if scores.get(&sibling.id()).copied().unwrap_or(0.0) + bonus >= threshold {
+                        return true;
+                    }
+                    if sibling.value().name() != "p" {
+                        return false;
+                    }
+                    let text = text_of(*sibling);
+                    let len = text.chars().count();
+                    let density = link_density(*sibling);
+                    (len > 80 && density < 0.25)
+                        || (len > 0 && density == 0.0 && text.contains(". "))
+                })
+                .collect()
+        }
+        None => vec![top],
+    };
+    let chars: usize = parts.iter().map(|p| text_of(*p).chars().count()).sum();
+    if chars < MIN_ARTICLE_CHARS {
+        return None;
+    }
+    let body: Vec<String> = parts.iter().map(|p| p.html()).collect();
+    Some(format!("<html><body>{}</body></html>", body.join("\n")))
+}
+
+fn is_junk(el: ElementRef) -> bool {
+    let name = el.value().name();
+    if JUNK_TAGS.contains(&name)
+        || el.attr("role").is_some_and(|r| JUNK_ROLES.contains(&r))
+        || el.attr("hidden").is_some()
+        || el.attr("aria-hidden") == Some("true")
+        || el.attr("style").is_some_and(|s| {
+            s.replace(' ', "")
+                .to_ascii_lowercase()
+                .contains("display:none")
+        })
+    {
+        return true;
+    }
+    if KEEP_TAGS.contains(&name) {
+        return false;
+    }
+    let names = format!(
+        "{} {}",
+        el.attr("class").unwrap_or_default(),
+        el.attr("id").unwrap_or_default()
+    );
+    UNLIKELY_RE.is_match(&names) && !MAYBE_RE.is_match(&names)
+}
+
+fn is_paragraph(el: ElementRef) -> bool {
+    match el.value().name() {
+        "p" | "pre" | "td" | "blockquote" => true,
+        "div" => !el
+            .descendent_elements()
+            .skip(1)
+            .any(|d| BLOCK_TAGS.contains(&d.value().name())),
+        _ => false,
+    }
+}
+
+fn initial_score(el: ElementRef) -> f64 {
+    let by_tag = match el.value().name() {
+        "div" | "article" | "main" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    by_tag + class_weight(el)
+}
+
+fn class_weight(el: ElementRef) -> f64 {
+    let mut weight = 0.0;
+    for name in [el.attr("class"), el.attr("id")].into_iter().flatten() {
+        if NEGATIVE_RE.is_match(name) {
+            weight -= 25.0;
+        }
+        if POSITIVE_RE.is_match(name) {
+            weight += 25.0;
+        }
+    }
+    weight
+}
+
+/// The share of `el`'s text that is link text.
+fn link_density(el: ElementRef) -> f64 {
+    let total = text_of(el).chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+    let links: usize = el
+        .select(&LINK_SELECTOR)
+        .map(|a| text_of(a).chars().count())
+        .sum();
+    links as f64 / total as f64
+}
+
+fn text_of(el: ElementRef) -> String {
+    collapse(&el.text().collect::<String>())
+}
+
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = include_str!("../tests/fixtures/article.html");
+    const LINK_LIST: &str = include_str!("../tests/fixtures/link_list.html");
+
+    #[test]
+    fn extract_keeps_the_article() {
+        let article = extract(&Html::parse_document(ARTICLE)).unwrap();
+        assert!(article.starts_with("<html><body><article class=\"post\">"));
+        for kept in [
+            "Release notes for version 2.0",
+            "Upgrading",
+            "legacy_mode",
+            "workers",
+        ] {
+            assert!(article.contains(kept), "missing {kept:?}");
+        }
+    }
+
+    #[test]
+    fn extract_drops_boilerplate() {
+        let article = extract(&Html::parse_document(ARTICLE)).unwrap();
+        for dropped in [
+            "window.analytics",
+            "Home",
+            "We use cookies",
+            "Share on Twitter",
+            "Related posts",
+            "Great release",
+            "All rights reserved",
+        ] {
+            assert!(!article.contains(dropped), "kept {dropped:?}");
+        }
+    }
+
+    #[test]
+    fn extract_finds_no_article_in_link_lists() {
+        assert_eq!(extract(&Html::parse_document(LINK_LIST)), None);
+        assert_eq!(extract(&Html::parse_document("<p>Too short.</p>")), None);
+    }
+
+    #[test]
+    fn title_prefers_og_title() {
+        assert_eq!(
+            title(&Html::parse_document(ARTICLE)).as_deref(),
+            Some("Release notes for version 2.0")
+        );
+        assert_eq!(
+            title(&Html::parse_document(LINK_LIST)).as_deref(),
+            Some("Search results")
+        );
+        assert_eq!(title(&Html::parse_document("<p>x</p>")), None);
+    }
+
+    #[test]
+    fn hidden_and_unlikely_elements_are_junk() {
+        let doc = Html::parse_fragment(
+            r#"<div class="sidebar-widget"></div><div class="main-column"></div>
+            <div style="display: none"></div><section aria-hidden="true"></section>
+            <div role="navigation"></div><article class="comments"></article>"#,
+        );
+        let junk: Vec<bool> = doc.root_element().child_elements().map(is_junk).collect();
+        assert_eq!(junk, vec![true, false, true, true, true, false]);
+    }
+}
@@ -0,0 +1,686 @@
+//! Headless Chromium rendering for `web_fetch` `render: true` (the `render` feature).
+//! Many sites send an empty shell and build the page with JavaScript; this loads the
+//! page in a fresh headless Chromium (`GRAIL_WEB_CHROMIUM`, default `chromium`, plus any
+//! `GRAIL_WEB_CHROMIUM_ARGS` such as `--no-sandbox` when running as root), waits for the
+//! load event and a short settle time, and returns the DOM as HTML.
+//!
+//! Chromium is driven over the DevTools protocol. Every request the page makes,
+//! redirects and subresources included, is paused and checked with the caller's `allow`
+//! (the same SSRF and domain checks as plain fetches); images, media and fonts are never
+//! loaded. Out-of-process iframes and workers are auto-attached and start paused, so the
+//! same filter is in place before their first request.
+//!
+//! Traffic the DevTools filter never sees (WebSocket handshakes, service worker fetches)
+//! is caught by a local proxy that Chromium must send every connection through: it asks
+//! `allow` about each target before connecting. QUIC and non-proxied WebRTC UDP are off.
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::process::Command;
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+/// After the load event, how long late scripts and XHRs get to fill the page in.
+const SETTLE: Duration = Duration::from_millis(1500);
+const BLOCKED_RESOURCES: [&str; 3] = ["Image", "Media", "Font"];
+/// Largest request head the proxy reads before giving up on a connection.
+const MAX_PROXY_HEAD: usize = 16 * 1024;
+const CHROMIUM_ARGS: [&str; 14] = [
+    "--headless=new",
+    "--disable-gpu",
+    "--disable-extensions",
+    "--disable-background-networking",
+    "--disable-sync",
+    "--no-first-run",
+    "--no-default-browser-check",
+    "--mute-audio",
+    "--hide-scrollbars",
+    "--remote-debugging-address=127.0.0.1",
+    "--remote-debugging-port=0",
+    // Send loopback through the proxy too, and keep everything on proxied TCP.
+    "--proxy-bypass-list=<-loopback>",
+    "--disable-quic",
+    "--force-webrtc-ip-handling-policy=disable_non_proxied_udp",
+];
+
+static PROFILES: AtomicU64 = AtomicU64::new(0);
+
+/// A URL the proxy wants checked, and where to send the verdict.
+type Check = (reqwest::Url, oneshot::Sender<bool>);
+
+pub struct Rendered {
+    pub final_url: String,
+    pub html: String,
+}
+
+/// Render `url`. `allow` decides which URLs the page may load.
+pub async fn render<F, Fut>(url: &reqwest::Url, allow: F) -> anyhow::Result<Rendered>
+where
+    F: Fn(reqwest::Url) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let bin = std::env::var("GRAIL_WEB_CHROMIUM")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "chromium".to_string());
+    let extra_args = std::env::var("GRAIL_WEB_CHROMIUM_ARGS").unwrap_or_default();
+    render_with(&bin, &extra_args, url, allow).await
+}
+
+async fn render_with<F, Fut>(
+    bin: &str,
+    extra_args: &str,
+    url: &reqwest::Url,
+    allow: F,
+) -> anyhow::Result<Rendered>
+where
+    F: Fn(reqwest::Url) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let profile: PathBuf = std::env::temp_dir().join(format!(
+        "grail-web-render-{}-{}",
+        std::process::id(),
+        PROFILES.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let (proxy, checks) = GuardProxy::start().await?;
+    let mut child = Command::new(bin)
+        .args(CHROMIUM_ARGS)
+        .arg(format!("--proxy-server=http://127.0.0.1:{}", proxy.port))
+        .args(extra_args.split_whitespace())
+        .arg(format!("--user-data-dir={}", profile.display()))
+        .arg("about:blank")
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("start {bin} (set GRAIL_WEB_CHROMIUM to the Chromium binary)"))?;
+
+    let result = tokio::time::timeout(TIMEOUT, drive(&mut child, url, checks, allow))
+        .await
+        .unwrap_or_else(|_| Err(anyhow!("rendering timed out after {}s", TIMEOUT.as_secs())));
+    let _ = child.kill().await;
+    let _ = tokio::fs::remove_dir_all(&profile).await;
+    result
+}
+
+async fn drive<F, Fut>(
+    child: &mut tokio::process::Child,
+    url: &reqwest::Url,
+    checks: mpsc::Receiver<Check>,
+    allow: F,
+) -> anyhow::Result<Rendered>
+where
+    F: Fn(reqwest::Url) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let stderr = child.stderr.take().context("chromium stderr")?;
+    let mut lines = BufReader::new(stderr).lines();
+    let endpoint = loop {
+        let line = lines
+            .next_line()
+            .await?
+            .context("chromium exited before DevTools started")?;
+        if let Some(endpoint) = line.split("DevTools listening on ").nth(1) {
+            break endpoint.trim().to_string();
+        }
+    };
+    // Keep draining stderr so Chromium never blocks on a full pipe.
+    tokio::spawn(async move { while let Ok(Some(_)) = lines.next_line().await {} });
+
+    let (ws, _) = tokio_tungstenite::connect_async(endpoint.as_str())
+        .await
+        .context("connect to Chromium DevTools")?;
+    let mut cdp = Cdp {
+        ws,
+        allow,
+        checks,
+        next_id: 0,
+        loaded: false,
+    };
+
+    let target = cdp
+        .call(None, "Target.createTarget", json!({ "url": "about:blank" }))
+        .await?;
+    let target_id = target["targetId"].as_str().context("no targetId")?;
+    let attached = cdp
+        .call(
+            None,
+            "Target.attachToTarget",
+            json!({ "targetId": target_id, "flatten": true }),
+        )
+        .await?;
+    let session = attached["sessionId"]
+        .as_str()
+        .context("no sessionId")?
+        .to_string();
+    let session = Some(session.as_str());
+
+    cdp.call(session, "Fetch.enable", fetch_patterns()).await?;
+    cdp.call(session, "Target.setAutoAttach", auto_attach())
+        .await?;
+    cdp.call(session, "Page.enable", json!({})).await?;
+    let nav = cdp
+        .call(session, "Page.navigate", json!({ "url": url.as_str() }))
+        .await?;
+    if let Some(error) = nav["errorText"].as_str().filter(|e| !e.is_empty()) {
+        bail!("navigation failed: {error}");
+    }
+    while !cdp.loaded {
+        let msg = cdp.next_message().await?;
+        cdp.on_event(&msg).await?;
+    }
+    let settle_until = tokio::time::Instant::now() + SETTLE;
+    while let Ok(msg) = tokio::time::timeout_at(settle_until, cdp.next_message()).await {
+        cdp.on_event(&msg?).await?;
+    }
+
+    let page = cdp
+        .call(
+            session,
+            "Runtime.evaluate",
+            json!({
+                "expression": "[location.href, document.documentElement.outerHTML]",
+                "returnByValue": true,
+            }),
+        )
+        .await?;
+    let value = &page["result"]["value"];
+    let (Some(final_url), Some(html)) = (value[0].as_str(), value[1].as_str()) else {
+        bail!("could not read the rendered page");
+    };
+    let rendered = Rendered {
+        final_url: final_url.to_string(),
+        html: html.to_string(),
+    };
+    let _ = cdp.send(None, "Browser.close", json!({})).await;
+    Ok(rendered)
+}
+
+fn fetch_patterns() -> Value {
+    json!({ "patterns": [{ "urlPattern": "*", "requestStage": "Request" }] })
+}
+
+/// Attach to iframes in other processes and to workers, paused until we resume them.
+fn auto_attach() -> Value {
+    json!({ "autoAttach": true, "flatten": true, "waitForDebuggerOnStart": true })
+}
+
+struct Cdp<F> {
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    allow: F,
+    /// Connections the proxy wants checked with `allow`.
+    checks: mpsc::Receiver<Check>,
+    next_id: u64,
+    /// Set once the page's load event fired.
+    loaded: bool,
+}
+
+impl<F, Fut> Cdp<F>
+where
+    F: Fn(reqwest::Url) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    async fn send(
+        &mut self,
+        session: Option<&str>,
+        method: &str,
+        params: Value,
+    ) -> anyhow::Result<u64> {
+        self.next_id += 1;
+        let mut msg = json!({ "id": self.next_id, "method": method, "params": params });
+        if let Some(session) = session {
+            msg["sessionId"] = json!(session);
+        }
+        self.ws
+            .send(Message::Text(msg.to_string().into()))
+            .await
+            .context("send to DevTools")?;
+        Ok(self.next_id)
+    }
+
+    /// Send a command and wait for its result, handling events meanwhile.
+    async fn call(
+        &mut self,
+        session: Option<&str>,
+        method: &str,
+        params: Value,
+    ) -> anyhow::Result<Value> {
+        let id = self.send(session, method, params).await?;
+        loop {
+            let msg = self.next_message().await?;
+            if msg["id"].as_u64() == Some(id) {
+                if let Some(error) = msg.get("error") {
+                    bail!(
+                        "{method}: {}",
+                        error["message"].as_str().unwrap_or("failed")
+                    );
+                }
+                return Ok(msg["result"].clone());
+            }
+            self.on_event(&msg).await?;
+        }
+    }
+
+    /// Next DevTools message, answering the proxy's checks while waiting.
+    async fn next_message(&mut self) -> anyhow::Result<Value> {
+        loop {
+            tokio::select! {
+                msg = self.ws.next() => match msg {
+                    Some(Ok(Message::Text(text))) => return Ok(serde_json::from_str(&text)?),
+                    Some(Ok(Message::Close(_))) | None => bail!("DevTools connection closed"),
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e).context("read from DevTools"),
+                },
+                Some((url, verdict)) = self.checks.recv() => {
+                    let _ = verdict.send((self.allow)(url).await);
+                }
+            }
+        }
+    }
+
+    async fn on_event(&mut self, msg: &Value) -> anyhow::Result<()> {
+        match msg["method"].as_str() {
+            Some("Page.loadEventFired") => self.loaded = true,
+            Some("Target.attachedToTarget") => {
+                // Filter the new target (and whatever it spawns) before letting it run.
+                let session = msg["params"]["sessionId"].as_str().map(str::to_string);
+                let session = session.as_deref();
+                self.send(session, "Fetch.enable", fetch_patterns()).await?;
+                self.send(session, "Target.setAutoAttach", auto_attach())
+                    .await?;
+                self.send(session, "Runtime.runIfWaitingForDebugger", json!({}))
+                    .await?;
+            }
+            Some("Fetch.requestPaused") => {
+                let params = &msg["params"];
+                let request_id = params["requestId"].clone();
+                let resource = params["resourceType"].as_str().unwrap_or("");
+                let allowed = !BLOCKED_RESOURCES.contains(&resource)
+                    && match reqwest::Url::parse(params["request"]["url"].as_str().unwrap_or("")) {
+                        Ok(url) if matches!(url.scheme(), "data" | "blob") => true,
+                        Ok(url) => (self.allow)(url).await,
+                        Err(_) => false,
+                    };
+                let session = msg["sessionId"].as_str();
+                if allowed {
+                    self.send(
+                        session,
+                        "Fetch.continueRequest",
+                        json!({ "requestId": request_id }),
+                    )
+                    .await?;
+                } else {
+                    self.send(
+                        session,
+                        "Fetch.failRequest",
+                        json!({ "requestId": request_id, "errorReason": "BlockedByClient" }),
+                    )
+                    .await?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Local HTTP proxy every Chromium connection goes through. Each request (`CONNECT` for
+/// TLS and WebSockets, absolute-form for plain HTTP) is checked before connecting; plain
+/// HTTP connections are closed after one exchange so every request is seen. Stops when
+/// dropped.
+struct GuardProxy {
+    port: u16,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl GuardProxy {
+    /// Returns the proxy and the checks it needs answered.
+    async fn start() -> anyhow::Result<(Self, mpsc::Receiver<Check>)> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .context("start render proxy")?;
+        let port = listener.local_addr()?.port();
+        let (tx, checks) = mpsc::channel(16);
+        let task = tokio::spawn(async move {
+            // Dropping the set (when the task is aborted) closes every connection.
+            let mut conns = tokio::task::JoinSet::new();
+            while let Ok((tcp, _)) = listener.accept().await {
+                conns.spawn(proxy_conn(tcp, tx.clone()));
+                while conns.try_join_next().is_some() {}
+            }
+        });
+        Ok((Self { port, task }, checks))
+    }
+}
+
+impl Drop for GuardProxy {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn proxy_conn(mut client: TcpStream, checks: mpsc::Sender<Check>) -> anyhow::Result<()> {
+    let mut buf = Vec::new();
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_PROXY_HEAD {
+            bail!("proxy request head too large");
+        }
+        let mut chunk = [0u8; 4096];
+        let n = client.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let head = std::str::from_utf8(&buf[..head_end]).context("proxy request head")?;
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let (method, target, version) = (
+        request_line.next().unwrap_or_default(),
+        request_line.next().unwrap_or_default(),
+        request_line.next().unwrap_or("HTTP/1.1"),
+    );
+    let connect = method == "CONNECT";
+    let url = if connect {
+        reqwest::Url::parse(&format!("https://{target}/"))
+    } else {
+        reqwest::Url::parse(target)
+    }
+    .ok()
+    .filter(|u| matches!(u.scheme(), "http" | "https"));
+    let mut allowed = false;
+    if let Some(url) = &url {
+        let (tx, verdict) = oneshot::channel();
+        if checks.send((url.clone(), tx)).await.is_ok() {
+            allowed = verdict.await.unwrap_or(false);
+        }
+    }
+    let (Some(url), true) = (url, allowed) else {
+        client
+            .write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .await?;
+        return Ok(());
+    };
+
+    let host = url.host_str().context("no host")?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = url.port_or_known_default().context("no port")?;
+    let mut upstream = TcpStream::connect((host, port)).await?;
+    if connect {
+        client
+            .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+            .await?;
+    } else {
+        let query = url.query().map(|q| format!("?{q}")).unwrap_or_default();
+        let mut out = format!("{method} {}{query} {version}\r\n", url.path());
+        for line in lines.filter(|l| !l.is_empty()) {
+            let name = line.split(':').next().unwrap_or_default().trim();
+            let hop_by_hop = [
+                "connection",
+                "proxy-connection",
+                "keep-alive",
+                "proxy-authorization",
+            ]
+            .iter()
+            .any(|h| name.eq_ignore_ascii_case(h));
+            if !hop_by_hop {
+                out.push_str(line);
+                out.push_str("\r\n");
+            }
+        }
+        out.push_str("Connection: close\r\n\r\n");
+        upstream.write_all(out.as_bytes()).await?;
+    }
+    upstream.write_all(&buf[head_end..]).await?;
+    tokio::io::copy_bidirectional(&mut client, &mut upstream).await?;
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    type Log = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// Stands in for Chromium's DevTools endpoint: answers the commands `drive` sends and
+    /// records them by session. When the page navigates it pauses four requests, then
+    /// attaches a paused cross-site iframe that requests a cloud metadata URL.
+    async fn fake_devtools(listener: TcpListener, resolved: Log, commands: Log) {
+        let (tcp, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+        while let Some(Ok(Message::Text(text))) = ws.next().await {
+            let msg: Value = serde_json::from_str(&text).unwrap();
+            let method = msg["method"].as_str().unwrap_or_default();
+            let session = msg["sessionId"].as_str().unwrap_or_default().to_string();
+            commands.lock().unwrap().push((session, method.to_string()));
+            let result = match method {
+                "Target.createTarget" => json!({ "targetId": "t1" }),
+                "Target.attachToTarget" => json!({ "sessionId": "s1" }),
+                "Runtime.evaluate" => json!({ "result": { "value": [
+                    "https://example.com/final",
+                    "<html><body><p>rendered</p></body></html>",
+                ] } }),
+                "Fetch.continueRequest" | "Fetch.failRequest" => {
+                    let id = msg["params"]["requestId"].as_str().unwrap().to_string();
+                    resolved.lock().unwrap().push((id, method.to_string()));
+                    json!({})
+                }
+                "Browser.close" => return,
+                _ => json!({}),
+            };
+            let reply = json!({ "id": msg["id"], "result": result });
+            ws.send(Message::Text(reply.to_string().into()))
+                .await
+                .unwrap();
+            if method == "Page.navigate" {
+                for (id, kind, url) in [
+                    ("r1", "Document", "https://example.com/"),
+                    ("r2", "Script", "http://10.0.0.1/tracker.js"),
+                    ("r3", "Image", "https://example.com/logo.png"),
+                    ("r4", "Script", "data:text/javascript,1"),
+                ] {
+                    let event = json!({
+                        "method": "Fetch.requestPaused",
+                        "sessionId": "s1",
+                        "params": { "requestId": id, "resourceType": kind, "request": { "url": url } },
+                    });
+                    ws.send(Message::Text(event.to_string().into()))
+                        .await
+                        .unwrap();
+                }
+                let iframe = [
+                    json!({
+                        "method": "Target.attachedToTarget",
+                        "sessionId": "s1",
+                        "params": {
+                            "sessionId": "s2",
+                            "targetInfo": { "targetId": "t2", "type": "iframe", "url": "https://ads.example.net/" },
+                            "waitingForDebugger": true,
+                        },
+                    }),
+                    json!({
+                        "method": "Fetch.requestPaused",
+                        "sessionId": "s2",
+                        "params": {
+                            "requestId": "r5",
+                            "resourceType": "XHR",
+                            "request": { "url": "http://169.254.169.254/latest/meta-data/" },
+                        },
+                    }),
+                    json!({ "method": "Page.loadEventFired", "sessionId": "s1", "params": {} }),
+                ];
+                for event in iframe {
+                    ws.send(Message::Text(event.to_string().into()))
+                        .await
+                        .unwrap();
+                }
+            }
+        }
+    }
+
+    /// A "Chromium" that only announces the fake DevTools endpoint.
+    fn fake_chromium(endpoint: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!(
+            "grail-web-fake-chromium-{}-{}",
+            std::process::id(),
+            PROFILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let script = format!("#!/bin/sh\necho 'DevTools listening on {endpoint}' >&2\nsleep 30\n");
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn renders_and_filters_page_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("ws://{}/devtools/browser/x", listener.local_addr().unwrap());
+        let resolved = Arc::new(Mutex::new(Vec::new()));
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let server = tokio::spawn(fake_devtools(listener, resolved.clone(), commands.clone()));
+        let bin = fake_chromium(&endpoint);
+
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let url = reqwest::Url::parse("https://example.com/").unwrap();
+        let rendered = render_with(bin.to_str().unwrap(), "", &url, |u: reqwest::Url| {
+            let asked = asked.clone();
+            async move {
+                asked.lock().unwrap().push(u.to_string());
+                u.host_str() == Some("example.com")
+            }
+        })
+        .await
+        .unwrap();
+        server.await.unwrap();
+        let _ = std::fs::remove_file(&bin);
+
+        assert_eq!(rendered.final_url, "https://example.com/final");
+        assert_eq!(rendered.html, "<html><body><p>rendered</p></body></html>");
+        // Images are refused without asking; data: URLs are always allowed.
+        assert_eq!(
+            *asked.lock().unwrap(),
+            vec![
+                "https://example.com/",
+                "http://10.0.0.1/tracker.js",
+                "http://169.254.169.254/latest/meta-data/",
+            ]
+        );
+        let resolved = resolved.lock().unwrap().clone();
+        let expected = [
+            ("r1", "Fetch.continueRequest"),
+            ("r2", "Fetch.failRequest"),
+            ("r3", "Fetch.failRequest"),
+            ("r4", "Fetch.continueRequest"),
+            ("r5", "Fetch.failRequest"),
+        ];
+        assert_eq!(
+            resolved,
+            expected.map(|(id, m)| (id.to_string(), m.to_string()))
+        );
+        // The iframe is filtered (and told to attach its own children) before it runs.
+        let iframe: Vec<String> = commands
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(session, method)| session == "s2" && !method.starts_with("Fetch.fail"))
+            .map(|(_, method)| method.clone())
+            .collect();
+        assert_eq!(
+            iframe,
+            [
+                "Fetch.enable",
+                "Target.setAutoAttach",
+                "Runtime.runIfWaitingForDebugger"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn proxy_checks_every_connection() {
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let upstream_port = upstream.local_addr().unwrap().port();
+        let received = tokio::spawn(async move {
+            let (mut tcp, _) = upstream.accept().await.unwrap();
+            let mut head = Vec::new();
+            while !head.ends_with(b"\r\n\r\n") {
+                let mut byte = [0u8; 1];
+                tcp.read_exact(&mut byte).await.unwrap();
+                head.push(byte[0]);
+            }
+            tcp.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .await
+                .unwrap();
+            String::from_utf8(head).unwrap()
+        });
+
+        let (proxy, mut checks) = GuardProxy::start().await.unwrap();
+        let asked = Arc::new(Mutex::new(Vec::new()));
+        let asked_by_proxy = asked.clone();
+        tokio::spawn(async move {
+            while let Some((url, verdict)) = checks.recv().await {
+                let allowed = url.port() == Some(upstream_port);
+                asked_by_proxy.lock().unwrap().push(url.to_string());
+                let _ = verdict.send(allowed);
+            }
+        });
+        let exchange = |request: String| async move {
+            let mut tcp = TcpStream::connect(("127.0.0.1", proxy.port)).await.unwrap();
+            tcp.write_all(request.as_bytes()).await.unwrap();
+            let mut reply = String::new();
+            tcp.read_to_string(&mut reply).await.unwrap();
+            reply
+        };
+
+        let reply = exchange(format!(
+            "GET http://127.0.0.1:{upstream_port}/x?y=1 HTTP/1.1\r\nHost: 127.0.0.1\r\nProxy-Connection: keep-alive\r\n\r\n"
+        ))
+        .await;
+        assert!(reply.ends_with("\r\n\r\nok"), "{reply}");
+        let head = received.await.unwrap();
+        assert!(head.starts_with("GET /x?y=1 HTTP/1.1\r\n"), "{head}");
+        assert!(head.contains("Connection: close") && !head.contains("Proxy-Connection"));
+
+        // WebSockets and TLS arrive as CONNECT; a refused target is never dialed.
+        let reply = exchange("CONNECT 169.254.169.254:80 HTTP/1.1\r\n\r\n".to_string()).await;
+        assert!(reply.starts_with("HTTP/1.1 403"), "{reply}");
+        assert_eq!(
+            *asked.lock().unwrap(),
+            vec![
+                format!("http://127.0.0.1:{upstream_port}/x?y=1"),
+                "https://169.254.169.254:80/".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_binary_is_reported() {
+        let url = reqwest::Url::parse("https://example.com/").unwrap();
+        let err = render_with("/nonexistent/chromium", "", &url, |_| async { true })
+            .await
+            .err()
+            .unwrap();
+        assert!(format!("{err:#}").contains("GRAIL_WEB_CHROMIUM"), "{err:#}");
+    }
+}
@@ -0,0 +1,118 @@
+//! Cookie sessions for `web_fetch`. Fetches that pass the same `session_id` share a
+//! cookie jar, including cookies set on redirects, so consent walls and session flows
+//! work across calls. Without a `session_id` no cookies are kept.
+//!
+//! Sessions live in memory, expire after `IDLE_TTL` unused, and at most `MAX_SESSIONS`
+//! are kept (least recently used dropped first).
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+const IDLE_TTL: Duration = Duration::from_secs(30 * 60);
+const MAX_SESSIONS: usize = 64;
+pub const MAX_ID_CHARS: usize = 128;
+
+struct Session {
+    client: reqwest::Client,
+    last_used: Instant,
+}
+
+#[derive(Default)]
+pub struct Sessions {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl Sessions {
+    /// The client for session `id`, created with an empty jar by `build` on first use.
+    pub async fn client(
+        &self,
+        id: &str,
+        build: impl FnOnce(Arc<reqwest::cookie::Jar>) -> anyhow::Result<reqwest::Client>,
+    ) -> anyhow::Result<reqwest::Client> {
+        let mut sessions = self.sessions.lock().await;
+        let now = Instant::now();
+        sessions.retain(|_, s| now.duration_since(s.last_used) < IDLE_TTL);
+        if let Some(session) = sessions.get_mut(id) {
+            session.last_used = now;
+            return Ok(session.client.clone());
+        }
+        if sessions.len() >= MAX_SESSIONS {
+            if let Some(oldest) = sessions
+                .iter()
+                .min_by_key(|(_, s)| s.last_used)
+                .map(|(k, _)| k.clone())
+            {
+                sessions.remove(&oldest);
+            }
+        }
+        let client = build(Arc::new(reqwest::cookie::Jar::default()))?;
+        sessions.insert(
+            id.to_string(),
+            Session {
+                client: client.clone(),
+                last_used: now,
+            },
+        );
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::cookie::CookieStore;
+
+    async fn jar_for(sessions: &Sessions, id: &str) -> Option<Arc<reqwest::cookie::Jar>> {
+        let mut built = None;
+        sessions
+            .client(id, |jar| {
+                built = Some(jar);
+                Ok(reqwest::Client::new())
+            })
+            .await
+            .unwrap();
+        built
+    }
+
+    #[tokio::test]
+    async fn a_session_keeps_its_client() {
+        let sessions = Sessions::default();
+        let jar = jar_for(&sessions, "a").await.unwrap();
+        assert!(jar_for(&sessions, "a").await.is_none());
+        assert!(jar_for(&sessions, "b").await.is_some());
+
+        let url = reqwest::Url::parse("https://example.com/").unwrap();
+        jar.add_cookie_str("sid=1", &url);
+        assert!(jar.cookies(&url).is_some());
+        let other = jar_for(&sessions, "c").await.unwrap();
+        assert!(other.cookies(&url).is_none());
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_session_is_dropped() {
+        let sessions = Sessions::default();
+        for i in 0..MAX_SESSIONS {
+            jar_for(&sessions, &i.to_string()).await.unwrap();
+        }
+        // Touch "0" so "1" is the least recently used.
+        assert!(jar_for(&sessions, "0").await.is_none());
+        jar_for(&sessions, "new").await.unwrap();
+        assert_eq!(sessions.sessions.lock().await.len(), MAX_SESSIONS);
+        assert!(jar_for(&sessions, "0").await.is_none());
+        assert!(jar_for(&sessions, "1").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn build_errors_are_returned() {
+        let sessions = Sessions::default();
+        let err = sessions
+            .client("a", |_| Err(anyhow::anyhow!("no client")))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no client");
+        assert!(sessions.sessions.lock().await.is_empty());
+    }
+}
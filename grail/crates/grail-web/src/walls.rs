@@ -0,0 +1,245 @@
+//! Detection of pages that hide their content behind a wall, and the alternate fetch
+//! strategies `web_fetch` retries with.
+//!
+//! A page counts as walled when its extracted text is short and reads like a cookie
+//! consent wall, a bot-check interstitial, an "enable JavaScript" stub or a subscription
+//! prompt. `web_fetch` then tries, in order: the page's AMP version and print version
+//! (from `<link rel="amphtml">` / `<link rel="alternate" media="print">`), a Googlebot
+//! user agent for domains listed in `GRAIL_WEB_GOOGLEBOT_DOMAINS`, and the readability
+//! endpoint in `GRAIL_WEB_READER_URL` (e.g. `https://r.jina.ai/{url}`). The first
+//! attempt that isn't walled wins. `GRAIL_WEB_FETCH_FALLBACKS=off` turns retries off.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{Html, Selector};
+
+pub const GOOGLEBOT_USER_AGENT: &str =
+    "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+/// Pages with more extracted text than this are never treated as walled.
+const MAX_WALL_CHARS: usize = 1_500;
+
+static INTERSTITIAL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)checking (if the site connection is secure|your browser)|just a moment\.\.\.|verify(ing)? (that )?you are (a )?human|are you a robot|attention required|press (&|and) hold|unusual traffic from your (computer|network)").unwrap()
+});
+static JAVASCRIPT_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(enable|turn on|activate) javascript|javascript (is )?(disabled|required|must be enabled)|requires javascript|you need to enable javascript").unwrap()
+});
+static COOKIE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)accept (all )?cookies|cookie (consent|preferences|settings)|we (and our partners )?use cookies|manage (your )?(privacy|consent) (choices|preferences)|before you continue to").unwrap()
+});
+static PAYWALL_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)subscribe (now )?to (continue|keep) reading|already a subscriber|(sign|log) in to (continue|read)|this (article|content) is (for|available to) (subscribers|members)( only)?|you have reached your (free )?article limit").unwrap()
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wall {
+    Interstitial,
+    JavascriptRequired,
+    CookieConsent,
+    Paywall,
+}
+
+impl Wall {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Wall::Interstitial => "interstitial",
+            Wall::JavascriptRequired => "javascript_required",
+            Wall::CookieConsent => "cookie_consent",
+            Wall::Paywall => "paywall",
+        }
+    }
+}
+
+/// The wall `text` (extracted from an HTML page) sits behind, if any.
+pub fn detect(text: &str) -> Option<Wall> {
+    let text = text.trim();
+    if text.chars().count() > MAX_WALL_CHARS {
+        return None;
+    }
+    if text.is_empty() {
+        // Nothing but scripts: a client-rendered app shell.
+        return Some(Wall::JavascriptRequired);
+    }
+    [
+        (&*INTERSTITIAL_RE, Wall::Interstitial),
+        (&*JAVASCRIPT_RE, Wall::JavascriptRequired),
+        (&*COOKIE_RE, Wall::CookieConsent),
+        (&*PAYWALL_RE, Wall::Paywall),
+    ]
+    .into_iter()
+    .find(|(re, _)| re.is_match(text))
+    .map(|(_, wall)| wall)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    Direct,
+    Amp,
+    Print,
+    Googlebot,
+    Reader,
+}
+
+impl Strategy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Strategy::Direct => "direct",
+            Strategy::Amp => "amp",
+            Strategy::Print => "print",
+            Strategy::Googlebot => "googlebot",
+            Strategy::Reader => "reader",
+        }
+    }
+}
+
+/// AMP and print versions the page links to, resolved against `base`.
+pub fn alternate_links(html: &str, base: &reqwest::Url) -> Vec<(Strategy, reqwest::Url)> {
+    static AMP: Lazy<Selector> = Lazy::new(|| Selector::parse(r#"link[rel~="amphtml"]"#).unwrap());
+    static PRINT: Lazy<Selector> =
+        Lazy::new(|| Selector::parse(r#"link[rel~="alternate"][media="print"]"#).unwrap());
+    let doc = Html::parse_document(html);
+    let mut out = Vec::new();
+    for (strategy, selector) in [(Strategy::Amp, &*AMP), (Strategy::Print, &*PRINT)] {
+        let url = doc
+            .select(selector)
+            .filter_map(|el| el.value().attr("href"))
+            .find_map(|href| base.join(href.trim()).ok())
+            .filter(|u| u != base);
+        if let Some(url) = url {
+            out.push((strategy, url));
+        }
+    }
+    out
+}
+
+pub struct Fallbacks {
+    pub enabled: bool,
+    googlebot_domains: Vec<String>,
+    reader_url: Option<String>,
+}
+
+impl Fallbacks {
+    pub fn from_env() -> Self {
+        let enabled = !matches!(
+            std::env::var("GRAIL_WEB_FETCH_FALLBACKS")
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase()
+                .as_str(),
+            "off" | "false" | "0"
+        );
+        Self {
+            enabled,
+            googlebot_domains: crate::parse_domain_list_env("GRAIL_WEB_GOOGLEBOT_DOMAINS"),
+            reader_url: std::env::var("GRAIL_WEB_READER_URL")
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty()),
+        }
+    }
+
+    /// Whether pages on `host` may be fetched with the Googlebot user agent.
+    pub fn googlebot_allowed(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.googlebot_domains
+            .iter()
+            .any(|d| crate::domain_matches(&host, d))
+    }
+
+    /// The readability endpoint URL for `url`: `{url}` in the template is replaced,
+    /// otherwise the URL is appended.
+    pub fn reader_url(&self, url: &reqwest::Url) -> Option<reqwest::Url> {
+        let template = self.reader_url.as_deref()?;
+        let raw = if template.contains("{url}") {
+            template.replace("{url}", url.as_str())
+        } else {
+            format!("{template}{url}")
+        };
+        reqwest::Url::parse(&raw).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> reqwest::Url {
+        reqwest::Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn detect_classifies_short_walls() {
+        assert_eq!(
+            detect("Just a moment... Checking your browser"),
+            Some(Wall::Interstitial)
+        );
+        assert_eq!(
+            detect("Please enable JavaScript to view this site."),
+            Some(Wall::JavascriptRequired)
+        );
+        assert_eq!(detect("   "), Some(Wall::JavascriptRequired));
+        assert_eq!(
+            detect("We use cookies. Accept all cookies to continue."),
+            Some(Wall::CookieConsent)
+        );
+        assert_eq!(
+            detect("Subscribe to continue reading. Already a subscriber?"),
+            Some(Wall::Paywall)
+        );
+        assert_eq!(detect("A short but ordinary article."), None);
+    }
+
+    #[test]
+    fn detect_ignores_long_pages() {
+        let text = format!("Accept cookies. {}", "Real content. ".repeat(200));
+        assert_eq!(detect(&text), None);
+    }
+
+    #[test]
+    fn alternate_links_resolves_amp_and_print() {
+        let base = url("https://news.example.com/story/1");
+        let html = r#"<html><head>
+            <link rel="amphtml" href="/amp/story/1">
+            <link rel="alternate" media="print" href="https://news.example.com/story/1?print=1">
+            <link rel="alternate" hreflang="de" href="/de/story/1">
+        </head></html>"#;
+        assert_eq!(
+            alternate_links(html, &base),
+            vec![
+                (Strategy::Amp, url("https://news.example.com/amp/story/1")),
+                (
+                    Strategy::Print,
+                    url("https://news.example.com/story/1?print=1")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn alternate_links_skips_self_links() {
+        let base = url("https://example.com/a");
+        let html = r#"<link rel="amphtml" href="https://example.com/a">"#;
+        assert!(alternate_links(html, &base).is_empty());
+    }
+
+    #[test]
+    fn reader_url_uses_template() {
+        let fallbacks = |reader: Option<&str>| Fallbacks {
+            enabled: true,
+            googlebot_domains: vec!["example.com".to_string()],
+            reader_url: reader.map(str::to_string),
+        };
+        let page = url("https://example.com/a?b=c");
+        assert_eq!(
+            fallbacks(Some("https://r.jina.ai/{url}")).reader_url(&page),
+            Some(url("https://r.jina.ai/https://example.com/a?b=c"))
+        );
+        assert_eq!(
+            fallbacks(Some("https://reader.local/?u=")).reader_url(&page),
+            Some(url("https://reader.local/?u=https://example.com/a?b=c"))
+        );
+        assert_eq!(fallbacks(None).reader_url(&page), None);
+        assert!(fallbacks(None).googlebot_allowed("News.Example.com"));
+        assert!(!fallbacks(None).googlebot_allowed("example.org"));
+    }
+}
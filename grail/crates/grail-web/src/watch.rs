@@ -0,0 +1,280 @@
+//! Page snapshots for `web_watch_diff`.
+//!
+//! Each watched URL keeps its last extracted text in a JSON file under
+//! `GRAIL_WEB_WATCH_DIR` (named by the SHA-256 of the scope and URL), so a cron prompt
+//! such as "tell me when this changelog changes" can compare against its own previous
+//! run. The scope (e.g. `cron:<job_id>`) keeps two jobs or conversations watching the
+//! same page from consuming each other's changes; an empty scope is keyed by the URL
+//! alone.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Above this many (trimmed) line pairs the diff falls back to a set comparison.
+const MAX_LCS_CELLS: usize = 4_000_000;
+/// Unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(default)]
+    pub scope: String,
+    pub url: String,
+    pub hash: String,
+    pub fetched_at: i64,
+    pub text: String,
+}
+
+pub struct WatchStore {
+    dir: PathBuf,
+}
+
+impl WatchStore {
+    pub fn from_env() -> Self {
+        let dir = std::env::var("GRAIL_WEB_WATCH_DIR")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| std::env::temp_dir().join("grail-web-watch"));
+        Self { dir }
+    }
+
+    fn path(&self, scope: &str, url: &str) -> PathBuf {
+        let key = if scope.is_empty() {
+            content_hash(url)
+        } else {
+            content_hash(&format!("{scope}\n{url}"))
+        };
+        self.dir.join(format!("{key}.json"))
+    }
+
+    pub async fn load(&self, scope: &str, url: &str) -> Option<Snapshot> {
+        let raw = tokio::fs::read(self.path(scope, url)).await.ok()?;
+        serde_json::from_slice(&raw).ok()
+    }
+
+    pub async fn save(&self, snapshot: &Snapshot) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.path(&snapshot.scope, &snapshot.url);
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        tokio::fs::write(&tmp, serde_json::to_vec(snapshot)?).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+}
+
+pub fn content_hash(text: &str) -> String {
+    hex::encode(Sha256::digest(text.as_bytes()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Same,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LineDiff {
+    pub added: usize,
+    pub removed: usize,
+    /// `+`/`-` prefixed lines with a little context; `@@ line N @@` starts each hunk.
+    pub text: String,
+}
+
+/// Line diff of `old` → `new`. Common leading/trailing lines are trimmed first, which
+/// keeps the usual "new entry at the top" changelog update cheap.
+pub fn diff_lines(old: &str, new: &str) -> LineDiff {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (mid_a, mid_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops: Vec<(Op, &str)> = a[..prefix].iter().map(|l| (Op::Same, *l)).collect();
+    if mid_a.len().saturating_mul(mid_b.len()) <= MAX_LCS_CELLS {
+        ops.extend(lcs_ops(mid_a, mid_b));
+    } else {
+        ops.extend(mid_a.iter().map(|l| (Op::Removed, *l)));
+        ops.extend(mid_b.iter().map(|l| (Op::Added, *l)));
+    }
+    ops.extend(a[a.len() - suffix..].iter().map(|l| (Op::Same, *l)));
+    render(&ops)
+}
+
+fn lcs_ops<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let (n, m) = (a.len(), b.len());
+    // lens[i][j] = LCS length of a[i..] and b[j..].
+    let mut lens = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lens[at(i, j)] = if a[i] == b[j] {
+                lens[at(i + 1, j + 1)] + 1
+            } else {
+                lens[at(i + 1, j)].max(lens[at(i, j + 1)])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut out = Vec::with_capacity(n + m);
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            out.push((Op::Same, a[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lens[at(i + 1, j)] >= lens[at(i, j + 1)]) {
+            out.push((Op::Removed, a[i]));
+            i += 1;
+        } else {
+            out.push((Op::Added, b[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+fn render(ops: &[(Op, &str)]) -> LineDiff {
+    let mut out = LineDiff::default();
+    let changed: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Same)
+        .map(|(i, _)| i)
+        .collect();
+    let near_change = |i: usize| {
+        let lo = changed.partition_point(|&c| c + CONTEXT_LINES < i);
+        changed.get(lo).is_some_and(|&c| c <= i + CONTEXT_LINES)
+    };
+
+    let mut new_line = 0;
+    let mut in_hunk = false;
+    for (i, (op, line)) in ops.iter().enumerate() {
+        if *op != Op::Removed {
+            new_line += 1;
+        }
+        if !near_change(i) {
+            in_hunk = false;
+            continue;
+        }
+        if !in_hunk {
+            out.text
+                .push_str(&format!("@@ line {} @@\n", new_line.max(1)));
+            in_hunk = true;
+        }
+        let prefix = match op {
+            Op::Same => ' ',
+            Op::Added => {
+                out.added += 1;
+                '+'
+            }
+            Op::Removed => {
+                out.removed += 1;
+                '-'
+            }
+        };
+        out.text.push(prefix);
+        out.text.push_str(line);
+        out.text.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_lines_reports_changes_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh";
+        let new = "a\nb\nc\nd\nE\nf\ng\nh";
+        let diff = diff_lines(old, new);
+        assert_eq!((diff.added, diff.removed), (1, 1));
+        assert_eq!(diff.text, "@@ line 3 @@\n c\n d\n-e\n+E\n f\n g\n");
+    }
+
+    #[test]
+    fn diff_lines_new_entry_at_top() {
+        let diff = diff_lines("v1\nfix", "v2\nfeature\nv1\nfix");
+        assert_eq!((diff.added, diff.removed), (2, 0));
+        assert_eq!(diff.text, "@@ line 1 @@\n+v2\n+feature\n v1\n fix\n");
+    }
+
+    #[test]
+    fn diff_lines_identical_is_empty() {
+        assert_eq!(diff_lines("a\nb", "a\nb"), LineDiff::default());
+        assert_eq!(diff_lines("", ""), LineDiff::default());
+    }
+
+    #[test]
+    fn diff_lines_splits_distant_hunks() {
+        let old: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        let mut new = old.clone();
+        new[2] = "two".into();
+        new[15] = "fifteen".into();
+        let diff = diff_lines(&old.join("\n"), &new.join("\n"));
+        assert_eq!((diff.added, diff.removed), (2, 2));
+        assert_eq!(diff.text.matches("@@ line").count(), 2);
+        assert!(diff
+            .text
+            .contains("@@ line 1 @@\n 0\n 1\n-2\n+two\n 3\n 4\n"));
+        assert!(diff
+            .text
+            .contains("@@ line 14 @@\n 13\n 14\n-15\n+fifteen\n 16\n 17\n"));
+    }
+
+    #[test]
+    fn lcs_ops_keeps_longest_common_subsequence() {
+        let ops = lcs_ops(&["a", "b", "c", "d"], &["b", "x", "d", "e"]);
+        assert_eq!(
+            ops,
+            vec![
+                (Op::Removed, "a"),
+                (Op::Same, "b"),
+                (Op::Removed, "c"),
+                (Op::Added, "x"),
+                (Op::Same, "d"),
+                (Op::Added, "e"),
+            ]
+        );
+        assert_eq!(lcs_ops(&[], &["a"]), vec![(Op::Added, "a")]);
+        assert_eq!(lcs_ops(&["a"], &[]), vec![(Op::Removed, "a")]);
+    }
+
+    #[test]
+    fn render_counts_and_numbers_new_lines() {
+        let ops = [
+            (Op::Same, "a"),
+            (Op::Removed, "b"),
+            (Op::Added, "B"),
+            (Op::Added, "C"),
+            (Op::Same, "d"),
+        ];
+        let diff = render(&ops);
+        assert_eq!((diff.added, diff.removed), (2, 1));
+        assert_eq!(diff.text, "@@ line 1 @@\n a\n-b\n+B\n+C\n d\n");
+        assert_eq!(render(&[(Op::Same, "a")]), LineDiff::default());
+    }
+
+    #[test]
+    fn snapshots_are_keyed_by_scope_and_url() {
+        let store = WatchStore {
+            dir: PathBuf::from("/watch"),
+        };
+        let url = "https://example.com/changelog";
+        assert_eq!(
+            store.path("", url),
+            PathBuf::from(format!("/watch/{}.json", content_hash(url)))
+        );
+        assert_ne!(store.path("cron:1", url), store.path("cron:2", url));
+        assert_ne!(store.path("cron:1", url), store.path("", url));
+    }
+}